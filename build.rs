@@ -0,0 +1,211 @@
+//! Embed the self-hosted builtins written in JavaScript (src/builtins/js/*.js) into the binary.
+//! The generated file is included by src/builtins/self_hosted.rs.
+//! With the capi feature, also generate the C header include/rapidus.h from src/capi.rs.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+fn main() {
+    embed_self_hosted();
+    if env::var("CARGO_FEATURE_CAPI").is_ok() {
+        generate_c_header();
+    }
+}
+
+fn embed_self_hosted() {
+    let js_dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("src/builtins/js");
+    println!("cargo:rerun-if-changed={}", js_dir.display());
+
+    let mut paths = fs::read_dir(&js_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "js"))
+        .filter(|path| is_enabled(path.file_name().unwrap().to_str().unwrap()))
+        .collect::<Vec<_>>();
+    // Install builtins in a fixed order.
+    paths.sort();
+
+    let out_path = Path::new(&env::var("OUT_DIR").unwrap()).join("self_hosted.rs");
+    let mut out = fs::File::create(out_path).unwrap();
+    writeln!(out, "pub static SOURCES: &[(&str, &str)] = &[").unwrap();
+    for path in paths {
+        println!("cargo:rerun-if-changed={}", path.display());
+        writeln!(
+            out,
+            "    ({:?}, include_str!({:?})),",
+            path.file_name().unwrap().to_str().unwrap(),
+            path.to_str().unwrap()
+        )
+        .unwrap();
+    }
+    writeln!(out, "];").unwrap();
+}
+
+/// Builtins behind a cargo feature are embedded only if the feature is enabled.
+fn is_enabled(file_name: &str) -> bool {
+    match file_name {
+        "intl.js" => env::var("CARGO_FEATURE_INTL").is_ok(),
+        _ => true,
+    }
+}
+
+const HEADER_START: &str = "/* Generated by build.rs from src/capi.rs. Do not edit. */
+
+#ifndef RAPIDUS_H
+#define RAPIDUS_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern \"C\" {
+#endif
+";
+
+const HEADER_END: &str = "
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RAPIDUS_H */
+";
+
+/// Declare the public constants, structs, callback types and extern "C" functions of
+/// src/capi.rs, with their doc comments. The header is checked in for C users, and is rewritten
+/// only if the API changed.
+fn generate_c_header() {
+    let root = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).to_path_buf();
+    let src_path = root.join("src/capi.rs");
+    println!("cargo:rerun-if-changed={}", src_path.display());
+    let src = fs::read_to_string(&src_path).unwrap();
+
+    let mut header = HEADER_START.to_string();
+    let mut doc: Vec<String> = vec![];
+    let mut prev_define = false;
+    let mut lines = src.lines();
+    while let Some(line) = lines.next() {
+        if let Some(rest) = line.strip_prefix("///") {
+            doc.push(rest.trim().replace("``", "`"));
+            continue;
+        }
+        if line.starts_with("#[") {
+            continue;
+        }
+        let decl = if let Some(rest) = line.strip_prefix("pub const ") {
+            // pub const NAME: c_int = 0;
+            let name = rest.split(':').next().unwrap();
+            let value = line.split('=').nth(1).unwrap().trim().trim_end_matches(';');
+            Some(format!("#define {} {}", name, value))
+        } else if let Some(rest) = line.strip_prefix("pub struct ") {
+            let name = rest.split(' ').next().unwrap();
+            Some(format!("typedef struct {0} {0};", name))
+        } else if line.starts_with("pub ") && line.contains("fn") {
+            // Join the signature split into lines by rustfmt.
+            let mut item = line.to_string();
+            while !item.ends_with('{') && !item.ends_with(';') {
+                item.push_str(lines.next().unwrap().trim());
+            }
+            Some(c_function(&item))
+        } else {
+            None
+        };
+        if let Some(decl) = decl {
+            let is_define = decl.starts_with("#define");
+            if !(is_define && prev_define && doc.is_empty()) {
+                header.push('\n');
+            }
+            if !doc.is_empty() {
+                header.push_str("/**\n");
+                for line in &doc {
+                    header.push_str(format!(" * {}", line).trim_end());
+                    header.push('\n');
+                }
+                header.push_str(" */\n");
+            }
+            header.push_str(&decl);
+            header.push('\n');
+            prev_define = is_define;
+        }
+        doc.clear();
+    }
+    header.push_str(HEADER_END);
+
+    let header_path = root.join("include/rapidus.h");
+    if fs::read_to_string(&header_path).ok().as_ref() != Some(&header) {
+        fs::create_dir_all(header_path.parent().unwrap()).unwrap();
+        fs::write(header_path, header).unwrap();
+    }
+}
+
+/// Declare the function or the function pointer type (``pub type X = extern "C" fn(..)``)
+/// ``item``.
+fn c_function(item: &str) -> String {
+    let params_start = item.find('(').unwrap();
+    let params_end = item.rfind(')').unwrap();
+    let params = item[params_start + 1..params_end]
+        .split(',')
+        .filter(|param| !param.trim().is_empty())
+        .map(|param| {
+            let mut name_type = param.splitn(2, ':');
+            let name = name_type.next().unwrap().trim();
+            c_declaration(c_type(name_type.next().unwrap()), name)
+        })
+        .collect::<Vec<_>>();
+    let params = if params.is_empty() {
+        "void".to_string()
+    } else {
+        params.join(", ")
+    };
+    let ret = item[params_end + 1..]
+        .trim_end_matches(['{', ';', ' '])
+        .trim();
+    let ret = if let Some(ret) = ret.strip_prefix("->") {
+        c_type(ret)
+    } else {
+        "void".to_string()
+    };
+
+    if let Some(rest) = item.strip_prefix("pub type ") {
+        let name = rest.split(' ').next().unwrap();
+        let declarator = format!("(*{})({})", name, params);
+        format!("typedef {};", c_declaration(ret, &declarator))
+    } else {
+        let name = item[..params_start].rsplit(' ').next().unwrap();
+        format!("{};", c_declaration(ret, &format!("{}({})", name, params)))
+    }
+}
+
+fn c_type(ty: &str) -> String {
+    let ty = ty.trim();
+    if let Some(pointee) = ty.strip_prefix("*mut ") {
+        c_declaration(c_type(pointee), "*")
+    } else if let Some(pointee) = ty.strip_prefix("*const ") {
+        let pointee = c_type(pointee);
+        if pointee.ends_with('*') {
+            format!("{}const *", pointee)
+        } else {
+            format!("const {} *", pointee)
+        }
+    } else {
+        match ty {
+            "c_char" => "char",
+            "c_int" => "int",
+            "c_double" => "double",
+            "c_void" => "void",
+            "usize" => "size_t",
+            ty if ty.starts_with("Rapidus") => ty,
+            ty => panic!("No C type for {} in src/capi.rs", ty),
+        }
+        .to_string()
+    }
+}
+
+/// ``ty declarator``, without a space after ``*``.
+fn c_declaration(ty: String, declarator: &str) -> String {
+    if ty.ends_with('*') {
+        format!("{}{}", ty, declarator)
+    } else {
+        format!("{} {}", ty, declarator)
+    }
+}
@@ -0,0 +1,238 @@
+/* Generated by build.rs from src/capi.rs. Do not edit. */
+
+#ifndef RAPIDUS_H
+#define RAPIDUS_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define RAPIDUS_TYPE_UNDEFINED 0
+#define RAPIDUS_TYPE_NULL 1
+#define RAPIDUS_TYPE_BOOLEAN 2
+#define RAPIDUS_TYPE_NUMBER 3
+#define RAPIDUS_TYPE_STRING 4
+#define RAPIDUS_TYPE_SYMBOL 5
+#define RAPIDUS_TYPE_OBJECT 6
+#define RAPIDUS_TYPE_FUNCTION 7
+
+/**
+ * A VM with the state of the scripts evaluated in it.
+ */
+typedef struct RapidusRuntime RapidusRuntime;
+
+/**
+ * A handle to a value.
+ */
+typedef struct RapidusValue RapidusValue;
+
+/**
+ * A function callable from scripts. `args` are the `argc` arguments. The handles given to
+ * the callback are freed after it returns. It returns a new handle, which the runtime frees,
+ * or one of the given handles. It returns NULL to throw an Error with the message given to
+ * `rapidus_throw_error`.
+ */
+typedef RapidusValue *(*RapidusCallback)(RapidusRuntime *rt, RapidusValue *this_value, RapidusValue *const *args, size_t argc, void *data);
+
+/**
+ * Create a runtime. Free it with `rapidus_runtime_free`.
+ */
+RapidusRuntime *rapidus_runtime_new(void);
+
+/**
+ * Free a runtime. The handles to its values must be freed before.
+ *
+ * # Safety
+ *
+ * `rt` must be NULL or a runtime given by `rapidus_runtime_new`. It and the handles to its
+ * values must not be used after this.
+ */
+void rapidus_runtime_free(RapidusRuntime *rt);
+
+/**
+ * The message of the last error, or NULL if nothing has failed. The string is valid until the
+ * next error.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed.
+ */
+const char *rapidus_last_error(RapidusRuntime *rt);
+
+/**
+ * Run `code` as a script named `file_name`, and return its completion value. The
+ * declarations of the scripts run before are visible. Cannot be called from a callback.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed. `file_name` and
+ * `code` must be NUL-terminated strings.
+ */
+RapidusValue *rapidus_eval(RapidusRuntime *rt, const char *file_name, const char *code);
+
+/**
+ * Get the global variable `name`.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed. `name` must be a
+ * NUL-terminated string.
+ */
+RapidusValue *rapidus_get_global(RapidusRuntime *rt, const char *name);
+
+/**
+ * Set the global variable `name` to `value`, defining it if it does not exist. Return 0,
+ * or -1 on error.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed. `name` must be a
+ * NUL-terminated string. `value` must be a handle given by `rt` and not freed.
+ */
+int rapidus_set_global(RapidusRuntime *rt, const char *name, RapidusValue *value);
+
+/**
+ * Define the global function `name` calling `callback` with `data`. Return 0, or -1 on
+ * error.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed. `name` must be a
+ * NUL-terminated string. `callback` is called with `data` until `rt` is freed, so `data`
+ * must be valid until then.
+ */
+int rapidus_register_function(RapidusRuntime *rt, const char *name, RapidusCallback callback, void *data);
+
+/**
+ * Call `func` with the `argc` values `args`. `this_value` may be NULL for undefined.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed. `func` and
+ * `this_value`, unless it is NULL, must be handles given by `rt` and not freed. `args` must
+ * point to `argc` such handles.
+ */
+RapidusValue *rapidus_call(RapidusRuntime *rt, RapidusValue *func, RapidusValue *this_value, RapidusValue *const *args, size_t argc);
+
+/**
+ * Make the running callback throw an Error with `message` when it returns NULL.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed. `message` must be a
+ * NUL-terminated string.
+ */
+void rapidus_throw_error(RapidusRuntime *rt, const char *message);
+
+/**
+ * The undefined value.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed.
+ */
+RapidusValue *rapidus_undefined(RapidusRuntime *rt);
+
+/**
+ * The null value.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed.
+ */
+RapidusValue *rapidus_null(RapidusRuntime *rt);
+
+/**
+ * A boolean, false if `b` is 0.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed.
+ */
+RapidusValue *rapidus_boolean(RapidusRuntime *rt, int b);
+
+/**
+ * A number.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed.
+ */
+RapidusValue *rapidus_number(RapidusRuntime *rt, double n);
+
+/**
+ * A string copied from `s`.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed. `s` must be a
+ * NUL-terminated string.
+ */
+RapidusValue *rapidus_string(RapidusRuntime *rt, const char *s);
+
+/**
+ * One of `RAPIDUS_TYPE_*`.
+ *
+ * # Safety
+ *
+ * `value` must be a handle which is not freed.
+ */
+int rapidus_value_type(RapidusValue *value);
+
+/**
+ * 1 if `value` is truthy, 0 if not.
+ *
+ * # Safety
+ *
+ * `value` must be a handle which is not freed.
+ */
+int rapidus_value_to_boolean(RapidusValue *value);
+
+/**
+ * Convert `value` into a number.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed. `value` must be a
+ * handle given by `rt` and not freed.
+ */
+double rapidus_value_to_number(RapidusRuntime *rt, RapidusValue *value);
+
+/**
+ * Convert `value` into a string. Free it with `rapidus_string_free`.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed. `value` must be a
+ * handle given by `rt` and not freed.
+ */
+char *rapidus_value_to_string(RapidusRuntime *rt, RapidusValue *value);
+
+/**
+ * Free a string given by `rapidus_value_to_string`.
+ *
+ * # Safety
+ *
+ * `s` must be NULL or a string given by `rapidus_value_to_string`, which must not be used
+ * after this.
+ */
+void rapidus_string_free(char *s);
+
+/**
+ * Free a handle. The value is collected when it has no handles and is not referenced by the
+ * scripts.
+ *
+ * # Safety
+ *
+ * `rt` must be a runtime given by `rapidus_runtime_new` and not freed. `value` must be NULL
+ * or a handle given by `rt`, which must not be used after this.
+ */
+void rapidus_value_free(RapidusRuntime *rt, RapidusValue *value);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RAPIDUS_H */
@@ -0,0 +1,16 @@
+//! The language server of Rapidus. See ``rapidus::lsp``.
+
+use std::io;
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let code = match rapidus::lsp::run(&mut stdin.lock(), &mut stdout.lock()) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            1
+        }
+    };
+    ::std::process::exit(code)
+}
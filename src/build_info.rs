@@ -0,0 +1,79 @@
+/// Version of this engine.
+pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+/// Kind of the garbage collector implemented in gc.rs.
+pub const GC_TYPE: &'static str = "incremental-mark-and-sweep";
+
+/// Information about the configuration this engine was built with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub features: Vec<&'static str>,
+    pub gc: &'static str,
+    pub pointer_size: usize,
+}
+
+impl BuildInfo {
+    pub fn new() -> Self {
+        let mut features = vec![];
+        if cfg!(debug_assertions) {
+            features.push("debug_assertions");
+        }
+        if cfg!(feature = "intl") {
+            features.push("intl");
+        }
+        if cfg!(feature = "jsx") {
+            features.push("jsx");
+        }
+        if cfg!(feature = "typescript") {
+            features.push("typescript");
+        }
+        if cfg!(feature = "remote-modules") {
+            features.push("remote-modules");
+        }
+        if cfg!(feature = "capi") {
+            features.push("capi");
+        }
+        if cfg!(feature = "node-compat") {
+            features.push("node-compat");
+        }
+        if cfg!(feature = "lsp") {
+            features.push("lsp");
+        }
+        BuildInfo {
+            version: VERSION,
+            features,
+            gc: GC_TYPE,
+            pointer_size: ::std::mem::size_of::<usize>() * 8,
+        }
+    }
+
+    /// Convert the build information into a JSON string.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"version\":\"{}\",\"features\":[{}],\"gc\":\"{}\",\"pointer_size\":{}}}",
+            self.version,
+            self.features
+                .iter()
+                .map(|f| format!("\"{}\"", f))
+                .collect::<Vec<String>>()
+                .join(","),
+            self.gc,
+            self.pointer_size
+        )
+    }
+}
+
+#[test]
+fn build_info_json() {
+    let info = BuildInfo {
+        version: "0.1.1",
+        features: vec!["a", "b"],
+        gc: GC_TYPE,
+        pointer_size: 64,
+    };
+    assert_eq!(
+        info.to_json(),
+        "{\"version\":\"0.1.1\",\"features\":[\"a\",\"b\"],\"gc\":\"incremental-mark-and-sweep\",\"pointer_size\":64}"
+    );
+}
@@ -1,4 +1,6 @@
 use crate::vm::{
+    atom::Atom,
+    error::RuntimeError,
     jsvalue::value::*,
     vm::{CallMode, VMValueResult, VM},
 };
@@ -11,11 +13,29 @@ pub fn parse_float(_vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult
     Ok(val)
 }
 
+/// Indirect eval, which runs the code in the global scope. Direct eval is done by the VM.
+/// https://tc39.github.io/ecma262/#sec-eval-x
+pub fn eval(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let code = match args.get(0) {
+        Some(val @ Value::String(_)) => val.to_string(),
+        Some(val) => return Ok(*val),
+        None => return Ok(Value::undefined()),
+    };
+    vm.prepare_context_for_eval(code, false, CallMode::FromNative)?;
+    // if called from builtin func, do not GC.
+    let save = vm.is_called_from_native;
+    vm.is_called_from_native = true;
+    let res = vm.run();
+    vm.is_called_from_native = save;
+    res
+}
+
 pub fn deep_seq(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
     if args.len() != 2 {
-        return Err(vm
-            .current_context
-            .error_general("__assert_deep_seq(): Two arguments are needed."));
+        return Err(vm.throw_error(
+            ErrorType::Error,
+            "__assert_deep_seq(): Two arguments are needed.",
+        ));
     };
     let lval = args.get(0).unwrap();
     let rval = args.get(1).unwrap();
@@ -23,7 +43,7 @@ pub fn deep_seq(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
     Ok(val)
 }
 
-/// Check deep strict equality.
+/// Check deep strict equality. Only enumerable properties are compared.
 /// Currently, only Object and Array are supported.
 /// Accesor property is not suppoeed. (alway return false)
 fn deep_seq_bool(lval: &Value, rval: &Value) -> bool {
@@ -34,11 +54,13 @@ fn deep_seq_bool(lval: &Value, rval: &Value) -> bool {
             // sort and compare properties
             let mut l_sorted_propmap = (&lobj_info.property)
                 .iter()
-                .collect::<Vec<(&String, &Property)>>();
+                .filter(|(_, prop)| prop.is_enumerable())
+                .collect::<Vec<(&Atom, &Property)>>();
             l_sorted_propmap.sort_by(|(key1, _), (key2, _)| key1.as_str().cmp(key2.as_str()));
             let mut r_sorted_propmap = (&robj_info.property)
                 .iter()
-                .collect::<Vec<(&String, &Property)>>();
+                .filter(|(_, prop)| prop.is_enumerable())
+                .collect::<Vec<(&Atom, &Property)>>();
             r_sorted_propmap.sort_by(|(key1, _), (key2, _)| key1.as_str().cmp(key2.as_str()));
             if l_sorted_propmap.len() != r_sorted_propmap.len() {
                 return false;
@@ -88,39 +110,55 @@ fn deep_seq_bool(lval: &Value, rval: &Value) -> bool {
 
 pub fn require(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
     let file_name = {
-        let val = args.get(0).ok_or(
-            vm.current_context
-                .error_general("require(): One argument is needed."),
-        )?;
+        let val = args.get(0).ok_or_else(|| {
+            vm.throw_error(ErrorType::Error, "require(): One argument is needed.")
+        })?;
         match val {
             Value::String(_) => val.to_string(),
             _ => {
-                return Err(vm
-                    .current_context
-                    .error_type("require(): An argument should be string."));
+                return Err(vm.throw_type_error("require(): An argument should be string."));
             }
         }
     };
 
-    use crate::parser::Parser;
-    let mut parser = Parser::load_module(file_name.clone())
-        .map_err(|e| return vm.current_context.error_general(format!("{:?}", e)))?;
+    let referrer = vm
+        .script_info
+        .iter()
+        .find(|info| info.0 == vm.current_context.func_ref.module_func_id)
+        .map(|info| info.1.file_name.clone());
+    let referrer = referrer.as_ref().map(|s| s.as_str());
+    let file_name = vm
+        .import_map
+        .resolve(file_name.as_str(), referrer)
+        .map_err(|msg| vm.throw_type_error(msg))?;
+
+    log::debug!("loading module {} (required from {:?})", file_name, referrer);
+    let mut parser = load_module(vm, file_name.as_str(), referrer)?;
+    vm.transform_source(&mut parser)
+        .map_err(|msg| vm.throw_error(ErrorType::Error, msg))?;
     let absolute_path = parser.file_name.clone();
 
-    let node = parser.parse_all().map_err(|parse_err| {
+    let mut node = parser.parse_all().map_err(|parse_err| {
         parser.handle_error(&parse_err);
-        vm.current_context
-            .error_general(format!("Error in parsing module \"{}\"", file_name))
+        vm.throw_error(
+            ErrorType::SyntaxError,
+            format!("Error in parsing module \"{}\"", file_name),
+        )
     })?;
 
     use crate::vm::codegen::Error;
-    let module_info = vm.compile(&node, true).map_err(|codegen_err| {
-        let Error { msg, token_pos, .. } = codegen_err;
-        parser.show_error_at(token_pos, msg);
-        vm.current_context
-            .error_general(format!("Error in parsing module \"{}\"", file_name))
-    })?;
+    let module_info = vm
+        .compile_with_source(&mut node, true, parser.source_text())
+        .map_err(|codegen_err| {
+            let Error { msg, token_pos, .. } = codegen_err;
+            parser.show_error_at(token_pos, msg);
+            vm.throw_error(
+                ErrorType::SyntaxError,
+                format!("Error in parsing module \"{}\"", file_name),
+            )
+        })?;
     let id = module_info.module_func_id;
+    log::debug!("loaded module {} as {:?}", absolute_path, id);
     let script_info = parser.into_script_info();
     vm.script_info.push((id, script_info));
 
@@ -154,3 +192,41 @@ pub fn require(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
 
     Ok(Value::empty())
 }
+
+/// Create the parser of the module ``file_name``. URLs are fetched if the ``remote-modules``
+/// feature is enabled, and relative specifiers in a remote module are resolved against its URL.
+fn load_module(
+    vm: &mut VM,
+    file_name: &str,
+    referrer: Option<&str>,
+) -> Result<crate::parser::Parser, RuntimeError> {
+    use crate::parser::Parser;
+
+    #[cfg(feature = "remote-modules")]
+    {
+        if let Some(url) = crate::remote_module::resolve(file_name, referrer) {
+            log::debug!("fetching remote module {}", url);
+            let source = vm
+                .remote_modules
+                .fetch(url.as_str())
+                .map_err(|msg| vm.throw_error(ErrorType::Error, msg))?;
+            return Ok(Parser::new(url, source));
+        }
+        // Remote code can not load files on the local disk.
+        if let Some(referrer) = referrer.filter(|r| crate::remote_module::is_url(r)) {
+            return Err(vm.throw_error(
+                ErrorType::Error,
+                format!(
+                    "Cannot load '{}' from the remote module '{}': only URLs and relative \
+                     specifiers are allowed",
+                    file_name, referrer
+                ),
+            ));
+        }
+    }
+    #[cfg(not(feature = "remote-modules"))]
+    let _ = referrer;
+
+    Parser::load_module(file_name)
+        .map_err(|e| vm.throw_error(ErrorType::Error, format!("{:?}", e)))
+}
@@ -1,4 +1,5 @@
 use crate::vm::{
+    error::message,
     jsvalue::{object::Property, value::Value},
     vm::{Factory, VMValueResult, VM},
 };
@@ -20,6 +21,7 @@ pub fn array_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueRe
                 let len = args[0];
                 if len.is_number() {
                     let len = len.to_uint32(&mut vm.factory.memory_allocator) as usize;
+                    vm.factory.memory_allocator.limits.check_array_length(len)?;
                     vec![Property::new_data_simple(Value::empty()); len]
                 } else {
                     vec![Property::new_data_simple(args[0])]
@@ -40,7 +42,9 @@ pub fn array_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueRe
 
 pub fn array_prototype_join(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
     if !this.is_array_object() {
-        return Err(vm.current_context.error_unknown());
+        return Err(
+            vm.throw_type_error(message::incompatible_receiver("Array.prototype.join", this))
+        );
     }
 
     let ary_info = this.as_array_mut();
@@ -49,17 +53,27 @@ pub fn array_prototype_join(vm: &mut VM, args: &[Value], this: Value) -> VMValue
         1 if args[0].is_undefined() => None,
         _ => Some(args[0].to_string()),
     };
-    let result = ary_info.join(seperator);
+    let limits = vm.factory.memory_allocator.limits;
+    let result = match ary_info.join_within(seperator, limits.max_string_length) {
+        Some(result) => result,
+        None => return Err(vm.throw_range_error("Invalid string length")),
+    };
     let val = vm.factory.string(result);
     Ok(val)
 }
 
 pub fn array_prototype_push(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
     if !this.is_array_object() {
-        return Err(vm.current_context.error_unknown());
+        return Err(
+            vm.throw_type_error(message::incompatible_receiver("Array.prototype.push", this))
+        );
     }
 
     let ary_info = this.as_array_mut();
+    vm.factory
+        .memory_allocator
+        .limits
+        .check_array_length(ary_info.get_length() + args.len())?;
 
     for arg in args {
         ary_info.elems.push(Property::new_data_simple(*arg));
@@ -72,7 +86,9 @@ pub fn array_prototype_push(vm: &mut VM, args: &[Value], this: Value) -> VMValue
 
 pub fn array_prototype_map(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
     if !this.is_array_object() {
-        return Err(vm.current_context.error_unknown());
+        return Err(
+            vm.throw_type_error(message::incompatible_receiver("Array.prototype.map", this))
+        );
     }
 
     let ary_info = this.as_array_mut();
@@ -1,7 +1,8 @@
 use crate::vm::{
+    atom::Atom,
     jsvalue::value::{
-        cstrp_to_str, AccessorProperty, DataProperty, ObjectKind, ObjectRef, Property, Value,
-        EMPTY, NULL, UNDEFINED, UNINITIALIZED,
+        cstrp_to_str, number_to_str, AccessorProperty, DataProperty, ObjectKind, ObjectRef,
+        Property, Value, EMPTY, NULL, UNDEFINED, UNINITIALIZED,
     },
     vm::VMValueResult,
     vm::VM,
@@ -22,29 +23,33 @@ pub fn console_log(_vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult
 }
 
 pub fn debug_print(val: &Value, nest: bool) {
-    fn show_obj(sorted_key_val: Vec<(&String, &Property)>) {
+    fn show_property(prop: &Property) {
+        match prop {
+            Property::Data(DataProperty { val, .. }) => {
+                debug_print(&val, true);
+            }
+            Property::Accessor(AccessorProperty { get, set, .. }) => {
+                let s_get = if get.is_undefined() { "" } else { "Getter" };
+                let s_set = if set.is_undefined() { "" } else { "Setter" };
+                print!(
+                    "[{}{}{}]",
+                    s_get,
+                    if !get.is_undefined() && !set.is_undefined() {
+                        "/"
+                    } else {
+                        ""
+                    },
+                    s_set
+                );
+            }
+        }
+    }
+
+    fn show_obj(sorted_key_val: Vec<(&Atom, &Property)>) {
         for (i, tupple) in sorted_key_val.iter().enumerate() {
             print!("'{}': ", tupple.0.as_str());
 
-            match tupple.1 {
-                Property::Data(DataProperty { val, .. }) => {
-                    debug_print(&val, true);
-                }
-                Property::Accessor(AccessorProperty { get, set, .. }) => {
-                    let s_get = if get.is_undefined() { "" } else { "Getter" };
-                    let s_set = if set.is_undefined() { "" } else { "Setter" };
-                    print!(
-                        "[{}{}{}]",
-                        s_get,
-                        if !get.is_undefined() && !set.is_undefined() {
-                            "/"
-                        } else {
-                            ""
-                        },
-                        s_set
-                    );
-                }
-            }
+            show_property(tupple.1);
 
             print!(
                 "{}",
@@ -66,9 +71,9 @@ pub fn debug_print(val: &Value, nest: bool) {
         Value::Bool(1) => print!("true"),
         Value::Bool(0) => print!("false"),
         Value::Bool(_) => unreachable!(),
-        Value::Number(n) if n.is_nan() => print!("NaN"),
-        Value::Number(n) if n.is_infinite() => print!("Infinity"),
-        Value::Number(n) => print!("{}", *n),
+        // console.log shows the sign of -0, unlike ToString.
+        Value::Number(n) if *n == 0.0 && n.is_sign_negative() => print!("-0"),
+        Value::Number(n) => print!("{}", number_to_str(*n)),
         Value::String(ref s) => {
             let s = cstrp_to_str(*s);
             if nest {
@@ -86,7 +91,7 @@ pub fn debug_print(val: &Value, nest: bool) {
 
                     let mut sorted_key_val = (&obj_info.property)
                         .iter()
-                        .collect::<Vec<(&String, &Property)>>();
+                        .collect::<Vec<(&Atom, &Property)>>();
                     sorted_key_val.sort_by(|(key1, _), (key2, _)| key1.as_str().cmp(key2.as_str()));
 
                     show_obj(sorted_key_val);
@@ -100,6 +105,11 @@ pub fn debug_print(val: &Value, nest: bool) {
                 ObjectKind::Error(ref _info) => {
                     print!("Error({})", obj_info.get_property("message").to_string())
                 }
+                ObjectKind::Map(_) | ObjectKind::Set(_) => print!("{}", val.debug_string(true)),
+                ObjectKind::Proxy(ref proxy_info) => debug_print(&proxy_info.target, nest),
+                ObjectKind::PrimitiveWrapper(val) => {
+                    print!("[{}: {}]", val.wrapper_name(), val.debug_string(true))
+                }
                 ObjectKind::Function(ref func_info) => {
                     if let Some(ref name) = func_info.name {
                         print!("[Function: {}]", name);
@@ -112,7 +122,7 @@ pub fn debug_print(val: &Value, nest: bool) {
 
                     let mut sorted_key_val = (&obj_info.property)
                         .iter()
-                        .collect::<Vec<(&String, &Property)>>();
+                        .collect::<Vec<(&Atom, &Property)>>();
                     sorted_key_val.sort_by(|(key1, _), (key2, _)| key1.as_str().cmp(key2.as_str()));
 
                     let length = ary_info.elems.len();
@@ -120,7 +130,7 @@ pub fn debug_print(val: &Value, nest: bool) {
                     let mut i = 0;
                     while i < length {
                         let mut empty_elems = 0;
-                        while i < length && Value::empty() == ary_info.elems[i].as_data().val {
+                        while i < length && ary_info.get_own_element(i).is_none() {
                             empty_elems += 1;
                             i += 1;
                         }
@@ -142,7 +152,7 @@ pub fn debug_print(val: &Value, nest: bool) {
                             }
                         }
 
-                        debug_print(&ary_info.elems[i].as_data().val, true);
+                        show_property(&ary_info.elems[i]);
 
                         if is_last_idx(i) && sorted_key_val.len() == 0 {
                             print!(" ")
@@ -1,24 +1,54 @@
 use crate::vm::{
-    jsvalue::value::Value,
+    jsvalue::value::{ErrorType, Value},
     vm::{Factory, VMValueResult, VM},
 };
 
 pub fn error(factory: &mut Factory) -> Value {
-    factory.generate_builtin_constructor(
-        "Error",
-        error_constructor,
-        factory.object_prototypes.error,
-    )
+    let constructor = native_error(factory, ErrorType::Error);
+    // The number of frames in the stack of errors, as in V8.
+    constructor.set_property("stackTraceLimit", Value::Number(10.0));
+    constructor
 }
 
-pub fn error_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
-    let message = if args.len() == 0 {
-        "".to_string()
-    } else {
-        args[0].to_string()
+/// https://tc39.github.io/ecma262/#sec-nativeerror-constructors
+pub fn native_error(factory: &mut Factory, error_type: ErrorType) -> Value {
+    let constructor = match error_type {
+        ErrorType::Error => error_constructor,
+        ErrorType::TypeError => type_error_constructor,
+        ErrorType::ReferenceError => reference_error_constructor,
+        ErrorType::RangeError => range_error_constructor,
+        ErrorType::SyntaxError => syntax_error_constructor,
+    };
+    let prototype = factory.object_prototypes.native_error(error_type);
+    factory.generate_builtin_constructor(error_type.name(), constructor, prototype)
+}
+
+fn construct(vm: &mut VM, args: &[Value], error_type: ErrorType) -> VMValueResult {
+    let message = match args.get(0) {
+        Some(message) if !message.is_undefined() => message.to_string(),
+        _ => "".to_string(),
     };
-    let obj = vm.factory.error(message);
-    Ok(obj)
+    Ok(vm.create_error(error_type, message))
+}
+
+pub fn error_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    construct(vm, args, ErrorType::Error)
+}
+
+pub fn type_error_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    construct(vm, args, ErrorType::TypeError)
+}
+
+pub fn reference_error_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    construct(vm, args, ErrorType::ReferenceError)
+}
+
+pub fn range_error_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    construct(vm, args, ErrorType::RangeError)
+}
+
+pub fn syntax_error_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    construct(vm, args, ErrorType::SyntaxError)
 }
 
 // pub fn init() -> Value {
@@ -1,4 +1,5 @@
 use crate::vm::{
+    error::message,
     jsvalue::{function::UserFunctionInfo, value::Value},
     vm::{Factory, VMValueResult, VM},
 };
@@ -26,3 +27,16 @@ pub fn function_prototype_call(vm: &mut VM, args: &[Value], this: Value) -> VMVa
     let func = this;
     vm.call_function(func, args.get(1..).unwrap_or(&[]), this_arg)
 }
+
+/// https://tc39.github.io/ecma262/#sec-function.prototype.bind
+pub fn function_prototype_bind(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    if !this.is_function_object() {
+        return Err(vm.throw_type_error(message::incompatible_receiver(
+            "Function.prototype.bind",
+            this,
+        )));
+    }
+    let this_arg = *args.get(0).unwrap_or(&Value::undefined());
+    let bound_args = args.get(1..).unwrap_or(&[]).to_vec();
+    Ok(vm.factory.bound_function(this, this_arg, bound_args))
+}
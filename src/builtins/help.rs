@@ -0,0 +1,281 @@
+//! Descriptions of functions for `Rapidus.inspect` and the `.help` command of the REPL, and
+//! their disassembly for the `.dis` command.
+
+use crate::builtin::{self, BuiltinFuncTy};
+use crate::builtins::{
+    array, console, error, function, json, map, math, number, object, proxy, reflect, set, string,
+    symbol,
+};
+use crate::bytecode_gen::{read_int32, show_inst, VMInst};
+use crate::vm::{
+    constant::Constant,
+    jsvalue::function::{FuncInfoRef, FunctionObjectKind, UserFunctionInfo},
+    jsvalue::value::Value,
+    vm::{VMValueResult, VM},
+};
+
+/// The signature and the description of a built-in function.
+pub struct BuiltinDoc {
+    pub func: BuiltinFuncTy,
+    pub signature: &'static str,
+    pub description: &'static str,
+}
+
+macro_rules! builtin_docs {
+    ($($func:expr, $signature:expr, $description:expr;)*) => {
+        &[$(BuiltinDoc { func: $func, signature: $signature, description: $description }),*]
+    };
+}
+
+pub static BUILTIN_DOCS: &[BuiltinDoc] = builtin_docs! {
+    console::console_log, "console.log(...values)",
+        "Print the values separated by spaces.";
+    builtin::parse_float, "parseFloat(string)",
+        "Parse the decimal number at the start of string. NaN if there is none.";
+    builtin::eval, "eval(code)",
+        "Run code as a script and return its completion value.";
+    builtin::require, "require(specifier)",
+        "Load the module specifier and return its module.exports.";
+    builtin::deep_seq, "__assert_deep_seq(actual, expected)",
+        "Whether the values are deeply and strictly equal.";
+    object::object_constructor, "Object(value)",
+        "Convert value into an object.";
+    object::object_define_property, "Object.defineProperty(obj, key, descriptor)",
+        "Define or change the property key of obj by the attributes in descriptor.";
+    object::object_get_own_property_descriptor, "Object.getOwnPropertyDescriptor(obj, key)",
+        "The attributes of the own property key of obj, or undefined.";
+    object::object_get_own_property_descriptors, "Object.getOwnPropertyDescriptors(obj)",
+        "The attributes of all the own properties of obj.";
+    object::object_keys, "Object.keys(obj)",
+        "The keys of the enumerable own properties of obj.";
+    function::function_prototype_call, "Function.prototype.call(thisArg, ...args)",
+        "Call the function with thisArg as this.";
+    function::function_prototype_bind, "Function.prototype.bind(thisArg, ...args)",
+        "Create a function calling the function with thisArg as this and args first.";
+    array::array_constructor, "Array(...items)",
+        "Create an array of items, or of the length given as the only argument.";
+    array::array_prototype_join, "Array.prototype.join(separator)",
+        "Join the elements into a string, separated by separator (\",\" by default).";
+    array::array_prototype_push, "Array.prototype.push(...items)",
+        "Append items to the array and return its new length.";
+    array::array_prototype_map, "Array.prototype.map(callback, thisArg)",
+        "Create an array of the results of calling callback on each element.";
+    number::number_constructor, "Number(value)",
+        "Convert value to a number, e.g. \"0x1F\" to 31. 0 without value.";
+    number::number_prototype_to_string, "Number.prototype.toString(radix)",
+        "The number in radix (2 to 36, 10 by default).";
+    string::string_prototype_split, "String.prototype.split(separator)",
+        "Split the string into an array of the substrings between separators.";
+    string::string_prototype_index_of, "String.prototype.indexOf(searchString)",
+        "The index of the first occurrence of searchString, or -1.";
+    string::string_prototype_locale_compare, "String.prototype.localeCompare(that, locales, options)",
+        "Compare the string with that in the sort order of the locale.";
+    math::math_random, "Math.random()",
+        "A pseudo-random number from 0 (inclusive) to 1 (exclusive).";
+    symbol::symbol_constructor, "Symbol(description)",
+        "Create a new unique symbol.";
+    symbol::symbol_for, "Symbol.for(key)",
+        "Get the symbol registered for key, registering a new one if there is none.";
+    symbol::symbol_key_for, "Symbol.keyFor(sym)",
+        "Get the key sym is registered for, or undefined.";
+    error::error_constructor, "Error(message)",
+        "Create an error.";
+    error::type_error_constructor, "TypeError(message)",
+        "Create an error for a value of an unexpected type.";
+    error::reference_error_constructor, "ReferenceError(message)",
+        "Create an error for an undefined variable.";
+    error::range_error_constructor, "RangeError(message)",
+        "Create an error for a value out of the allowed range.";
+    error::syntax_error_constructor, "SyntaxError(message)",
+        "Create an error for invalid code.";
+    map::map_constructor, "Map(entries)",
+        "Create a map of the [key, value] pairs in the array entries.";
+    map::map_prototype_get, "Map.prototype.get(key)",
+        "Get the value for key, or undefined.";
+    map::map_prototype_set, "Map.prototype.set(key, value)",
+        "Set the value for key and return the map.";
+    map::map_prototype_has, "Map.prototype.has(key)",
+        "Whether the map has key.";
+    map::map_prototype_delete, "Map.prototype.delete(key)",
+        "Remove key, and return whether it was in the map.";
+    map::map_prototype_clear, "Map.prototype.clear()",
+        "Remove all the entries.";
+    map::map_prototype_size, "Map.prototype.size",
+        "The number of entries.";
+    map::map_prototype_for_each, "Map.prototype.forEach(callback, thisArg)",
+        "Call callback with each value and key.";
+    set::set_constructor, "Set(values)",
+        "Create a set of the values in the array values.";
+    set::set_prototype_add, "Set.prototype.add(value)",
+        "Add value and return the set.";
+    set::set_prototype_has, "Set.prototype.has(value)",
+        "Whether the set has value.";
+    set::set_prototype_delete, "Set.prototype.delete(value)",
+        "Remove value, and return whether it was in the set.";
+    set::set_prototype_clear, "Set.prototype.clear()",
+        "Remove all the values.";
+    set::set_prototype_size, "Set.prototype.size",
+        "The number of values.";
+    set::set_prototype_for_each, "Set.prototype.forEach(callback, thisArg)",
+        "Call callback with each value.";
+    proxy::proxy_constructor, "Proxy(target, handler)",
+        "Create a proxy of target whose operations are intercepted by the traps of handler.";
+    reflect::reflect_get, "Reflect.get(target, key, receiver)",
+        "Get the property key of target, calling its getter with receiver as this.";
+    reflect::reflect_set, "Reflect.set(target, key, value, receiver)",
+        "Set the property key of target, and return whether it was set.";
+    reflect::reflect_has, "Reflect.has(target, key)",
+        "Whether target has the property key, as by the in operator.";
+    reflect::reflect_own_keys, "Reflect.ownKeys(target)",
+        "The keys of the own properties of target.";
+    reflect::reflect_define_property, "Reflect.defineProperty(target, key, descriptor)",
+        "Define the property key of target, and return whether it was defined.";
+    reflect::reflect_get_prototype_of, "Reflect.getPrototypeOf(target)",
+        "The prototype of target.";
+    reflect::reflect_apply, "Reflect.apply(func, thisArg, args)",
+        "Call func with thisArg as this and the elements of the array args.";
+    reflect::reflect_construct, "Reflect.construct(func, args)",
+        "Call func by new with the elements of the array args.";
+    json::json_parse, "JSON.parse(text, reviver)",
+        "Parse the JSON text. reviver, if given, is called on each key and value to replace it.";
+    rapidus_inspect, "Rapidus.inspect(value)",
+        "Print the parameters and the source of a function, or the description of a builtin.";
+};
+
+/// The number of lines of a function printed at most.
+const SNIPPET_LINES: usize = 5;
+
+pub fn builtin_doc(func: BuiltinFuncTy) -> Option<&'static BuiltinDoc> {
+    BUILTIN_DOCS
+        .iter()
+        .find(|doc| doc.func as *const () == func as *const ())
+}
+
+/// Print the description of ``args[0]``. See ``describe``.
+pub fn rapidus_inspect(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let val = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    println!("{}", describe(vm, val));
+    Ok(Value::undefined())
+}
+
+/// Describe ``val``. Functions are described by their parameters and source, or by the
+/// description in ``BUILTIN_DOCS``. Other values are just shown.
+pub fn describe(vm: &VM, val: Value) -> String {
+    if !val.is_function_object() {
+        return val.debug_string(true);
+    }
+    let func = val.as_function();
+    let name = func.name.as_ref().map_or("", |name| name.as_str());
+    match &func.kind {
+        FunctionObjectKind::User { info, .. } => {
+            let mut text = format!("function {}({})", name, parameters(info));
+            if let Some(snippet) = source_snippet(vm, info) {
+                text.push('\n');
+                text.push_str(&snippet);
+            }
+            text
+        }
+        FunctionObjectKind::Builtin(func) => match builtin_doc(*func) {
+            Some(doc) => format!("{} [builtin]\n  {}", doc.signature, doc.description),
+            None => format!("function {}() [builtin]", name),
+        },
+        FunctionObjectKind::Native(_) => format!("function {}() [native]", name),
+        FunctionObjectKind::Bound { target, .. } => {
+            format!("function {}() [bound]\n  {}", name, describe(vm, *target))
+        }
+    }
+}
+
+/// The bytecode of the function ``info``, followed by that of the functions defined in it.
+pub fn disassemble(vm: &VM, info: &UserFunctionInfo) -> String {
+    let name = info
+        .func_name
+        .as_ref()
+        .map_or("(anonymous)", |name| name.as_str());
+    let mut text = format!(
+        "function {}({}) [id {:?}]",
+        name,
+        parameters(info),
+        info.func_id
+    );
+    let mut inner_funcs: Vec<FuncInfoRef> = info.func_decls.clone();
+    let mut pc = 0;
+    let mut line = None;
+    while pc < info.code.len() {
+        // The source line of the instructions which follow, when it changes.
+        if let Some((new_line, source)) = vm.source_line(info, pc) {
+            if line != Some(new_line) {
+                text.push_str(&format!("\n  ; {}: {}", new_line, source.trim()));
+                line = Some(new_line);
+            }
+        }
+        text.push_str("\n  ");
+        text.push_str(show_inst(&info.code, pc, &vm.constant_table).trim_end());
+        // Function expressions are constants.
+        if info.code[pc] == VMInst::PUSH_CONST {
+            let id = read_int32(&info.code, pc + 1) as usize;
+            if let Constant::Value(val) = vm.constant_table.get(id) {
+                if val.is_function_object() {
+                    if let FunctionObjectKind::User { info, .. } = val.as_function().kind {
+                        inner_funcs.push(info);
+                    }
+                }
+            }
+        }
+        pc += VMInst::get_inst_size(info.code[pc]).expect("inst_size not defined.");
+    }
+    for inner_func in inner_funcs {
+        text.push_str("\n\n");
+        text.push_str(&disassemble(vm, &inner_func));
+    }
+    text
+}
+
+fn parameters(info: &UserFunctionInfo) -> String {
+    info.params
+        .iter()
+        .map(|param| {
+            if param.rest_param {
+                format!("...{}", param.name)
+            } else {
+                param.name.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The location of the function and its first lines, up to the line closing its body.
+fn source_snippet(vm: &VM, info: &UserFunctionInfo) -> Option<String> {
+    let (line, column) = info.location?;
+    let script = vm.script(info.module_func_id)?;
+    // The line numbers of the script may start from a line offset.
+    let first_line = script.pos_line_list.first().map_or(1, |(_, line)| *line);
+    let mut lines = script.code.lines().skip(line.checked_sub(first_line)?);
+
+    let mut snippet = format!("  at {}:{}:{}", script.file_name, line, column);
+    let mut depth = 0;
+    for (i, text) in lines.by_ref().take(SNIPPET_LINES).enumerate() {
+        snippet.push_str("\n  | ");
+        snippet.push_str(text);
+        let body = if i == 0 {
+            text.chars().skip(column - 1).collect::<String>()
+        } else {
+            text.to_string()
+        };
+        for c in body.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        if depth <= 0 {
+            return Some(snippet);
+        }
+    }
+    if lines.next().is_some() {
+        snippet.push_str("\n  | ...");
+    }
+    Some(snippet)
+}
@@ -0,0 +1,885 @@
+//! Internationalization builtins, enabled by the ``intl`` feature.
+//! Only the root collation is supported for now: the given locales are ignored, and accented
+//! Latin letters are compared as their base letters at the primary level.
+//! Intl.PluralRules and Intl.NumberFormat use a built-in subset of the CLDR data, which has
+//! the ``en`` and the root locales. Other locales fall back to the root locale.
+//! https://tc39.github.io/ecma402/
+
+use crate::gc::MemoryAllocator;
+use crate::vm::{
+    error::message,
+    jsvalue::value::Value,
+    vm::{Factory, VMValueResult, VM},
+};
+use std::cmp::Ordering;
+
+/// Intl.Collator is self-hosted in src/builtins/js/intl.js.
+pub fn intl(factory: &mut Factory) -> Value {
+    let plural_rules = factory.builtin_constructor("PluralRules", plural_rules_constructor);
+    let number_format = factory.builtin_constructor("NumberFormat", number_format_constructor);
+    make_normal_object!(factory,
+        PluralRules  => true, false, true: plural_rules,
+        NumberFormat => true, false, true: number_format
+    )
+}
+
+/// https://tc39.github.io/ecma402/#sec-collator-comparestrings
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sensitivity {
+    /// Only base letters differ: a ≠ b, a = á, a = A.
+    Base,
+    /// Base letters or accents differ: a ≠ b, a ≠ á, a = A.
+    Accent,
+    /// Base letters or case differ: a ≠ b, a = á, a ≠ A.
+    Case,
+    /// Base letters, accents, case or other differences: a ≠ b, a ≠ á, a ≠ A.
+    Variant,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CollatorOptions {
+    pub sensitivity: Sensitivity,
+    /// Compare sequences of digits by their numeric value.
+    pub numeric: bool,
+}
+
+impl Default for CollatorOptions {
+    fn default() -> Self {
+        CollatorOptions {
+            sensitivity: Sensitivity::Variant,
+            numeric: false,
+        }
+    }
+}
+
+impl CollatorOptions {
+    /// Read the options object given to Intl.Collator or String.prototype.localeCompare.
+    /// Return the error message if an option has an invalid value.
+    pub fn from_value(options: Value) -> Result<CollatorOptions, String> {
+        let mut collator_options = CollatorOptions::default();
+
+        let sensitivity = options.get_property("sensitivity");
+        if !sensitivity.is_undefined() {
+            collator_options.sensitivity = match sensitivity.to_string().as_str() {
+                "base" => Sensitivity::Base,
+                "accent" => Sensitivity::Accent,
+                "case" => Sensitivity::Case,
+                "variant" => Sensitivity::Variant,
+                other => {
+                    return Err(format!(
+                        "Value {} out of range for Intl.Collator options property sensitivity",
+                        other
+                    ));
+                }
+            };
+        }
+
+        let numeric = options.get_property("numeric");
+        if !numeric.is_undefined() {
+            collator_options.numeric = numeric.to_boolean();
+        }
+
+        Ok(collator_options)
+    }
+}
+
+/// Accented letters and their base letters.
+const ACCENTED_LETTERS: &[(&str, char)] = &[
+    ("àáâãäåāăą", 'a'),
+    ("çćĉċč", 'c'),
+    ("ďđ", 'd'),
+    ("èéêëēĕėęě", 'e'),
+    ("ĝğġģ", 'g'),
+    ("ĥħ", 'h'),
+    ("ìíîïĩīĭįı", 'i'),
+    ("ĵ", 'j'),
+    ("ķ", 'k'),
+    ("ĺļľŀł", 'l'),
+    ("ñńņňŉ", 'n'),
+    ("òóôõöøōŏő", 'o'),
+    ("ŕŗř", 'r'),
+    ("śŝşš", 's'),
+    ("ţťŧ", 't'),
+    ("ùúûüũūŭůűų", 'u'),
+    ("ŵ", 'w'),
+    ("ýÿŷ", 'y'),
+    ("źżž", 'z'),
+];
+
+/// Primary weight of a collation element. Variants are ordered as in the root collation:
+/// whitespace, punctuation and symbols, digits, then letters.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Primary {
+    Whitespace(char),
+    Symbol(char),
+    /// Digits without leading zeros, compared by length first when ``numeric`` is set.
+    Number(usize, String),
+    Letter(char),
+}
+
+#[derive(Clone, Debug)]
+struct CollationElement {
+    primary: Primary,
+    /// 0 for unaccented letters.
+    secondary: usize,
+    /// 0 for lowercase, so that lowercase letters sort first.
+    tertiary: usize,
+}
+
+fn collation_elements(s: &str, numeric: bool) -> Vec<CollationElement> {
+    let mut elements = vec![];
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if numeric && c.is_ascii_digit() {
+            let mut digits = c.to_string();
+            while let Some(&c) = chars.peek() {
+                if !c.is_ascii_digit() {
+                    break;
+                }
+                digits.push(c);
+                chars.next();
+            }
+            let digits = digits.trim_start_matches('0').to_string();
+            elements.push(CollationElement {
+                primary: Primary::Number(digits.len(), digits),
+                secondary: 0,
+                tertiary: 0,
+            });
+            continue;
+        }
+
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        let tertiary = if lower != c { 1 } else { 0 };
+        let (base, secondary) = ACCENTED_LETTERS
+            .iter()
+            .find_map(|(accented, base)| {
+                accented
+                    .chars()
+                    .position(|accented| accented == lower)
+                    .map(|i| (*base, i + 1))
+            })
+            .unwrap_or((lower, 0));
+
+        let primary = if base.is_whitespace() {
+            Primary::Whitespace(base)
+        } else if base.is_numeric() {
+            Primary::Number(1, base.to_string())
+        } else if base.is_alphabetic() {
+            Primary::Letter(base)
+        } else {
+            Primary::Symbol(base)
+        };
+
+        elements.push(CollationElement {
+            primary,
+            secondary,
+            tertiary,
+        });
+    }
+
+    elements
+}
+
+/// Compare two strings at the levels the sensitivity asks for: base letters, then accents,
+/// then case.
+pub fn compare(x: &str, y: &str, options: &CollatorOptions) -> Ordering {
+    let x_elements = collation_elements(x, options.numeric);
+    let y_elements = collation_elements(y, options.numeric);
+
+    let primary = x_elements
+        .iter()
+        .map(|e| &e.primary)
+        .cmp(y_elements.iter().map(|e| &e.primary));
+    let secondary = || {
+        x_elements
+            .iter()
+            .map(|e| e.secondary)
+            .cmp(y_elements.iter().map(|e| e.secondary))
+    };
+    let tertiary = || {
+        x_elements
+            .iter()
+            .map(|e| e.tertiary)
+            .cmp(y_elements.iter().map(|e| e.tertiary))
+    };
+
+    match options.sensitivity {
+        Sensitivity::Base => primary,
+        Sensitivity::Accent => primary.then_with(secondary),
+        Sensitivity::Case => primary.then_with(tertiary),
+        Sensitivity::Variant => primary
+            .then_with(secondary)
+            .then_with(tertiary)
+            .then_with(|| x.cmp(y)),
+    }
+}
+
+/// Number symbols and plural rules of a locale, taken from CLDR.
+#[derive(Debug)]
+pub struct LocaleData {
+    /// The resolved locale reported by resolvedOptions().
+    pub locale: &'static str,
+    pub decimal: &'static str,
+    pub group: &'static str,
+    pub percent_sign: &'static str,
+    pub minus_sign: &'static str,
+    pub infinity: &'static str,
+    pub nan: &'static str,
+    pub cardinal: fn(&PluralOperands) -> PluralCategory,
+    pub ordinal: fn(&PluralOperands) -> PluralCategory,
+}
+
+pub static EN: LocaleData = LocaleData {
+    locale: "en",
+    decimal: ".",
+    group: ",",
+    percent_sign: "%",
+    minus_sign: "-",
+    infinity: "∞",
+    nan: "NaN",
+    cardinal: en_cardinal,
+    ordinal: en_ordinal,
+};
+
+/// The root locale, whose plural rules have only the ``other`` category.
+pub static ROOT: LocaleData = LocaleData {
+    locale: "und",
+    decimal: ".",
+    group: ",",
+    percent_sign: "%",
+    minus_sign: "-",
+    infinity: "∞",
+    nan: "NaN",
+    cardinal: root_plural,
+    ordinal: root_plural,
+};
+
+/// Look up the data for the first requested locale. ``en`` is the default locale.
+/// https://tc39.github.io/ecma402/#sec-resolvelocale
+pub fn resolve_locale(locales: Value) -> &'static LocaleData {
+    let locale = if locales.is_undefined() {
+        return &EN;
+    } else if locales.is_array_object() {
+        locales.get_property("0").to_string()
+    } else {
+        locales.to_string()
+    };
+    let language = locale.split(|c| c == '-' || c == '_').next().unwrap_or("");
+    if language.eq_ignore_ascii_case("en") {
+        &EN
+    } else {
+        &ROOT
+    }
+}
+
+/// https://unicode.org/reports/tr35/tr35-numbers.html#Language_Plural_Rules
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PluralCategory {
+    One,
+    Two,
+    Few,
+    Other,
+}
+
+impl PluralCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PluralCategory::One => "one",
+            PluralCategory::Two => "two",
+            PluralCategory::Few => "few",
+            PluralCategory::Other => "other",
+        }
+    }
+}
+
+/// Operands of a number for plural rules, taken from the digits it is formatted with.
+/// https://unicode.org/reports/tr35/tr35-numbers.html#Operands
+#[derive(Clone, Debug, PartialEq)]
+pub struct PluralOperands {
+    /// The integer digits, saturated at ``u64::MAX``.
+    pub i: u64,
+    /// The last two integer digits, for the rules using ``i % 100``.
+    pub i_mod_100: u64,
+    /// Number of visible fraction digits, with trailing zeros.
+    pub v: usize,
+    /// Whether all the visible fraction digits are zero.
+    pub is_integer: bool,
+}
+
+impl PluralOperands {
+    fn new(integer: &str, fraction: &str) -> Self {
+        let last_two = &integer[integer.len().saturating_sub(2)..];
+        PluralOperands {
+            i: integer.parse().unwrap_or(::std::u64::MAX),
+            i_mod_100: last_two.parse().unwrap_or(0),
+            v: fraction.len(),
+            is_integer: fraction.chars().all(|c| c == '0'),
+        }
+    }
+}
+
+fn root_plural(_operands: &PluralOperands) -> PluralCategory {
+    PluralCategory::Other
+}
+
+/// one: i = 1 and v = 0
+fn en_cardinal(operands: &PluralOperands) -> PluralCategory {
+    if operands.i == 1 && operands.v == 0 {
+        PluralCategory::One
+    } else {
+        PluralCategory::Other
+    }
+}
+
+/// one: n % 10 = 1 and n % 100 != 11, two: n % 10 = 2 and n % 100 != 12,
+/// few: n % 10 = 3 and n % 100 != 13
+fn en_ordinal(operands: &PluralOperands) -> PluralCategory {
+    if !operands.is_integer {
+        return PluralCategory::Other;
+    }
+    match (operands.i_mod_100 % 10, operands.i_mod_100) {
+        (1, n) if n != 11 => PluralCategory::One,
+        (2, n) if n != 12 => PluralCategory::Two,
+        (3, n) if n != 13 => PluralCategory::Few,
+        _ => PluralCategory::Other,
+    }
+}
+
+/// https://tc39.github.io/ecma402/#sec-getoption
+fn get_string_option(
+    options: Value,
+    property: &str,
+    values: &[&'static str],
+    default: &'static str,
+) -> Result<&'static str, String> {
+    let val = options.get_property(property);
+    if val.is_undefined() {
+        return Ok(default);
+    }
+    let val = val.to_string();
+    values
+        .iter()
+        .find(|v| **v == val.as_str())
+        .map(|v| *v)
+        .ok_or_else(|| {
+            format!(
+                "Value {} out of range for options property {}",
+                val, property
+            )
+        })
+}
+
+/// https://tc39.github.io/ecma402/#sec-getnumberoption
+fn get_number_option(
+    allocator: &mut MemoryAllocator,
+    options: Value,
+    property: &str,
+    minimum: usize,
+    maximum: usize,
+    default: usize,
+) -> Result<usize, String> {
+    let val = options.get_property(property);
+    if val.is_undefined() {
+        return Ok(default);
+    }
+    let n = val.to_number(allocator);
+    if n.is_nan() || n < minimum as f64 || n > maximum as f64 {
+        return Err(format!("{} value is out of range.", property));
+    }
+    Ok(n.floor() as usize)
+}
+
+/// The digit options shared by Intl.NumberFormat and Intl.PluralRules.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DigitOptions {
+    pub minimum_integer_digits: usize,
+    pub minimum_fraction_digits: usize,
+    pub maximum_fraction_digits: usize,
+}
+
+impl DigitOptions {
+    /// https://tc39.github.io/ecma402/#sec-setnfdigitoptions
+    fn from_value(
+        allocator: &mut MemoryAllocator,
+        options: Value,
+        maximum_fraction_digits_default: usize,
+    ) -> Result<DigitOptions, String> {
+        let minimum_integer_digits =
+            get_number_option(allocator, options, "minimumIntegerDigits", 1, 21, 1)?;
+        let minimum_fraction_digits =
+            get_number_option(allocator, options, "minimumFractionDigits", 0, 20, 0)?;
+        let maximum_fraction_digits = get_number_option(
+            allocator,
+            options,
+            "maximumFractionDigits",
+            minimum_fraction_digits,
+            20,
+            maximum_fraction_digits_default.max(minimum_fraction_digits),
+        )?;
+        Ok(DigitOptions {
+            minimum_integer_digits,
+            minimum_fraction_digits,
+            maximum_fraction_digits,
+        })
+    }
+
+    fn set_resolved_options(&self, resolved: Value) {
+        resolved.set_property(
+            "minimumIntegerDigits",
+            Value::Number(self.minimum_integer_digits as f64),
+        );
+        resolved.set_property(
+            "minimumFractionDigits",
+            Value::Number(self.minimum_fraction_digits as f64),
+        );
+        resolved.set_property(
+            "maximumFractionDigits",
+            Value::Number(self.maximum_fraction_digits as f64),
+        );
+    }
+
+    /// Round the finite, non-negative ``x`` half away from zero, and return its integer and
+    /// fraction digits.
+    pub fn format_digits(&self, x: f64) -> (String, String) {
+        // The shortest representation that round-trips, without an exponent.
+        let repr = format!("{}", x);
+        let (integer, fraction) = match repr.find('.') {
+            Some(pos) => (&repr[..pos], &repr[pos + 1..]),
+            None => (repr.as_str(), ""),
+        };
+
+        let mut integer_len = integer.len();
+        let mut digits = integer
+            .bytes()
+            .chain(fraction.bytes())
+            .map(|b| b - b'0')
+            .collect::<Vec<u8>>();
+        let len = integer_len + self.maximum_fraction_digits;
+        if digits.len() > len {
+            let round_up = digits[len] >= 5;
+            digits.truncate(len);
+            if round_up {
+                let mut i = len;
+                loop {
+                    if i == 0 {
+                        digits.insert(0, 1);
+                        integer_len += 1;
+                        break;
+                    }
+                    i -= 1;
+                    if digits[i] == 9 {
+                        digits[i] = 0;
+                    } else {
+                        digits[i] += 1;
+                        break;
+                    }
+                }
+            }
+        }
+
+        let to_string = |digits: &[u8]| {
+            digits
+                .iter()
+                .map(|d| (d + b'0') as char)
+                .collect::<String>()
+        };
+        let mut integer = to_string(&digits[..integer_len]);
+        let mut fraction = to_string(&digits[integer_len..]);
+        while fraction.len() > self.minimum_fraction_digits && fraction.ends_with('0') {
+            fraction.pop();
+        }
+        while fraction.len() < self.minimum_fraction_digits {
+            fraction.push('0');
+        }
+        while integer.len() < self.minimum_integer_digits {
+            integer.insert(0, '0');
+        }
+        (integer, fraction)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PluralType {
+    Cardinal,
+    Ordinal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PluralRulesOptions {
+    pub plural_type: PluralType,
+    pub digits: DigitOptions,
+}
+
+impl PluralRulesOptions {
+    /// Read the options object given to Intl.PluralRules, or the resolved options.
+    /// Return the error message if an option has an invalid value.
+    pub fn from_value(
+        allocator: &mut MemoryAllocator,
+        options: Value,
+    ) -> Result<PluralRulesOptions, String> {
+        let plural_type =
+            match get_string_option(options, "type", &["cardinal", "ordinal"], "cardinal")? {
+                "ordinal" => PluralType::Ordinal,
+                _ => PluralType::Cardinal,
+            };
+        Ok(PluralRulesOptions {
+            plural_type,
+            digits: DigitOptions::from_value(allocator, options, 3)?,
+        })
+    }
+
+    /// https://tc39.github.io/ecma402/#sec-intl.pluralrules.prototype.resolvedoptions
+    pub fn to_value(&self, factory: &mut Factory, locale: &LocaleData) -> Value {
+        let locale = factory.string(locale.locale);
+        let plural_type = factory.string(match self.plural_type {
+            PluralType::Cardinal => "cardinal",
+            PluralType::Ordinal => "ordinal",
+        });
+        let resolved = make_normal_object!(factory,
+            locale => true, true, true: locale
+        );
+        resolved.set_property("type", plural_type);
+        self.digits.set_resolved_options(resolved);
+        resolved
+    }
+}
+
+/// https://tc39.github.io/ecma402/#sec-resolveplural
+pub fn select_plural(x: f64, locale: &LocaleData, options: &PluralRulesOptions) -> PluralCategory {
+    if !x.is_finite() {
+        return PluralCategory::Other;
+    }
+    let (integer, fraction) = options.digits.format_digits(x.abs());
+    let operands = PluralOperands::new(integer.as_str(), fraction.as_str());
+    match options.plural_type {
+        PluralType::Cardinal => (locale.cardinal)(&operands),
+        PluralType::Ordinal => (locale.ordinal)(&operands),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NumberStyle {
+    Decimal,
+    Percent,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NumberFormatOptions {
+    pub style: NumberStyle,
+    pub digits: DigitOptions,
+    pub use_grouping: bool,
+}
+
+impl NumberFormatOptions {
+    /// Read the options object given to Intl.NumberFormat, or the resolved options.
+    /// Return the error message if an option has an invalid value.
+    pub fn from_value(
+        allocator: &mut MemoryAllocator,
+        options: Value,
+    ) -> Result<NumberFormatOptions, String> {
+        let style = match get_string_option(options, "style", &["decimal", "percent"], "decimal")? {
+            "percent" => NumberStyle::Percent,
+            _ => NumberStyle::Decimal,
+        };
+        let maximum_fraction_digits_default = match style {
+            NumberStyle::Decimal => 3,
+            NumberStyle::Percent => 0,
+        };
+        let use_grouping = options.get_property("useGrouping");
+        Ok(NumberFormatOptions {
+            style,
+            digits: DigitOptions::from_value(allocator, options, maximum_fraction_digits_default)?,
+            use_grouping: use_grouping.is_undefined() || use_grouping.to_boolean(),
+        })
+    }
+
+    /// https://tc39.github.io/ecma402/#sec-intl.numberformat.prototype.resolvedoptions
+    pub fn to_value(&self, factory: &mut Factory, locale: &LocaleData) -> Value {
+        let locale = factory.string(locale.locale);
+        let numbering_system = factory.string("latn");
+        let style = factory.string(match self.style {
+            NumberStyle::Decimal => "decimal",
+            NumberStyle::Percent => "percent",
+        });
+        let resolved = make_normal_object!(factory,
+            locale          => true, true, true: locale,
+            numberingSystem => true, true, true: numbering_system,
+            style           => true, true, true: style
+        );
+        self.digits.set_resolved_options(resolved);
+        resolved.set_property("useGrouping", Value::bool(self.use_grouping));
+        resolved
+    }
+}
+
+/// https://tc39.github.io/ecma402/#sec-formatnumber
+pub fn format_number(x: f64, locale: &LocaleData, options: &NumberFormatOptions) -> String {
+    if x.is_nan() {
+        return locale.nan.to_string();
+    }
+
+    let x = match options.style {
+        NumberStyle::Decimal => x,
+        NumberStyle::Percent => x * 100.0,
+    };
+    let mut formatted = if x.is_infinite() {
+        locale.infinity.to_string()
+    } else {
+        let (integer, fraction) = options.digits.format_digits(x.abs());
+        let mut formatted = String::new();
+        for (i, digit) in integer.chars().enumerate() {
+            if options.use_grouping && i > 0 && (integer.len() - i) % 3 == 0 {
+                formatted.push_str(locale.group);
+            }
+            formatted.push(digit);
+        }
+        if !fraction.is_empty() {
+            formatted.push_str(locale.decimal);
+            formatted.push_str(fraction.as_str());
+        }
+        formatted
+    };
+
+    if options.style == NumberStyle::Percent {
+        formatted.push_str(locale.percent_sign);
+    }
+    if x.is_sign_negative() {
+        formatted.insert_str(0, locale.minus_sign);
+    }
+    formatted
+}
+
+/// Intl.PluralRules and Intl.NumberFormat objects keep their resolved options in this
+/// non-enumerable property, and read them back on every call.
+const RESOLVED_OPTIONS: &str = "__resolvedOptions";
+
+macro_rules! this_resolved_options {
+    ($vm:expr, $this:expr, $method:expr) => {{
+        if !$this.has_own_property(RESOLVED_OPTIONS) {
+            return Err($vm.throw_type_error(message::incompatible_receiver($method, $this)));
+        }
+        $this.get_property(RESOLVED_OPTIONS)
+    }};
+}
+
+/// Create an Intl object which has the given methods and keeps ``resolved``.
+fn intl_object(
+    vm: &mut VM,
+    resolved: Value,
+    methods: &[(&str, crate::builtin::BuiltinFuncTy)],
+) -> Value {
+    let resolved_options = vm
+        .factory
+        .builtin_function("resolvedOptions", intl_resolved_options);
+    let object = make_normal_object!(vm.factory,
+        resolvedOptions   => true,  false, true:  resolved_options,
+        __resolvedOptions => false, false, false: resolved
+    );
+    for (name, func) in methods {
+        let method = vm.factory.builtin_function(*name, *func);
+        object.set_property(*name, method);
+    }
+    object
+}
+
+/// Return a copy of the resolved options, so that changing it does not affect the object.
+fn intl_resolved_options(vm: &mut VM, _args: &[Value], this: Value) -> VMValueResult {
+    let resolved = this_resolved_options!(vm, this, "resolvedOptions");
+    let copy = make_normal_object!(vm.factory);
+    if let Some(properties) = resolved.get_object_properties() {
+        for name in properties.keys() {
+            copy.set_property(name.as_str(), resolved.get_property(name.as_str()));
+        }
+    }
+    Ok(copy)
+}
+
+/// https://tc39.github.io/ecma402/#sec-intl-pluralrules-constructor
+pub fn plural_rules_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let locale = resolve_locale(args.get(0).map(|x| *x).unwrap_or(Value::undefined()));
+    let options = args.get(1).map(|x| *x).unwrap_or(Value::undefined());
+    let options = PluralRulesOptions::from_value(&mut vm.factory.memory_allocator, options)
+        .map_err(|msg| vm.throw_range_error(msg))?;
+    let resolved = options.to_value(&mut vm.factory, locale);
+    Ok(intl_object(
+        vm,
+        resolved,
+        &[("select", plural_rules_prototype_select)],
+    ))
+}
+
+/// https://tc39.github.io/ecma402/#sec-intl.pluralrules.prototype.select
+pub fn plural_rules_prototype_select(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    let resolved = this_resolved_options!(vm, this, "Intl.PluralRules.prototype.select");
+    let locale = resolve_locale(resolved.get_property("locale"));
+    let options = PluralRulesOptions::from_value(&mut vm.factory.memory_allocator, resolved)
+        .map_err(|msg| vm.throw_range_error(msg))?;
+    let x = args
+        .get(0)
+        .unwrap_or(&Value::undefined())
+        .to_number(&mut vm.factory.memory_allocator);
+    let category = select_plural(x, locale, &options);
+    Ok(vm.factory.string(category.as_str()))
+}
+
+/// https://tc39.github.io/ecma402/#sec-intl-numberformat-constructor
+pub fn number_format_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let locale = resolve_locale(args.get(0).map(|x| *x).unwrap_or(Value::undefined()));
+    let options = args.get(1).map(|x| *x).unwrap_or(Value::undefined());
+    let options = NumberFormatOptions::from_value(&mut vm.factory.memory_allocator, options)
+        .map_err(|msg| vm.throw_range_error(msg))?;
+    let resolved = options.to_value(&mut vm.factory, locale);
+    Ok(intl_object(
+        vm,
+        resolved,
+        &[("format", number_format_prototype_format)],
+    ))
+}
+
+/// https://tc39.github.io/ecma402/#sec-number-format-functions
+pub fn number_format_prototype_format(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    let resolved = this_resolved_options!(vm, this, "Intl.NumberFormat.prototype.format");
+    let locale = resolve_locale(resolved.get_property("locale"));
+    let options = NumberFormatOptions::from_value(&mut vm.factory.memory_allocator, resolved)
+        .map_err(|msg| vm.throw_range_error(msg))?;
+    let x = args
+        .get(0)
+        .unwrap_or(&Value::undefined())
+        .to_number(&mut vm.factory.memory_allocator);
+    let formatted = format_number(x, locale, &options);
+    Ok(vm.factory.string(formatted))
+}
+
+#[test]
+fn collation() {
+    let variant = CollatorOptions::default();
+    let with = |sensitivity| CollatorOptions {
+        sensitivity,
+        numeric: false,
+    };
+
+    assert_eq!(compare("a", "b", &variant), Ordering::Less);
+    assert_eq!(compare("a", "B", &variant), Ordering::Less);
+    assert_eq!(compare("a", "A", &variant), Ordering::Less);
+    assert_eq!(compare("A", "á", &variant), Ordering::Less);
+    assert_eq!(compare("résumé", "resume", &variant), Ordering::Greater);
+    assert_eq!(compare("a b", "ab", &variant), Ordering::Less);
+    assert_eq!(compare("9", "a", &variant), Ordering::Less);
+
+    assert_eq!(compare("a", "Á", &with(Sensitivity::Base)), Ordering::Equal);
+    assert_eq!(
+        compare("a", "A", &with(Sensitivity::Accent)),
+        Ordering::Equal
+    );
+    assert_eq!(
+        compare("a", "á", &with(Sensitivity::Accent)),
+        Ordering::Less
+    );
+    assert_eq!(compare("a", "á", &with(Sensitivity::Case)), Ordering::Equal);
+    assert_eq!(compare("a", "A", &with(Sensitivity::Case)), Ordering::Less);
+
+    let numeric = CollatorOptions {
+        sensitivity: Sensitivity::Variant,
+        numeric: true,
+    };
+    assert_eq!(compare("2", "10", &variant), Ordering::Greater);
+    assert_eq!(compare("2", "10", &numeric), Ordering::Less);
+    assert_eq!(
+        compare("file9.txt", "file010.txt", &numeric),
+        Ordering::Less
+    );
+}
+
+#[test]
+fn plural_rules() {
+    let cardinal = PluralRulesOptions {
+        plural_type: PluralType::Cardinal,
+        digits: DigitOptions {
+            minimum_integer_digits: 1,
+            minimum_fraction_digits: 0,
+            maximum_fraction_digits: 3,
+        },
+    };
+    let ordinal = PluralRulesOptions {
+        plural_type: PluralType::Ordinal,
+        ..cardinal
+    };
+
+    assert_eq!(select_plural(1.0, &EN, &cardinal), PluralCategory::One);
+    assert_eq!(select_plural(-1.0, &EN, &cardinal), PluralCategory::One);
+    assert_eq!(select_plural(0.0, &EN, &cardinal), PluralCategory::Other);
+    assert_eq!(select_plural(1.5, &EN, &cardinal), PluralCategory::Other);
+    // 1.0001 is formatted as "1" with three fraction digits at most.
+    assert_eq!(select_plural(1.0001, &EN, &cardinal), PluralCategory::One);
+    let fixed = PluralRulesOptions {
+        digits: DigitOptions {
+            minimum_fraction_digits: 1,
+            ..cardinal.digits
+        },
+        ..cardinal
+    };
+    assert_eq!(select_plural(1.0, &EN, &fixed), PluralCategory::Other);
+
+    assert_eq!(select_plural(1.0, &EN, &ordinal), PluralCategory::One);
+    assert_eq!(select_plural(22.0, &EN, &ordinal), PluralCategory::Two);
+    assert_eq!(select_plural(103.0, &EN, &ordinal), PluralCategory::Few);
+    assert_eq!(select_plural(11.0, &EN, &ordinal), PluralCategory::Other);
+    assert_eq!(select_plural(112.0, &EN, &ordinal), PluralCategory::Other);
+    assert_eq!(select_plural(4.0, &EN, &ordinal), PluralCategory::Other);
+
+    assert_eq!(select_plural(1.0, &ROOT, &cardinal), PluralCategory::Other);
+    assert_eq!(select_plural(2.0, &ROOT, &ordinal), PluralCategory::Other);
+}
+
+#[test]
+fn number_format() {
+    let decimal = NumberFormatOptions {
+        style: NumberStyle::Decimal,
+        digits: DigitOptions {
+            minimum_integer_digits: 1,
+            minimum_fraction_digits: 0,
+            maximum_fraction_digits: 3,
+        },
+        use_grouping: true,
+    };
+    let format = |x, options: &NumberFormatOptions| format_number(x, &EN, options);
+
+    assert_eq!(format(0.0, &decimal), "0");
+    assert_eq!(format(1234567.891, &decimal), "1,234,567.891");
+    assert_eq!(format(-1234.5, &decimal), "-1,234.5");
+    assert_eq!(format(0.0005, &decimal), "0.001");
+    assert_eq!(format(0.9999, &decimal), "1");
+    assert_eq!(format(999.9999, &decimal), "1,000");
+    assert_eq!(format(1e21, &decimal), "1,000,000,000,000,000,000,000");
+    assert_eq!(format(::std::f64::NAN, &decimal), "NaN");
+    assert_eq!(format(::std::f64::NEG_INFINITY, &decimal), "-∞");
+
+    let no_grouping = NumberFormatOptions {
+        use_grouping: false,
+        ..decimal
+    };
+    assert_eq!(format(1234.5, &no_grouping), "1234.5");
+
+    let fixed = NumberFormatOptions {
+        digits: DigitOptions {
+            minimum_integer_digits: 3,
+            minimum_fraction_digits: 2,
+            maximum_fraction_digits: 2,
+        },
+        ..decimal
+    };
+    assert_eq!(format(5.0, &fixed), "005.00");
+    assert_eq!(format(1.005, &fixed), "001.01");
+
+    let percent = NumberFormatOptions {
+        style: NumberStyle::Percent,
+        digits: DigitOptions {
+            maximum_fraction_digits: 0,
+            ..decimal.digits
+        },
+        ..decimal
+    };
+    assert_eq!(format(0.256, &percent), "26%");
+    assert_eq!(format(-12.5, &percent), "-1,250%");
+}
@@ -0,0 +1,67 @@
+// Array.prototype methods implemented in JavaScript.
+// Holes are not skipped since arrays are always dense in this engine.
+
+// https://tc39.github.io/ecma262/#sec-array.prototype.foreach
+Array.prototype.forEach = function(callback, thisArg) {
+  for (let i = 0; i < this.length; i++) {
+    callback.call(thisArg, this[i], i, this)
+  }
+}
+
+// https://tc39.github.io/ecma262/#sec-array.prototype.filter
+Array.prototype.filter = function(callback, thisArg) {
+  const ret = []
+  for (let i = 0; i < this.length; i++) {
+    const elem = this[i]
+    if (callback.call(thisArg, elem, i, this)) ret.push(elem)
+  }
+  return ret
+}
+
+// https://tc39.github.io/ecma262/#sec-array.prototype.reduce
+// initialValue is a rest parameter to tell whether it is given, since 'arguments' is not supported.
+Array.prototype.reduce = function(callback, ...initialValue) {
+  let i = 0
+  let acc = initialValue[0]
+  if (initialValue.length === 0) {
+    if (this.length === 0) throw new Error('Reduce of empty array with no initial value')
+    acc = this[0]
+    i = 1
+  }
+  for (; i < this.length; i++) {
+    acc = callback.call(undefined, acc, this[i], i, this)
+  }
+  return acc
+}
+
+// https://tc39.github.io/ecma262/#sec-array.prototype.some
+Array.prototype.some = function(callback, thisArg) {
+  for (let i = 0; i < this.length; i++) {
+    if (callback.call(thisArg, this[i], i, this)) return true
+  }
+  return false
+}
+
+// https://tc39.github.io/ecma262/#sec-array.prototype.every
+Array.prototype.every = function(callback, thisArg) {
+  for (let i = 0; i < this.length; i++) {
+    if (!callback.call(thisArg, this[i], i, this)) return false
+  }
+  return true
+}
+
+// https://tc39.github.io/ecma262/#sec-array.prototype.find
+Array.prototype.find = function(predicate, thisArg) {
+  for (let i = 0; i < this.length; i++) {
+    if (predicate.call(thisArg, this[i], i, this)) return this[i]
+  }
+  return undefined
+}
+
+// https://tc39.github.io/ecma262/#sec-array.prototype.findindex
+Array.prototype.findIndex = function(predicate, thisArg) {
+  for (let i = 0; i < this.length; i++) {
+    if (predicate.call(thisArg, this[i], i, this)) return i
+  }
+  return -1
+}
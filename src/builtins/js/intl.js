@@ -0,0 +1,29 @@
+// Intl.Collator implemented on top of String.prototype.localeCompare, which compares natively
+// (see src/builtins/intl.rs). Only the root collation is supported, so locales are ignored.
+
+// https://tc39.github.io/ecma402/#sec-the-intl-collator-constructor
+Intl.Collator = function(locales, options) {
+  if (options === undefined) options = {}
+  const resolved = {
+    locale: 'en-US',
+    usage: 'sort',
+    sensitivity: options.sensitivity === undefined ? 'variant' : '' + options.sensitivity,
+    ignorePunctuation: false,
+    collation: 'default',
+    numeric: !!options.numeric,
+    caseFirst: 'false'
+  }
+  // Reject invalid options here rather than on the first comparison.
+  ''.localeCompare('', locales, resolved)
+
+  // https://tc39.github.io/ecma402/#sec-intl.collator.prototype.compare
+  // compare is bound to the collator, so that it can be passed around as a function.
+  this.compare = function(x, y) {
+    return ('' + x).localeCompare('' + y, locales, resolved)
+  }
+
+  // https://tc39.github.io/ecma402/#sec-intl.collator.prototype.resolvedoptions
+  this.resolvedOptions = function() {
+    return { ...resolved }
+  }
+}
@@ -0,0 +1,288 @@
+//! The JSON object, and ``JsonStream`` for embedders reading JSON values from a stream. Both read
+//! the text with ``JsonReader``, so that deeply nested values do not overflow the native stack.
+//! https://tc39.github.io/ecma262/#sec-json-object
+
+use crate::json::{Event, Json, JsonReader};
+use crate::vm::{
+    error::RuntimeError,
+    internal_methods::internal_methods,
+    jsvalue::value::{ErrorType, Property, PropertyDescriptor, Value},
+    vm::{Factory, VMValueResult, VM},
+};
+
+pub fn json(factory: &mut Factory) -> Value {
+    let parse = factory.builtin_function("parse", json_parse);
+    make_normal_object!(factory,
+        parse => true, false, true: parse
+    )
+}
+
+/// Makes the value of a JSON text from its events.
+#[derive(Default)]
+struct ValueBuilder {
+    /// The open arrays and objects, with the key of the next member of each object.
+    stack: Vec<(Value, Option<String>)>,
+}
+
+impl ValueBuilder {
+    /// Add ``event`` to the value. Returns the value once it is complete.
+    fn add(&mut self, vm: &mut VM, event: Event) -> Result<Option<Value>, RuntimeError> {
+        let val = match event {
+            Event::StartObject => {
+                self.stack.push((make_normal_object!(vm.factory), None));
+                return Ok(None);
+            }
+            Event::StartArray => {
+                self.stack.push((vm.factory.array(vec![]), None));
+                return Ok(None);
+            }
+            Event::Key(key) => {
+                self.stack.last_mut().unwrap().1 = Some(key);
+                return Ok(None);
+            }
+            Event::EndObject | Event::EndArray => self.stack.pop().unwrap().0,
+            Event::Scalar(Json::Null) => Value::null(),
+            Event::Scalar(Json::Bool(b)) => Value::bool(b),
+            Event::Scalar(Json::Number(n)) => Value::Number(n),
+            Event::Scalar(Json::String(s)) => {
+                vm.factory
+                    .memory_allocator
+                    .limits
+                    .check_string_length(s.len())?;
+                vm.factory.string(s)
+            }
+            Event::Scalar(_) => unreachable!(),
+        };
+        let limits = &vm.factory.memory_allocator.limits;
+        match self.stack.last_mut() {
+            None => return Ok(Some(val)),
+            Some((parent, key)) => match key.take() {
+                // CreateDataProperty: an own property, even ``__proto__``, which does not set
+                // the prototype.
+                Some(key) => {
+                    let property = &mut parent.get_object_info().property;
+                    limits.check_property_count(property.len() + 1)?;
+                    property.insert(key, Property::new_data_simple(val));
+                }
+                None => {
+                    let elems = &mut parent.as_array_mut().elems;
+                    limits.check_array_length(elems.len() + 1)?;
+                    elems.push(Property::new_data_simple(val));
+                }
+            },
+        }
+        Ok(None)
+    }
+}
+
+/// Read ``reader`` to the end, and make its value.
+fn read_value(vm: &mut VM, reader: &mut JsonReader) -> VMValueResult {
+    let mut builder = ValueBuilder::default();
+    let mut result = Value::undefined();
+    loop {
+        match reader.next_event() {
+            Ok(Some(event)) => {
+                if let Some(val) = builder.add(vm, event)? {
+                    result = val;
+                }
+            }
+            Ok(None) => return Ok(result),
+            Err(msg) => return Err(vm.throw_error(ErrorType::SyntaxError, msg)),
+        }
+    }
+}
+
+/// https://tc39.github.io/ecma262/#sec-json.parse
+pub fn json_parse(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let text = args.get(0).unwrap_or(&Value::undefined()).to_string();
+    let reviver = *args.get(1).unwrap_or(&Value::undefined());
+    let mut reader = JsonReader::new();
+    reader.feed(&text);
+    reader.finish();
+    let val = read_value(vm, &mut reader)?;
+    if !reviver.is_callable() {
+        return Ok(val);
+    }
+    let root = make_normal_object!(vm.factory);
+    root.get_object_info()
+        .property
+        .insert("".to_string(), Property::new_data_simple(val));
+    internalize(vm, root, reviver)
+}
+
+/// A value whose properties are being revived, with the keys left to revive.
+struct Holder {
+    holder: Value,
+    name: Value,
+    val: Value,
+    keys: Vec<Value>,
+}
+
+/// Call ``reviver`` on the properties of the parsed value in ``root``, innermost first, and
+/// replace them by the results. Not recursive, like the reader.
+/// https://tc39.github.io/ecma262/#sec-internalizejsonproperty
+fn internalize(vm: &mut VM, root: Value, reviver: Value) -> VMValueResult {
+    let name = vm.factory.string("");
+    let val = vm.get_property_by_value(root, name)?;
+    let mut stack = vec![holder(vm, root, name, val)?];
+    while let Some(top) = stack.last_mut() {
+        if let Some(key) = top.keys.pop() {
+            let obj = top.val;
+            let val = vm.get_property_by_value(obj, key)?;
+            stack.push(holder(vm, obj, key, val)?);
+            continue;
+        }
+        let Holder {
+            holder, name, val, ..
+        } = stack.pop().unwrap();
+        let new_val = vm.call_function(reviver, &[name, val], holder)?;
+        if stack.is_empty() {
+            return Ok(new_val);
+        }
+        if new_val.is_undefined() {
+            internal_methods(holder).delete(vm, holder, name)?;
+        } else {
+            let desc = PropertyDescriptor {
+                value: Some(new_val),
+                writable: Some(true),
+                enumerable: Some(true),
+                configurable: Some(true),
+                ..Default::default()
+            };
+            internal_methods(holder).define_own_property(vm, holder, name, desc)?;
+        }
+    }
+    unreachable!()
+}
+
+/// The ``Holder`` of the property ``name`` of ``holder``, whose value is ``val``.
+fn holder(vm: &mut VM, holder: Value, name: Value, val: Value) -> Result<Holder, RuntimeError> {
+    let mut keys = vec![];
+    if val.is_array_object() {
+        for idx in 0..val.as_array_mut().get_length() {
+            keys.push(vm.factory.string(idx.to_string()));
+        }
+    } else if val.is_object() {
+        for key in internal_methods(val).own_property_keys(vm, val)? {
+            if key.is_symbol() {
+                continue;
+            }
+            match internal_methods(val).get_own_property(vm, val, key)? {
+                Some(prop) if prop.is_enumerable() => keys.push(key),
+                _ => {}
+            }
+        }
+    }
+    // Popped from the end.
+    keys.reverse();
+    Ok(Holder {
+        holder,
+        name,
+        val,
+        keys,
+    })
+}
+
+/// Reads JSON values from text given in chunks, e.g. a file or a socket read piece by piece,
+/// without the whole text in memory. The text is a sequence of values separated by whitespace
+/// (e.g. JSON Lines), or with ``JsonStream::elements``, one array whose elements are given one
+/// by one.
+///
+/// A value is made only once its text is complete, since the garbage collector does not see
+/// values kept by the stream between the calls. It does not see the values returned either,
+/// so give them to scripts before running any.
+pub struct JsonStream {
+    reader: JsonReader,
+    /// The events of the value being read.
+    events: Vec<Event>,
+    elements: bool,
+    started: bool,
+}
+
+impl Default for JsonStream {
+    fn default() -> Self {
+        JsonStream::new()
+    }
+}
+
+impl JsonStream {
+    /// A stream of whitespace-separated values.
+    pub fn new() -> JsonStream {
+        JsonStream {
+            reader: JsonReader::new().with_multiple_values(),
+            events: vec![],
+            elements: false,
+            started: false,
+        }
+    }
+
+    /// A stream of the elements of one array.
+    pub fn elements() -> JsonStream {
+        JsonStream {
+            reader: JsonReader::new(),
+            events: vec![],
+            elements: true,
+            started: false,
+        }
+    }
+
+    /// Limit the nesting of arrays and objects to ``max_depth``, including the array of
+    /// ``JsonStream::elements``.
+    pub fn with_max_depth(mut self, max_depth: usize) -> JsonStream {
+        self.reader = self.reader.with_max_depth(max_depth);
+        self
+    }
+
+    /// Read ``chunk``, and return the values it completes. A syntax error is thrown as a
+    /// SyntaxError.
+    pub fn feed(&mut self, vm: &mut VM, chunk: &str) -> Result<Vec<Value>, RuntimeError> {
+        self.reader.feed(chunk);
+        self.read(vm)
+    }
+
+    /// Tell that all the text was given, and return the values it completes, e.g. a number at
+    /// the end of the text.
+    pub fn finish(&mut self, vm: &mut VM) -> Result<Vec<Value>, RuntimeError> {
+        self.reader.finish();
+        self.read(vm)
+    }
+
+    fn read(&mut self, vm: &mut VM) -> Result<Vec<Value>, RuntimeError> {
+        // The depth of the values given.
+        let depth = if self.elements { 1 } else { 0 };
+        let mut values = vec![];
+        loop {
+            let event = match self.reader.next_event() {
+                Ok(Some(event)) => event,
+                Ok(None) => return Ok(values),
+                Err(msg) => return Err(vm.throw_error(ErrorType::SyntaxError, msg)),
+            };
+            if self.elements && !self.started {
+                if event != Event::StartArray {
+                    return Err(vm.throw_error(ErrorType::SyntaxError, "Expected a JSON array"));
+                }
+                self.started = true;
+                continue;
+            }
+            if self.reader.depth() < depth {
+                // The end of the array.
+                continue;
+            }
+            let complete = match event {
+                Event::Scalar(_) | Event::EndObject | Event::EndArray => {
+                    self.reader.depth() == depth
+                }
+                _ => false,
+            };
+            self.events.push(event);
+            if complete {
+                let mut builder = ValueBuilder::default();
+                for event in self.events.drain(..) {
+                    if let Some(val) = builder.add(vm, event)? {
+                        values.push(val);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::vm::{
+    error::message,
+    jsvalue::value::Value,
+    vm::{Factory, VMResult, VMValueResult, VM},
+};
+
+pub fn map(factory: &mut Factory) -> Value {
+    factory.generate_builtin_constructor("Map", map_constructor, factory.object_prototypes.map)
+}
+
+/// https://tc39.github.io/ecma262/#sec-map-iterable
+/// TODO: Only arrays are supported as an iterable.
+pub fn map_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let map = vm.factory.map();
+    let iterable = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    if iterable.is_undefined() || iterable.is_null() {
+        return Ok(map);
+    }
+
+    if !iterable.is_array_object() {
+        return Err(vm.throw_type_error(message::not_iterable(iterable)));
+    }
+
+    let len = iterable.as_array_mut().get_length();
+    for i in 0..len {
+        let entry = vm.get_property_by_value(iterable, Value::Number(i as f64))?;
+        if !entry.is_object() {
+            return Err(vm.throw_type_error(message::not_an_entry_object(entry)));
+        }
+        let key = vm.get_property_by_value(entry, Value::Number(0.0))?;
+        let val = vm.get_property_by_value(entry, Value::Number(1.0))?;
+        map.as_map_mut().set(key, val);
+    }
+
+    Ok(map)
+}
+
+macro_rules! this_map {
+    ($vm:expr, $this:expr, $method:expr) => {{
+        if !$this.is_map_object() {
+            return Err($vm.throw_type_error(message::incompatible_receiver(
+                &format!("Map.prototype.{}", $method),
+                $this,
+            )));
+        }
+        $this.as_map_mut()
+    }};
+}
+
+pub fn map_prototype_get(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    let map = this_map!(vm, this, "get");
+    let key = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    Ok(map.get(key).unwrap_or(Value::undefined()))
+}
+
+pub fn map_prototype_set(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    let map = this_map!(vm, this, "set");
+    let key = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    let val = args.get(1).map(|x| *x).unwrap_or(Value::undefined());
+    map.set(key, val);
+    Ok(this)
+}
+
+pub fn map_prototype_has(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    let map = this_map!(vm, this, "has");
+    let key = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    Ok(Value::bool(map.has(key)))
+}
+
+pub fn map_prototype_delete(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    let map = this_map!(vm, this, "delete");
+    let key = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    Ok(Value::bool(map.delete(key)))
+}
+
+pub fn map_prototype_clear(vm: &mut VM, _args: &[Value], this: Value) -> VMValueResult {
+    let map = this_map!(vm, this, "clear");
+    map.clear();
+    Ok(Value::undefined())
+}
+
+pub fn map_prototype_size(vm: &mut VM, _args: &[Value], this: Value) -> VMValueResult {
+    let map = this_map!(vm, this, "size");
+    Ok(Value::Number(map.size() as f64))
+}
+
+/// https://tc39.github.io/ecma262/#sec-map.prototype.foreach
+pub fn map_prototype_for_each(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    this_map!(vm, this, "forEach");
+    let callback = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    if !callback.is_callable() {
+        return Err(vm.throw_type_error(message::not_a_function(callback)));
+    }
+    let this_arg = args.get(1).map(|x| *x).unwrap_or(Value::undefined());
+
+    for_each_entry(this, |key, val| {
+        vm.call_function(callback, &[val, key, this], this_arg)
+    })?;
+
+    Ok(Value::undefined())
+}
+
+/// Call ``f`` with each entry of the Map or Set ``map``, including the entries added by ``f``.
+pub fn for_each_entry(map: Value, mut f: impl FnMut(Value, Value) -> VMValueResult) -> VMResult {
+    map.as_map_mut().begin_iteration();
+    // The callback may add or delete entries, so the length is checked on every iteration.
+    let mut i = 0;
+    let mut result = Ok(());
+    while i < map.as_map_mut().slots_len() {
+        if let Some((key, val)) = map.as_map_mut().get_entry(i) {
+            if let Err(err) = f(key, val) {
+                result = Err(err);
+                break;
+            }
+        }
+        i += 1;
+    }
+    map.as_map_mut().end_iteration();
+    result
+}
@@ -3,8 +3,20 @@ pub mod console;
 pub mod date;
 pub mod error;
 pub mod function;
+pub mod help;
+#[cfg(feature = "intl")]
+pub mod intl;
+pub mod json;
+pub mod map;
 pub mod math;
+#[cfg(feature = "node-compat")]
+pub mod node_compat;
 pub mod number;
 pub mod object;
+pub mod proxy;
+pub mod rapidus;
+pub mod reflect;
+pub mod self_hosted;
+pub mod set;
 pub mod string;
 pub mod symbol;
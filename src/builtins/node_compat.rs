@@ -0,0 +1,291 @@
+//! A compatibility layer for scripts written for Node.js, enabled by the ``node-compat``
+//! feature and installed by ``VM::node_compat`` (the CLI always installs it).
+//! Only what common scripts and test utilities use is provided, and it is not meant to grow
+//! into the Node.js API:
+//! - ``Buffer.from``, ``Buffer.byteLength`` and ``Buffer.prototype.toString`` with the
+//!   ``utf8``, ``hex`` and ``base64`` encodings. A Buffer is an array of byte numbers whose
+//!   prototype is ``Buffer.prototype``, which inherits from ``Array.prototype``.
+//! - ``process.hrtime`` and ``process.hrtime.bigint``. There is no BigInt, so the latter gives
+//!   the nanoseconds as a Number, which is exact for about 104 days.
+
+use crate::vm::{
+    error::{message, RuntimeError},
+    jsvalue::{object::Property, value::Value},
+    vm::{VMValueResult, VM},
+};
+use std::time::Instant;
+
+thread_local! {
+    /// The origin of ``process.hrtime``, which is arbitrary as in Node.js.
+    static HRTIME_ORIGIN: Instant = Instant::now();
+}
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Put ``Buffer`` in the global object and ``hrtime`` in ``process``.
+pub fn install(vm: &mut VM) {
+    let global_object = vm.global_environment.get_global_object();
+
+    let to_string = vm
+        .factory
+        .builtin_function("toString", buffer_prototype_to_string);
+    let prototype = make_normal_object!(vm.factory,
+        toString => true, false, true: to_string
+    );
+    prototype.get_object_info().prototype = vm.factory.object_prototypes.array;
+    // Buffer.from refers to the prototype even if Buffer.prototype is replaced.
+    vm.factory.memory_allocator.lock_value(prototype);
+
+    let from = vm.native_function("from", move |vm, args, _this| {
+        buffer_from(vm, args, prototype)
+    });
+    let byte_length = vm
+        .factory
+        .builtin_function("byteLength", buffer_byte_length);
+    let buffer = vm
+        .factory
+        .generate_builtin_constructor("Buffer", buffer_constructor, prototype);
+    // Only Buffer.from makes Buffers, as new Buffer() is deprecated in Node.js.
+    buffer.as_function_mut().constructible = false;
+    buffer.set_property("from", from);
+    buffer.set_property("byteLength", byte_length);
+    global_object.set_property("Buffer", buffer);
+
+    let hrtime = vm.factory.builtin_function("hrtime", process_hrtime);
+    let hrtime_bigint = vm.factory.builtin_function("bigint", process_hrtime_bigint);
+    hrtime.set_property("bigint", hrtime_bigint);
+    global_object
+        .get_property("process")
+        .set_property("hrtime", hrtime);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    Utf8,
+    Hex,
+    Base64,
+}
+
+impl Encoding {
+    /// Read the encoding argument. Undefined means UTF-8.
+    fn from_value(vm: &mut VM, val: Option<&Value>) -> Result<Encoding, RuntimeError> {
+        let name = match val {
+            Some(val) if !val.is_undefined() => val.to_string(),
+            _ => return Ok(Encoding::Utf8),
+        };
+        match name.to_lowercase().as_str() {
+            "utf8" | "utf-8" => Ok(Encoding::Utf8),
+            "hex" => Ok(Encoding::Hex),
+            "base64" => Ok(Encoding::Base64),
+            _ => Err(vm.throw_type_error(format!("Unknown encoding: {}", name))),
+        }
+    }
+
+    /// The bytes of ``string`` in the encoding. As in Node.js, decoding stops at the first
+    /// invalid hex digit, and characters other than those of base64 are ignored.
+    fn decode(self, string: &str) -> Vec<u8> {
+        match self {
+            Encoding::Utf8 => string.as_bytes().to_vec(),
+            Encoding::Hex => {
+                let mut bytes = vec![];
+                for pair in string.as_bytes().chunks(2) {
+                    match pair {
+                        [high, low] => match (hex_digit(*high), hex_digit(*low)) {
+                            (Some(high), Some(low)) => bytes.push((high << 4 | low) as u8),
+                            _ => break,
+                        },
+                        _ => break,
+                    }
+                }
+                bytes
+            }
+            Encoding::Base64 => {
+                let mut bytes = vec![];
+                let (mut bits, mut bit_count) = (0u32, 0);
+                for c in string.bytes().take_while(|c| *c != b'=') {
+                    let sextet = match c {
+                        b'-' => 62,
+                        b'_' => 63,
+                        c => match BASE64_CHARS.iter().position(|x| *x == c) {
+                            Some(sextet) => sextet as u32,
+                            None => continue,
+                        },
+                    };
+                    bits = (bits << 6) | sextet;
+                    bit_count += 6;
+                    if bit_count >= 8 {
+                        bit_count -= 8;
+                        bytes.push((bits >> bit_count) as u8);
+                    }
+                }
+                bytes
+            }
+        }
+    }
+
+    fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Encoding::Hex => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+            Encoding::Base64 => {
+                let mut string = String::new();
+                for chunk in bytes.chunks(3) {
+                    let bits = chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0u32, |bits, (i, b)| bits | (*b as u32) << (16 - i * 8));
+                    for i in 0..4 {
+                        if i <= chunk.len() {
+                            let sextet = (bits >> (18 - i * 6)) & 0x3f;
+                            string.push(BASE64_CHARS[sextet as usize] as char);
+                        } else {
+                            string.push('=');
+                        }
+                    }
+                }
+                string
+            }
+        }
+    }
+}
+
+fn hex_digit(c: u8) -> Option<u32> {
+    (c as char).to_digit(16)
+}
+
+/// The element ``idx`` of the array ``array`` converted into a number. NaN for accessors.
+fn element_number(vm: &mut VM, array: Value, idx: usize) -> f64 {
+    match array.as_array_mut().get_element(idx) {
+        Property::Data(data) => data.val.to_number(&mut vm.factory.memory_allocator),
+        Property::Accessor(_) => ::std::f64::NAN,
+    }
+}
+
+/// The bytes of the array ``array``. Elements are converted as by ``Uint8Array``.
+fn array_bytes(vm: &mut VM, array: Value) -> Vec<u8> {
+    let mut bytes = vec![];
+    for idx in 0..array.as_array_mut().get_length() {
+        let number = Value::Number(element_number(vm, array, idx));
+        bytes.push(number.to_int32(&mut vm.factory.memory_allocator) as u8);
+    }
+    bytes
+}
+
+pub fn buffer_constructor(vm: &mut VM, _args: &[Value], _this: Value) -> VMValueResult {
+    Err(vm.throw_type_error("Buffer() is not supported. Use Buffer.from() instead"))
+}
+
+/// ``Buffer.from(string, encoding)`` or ``Buffer.from(array)``.
+fn buffer_from(vm: &mut VM, args: &[Value], prototype: Value) -> VMValueResult {
+    let val = *args.get(0).unwrap_or(&Value::undefined());
+    let bytes = if val.is_string() {
+        let encoding = Encoding::from_value(vm, args.get(1))?;
+        encoding.decode(val.into_str())
+    } else if val.is_array_object() {
+        array_bytes(vm, val)
+    } else {
+        return Err(vm.throw_type_error(format!(
+            "The first argument must be a string or an array, but got {}",
+            val.debug_string(true)
+        )));
+    };
+    vm.factory
+        .memory_allocator
+        .limits
+        .check_array_length(bytes.len())?;
+    let elems = bytes
+        .into_iter()
+        .map(|b| Property::new_data_simple(Value::Number(b as f64)))
+        .collect();
+    let buffer = vm.factory.array(elems);
+    buffer.get_object_info().prototype = prototype;
+    Ok(buffer)
+}
+
+/// ``Buffer.byteLength(string, encoding)``, or the length of the Buffer ``string``.
+pub fn buffer_byte_length(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let val = *args.get(0).unwrap_or(&Value::undefined());
+    if val.is_array_object() {
+        return Ok(Value::Number(val.as_array_mut().get_length() as f64));
+    }
+    if !val.is_string() {
+        return Err(vm.throw_type_error(format!(
+            "The first argument must be a string or a Buffer, but got {}",
+            val.debug_string(true)
+        )));
+    }
+    let encoding = Encoding::from_value(vm, args.get(1))?;
+    Ok(Value::Number(encoding.decode(val.into_str()).len() as f64))
+}
+
+/// ``Buffer.prototype.toString(encoding)``. Invalid UTF-8 is replaced by U+FFFD.
+pub fn buffer_prototype_to_string(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    if !this.is_array_object() {
+        return Err(vm.throw_type_error(message::incompatible_receiver(
+            "Buffer.prototype.toString",
+            this,
+        )));
+    }
+    let encoding = Encoding::from_value(vm, args.get(0))?;
+    let string = encoding.encode(&array_bytes(vm, this));
+    // Strings are NUL-terminated for now.
+    if string.contains('\0') {
+        return Err(vm.throw_range_error("Cannot make a string containing NUL"));
+    }
+    Ok(vm.factory.string(string))
+}
+
+/// The nanoseconds since ``HRTIME_ORIGIN``.
+fn hrtime_nanos() -> u128 {
+    HRTIME_ORIGIN.with(|origin| origin.elapsed().as_nanos())
+}
+
+/// ``process.hrtime(time)``: ``[seconds, nanoseconds]``, relative to ``time`` if it is given.
+pub fn process_hrtime(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let mut nanos = hrtime_nanos() as f64;
+    match args.get(0) {
+        Some(prev) if prev.is_array_object() => {
+            nanos -= element_number(vm, *prev, 0) * 1e9 + element_number(vm, *prev, 1);
+        }
+        Some(prev) if !prev.is_undefined() => {
+            return Err(vm.throw_type_error(format!(
+                "The time must be an array, but got {}",
+                prev.debug_string(true)
+            )));
+        }
+        _ => {}
+    }
+    let seconds = (nanos / 1e9).floor();
+    let elems = vec![
+        Property::new_data_simple(Value::Number(seconds)),
+        Property::new_data_simple(Value::Number(nanos - seconds * 1e9)),
+    ];
+    Ok(vm.factory.array(elems))
+}
+
+/// ``process.hrtime.bigint()``: the nanoseconds as a Number.
+pub fn process_hrtime_bigint(_vm: &mut VM, _args: &[Value], _this: Value) -> VMValueResult {
+    Ok(Value::Number(hrtime_nanos() as f64))
+}
+
+#[test]
+fn encodings() {
+    for (bytes, hex, base64) in &[
+        ("", "", ""),
+        ("f", "66", "Zg=="),
+        ("fo", "666f", "Zm8="),
+        ("foo", "666f6f", "Zm9v"),
+        ("foob", "666f6f62", "Zm9vYg=="),
+    ] {
+        let bytes = bytes.as_bytes();
+        assert_eq!(Encoding::Hex.encode(bytes), *hex);
+        assert_eq!(Encoding::Base64.encode(bytes), *base64);
+        assert_eq!(Encoding::Hex.decode(hex), bytes);
+        assert_eq!(Encoding::Base64.decode(base64), bytes);
+    }
+    assert_eq!(Encoding::Hex.decode("41zz42"), b"A");
+    assert_eq!(Encoding::Hex.decode("414"), b"A");
+    assert_eq!(Encoding::Base64.decode("Zm9v\nYmFy"), b"foobar");
+    assert_eq!(Encoding::Base64.decode("-_8"), vec![0xfb, 0xff]);
+    assert_eq!(Encoding::Utf8.encode(&[0x61, 0xff]), "a\u{fffd}");
+}
@@ -1,76 +1,216 @@
-// use vm::{error::RuntimeError, value::*, vm::VM};
-//
-// thread_local!(
-//     pub static NUMBER_PROTOTYPE: Value =
-//         { make_object!(toString: Value::default_builtin_function(number_prototype_tostring)) };
-// );
-//
-// pub fn number_prototype_tostring(
-//     vm: &mut VM,
-//     args: &Vec<Value>,
-//     callobj: CallObjectRef,
-// ) -> Result<(), RuntimeError> {
-//     let number = if let Value::Number(num) = *callobj.this {
-//         num
-//     } else {
-//         vm.set_return_value(Value::Undefined);
-//         return Ok(());
-//     };
-//
-//     let base = match args.get(0) {
-//         Some(val) => {
-//             let num = val.to_number();
-//             if num - num.floor() == 0.0 && 2.0 <= num && num <= 36.0 {
-//                 num as usize
-//             } else {
-//                 10
-//             }
-//         }
-//         _ => 10,
-//     };
-//
-//     vm.set_return_value(Value::string(f64_to_string(number, base)));
-//
-//     Ok(())
-// }
-//
-// // TODO: Maybe, this function had better be somewhere else. (like ./src/util.rs)
-// fn f64_to_string(f: f64, radix: usize) -> String {
-//     if f.is_nan() {
-//         return "NaN".to_string();
-//     }
-//     if f.is_infinite() {
-//         return "Infinity".to_string();
-//     }
-//
-//     let chars = "0123456789abcdefghijklmnopqrstuvwxyz";
-//     let negative = f < 0.0;
-//     let mut integer = f.abs() as usize;
-//     let mut fraction = f.abs() - integer as f64;
-//     let mut s = "".to_string();
-//     let max_digits = 14;
-//
-//     while integer > 0 {
-//         s.push(chars[(integer % radix)..].chars().next().unwrap());
-//         integer /= radix;
-//     }
-//
-//     s = s.chars().rev().collect();
-//     if fraction != 0.0 {
-//         s.push('.');
-//     }
-//
-//     let mut count = 0;
-//     while fraction > 0.0 && count < max_digits {
-//         fraction *= radix as f64;
-//         s.push(chars[fraction as usize..].chars().next().unwrap());
-//         fraction -= fraction.floor();
-//         count += 1;
-//     }
-//
-//     if negative {
-//         s.insert(0, '-')
-//     }
-//
-//     s
-// }
+use crate::vm::{
+    error::message,
+    jsvalue::value::{number_to_str, ErrorType, Value},
+    operations,
+    vm::{Factory, VMValueResult, VM},
+};
+
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+pub fn number(factory: &mut Factory) -> Value {
+    let obj = factory.generate_builtin_constructor(
+        "Number",
+        number_constructor,
+        factory.object_prototypes.number,
+    );
+    // Number objects are only made for ``this`` of non-strict functions, so Number can only
+    // convert values.
+    obj.as_function_mut().constructible = false;
+    obj
+}
+
+/// https://tc39.github.io/ecma262/#sec-number-constructor-number-value
+pub fn number_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    match args.get(0) {
+        None => Ok(Value::Number(0.0)),
+        Some(value) => Ok(Value::Number(operations::to_number(vm, *value)?)),
+    }
+}
+
+/// https://tc39.github.io/ecma262/#sec-number.prototype.tostring
+pub fn number_prototype_to_string(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    let n = match this.unwrap_primitive() {
+        Value::Number(n) => n,
+        _ => {
+            return Err(vm.throw_type_error(message::incompatible_receiver(
+                "Number.prototype.toString",
+                this,
+            )))
+        }
+    };
+    let radix = match args.get(0) {
+        None => 10.0,
+        Some(radix) if radix.is_undefined() => 10.0,
+        Some(radix) => radix.to_number(&mut vm.factory.memory_allocator).trunc(),
+    };
+    if !(radix >= 2.0 && radix <= 36.0) {
+        return Err(vm.throw_error(
+            ErrorType::RangeError,
+            "toString() radix must be between 2 and 36",
+        ));
+    }
+    let s = if radix == 10.0 {
+        number_to_str(n).into_owned()
+    } else {
+        to_radix_string(n, radix as u32)
+    };
+    Ok(vm.factory.string(s))
+}
+
+/// https://tc39.github.io/ecma262/#sec-number.prototype.valueof
+pub fn number_prototype_value_of(vm: &mut VM, _args: &[Value], this: Value) -> VMValueResult {
+    match this.unwrap_primitive() {
+        Value::Number(n) => Ok(Value::Number(n)),
+        _ => Err(vm.throw_type_error(message::incompatible_receiver(
+            "Number.prototype.valueOf",
+            this,
+        ))),
+    }
+}
+
+/// ``n`` in ``radix``, with as many fraction digits as tell ``n`` from the adjacent doubles,
+/// the last one rounded to even. Not locale-dependent, and the digits are written to a buffer
+/// on the stack, so that the result is the only allocation.
+pub fn to_radix_string(n: f64, radix: u32) -> String {
+    if n.is_nan() {
+        return "NaN".to_string();
+    }
+    if n.is_infinite() {
+        return if n > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    // The integer digits are written to the left from the middle, and the fraction digits to
+    // the right. The integer part of a double has at most 1024 binary digits, and the fraction
+    // part at most 1074.
+    let mut buf = [0u8; 2200];
+    let middle = 1100;
+    let mut start = middle;
+    let mut end = middle;
+    let negative = n < 0.0;
+    let value = n.abs();
+    let radix_f = f64::from(radix);
+
+    let mut integer = value.floor();
+    // Integers below 2^53 are exact in u64, which is faster to divide.
+    if value == integer && integer < 9007199254740992.0 {
+        let radix = u64::from(radix);
+        let mut integer = integer as u64;
+        loop {
+            start -= 1;
+            buf[start] = DIGITS[(integer % radix) as usize];
+            integer /= radix;
+            if integer == 0 {
+                break;
+            }
+        }
+        return finish(&mut buf, start, end, negative);
+    }
+
+    let mut fraction = value - integer;
+    // Half the distance to the next double, under which the digits would be noise.
+    let mut delta = 0.5 * (next_double(value) - value);
+    if delta <= 0.0 {
+        delta = next_double(0.0);
+    }
+    if fraction >= delta {
+        buf[end] = b'.';
+        end += 1;
+        loop {
+            fraction *= radix_f;
+            delta *= radix_f;
+            let digit = fraction as u32;
+            buf[end] = DIGITS[digit as usize];
+            end += 1;
+            fraction -= f64::from(digit);
+            if fraction > 0.5 || (fraction == 0.5 && digit & 1 == 1) {
+                if fraction + delta > 1.0 {
+                    // Round up, carrying over to the digits written before.
+                    loop {
+                        end -= 1;
+                        if end == middle {
+                            // The '.' is dropped.
+                            integer += 1.0;
+                            break;
+                        }
+                        let digit = digit_value(buf[end]);
+                        if digit + 1 < radix {
+                            buf[end] = DIGITS[digit as usize + 1];
+                            end += 1;
+                            break;
+                        }
+                    }
+                    break;
+                }
+            }
+            if fraction < delta {
+                break;
+            }
+        }
+    }
+
+    // The digits of an integer above 2^53 below its precision are zeros.
+    while integer / radix_f >= 9007199254740992.0 {
+        integer /= radix_f;
+        start -= 1;
+        buf[start] = b'0';
+    }
+    loop {
+        let remainder = integer % radix_f;
+        start -= 1;
+        buf[start] = DIGITS[remainder as usize];
+        integer = (integer - remainder) / radix_f;
+        if integer <= 0.0 {
+            break;
+        }
+    }
+    finish(&mut buf, start, end, negative)
+}
+
+fn finish(buf: &mut [u8], mut start: usize, end: usize, negative: bool) -> String {
+    if negative {
+        start -= 1;
+        buf[start] = b'-';
+    }
+    String::from_utf8(buf[start..end].to_vec()).unwrap()
+}
+
+/// The smallest double greater than the finite non-negative ``n``.
+fn next_double(n: f64) -> f64 {
+    f64::from_bits(n.to_bits() + 1)
+}
+
+fn digit_value(c: u8) -> u32 {
+    match c {
+        b'0'..=b'9' => u32::from(c - b'0'),
+        _ => u32::from(c - b'a') + 10,
+    }
+}
+
+#[test]
+fn radix_string() {
+    assert_eq!(to_radix_string(255.0, 16), "ff");
+    assert_eq!(to_radix_string(-255.0, 2), "-11111111");
+    assert_eq!(to_radix_string(0.0, 2), "0");
+    assert_eq!(to_radix_string(-0.0, 36), "0");
+    assert_eq!(to_radix_string(35.0, 36), "z");
+    assert_eq!(to_radix_string(0.5, 2), "0.1");
+    assert_eq!(to_radix_string(-2.75, 2), "-10.11");
+    assert_eq!(
+        to_radix_string(0.1, 2),
+        "0.0001100110011001100110011001100110011001100110011001101"
+    );
+    assert_eq!(
+        to_radix_string(0.1, 3),
+        "0.0022002200220022002200220022002201"
+    );
+    assert_eq!(to_radix_string(3.14159, 16), "3.243f3e0370cdc");
+    assert_eq!(to_radix_string(1.0 / 3.0, 3), "0.1");
+    assert_eq!(
+        to_radix_string(2f64.powi(60), 2),
+        format!("1{}", "0".repeat(60))
+    );
+    assert_eq!(to_radix_string(1e21, 36), "5v1j4f4ds7c000");
+    assert_eq!(to_radix_string(::std::f64::MAX, 2).len(), 1024);
+    assert_eq!(to_radix_string(5e-324, 2).len(), 1076);
+    assert_eq!(to_radix_string(::std::f64::NAN, 2), "NaN");
+    assert_eq!(to_radix_string(-::std::f64::INFINITY, 2), "-Infinity");
+}
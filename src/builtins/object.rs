@@ -1,27 +1,53 @@
 use crate::vm::{
+    error::{message, RuntimeError},
+    internal_methods::internal_methods,
     jsvalue::value::*,
     vm::{Factory, VMValueResult, VM},
 };
-use rustc_hash::FxHashMap;
 
 pub fn object(factory: &mut Factory) -> Value {
-    factory.generate_builtin_constructor(
+    let obj = factory.generate_builtin_constructor(
         "Object",
         object_constructor,
         factory.object_prototypes.object,
-    )
+    );
+
+    // Object.defineProperty
+    obj.set_property(
+        "defineProperty",
+        factory.builtin_function("defineProperty", object_define_property),
+    );
+    // Object.getOwnPropertyDescriptor
+    obj.set_property(
+        "getOwnPropertyDescriptor",
+        factory.builtin_function(
+            "getOwnPropertyDescriptor",
+            object_get_own_property_descriptor,
+        ),
+    );
+    // Object.getOwnPropertyDescriptors
+    obj.set_property(
+        "getOwnPropertyDescriptors",
+        factory.builtin_function(
+            "getOwnPropertyDescriptors",
+            object_get_own_property_descriptors,
+        ),
+    );
+    // Object.keys
+    obj.set_property("keys", factory.builtin_function("keys", object_keys));
+    obj
 }
 
 pub fn object_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
     if args.len() == 0 {
-        let empty_obj = vm.factory.object(FxHashMap::default());
+        let empty_obj = vm.factory.object(PropertyMap::default());
         vm.current_context.stack.push(empty_obj.into());
         return Ok(empty_obj);
     }
 
     match &args[0] {
         Value::Other(NULL) | Value::Other(UNDEFINED) => {
-            let empty_obj = vm.factory.object(FxHashMap::default());
+            let empty_obj = vm.factory.object(PropertyMap::default());
             Ok(empty_obj)
         }
         Value::Other(EMPTY) => unreachable!(),
@@ -31,3 +57,162 @@ pub fn object_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueR
         }
     }
 }
+
+/// https://tc39.github.io/ecma262/#sec-object.defineproperty
+pub fn object_define_property(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let obj = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    let key = args.get(1).map(|x| *x).unwrap_or(Value::undefined());
+    let attributes = args.get(2).map(|x| *x).unwrap_or(Value::undefined());
+    if !obj.is_object() {
+        return Err(vm.throw_type_error(message::called_on_non_object("Object.defineProperty")));
+    }
+    let desc = to_property_descriptor(vm, attributes)?;
+    let defined = internal_methods(obj).define_own_property(vm, obj, key, desc)?;
+    if !defined {
+        return Err(vm.throw_type_error(message::cannot_redefine_property(key)));
+    }
+    Ok(obj)
+}
+
+/// https://tc39.github.io/ecma262/#sec-object.getownpropertydescriptor
+pub fn object_get_own_property_descriptor(
+    vm: &mut VM,
+    args: &[Value],
+    _this: Value,
+) -> VMValueResult {
+    let obj = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    let key = args.get(1).map(|x| *x).unwrap_or(Value::undefined());
+    let prop = match own_object(vm, obj)? {
+        Some(obj) => internal_methods(obj).get_own_property(vm, obj, key)?,
+        None => None,
+    };
+    match prop {
+        Some(prop) => Ok(from_property(&mut vm.factory, prop)),
+        None => Ok(Value::undefined()),
+    }
+}
+
+/// Properties with symbol keys are not included.
+/// https://tc39.github.io/ecma262/#sec-object.getownpropertydescriptors
+pub fn object_get_own_property_descriptors(
+    vm: &mut VM,
+    args: &[Value],
+    _this: Value,
+) -> VMValueResult {
+    let obj = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    let mut descriptors = PropertyMap::default();
+    if let Some(obj) = own_object(vm, obj)? {
+        for key in internal_methods(obj).own_property_keys(vm, obj)? {
+            if key.is_symbol() {
+                continue;
+            }
+            if let Some(prop) = internal_methods(obj).get_own_property(vm, obj, key)? {
+                let desc = from_property(&mut vm.factory, prop);
+                descriptors.insert(key.to_string(), Property::new_data_simple(desc));
+            }
+        }
+    }
+    Ok(vm.factory.object(descriptors))
+}
+
+/// The enumerable own property keys which are strings, in the order of
+/// ``InternalMethods::own_property_keys``.
+/// https://tc39.github.io/ecma262/#sec-object.keys
+pub fn object_keys(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let obj = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    let mut keys = vec![];
+    if let Some(obj) = own_object(vm, obj)? {
+        for key in internal_methods(obj).own_property_keys(vm, obj)? {
+            if key.is_symbol() {
+                continue;
+            }
+            match internal_methods(obj).get_own_property(vm, obj, key)? {
+                Some(prop) if prop.is_enumerable() => keys.push(Property::new_data_simple(key)),
+                _ => {}
+            }
+        }
+    }
+    Ok(vm.factory.array(keys))
+}
+
+/// Get the object whose own properties the functions above look at. Primitives other than
+/// null and undefined have no own properties here.
+fn own_object(vm: &mut VM, val: Value) -> Result<Option<Value>, RuntimeError> {
+    match val {
+        Value::Object(_) => Ok(Some(val)),
+        Value::Other(_) => Err(vm.throw_type_error(message::cannot_convert_to_object(val))),
+        _ => Ok(None),
+    }
+}
+
+/// https://tc39.github.io/ecma262/#sec-topropertydescriptor
+pub fn to_property_descriptor(
+    vm: &mut VM,
+    attributes: Value,
+) -> Result<PropertyDescriptor, RuntimeError> {
+    if !attributes.is_object() {
+        return Err(vm.throw_type_error(message::property_description_must_be_object(attributes)));
+    }
+    let desc = PropertyDescriptor {
+        enumerable: descriptor_field(vm, attributes, "enumerable")?.map(|x| x.to_boolean()),
+        configurable: descriptor_field(vm, attributes, "configurable")?.map(|x| x.to_boolean()),
+        value: descriptor_field(vm, attributes, "value")?,
+        writable: descriptor_field(vm, attributes, "writable")?.map(|x| x.to_boolean()),
+        get: descriptor_field(vm, attributes, "get")?,
+        set: descriptor_field(vm, attributes, "set")?,
+    };
+    for (field, accessor) in &[("get", desc.get), ("set", desc.set)] {
+        if let Some(accessor) = accessor {
+            if !accessor.is_undefined() && !accessor.is_callable() {
+                return Err(
+                    vm.throw_type_error(message::accessor_must_be_function(field, *accessor))
+                );
+            }
+        }
+    }
+    if desc.is_accessor_descriptor() && desc.is_data_descriptor() {
+        return Err(vm.throw_type_error(message::invalid_property_descriptor()));
+    }
+    Ok(desc)
+}
+
+/// Get the field ``name`` of the attributes object, own or inherited. None if absent.
+fn descriptor_field(
+    vm: &mut VM,
+    attributes: Value,
+    name: &str,
+) -> Result<Option<Value>, RuntimeError> {
+    let key = vm.factory.string(name);
+    if !vm.has_property(attributes, key)? {
+        return Ok(None);
+    }
+    vm.get_property_by_value(attributes, key).map(Some)
+}
+
+/// https://tc39.github.io/ecma262/#sec-frompropertydescriptor
+fn from_property(factory: &mut Factory, prop: Property) -> Value {
+    match prop {
+        Property::Data(DataProperty {
+            val,
+            writable,
+            enumerable,
+            configurable,
+        }) => make_normal_object!(factory,
+            value        => true, true, true: val,
+            writable     => true, true, true: Value::bool(writable),
+            enumerable   => true, true, true: Value::bool(enumerable),
+            configurable => true, true, true: Value::bool(configurable)
+        ),
+        Property::Accessor(AccessorProperty {
+            get,
+            set,
+            enumerable,
+            configurable,
+        }) => make_normal_object!(factory,
+            get          => true, true, true: get,
+            set          => true, true, true: set,
+            enumerable   => true, true, true: Value::bool(enumerable),
+            configurable => true, true, true: Value::bool(configurable)
+        ),
+    }
+}
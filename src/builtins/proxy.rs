@@ -0,0 +1,200 @@
+//! Proxy objects, whose internal methods call the traps of their handlers. The traps ``get``,
+//! ``set``, ``has``, ``deleteProperty``, ``ownKeys``, ``apply`` and ``construct`` are
+//! supported. Other internal methods, and those whose traps the handler does not have, are
+//! forwarded to the target. The invariants of the results of traps are not checked.
+//! https://tc39.github.io/ecma262/#sec-proxy-object-internal-methods-and-internal-slots
+
+use crate::vm::{
+    error::{message, RuntimeError},
+    internal_methods::{internal_methods, InternalMethods},
+    jsvalue::value::{Property, PropertyDescriptor, SetResult, Value},
+    vm::{Factory, VMValueResult, VM},
+};
+
+pub fn proxy(factory: &mut Factory) -> Value {
+    factory.builtin_constructor("Proxy", proxy_constructor)
+}
+
+/// https://tc39.github.io/ecma262/#sec-proxy-target-handler
+pub fn proxy_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let target = *args.get(0).unwrap_or(&Value::undefined());
+    let handler = *args.get(1).unwrap_or(&Value::undefined());
+    if !target.is_object() || !handler.is_object() {
+        return Err(vm.throw_type_error(message::cannot_create_proxy()));
+    }
+    Ok(vm.factory.proxy(target, handler))
+}
+
+/// The internal methods of proxies.
+pub struct ProxyObject;
+
+/// Get the target of the proxy ``obj``, and the trap ``name`` of its handler, which is None
+/// if the handler does not have it.
+fn trap(vm: &mut VM, obj: Value, name: &str) -> Result<(Value, Option<Value>), RuntimeError> {
+    let (target, handler) = {
+        let info = obj.as_proxy();
+        (info.target, info.handler)
+    };
+    let key = vm.factory.string(name);
+    let trap = vm.get_property_by_value(handler, key)?;
+    if trap.is_undefined() || trap.is_null() {
+        return Ok((target, None));
+    }
+    if !trap.is_callable() {
+        return Err(vm.throw_type_error(message::proxy_trap_not_function(name, trap)));
+    }
+    Ok((target, Some(trap)))
+}
+
+/// Call ``trap`` with ``args``, with the handler as this.
+fn call_trap(vm: &mut VM, obj: Value, trap: Value, args: &[Value]) -> VMValueResult {
+    let handler = obj.as_proxy().handler;
+    vm.call_function(trap, args, handler)
+}
+
+/// The property key given to traps, which is a string or a symbol.
+fn property_key(vm: &mut VM, key: Value) -> Value {
+    if key.is_string() || key.is_symbol() {
+        key
+    } else {
+        vm.factory.string(key.to_string())
+    }
+}
+
+fn array(vm: &mut VM, elems: &[Value]) -> Value {
+    let elems = elems
+        .iter()
+        .map(|elem| Property::new_data_simple(*elem))
+        .collect();
+    vm.factory.array(elems)
+}
+
+impl InternalMethods for ProxyObject {
+    fn get_prototype_of(&self, vm: &mut VM, obj: Value) -> Result<Value, RuntimeError> {
+        let target = obj.as_proxy().target;
+        internal_methods(target).get_prototype_of(vm, target)
+    }
+
+    fn get_own_property(
+        &self,
+        vm: &mut VM,
+        obj: Value,
+        key: Value,
+    ) -> Result<Option<Property>, RuntimeError> {
+        let target = obj.as_proxy().target;
+        internal_methods(target).get_own_property(vm, target, key)
+    }
+
+    fn define_own_property(
+        &self,
+        vm: &mut VM,
+        obj: Value,
+        key: Value,
+        desc: PropertyDescriptor,
+    ) -> Result<bool, RuntimeError> {
+        let target = obj.as_proxy().target;
+        internal_methods(target).define_own_property(vm, target, key, desc)
+    }
+
+    fn has_property(&self, vm: &mut VM, obj: Value, key: Value) -> Result<bool, RuntimeError> {
+        match trap(vm, obj, "has")? {
+            (target, Some(trap)) => {
+                let key = property_key(vm, key);
+                Ok(call_trap(vm, obj, trap, &[target, key])?.to_boolean())
+            }
+            (target, None) => internal_methods(target).has_property(vm, target, key),
+        }
+    }
+
+    fn get(&self, vm: &mut VM, obj: Value, key: Value) -> Result<Property, RuntimeError> {
+        match trap(vm, obj, "get")? {
+            (target, Some(trap)) => {
+                let key = property_key(vm, key);
+                let val = call_trap(vm, obj, trap, &[target, key, obj])?;
+                Ok(Property::new_data_simple(val))
+            }
+            (target, None) => internal_methods(target).get(vm, target, key),
+        }
+    }
+
+    fn set(
+        &self,
+        vm: &mut VM,
+        obj: Value,
+        key: Value,
+        val: Value,
+    ) -> Result<SetResult, RuntimeError> {
+        match trap(vm, obj, "set")? {
+            (target, Some(trap)) => {
+                let key = property_key(vm, key);
+                let done = call_trap(vm, obj, trap, &[target, key, val, obj])?.to_boolean();
+                Ok(if done {
+                    SetResult::Done
+                } else {
+                    SetResult::Rejected
+                })
+            }
+            (target, None) => internal_methods(target).set(vm, target, key, val),
+        }
+    }
+
+    fn delete(&self, vm: &mut VM, obj: Value, key: Value) -> Result<bool, RuntimeError> {
+        match trap(vm, obj, "deleteProperty")? {
+            (target, Some(trap)) => {
+                let key = property_key(vm, key);
+                Ok(call_trap(vm, obj, trap, &[target, key])?.to_boolean())
+            }
+            (target, None) => internal_methods(target).delete(vm, target, key),
+        }
+    }
+
+    /// The ``ownKeys`` trap returns an array of strings and symbols.
+    fn own_property_keys(&self, vm: &mut VM, obj: Value) -> Result<Vec<Value>, RuntimeError> {
+        let (target, trap) = match trap(vm, obj, "ownKeys")? {
+            (target, Some(trap)) => (target, trap),
+            (target, None) => return internal_methods(target).own_property_keys(vm, target),
+        };
+        let keys = call_trap(vm, obj, trap, &[target])?;
+        if !keys.is_array_object() {
+            return Err(
+                vm.throw_type_error(message::proxy_trap_returned_non_object("ownKeys", keys))
+            );
+        }
+        let mut list = vec![];
+        for idx in 0..keys.as_array_mut().get_length() {
+            let key = vm.get_property_by_value(keys, Value::Number(idx as f64))?;
+            if !key.is_string() && !key.is_symbol() {
+                return Err(vm.throw_type_error(message::proxy_own_keys_invalid_key(key)));
+            }
+            list.push(key);
+        }
+        Ok(list)
+    }
+
+    fn call(&self, vm: &mut VM, obj: Value, args: &[Value], this: Value) -> VMValueResult {
+        match trap(vm, obj, "apply")? {
+            (target, Some(trap)) => {
+                let args = array(vm, args);
+                call_trap(vm, obj, trap, &[target, this, args])
+            }
+            (target, None) => internal_methods(target).call(vm, target, args, this),
+        }
+    }
+
+    fn construct(&self, vm: &mut VM, obj: Value, args: &[Value]) -> VMValueResult {
+        match trap(vm, obj, "construct")? {
+            (target, Some(trap)) => {
+                let args = array(vm, args);
+                let new_obj = call_trap(vm, obj, trap, &[target, args, obj])?;
+                if !new_obj.is_object() {
+                    return Err(vm.throw_type_error(message::proxy_trap_returned_non_object(
+                        "construct",
+                        new_obj,
+                    )));
+                }
+                Ok(new_obj)
+            }
+            (target, None) => internal_methods(target).construct(vm, target, args),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use crate::build_info::BuildInfo;
+use crate::builtins::help::rapidus_inspect;
+use crate::vm::{jsvalue::object::Property, jsvalue::value::Value, vm::Factory};
+
+/// The `Rapidus` object, which exposes information about the engine itself.
+pub fn rapidus(factory: &mut Factory) -> Value {
+    let info = BuildInfo::new();
+    let version = factory.string(info.version);
+    let features = info
+        .features
+        .iter()
+        .map(|f| Property::new_data_simple(factory.string(*f)))
+        .collect::<Vec<Property>>();
+    let features = factory.array(features);
+    let gc = factory.string(info.gc);
+    let inspect = factory.builtin_function("inspect", rapidus_inspect);
+    make_normal_object!(factory,
+        version     => false, false, false: version,
+        features    => false, false, false: features,
+        gc          => false, false, false: gc,
+        pointerSize => false, false, false: Value::Number(info.pointer_size as f64),
+        inspect     => true,  false, true:  inspect
+    )
+}
+
+/// A minimal `process` object. Only `process.versions` is provided for now.
+pub fn process(factory: &mut Factory) -> Value {
+    let version = factory.string(BuildInfo::new().version);
+    let versions = make_normal_object!(factory,
+        rapidus => false, true, false: version
+    );
+    make_normal_object!(factory,
+        versions => true, false, true: versions
+    )
+}
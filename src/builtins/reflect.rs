@@ -0,0 +1,149 @@
+//! The Reflect object, whose functions perform the internal methods of objects, including
+//! proxies, as they are.
+//! https://tc39.github.io/ecma262/#sec-reflect-object
+
+use crate::builtins::object::to_property_descriptor;
+use crate::vm::{
+    error::{message, RuntimeError},
+    internal_methods::internal_methods,
+    jsvalue::value::{AccessorProperty, DataProperty, Property, SetResult, Value},
+    vm::{Factory, VMValueResult, VM},
+};
+
+pub fn reflect(factory: &mut Factory) -> Value {
+    let get = factory.builtin_function("get", reflect_get);
+    let set = factory.builtin_function("set", reflect_set);
+    let has = factory.builtin_function("has", reflect_has);
+    let own_keys = factory.builtin_function("ownKeys", reflect_own_keys);
+    let define_property = factory.builtin_function("defineProperty", reflect_define_property);
+    let get_prototype_of = factory.builtin_function("getPrototypeOf", reflect_get_prototype_of);
+    let apply = factory.builtin_function("apply", reflect_apply);
+    let construct = factory.builtin_function("construct", reflect_construct);
+
+    make_normal_object!(factory,
+        get            => true, false, true: get,
+        set            => true, false, true: set,
+        has            => true, false, true: has,
+        ownKeys        => true, false, true: own_keys,
+        defineProperty => true, false, true: define_property,
+        getPrototypeOf => true, false, true: get_prototype_of,
+        apply          => true, false, true: apply,
+        construct      => true, false, true: construct
+    )
+}
+
+/// The argument ``idx``, or undefined.
+fn arg(args: &[Value], idx: usize) -> Value {
+    *args.get(idx).unwrap_or(&Value::undefined())
+}
+
+/// The first argument, which must be an object.
+fn target(vm: &mut VM, args: &[Value], method: &str) -> Result<Value, RuntimeError> {
+    let target = arg(args, 0);
+    if !target.is_object() {
+        return Err(vm.throw_type_error(message::called_on_non_object(method)));
+    }
+    Ok(target)
+}
+
+/// The elements of the array ``list``.
+/// https://tc39.github.io/ecma262/#sec-createlistfromarraylike
+fn list_from_array(vm: &mut VM, list: Value) -> Result<Vec<Value>, RuntimeError> {
+    if !list.is_array_object() {
+        return Err(vm.throw_type_error(message::called_on_non_object("CreateListFromArrayLike")));
+    }
+    let mut elems = vec![];
+    for idx in 0..list.as_array_mut().get_length() {
+        elems.push(vm.get_property_by_value(list, Value::Number(idx as f64))?);
+    }
+    Ok(elems)
+}
+
+/// ``Reflect.get(target, key, receiver)``. The receiver is the this of getters.
+/// https://tc39.github.io/ecma262/#sec-reflect.get
+pub fn reflect_get(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let target = target(vm, args, "Reflect.get")?;
+    let key = arg(args, 1);
+    let receiver = args.get(2).map(|x| *x).unwrap_or(target);
+    match internal_methods(target).get(vm, target, key)? {
+        Property::Data(DataProperty { val, .. }) => Ok(val),
+        Property::Accessor(AccessorProperty { get, .. }) => {
+            if get.is_undefined() {
+                return Ok(Value::undefined());
+            }
+            vm.call_function(get, &[], receiver)
+        }
+    }
+}
+
+/// ``Reflect.set(target, key, value, receiver)``. The receiver is the this of setters.
+/// Whether the property was set.
+/// https://tc39.github.io/ecma262/#sec-reflect.set
+pub fn reflect_set(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let target = target(vm, args, "Reflect.set")?;
+    let key = arg(args, 1);
+    let val = arg(args, 2);
+    let receiver = args.get(3).map(|x| *x).unwrap_or(target);
+    match internal_methods(target).set(vm, target, key, val)? {
+        SetResult::Done => Ok(Value::bool(true)),
+        SetResult::CallSetter(setter) => {
+            vm.call_function(setter, &[val], receiver)?;
+            Ok(Value::bool(true))
+        }
+        SetResult::Rejected => Ok(Value::bool(false)),
+    }
+}
+
+/// https://tc39.github.io/ecma262/#sec-reflect.has
+pub fn reflect_has(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let target = target(vm, args, "Reflect.has")?;
+    Ok(Value::bool(vm.has_property(target, arg(args, 1))?))
+}
+
+/// https://tc39.github.io/ecma262/#sec-reflect.ownkeys
+pub fn reflect_own_keys(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let target = target(vm, args, "Reflect.ownKeys")?;
+    let keys = internal_methods(target)
+        .own_property_keys(vm, target)?
+        .into_iter()
+        .map(Property::new_data_simple)
+        .collect();
+    Ok(vm.factory.array(keys))
+}
+
+/// Whether the property was defined, where ``Object.defineProperty`` throws a TypeError.
+/// https://tc39.github.io/ecma262/#sec-reflect.defineproperty
+pub fn reflect_define_property(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let target = target(vm, args, "Reflect.defineProperty")?;
+    let key = arg(args, 1);
+    let desc = to_property_descriptor(vm, arg(args, 2))?;
+    let defined = internal_methods(target).define_own_property(vm, target, key, desc)?;
+    Ok(Value::bool(defined))
+}
+
+/// https://tc39.github.io/ecma262/#sec-reflect.getprototypeof
+pub fn reflect_get_prototype_of(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let target = target(vm, args, "Reflect.getPrototypeOf")?;
+    internal_methods(target).get_prototype_of(vm, target)
+}
+
+/// https://tc39.github.io/ecma262/#sec-reflect.apply
+pub fn reflect_apply(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let func = arg(args, 0);
+    if !func.is_callable() {
+        return Err(vm.throw_type_error(message::not_a_function(func)));
+    }
+    let list = list_from_array(vm, arg(args, 2))?;
+    vm.call_function(func, &list, arg(args, 1))
+}
+
+/// ``Reflect.construct(target, args)``. A different new.target is not supported.
+/// https://tc39.github.io/ecma262/#sec-reflect.construct
+pub fn reflect_construct(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let func = arg(args, 0);
+    if !func.is_constructor() {
+        return Err(vm.throw_type_error(message::not_a_constructor(func)));
+    }
+    let list = list_from_array(vm, arg(args, 1))?;
+    vm.construct(func, &list)
+}
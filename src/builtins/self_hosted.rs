@@ -0,0 +1,27 @@
+//! Builtins written in JavaScript. The sources in src/builtins/js are embedded by build.rs, and
+//! compiled and run on every VM startup.
+
+use crate::parser::Parser;
+use crate::vm::vm::VM;
+
+include!(concat!(env!("OUT_DIR"), "/self_hosted.rs"));
+
+/// Compile and run all the self-hosted builtins.
+/// ### Panic
+/// Panic if a self-hosted builtin has an error, which is a bug of this engine.
+pub fn install(vm: &mut VM) {
+    for (file_name, source) in SOURCES {
+        let mut parser = Parser::new(*file_name, *source);
+        let mut node = parser
+            .parse_all()
+            .unwrap_or_else(|err| panic!("{}: parse error: {:?}", file_name, err));
+        let func_info = vm
+            .compile_with_source(&mut node, false, parser.source_text())
+            .unwrap_or_else(|err| panic!("{}: compile error: {}", file_name, err.msg));
+        vm.script_info
+            .push((func_info.module_func_id, parser.into_script_info()));
+        vm.builtin_modules.push(func_info.module_func_id);
+        vm.run_script(func_info)
+            .unwrap_or_else(|err| panic!("{}: runtime error: {:?}", file_name, err));
+    }
+}
@@ -0,0 +1,90 @@
+use crate::builtins::map::for_each_entry;
+use crate::vm::{
+    error::message,
+    jsvalue::value::Value,
+    vm::{Factory, VMValueResult, VM},
+};
+
+pub fn set(factory: &mut Factory) -> Value {
+    factory.generate_builtin_constructor("Set", set_constructor, factory.object_prototypes.set)
+}
+
+/// https://tc39.github.io/ecma262/#sec-set-iterable
+/// TODO: Only arrays are supported as an iterable.
+pub fn set_constructor(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResult {
+    let set = vm.factory.set();
+    let iterable = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    if iterable.is_undefined() || iterable.is_null() {
+        return Ok(set);
+    }
+
+    if !iterable.is_array_object() {
+        return Err(vm.throw_type_error(message::not_iterable(iterable)));
+    }
+
+    let len = iterable.as_array_mut().get_length();
+    for i in 0..len {
+        let val = vm.get_property_by_value(iterable, Value::Number(i as f64))?;
+        set.as_map_mut().set(val, val);
+    }
+
+    Ok(set)
+}
+
+macro_rules! this_set {
+    ($vm:expr, $this:expr, $method:expr) => {{
+        if !$this.is_set_object() {
+            return Err($vm.throw_type_error(message::incompatible_receiver(
+                &format!("Set.prototype.{}", $method),
+                $this,
+            )));
+        }
+        $this.as_map_mut()
+    }};
+}
+
+pub fn set_prototype_add(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    let set = this_set!(vm, this, "add");
+    let val = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    set.set(val, val);
+    Ok(this)
+}
+
+pub fn set_prototype_has(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    let set = this_set!(vm, this, "has");
+    let val = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    Ok(Value::bool(set.has(val)))
+}
+
+pub fn set_prototype_delete(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    let set = this_set!(vm, this, "delete");
+    let val = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    Ok(Value::bool(set.delete(val)))
+}
+
+pub fn set_prototype_clear(vm: &mut VM, _args: &[Value], this: Value) -> VMValueResult {
+    let set = this_set!(vm, this, "clear");
+    set.clear();
+    Ok(Value::undefined())
+}
+
+pub fn set_prototype_size(vm: &mut VM, _args: &[Value], this: Value) -> VMValueResult {
+    let set = this_set!(vm, this, "size");
+    Ok(Value::Number(set.size() as f64))
+}
+
+/// https://tc39.github.io/ecma262/#sec-set.prototype.foreach
+pub fn set_prototype_for_each(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    this_set!(vm, this, "forEach");
+    let callback = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
+    if !callback.is_callable() {
+        return Err(vm.throw_type_error(message::not_a_function(callback)));
+    }
+    let this_arg = args.get(1).map(|x| *x).unwrap_or(Value::undefined());
+
+    for_each_entry(this, |val, _| {
+        vm.call_function(callback, &[val, val, this], this_arg)
+    })?;
+
+    Ok(Value::undefined())
+}
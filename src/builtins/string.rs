@@ -1,11 +1,13 @@
 use crate::vm::{
+    error::{message, RuntimeError},
     jsvalue::value::{Property, Value},
     vm::VMValueResult,
     vm::VM,
 };
+use std::cmp::Ordering;
 
 pub fn string_prototype_split(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
-    let string = this.into_str();
+    let string = this.unwrap_primitive().into_str();
     let separator_ = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
     if separator_.is_undefined() {
         let ary = vm.factory.array(vec![Property::new_data_simple(this)]);
@@ -23,7 +25,7 @@ pub fn string_prototype_split(vm: &mut VM, args: &[Value], this: Value) -> VMVal
 }
 
 pub fn string_prototype_index_of(_vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
-    let string = this.into_str();
+    let string = this.unwrap_primitive().into_str();
     let search_string = args.get(0).unwrap_or(&Value::undefined()).to_string();
     let position = args.get(1).unwrap_or(&Value::Number(0.0)).into_number() as usize;
     let found_pos = string[position..]
@@ -32,3 +34,53 @@ pub fn string_prototype_index_of(_vm: &mut VM, args: &[Value], this: Value) -> V
     let val = Value::Number(found_pos);
     Ok(val)
 }
+
+/// https://tc39.github.io/ecma262/#sec-string.prototype.tostring
+pub fn string_prototype_to_string(vm: &mut VM, _args: &[Value], this: Value) -> VMValueResult {
+    this_string_value(vm, this, "String.prototype.toString")
+}
+
+/// https://tc39.github.io/ecma262/#sec-string.prototype.valueof
+pub fn string_prototype_value_of(vm: &mut VM, _args: &[Value], this: Value) -> VMValueResult {
+    this_string_value(vm, this, "String.prototype.valueOf")
+}
+
+/// https://tc39.github.io/ecma262/#sec-thisstringvalue
+fn this_string_value(vm: &mut VM, this: Value, method: &str) -> VMValueResult {
+    match this.unwrap_primitive() {
+        val @ Value::String(_) => Ok(val),
+        _ => Err(vm.throw_type_error(message::incompatible_receiver(method, this))),
+    }
+}
+
+/// https://tc39.github.io/ecma402/#sup-String.prototype.localeCompare
+pub fn string_prototype_locale_compare(vm: &mut VM, args: &[Value], this: Value) -> VMValueResult {
+    let string = this.unwrap_primitive().into_str();
+    let that = args.get(0).unwrap_or(&Value::undefined()).to_string();
+    let options = args.get(2).map(|x| *x).unwrap_or(Value::undefined());
+    let val = match locale_compare(vm, string, that.as_str(), options)? {
+        Ordering::Less => -1.0,
+        Ordering::Equal => 0.0,
+        Ordering::Greater => 1.0,
+    };
+    Ok(Value::Number(val))
+}
+
+#[cfg(feature = "intl")]
+fn locale_compare(vm: &mut VM, x: &str, y: &str, options: Value) -> Result<Ordering, RuntimeError> {
+    use crate::builtins::intl;
+    let options =
+        intl::CollatorOptions::from_value(options).map_err(|msg| vm.throw_range_error(msg))?;
+    Ok(intl::compare(x, y, &options))
+}
+
+/// Without the intl feature, strings are compared by code points and the options are ignored.
+#[cfg(not(feature = "intl"))]
+fn locale_compare(
+    _vm: &mut VM,
+    x: &str,
+    y: &str,
+    _options: Value,
+) -> Result<Ordering, RuntimeError> {
+    Ok(x.cmp(y))
+}
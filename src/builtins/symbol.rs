@@ -1,4 +1,5 @@
 use crate::vm::{
+    error::message,
     jsvalue::value::*,
     vm::{Factory, VMValueResult, VM},
 };
@@ -9,11 +10,15 @@ pub fn symbol(factory: &mut Factory) -> Value {
         symbol_constructor,
         factory.object_prototypes.symbol,
     );
+    // Symbols are not objects, so Symbol can not be called by new.
+    obj.as_function_mut().constructible = false;
 
     // Symbol.for
     obj.set_property("for", factory.builtin_function("for", symbol_for));
     // Symbol.keyFor
     obj.set_property("keyFor", factory.builtin_function("keyFor", symbol_key_for));
+    // Symbol.toPrimitive
+    obj.set_property("toPrimitive", factory.well_known_symbols.to_primitive);
     obj
 }
 
@@ -34,9 +39,7 @@ pub fn symbol_key_for(vm: &mut VM, args: &[Value], _this: Value) -> VMValueResul
     let sym = args.get(0).map(|x| *x).unwrap_or(Value::undefined());
 
     if !sym.is_symbol() {
-        return Err(vm
-            .current_context
-            .error_type(format!("{} is not symbol", sym.debug_string(true))));
+        return Err(vm.throw_type_error(message::not_a_symbol(sym)));
     }
 
     let key = vm.global_symbol_registry.key_for(&mut vm.factory, sym);
@@ -66,8 +66,12 @@ impl<'a> ByteCodeGenerator<'a> {
     }
 
     pub fn append_push_const(&mut self, val: Value, iseq: &mut ByteCode) {
-        iseq.push(VMInst::PUSH_CONST);
         let id = self.constant_table.add_value(val);
+        self.append_push_const_id(id, iseq);
+    }
+
+    pub fn append_push_const_id(&self, id: usize, iseq: &mut ByteCode) {
+        iseq.push(VMInst::PUSH_CONST);
         self.append_int32(id as i32, iseq);
     }
 
@@ -110,6 +114,12 @@ impl<'a> ByteCodeGenerator<'a> {
     pub fn append_sub(&self, iseq: &mut ByteCode) {
         iseq.push(VMInst::SUB);
     }
+    pub fn append_inc(&self, iseq: &mut ByteCode) {
+        iseq.push(VMInst::INC);
+    }
+    pub fn append_dec(&self, iseq: &mut ByteCode) {
+        iseq.push(VMInst::DEC);
+    }
     pub fn append_mul(&self, iseq: &mut ByteCode) {
         iseq.push(VMInst::MUL);
     }
@@ -187,11 +197,45 @@ impl<'a> ByteCodeGenerator<'a> {
         iseq.push(VMInst::SET_MEMBER);
     }
 
+    pub fn append_delete_member(&self, iseq: &mut ByteCode) {
+        iseq.push(VMInst::DELETE_MEMBER);
+    }
+
+    pub fn append_in(&self, iseq: &mut ByteCode) {
+        iseq.push(VMInst::IN);
+    }
+
+    /// ``key_id`` is the id of the property key given by ``ConstantTable::add_property_key``.
+    pub fn append_get_named_member(&self, key_id: usize, iseq: &mut ByteCode) {
+        iseq.push(VMInst::GET_NAMED_MEMBER);
+        self.append_int32(key_id as i32, iseq);
+    }
+
+    pub fn append_set_named_member(&self, key_id: usize, iseq: &mut ByteCode) {
+        iseq.push(VMInst::SET_NAMED_MEMBER);
+        self.append_int32(key_id as i32, iseq);
+    }
+
     pub fn append_call(&self, argc: u32, iseq: &mut ByteCode) {
         iseq.push(VMInst::CALL);
         self.append_int32(argc as i32, iseq);
     }
 
+    pub fn append_tail_call(&self, argc: u32, iseq: &mut ByteCode) {
+        iseq.push(VMInst::TAIL_CALL);
+        self.append_int32(argc as i32, iseq);
+    }
+
+    pub fn append_tail_call_method(&self, argc: u32, iseq: &mut ByteCode) {
+        iseq.push(VMInst::TAIL_CALL_METHOD);
+        self.append_int32(argc as i32, iseq);
+    }
+
+    pub fn append_call_eval(&self, argc: u32, iseq: &mut ByteCode) {
+        iseq.push(VMInst::CALL_EVAL);
+        self.append_int32(argc as i32, iseq);
+    }
+
     pub fn append_call_method(&self, argc: u32, iseq: &mut ByteCode) {
         iseq.push(VMInst::CALL_METHOD);
         self.append_int32(argc as i32, iseq);
@@ -207,6 +251,19 @@ impl<'a> ByteCodeGenerator<'a> {
         self.append_int32(dst, iseq);
     }
 
+    /// Compare the two values at the top of the stack by ``op`` (``LT``, ``LE``, ``GT`` or
+    /// ``GE``), and jump if false, in one instruction.
+    pub fn append_cmp_jmp_if_false(&self, op: u8, dst: i32, iseq: &mut ByteCode) {
+        iseq.push(match op {
+            VMInst::LT => VMInst::LT_JMP_IF_FALSE,
+            VMInst::LE => VMInst::LE_JMP_IF_FALSE,
+            VMInst::GT => VMInst::GT_JMP_IF_FALSE,
+            VMInst::GE => VMInst::GE_JMP_IF_FALSE,
+            _ => unreachable!(),
+        });
+        self.append_int32(dst, iseq);
+    }
+
     pub fn append_return_sub(&self, iseq: &mut ByteCode) {
         iseq.push(VMInst::RETURN_SUB);
     }
@@ -222,21 +279,57 @@ impl<'a> ByteCodeGenerator<'a> {
 
     pub fn append_return_try(&self, iseq: &mut ByteCode) {
         iseq.push(VMInst::RETURN_TRY);
-        self.append_int32(0, iseq);
     }
 
     pub fn append_get_value(&mut self, name: &String, iseq: &mut ByteCode) {
-        let id = self.constant_table.add_string(name.clone()) as i32;
+        let id = self.constant_table.add_string(name) as i32;
         iseq.push(VMInst::GET_VALUE);
         self.append_int32(id, iseq);
     }
 
     pub fn append_set_value(&mut self, name: &String, iseq: &mut ByteCode) {
-        let id = self.constant_table.add_string(name.clone()) as i32;
+        let id = self.constant_table.add_string(name) as i32;
         iseq.push(VMInst::SET_VALUE);
         self.append_int32(id, iseq);
     }
 
+    /// Get a variable captured from an enclosing function, looking it up from the environment
+    /// the current function closes over.
+    pub fn append_get_upvalue(&mut self, name: &String, iseq: &mut ByteCode) {
+        let id = self.constant_table.add_string(name) as i32;
+        iseq.push(VMInst::GET_UPVALUE);
+        self.append_int32(id, iseq);
+    }
+
+    pub fn append_set_upvalue(&mut self, name: &String, iseq: &mut ByteCode) {
+        let id = self.constant_table.add_string(name) as i32;
+        iseq.push(VMInst::SET_UPVALUE);
+        self.append_int32(id, iseq);
+    }
+
+    /// Get a variable stored in the register ``index`` of the frame.
+    pub fn append_get_register(&self, index: usize, iseq: &mut ByteCode) {
+        iseq.push(VMInst::GET_REGISTER);
+        self.append_int32(index as i32, iseq);
+    }
+
+    pub fn append_set_register(&self, index: usize, iseq: &mut ByteCode) {
+        iseq.push(VMInst::SET_REGISTER);
+        self.append_int32(index as i32, iseq);
+    }
+
+    pub fn append_decl_let(&mut self, name: &String, iseq: &mut ByteCode) {
+        let id = self.constant_table.add_string(name) as i32;
+        iseq.push(VMInst::DECL_LET);
+        self.append_int32(id, iseq);
+    }
+
+    pub fn append_decl_const(&mut self, name: &String, iseq: &mut ByteCode) {
+        let id = self.constant_table.add_string(name) as i32;
+        iseq.push(VMInst::DECL_CONST);
+        self.append_int32(id, iseq);
+    }
+
     pub fn append_cond_op(&mut self, iseq: &mut ByteCode) {
         iseq.push(VMInst::COND_OP);
     }
@@ -259,6 +352,14 @@ impl<'a> ByteCodeGenerator<'a> {
         iseq.push(VMInst::POP_ENV);
     }
 
+    pub fn append_drop_sub(&mut self, iseq: &mut ByteCode) {
+        iseq.push(VMInst::DROP_SUB);
+    }
+
+    pub fn append_copy_env(&mut self, iseq: &mut ByteCode) {
+        iseq.push(VMInst::COPY_ENV);
+    }
+
     pub fn append_set_outer_env(&mut self, iseq: &mut ByteCode) {
         iseq.push(VMInst::SET_OUTER_ENV);
     }
@@ -267,6 +368,16 @@ impl<'a> ByteCodeGenerator<'a> {
         iseq.push(VMInst::TYPEOF);
     }
 
+    pub fn append_typeof_value(&mut self, name: &String, iseq: &mut ByteCode) {
+        let id = self.constant_table.add_string(name) as i32;
+        iseq.push(VMInst::TYPEOF_VALUE);
+        self.append_int32(id, iseq);
+    }
+
+    pub fn append_debugger(&mut self, iseq: &mut ByteCode) {
+        iseq.push(VMInst::DEBUGGER);
+    }
+
     // Utils
 
     pub fn append_int8(&self, n: i8, iseq: &mut ByteCode) {
@@ -332,7 +443,7 @@ pub fn show_inst(code: &ByteCode, i: usize, const_table: &constant::ConstantTabl
                 format!("CreateObject {}", int32)
             }
             VMInst::PUSH_INT8 => {
-                let int8 = code[i + 1] as i32;
+                let int8 = code[i + 1] as i8 as i32;
                 format!("PushInt8 {}", int8)
             }
             VMInst::PUSH_INT32 => {
@@ -345,6 +456,16 @@ pub fn show_inst(code: &ByteCode, i: usize, const_table: &constant::ConstantTabl
                 // TODO: Implement 'format' for 'value'
                 format!("PushConst {}", value)
             }
+            VMInst::GET_NAMED_MEMBER => {
+                let int32 = read_int32(code, i + 1);
+                let key = const_table.get(int32 as usize).as_value();
+                format!("GetNamedMember {}", key)
+            }
+            VMInst::SET_NAMED_MEMBER => {
+                let int32 = read_int32(code, i + 1);
+                let key = const_table.get(int32 as usize).as_value();
+                format!("SetNamedMember {}", key)
+            }
             VMInst::JMP_IF_FALSE => {
                 let int32 = read_int32(code, i + 1);
                 format!("JmpIfFalse {:05}", i as i32 + int32 + 5)
@@ -353,10 +474,29 @@ pub fn show_inst(code: &ByteCode, i: usize, const_table: &constant::ConstantTabl
                 let int32 = read_int32(code, i + 1);
                 format!("Jmp {:05}", i as i32 + int32 + 5)
             }
+            VMInst::LT_JMP_IF_FALSE
+            | VMInst::LE_JMP_IF_FALSE
+            | VMInst::GT_JMP_IF_FALSE
+            | VMInst::GE_JMP_IF_FALSE => {
+                let int32 = read_int32(code, i + 1);
+                format!("{} {:05}", inst_to_inst_name(code[i]), i as i32 + int32 + 5)
+            }
             VMInst::CALL => {
                 let int32 = read_int32(code, i + 1);
                 format!("Call {}", int32)
             }
+            VMInst::TAIL_CALL => {
+                let int32 = read_int32(code, i + 1);
+                format!("TailCall {}", int32)
+            }
+            VMInst::TAIL_CALL_METHOD => {
+                let int32 = read_int32(code, i + 1);
+                format!("TailCallMethod {}", int32)
+            }
+            VMInst::CALL_EVAL => {
+                let int32 = read_int32(code, i + 1);
+                format!("CallEval {}", int32)
+            }
             VMInst::CALL_METHOD => {
                 let int32 = read_int32(code, i + 1);
                 format!("CallMethod {}", int32)
@@ -371,6 +511,29 @@ pub fn show_inst(code: &ByteCode, i: usize, const_table: &constant::ConstantTabl
                 let name = const_table.get(int32 as usize).as_string();
                 format!("SetValue '{}'", name)
             }
+            VMInst::TYPEOF_VALUE => {
+                let int32 = read_int32(code, i + 1);
+                let name = const_table.get(int32 as usize).as_string();
+                format!("TypeofValue '{}'", name)
+            }
+            VMInst::GET_UPVALUE => {
+                let int32 = read_int32(code, i + 1);
+                let name = const_table.get(int32 as usize).as_string();
+                format!("GetUpvalue '{}'", name)
+            }
+            VMInst::SET_UPVALUE => {
+                let int32 = read_int32(code, i + 1);
+                let name = const_table.get(int32 as usize).as_string();
+                format!("SetUpvalue '{}'", name)
+            }
+            VMInst::GET_REGISTER => {
+                let int32 = read_int32(code, i + 1);
+                format!("GetRegister {}", int32)
+            }
+            VMInst::SET_REGISTER => {
+                let int32 = read_int32(code, i + 1);
+                format!("SetRegister {}", int32)
+            }
             VMInst::DECL_VAR => {
                 let int32 = read_int32(code, i + 1);
                 let name = const_table.get(int32 as usize).as_string();
@@ -420,6 +583,8 @@ pub fn inst_to_inst_name(inst: u8) -> &'static str {
         VMInst::NEG => "Neg",
         VMInst::ADD => "Add",
         VMInst::SUB => "Sub",
+        VMInst::INC => "Inc",
+        VMInst::DEC => "Dec",
         VMInst::MUL => "Mul",
         VMInst::DIV => "Div",
         VMInst::REM => "Rem",
@@ -439,9 +604,20 @@ pub fn inst_to_inst_name(inst: u8) -> &'static str {
         VMInst::ZFSHR => "ZeroFill-Shift-R",
         VMInst::GET_MEMBER => "GetMember",
         VMInst::SET_MEMBER => "SetMember",
+        VMInst::DELETE_MEMBER => "DeleteMember",
+        VMInst::IN => "In",
+        VMInst::GET_NAMED_MEMBER => "GetNamedMember",
+        VMInst::SET_NAMED_MEMBER => "SetNamedMember",
         VMInst::JMP_IF_FALSE => "JmpIfFalse",
         VMInst::JMP => "Jmp",
+        VMInst::LT_JMP_IF_FALSE => "LtJmpIfFalse",
+        VMInst::LE_JMP_IF_FALSE => "LeJmpIfFalse",
+        VMInst::GT_JMP_IF_FALSE => "GtJmpIfFalse",
+        VMInst::GE_JMP_IF_FALSE => "GeJmpIfFalse",
         VMInst::CALL => "Call",
+        VMInst::TAIL_CALL => "TailCall",
+        VMInst::TAIL_CALL_METHOD => "TailCallMethod",
+        VMInst::CALL_EVAL => "CallEval",
         VMInst::CALL_METHOD => "CallMethod",
         VMInst::RETURN => "Return",
         VMInst::DOUBLE => "Double",
@@ -450,11 +626,16 @@ pub fn inst_to_inst_name(inst: u8) -> &'static str {
         VMInst::LOR => "LogOr",
         VMInst::GET_VALUE => "GetValue",
         VMInst::SET_VALUE => "SetValue",
+        VMInst::GET_UPVALUE => "GetUpvalue",
+        VMInst::SET_UPVALUE => "SetUpvalue",
+        VMInst::GET_REGISTER => "GetRegister",
+        VMInst::SET_REGISTER => "SetRegister",
         VMInst::DECL_VAR => "DeclVar",
         VMInst::DECL_CONST => "DeclConst",
         VMInst::DECL_LET => "DeclLet",
         VMInst::PUSH_ENV => "PushEnv",
         VMInst::POP_ENV => "PopEnv",
+        VMInst::COPY_ENV => "CopyEnv",
         VMInst::COND_OP => "CondOp",
         VMInst::LOOP_START => "LoopStart",
         VMInst::THROW => "Throw",
@@ -463,10 +644,13 @@ pub fn inst_to_inst_name(inst: u8) -> &'static str {
         VMInst::SET_OUTER_ENV => "SetOuterEnv",
         VMInst::JMP_SUB => "JmpSub",
         VMInst::RETURN_SUB => "ReturnSub",
+        VMInst::DROP_SUB => "DropSub",
         VMInst::TYPEOF => "Typeof",
+        VMInst::TYPEOF_VALUE => "TypeofValue",
         VMInst::EXP => "Exp",
         VMInst::PUSH_SEPERATOR => "PushSeperator",
         VMInst::SPREAD_ARRAY => "SpreadArray",
+        VMInst::DEBUGGER => "Debugger",
         _ => "???",
     }
 }
@@ -494,6 +678,8 @@ pub mod VMInst {
     pub const NEG: u8 = 0x0f;
     pub const ADD: u8 = 0x10;
     pub const SUB: u8 = 0x11;
+    pub const INC: u8 = 0x58;
+    pub const DEC: u8 = 0x59;
     pub const MUL: u8 = 0x12;
     pub const DIV: u8 = 0x13;
     pub const REM: u8 = 0x14;
@@ -517,19 +703,39 @@ pub mod VMInst {
     pub const ZFSHR: u8 = 0x22;
     pub const JMP_IF_FALSE: u8 = 0x25;
     pub const JMP: u8 = 0x26;
+    pub const LT_JMP_IF_FALSE: u8 = 0x4f;
+    pub const LE_JMP_IF_FALSE: u8 = 0x50;
+    pub const GT_JMP_IF_FALSE: u8 = 0x51;
+    pub const GE_JMP_IF_FALSE: u8 = 0x52;
     pub const CALL: u8 = 0x27;
     pub const CALL_METHOD: u8 = 0x41;
+    /// A call in tail position of strict code, which reuses the frame of the caller.
+    pub const TAIL_CALL: u8 = 0x57;
+    /// A method call in tail position of strict code, which reuses the frame of the caller.
+    pub const TAIL_CALL_METHOD: u8 = 0x5d;
     pub const CONSTRUCT: u8 = 0x02;
     pub const RETURN: u8 = 0x28;
     pub const GET_MEMBER: u8 = 0x23;
     pub const SET_MEMBER: u8 = 0x24;
+    pub const DELETE_MEMBER: u8 = 0x53;
+    pub const IN: u8 = 0x56;
+    pub const GET_NAMED_MEMBER: u8 = 0x4a;
+    pub const SET_NAMED_MEMBER: u8 = 0x4b;
     pub const GET_VALUE: u8 = 0x2e;
     pub const SET_VALUE: u8 = 0x2f;
+    pub const GET_UPVALUE: u8 = 0x4c;
+    pub const SET_UPVALUE: u8 = 0x4d;
+    pub const GET_REGISTER: u8 = 0x54;
+    pub const SET_REGISTER: u8 = 0x55;
+    pub const CALL_EVAL: u8 = 0x4e;
     pub const DECL_VAR: u8 = 0x30;
     pub const DECL_CONST: u8 = 0x3b;
     pub const DECL_LET: u8 = 0x3c;
     pub const PUSH_ENV: u8 = 0x3f;
     pub const POP_ENV: u8 = 0x40;
+    /// Replace the lexical environment with a copy of it, which gives each iteration of a
+    /// ``for (let ...)`` loop its own bindings.
+    pub const COPY_ENV: u8 = 0x5b;
     pub const COND_OP: u8 = 0x31;
     pub const LOOP_START: u8 = 0x32;
     pub const THROW: u8 = 0x33;
@@ -537,22 +743,51 @@ pub mod VMInst {
     pub const SET_OUTER_ENV: u8 = 0x42;
     pub const JMP_SUB: u8 = 0x43;
     pub const RETURN_SUB: u8 = 0x44;
+    /// Leave the finally clause being run by a break or continue. An exception or a return
+    /// value the clause was entered with is discarded.
+    pub const DROP_SUB: u8 = 0x5c;
     pub const TYPEOF: u8 = 0x45;
+    /// ``typeof`` of a variable looked up by name, which is "undefined" if it is not declared.
+    pub const TYPEOF_VALUE: u8 = 0x5a;
+    pub const DEBUGGER: u8 = 0x49;
 
     pub fn get_inst_size(inst: u8) -> Option<usize> {
         match inst {
-            THROW | RETURN_SUB | SET_OUTER_ENV | POP_ENV | TYPEOF | PUSH_NULL => Some(1),
-            CONSTRUCT | CREATE_OBJECT | PUSH_CONST | PUSH_INT32 | JMP_IF_FALSE | RETURN_TRY
-            | DECL_VAR | LOOP_START | JMP | SET_VALUE | GET_VALUE | CALL | JMP_SUB
-            | CALL_METHOD | PUSH_ENV | DECL_LET | DECL_CONST => Some(5),
+            THROW | RETURN_TRY | RETURN_SUB | DROP_SUB | SET_OUTER_ENV | POP_ENV | TYPEOF
+            | PUSH_NULL | DEBUGGER => Some(1),
+            CONSTRUCT | CREATE_OBJECT | PUSH_CONST | PUSH_INT32 | JMP_IF_FALSE | DECL_VAR
+            | LOOP_START | JMP | SET_VALUE | GET_VALUE | CALL | JMP_SUB | CALL_METHOD
+            | PUSH_ENV | DECL_LET | DECL_CONST | GET_NAMED_MEMBER | SET_NAMED_MEMBER
+            | GET_UPVALUE | SET_UPVALUE | CALL_EVAL | LT_JMP_IF_FALSE | LE_JMP_IF_FALSE
+            | GT_JMP_IF_FALSE | GE_JMP_IF_FALSE | GET_REGISTER | SET_REGISTER | TAIL_CALL
+            | TAIL_CALL_METHOD | TYPEOF_VALUE => Some(5),
             PUSH_INT8 => Some(2),
             PUSH_FALSE | END | PUSH_TRUE | PUSH_THIS | ADD | SUB | MUL | DIV | REM | LT | EXP
             | PUSH_ARGUMENTS | NEG | POSI | GT | LE | GE | EQ | NE | GET_MEMBER | RETURN | SNE
             | ZFSHR | POP | DOUBLE | AND | COND_OP | OR | SEQ | SET_MEMBER | LNOT
-            | PUSH_UNDEFINED | LAND | SHR | SHL | XOR | LOR | NOT | CREATE_ARRAY | SPREAD_ARRAY => {
-                Some(1)
-            }
+            | PUSH_UNDEFINED | LAND | SHR | SHL | XOR | LOR | NOT | CREATE_ARRAY | SPREAD_ARRAY
+            | PUSH_SEPERATOR | DELETE_MEMBER | IN | INC | DEC | COPY_ENV => Some(1),
             _ => None,
         }
     }
+
+    /// Whether the operand of ``inst`` is an id in the constant table.
+    pub fn has_constant_operand(inst: u8) -> bool {
+        matches!(
+            inst,
+            CREATE_OBJECT
+                | PUSH_CONST
+                | GET_NAMED_MEMBER
+                | SET_NAMED_MEMBER
+                | GET_VALUE
+                | SET_VALUE
+                | TYPEOF_VALUE
+                | GET_UPVALUE
+                | SET_UPVALUE
+                | DECL_VAR
+                | DECL_CONST
+                | DECL_LET
+                | PUSH_ENV
+        )
+    }
 }
@@ -0,0 +1,745 @@
+//! The C API, enabled by the ``capi`` feature, for embedding rapidus in programs not written in
+//! Rust. include/rapidus.h is generated from this file by build.rs, so every item exported here
+//! is declared there with its doc comment.
+//!
+//! Values are passed to C as handles, which keep the values alive until they are freed with
+//! ``rapidus_value_free``. Functions that can fail return NULL (or -1), and the message of the
+//! error is given by ``rapidus_last_error``. Strings are UTF-8 and NUL-terminated.
+
+use crate::parser::Parser;
+use crate::vm::exec_context::{ExecContext, LexicalEnvironmentRef};
+use crate::vm::jsvalue::value::{ErrorType, Value};
+use crate::vm::vm::{VMValueResult, VM};
+use rustc_hash::FxHashMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_double, c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+pub const RAPIDUS_TYPE_UNDEFINED: c_int = 0;
+pub const RAPIDUS_TYPE_NULL: c_int = 1;
+pub const RAPIDUS_TYPE_BOOLEAN: c_int = 2;
+pub const RAPIDUS_TYPE_NUMBER: c_int = 3;
+pub const RAPIDUS_TYPE_STRING: c_int = 4;
+pub const RAPIDUS_TYPE_SYMBOL: c_int = 5;
+pub const RAPIDUS_TYPE_OBJECT: c_int = 6;
+pub const RAPIDUS_TYPE_FUNCTION: c_int = 7;
+
+/// A VM with the state of the scripts evaluated in it.
+pub struct RapidusRuntime {
+    vm: VM,
+    /// The context the scripts given to ``rapidus_eval`` run in. Kept across the calls so that
+    /// their declarations stay visible.
+    global_context: Option<ExecContext>,
+    /// The values referenced by handles, indexed by the ids of the handles. None for the ids
+    /// of freed handles, which are reused.
+    handles: Vec<Option<Value>>,
+    free_handle_ids: Vec<usize>,
+    /// The number of the handles to each value collected by the GC, by the address of the
+    /// value. The values are locked while they have handles.
+    handle_counts: FxHashMap<usize, usize>,
+    last_error: Option<CString>,
+    /// The message given to ``rapidus_throw_error`` by the running callback.
+    pending_error: Option<String>,
+    /// The number of callbacks being run.
+    callback_depth: usize,
+}
+
+/// A handle to a value.
+pub struct RapidusValue {
+    val: Value,
+    id: usize,
+}
+
+/// A function callable from scripts. ``args`` are the ``argc`` arguments. The handles given to
+/// the callback are freed after it returns. It returns a new handle, which the runtime frees,
+/// or one of the given handles. It returns NULL to throw an Error with the message given to
+/// ``rapidus_throw_error``.
+pub type RapidusCallback = unsafe extern "C" fn(
+    rt: *mut RapidusRuntime,
+    this_value: *mut RapidusValue,
+    args: *const *mut RapidusValue,
+    argc: usize,
+    data: *mut c_void,
+) -> *mut RapidusValue;
+
+impl RapidusRuntime {
+    fn new_handle(&mut self, val: Value) -> *mut RapidusValue {
+        let id = match self.free_handle_ids.pop() {
+            Some(id) => {
+                self.handles[id] = Some(val);
+                id
+            }
+            None => {
+                self.handles.push(Some(val));
+                self.handles.len() - 1
+            }
+        };
+        if let Some(addr) = gc_address(val) {
+            let count = self.handle_counts.entry(addr).or_insert(0);
+            if *count == 0 {
+                self.vm.factory.memory_allocator.lock_value(val);
+            }
+            *count += 1;
+        }
+        Box::into_raw(Box::new(RapidusValue { val, id }))
+    }
+
+    fn free_handle(&mut self, handle: *mut RapidusValue) {
+        let id = unsafe { Box::from_raw(handle) }.id;
+        let val = match self.handles.get_mut(id).and_then(|slot| slot.take()) {
+            Some(val) => val,
+            None => return,
+        };
+        self.free_handle_ids.push(id);
+        if let Some(addr) = gc_address(val) {
+            let count = self.handle_counts.get_mut(&addr).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                self.handle_counts.remove(&addr);
+                self.vm.factory.memory_allocator.unlock_value(val);
+            }
+        }
+    }
+
+    fn set_error(&mut self, msg: impl Into<String>) {
+        let msg = msg.into().replace('\0', " ");
+        self.last_error = Some(CString::new(msg).unwrap());
+    }
+
+    fn set_syntax_error(&mut self, parser: &Parser, pos: usize, msg: String) {
+        let (line, column) = parser.source_text().line_column(pos);
+        self.set_error(format!(
+            "{}:{}:{}: SyntaxError: {}",
+            parser.file_name, line, column, msg
+        ));
+    }
+
+    /// Convert the result of running scripts into a new handle, or set the error and return
+    /// NULL.
+    fn handle_result(&mut self, result: VMValueResult) -> *mut RapidusValue {
+        match result {
+            Ok(val) => self.new_handle(val),
+            Err(err) => {
+                let location = self.vm.error_location(&err);
+                let val = self.vm.error_to_value(err);
+                let msg = if val.is_error_object() {
+                    val.to_string()
+                } else {
+                    format!("Thrown: {}", val.to_string())
+                };
+                self.set_error(match location {
+                    Some(location) => format!("{}: {}", location, msg),
+                    None => msg,
+                });
+                // Discard the frames left by the error.
+                if self.callback_depth == 0 {
+                    self.vm.saved_context.clear();
+                }
+                ptr::null_mut()
+            }
+        }
+    }
+
+    /// The environment the scripts given to ``rapidus_eval`` declare their variables in.
+    fn global_environment(&self) -> LexicalEnvironmentRef {
+        match &self.global_context {
+            Some(context) => context.lexical_environment,
+            None => self.vm.global_environment,
+        }
+    }
+
+    fn set_global(&mut self, name: &str, val: Value) -> c_int {
+        match self.global_environment().set_value(name, val) {
+            Ok(()) => 0,
+            Err(err) => {
+                self.handle_result(Err(err));
+                -1
+            }
+        }
+    }
+
+    fn eval(&mut self, file_name: &str, code: &str) -> *mut RapidusValue {
+        if self.callback_depth > 0 {
+            self.set_error("rapidus_eval cannot be called from a callback");
+            return ptr::null_mut();
+        }
+        let mut parser = Parser::new(file_name, code);
+        if let Err(msg) = self.vm.transform_source(&mut parser) {
+            self.set_error(msg);
+            return ptr::null_mut();
+        }
+        let mut node = match parser.parse_all() {
+            Ok(node) => node,
+            Err(err) => {
+                self.set_syntax_error(&parser, parser.error_pos(&err), Parser::error_message(&err));
+                return ptr::null_mut();
+            }
+        };
+        let global_info = match self
+            .vm
+            .compile_with_source(&mut node, true, parser.source_text())
+        {
+            Ok(info) => info,
+            Err(err) => {
+                self.set_syntax_error(&parser, err.token_pos, err.msg);
+                return ptr::null_mut();
+            }
+        };
+        let context = match self.global_context.take() {
+            Some(mut context) => {
+                context.append_from_function_info(&mut self.vm.factory, &global_info);
+                context.func_ref = global_info;
+                context
+            }
+            None => self.vm.create_global_context(global_info),
+        };
+        self.global_context = Some(context.clone());
+        self.vm.current_context = context;
+        self.vm
+            .script_info
+            .push((global_info.module_func_id, parser.into_script_info()));
+        let result = self.vm.run();
+        self.handle_result(result)
+    }
+}
+
+/// The address of ``val`` if it is collected by the GC. Only strings and objects are.
+fn gc_address(val: Value) -> Option<usize> {
+    match val {
+        Value::String(s) => Some(s as usize),
+        Value::Object(obj) => Some(obj as usize),
+        _ => None,
+    }
+}
+
+unsafe fn to_str<'a>(s: *const c_char) -> Result<&'a str, String> {
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| "The string is not UTF-8".to_string())
+}
+
+/// Run ``f``, making a panic in rapidus an error so that it does not unwind into C. Return
+/// ``on_panic`` if it panics.
+fn catch_panic<T>(
+    rt: &mut RapidusRuntime,
+    on_panic: T,
+    f: impl FnOnce(&mut RapidusRuntime) -> T,
+) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(|| f(rt))) {
+        Ok(ret) => ret,
+        Err(_) => {
+            rt.set_error("Internal error in rapidus");
+            on_panic
+        }
+    }
+}
+
+/// Create a runtime. Free it with ``rapidus_runtime_free``.
+#[no_mangle]
+pub extern "C" fn rapidus_runtime_new() -> *mut RapidusRuntime {
+    Box::into_raw(Box::new(RapidusRuntime {
+        vm: VM::new(),
+        global_context: None,
+        handles: vec![],
+        free_handle_ids: vec![],
+        handle_counts: FxHashMap::default(),
+        last_error: None,
+        pending_error: None,
+        callback_depth: 0,
+    }))
+}
+
+/// Free a runtime. The handles to its values must be freed before.
+///
+/// # Safety
+///
+/// ``rt`` must be NULL or a runtime given by ``rapidus_runtime_new``. It and the handles to its
+/// values must not be used after this.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_runtime_free(rt: *mut RapidusRuntime) {
+    if !rt.is_null() {
+        drop(Box::from_raw(rt));
+    }
+}
+
+/// The message of the last error, or NULL if nothing has failed. The string is valid until the
+/// next error.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_last_error(rt: *mut RapidusRuntime) -> *const c_char {
+    (*rt)
+        .last_error
+        .as_ref()
+        .map_or(ptr::null(), |msg| msg.as_ptr())
+}
+
+/// Run ``code`` as a script named ``file_name``, and return its completion value. The
+/// declarations of the scripts run before are visible. Cannot be called from a callback.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed. ``file_name`` and
+/// ``code`` must be NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_eval(
+    rt: *mut RapidusRuntime,
+    file_name: *const c_char,
+    code: *const c_char,
+) -> *mut RapidusValue {
+    let rt = &mut *rt;
+    let (file_name, code) = match (to_str(file_name), to_str(code)) {
+        (Ok(file_name), Ok(code)) => (file_name, code),
+        (Err(msg), _) | (_, Err(msg)) => {
+            rt.set_error(msg);
+            return ptr::null_mut();
+        }
+    };
+    catch_panic(rt, ptr::null_mut(), |rt| rt.eval(file_name, code))
+}
+
+/// Get the global variable ``name``.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed. ``name`` must be a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_get_global(
+    rt: *mut RapidusRuntime,
+    name: *const c_char,
+) -> *mut RapidusValue {
+    let rt = &mut *rt;
+    let name = match to_str(name) {
+        Ok(name) => name,
+        Err(msg) => {
+            rt.set_error(msg);
+            return ptr::null_mut();
+        }
+    };
+    catch_panic(rt, ptr::null_mut(), |rt| {
+        let result = rt.global_environment().get_value(name);
+        rt.handle_result(result)
+    })
+}
+
+/// Set the global variable ``name`` to ``value``, defining it if it does not exist. Return 0,
+/// or -1 on error.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed. ``name`` must be a
+/// NUL-terminated string. ``value`` must be a handle given by ``rt`` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_set_global(
+    rt: *mut RapidusRuntime,
+    name: *const c_char,
+    value: *mut RapidusValue,
+) -> c_int {
+    let rt = &mut *rt;
+    let name = match to_str(name) {
+        Ok(name) => name,
+        Err(msg) => {
+            rt.set_error(msg);
+            return -1;
+        }
+    };
+    let val = (*value).val;
+    catch_panic(rt, -1, |rt| rt.set_global(name, val))
+}
+
+/// Define the global function ``name`` calling ``callback`` with ``data``. Return 0, or -1 on
+/// error.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed. ``name`` must be a
+/// NUL-terminated string. ``callback`` is called with ``data`` until ``rt`` is freed, so ``data``
+/// must be valid until then.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_register_function(
+    rt: *mut RapidusRuntime,
+    name: *const c_char,
+    callback: RapidusCallback,
+    data: *mut c_void,
+) -> c_int {
+    let name = match to_str(name) {
+        Ok(name) => name.to_string(),
+        Err(msg) => {
+            (*rt).set_error(msg);
+            return -1;
+        }
+    };
+    let func_name = name.clone();
+    let func = (*rt)
+        .vm
+        .native_function(name.as_str(), move |_vm, args, this| {
+            call_callback(rt, callback, data, &func_name, args, this)
+        });
+    (*rt).set_global(&name, func)
+}
+
+/// Call ``callback`` from a script. The VM given to the native function is ``(*rt).vm``, which
+/// is used through ``rt`` since the callback can call back into the runtime.
+unsafe fn call_callback(
+    rt: *mut RapidusRuntime,
+    callback: RapidusCallback,
+    data: *mut c_void,
+    name: &str,
+    args: &[Value],
+    this: Value,
+) -> VMValueResult {
+    let this = (*rt).new_handle(this);
+    let args = args
+        .iter()
+        .map(|arg| (*rt).new_handle(*arg))
+        .collect::<Vec<_>>();
+    (*rt).callback_depth += 1;
+    let ret = callback(rt, this, args.as_ptr(), args.len(), data);
+    (*rt).callback_depth -= 1;
+
+    let result = if ret.is_null() {
+        let msg = (*rt)
+            .pending_error
+            .take()
+            .unwrap_or_else(|| format!("Native function {} failed", name));
+        Err((*rt).vm.throw_error(ErrorType::Error, msg))
+    } else {
+        Ok((*ret).val)
+    };
+    if !ret.is_null() && ret != this && !args.contains(&ret) {
+        (*rt).free_handle(ret);
+    }
+    (*rt).free_handle(this);
+    for arg in args {
+        (*rt).free_handle(arg);
+    }
+    result
+}
+
+/// Call ``func`` with the ``argc`` values ``args``. ``this_value`` may be NULL for undefined.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed. ``func`` and
+/// ``this_value``, unless it is NULL, must be handles given by ``rt`` and not freed. ``args`` must
+/// point to ``argc`` such handles.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_call(
+    rt: *mut RapidusRuntime,
+    func: *mut RapidusValue,
+    this_value: *mut RapidusValue,
+    args: *const *mut RapidusValue,
+    argc: usize,
+) -> *mut RapidusValue {
+    let rt = &mut *rt;
+    let this = if this_value.is_null() {
+        Value::undefined()
+    } else {
+        (*this_value).val
+    };
+    let args = (0..argc)
+        .map(|i| (**args.add(i)).val)
+        .collect::<Vec<Value>>();
+    catch_panic(rt, ptr::null_mut(), |rt| {
+        let result = rt.vm.call_function((*func).val, &args, this);
+        rt.handle_result(result)
+    })
+}
+
+/// Make the running callback throw an Error with ``message`` when it returns NULL.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed. ``message`` must be a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_throw_error(rt: *mut RapidusRuntime, message: *const c_char) {
+    let message = CStr::from_ptr(message).to_string_lossy().into_owned();
+    (*rt).pending_error = Some(message);
+}
+
+/// The undefined value.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_undefined(rt: *mut RapidusRuntime) -> *mut RapidusValue {
+    (*rt).new_handle(Value::undefined())
+}
+
+/// The null value.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_null(rt: *mut RapidusRuntime) -> *mut RapidusValue {
+    (*rt).new_handle(Value::null())
+}
+
+/// A boolean, false if ``b`` is 0.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_boolean(rt: *mut RapidusRuntime, b: c_int) -> *mut RapidusValue {
+    (*rt).new_handle(Value::bool(b != 0))
+}
+
+/// A number.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_number(rt: *mut RapidusRuntime, n: c_double) -> *mut RapidusValue {
+    (*rt).new_handle(Value::Number(n))
+}
+
+/// A string copied from ``s``.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed. ``s`` must be a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_string(
+    rt: *mut RapidusRuntime,
+    s: *const c_char,
+) -> *mut RapidusValue {
+    let rt = &mut *rt;
+    match to_str(s) {
+        Ok(s) => {
+            let val = rt.vm.factory.string(s);
+            rt.new_handle(val)
+        }
+        Err(msg) => {
+            rt.set_error(msg);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// One of ``RAPIDUS_TYPE_*``.
+///
+/// # Safety
+///
+/// ``value`` must be a handle which is not freed.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_value_type(value: *mut RapidusValue) -> c_int {
+    let val = (*value).val;
+    match val {
+        Value::Bool(_) => RAPIDUS_TYPE_BOOLEAN,
+        Value::Number(_) => RAPIDUS_TYPE_NUMBER,
+        Value::String(_) => RAPIDUS_TYPE_STRING,
+        Value::Object(_) if val.is_symbol() => RAPIDUS_TYPE_SYMBOL,
+        Value::Object(_) if val.is_function_object() => RAPIDUS_TYPE_FUNCTION,
+        Value::Object(_) => RAPIDUS_TYPE_OBJECT,
+        _ if val.is_null() => RAPIDUS_TYPE_NULL,
+        _ => RAPIDUS_TYPE_UNDEFINED,
+    }
+}
+
+/// 1 if ``value`` is truthy, 0 if not.
+///
+/// # Safety
+///
+/// ``value`` must be a handle which is not freed.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_value_to_boolean(value: *mut RapidusValue) -> c_int {
+    (*value).val.to_boolean() as c_int
+}
+
+/// Convert ``value`` into a number.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed. ``value`` must be a
+/// handle given by ``rt`` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_value_to_number(
+    rt: *mut RapidusRuntime,
+    value: *mut RapidusValue,
+) -> c_double {
+    (*value)
+        .val
+        .to_number(&mut (*rt).vm.factory.memory_allocator)
+}
+
+/// Convert ``value`` into a string. Free it with ``rapidus_string_free``.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed. ``value`` must be a
+/// handle given by ``rt`` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_value_to_string(
+    rt: *mut RapidusRuntime,
+    value: *mut RapidusValue,
+) -> *mut c_char {
+    match CString::new((*value).val.to_string()) {
+        Ok(s) => s.into_raw(),
+        Err(_) => {
+            (*rt).set_error("The string contains a NUL character");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a string given by ``rapidus_value_to_string``.
+///
+/// # Safety
+///
+/// ``s`` must be NULL or a string given by ``rapidus_value_to_string``, which must not be used
+/// after this.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Free a handle. The value is collected when it has no handles and is not referenced by the
+/// scripts.
+///
+/// # Safety
+///
+/// ``rt`` must be a runtime given by ``rapidus_runtime_new`` and not freed. ``value`` must be NULL
+/// or a handle given by ``rt``, which must not be used after this.
+#[no_mangle]
+pub unsafe extern "C" fn rapidus_value_free(rt: *mut RapidusRuntime, value: *mut RapidusValue) {
+    if !value.is_null() {
+        (*rt).free_handle(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn eval(rt: *mut RapidusRuntime, code: &str) -> Result<String, String> {
+        let file_name = CString::new("test.js").unwrap();
+        let code = CString::new(code).unwrap();
+        let val = rapidus_eval(rt, file_name.as_ptr(), code.as_ptr());
+        if val.is_null() {
+            return Err(CStr::from_ptr(rapidus_last_error(rt))
+                .to_string_lossy()
+                .into_owned());
+        }
+        let s = rapidus_value_to_string(rt, val);
+        let result = CStr::from_ptr(s).to_string_lossy().into_owned();
+        rapidus_string_free(s);
+        rapidus_value_free(rt, val);
+        Ok(result)
+    }
+
+    unsafe extern "C" fn add(
+        rt: *mut RapidusRuntime,
+        _this: *mut RapidusValue,
+        args: *const *mut RapidusValue,
+        argc: usize,
+        data: *mut c_void,
+    ) -> *mut RapidusValue {
+        *(data as *mut usize) += 1;
+        if argc != 2 {
+            let message = CString::new("add needs two arguments").unwrap();
+            rapidus_throw_error(rt, message.as_ptr());
+            return ptr::null_mut();
+        }
+        let a = rapidus_value_to_number(rt, *args);
+        let b = rapidus_value_to_number(rt, *args.add(1));
+        rapidus_number(rt, a + b)
+    }
+
+    unsafe extern "C" fn call_twice(
+        rt: *mut RapidusRuntime,
+        _this: *mut RapidusValue,
+        args: *const *mut RapidusValue,
+        _argc: usize,
+        _data: *mut c_void,
+    ) -> *mut RapidusValue {
+        let ret = rapidus_call(rt, *args, ptr::null_mut(), args.add(1), 1);
+        if ret.is_null() {
+            return ptr::null_mut();
+        }
+        let twice = rapidus_call(rt, *args, ptr::null_mut(), &ret, 1);
+        rapidus_value_free(rt, ret);
+        twice
+    }
+
+    #[test]
+    fn capi() {
+        unsafe {
+            let rt = rapidus_runtime_new();
+            assert_eq!(eval(rt, "let a = 1; var b = 2; a + b"), Ok("3".to_string()));
+            assert_eq!(eval(rt, "a = a * 10; a + b"), Ok("12".to_string()));
+            let err = eval(rt, "\nnull.x").unwrap_err();
+            assert!(err.starts_with("test.js:2:") && err.contains("TypeError"));
+            let err = eval(rt, "1 *").unwrap_err();
+            assert!(err.starts_with("test.js:1:") && err.contains("SyntaxError"));
+
+            let a = CString::new("a").unwrap();
+            let c = CString::new("c").unwrap();
+            let val = rapidus_get_global(rt, a.as_ptr());
+            assert_eq!(rapidus_value_type(val), RAPIDUS_TYPE_NUMBER);
+            assert_eq!(rapidus_value_to_number(rt, val), 10.0);
+            rapidus_value_free(rt, val);
+            assert!(rapidus_get_global(rt, c.as_ptr()).is_null());
+
+            let s = CString::new("hello").unwrap();
+            let val = rapidus_string(rt, s.as_ptr());
+            assert_eq!(rapidus_set_global(rt, a.as_ptr(), val), 0);
+            assert_eq!(rapidus_set_global(rt, c.as_ptr(), val), 0);
+            rapidus_value_free(rt, val);
+            assert_eq!(eval(rt, "a + c"), Ok("hellohello".to_string()));
+
+            let mut calls = 0usize;
+            let name = CString::new("add").unwrap();
+            let data = &mut calls as *mut usize as *mut c_void;
+            assert_eq!(rapidus_register_function(rt, name.as_ptr(), add, data), 0);
+            assert_eq!(eval(rt, "add(1, add(2, 3))"), Ok("6".to_string()));
+            assert_eq!(
+                eval(rt, "var m; try { add(1) } catch (e) { m = e.message }; m"),
+                Ok("add needs two arguments".to_string())
+            );
+            assert_eq!(calls, 3);
+
+            let name = CString::new("callTwice").unwrap();
+            let data = ptr::null_mut();
+            assert_eq!(
+                rapidus_register_function(rt, name.as_ptr(), call_twice, data),
+                0
+            );
+            assert_eq!(
+                eval(rt, "callTwice(function(x) { return x * 3 }, 2)"),
+                Ok("18".to_string())
+            );
+            let f = CString::new("callTwice").unwrap();
+            let val = rapidus_get_global(rt, f.as_ptr());
+            assert_eq!(rapidus_value_type(val), RAPIDUS_TYPE_FUNCTION);
+            rapidus_value_free(rt, val);
+
+            // Handles to the same value lock it until the last one is freed.
+            let s = CString::new("shared").unwrap();
+            let val1 = rapidus_string(rt, s.as_ptr());
+            assert_eq!(rapidus_set_global(rt, s.as_ptr(), val1), 0);
+            let val2 = rapidus_get_global(rt, s.as_ptr());
+            assert_eq!((*val1).val, (*val2).val);
+            assert_ne!((*val1).id, (*val2).id);
+            rapidus_value_free(rt, val1);
+            assert_eq!(rt.as_ref().unwrap().handle_counts.len(), 1);
+            rapidus_value_free(rt, val2);
+
+            let rt_ref = rt.as_ref().unwrap();
+            assert!(rt_ref.handles.iter().all(|slot| slot.is_none()));
+            assert!(rt_ref.handle_counts.is_empty());
+            rapidus_runtime_free(rt);
+        }
+    }
+}
@@ -0,0 +1,790 @@
+//! Serialize the AST into ESTree-shaped JSON.
+//! https://github.com/estree/estree
+
+use crate::lexer::line_column;
+use crate::node::{
+    BinOp, FormalParameter, FormalParameters, JSXAttribute, JSXElement, MethodDefinitionKind, Node,
+    NodeBase, PropertyDefinition, UnaryOp, VarKind,
+};
+
+/// Convert the script ``node`` parsed from ``code`` into ESTree JSON.
+/// ``start`` and ``end`` are byte offsets into ``code``, and ``loc`` has 1-based lines and
+/// 0-based columns as ESTree requires.
+pub fn to_json(node: &Node, code: &str, pos_line_list: &[(usize, usize)]) -> String {
+    ESTree {
+        code,
+        pos_line_list,
+    }
+    .program(node)
+}
+
+struct ESTree<'a> {
+    code: &'a str,
+    pos_line_list: &'a [(usize, usize)],
+}
+
+impl<'a> ESTree<'a> {
+    /// Build a JSON object of the given ``type`` with the position of ``node``.
+    fn object(&self, ty: &str, node: &Node, fields: Vec<(&str, String)>) -> String {
+        let loc = |pos| {
+            let (line, column) = line_column(self.code, self.pos_line_list, pos);
+            format!("{{\"line\":{},\"column\":{}}}", line, column - 1)
+        };
+        let start = node.start();
+        let mut s = format!(
+            "{{\"type\":\"{}\",\"start\":{},\"end\":{},\"loc\":{{\"start\":{},\"end\":{}}}",
+            ty,
+            start,
+            node.end,
+            loc(start),
+            loc(node.end)
+        );
+        for (key, val) in fields {
+            s += &format!(",\"{}\":{}", key, val);
+        }
+        s + "}"
+    }
+
+    /// Build a JSON object of the given ``type`` for a node that has no own position.
+    fn object_without_pos(&self, ty: &str, fields: Vec<(&str, String)>) -> String {
+        let mut s = format!("{{\"type\":\"{}\"", ty);
+        for (key, val) in fields {
+            s += &format!(",\"{}\":{}", key, val);
+        }
+        s + "}"
+    }
+
+    fn program(&self, node: &Node) -> String {
+        let body = match node.base {
+            NodeBase::StatementList(ref items) => self.statements(items),
+            _ => array(vec![self.statement(node)]),
+        };
+        self.object(
+            "Program",
+            node,
+            vec![("sourceType", string("script")), ("body", body)],
+        )
+    }
+
+    fn statements(&self, items: &[Node]) -> String {
+        array(items.iter().map(|item| self.statement(item)).collect())
+    }
+
+    fn block(&self, node: &Node) -> String {
+        match node.base {
+            NodeBase::StatementList(ref items) | NodeBase::Block(ref items) => self.object(
+                "BlockStatement",
+                node,
+                vec![("body", self.statements(items))],
+            ),
+            _ => self.statement(node),
+        }
+    }
+
+    fn statement(&self, node: &Node) -> String {
+        match node.base {
+            // The parser always wraps a for statement in a block to scope its let
+            // declarations. Such a block starts at the same position as the for.
+            NodeBase::Block(ref items)
+                if items.len() == 1 && items[0].pos == node.pos && is_for(&items[0]) =>
+            {
+                self.statement(&items[0])
+            }
+            NodeBase::StatementList(ref items) if is_declaration_list(items) => {
+                self.variable_declaration(node, items)
+            }
+            NodeBase::StatementList(_) | NodeBase::Block(_) => self.block(node),
+            NodeBase::FunctionDecl(ref name, ref params, ref body) => self.object(
+                "FunctionDeclaration",
+                node,
+                self.function_fields(Some(name), params, body),
+            ),
+            NodeBase::If(ref cond, ref then_, ref else_) => self.object(
+                "IfStatement",
+                node,
+                vec![
+                    ("test", self.expression(cond)),
+                    ("consequent", self.statement(then_)),
+                    ("alternate", self.optional_statement(else_)),
+                ],
+            ),
+            NodeBase::While(ref cond, ref body) => self.object(
+                "WhileStatement",
+                node,
+                vec![
+                    ("test", self.expression(cond)),
+                    ("body", self.statement(body)),
+                ],
+            ),
+            NodeBase::For(ref init, ref cond, ref step, ref body) => {
+                let init = match init.base {
+                    NodeBase::Nope => "null".to_string(),
+                    NodeBase::StatementList(ref items) if is_declaration_list(items) => {
+                        self.variable_declaration(init, items)
+                    }
+                    _ => self.expression(init),
+                };
+                // A missing condition is filled with `true` by the parser.
+                let test = match cond.base {
+                    NodeBase::Boolean(true) if !self.source(cond).starts_with("true") => {
+                        "null".to_string()
+                    }
+                    _ => self.expression(cond),
+                };
+                self.object(
+                    "ForStatement",
+                    node,
+                    vec![
+                        ("init", init),
+                        ("test", test),
+                        ("update", self.optional_expression(step)),
+                        ("body", self.statement(body)),
+                    ],
+                )
+            }
+            NodeBase::Return(ref val) => self.object(
+                "ReturnStatement",
+                node,
+                vec![(
+                    "argument",
+                    val.as_ref()
+                        .map_or("null".to_string(), |val| self.expression(val)),
+                )],
+            ),
+            NodeBase::Label(ref name, ref body) => self.object(
+                "LabeledStatement",
+                node,
+                vec![
+                    ("label", self.identifier(name)),
+                    ("body", self.statement(body)),
+                ],
+            ),
+            NodeBase::Break(ref label) => self.object(
+                "BreakStatement",
+                node,
+                vec![("label", self.optional_label(label))],
+            ),
+            NodeBase::Continue(ref label) => self.object(
+                "ContinueStatement",
+                node,
+                vec![("label", self.optional_label(label))],
+            ),
+            NodeBase::Try(ref try_, ref catch, ref param, ref finally) => {
+                let handler = match catch.base {
+                    NodeBase::Nope => "null".to_string(),
+                    _ => self.object(
+                        "CatchClause",
+                        catch,
+                        vec![
+                            ("param", self.optional_expression(param)),
+                            ("body", self.block(catch)),
+                        ],
+                    ),
+                };
+                let finalizer = match finally.base {
+                    NodeBase::Nope => "null".to_string(),
+                    _ => self.block(finally),
+                };
+                self.object(
+                    "TryStatement",
+                    node,
+                    vec![
+                        ("block", self.block(try_)),
+                        ("handler", handler),
+                        ("finalizer", finalizer),
+                    ],
+                )
+            }
+            NodeBase::Throw(ref val) => self.object(
+                "ThrowStatement",
+                node,
+                vec![("argument", self.expression(val))],
+            ),
+            NodeBase::Debugger => self.object("DebuggerStatement", node, vec![]),
+            NodeBase::Nope => self.object("EmptyStatement", node, vec![]),
+            _ => self.object(
+                "ExpressionStatement",
+                node,
+                vec![("expression", self.expression(node))],
+            ),
+        }
+    }
+
+    fn optional_statement(&self, node: &Node) -> String {
+        match node.base {
+            NodeBase::Nope => "null".to_string(),
+            _ => self.statement(node),
+        }
+    }
+
+    fn variable_declaration(&self, node: &Node, items: &[Node]) -> String {
+        let kind = match items[0].base {
+            NodeBase::VarDecl(_, _, VarKind::Var) => "var",
+            NodeBase::VarDecl(_, _, VarKind::Let) => "let",
+            _ => "const",
+        };
+        let declarations = items
+            .iter()
+            .map(|item| match item.base {
+                NodeBase::VarDecl(ref name, ref init, _) => self.object(
+                    "VariableDeclarator",
+                    item,
+                    vec![
+                        ("id", self.identifier(name)),
+                        (
+                            "init",
+                            init.as_ref()
+                                .map_or("null".to_string(), |init| self.expression(init)),
+                        ),
+                    ],
+                ),
+                _ => unreachable!(),
+            })
+            .collect();
+        self.object(
+            "VariableDeclaration",
+            node,
+            vec![
+                ("kind", string(kind)),
+                ("declarations", array(declarations)),
+            ],
+        )
+    }
+
+    fn function_fields(
+        &self,
+        name: Option<&String>,
+        params: &FormalParameters,
+        body: &Node,
+    ) -> Vec<(&'static str, String)> {
+        vec![
+            (
+                "id",
+                name.map_or("null".to_string(), |name| self.identifier(name)),
+            ),
+            ("params", self.params(params)),
+            ("body", self.block(body)),
+            ("generator", "false".to_string()),
+            ("async", "false".to_string()),
+            ("expression", "false".to_string()),
+        ]
+    }
+
+    fn params(&self, params: &FormalParameters) -> String {
+        array(
+            params
+                .iter()
+                .map(
+                    |FormalParameter {
+                         name,
+                         init,
+                         is_rest_param,
+                     }| {
+                        let id = self.identifier(name);
+                        if *is_rest_param {
+                            self.object_without_pos("RestElement", vec![("argument", id)])
+                        } else if let Some(init) = init {
+                            self.object_without_pos(
+                                "AssignmentPattern",
+                                vec![("left", id), ("right", self.expression(init))],
+                            )
+                        } else {
+                            id
+                        }
+                    },
+                )
+                .collect(),
+        )
+    }
+
+    fn expression(&self, node: &Node) -> String {
+        match node.base {
+            NodeBase::FunctionExpr(ref name, ref params, ref body) => self.object(
+                "FunctionExpression",
+                node,
+                self.function_fields(name.as_ref(), params, body),
+            ),
+            NodeBase::ArrowFunction(ref params, ref body) => {
+                // A concise body is parsed as a return statement.
+                let (body, is_expression) = match body.base {
+                    NodeBase::Return(Some(ref val)) => (self.expression(val), true),
+                    _ => (self.block(body), false),
+                };
+                self.object(
+                    "ArrowFunctionExpression",
+                    node,
+                    vec![
+                        ("id", "null".to_string()),
+                        ("params", self.params(params)),
+                        ("body", body),
+                        ("generator", "false".to_string()),
+                        ("async", "false".to_string()),
+                        ("expression", is_expression.to_string()),
+                    ],
+                )
+            }
+            NodeBase::Member(ref obj, ref name) => self.object(
+                "MemberExpression",
+                node,
+                vec![
+                    ("object", self.expression(obj)),
+                    ("property", self.identifier(name)),
+                    ("computed", "false".to_string()),
+                ],
+            ),
+            NodeBase::Index(ref obj, ref idx) => self.object(
+                "MemberExpression",
+                node,
+                vec![
+                    ("object", self.expression(obj)),
+                    ("property", self.expression(idx)),
+                    ("computed", "true".to_string()),
+                ],
+            ),
+            NodeBase::New(ref call) => match call.base {
+                NodeBase::Call(ref callee, ref args) => self.object(
+                    "NewExpression",
+                    node,
+                    vec![
+                        ("callee", self.expression(callee)),
+                        ("arguments", self.expressions(args)),
+                    ],
+                ),
+                _ => self.object(
+                    "NewExpression",
+                    node,
+                    vec![
+                        ("callee", self.expression(call)),
+                        ("arguments", "[]".to_string()),
+                    ],
+                ),
+            },
+            NodeBase::Call(ref callee, ref args) => self.object(
+                "CallExpression",
+                node,
+                vec![
+                    ("callee", self.expression(callee)),
+                    ("arguments", self.expressions(args)),
+                ],
+            ),
+            NodeBase::Assign(ref dst, ref src) => {
+                // The parser desugars `a op= b` into `a = a op b`, giving the binary operation
+                // the position of the assignment.
+                let (op, src) = match src.base {
+                    NodeBase::BinaryOp(ref lhs, ref rhs, ref op)
+                        if src.pos == node.pos && lhs == dst =>
+                    {
+                        (format!("{}=", binop_to_str(op)), rhs)
+                    }
+                    _ => ("=".to_string(), src),
+                };
+                self.object(
+                    "AssignmentExpression",
+                    node,
+                    vec![
+                        ("operator", string(&op)),
+                        ("left", self.expression(dst)),
+                        ("right", self.expression(src)),
+                    ],
+                )
+            }
+            NodeBase::UnaryOp(ref expr, ref op) => {
+                let (ty, op, prefix) = match op {
+                    UnaryOp::PrInc => ("UpdateExpression", "++", true),
+                    UnaryOp::PrDec => ("UpdateExpression", "--", true),
+                    UnaryOp::PoInc => ("UpdateExpression", "++", false),
+                    UnaryOp::PoDec => ("UpdateExpression", "--", false),
+                    UnaryOp::Delete => ("UnaryExpression", "delete", true),
+                    UnaryOp::Void => ("UnaryExpression", "void", true),
+                    UnaryOp::Typeof => ("UnaryExpression", "typeof", true),
+                    UnaryOp::Plus => ("UnaryExpression", "+", true),
+                    UnaryOp::Minus => ("UnaryExpression", "-", true),
+                    UnaryOp::BitwiseNot => ("UnaryExpression", "~", true),
+                    UnaryOp::Not => ("UnaryExpression", "!", true),
+                };
+                self.object(
+                    ty,
+                    node,
+                    vec![
+                        ("operator", string(op)),
+                        ("prefix", prefix.to_string()),
+                        ("argument", self.expression(expr)),
+                    ],
+                )
+            }
+            NodeBase::BinaryOp(_, _, BinOp::Comma) => {
+                let mut exprs = vec![];
+                flatten_sequence(node, &mut exprs);
+                let exprs = exprs.iter().map(|expr| self.expression(expr)).collect();
+                self.object(
+                    "SequenceExpression",
+                    node,
+                    vec![("expressions", array(exprs))],
+                )
+            }
+            NodeBase::BinaryOp(ref lhs, ref rhs, ref op) => {
+                let ty = match op {
+                    BinOp::LAnd | BinOp::LOr => "LogicalExpression",
+                    _ => "BinaryExpression",
+                };
+                self.object(
+                    ty,
+                    node,
+                    vec![
+                        ("operator", string(binop_to_str(op))),
+                        ("left", self.expression(lhs)),
+                        ("right", self.expression(rhs)),
+                    ],
+                )
+            }
+            NodeBase::TernaryOp(ref cond, ref then_, ref else_) => self.object(
+                "ConditionalExpression",
+                node,
+                vec![
+                    ("test", self.expression(cond)),
+                    ("consequent", self.expression(then_)),
+                    ("alternate", self.expression(else_)),
+                ],
+            ),
+            NodeBase::Array(ref elems) => self.object(
+                "ArrayExpression",
+                node,
+                vec![("elements", self.expressions(elems))],
+            ),
+            NodeBase::Object(ref props) => self.object(
+                "ObjectExpression",
+                node,
+                vec![(
+                    "properties",
+                    array(props.iter().map(|prop| self.property(prop)).collect()),
+                )],
+            ),
+            NodeBase::Spread(ref val) => self.object(
+                "SpreadElement",
+                node,
+                vec![("argument", self.expression(val))],
+            ),
+            NodeBase::Identifier(ref name) => {
+                self.object("Identifier", node, vec![("name", string(name))])
+            }
+            NodeBase::JSXElement(ref element) => self.jsx_element(node, element),
+            NodeBase::This => self.object("ThisExpression", node, vec![]),
+            NodeBase::Null => self.literal(node, "null".to_string()),
+            NodeBase::String(ref s) => self.literal(node, string(s)),
+            NodeBase::Boolean(b) => self.literal(node, b.to_string()),
+            NodeBase::Number(n) => self.literal(node, number(n)),
+            NodeBase::Nope => "null".to_string(),
+            _ => self.statement(node),
+        }
+    }
+
+    fn optional_expression(&self, node: &Node) -> String {
+        match node.base {
+            NodeBase::Nope => "null".to_string(),
+            _ => self.expression(node),
+        }
+    }
+
+    fn expressions(&self, nodes: &[Node]) -> String {
+        array(nodes.iter().map(|node| self.expression(node)).collect())
+    }
+
+    fn property(&self, prop: &PropertyDefinition) -> String {
+        let (key, value, kind, shorthand) = match prop {
+            PropertyDefinition::IdentifierReference(name) => {
+                (self.identifier(name), self.identifier(name), "init", true)
+            }
+            PropertyDefinition::Property(name, val) => {
+                (self.identifier(name), self.expression(val), "init", false)
+            }
+            PropertyDefinition::MethodDefinition(kind, name, f) => (
+                self.identifier(name),
+                self.expression(f),
+                match kind {
+                    MethodDefinitionKind::Get => "get",
+                    MethodDefinitionKind::Set => "set",
+                    MethodDefinitionKind::Ordinary => "init",
+                },
+                false,
+            ),
+            PropertyDefinition::SpreadObject(val) => {
+                return self
+                    .object_without_pos("SpreadElement", vec![("argument", self.expression(val))]);
+            }
+        };
+        self.object_without_pos(
+            "Property",
+            vec![
+                ("key", key),
+                ("value", value),
+                ("kind", string(kind)),
+                ("method", "false".to_string()),
+                ("shorthand", shorthand.to_string()),
+                ("computed", "false".to_string()),
+            ],
+        )
+    }
+
+    /// https://github.com/facebook/jsx/blob/master/AST.md
+    /// An element without children is written as self-closing.
+    fn jsx_element(&self, node: &Node, element: &JSXElement) -> String {
+        let children = element
+            .children
+            .iter()
+            .map(|child| match child.base {
+                NodeBase::String(ref s) => self.object(
+                    "JSXText",
+                    child,
+                    vec![("value", string(s)), ("raw", string(self.source(child)))],
+                ),
+                NodeBase::JSXElement(ref element) => self.jsx_element(child, element),
+                _ => self.jsx_expression_container(child),
+            })
+            .collect();
+
+        let name = match element.name {
+            Some(ref name) => name,
+            None => {
+                return self.object(
+                    "JSXFragment",
+                    node,
+                    vec![
+                        (
+                            "openingFragment",
+                            self.object_without_pos("JSXOpeningFragment", vec![]),
+                        ),
+                        (
+                            "closingFragment",
+                            self.object_without_pos("JSXClosingFragment", vec![]),
+                        ),
+                        ("children", array(children)),
+                    ],
+                );
+            }
+        };
+
+        let attributes = element
+            .attributes
+            .iter()
+            .map(|attribute| match attribute {
+                JSXAttribute::Attribute(name, value) => {
+                    let value = match value.base {
+                        // No value is given.
+                        NodeBase::Boolean(true) if value.end == value.pos => "null".to_string(),
+                        NodeBase::String(_) | NodeBase::JSXElement(_) => self.expression(value),
+                        _ => self.jsx_expression_container(value),
+                    };
+                    self.object_without_pos(
+                        "JSXAttribute",
+                        vec![("name", self.jsx_name(name)), ("value", value)],
+                    )
+                }
+                JSXAttribute::Spread(value) => self.object_without_pos(
+                    "JSXSpreadAttribute",
+                    vec![("argument", self.expression(value))],
+                ),
+            })
+            .collect();
+        let self_closing = element.children.is_empty();
+        let opening = self.object_without_pos(
+            "JSXOpeningElement",
+            vec![
+                ("name", self.jsx_name(name)),
+                ("attributes", array(attributes)),
+                ("selfClosing", self_closing.to_string()),
+            ],
+        );
+        let closing = if self_closing {
+            "null".to_string()
+        } else {
+            self.object_without_pos("JSXClosingElement", vec![("name", self.jsx_name(name))])
+        };
+        self.object(
+            "JSXElement",
+            node,
+            vec![
+                ("openingElement", opening),
+                ("closingElement", closing),
+                ("children", array(children)),
+            ],
+        )
+    }
+
+    fn jsx_expression_container(&self, node: &Node) -> String {
+        self.object_without_pos(
+            "JSXExpressionContainer",
+            vec![("expression", self.expression(node))],
+        )
+    }
+
+    /// ``a``, ``a:b`` or ``a.b.c``.
+    fn jsx_name(&self, name: &str) -> String {
+        let identifier =
+            |name: &str| self.object_without_pos("JSXIdentifier", vec![("name", string(name))]);
+        if let Some(i) = name.find(':') {
+            return self.object_without_pos(
+                "JSXNamespacedName",
+                vec![
+                    ("namespace", identifier(&name[..i])),
+                    ("name", identifier(&name[i + 1..])),
+                ],
+            );
+        }
+        let mut names = name.split('.');
+        let first = identifier(names.next().unwrap_or(""));
+        names.fold(first, |object, name| {
+            self.object_without_pos(
+                "JSXMemberExpression",
+                vec![("object", object), ("property", identifier(name))],
+            )
+        })
+    }
+
+    fn literal(&self, node: &Node, value: String) -> String {
+        self.object(
+            "Literal",
+            node,
+            vec![("value", value), ("raw", string(self.source(node)))],
+        )
+    }
+
+    /// An identifier for a name that has no node of its own.
+    fn identifier(&self, name: &str) -> String {
+        self.object_without_pos("Identifier", vec![("name", string(name))])
+    }
+
+    fn optional_label(&self, label: &Option<String>) -> String {
+        label
+            .as_ref()
+            .map_or("null".to_string(), |label| self.identifier(label))
+    }
+
+    fn source(&self, node: &Node) -> &str {
+        self.code.get(node.pos..node.end).unwrap_or("")
+    }
+}
+
+fn is_for(node: &Node) -> bool {
+    match node.base {
+        NodeBase::For(_, _, _, _) => true,
+        _ => false,
+    }
+}
+
+/// Variable and lexical declarations are parsed as a list of ``VarDecl``s.
+fn is_declaration_list(items: &[Node]) -> bool {
+    !items.is_empty()
+        && items.iter().all(|item| match item.base {
+            NodeBase::VarDecl(_, _, _) => true,
+            _ => false,
+        })
+}
+
+fn flatten_sequence<'a>(node: &'a Node, exprs: &mut Vec<&'a Node>) {
+    match node.base {
+        NodeBase::BinaryOp(ref lhs, ref rhs, BinOp::Comma) => {
+            flatten_sequence(lhs, exprs);
+            exprs.push(rhs);
+        }
+        _ => exprs.push(node),
+    }
+}
+
+fn binop_to_str(op: &BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+        BinOp::Rem => "%",
+        BinOp::Exp => "**",
+        BinOp::And => "&",
+        BinOp::Or => "|",
+        BinOp::Xor => "^",
+        BinOp::LAnd => "&&",
+        BinOp::LOr => "||",
+        BinOp::Eq => "==",
+        BinOp::Ne => "!=",
+        BinOp::SEq => "===",
+        BinOp::SNe => "!==",
+        BinOp::Lt => "<",
+        BinOp::Gt => ">",
+        BinOp::Le => "<=",
+        BinOp::Ge => ">=",
+        BinOp::Shl => "<<",
+        BinOp::Shr => ">>",
+        BinOp::ZFShr => ">>>",
+        BinOp::In => "in",
+        BinOp::Comma => ",",
+        BinOp::Assign => "=",
+    }
+}
+
+fn array(elems: Vec<String>) -> String {
+    format!("[{}]", elems.join(","))
+}
+
+fn number(n: f64) -> String {
+    if n.is_finite() {
+        format!("{}", n)
+    } else {
+        "null".to_string()
+    }
+}
+
+fn string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped += "\\\"",
+            '\\' => escaped += "\\\\",
+            '\n' => escaped += "\\n",
+            '\r' => escaped += "\\r",
+            '\t' => escaped += "\\t",
+            c if (c as u32) < 0x20 => escaped += &format!("\\u{:04x}", c as u32),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+fn parse_to_json(code: &str) -> String {
+    let mut parser = crate::parser::Parser::new("test", code.to_string());
+    let node = parser.parse_all().unwrap();
+    to_json(&node, &parser.lexer.code, &parser.lexer.pos_line_list)
+}
+
+#[test]
+fn estree_identifier() {
+    assert_eq!(
+        parse_to_json("x"),
+        "{\"type\":\"Program\",\"start\":0,\"end\":1,\
+         \"loc\":{\"start\":{\"line\":1,\"column\":0},\"end\":{\"line\":1,\"column\":1}},\
+         \"sourceType\":\"script\",\"body\":[\
+         {\"type\":\"ExpressionStatement\",\"start\":0,\"end\":1,\
+         \"loc\":{\"start\":{\"line\":1,\"column\":0},\"end\":{\"line\":1,\"column\":1}},\
+         \"expression\":{\"type\":\"Identifier\",\"start\":0,\"end\":1,\
+         \"loc\":{\"start\":{\"line\":1,\"column\":0},\"end\":{\"line\":1,\"column\":1}},\
+         \"name\":\"x\"}}]}"
+    );
+}
+
+#[test]
+fn estree_desugared_nodes() {
+    let json = parse_to_json("for (;;) a += 1, b++");
+    assert!(json.starts_with("{\"type\":\"Program\""));
+    assert!(json.contains("\"type\":\"ForStatement\""));
+    assert!(!json.contains("\"type\":\"BlockStatement\""));
+    assert!(json.contains("\"init\":null,\"test\":null,\"update\":null"));
+    assert!(json.contains("\"type\":\"SequenceExpression\""));
+    assert!(json.contains("\"operator\":\"+=\""));
+    assert!(json.contains("\"operator\":\"++\",\"prefix\":false"));
+
+    let json = parse_to_json("a = a + 1; let s = \"\\\"\"");
+    assert!(json.contains("\"operator\":\"=\""));
+    assert!(json.contains("\"kind\":\"let\""));
+    assert!(json.contains("\"value\":\"\\\"\""));
+}
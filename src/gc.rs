@@ -1,16 +1,22 @@
 use crate::vm::{
     constant,
     jsvalue::{function, object, prototype, value::Value},
+    limits::Limits,
+    realm::Realm,
     vm::*,
 };
 use rustc_hash::{FxHashMap, FxHashSet};
 use std::hash::{Hash, Hasher};
 use std::mem;
+use std::ptr;
 
 pub type RawPointer = *mut u8;
 pub type MarkMap = FxHashMap<GcTargetKey, MarkState>;
 pub type MarkSet = FxHashSet<GcTargetKey>;
 
+/// Byte pattern written over freed memory in the verification mode.
+pub const POISON: u8 = 0xdb;
+
 #[derive(Debug, Clone, Eq, Copy)]
 pub struct GcTargetKey(pub *mut dyn GcTarget);
 
@@ -40,6 +46,13 @@ pub struct MemoryAllocator {
     pub state: GCState,
     white: MarkState,
     counter: u32,
+    /// If true, verify the heap after every sweep, and poison freed memory instead of releasing
+    /// it. For debugging the collector.
+    pub verify: bool,
+    /// Objects freed (and poisoned) in the verification mode.
+    poisoned: MarkSet,
+    /// Checked where strings, arrays and properties are made.
+    pub limits: Limits,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -79,6 +92,9 @@ impl MemoryAllocator {
             state: GCState::Initial,
             white: MarkState::White,
             counter: 0,
+            verify: false,
+            poisoned: MarkSet::default(),
+            limits: Limits::default(),
         }
     }
 
@@ -96,6 +112,7 @@ impl MemoryAllocator {
         &mut self,
         global: LexicalEnvironmentRef,
         object_prototypes: &prototype::ObjectPrototypes,
+        realms: &[Realm],
         constant_table: &constant::ConstantTable,
         cur_context: &ExecContext,
         saved_context: &Vec<ExecContext>,
@@ -110,27 +127,20 @@ impl MemoryAllocator {
 
         self.state = match self.state {
             GCState::Initial => {
-                markset.insert(GcTargetKey(global.as_ptr()));
-                global.initial_trace(&mut markset);
-
-                cur_context.initial_trace(&mut markset);
-                cur_context.this.initial_trace(&mut markset);
-
-                object_prototypes.object.initial_trace(&mut markset);
-                object_prototypes.function.initial_trace(&mut markset);
-                object_prototypes.string.initial_trace(&mut markset);
-                object_prototypes.array.initial_trace(&mut markset);
-
-                constant_table.initial_trace(&mut markset);
-
-                for context in saved_context {
-                    context.initial_trace(&mut markset);
-                    context.this.initial_trace(&mut markset);
-                }
+                collect_roots(
+                    &mut markset,
+                    global,
+                    object_prototypes,
+                    realms,
+                    constant_table,
+                    cur_context,
+                    saved_context,
+                );
 
                 self.white = self.white.flip_white();
 
                 self.roots = &markset | &self.locked;
+                log::trace!("gc: marking from {} roots", self.roots.len());
 
                 GCState::Marking
             }
@@ -150,27 +160,94 @@ impl MemoryAllocator {
                 }
             }
             GCState::ReadyToSweep => {
-                let white = self.white;
-                let mut size = 0;
-                self.allocated_memory.retain(|obj, mark| {
-                    if mark == &MarkState::Black || mark == &MarkState::NeverReleased {
-                        *mark = white;
-                        return true;
+                self.sweep();
+
+                if self.verify {
+                    let mut roots = self.locked.clone();
+                    collect_roots(
+                        &mut roots,
+                        global,
+                        object_prototypes,
+                        realms,
+                        constant_table,
+                        cur_context,
+                        saved_context,
+                    );
+                    if let Err(msg) = self.verify_heap(roots) {
+                        panic!("GC verification failed: {}", msg);
                     }
-                    if mark == &white {
-                        return true;
-                    }
-                    size += unsafe { Box::from_raw(obj.0).free() };
-                    false
-                });
-                self.collected_size += size;
-                self.allocated_size -= size;
+                    log::trace!("gc: heap verified");
+                }
 
                 GCState::Initial
             }
         }
     }
 
+    fn sweep(&mut self) {
+        let white = self.white;
+        let verify = self.verify;
+        let poisoned = &mut self.poisoned;
+        let (mut size, mut count) = (0, 0);
+        self.allocated_memory.retain(|obj, mark| {
+            if mark == &MarkState::Black || mark == &MarkState::NeverReleased {
+                *mark = white;
+                return true;
+            }
+            if mark == &white {
+                return true;
+            }
+            if verify {
+                size += unsafe { poison(*obj) };
+                poisoned.insert(*obj);
+            } else {
+                size += unsafe { Box::from_raw(obj.0).free() };
+            }
+            count += 1;
+            false
+        });
+        self.collected_size += size;
+        self.allocated_size -= size;
+        log::debug!(
+            "gc: freed {} objects ({} bytes), {} objects ({} bytes) alive",
+            count,
+            size,
+            self.allocated_memory.len(),
+            self.allocated_size
+        );
+    }
+
+    /// Check that every object reachable from the roots is alive, and that every object is
+    /// white (i.e. not being marked) after a sweep.
+    pub fn verify_heap(&self, roots: MarkSet) -> Result<(), String> {
+        for (obj, mark) in &self.allocated_memory {
+            if mark != &self.white && mark != &MarkState::NeverReleased {
+                return Err(format!("{:?} is {:?} after sweep", obj.0 as *mut u8, mark));
+            }
+        }
+
+        let mut visited = MarkSet::default();
+        let mut worklist: Vec<GcTargetKey> = roots.into_iter().collect();
+        while let Some(obj) = worklist.pop() {
+            if !visited.insert(obj) {
+                continue;
+            }
+            // Never dereference a pointer before checking it.
+            if !self.allocated_memory.contains_key(&obj) {
+                return Err(if self.poisoned.contains(&obj) {
+                    format!("reachable object {:?} has been freed", obj.0 as *mut u8)
+                } else {
+                    format!("reachable pointer {:?} is not allocated", obj.0 as *mut u8)
+                });
+            }
+            let mut children = MarkSet::default();
+            unsafe { &*obj.0 }.initial_trace(&mut children);
+            worklist.extend(children);
+        }
+
+        Ok(())
+    }
+
     pub fn gray(&mut self, object: GcTargetKey) {
         unsafe { &*object.0 }.initial_trace(&mut self.roots);
     }
@@ -179,6 +256,18 @@ impl MemoryAllocator {
         object.initial_trace(&mut self.roots);
     }
 
+    /// Never collect the given value.
+    pub fn lock_value(&mut self, val: Value) {
+        val.initial_trace(&mut self.locked);
+        self.roots = &self.roots | &self.locked;
+    }
+
+    pub fn unlock_value(&mut self, val: Value) {
+        let mut map = MarkSet::default();
+        val.initial_trace(&mut map);
+        self.locked = &self.locked - &map;
+    }
+
     pub fn lock<T: GcTarget>(&mut self, val: T) {
         val.initial_trace(&mut self.locked);
         self.roots = &self.roots | &self.locked;
@@ -202,6 +291,85 @@ impl MemoryAllocator {
     // }
 }
 
+fn collect_roots(
+    markset: &mut MarkSet,
+    global: LexicalEnvironmentRef,
+    object_prototypes: &prototype::ObjectPrototypes,
+    realms: &[Realm],
+    constant_table: &constant::ConstantTable,
+    cur_context: &ExecContext,
+    saved_context: &Vec<ExecContext>,
+) {
+    cur_context.initial_trace(markset);
+    cur_context.this.initial_trace(markset);
+
+    collect_realm_roots(markset, global, object_prototypes);
+    for realm in realms {
+        collect_realm_roots(markset, realm.global_environment, &realm.object_prototypes);
+    }
+
+    constant_table.initial_trace(markset);
+
+    for context in saved_context {
+        context.initial_trace(markset);
+        context.this.initial_trace(markset);
+    }
+}
+
+fn collect_realm_roots(
+    markset: &mut MarkSet,
+    global: LexicalEnvironmentRef,
+    object_prototypes: &prototype::ObjectPrototypes,
+) {
+    markset.insert(GcTargetKey(global.as_ptr()));
+    global.initial_trace(markset);
+
+    // Every prototype, since the VM creates objects with them (e.g. errors) even if their
+    // constructors are unreachable. Destructured so that a new one is not forgotten.
+    let prototype::ObjectPrototypes {
+        object,
+        function,
+        string,
+        number,
+        array,
+        symbol,
+        error,
+        type_error,
+        reference_error,
+        range_error,
+        syntax_error,
+        map,
+        set,
+    } = object_prototypes;
+    for proto in &[
+        object,
+        function,
+        string,
+        number,
+        array,
+        symbol,
+        error,
+        type_error,
+        reference_error,
+        range_error,
+        syntax_error,
+        map,
+        set,
+    ] {
+        proto.initial_trace(markset);
+    }
+}
+
+/// Drop the object, but keep its memory filled with `POISON` instead of releasing it, so that the
+/// memory is never reused and a stale pointer to it can be detected.
+unsafe fn poison(obj: GcTargetKey) -> usize {
+    let size = (*obj.0).free();
+    let mem_size = mem::size_of_val(&*obj.0);
+    ptr::drop_in_place(obj.0);
+    ptr::write_bytes(obj.0 as *mut u8, POISON, mem_size);
+    size
+}
+
 pub trait GcTarget {
     fn initial_trace(&self, markset: &mut MarkSet);
     fn trace(&self, allocator: &mut MemoryAllocator, makeset: &mut MarkSet);
@@ -238,6 +406,9 @@ impl ExecContext {
             let val: Value = (*val_boxed).into();
             val.initial_trace(markset);
         }
+        for val in &self.registers {
+            val.initial_trace(markset);
+        }
     }
     /*
     fn trace(&self, allocator: &mut MemoryAllocator, markset: &mut MarkSet) {
@@ -340,16 +511,24 @@ impl Value {
 impl GcTarget for object::ObjectInfo {
     fn initial_trace(&self, markset: &mut MarkSet) {
         self.kind.initial_trace(markset);
+        self.prototype.initial_trace(markset);
         for (_, property) in &self.property {
             property.initial_trace(markset)
         }
+        for (_, property) in &self.sym_property {
+            property.initial_trace(markset)
+        }
     }
 
     fn trace(&self, allocator: &mut MemoryAllocator, markset: &mut MarkSet) {
         self.kind.trace(allocator, markset);
+        self.prototype.trace(allocator, markset);
         for (_, property) in &self.property {
             property.trace(allocator, markset)
         }
+        for (_, property) in &self.sym_property {
+            property.trace(allocator, markset)
+        }
     }
 
     fn free(&self) -> usize {
@@ -390,7 +569,19 @@ impl object::ObjectKind {
                         mark!(markset, env.as_ptr());
                     }
                 }
-                function::FunctionObjectKind::Builtin(_) => {}
+                function::FunctionObjectKind::Bound {
+                    target,
+                    this,
+                    ref args,
+                } => {
+                    target.initial_trace(markset);
+                    this.initial_trace(markset);
+                    for arg in args {
+                        arg.initial_trace(markset)
+                    }
+                }
+                function::FunctionObjectKind::Builtin(_)
+                | function::FunctionObjectKind::Native(_) => {}
             },
             object::ObjectKind::Array(ary_info) => {
                 for elem in &ary_info.elems {
@@ -399,6 +590,17 @@ impl object::ObjectKind {
             }
             object::ObjectKind::Symbol(_) => {}
             object::ObjectKind::Error(_) => {}
+            object::ObjectKind::Map(map_info) | object::ObjectKind::Set(map_info) => {
+                for (key, val) in map_info.iter() {
+                    key.initial_trace(markset);
+                    val.initial_trace(markset);
+                }
+            }
+            object::ObjectKind::Proxy(proxy_info) => {
+                proxy_info.target.initial_trace(markset);
+                proxy_info.handler.initial_trace(markset);
+            }
+            object::ObjectKind::PrimitiveWrapper(val) => val.initial_trace(markset),
             object::ObjectKind::Ordinary => {}
         }
     }
@@ -411,7 +613,19 @@ impl object::ObjectKind {
                         mark_if_white!(allocator, markset, env.as_ptr());
                     }
                 }
-                function::FunctionObjectKind::Builtin(_) => {}
+                function::FunctionObjectKind::Bound {
+                    target,
+                    this,
+                    ref args,
+                } => {
+                    target.trace(allocator, markset);
+                    this.trace(allocator, markset);
+                    for arg in args {
+                        arg.trace(allocator, markset)
+                    }
+                }
+                function::FunctionObjectKind::Builtin(_)
+                | function::FunctionObjectKind::Native(_) => {}
             },
             object::ObjectKind::Array(ary_info) => {
                 for elem in &ary_info.elems {
@@ -420,6 +634,17 @@ impl object::ObjectKind {
             }
             object::ObjectKind::Symbol(_) => {}
             object::ObjectKind::Error(_) => {}
+            object::ObjectKind::Map(map_info) | object::ObjectKind::Set(map_info) => {
+                for (key, val) in map_info.iter() {
+                    key.trace(allocator, markset);
+                    val.trace(allocator, markset);
+                }
+            }
+            object::ObjectKind::Proxy(proxy_info) => {
+                proxy_info.target.trace(allocator, markset);
+                proxy_info.handler.trace(allocator, markset);
+            }
+            object::ObjectKind::PrimitiveWrapper(val) => val.trace(allocator, markset),
             object::ObjectKind::Ordinary => {}
         }
     }
@@ -435,3 +660,27 @@ impl constant::ConstantTable {
         }
     }
 }
+
+#[test]
+fn verify_heap() {
+    let mut allocator = MemoryAllocator::new();
+    allocator.verify = true;
+    let string = allocator.alloc(::std::ffi::CString::new("string").unwrap());
+    let mut env = LexicalEnvironment::new_declarative(None);
+    env.set_own_value("s", Value::String(string)).unwrap();
+    let env = allocator.alloc(env);
+    let mut roots = MarkSet::default();
+    roots.insert(GcTargetKey(env));
+    assert_eq!(allocator.verify_heap(roots.clone()), Ok(()));
+
+    // Sweep with the string not marked, though it is reachable from the environment.
+    allocator.white = allocator.white.flip_white();
+    allocator
+        .allocated_memory
+        .insert(GcTargetKey(env), MarkState::Black);
+    allocator.sweep();
+    assert!(allocator
+        .verify_heap(roots)
+        .unwrap_err()
+        .contains("has been freed"));
+}
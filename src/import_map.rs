@@ -0,0 +1,253 @@
+//! Import maps, which redirect the specifiers given to ``require`` to other modules.
+//! https://github.com/WICG/import-maps
+//!
+//! ```json
+//! {
+//!   "imports": { "lodash": "./vendor/lodash.js", "lib/": "./src/lib/" },
+//!   "scopes": { "./vendor/": { "lodash": "./vendor/lodash-legacy.js" } }
+//! }
+//! ```
+//!
+//! Relative addresses and scopes are resolved against the directory of the import map.
+
+use crate::json::Json;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Module specifier map. ``None`` addresses block the specifier.
+type SpecifierMap = Vec<(String, Option<String>)>;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportMap {
+    /// Sorted so that longer specifiers come first.
+    imports: SpecifierMap,
+    /// Sorted so that longer scope prefixes come first.
+    scopes: Vec<(String, SpecifierMap)>,
+}
+
+impl ImportMap {
+    /// Read an import map from ``file_name``.
+    pub fn from_file(file_name: impl AsRef<Path>) -> Result<ImportMap, String> {
+        let path = file_name.as_ref();
+        let json = fs::read_to_string(path)
+            .map_err(|err| format!("Cannot read import map '{}': {}", path.display(), err))?;
+        let base = path
+            .canonicalize()
+            .ok()
+            .and_then(|path| path.parent().map(|dir| dir.to_path_buf()))
+            .unwrap_or_else(|| PathBuf::from("."));
+        ImportMap::parse(json.as_str(), base)
+    }
+
+    /// Parse the JSON text of an import map. Relative addresses are resolved against ``base``.
+    pub fn parse(json: &str, base: impl AsRef<Path>) -> Result<ImportMap, String> {
+        let base = base.as_ref();
+        let mut import_map = ImportMap::default();
+
+        let top_level = match Json::parse(json).map_err(|err| format!("{} in import map", err))? {
+            Json::Object(members) => members,
+            _ => return Err("Import map must be a JSON object".to_string()),
+        };
+        for (key, val) in top_level {
+            match key.as_str() {
+                "imports" => import_map.imports = specifier_map(val, "imports", base)?,
+                "scopes" => {
+                    let scopes = match val {
+                        Json::Object(scopes) => scopes,
+                        _ => return Err("Import map \"scopes\" must be a JSON object".to_string()),
+                    };
+                    for (prefix, map) in scopes {
+                        let map = specifier_map(map, prefix.as_str(), base)?;
+                        let prefix = resolve_address(prefix.as_str(), base)
+                            .ok_or_else(|| format!("Invalid scope \"{}\"", prefix))?;
+                        import_map.scopes.push((prefix, map));
+                    }
+                }
+                // Unknown top-level keys are ignored, as the standard requires.
+                _ => {}
+            }
+        }
+        import_map
+            .scopes
+            .sort_by(|(x, _), (y, _)| y.len().cmp(&x.len()));
+        Ok(import_map)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.imports.is_empty() && self.scopes.is_empty()
+    }
+
+    /// Resolve ``specifier`` required from the module ``referrer``. Return the specifier as is
+    /// if no entry matches it, or an error message if the import map blocks it.
+    /// https://wicg.github.io/import-maps/#resolve-a-module-specifier
+    pub fn resolve(&self, specifier: &str, referrer: Option<&str>) -> Result<String, String> {
+        if let Some(referrer) = referrer {
+            for (prefix, map) in &self.scopes {
+                if referrer == prefix || prefix.ends_with('/') && referrer.starts_with(prefix) {
+                    if let Some(resolved) = resolve_imports_match(specifier, map)? {
+                        return Ok(resolved);
+                    }
+                }
+            }
+        }
+        Ok(resolve_imports_match(specifier, &self.imports)?
+            .unwrap_or_else(|| specifier.to_string()))
+    }
+}
+
+/// https://wicg.github.io/import-maps/#resolve-an-imports-match
+fn resolve_imports_match(specifier: &str, map: &SpecifierMap) -> Result<Option<String>, String> {
+    for (key, address) in map {
+        let after_prefix = if key == specifier {
+            ""
+        } else if key.ends_with('/') && specifier.starts_with(key.as_str()) {
+            &specifier[key.len()..]
+        } else {
+            continue;
+        };
+        return match address {
+            Some(address) => Ok(Some(format!("{}{}", address, after_prefix))),
+            None => Err(format!(
+                "Module specifier \"{}\" is blocked by the import map",
+                specifier
+            )),
+        };
+    }
+    Ok(None)
+}
+
+/// https://wicg.github.io/import-maps/#sort-and-normalize-a-specifier-map
+fn specifier_map(val: Json, name: &str, base: &Path) -> Result<SpecifierMap, String> {
+    let members = match val {
+        Json::Object(members) => members,
+        _ => return Err(format!("Import map \"{}\" must be a JSON object", name)),
+    };
+    let mut map = vec![];
+    for (specifier, address) in members {
+        let address = match address {
+            Json::Null => None,
+            Json::String(address) => {
+                let resolved = resolve_address(address.as_str(), base).ok_or_else(|| {
+                    format!("Invalid address \"{}\" for \"{}\"", address, specifier)
+                })?;
+                if specifier.ends_with('/') && !resolved.ends_with('/') {
+                    return Err(format!(
+                        "Address \"{}\" for \"{}\" must end with '/'",
+                        address, specifier
+                    ));
+                }
+                Some(resolved)
+            }
+            _ => {
+                return Err(format!(
+                    "Address for \"{}\" must be a string or null",
+                    specifier
+                ));
+            }
+        };
+        map.push((specifier, address));
+    }
+    map.sort_by(|(x, _), (y, _)| y.len().cmp(&x.len()));
+    Ok(map)
+}
+
+/// Resolve a relative path against ``base``. URLs and absolute paths are used as they are.
+/// Bare names are not valid addresses.
+fn resolve_address(address: &str, base: &Path) -> Option<String> {
+    if address.starts_with('/') || is_url(address) {
+        return Some(address.to_string());
+    }
+    if !address.starts_with("./") && !address.starts_with("../") {
+        return None;
+    }
+
+    let mut path = base.to_path_buf();
+    for component in Path::new(address).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                path.pop();
+            }
+            component => path.push(component),
+        }
+    }
+    let mut resolved = path.to_string_lossy().into_owned();
+    if address.ends_with('/') && !resolved.ends_with('/') {
+        resolved.push('/');
+    }
+    Some(resolved)
+}
+
+fn is_url(address: &str) -> bool {
+    match address.find(':') {
+        Some(pos) if pos > 1 => address[..pos]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.'),
+        _ => false,
+    }
+}
+
+#[test]
+fn import_map() {
+    let import_map = ImportMap::parse(
+        r#"{
+            "imports": {
+                "lodash": "./vendor/lodash.js",
+                "lodash/": "./vendor/lodash/",
+                "lib/": "../lib/",
+                "cdn": "https://example.com/cdn.js",
+                "blocked": null
+            },
+            "scopes": {
+                "./vendor/": { "lodash": "./vendor/lodash-legacy.js" },
+                "./vendor/old/": { "lodash": "/opt/lodash.js" }
+            },
+            "unknown": [1, true, "A"]
+        }"#,
+        "/app",
+    )
+    .unwrap();
+
+    let resolve = |specifier, referrer| import_map.resolve(specifier, referrer);
+    assert_eq!(
+        resolve("lodash", None),
+        Ok("/app/vendor/lodash.js".to_string())
+    );
+    assert_eq!(
+        resolve("lodash/map", None),
+        Ok("/app/vendor/lodash/map".to_string())
+    );
+    assert_eq!(resolve("lib/a", None), Ok("/lib/a".to_string()));
+    assert_eq!(
+        resolve("cdn", None),
+        Ok("https://example.com/cdn.js".to_string())
+    );
+    assert_eq!(resolve("./local", None), Ok("./local".to_string()));
+    assert!(resolve("blocked", None).is_err());
+
+    assert_eq!(
+        resolve("lodash", Some("/app/vendor/a.js")),
+        Ok("/app/vendor/lodash-legacy.js".to_string())
+    );
+    assert_eq!(
+        resolve("lodash", Some("/app/vendor/old/a.js")),
+        Ok("/opt/lodash.js".to_string())
+    );
+    // Entries missing in a scope fall back to the top-level imports.
+    assert_eq!(
+        resolve("lodash/map", Some("/app/vendor/a.js")),
+        Ok("/app/vendor/lodash/map".to_string())
+    );
+    assert_eq!(
+        resolve("lodash", Some("/app/main.js")),
+        Ok("/app/vendor/lodash.js".to_string())
+    );
+
+    assert!(ImportMap::parse("[]", "/").is_err());
+    assert!(ImportMap::parse(r#"{"imports": {"a": "a.js"}}"#, "/").is_err());
+    assert!(ImportMap::parse(r#"{"imports": {"a/": "./a"}}"#, "/").is_err());
+    assert!(ImportMap::parse(r#"{"imports": {"a": 1}}"#, "/").is_err());
+    assert!(ImportMap::parse(r#"{"imports": {"#, "/").is_err());
+    assert!(ImportMap::parse("{} {}", "/").is_err());
+    assert!(ImportMap::parse("{}", "/").unwrap().is_empty());
+}
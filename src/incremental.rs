@@ -0,0 +1,192 @@
+//! Incremental re-parsing for editors. After an edit, only the top-level statements around it
+//! are lexed and parsed again, and the other statements of the previous tree are kept with
+//! their positions shifted, so that a large file is not parsed again on each keystroke.
+//!
+//! The result is the tree a full parse of the edited script would give, including the node
+//! ids. Where that can not be guaranteed from the statements around the edit, e.g. when the
+//! edit joins a statement with the next one, the whole script is parsed again. Early errors
+//! between statements, such as a redeclaration of a variable declared in a kept statement,
+//! are not detected.
+
+use crate::node::{Node, NodeBase};
+use crate::parser::{Error, Parser};
+
+/// Replacement of the bytes ``start..end`` of the script with ``text``.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(start: usize, end: usize, text: impl Into<String>) -> TextEdit {
+        TextEdit {
+            start,
+            end,
+            text: text.into(),
+        }
+    }
+
+    pub fn apply(&self, code: &str) -> String {
+        format!("{}{}{}", &code[..self.start], self.text, &code[self.end..])
+    }
+
+    /// How much the positions after the edit move.
+    fn delta(&self) -> isize {
+        self.text.len() as isize - (self.end - self.start) as isize
+    }
+}
+
+impl Parser {
+    /// Apply ``edit`` to the script this parser parsed into ``script``, and parse the edited
+    /// script. Returns the parser of the edited script, which positions in the tree and errors
+    /// refer to, and the tree or the syntax error.
+    pub fn reparse(&self, script: Node, edit: &TextEdit) -> (Parser, Result<Node, Error>) {
+        let code = edit.apply(&self.lexer.code);
+        let mut parser = self.reparser(code.clone());
+        let node = match reparse_statements(self, script, edit, &code) {
+            Some(node) => Ok(node),
+            None => parser.parse_all(),
+        };
+        (parser, node)
+    }
+
+    /// A parser with the options of this one, for ``code``.
+    fn reparser(&self, code: String) -> Parser {
+        let mut parser =
+            Parser::new(self.file_name.clone(), code).with_options(self.options.clone());
+        parser.lexer.collect_comments = self.lexer.collect_comments;
+        parser
+    }
+}
+
+/// Parse the statements around ``edit`` in the edited script ``code``, and splice them into
+/// ``script``. None if the whole script must be parsed instead.
+fn reparse_statements(parser: &Parser, script: Node, edit: &TextEdit, code: &str) -> Option<Node> {
+    let (root_pos, root_end, mut stmts) = match script.base {
+        NodeBase::StatementList(stmts) => (script.pos, script.end, stmts),
+        _ => return None,
+    };
+    let old_len = parser.lexer.code.len();
+    // Each statement owns the source from its leading comments to the next statement.
+    let starts: Vec<usize> = stmts.iter().map(source_start).collect();
+    let end_of = |idx: usize| *starts.get(idx + 1).unwrap_or(&old_len);
+    let first = (0..stmts.len()).find(|idx| end_of(*idx) >= edit.start)?;
+    let last = (0..stmts.len())
+        .rev()
+        .find(|idx| starts[*idx] <= edit.end)?;
+    if last < first {
+        return None;
+    }
+    // The statements next to the edited ones are parsed again too, and must not change, so
+    // that the boundaries with the kept statements are the same as in the previous tree.
+    let lo = first.saturating_sub(1);
+    let hi = ::std::cmp::min(last + 1, stmts.len() - 1);
+    let region_start = if lo == 0 { 0 } else { starts[lo] };
+    let region_end = (end_of(hi) as isize + edit.delta()) as usize;
+
+    let mut region = parser
+        .reparser(code[region_start..region_end].to_string())
+        .parse_all()
+        .ok()?;
+    shift(&mut region, region_start as isize);
+    let (region_stmts, region_pos, region_end) = match region.base {
+        NodeBase::StatementList(stmts) => (stmts, region.pos, region.end),
+        _ => return None,
+    };
+    let mut kept: Vec<Node> = stmts.drain(hi + 1..).collect();
+    for stmt in &mut kept {
+        shift(stmt, edit.delta());
+    }
+    let mut old_region: Vec<Node> = stmts.drain(lo..).collect();
+    if hi > last {
+        shift(old_region.last_mut().unwrap(), edit.delta());
+        if region_stmts.last() != old_region.last() {
+            return None;
+        }
+    }
+    if lo < first && region_stmts.first() != old_region.first() {
+        return None;
+    }
+
+    let pos = if lo == 0 { region_pos } else { root_pos };
+    let end = if kept.is_empty() {
+        region_end
+    } else {
+        (root_end as isize + edit.delta()) as usize
+    };
+    stmts.extend(region_stmts);
+    stmts.extend(kept);
+    let mut node = Node::new_span(NodeBase::StatementList(stmts), pos, end);
+    node.assign_ids(1);
+    Some(node)
+}
+
+/// Where the source of the statement ``stmt``, including its leading comments, starts.
+fn source_start(stmt: &Node) -> usize {
+    match stmt.comments.first() {
+        Some(comment) if comment.pos < stmt.start() => comment.pos,
+        _ => stmt.start(),
+    }
+}
+
+/// Move ``node`` and all the nodes and comments inside it by ``delta``.
+fn shift(node: &mut Node, delta: isize) {
+    let by = |pos: usize| (pos as isize + delta) as usize;
+    // Not recursive, so that deeply nested nodes can be moved.
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        node.pos = by(node.pos);
+        node.end = by(node.end);
+        for comment in &mut node.comments {
+            comment.pos = by(comment.pos);
+            comment.end = by(comment.end);
+        }
+        stack.extend(node.children_mut());
+    }
+}
+
+#[test]
+fn reparse() {
+    /// Reparse ``code`` after the edit, and compare the result with a full parse.
+    fn check(code: &str, edit: TextEdit) {
+        let mut parser = Parser::new("test", code).with_comments();
+        let script = parser.parse_all().unwrap();
+        let (parser, node) = parser.reparse(script, &edit);
+        assert_eq!(parser.lexer.code, edit.apply(code));
+        let expected = Parser::new("test", edit.apply(code))
+            .with_comments()
+            .parse_all();
+        assert_eq!(format!("{:?}", node), format!("{:?}", expected));
+    }
+
+    let code = "var a = 1\n// f\nfunction f(x) {\n  return x + a\n}\nvar b = f(2) // two\nb";
+    // In a function body.
+    let pos = code.find("x + a").unwrap();
+    check(code, TextEdit::new(pos, pos + 1, "(x * 2)"));
+    // Joining the last statement to the previous one, and splitting it.
+    let pos = code.rfind("\nb").unwrap();
+    check(code, TextEdit::new(pos, pos + 1, " + "));
+    check(code, TextEdit::new(pos, pos, ";\nb;"));
+    // At both ends, and across statements.
+    check(code, TextEdit::new(0, 0, "/* header */ "));
+    check(code, TextEdit::new(code.len(), code.len(), "\n(b)"));
+    check(code, TextEdit::new(4, code.len() - 1, "c = 0; c"));
+    // Syntax errors are reported at their position in the edited script.
+    check(code, TextEdit::new(pos, pos, "\n)"));
+}
+
+#[test]
+fn reparse_region() {
+    let code = "var a = 1\nvar b = 2\nvar c = 3\nvar d = 4\nvar e = 5";
+    let reparsed = |edit: TextEdit| {
+        let mut parser = Parser::new("test", code);
+        let script = parser.parse_all().unwrap();
+        reparse_statements(&parser, script, &edit, &edit.apply(code)).is_some()
+    };
+    let pos = code.find('3').unwrap();
+    assert!(reparsed(TextEdit::new(pos, pos + 1, "30")));
+    // An unterminated comment reaches beyond the statements around the edit.
+    assert!(!reparsed(TextEdit::new(pos, pos, "/*")));
+}
@@ -0,0 +1,661 @@
+//! JSON for the tooling around the engine, e.g. import maps and the language server, and for
+//! ``JSON.parse``. ``JsonReader`` reads JSON text incrementally into a sequence of events,
+//! without recursion, so that neither deeply nested nor huge documents need a deep native stack
+//! or the whole text in memory.
+//! https://tools.ietf.org/html/rfc8259
+
+use crate::vm::jsvalue::value::number_to_str;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Members in the order they appear. A later duplicate key overrides the earlier one.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut reader = JsonReader::new();
+        reader.feed(text);
+        reader.finish();
+        // The open arrays and objects, with the key of the next member of objects.
+        let mut stack: Vec<(Json, Option<String>)> = vec![];
+        let mut result = None;
+        while let Some(event) = reader.next_event()? {
+            let val = match event {
+                Event::StartObject => {
+                    stack.push((Json::Object(vec![]), None));
+                    continue;
+                }
+                Event::StartArray => {
+                    stack.push((Json::Array(vec![]), None));
+                    continue;
+                }
+                Event::Key(key) => {
+                    stack.last_mut().unwrap().1 = Some(key);
+                    continue;
+                }
+                Event::EndObject | Event::EndArray => stack.pop().unwrap().0,
+                Event::Scalar(val) => val,
+            };
+            match stack.last_mut() {
+                Some((Json::Object(members), key)) => {
+                    let key = key.take().unwrap();
+                    members.retain(|(k, _)| *k != key);
+                    members.push((key, val));
+                }
+                Some((Json::Array(elems), _)) => elems.push(val),
+                Some(_) => unreachable!(),
+                None => result = Some(val),
+            }
+        }
+        // The reader gives an error instead of the end if there is no value.
+        Ok(result.unwrap())
+    }
+
+    /// An object with the members ``members``.
+    pub fn object(members: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(key, val)| (key.to_string(), val))
+                .collect(),
+        )
+    }
+
+    /// The member ``key`` of an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(elems) => Some(elems.as_slice()),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Json {
+        Json::String(s)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(n: f64) -> Json {
+        Json::Number(n)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Json {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Json {
+        Json::Bool(b)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(elems: Vec<Json>) -> Json {
+        Json::Array(elems)
+    }
+}
+
+/// Write the value without whitespace.
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) if n.is_finite() => write!(f, "{}", number_to_str(*n)),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(elems) => {
+                write!(f, "[")?;
+                for (i, elem) in elems.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", elem)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, val)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", val)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// A part of a JSON text. ``JsonReader`` gives them in the order of the text.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    StartObject,
+    /// The key of the next member of the innermost object.
+    Key(String),
+    EndObject,
+    StartArray,
+    EndArray,
+    /// Null, a boolean, a number or a string.
+    Scalar(Json),
+}
+
+/// What the reader expects next.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    /// A value at the top level, after ':' or after ',' in an array.
+    Value,
+    /// A value or ']' after '['.
+    FirstElement,
+    /// A key or '}' after '{'.
+    FirstKey,
+    /// A key after ',' in an object.
+    Key,
+    Colon,
+    /// ',' or the end of the innermost array or object, after a value.
+    AfterValue,
+}
+
+/// Reads JSON text given in chunks, e.g. as it is received, into events. Only the text not
+/// read yet is kept, and nesting is kept in a stack instead of by recursion.
+#[derive(Clone, Debug)]
+pub struct JsonReader {
+    /// The text given and not read yet, from ``pos``.
+    buf: String,
+    pos: usize,
+    /// The number of bytes dropped from the start of ``buf``.
+    dropped: usize,
+    finished: bool,
+    /// Whether each open container is an object rather than an array.
+    stack: Vec<bool>,
+    state: State,
+    max_depth: usize,
+    multiple_values: bool,
+}
+
+impl Default for JsonReader {
+    fn default() -> Self {
+        JsonReader::new()
+    }
+}
+
+impl JsonReader {
+    pub fn new() -> JsonReader {
+        JsonReader {
+            buf: String::new(),
+            pos: 0,
+            dropped: 0,
+            finished: false,
+            stack: vec![],
+            state: State::Value,
+            max_depth: ::std::usize::MAX,
+            multiple_values: false,
+        }
+    }
+
+    /// Limit the nesting of arrays and objects to ``max_depth``.
+    pub fn with_max_depth(mut self, max_depth: usize) -> JsonReader {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Accept a sequence of values separated by whitespace, e.g. JSON Lines, instead of one.
+    pub fn with_multiple_values(mut self) -> JsonReader {
+        self.multiple_values = true;
+        self
+    }
+
+    /// Append ``chunk`` to the text.
+    pub fn feed(&mut self, chunk: &str) {
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.dropped += self.pos;
+            self.pos = 0;
+        }
+        self.buf.push_str(chunk);
+    }
+
+    /// Tell that all the text was given.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// The number of arrays and objects the last event is in.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Read the next event. None if the text given so far is all read, which is the end of the
+    /// text after ``finish``.
+    pub fn next_event(&mut self) -> Result<Option<Event>, String> {
+        loop {
+            while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') =
+                self.buf.as_bytes().get(self.pos)
+            {
+                self.pos += 1;
+            }
+            let c = match self.buf.as_bytes().get(self.pos) {
+                Some(c) => *c,
+                None if !self.finished => return Ok(None),
+                None if self.stack.is_empty() && self.state == State::AfterValue => {
+                    return Ok(None);
+                }
+                None if self.stack.is_empty() && self.multiple_values => return Ok(None),
+                None => return Err(self.error()),
+            };
+            let in_object = self.stack.last() == Some(&true);
+            match self.state {
+                State::AfterValue if self.stack.is_empty() => {
+                    if !self.multiple_values {
+                        return Err(self.error());
+                    }
+                    self.state = State::Value;
+                }
+                State::AfterValue => {
+                    self.pos += 1;
+                    match c {
+                        b',' if in_object => self.state = State::Key,
+                        b',' => self.state = State::Value,
+                        b'}' if in_object => return Ok(Some(self.end(Event::EndObject))),
+                        b']' if !in_object => return Ok(Some(self.end(Event::EndArray))),
+                        _ => {
+                            self.pos -= 1;
+                            return Err(self.error());
+                        }
+                    }
+                }
+                State::FirstKey if c == b'}' => {
+                    self.pos += 1;
+                    return Ok(Some(self.end(Event::EndObject)));
+                }
+                State::FirstKey | State::Key if c == b'"' => {
+                    return Ok(self.string()?.map(|key| {
+                        self.state = State::Colon;
+                        Event::Key(key)
+                    }));
+                }
+                State::Colon if c == b':' => {
+                    self.pos += 1;
+                    self.state = State::Value;
+                }
+                State::FirstElement if c == b']' => {
+                    self.pos += 1;
+                    return Ok(Some(self.end(Event::EndArray)));
+                }
+                State::Value | State::FirstElement => return self.value(c),
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    /// Read the value starting with ``c``.
+    fn value(&mut self, c: u8) -> Result<Option<Event>, String> {
+        let scalar = match c {
+            b'{' | b'[' => {
+                if self.stack.len() >= self.max_depth {
+                    return Err(format!(
+                        "JSON nested too deeply at position {}",
+                        self.dropped + self.pos
+                    ));
+                }
+                self.pos += 1;
+                self.stack.push(c == b'{');
+                return Ok(Some(if c == b'{' {
+                    self.state = State::FirstKey;
+                    Event::StartObject
+                } else {
+                    self.state = State::FirstElement;
+                    Event::StartArray
+                }));
+            }
+            b'"' => self.string()?.map(Json::String),
+            b'-' | b'0'..=b'9' => self.number()?,
+            _ => self.keyword()?,
+        };
+        Ok(scalar.map(|val| {
+            self.state = State::AfterValue;
+            Event::Scalar(val)
+        }))
+    }
+
+    /// Close the innermost container.
+    fn end(&mut self, event: Event) -> Event {
+        self.stack.pop();
+        self.state = State::AfterValue;
+        event
+    }
+
+    /// None if the token at ``pos`` may continue in the text not given yet.
+    fn incomplete<T>(&self) -> Result<Option<T>, String> {
+        if self.finished {
+            Err("Unexpected end of JSON".to_string())
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn error(&self) -> String {
+        self.error_at(self.pos)
+    }
+
+    fn error_at(&self, pos: usize) -> String {
+        match self.buf[pos..].chars().next() {
+            Some(c) => format!("Unexpected '{}' at position {}", c, self.dropped + pos),
+            None => "Unexpected end of JSON".to_string(),
+        }
+    }
+
+    fn string(&mut self) -> Result<Option<String>, String> {
+        let start = self.pos + 1;
+        let mut s = String::new();
+        let mut chars = self.buf[start..].char_indices();
+        let hex4 = |chars: &mut ::std::str::CharIndices| -> Option<Option<u32>> {
+            let mut code = 0;
+            for _ in 0..4 {
+                match chars.next() {
+                    Some((_, c)) => code = code * 16 + c.to_digit(16)?,
+                    None => return Some(None),
+                }
+            }
+            Some(Some(code))
+        };
+        loop {
+            let (i, c) = match chars.next() {
+                Some(next) => next,
+                None => return self.incomplete(),
+            };
+            match c {
+                '"' => {
+                    self.pos = start + i + 1;
+                    return Ok(Some(s));
+                }
+                '\\' => {
+                    let c = match chars.next() {
+                        Some((_, c)) => c,
+                        None => return self.incomplete(),
+                    };
+                    match c {
+                        '"' | '\\' | '/' => s.push(c),
+                        'b' => s.push('\u{8}'),
+                        'f' => s.push('\u{c}'),
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'u' => {
+                            let mut code = match hex4(&mut chars) {
+                                Some(Some(code)) => code,
+                                Some(None) => return self.incomplete(),
+                                None => return Err(self.error_at(start + i)),
+                            };
+                            // A surrogate pair. Lone surrogates become U+FFFD, since strings
+                            // are UTF-8 in this engine.
+                            if (0xd800..0xdc00).contains(&code) {
+                                if chars.as_str().len() < 6 && !self.finished {
+                                    return self.incomplete();
+                                }
+                                if chars.as_str().starts_with("\\u") {
+                                    let mut lookahead = chars.clone();
+                                    lookahead.next();
+                                    lookahead.next();
+                                    if let Some(Some(low)) = hex4(&mut lookahead) {
+                                        if (0xdc00..0xe000).contains(&low) {
+                                            code =
+                                                0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                                            chars = lookahead;
+                                        }
+                                    }
+                                }
+                            }
+                            s.push(::std::char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        _ => return Err(self.error_at(start + i)),
+                    }
+                }
+                c if (c as u32) < 0x20 => return Err(self.error_at(start + i)),
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Option<Json>, String> {
+        let rest = &self.buf.as_bytes()[self.pos..];
+        let len = rest
+            .iter()
+            .position(|c| !b"+-.eE0123456789".contains(c))
+            .unwrap_or(rest.len());
+        if len == rest.len() && !self.finished {
+            return Ok(None);
+        }
+        let text = &self.buf[self.pos..self.pos + len];
+        match number_len(text.as_bytes()) {
+            Some(len) if len == text.len() => {
+                self.pos += len;
+                Ok(Some(Json::Number(text.parse().unwrap())))
+            }
+            Some(len) => Err(self.error_at(self.pos + len)),
+            None => Err(self.error()),
+        }
+    }
+
+    fn keyword(&mut self) -> Result<Option<Json>, String> {
+        let rest = &self.buf[self.pos..];
+        for (keyword, val) in &[
+            ("null", Json::Null),
+            ("true", Json::Bool(true)),
+            ("false", Json::Bool(false)),
+        ] {
+            if rest.starts_with(keyword) {
+                self.pos += keyword.len();
+                return Ok(Some(val.clone()));
+            }
+            if keyword.starts_with(rest) {
+                return self.incomplete();
+            }
+        }
+        Err(self.error())
+    }
+}
+
+/// The length of the longest prefix of ``text`` that is a JSON number, or None if there is
+/// none.
+fn number_len(text: &[u8]) -> Option<usize> {
+    let digits = |from: usize| {
+        text[from..]
+            .iter()
+            .position(|c| !c.is_ascii_digit())
+            .map_or(text.len(), |len| from + len)
+    };
+    let mut pos = if text.first() == Some(&b'-') { 1 } else { 0 };
+    pos = match text.get(pos) {
+        Some(b'0') => pos + 1,
+        Some(b'1'..=b'9') => digits(pos),
+        _ => return None,
+    };
+    if text.get(pos) == Some(&b'.') && digits(pos + 1) > pos + 1 {
+        pos = digits(pos + 1);
+    }
+    if let Some(b'e') | Some(b'E') = text.get(pos) {
+        let sign = match text.get(pos + 1) {
+            Some(b'+') | Some(b'-') => 1,
+            _ => 0,
+        };
+        if digits(pos + 1 + sign) > pos + 1 + sign {
+            pos = digits(pos + 1 + sign);
+        }
+    }
+    Some(pos)
+}
+
+#[test]
+fn json() {
+    let text = r#"{"a": [1, -2.5e1, true, null], "b": "\"x\"\né😀", "a": {}}"#;
+    let val = Json::parse(text).unwrap();
+    assert_eq!(val.get("a"), Some(&Json::Object(vec![])));
+    assert_eq!(
+        val.get("b").and_then(Json::as_str),
+        Some("\"x\"\n\u{e9}\u{1f600}")
+    );
+    assert_eq!(
+        Json::parse(&val.to_string()).unwrap(),
+        Json::object(vec![
+            ("b", val.get("b").unwrap().clone()),
+            ("a", Json::object(vec![]))
+        ])
+    );
+    let val = Json::parse("[1, -2.5e1, true, null]").unwrap();
+    assert_eq!(
+        val,
+        Json::Array(vec![
+            Json::Number(1.0),
+            Json::Number(-25.0),
+            Json::Bool(true),
+            Json::Null
+        ])
+    );
+    assert_eq!(val.to_string(), "[1,-25,true,null]");
+    assert_eq!(Json::Number(1e21).to_string(), "1e+21");
+    assert_eq!(Json::parse("[1,").unwrap_err(), "Unexpected end of JSON");
+    assert_eq!(
+        Json::parse("{} x").unwrap_err(),
+        "Unexpected 'x' at position 3"
+    );
+    assert!(Json::parse("1-").is_err());
+    assert!(Json::parse("01").is_err());
+    assert!(Json::parse("\"\t\"").is_err());
+    assert_eq!(
+        Json::parse(r#""\ud83d\ude00\ud800""#),
+        Ok(Json::String("\u{1f600}\u{fffd}".to_string()))
+    );
+    let deep = "[".repeat(100_000) + &"]".repeat(100_000);
+    let mut reader = JsonReader::new();
+    reader.feed(&deep);
+    reader.finish();
+    while reader.next_event().unwrap().is_some() {}
+    let mut reader = JsonReader::new().with_max_depth(2);
+    reader.feed("[[[]]]");
+    assert_eq!(reader.next_event(), Ok(Some(Event::StartArray)));
+    assert_eq!(reader.next_event(), Ok(Some(Event::StartArray)));
+    assert!(reader.next_event().is_err());
+}
+
+#[test]
+fn json_reader() {
+    let mut reader = JsonReader::new();
+    let mut events = vec![];
+    // Tokens split across the chunks.
+    let chunks = [
+        "[1",
+        "2, tr",
+        r#"ue, "a\"#,
+        "u00e9",
+        r#"""#,
+        r#", {"k""#,
+        ":",
+        "-1e",
+        "3}]",
+    ];
+    for chunk in &chunks {
+        reader.feed(chunk);
+        while let Some(event) = reader.next_event().unwrap() {
+            events.push(event);
+        }
+    }
+    reader.finish();
+    assert_eq!(reader.next_event(), Ok(None));
+    assert_eq!(
+        events,
+        [
+            Event::StartArray,
+            Event::Scalar(Json::Number(12.0)),
+            Event::Scalar(Json::Bool(true)),
+            Event::Scalar(Json::String("a\u{e9}".to_string())),
+            Event::StartObject,
+            Event::Key("k".to_string()),
+            Event::Scalar(Json::Number(-1000.0)),
+            Event::EndObject,
+            Event::EndArray,
+        ]
+    );
+
+    let mut reader = JsonReader::new().with_multiple_values();
+    reader.feed("1\n{}\n");
+    reader.finish();
+    let mut events = vec![];
+    while let Some(event) = reader.next_event().unwrap() {
+        events.push(event);
+    }
+    assert_eq!(
+        events,
+        [
+            Event::Scalar(Json::Number(1.0)),
+            Event::StartObject,
+            Event::EndObject
+        ]
+    );
+    let mut reader = JsonReader::new();
+    reader.feed("[1");
+    reader.finish();
+    assert_eq!(reader.next_event(), Ok(Some(Event::StartArray)));
+    assert_eq!(
+        reader.next_event(),
+        Ok(Some(Event::Scalar(Json::Number(1.0))))
+    );
+    assert!(reader.next_event().is_err());
+}
@@ -1,5 +1,5 @@
 use crate::parser::Error;
-use crate::token::{convert_reserved_keyword, Kind, Symbol, Token};
+use crate::token::{convert_reserved_keyword, Comment, Kind, Symbol, Token};
 
 use std::collections::VecDeque;
 
@@ -32,6 +32,32 @@ pub struct Lexer {
 
     /// Saved states
     pub states: Vec<usize>,
+
+    /// If true, collect comments in ``comments`` while tokenizing.
+    pub collect_comments: bool,
+
+    /// If false, legacy octal literals like ``010`` are syntax errors.
+    pub legacy_octal: bool,
+
+    /// If true, tokenize JSX. Only takes effect with the ``jsx`` feature.
+    pub jsx: bool,
+
+    /// Where the lexer is in nested JSX elements.
+    #[cfg(feature = "jsx")]
+    jsx_modes: Vec<JSXMode>,
+
+    pub comments: Vec<Comment>,
+}
+
+#[cfg(feature = "jsx")]
+#[derive(Clone, Debug, PartialEq)]
+enum JSXMode {
+    /// In an opening tag, or in a closing tag if ``closing`` is true.
+    Tag { closing: bool },
+    /// Between the opening and the closing tags.
+    Children,
+    /// In ``{}`` in a tag or children, with the number of braces open in the expression.
+    Expression(usize),
 }
 
 impl Lexer {
@@ -45,6 +71,12 @@ impl Lexer {
             token_pos: 0,
             prev_token_pos: 0,
             states: vec![],
+            collect_comments: false,
+            legacy_octal: true,
+            jsx: false,
+            #[cfg(feature = "jsx")]
+            jsx_modes: vec![],
+            comments: vec![],
         }
     }
 
@@ -52,7 +84,10 @@ impl Lexer {
     pub fn tokenize_all(&mut self) -> Result<(), Error> {
         loop {
             match self.tokenize() {
-                Ok(tok) => self.buf.push_back(tok),
+                Ok(mut tok) => {
+                    tok.end = self.pos;
+                    self.buf.push_back(tok)
+                }
                 Err(Error::NormalEOF) => break,
                 Err(err) => {
                     // When error occurs in tokenizer, pos_line_list is not completed.
@@ -66,10 +101,27 @@ impl Lexer {
 
         self.pos_line_list.push((self.pos + 1, self.line + 1));
 
+        // Count columns incrementally, as a line of minified code may have a lot of tokens.
         let mut prev_pos = 0;
+        let mut line_index = 0;
+        let (mut column_pos, mut column) = (0, 1);
         for mut tok in &mut self.buf {
             tok.prev_pos = prev_pos;
             prev_pos = tok.pos;
+            while line_index + 1 < self.pos_line_list.len()
+                && self.pos_line_list[line_index + 1].0 <= tok.pos
+            {
+                line_index += 1;
+                column_pos = self.pos_line_list[line_index].0;
+                column = 1;
+            }
+            column += self
+                .code
+                .get(column_pos..tok.pos)
+                .map_or(tok.pos - column_pos, |s| s.chars().count());
+            column_pos = tok.pos;
+            tok.line = self.pos_line_list[line_index].1;
+            tok.column = column;
         }
 
         Ok(())
@@ -168,6 +220,22 @@ impl Lexer {
         }
     }
 
+    /// Get char position in the script just after the previous token, not counting line
+    /// terminators.
+    pub fn get_prev_end(&self) -> usize {
+        self.buf
+            .iter()
+            .take(self.token_pos)
+            .rev()
+            .find(|tok| tok.kind != Kind::LineTerminator)
+            .map_or(0, |tok| tok.end)
+    }
+
+    /// Get the line and column of char position ``pos`` in the script.
+    pub fn get_line_column(&self, pos: usize) -> (usize, usize) {
+        line_column(&self.code, &self.pos_line_list, pos)
+    }
+
     /// Peek the next token and if it is ``kind``, get the next token, return true.
     /// Otherwise, return false.
     pub fn next_if(&mut self, kind: Kind) -> bool {
@@ -225,61 +293,382 @@ impl Lexer {
 impl Lexer {
     /// Tokenize and return the token
     fn tokenize(&mut self) -> Result<Token, Error> {
-        if self.starts_with("//") {
+        #[cfg(feature = "jsx")]
+        {
+            if self.jsx_modes.last() == Some(&JSXMode::Children) {
+                return self.read_jsx_child();
+            }
+        }
+
+        while let Some(tok) = self.read_trivia()? {
+            if let Kind::Comment(text) = tok.kind {
+                if self.collect_comments {
+                    self.comments.push(Comment {
+                        text,
+                        pos: tok.pos,
+                        end: self.pos,
+                    });
+                }
+            }
+        }
+
+        #[cfg(feature = "jsx")]
+        {
+            if self.jsx {
+                return self.read_non_trivia_in_jsx();
+            }
+        }
+
+        self.read_non_trivia()
+    }
+
+    /// Read a comment or whitespace other than line terminators, if any.
+    /// A hashbang comment is allowed only at the start of the script.
+    /// https://tc39.github.io/proposal-hashbang/out.html
+    fn read_trivia(&mut self) -> Result<Option<Token>, Error> {
+        let pos = self.pos;
+        if self.starts_with("//") || (pos == 0 && self.starts_with("#!")) {
             self.skip_line_comment()?;
         } else if self.starts_with("/*") {
             self.skip_normal_comment()?;
+        } else {
+            match self.peek_char()? {
+                c if c != '\n' && c.is_whitespace() => {
+                    self.skip_whitespace()?;
+                    return Ok(Some(Token::new_whitespace(pos)));
+                }
+                _ => return Ok(None),
+            }
         }
+        let comment = self.code[pos..self.pos].to_string();
+        Ok(Some(Token::new_comment(comment, pos)))
+    }
 
+    fn read_non_trivia(&mut self) -> Result<Token, Error> {
         match self.peek_char()? {
-            'a'..='z' | 'A'..='Z' | '_' | '$' => self.read_identifier(),
+            c if is_id_start(c) || c == '\\' => self.read_identifier(),
             '0'..='9' => self.read_number(),
             '\'' | '\"' => self.read_string_literal(),
             '\n' => self.read_line_terminator(),
-            c if c.is_whitespace() => {
-                self.skip_whitespace()?;
-                self.tokenize()
-            }
             _ => self.read_symbol(),
         }
     }
 }
 
+/// JSX is tokenized as follows: ``<`` where an expression can start begins a tag, where
+/// identifiers may contain ``-``. Text between tags is a ``Kind::JSXText``, and ``{}`` in
+/// a tag or children contains ordinary tokens.
+/// https://facebook.github.io/jsx/
+#[cfg(feature = "jsx")]
+impl Lexer {
+    fn read_jsx_child(&mut self) -> Result<Token, Error> {
+        let pos = self.pos;
+        match self.peek_char()? {
+            '<' => {
+                self.take_char()?;
+                self.jsx_modes.push(JSXMode::Tag { closing: false });
+                Ok(Token::new_symbol(Symbol::Lt, pos))
+            }
+            '{' => {
+                self.take_char()?;
+                self.jsx_modes.push(JSXMode::Expression(0));
+                Ok(Token::new_symbol(Symbol::OpeningBrace, pos))
+            }
+            _ => {
+                let text = self.take_char_while(|c| c != '<' && c != '{')?;
+                for (i, _) in text.match_indices('\n') {
+                    self.line += 1;
+                    self.pos_line_list.push((pos + i + 1, self.line));
+                }
+                Ok(Token::new_jsx_text(text, pos))
+            }
+        }
+    }
+
+    fn read_non_trivia_in_jsx(&mut self) -> Result<Token, Error> {
+        let pos = self.pos;
+        let closing = match self.jsx_modes.last() {
+            Some(JSXMode::Tag { closing }) => Some(*closing),
+            _ => None,
+        };
+
+        if let Some(closing) = closing {
+            match self.peek_char()? {
+                c if is_id_start(c) => {
+                    let name = self.take_char_while(|c| is_id_continue(c) || c == '-')?;
+                    return Ok(Token::new_identifier(name, pos));
+                }
+                '/' => {
+                    self.take_char()?;
+                    if let Some(Kind::Symbol(Symbol::Lt)) = self.buf.back().map(|tok| &tok.kind) {
+                        self.jsx_modes.pop();
+                        self.jsx_modes.push(JSXMode::Tag { closing: true });
+                    }
+                    return Ok(Token::new_symbol(Symbol::Div, pos));
+                }
+                '>' => {
+                    self.take_char()?;
+                    let self_closing = match self.buf.back() {
+                        Some(tok) => tok.kind == Kind::Symbol(Symbol::Div),
+                        None => false,
+                    };
+                    self.jsx_modes.pop();
+                    if closing {
+                        // Leave the children of the element.
+                        self.jsx_modes.pop();
+                    } else if !self_closing {
+                        self.jsx_modes.push(JSXMode::Children);
+                    }
+                    return Ok(Token::new_symbol(Symbol::Gt, pos));
+                }
+                _ => {}
+            }
+        }
+
+        let starts_element = self.starts_jsx_element();
+        let tok = self.read_non_trivia()?;
+        match (&tok.kind, self.jsx_modes.last_mut()) {
+            (Kind::Symbol(Symbol::Lt), _) if starts_element => {
+                self.jsx_modes.push(JSXMode::Tag { closing: false })
+            }
+            (Kind::Symbol(Symbol::OpeningBrace), Some(JSXMode::Tag { .. })) => {
+                self.jsx_modes.push(JSXMode::Expression(0))
+            }
+            (Kind::Symbol(Symbol::OpeningBrace), Some(JSXMode::Expression(depth))) => *depth += 1,
+            (Kind::Symbol(Symbol::ClosingBrace), Some(JSXMode::Expression(0))) => {
+                self.jsx_modes.pop();
+            }
+            (Kind::Symbol(Symbol::ClosingBrace), Some(JSXMode::Expression(depth))) => *depth -= 1,
+            _ => {}
+        }
+        Ok(tok)
+    }
+
+    /// Whether the next ``<`` starts an element rather than being the less-than operator, that
+    /// is, it does not follow an operand and is followed by a name or ``>``.
+    fn starts_jsx_element(&self) -> bool {
+        match self.jsx_modes.last() {
+            None | Some(JSXMode::Expression(_)) => {}
+            _ => return false,
+        }
+        let mut chars = self.code[self.pos..].chars();
+        match (chars.next(), chars.next()) {
+            (Some('<'), Some(c)) if is_id_start(c) || c == '>' => {}
+            _ => return false,
+        }
+        let prev = self
+            .buf
+            .iter()
+            .rev()
+            .find(|tok| tok.kind != Kind::LineTerminator);
+        let follows_line_terminator =
+            self.buf.back().map(|tok| &tok.kind) == Some(&Kind::LineTerminator);
+        match prev.map(|tok| &tok.kind) {
+            Some(Kind::Identifier(_))
+            | Some(Kind::Number(_))
+            | Some(Kind::String(_))
+            | Some(Kind::Keyword(crate::token::Keyword::This))
+            | Some(Kind::Symbol(Symbol::ClosingParen))
+            | Some(Kind::Symbol(Symbol::ClosingBoxBracket)) => false,
+            // ``}`` at the end of a line is more likely to close a block than an object literal.
+            Some(Kind::Symbol(Symbol::ClosingBrace)) => follows_line_terminator,
+            _ => true,
+        }
+    }
+}
+
+/// An iterator of the tokens in a script, for tooling such as syntax highlighters and
+/// formatters. Each token has its span and line/column set.
+/// Stops after the first error.
+#[derive(Clone, Debug)]
+pub struct TokenStream {
+    lexer: Lexer,
+    /// Also produce comments and whitespace as ``Kind::Comment`` and ``Kind::Whitespace``.
+    trivia: bool,
+    prev_pos: usize,
+    finished: bool,
+}
+
+impl TokenStream {
+    pub fn new(code: impl Into<String>) -> TokenStream {
+        TokenStream {
+            lexer: Lexer::new(code.into()),
+            trivia: false,
+            prev_pos: 0,
+            finished: false,
+        }
+    }
+
+    /// Also produce comments and whitespace, so that concatenating the spans of all the tokens
+    /// gives back the script.
+    pub fn with_trivia(mut self) -> TokenStream {
+        self.trivia = true;
+        self
+    }
+}
+
+impl Iterator for TokenStream {
+    type Item = Result<Token, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let lexer = &mut self.lexer;
+        let token = if self.trivia {
+            match lexer.read_trivia() {
+                Ok(Some(tok)) => Ok(tok),
+                Ok(None) => lexer.read_non_trivia(),
+                Err(err) => Err(err),
+            }
+        } else {
+            lexer.tokenize()
+        };
+
+        match token {
+            Ok(mut tok) => {
+                tok.end = lexer.pos;
+                tok.prev_pos = self.prev_pos;
+                self.prev_pos = tok.pos;
+                let (line, column) = lexer.get_line_column(tok.pos);
+                tok.line = line;
+                tok.column = column;
+                Some(Ok(tok))
+            }
+            Err(Error::NormalEOF) => {
+                self.finished = true;
+                None
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
 impl Lexer {
     fn skip_line_comment(&mut self) -> Result<(), Error> {
         self.skip_char_while(|c| c != '\n')
     }
 
     fn skip_normal_comment(&mut self) -> Result<(), Error> {
+        let start = self.pos;
         let mut last_char = ' ';
-        let mut line = self.line;
         self.skip_char_while(|c| {
-            if c == '\n' {
-                line += 1;
-            }
             let end_of_comment = last_char == '*' && c == '/';
             last_char = c;
             !end_of_comment
         })?;
-        if self.line != line {
-            self.pos_line_list.push((self.pos, line));
+        for (i, _) in self.code[start..self.pos].match_indices('\n') {
+            self.line += 1;
+            self.pos_line_list.push((start + i + 1, self.line));
         }
-        self.line = line;
         assert_eq!(self.take_char()?, '/');
         Ok(())
     }
 }
 
 impl Lexer {
+    /// https://tc39.github.io/ecma262/#sec-names-and-keywords
     fn read_identifier(&mut self) -> Result<Token, Error> {
         let pos = self.pos;
-        let ident = self.take_char_while(|c| c.is_alphanumeric() || c == '_' || c == '$')?;
+        let mut ident = "".to_string();
+        let mut has_escape = false;
+        while !self.eof() {
+            let c = match self.peek_char()? {
+                '\\' => {
+                    let escape_pos = self.pos;
+                    assert_eq!(self.take_char()?, '\\');
+                    has_escape = true;
+                    self.read_identifier_escape(escape_pos)?
+                }
+                c if is_id_continue(c) => self.take_char()?,
+                _ => break,
+            };
+            let is_valid = if ident.is_empty() {
+                is_id_start(c)
+            } else {
+                is_id_continue(c)
+            };
+            if !is_valid {
+                return Err(Error::General(
+                    pos,
+                    format!("Invalid character '{}' in identifier", c),
+                ));
+            }
+            ident.push(c);
+        }
+
         if let Some(keyword) = convert_reserved_keyword(ident.as_str()) {
+            if has_escape {
+                return Err(Error::General(
+                    pos,
+                    "Keyword must not contain escaped characters".to_string(),
+                ));
+            }
             Ok(Token::new_keyword(keyword, pos))
         } else {
             Ok(Token::new_identifier(ident, pos))
         }
     }
+
+    /// Read ``uXXXX`` or ``u{X..X}`` after a backslash in an identifier.
+    fn read_identifier_escape(&mut self, pos: usize) -> Result<char, Error> {
+        let invalid = || Error::General(pos, "Invalid Unicode escape sequence".to_string());
+        if !self.take_char_if('u')? {
+            return Err(invalid());
+        }
+        let hex = if self.take_char_if('{')? {
+            let hex = self.take_char_while(|c| c.is_ascii_hexdigit())?;
+            if !self.take_char_if('}')? {
+                return Err(invalid());
+            }
+            hex
+        } else {
+            let mut hex = "".to_string();
+            for _ in 0..4 {
+                match self.peek_char() {
+                    Ok(c) if c.is_ascii_hexdigit() => hex.push(self.take_char()?),
+                    _ => return Err(invalid()),
+                }
+            }
+            hex
+        };
+        u32::from_str_radix(hex.as_str(), 16)
+            .ok()
+            .and_then(std::char::from_u32)
+            .ok_or_else(invalid)
+    }
+}
+
+/// Approximation of the Unicode ID_Start property with the tables in std: letters and letter
+/// numbers, plus ``$`` and ``_`` as JavaScript allows.
+/// https://tc39.github.io/ecma262/#prod-IdentifierStart
+pub fn is_id_start(c: char) -> bool {
+    c == '$' || c == '_' || c.is_alphabetic()
+}
+
+/// Approximation of the Unicode ID_Continue property: ID_Start, digits, combining marks and
+/// connector punctuation, plus ZWNJ and ZWJ.
+/// https://tc39.github.io/ecma262/#prod-IdentifierPart
+pub fn is_id_continue(c: char) -> bool {
+    is_id_start(c)
+        || c.is_numeric()
+        || match c {
+            '\u{200C}' | '\u{200D}' => true,
+            // Combining marks
+            '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}' => true,
+            // Connector punctuation other than '_'
+            '\u{203F}' | '\u{2040}' | '\u{2054}' | '\u{FE33}' | '\u{FE34}' => true,
+            '\u{FE4D}'..='\u{FE4F}' | '\u{FF3F}' => true,
+            _ => false,
+        }
 }
 
 impl Lexer {
@@ -305,6 +694,12 @@ impl Lexer {
                     'x' | 'X' => kind = NumLiteralKind::Hex,
                     'b' | 'B' => kind = NumLiteralKind::Bin,
                     'o' | 'O' => kind = NumLiteralKind::Oct,
+                    '0'..='9' if !self.legacy_octal => {
+                        return Err(Error::General(
+                            pos,
+                            "Legacy octal literals are not allowed.".to_string(),
+                        ));
+                    }
                     '0'..='7' => {
                         kind = NumLiteralKind::OldOct;
                         num_literal.push(c);
@@ -645,9 +1040,9 @@ impl Lexer {
 }
 
 impl Lexer {
-    /// Skip whitespace and tabs
+    /// Skip whitespace other than line terminators
     fn skip_whitespace(&mut self) -> Result<(), Error> {
-        self.take_char_while(|c| c == ' ' || c == '\t').and(Ok(()))
+        self.skip_char_while(|c| c != '\n' && c.is_whitespace())
     }
 
     /// While ``f(char)`` is true, read chars, and move cursor next.
@@ -710,27 +1105,61 @@ impl Lexer {
 
 impl Lexer {
     pub fn get_code_around_err_point(&self, pos: usize) -> (String, usize, usize) {
-        let code = self.code.as_bytes();
-        let iter = self.pos_line_list.iter();
-        let (start_pos, line) = iter.take_while(|x| x.0 <= pos).last().unwrap();
-
-        let mut iter = self.pos_line_list.iter();
-        let end_pos = match iter
-            .find(|x| x.0 > pos)
-            .unwrap_or(self.pos_line_list.last().unwrap())
-            .0
-        {
-            x if x == 0 => 0,
-            x => x - 1,
-        };
-        let surrounding_code = String::from_utf8(code[*start_pos..end_pos].to_vec())
-            .unwrap()
-            .to_string();
-        let err_point = format!("{}{}", " ".repeat(pos - start_pos), '^',);
-        (surrounding_code + "\n" + err_point.as_str(), pos, *line)
+        code_around_err_point(&self.code, &self.pos_line_list, pos)
     }
 }
 
+/// Get the line of ``code`` at ``pos`` with ``^`` under ``pos``, ``pos`` and the line number.
+pub fn code_around_err_point(
+    code: &str,
+    pos_line_list: &[(usize, usize)],
+    pos: usize,
+) -> (String, usize, usize) {
+    let code = code.as_bytes();
+    let iter = pos_line_list.iter();
+    let (start_pos, line) = iter.take_while(|x| x.0 <= pos).last().unwrap();
+
+    let mut iter = pos_line_list.iter();
+    let end_pos = match iter
+        .find(|x| x.0 > pos)
+        .unwrap_or(pos_line_list.last().unwrap())
+        .0
+    {
+        x if x == 0 => 0,
+        x => x - 1,
+    };
+    let surrounding_code = String::from_utf8(code[*start_pos..end_pos].to_vec())
+        .unwrap()
+        .to_string();
+    let err_point = format!("{}{}", " ".repeat(pos - start_pos), '^',);
+    (surrounding_code + "\n" + err_point.as_str(), pos, *line)
+}
+
+/// The ``pos_line_list`` the lexer makes for the whole of ``code``, whose first line is
+/// ``first_line``.
+pub fn pos_line_list(code: &str, first_line: usize) -> Vec<(usize, usize)> {
+    let mut list = vec![(0, first_line)];
+    for (i, _) in code.match_indices('\n') {
+        list.push((i + 1, first_line + list.len()));
+    }
+    list.push((code.len() + 1, first_line + list.len()));
+    list
+}
+
+/// Get the 1-based line and column of ``pos`` in ``code`` from the list of line start
+/// positions. Columns are counted in chars.
+pub fn line_column(code: &str, pos_line_list: &[(usize, usize)], pos: usize) -> (usize, usize) {
+    let (start_pos, line) = pos_line_list
+        .iter()
+        .take_while(|x| x.0 <= pos)
+        .last()
+        .unwrap_or(&(0, 1));
+    let column = code
+        .get(*start_pos..pos)
+        .map_or(pos - start_pos, |s| s.chars().count());
+    (*line, column + 1)
+}
+
 #[test]
 fn number() {
     let mut lexer = Lexer::new("1 2 0x34 056 7.89 0b10 5e3 5e+3 5e-3 0999 0O123".to_string());
@@ -978,3 +1407,115 @@ fn comment() {
         Kind::Identifier("y".to_string())
     );
 }
+
+#[test]
+fn hashbang() {
+    let mut lexer = Lexer::new("#!/usr/bin/env rapidus\nx".to_string());
+    lexer.tokenize_all().unwrap();
+    assert_eq!(
+        lexer.next_skip_lineterminator().unwrap().kind,
+        Kind::Identifier("x".to_string())
+    );
+
+    let mut lexer = Lexer::new("#!/usr/bin/env rapidus".to_string());
+    lexer.tokenize_all().unwrap();
+    assert!(lexer.next_skip_lineterminator().is_err());
+
+    // Only allowed at the start of the script
+    for input in [" #!/usr/bin/env rapidus", "x\n#!/usr/bin/env rapidus"].iter() {
+        let mut parser = crate::parser::Parser::new("test", input.to_string());
+        parser.parse_all().expect_err("should be error");
+    }
+}
+
+#[test]
+fn line_and_column() {
+    let mut lexer = Lexer::new("let a =\n  /* x\n y */ 'ab'\n\tà + 1".to_string());
+    lexer.tokenize_all().unwrap();
+    let tokens: Vec<(usize, usize, usize)> = lexer
+        .buf
+        .iter()
+        .filter(|tok| tok.kind != Kind::LineTerminator)
+        .map(|tok| (tok.line, tok.column, tok.end - tok.pos))
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            (1, 1, 3),
+            (1, 5, 1),
+            (1, 7, 1),
+            (3, 7, 4),
+            (4, 2, 2),
+            (4, 4, 1),
+            (4, 6, 1),
+        ]
+    );
+}
+
+#[test]
+fn unicode_identifier() {
+    let mut lexer =
+        Lexer::new("café π_1 x\u{200D}y \\u0061b c\\u{64} \\u{5909}数 a\u{0301}".to_string());
+    lexer.tokenize_all().unwrap();
+    for expected in ["café", "π_1", "x\u{200D}y", "ab", "cd", "変数", "a\u{0301}"].iter() {
+        assert_eq!(
+            lexer.next().unwrap().kind,
+            Kind::Identifier(expected.to_string())
+        );
+    }
+
+    for input in ["\\u0031a", "a\\u002B", "\\u00", "\\x61", "\\u{69}f"].iter() {
+        let mut lexer = Lexer::new(input.to_string());
+        lexer.tokenize_all().expect_err("should be error");
+    }
+}
+
+#[test]
+fn token_stream() {
+    use crate::token::Keyword;
+    let code = "#!/usr/bin/env rapidus\nlet a = 1 // one\n/* two */\ta";
+    let kinds: Vec<Kind> = TokenStream::new(code)
+        .map(|tok| tok.unwrap().kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            Kind::LineTerminator,
+            Kind::Keyword(Keyword::Let),
+            Kind::Identifier("a".to_string()),
+            Kind::Symbol(Symbol::Assign),
+            Kind::Number(1.0),
+            Kind::LineTerminator,
+            Kind::Identifier("a".to_string()),
+        ]
+    );
+
+    let tokens: Vec<Token> = TokenStream::new(code)
+        .with_trivia()
+        .map(|tok| tok.unwrap())
+        .collect();
+    let source: String = tokens.iter().map(|tok| &code[tok.pos..tok.end]).collect();
+    assert_eq!(source, code);
+    let comments: Vec<(&Kind, usize, usize)> = tokens
+        .iter()
+        .filter(|tok| match tok.kind {
+            Kind::Comment(_) => true,
+            _ => false,
+        })
+        .map(|tok| (&tok.kind, tok.line, tok.column))
+        .collect();
+    assert_eq!(
+        comments,
+        vec![
+            (&Kind::Comment("#!/usr/bin/env rapidus".to_string()), 1, 1),
+            (&Kind::Comment("// one".to_string()), 2, 11),
+            (&Kind::Comment("/* two */".to_string()), 3, 1),
+        ]
+    );
+    assert_eq!(tokens.last().unwrap().column, 11);
+
+    let mut stream = TokenStream::new("a \\u00");
+    assert!(stream.next().unwrap().is_ok());
+    assert!(stream.next().unwrap().is_err());
+    assert!(stream.next().is_none());
+}
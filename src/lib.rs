@@ -5,14 +5,26 @@
 pub mod util;
 #[macro_use]
 pub mod vm;
+pub mod build_info;
 pub mod builtin;
 pub mod builtins;
 pub mod bytecode_gen;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod estree;
 pub mod gc;
 pub mod id;
+pub mod import_map;
+pub mod incremental;
+pub mod json;
 pub mod lexer;
+#[cfg(feature = "lsp")]
+pub mod lsp;
 pub mod node;
 pub mod parser;
+#[cfg(feature = "remote-modules")]
+pub mod remote_module;
+pub mod source_transform;
 pub mod token;
 
 extern crate ansi_term;
@@ -20,6 +32,7 @@ extern crate chrono;
 extern crate encoding;
 extern crate libc;
 extern crate libloading;
+extern crate log;
 //extern crate llvm_sys as llvm;
 extern crate nix;
 extern crate rand;
@@ -0,0 +1,709 @@
+//! A language server, run by the ``rapidus-lsp`` binary of the ``lsp`` feature. It reports the
+//! syntax errors and the compile errors and warnings of the open documents, and lists their
+//! functions and variables as document symbols (the outline). Messages are JSON-RPC on the
+//! standard input and output.
+//! https://microsoft.github.io/language-server-protocol/specification
+//!
+//! The outline of a document with syntax errors is the one from its last successful parse.
+
+use crate::incremental::TextEdit;
+use crate::json::Json;
+use crate::node::{FormalParameters, Node, NodeBase, PropertyDefinition, VarKind};
+use crate::parser::{Error, Parser, ParserOptions};
+use crate::vm::codegen;
+use crate::vm::source_position::SourceText;
+use crate::vm::vm::VM;
+use rustc_hash::FxHashMap;
+use std::io::{self, BufRead, Write};
+
+/// A problem found in a document. Positions are byte offsets in the document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub start: usize,
+    pub end: usize,
+    pub severity: Severity,
+    pub message: String,
+    /// Another position the problem involves, with a message, e.g. the previous declaration of
+    /// a redeclared name.
+    pub related: Option<(usize, String)>,
+}
+
+/// https://microsoft.github.io/language-server-protocol/specification#diagnostic
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error = 1,
+    Warning = 2,
+}
+
+/// A declaration in the outline of a document. Positions are byte offsets in the document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentSymbol {
+    pub name: String,
+    /// The parameters of functions, e.g. ``(a, b)``.
+    pub detail: Option<String>,
+    pub kind: SymbolKind,
+    /// The whole declaration.
+    pub range: (usize, usize),
+    /// The name in the declaration.
+    pub selection_range: (usize, usize),
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// https://microsoft.github.io/language-server-protocol/specification#documentSymbol
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolKind {
+    Method = 6,
+    Property = 7,
+    Function = 12,
+    Variable = 13,
+    Constant = 14,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    /// None if the document has syntax errors.
+    pub symbols: Option<Vec<DocumentSymbol>>,
+}
+
+/// Parse and compile ``code`` without running it. ``file_name`` chooses the syntax: ``.ts``
+/// files are TypeScript and ``.jsx`` files are JSX.
+pub fn analyze(file_name: &str, code: &str) -> Analysis {
+    let mut parser = Parser::new(file_name, code).with_options(ParserOptions {
+        jsx: file_name.ends_with(".jsx"),
+        typescript: file_name.ends_with(".ts"),
+        ..ParserOptions::default()
+    });
+    let mut node = match parser.parse_all_with_recovery() {
+        Ok(node) => node,
+        Err(errs) => {
+            let source = parser.source_text();
+            let diagnostics = errs
+                .iter()
+                .map(|err| syntax_error(&parser, source, err))
+                .collect();
+            return Analysis {
+                diagnostics,
+                symbols: None,
+            };
+        }
+    };
+    let source = parser.source_text();
+    let mut symbols = vec![];
+    outline(source, &node, &mut symbols);
+
+    let mut diagnostics = vec![];
+    let mut vm = VM::new();
+    if let Err(err) = vm.compile_with_source(&mut node, false, source) {
+        let related = match err.kind {
+            codegen::ErrorKind::Redeclaration(prev_pos) => Some((
+                source.original_pos(prev_pos),
+                "Previously declared here".to_string(),
+            )),
+            _ => None,
+        };
+        diagnostics.push(diagnostic(
+            source,
+            err.token_pos,
+            Severity::Error,
+            err.msg,
+            related,
+        ));
+    }
+    for warning in &vm.compile_warnings {
+        let msg = warning.msg.clone();
+        diagnostics.push(diagnostic(
+            source,
+            warning.token_pos,
+            Severity::Warning,
+            msg,
+            None,
+        ));
+    }
+    Analysis {
+        diagnostics,
+        symbols: Some(symbols),
+    }
+}
+
+fn syntax_error(parser: &Parser, source: SourceText, err: &Error) -> Diagnostic {
+    let related = match err {
+        Error::Redeclaration(_, prev_pos, _) => Some((
+            source.original_pos(*prev_pos),
+            "Previously declared here".to_string(),
+        )),
+        _ => None,
+    };
+    let pos = parser.error_pos(err);
+    let msg = Parser::error_message(err);
+    diagnostic(source, pos, Severity::Error, msg, related)
+}
+
+/// A diagnostic at ``pos`` in the parsed script, which covers the word there.
+fn diagnostic(
+    source: SourceText,
+    pos: usize,
+    severity: Severity,
+    message: String,
+    related: Option<(usize, String)>,
+) -> Diagnostic {
+    let start = ::std::cmp::min(source.original_pos(pos), source.code.len());
+    let rest = &source.code[start..];
+    let word = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .unwrap_or(rest.len());
+    let end = match word {
+        0 => start + rest.chars().next().map_or(0, |c| c.len_utf8()),
+        word => start + word,
+    };
+    Diagnostic {
+        start,
+        end,
+        severity,
+        message,
+        related,
+    }
+}
+
+/// Add the symbols declared in ``node`` to ``symbols``. The declarations in anonymous
+/// functions are not listed.
+fn outline(source: SourceText, node: &Node, symbols: &mut Vec<DocumentSymbol>) {
+    match &node.base {
+        NodeBase::FunctionDecl(name, params, body) => {
+            let mut symbol = symbol(source, node, name, SymbolKind::Function);
+            symbol.detail = Some(parameters(params));
+            outline(source, body, &mut symbol.children);
+            symbols.push(symbol);
+        }
+        NodeBase::VarDecl(name, init, var_kind) => {
+            let kind = match var_kind {
+                VarKind::Const => SymbolKind::Constant,
+                VarKind::Var | VarKind::Let => SymbolKind::Variable,
+            };
+            let mut symbol = symbol(source, node, name, kind);
+            match init.as_ref().map(|init| &init.base) {
+                Some(NodeBase::FunctionExpr(_, params, body))
+                | Some(NodeBase::ArrowFunction(params, body)) => {
+                    symbol.kind = SymbolKind::Function;
+                    symbol.detail = Some(parameters(params));
+                    outline(source, body, &mut symbol.children);
+                }
+                Some(NodeBase::Object(props)) => {
+                    for prop in props {
+                        symbol.children.extend(property(source, prop));
+                    }
+                }
+                _ => {}
+            }
+            symbols.push(symbol);
+        }
+        NodeBase::FunctionExpr(_, _, _) | NodeBase::ArrowFunction(_, _) => {}
+        _ => {
+            for child in node.children() {
+                outline(source, child, symbols)
+            }
+        }
+    }
+}
+
+/// The symbol of the declaration ``node`` of ``name``.
+fn symbol(source: SourceText, node: &Node, name: &str, kind: SymbolKind) -> DocumentSymbol {
+    let start = source.original_pos(node.start());
+    let end = ::std::cmp::max(start, source.original_pos(node.end));
+    let name_pos = source
+        .code
+        .get(start..end)
+        .and_then(|decl| decl.find(name))
+        .map_or(start, |pos| start + pos);
+    DocumentSymbol {
+        name: name.to_string(),
+        detail: None,
+        kind,
+        range: (start, end),
+        selection_range: (name_pos, name_pos + name.len()),
+        children: vec![],
+    }
+}
+
+/// The symbol of a property in an object literal, which starts at its name before the value.
+fn property(source: SourceText, prop: &PropertyDefinition) -> Option<DocumentSymbol> {
+    let (name, val, kind) = match prop {
+        PropertyDefinition::Property(name, val) => (name, val, SymbolKind::Property),
+        PropertyDefinition::MethodDefinition(_, name, val) => (name, val, SymbolKind::Method),
+        PropertyDefinition::IdentifierReference(_) | PropertyDefinition::SpreadObject(_) => {
+            return None;
+        }
+    };
+    let mut symbol = symbol(source, val, name, kind);
+    let val_start = symbol.range.0;
+    if let Some(name_pos) = source
+        .code
+        .get(..val_start)
+        .and_then(|s| s.rfind(name.as_str()))
+    {
+        symbol.range.0 = name_pos;
+        symbol.selection_range = (name_pos, name_pos + name.len());
+    }
+    if let NodeBase::FunctionExpr(_, params, body) | NodeBase::ArrowFunction(params, body) =
+        &val.base
+    {
+        if kind == SymbolKind::Property {
+            symbol.kind = SymbolKind::Function;
+        }
+        symbol.detail = Some(parameters(params));
+        outline(source, body, &mut symbol.children);
+    }
+    Some(symbol)
+}
+
+fn parameters(params: &FormalParameters) -> String {
+    let names: Vec<String> = params
+        .iter()
+        .map(|param| {
+            if param.is_rest_param {
+                format!("...{}", param.name)
+            } else {
+                param.name.clone()
+            }
+        })
+        .collect();
+    format!("({})", names.join(", "))
+}
+
+/// The LSP position of the byte offset ``pos`` in ``code``: the 0-based line, and the offset
+/// in the line in UTF-16 code units.
+pub fn position(code: &str, pos: usize) -> (usize, usize) {
+    let before = &code[..::std::cmp::min(pos, code.len())];
+    let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+    let line = before.matches('\n').count();
+    let character = before[line_start..].encode_utf16().count();
+    (line, character)
+}
+
+/// The byte offset of the LSP position ``line`` and ``character``. Positions past the end of
+/// a line are at its end.
+pub fn offset(code: &str, line: usize, character: usize) -> usize {
+    let line_start = if line == 0 {
+        0
+    } else {
+        match code.match_indices('\n').nth(line - 1) {
+            Some((pos, _)) => pos + 1,
+            None => return code.len(),
+        }
+    };
+    let mut units = 0;
+    for (pos, c) in code[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + pos;
+        }
+        units += c.len_utf16();
+    }
+    code.len()
+}
+
+struct Document {
+    text: String,
+    version: Json,
+    /// The outline of the last version without syntax errors.
+    symbols: Vec<DocumentSymbol>,
+}
+
+/// The state of the language server between messages.
+#[derive(Default)]
+pub struct Server {
+    documents: FxHashMap<String, Document>,
+    shutdown: bool,
+    /// The exit code, once the client sent the exit notification.
+    pub exit_code: Option<i32>,
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server::default()
+    }
+
+    /// Handle the message ``msg`` from the client, and return the messages to send to it.
+    pub fn handle(&mut self, msg: &Json) -> Vec<Json> {
+        let method = msg.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = msg.get("params").unwrap_or(&Json::Null);
+        let id = match msg.get("id") {
+            Some(id) => id.clone(),
+            // A notification
+            None => return self.notification(method, params),
+        };
+        let result = match method {
+            "initialize" => Ok(Json::object(vec![
+                (
+                    "capabilities",
+                    Json::object(vec![
+                        (
+                            "textDocumentSync",
+                            Json::object(vec![
+                                ("openClose", true.into()),
+                                // Incremental
+                                ("change", Json::Number(2.0)),
+                            ]),
+                        ),
+                        ("documentSymbolProvider", true.into()),
+                    ]),
+                ),
+                (
+                    "serverInfo",
+                    Json::object(vec![
+                        ("name", "rapidus".into()),
+                        ("version", crate::build_info::VERSION.into()),
+                    ]),
+                ),
+            ])),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            }
+            "textDocument/documentSymbol" => match self.document(params) {
+                Some(doc) => Ok(doc
+                    .symbols
+                    .iter()
+                    .map(|symbol| symbol_json(&doc.text, symbol))
+                    .collect::<Vec<_>>()
+                    .into()),
+                None => Err((-32602, "Unknown document".to_string())),
+            },
+            _ => Err((-32601, format!("Unsupported method '{}'", method))),
+        };
+        vec![response(id, result)]
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let doc = params.get("textDocument").unwrap_or(&Json::Null);
+        let uri = doc.get("uri").and_then(Json::as_str).unwrap_or("");
+        let version = doc.get("version").cloned().unwrap_or(Json::Null);
+        match method {
+            "textDocument/didOpen" => {
+                let text = doc.get("text").and_then(Json::as_str).unwrap_or("");
+                let doc = Document {
+                    text: text.to_string(),
+                    version,
+                    symbols: vec![],
+                };
+                self.documents.insert(uri.to_string(), doc);
+                self.publish_diagnostics(uri)
+            }
+            "textDocument/didChange" => {
+                let changes = params.get("contentChanges").and_then(Json::as_array);
+                match (self.documents.get_mut(uri), changes) {
+                    (Some(doc), Some(changes)) => {
+                        for change in changes {
+                            apply_change(&mut doc.text, change);
+                        }
+                        doc.version = version;
+                    }
+                    _ => return vec![],
+                }
+                self.publish_diagnostics(uri)
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                vec![diagnostics_notification(uri, Json::Null, vec![])]
+            }
+            "exit" => {
+                self.exit_code = Some(if self.shutdown { 0 } else { 1 });
+                vec![]
+            }
+            // Including "initialized" and the notifications this server does not support.
+            _ => vec![],
+        }
+    }
+
+    /// The document given by the ``textDocument`` of the request parameters ``params``.
+    fn document(&self, params: &Json) -> Option<&Document> {
+        let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+        self.documents.get(uri)
+    }
+
+    /// Analyze the document ``uri``, and report its problems.
+    fn publish_diagnostics(&mut self, uri: &str) -> Vec<Json> {
+        let doc = match self.documents.get_mut(uri) {
+            Some(doc) => doc,
+            None => return vec![],
+        };
+        let analysis = analyze(uri, &doc.text);
+        if let Some(symbols) = analysis.symbols {
+            doc.symbols = symbols;
+        }
+        let diagnostics = analysis
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic_json(uri, &doc.text, diagnostic))
+            .collect();
+        vec![diagnostics_notification(
+            uri,
+            doc.version.clone(),
+            diagnostics,
+        )]
+    }
+}
+
+/// Apply a ``TextDocumentContentChangeEvent``, which replaces ``range`` with ``text``, or the
+/// whole text if it has no range.
+fn apply_change(text: &mut String, change: &Json) {
+    let new_text = change.get("text").and_then(Json::as_str).unwrap_or("");
+    let code = text.as_str();
+    let offset_of = |pos: &Json| -> Option<usize> {
+        let line = pos.get("line")?.as_f64()? as usize;
+        let character = pos.get("character")?.as_f64()? as usize;
+        Some(offset(code, line, character))
+    };
+    let range = change.get("range").and_then(|range| {
+        let start = offset_of(range.get("start")?)?;
+        let end = offset_of(range.get("end")?)?;
+        Some((start, ::std::cmp::max(start, end)))
+    });
+    *text = match range {
+        Some((start, end)) => TextEdit::new(start, end, new_text).apply(code),
+        None => new_text.to_string(),
+    };
+}
+
+fn response(id: Json, result: Result<Json, (i32, String)>) -> Json {
+    let (key, val) = match result {
+        Ok(result) => ("result", result),
+        Err((code, msg)) => (
+            "error",
+            Json::object(vec![
+                ("code", (code as f64).into()),
+                ("message", msg.into()),
+            ]),
+        ),
+    };
+    Json::object(vec![("jsonrpc", "2.0".into()), ("id", id), (key, val)])
+}
+
+/// The ``textDocument/publishDiagnostics`` notification.
+fn diagnostics_notification(uri: &str, version: Json, diagnostics: Vec<Json>) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        (
+            "params",
+            Json::object(vec![
+                ("uri", uri.into()),
+                ("version", version),
+                ("diagnostics", diagnostics.into()),
+            ]),
+        ),
+    ])
+}
+
+fn range_json(code: &str, start: usize, end: usize) -> Json {
+    let pos = |pos| {
+        let (line, character) = position(code, pos);
+        Json::object(vec![("line", line.into()), ("character", character.into())])
+    };
+    Json::object(vec![("start", pos(start)), ("end", pos(end))])
+}
+
+fn diagnostic_json(uri: &str, code: &str, diagnostic: &Diagnostic) -> Json {
+    let mut members = vec![
+        ("range", range_json(code, diagnostic.start, diagnostic.end)),
+        ("severity", (diagnostic.severity as usize).into()),
+        ("source", "rapidus".into()),
+        ("message", diagnostic.message.as_str().into()),
+    ];
+    if let Some((pos, msg)) = &diagnostic.related {
+        let location = Json::object(vec![
+            ("uri", uri.into()),
+            ("range", range_json(code, *pos, *pos)),
+        ]);
+        members.push((
+            "relatedInformation",
+            vec![Json::object(vec![
+                ("location", location),
+                ("message", msg.as_str().into()),
+            ])]
+            .into(),
+        ));
+    }
+    Json::object(members)
+}
+
+fn symbol_json(code: &str, symbol: &DocumentSymbol) -> Json {
+    let mut members = vec![
+        ("name", symbol.name.as_str().into()),
+        ("kind", (symbol.kind as usize).into()),
+        ("range", range_json(code, symbol.range.0, symbol.range.1)),
+        (
+            "selectionRange",
+            range_json(code, symbol.selection_range.0, symbol.selection_range.1),
+        ),
+        (
+            "children",
+            symbol
+                .children
+                .iter()
+                .map(|child| symbol_json(code, child))
+                .collect::<Vec<_>>()
+                .into(),
+        ),
+    ];
+    if let Some(detail) = &symbol.detail {
+        members.push(("detail", detail.as_str().into()));
+    }
+    Json::object(members)
+}
+
+/// Read a message with its ``Content-Length`` header. None at the end of the input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut len = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let header = "Content-Length:";
+        if line.starts_with(header) {
+            len = line[header.len()..].trim().parse::<usize>().ok();
+        }
+    }
+    let len = len.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+    let mut body = vec![0; len];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn write_message(output: &mut impl Write, msg: &Json) -> io::Result<()> {
+    let body = msg.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// Serve the client on ``input`` and ``output`` until it sends the exit notification. Returns
+/// the exit code of the server.
+pub fn run(input: &mut impl BufRead, output: &mut impl Write) -> io::Result<i32> {
+    let mut server = Server::new();
+    while let Some(body) = read_message(input)? {
+        let replies = match Json::parse(&body) {
+            Ok(msg) => server.handle(&msg),
+            Err(err) => vec![response(Json::Null, Err((-32700, err)))],
+        };
+        for reply in &replies {
+            write_message(output, reply)?;
+        }
+        if let Some(code) = server.exit_code {
+            return Ok(code);
+        }
+    }
+    Ok(1)
+}
+
+#[test]
+fn analyze_document() {
+    let code = "function f(a, ...b) {\n  var x = 1\n  return a\n  x\n}\nconst o = { g: function() {}, h: 1 }";
+    let analysis = analyze("test.js", code);
+    assert_eq!(analysis.diagnostics.len(), 1);
+    let unreachable = &analysis.diagnostics[0];
+    assert_eq!(unreachable.severity, Severity::Warning);
+    assert_eq!(&code[unreachable.start..unreachable.end], "x");
+    assert_eq!(position(code, unreachable.start), (3, 2));
+
+    let symbols = analysis.symbols.unwrap();
+    let names = |symbols: &[DocumentSymbol]| {
+        symbols
+            .iter()
+            .map(|symbol| (symbol.name.clone(), symbol.kind))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        names(&symbols),
+        [
+            ("f".to_string(), SymbolKind::Function),
+            ("o".to_string(), SymbolKind::Constant)
+        ]
+    );
+    assert_eq!(symbols[0].detail, Some("(a, ...b)".to_string()));
+    assert_eq!(
+        names(&symbols[0].children),
+        [("x".to_string(), SymbolKind::Variable)]
+    );
+    assert_eq!(
+        names(&symbols[1].children),
+        [
+            ("g".to_string(), SymbolKind::Function),
+            ("h".to_string(), SymbolKind::Property)
+        ]
+    );
+    let (start, end) = symbols[1].children[1].selection_range;
+    assert_eq!(&code[start..end], "h");
+
+    let analysis = analyze("test.js", "let a = 1\nlet a = (\nvar b = 2");
+    assert_eq!(analysis.symbols, None);
+    assert!(!analysis.diagnostics.is_empty());
+    assert!(analysis
+        .diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.severity == Severity::Error));
+}
+
+#[test]
+fn positions() {
+    let code = "a\n\u{1f600}b\n";
+    assert_eq!(position(code, 2), (1, 0));
+    assert_eq!(position(code, 6), (1, 2));
+    assert_eq!(offset(code, 1, 2), 6);
+    assert_eq!(offset(code, 1, 10), 7);
+    assert_eq!(offset(code, 5, 0), code.len());
+}
+
+#[test]
+fn session() {
+    let messages = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.js","version":1,"text":"var a = (\n"}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///a.js","version":2},"contentChanges":[{"range":{"start":{"line":0,"character":8},"end":{"line":1,"character":0}},"text":"1\nfunction f() {}"}]}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/documentSymbol","params":{"textDocument":{"uri":"file:///a.js"}}}"#,
+        r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#,
+        r#"{"jsonrpc":"2.0","method":"exit"}"#,
+    ];
+    let mut input: Vec<u8> = vec![];
+    for msg in &messages {
+        write!(input, "Content-Length: {}\r\n\r\n{}", msg.len(), msg).unwrap();
+    }
+    let mut output: Vec<u8> = vec![];
+    assert_eq!(run(&mut io::Cursor::new(input), &mut output).unwrap(), 0);
+
+    let mut output = io::Cursor::new(output);
+    let mut replies = vec![];
+    while let Some(body) = read_message(&mut output).unwrap() {
+        replies.push(Json::parse(&body).unwrap());
+    }
+    assert_eq!(replies.len(), 5);
+    let capabilities = replies[0].get("result").unwrap().get("capabilities");
+    assert_eq!(
+        capabilities.and_then(|c| c.get("documentSymbolProvider")),
+        Some(&Json::Bool(true))
+    );
+    let diagnostics = |reply: &Json| {
+        let params = reply.get("params").unwrap();
+        assert_eq!(
+            params.get("uri").and_then(Json::as_str),
+            Some("file:///a.js")
+        );
+        params.get("diagnostics").unwrap().as_array().unwrap().len()
+    };
+    assert_eq!(diagnostics(&replies[1]), 1);
+    assert_eq!(diagnostics(&replies[2]), 0);
+    let symbols = replies[3].get("result").unwrap().as_array().unwrap();
+    let names: Vec<_> = symbols
+        .iter()
+        .map(|symbol| symbol.get("name").and_then(Json::as_str).unwrap())
+        .collect();
+    assert_eq!(names, ["a", "f"]);
+    assert_eq!(replies[4].get("result"), Some(&Json::Null));
+}
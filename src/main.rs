@@ -1,18 +1,27 @@
 #![feature(test)]
 //extern crate rapidus;
+use rapidus::build_info::BuildInfo;
+use rapidus::builtins::help;
+use rapidus::estree;
+use rapidus::import_map::ImportMap;
 use rapidus::parser;
+use rapidus::util::StackLimit;
+use rapidus::vm::bytecode_file;
+use rapidus::vm::jsvalue::function::FuncInfoRef;
+use rapidus::vm::jsvalue::value::Value;
+use rapidus::vm::scope_analysis::ScopeTree;
 use rapidus::{vm, vm::exec_context, vm::vm::VM};
 extern crate clap;
 extern crate libc;
 extern crate rustyline;
 extern crate test;
-use clap::{App, Arg};
-
-const VERSION_STR: &'static str = env!("CARGO_PKG_VERSION");
+use clap::{App, Arg, ArgMatches};
+use std::fs;
+use std::path::Path;
 
 fn main() {
     let app = App::new("Rapidus")
-        .version(VERSION_STR)
+        .version(rapidus::build_info::VERSION)
         .author("uint256_t")
         .about("A toy JavaScript engine")
         .arg(
@@ -25,79 +34,282 @@ fn main() {
                 .help("Collect and print performance profile")
                 .long("profile"),
         )
+        .arg(
+            Arg::with_name("profile-functions")
+                .help("Print time, calls and allocations per function at exit")
+                .long("profile-functions"),
+        )
         .arg(
             Arg::with_name("trace")
                 .help("Tracing execution")
                 .long("trace"),
         )
+        .arg(
+            Arg::with_name("gc-verify")
+                .help("Verify the heap after every GC (slow)")
+                .long("gc-verify"),
+        )
+        .arg(
+            Arg::with_name("check")
+                .help("Parse and compile the input without executing it")
+                .long("check"),
+        )
+        .arg(
+            Arg::with_name("warnings")
+                .help("Print warnings on the input, e.g. unreachable code")
+                .long("warnings"),
+        )
+        .arg(
+            Arg::with_name("dump-ast")
+                .help("Print the AST of the input as ESTree JSON")
+                .long("dump-ast"),
+        )
+        .arg(
+            Arg::with_name("dump-scopes")
+                .help("Print how the names in the input are resolved")
+                .long("dump-scopes"),
+        )
+        .arg(
+            Arg::with_name("compile")
+                .help("Compile the input to bytecode, which can be run later without parsing it")
+                .long("compile"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .help("Output file name for --compile (default: the input with .rbc extension)")
+                .short("o")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("import-map")
+                .help("Import map (JSON) redirecting the module names given to require")
+                .long("import-map")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("build-info")
+                .help("Print build information as JSON")
+                .long("build-info"),
+        )
         .arg(Arg::with_name("file").help("Input file name").index(1));
+    #[cfg(feature = "remote-modules")]
+    let app = app.arg(
+        Arg::with_name("allow-remote")
+            .help("Comma-separated hosts which require may fetch modules from")
+            .long("allow-remote")
+            .takes_value(true),
+    );
     let app_matches = app.clone().get_matches();
     let is_debug = app_matches.is_present("debug");
     let is_profile = app_matches.is_present("profile");
     let is_trace = app_matches.is_present("trace");
+    let is_check = app_matches.is_present("check");
+    let is_gc_verify = app_matches.is_present("gc-verify");
+    let is_dump_ast = app_matches.is_present("dump-ast");
+    let is_dump_scopes = app_matches.is_present("dump-scopes");
+    let is_compile = app_matches.is_present("compile");
+    let is_warnings = app_matches.is_present("warnings");
+    if app_matches.is_present("build-info") {
+        println!("{}", BuildInfo::new().to_json());
+        return;
+    }
     let file_name = match app_matches.value_of("file") {
         Some(file_name) => file_name,
         None => {
-            repl(is_profile, is_trace);
+            repl(is_profile, is_trace, is_gc_verify);
             return;
         }
     };
+    if file_name.ends_with(".rbc") {
+        run_compiled(&app_matches, file_name);
+        return;
+    }
 
     let mut parser = match parser::Parser::load_module(file_name.clone()) {
         Ok(ok) => ok,
-        Err(_) => return,
+        Err(_) => exit_on_error(is_check),
     };
 
-    let node = match parser.parse_all() {
+    let mut node = match parser.parse_all_with_recovery() {
         Ok(ok) => ok,
-        Err(err) => {
-            parser.handle_error(&err);
-            return;
+        Err(errs) => {
+            parser.handle_errors(&errs);
+            exit_on_error(is_check)
         }
     };
     if is_debug {
         println!("Parser:");
         println!("{:?}", node);
     };
-
-    let mut vm = VM::new();
-    if is_profile {
-        vm = vm.profile();
+    if is_dump_ast {
+        println!(
+            "{}",
+            estree::to_json(&node, &parser.lexer.code, &parser.lexer.pos_line_list)
+        );
+        return;
     }
-    if is_trace {
-        vm = vm.trace();
+    if is_dump_scopes {
+        let tree = ScopeTree::new(&node, &StackLimit::new());
+        print!(
+            "{}",
+            tree.dump(&parser.lexer.code, &parser.lexer.pos_line_list)
+        );
+        return;
     }
 
-    let global_info = match vm.compile(&node, false) {
+    let mut vm = create_vm(&app_matches);
+    let global_info = match vm.compile_with_source(&mut node, false, parser.source_text()) {
         Ok(ok) => ok,
-        Err(vm::codegen::Error { msg, token_pos, .. }) => {
-            parser.show_error_at(token_pos, msg);
-            return;
+        Err(err) => {
+            show_compile_error(&parser, err);
+            exit_on_error(is_check)
         }
     };
 
+    if is_warnings {
+        for warning in &vm.compile_warnings {
+            parser.show_warning_at(warning.token_pos, warning.msg.clone());
+        }
+    }
+
+    vm.script_info
+        .push((global_info.module_func_id, parser.into_script_info()));
+
     if is_debug {
         println!("Codegen:");
-        rapidus::bytecode_gen::show_inst_seq(&global_info.code, &vm.constant_table);
+        println!("{}", help::disassemble(&vm, &global_info));
     };
 
-    let script_info = parser.into_script_info();
-    vm.script_info
-        .push((global_info.module_func_id, script_info));
+    if is_check {
+        return;
+    }
+
+    if is_compile {
+        let script_info = vm.script(global_info.module_func_id);
+        let output = match app_matches.value_of("output") {
+            Some(output) => output.to_string(),
+            None => Path::new(file_name)
+                .with_extension("rbc")
+                .to_string_lossy()
+                .into_owned(),
+        };
+        let bytes = match bytecode_file::serialize(&vm, global_info, script_info) {
+            Ok(ok) => ok,
+            Err(err) => {
+                println!("Error: Cannot compile '{}': {}", file_name, err);
+                exit_on_error(true)
+            }
+        };
+        if let Err(err) = fs::write(&output, bytes) {
+            println!("Error: Cannot write '{}': {}", output, err);
+            exit_on_error(true)
+        }
+        return;
+    }
+
+    run(vm, global_info);
+}
+
+/// Run a script compiled with `--compile`.
+fn run_compiled(app_matches: &ArgMatches, file_name: &str) {
+    let mut vm = create_vm(app_matches);
+    let global_info = match fs::read(file_name) {
+        Ok(bytes) => bytecode_file::deserialize(&mut vm, &bytes).map_err(|err| err.to_string()),
+        Err(err) => Err(err.to_string()),
+    };
+    match global_info {
+        Ok(global_info) => run(vm, global_info),
+        Err(err) => println!("Error: Cannot load '{}': {}", file_name, err),
+    }
+}
+
+fn create_vm(app_matches: &ArgMatches) -> VM {
+    let mut vm = VM::new();
+    #[cfg(feature = "node-compat")]
+    {
+        vm = vm.node_compat();
+    }
+    if app_matches.is_present("profile") {
+        vm = vm.profile();
+    }
+    if app_matches.is_present("profile-functions") {
+        vm = vm.profile_functions();
+    }
+    if app_matches.is_present("trace") {
+        vm = vm.trace();
+    }
+    if app_matches.is_present("gc-verify") {
+        vm = vm.gc_verify();
+    }
+    if let Some(file_name) = app_matches.value_of("import-map") {
+        match ImportMap::from_file(file_name) {
+            Ok(import_map) => vm = vm.with_import_map(import_map),
+            Err(err) => {
+                println!("Error: {}", err);
+                exit_on_error(true)
+            }
+        }
+    }
+    #[cfg(feature = "remote-modules")]
+    {
+        if let Some(hosts) = app_matches.value_of("allow-remote") {
+            vm = vm.allow_remote_hosts(hosts.split(',').map(|h| h.trim().to_string()).collect());
+        }
+    }
+    if app_matches.is_present("debug") {
+        vm.set_debugger_hook(|vm| {
+            println!(
+                "debugger: module_id:{:?} func_id:{:?} pc:{}",
+                vm.current_context.func_ref.module_func_id,
+                vm.current_context.func_ref.func_id,
+                vm.current_context.current_inst_pc
+            );
+            for val in &vm.current_context.stack {
+                let val: Value = (*val).into();
+                println!("  {}", val.debug_string(true));
+            }
+        });
+    }
+    vm
+}
+
+fn run(mut vm: VM, global_info: FuncInfoRef) {
     if let Err(e) = vm.run_global(global_info) {
         vm.show_error_message(e);
     }
+    vm.print_function_profile();
 }
 
-fn repl(is_profile: bool, is_trace: bool) {
+/// Show a compile error, and the previous declaration for a redeclaration.
+fn show_compile_error(parser: &parser::Parser, err: vm::codegen::Error) {
+    parser.show_error_at(err.token_pos, err.msg);
+    if let vm::codegen::ErrorKind::Redeclaration(prev_pos) = err.kind {
+        parser.show_note_at(prev_pos, "Previously declared here")
+    }
+}
+
+/// In check mode, a parse or compile error makes the process exit with a nonzero code so that
+/// `--check` can be used as a syntax gate.
+fn exit_on_error(is_check: bool) -> ! {
+    ::std::process::exit(if is_check { 1 } else { 0 })
+}
+
+fn repl(is_profile: bool, is_trace: bool, is_gc_verify: bool) {
     let mut rl = rustyline::Editor::<()>::new();
     let mut vm = VM::new();
+    #[cfg(feature = "node-compat")]
+    {
+        vm = vm.node_compat();
+    }
     if is_profile {
         vm = vm.profile();
     }
     if is_trace {
         vm = vm.trace();
     }
+    if is_gc_verify {
+        vm = vm.gc_verify();
+    }
     let mut global_context: Option<exec_context::ExecContext> = None;
 
     loop {
@@ -111,20 +323,41 @@ fn repl(is_profile: bool, is_trace: bool) {
 
         rl.add_history_entry(line.clone());
 
-        let mut lines = line + "\n";
+        // `.help expr` describes the value of expr instead of showing it.
+        let is_help = line.trim_start().starts_with(".help ");
+        if line.trim_start().starts_with(".dis ") {
+            let input = &line.trim_start()[".dis ".len()..];
+            print_disassembly(&mut vm, global_context.as_ref(), input);
+            continue;
+        }
+        let is_editor_mode = line.trim() == ".editor";
+        let mut lines = if is_editor_mode {
+            match read_editor_input(&mut rl) {
+                Some(lines) => lines,
+                None => continue,
+            }
+        } else if line.trim() == ".help" {
+            print_repl_help();
+            continue;
+        } else if is_help {
+            line.trim_start()[".help ".len()..].to_string() + "\n"
+        } else {
+            line + "\n"
+        };
 
         loop {
             parser = parser::Parser::new("REPL", lines.clone());
             match parser.parse_all() {
-                Ok(node) => {
+                Ok(mut node) => {
                     // compile and execute
-                    let global_info = match vm.compile(&node, true) {
-                        Ok(ok) => ok,
-                        Err(vm::codegen::Error { msg, token_pos, .. }) => {
-                            parser.show_error_at(token_pos, msg);
-                            break;
-                        }
-                    };
+                    let global_info =
+                        match vm.compile_with_source(&mut node, true, parser.source_text()) {
+                            Ok(ok) => ok,
+                            Err(err) => {
+                                show_compile_error(&parser, err);
+                                break;
+                            }
+                        };
 
                     match global_context {
                         Some(ref mut context) => {
@@ -136,14 +369,21 @@ fn repl(is_profile: bool, is_trace: bool) {
 
                     vm.current_context = global_context.clone().unwrap();
                     let script_info = parser.into_script_info();
-                    vm.script_info = vec![(vm.current_context.func_ref.module_func_id, script_info)];
+                    // Keep the scripts of the previous inputs, where the functions defined by
+                    // them are.
+                    vm.script_info
+                        .push((vm.current_context.func_ref.module_func_id, script_info));
 
                     match vm.run() {
+                        Ok(val) if is_help => println!("{}", help::describe(&vm, val)),
                         Ok(val) => println!("{}", val.debug_string(true)),
                         Err(e) => {
+                            // Keep the global environment, and discard the frames left by the
+                            // error so that the session can go on.
+                            vm.saved_context.clear();
                             let val = e.to_value(&mut vm.factory);
                             if val.is_error_object() {
-                                println!("Error: {}", val.get_property("message"));
+                                println!("{}", val.to_string());
                             } else {
                                 println!("Thrown: {}", val.to_string())
                             };
@@ -151,15 +391,17 @@ fn repl(is_profile: bool, is_trace: bool) {
                     }
                     break;
                 }
-                Err(parser::Error::UnexpectedEOF(_)) => match rl.readline("... ") {
-                    Ok(line) => {
-                        rl.add_history_entry(line.clone());
-                        lines += line.as_str();
-                        lines += "\n";
-                        continue;
+                Err(parser::Error::UnexpectedEOF(_)) if !is_editor_mode => {
+                    match rl.readline("... ") {
+                        Ok(line) => {
+                            rl.add_history_entry(line.clone());
+                            lines += line.as_str();
+                            lines += "\n";
+                            continue;
+                        }
+                        Err(_) => break,
                     }
-                    Err(_) => break,
-                },
+                }
                 Err(e) => {
                     parser.handle_error(&e);
                     break;
@@ -168,3 +410,59 @@ fn repl(is_profile: bool, is_trace: bool) {
         }
     }
 }
+
+/// Print the commands of REPL, for `.help` command.
+fn print_repl_help() {
+    println!(".editor       Enter multi-line input (Ctrl-D to finish, Ctrl-C to cancel)");
+    println!(".help         Print this help");
+    println!(".help <expr>  Describe the value of <expr>, e.g. the source of a function");
+    println!(".dis <name>   Print the bytecode of the function <name>");
+    println!(".dis <code>   Print the bytecode <code> is compiled into, without running it");
+}
+
+/// Print the bytecode of the function named ``input``, or else that of ``input`` compiled as
+/// a script, for `.dis` command in REPL.
+fn print_disassembly(vm: &mut VM, context: Option<&exec_context::ExecContext>, input: &str) {
+    use rapidus::vm::jsvalue::function::FunctionObjectKind;
+
+    if let Some(Ok(val)) = context.map(|context| context.lex_env().get_value(input.trim())) {
+        if val.is_function_object() {
+            if let FunctionObjectKind::User { info, .. } = val.as_function().kind {
+                println!("{}", help::disassemble(vm, &info));
+                return;
+            }
+        }
+    }
+
+    let mut parser = parser::Parser::new("REPL", input.to_string() + "\n");
+    let mut node = match parser.parse_all() {
+        Ok(node) => node,
+        Err(e) => return parser.handle_error(&e),
+    };
+    let info = match vm.compile_with_source(&mut node, true, parser.source_text()) {
+        Ok(info) => info,
+        Err(err) => return show_compile_error(&parser, err),
+    };
+    vm.script_info
+        .push((info.module_func_id, parser.into_script_info()));
+    println!("{}", help::disassemble(vm, &info));
+}
+
+/// Read lines until Ctrl-D, for `.editor` command in REPL.
+/// Return None if canceled by Ctrl-C.
+fn read_editor_input(rl: &mut rustyline::Editor<()>) -> Option<String> {
+    use rustyline::error::ReadlineError;
+
+    println!("// Entering editor mode (Ctrl-D to finish, Ctrl-C to cancel)");
+    let mut lines = String::new();
+    loop {
+        match rl.readline("") {
+            Ok(line) => {
+                lines += line.as_str();
+                lines += "\n";
+            }
+            Err(ReadlineError::Eof) => return Some(lines),
+            Err(_) => return None,
+        }
+    }
+}
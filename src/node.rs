@@ -1,3 +1,6 @@
+use crate::token::Comment;
+use rustc_hash::FxHashMap;
+
 // TODO: Support all features: https://tc39.github.io/ecma262/#prod-FormalParameter
 //       Especially, destructing of rest parameter.
 #[derive(Clone, Debug, PartialEq)]
@@ -28,6 +31,25 @@ pub enum PropertyDefinition {
     SpreadObject(Node),
 }
 
+/// https://facebook.github.io/jsx/
+#[derive(Clone, Debug, PartialEq)]
+pub struct JSXElement {
+    /// ``None`` for a fragment ``<>...</>``. Member and namespaced names like ``a.b`` and
+    /// ``a:b`` are kept as written.
+    pub name: Option<String>,
+    pub attributes: Vec<JSXAttribute>,
+    /// Texts as ``NodeBase::String``, expressions in ``{}`` and elements.
+    pub children: Vec<Node>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JSXAttribute {
+    /// ``name="value"``, ``name={value}``, or ``name`` whose value is ``true``.
+    Attribute(String, Node),
+    /// ``{...value}``
+    Spread(Node),
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum MethodDefinitionKind {
     Get,
@@ -35,10 +57,135 @@ pub enum MethodDefinitionKind {
     Ordinary,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// Identifies a node in the tree it was parsed into, so that what an analysis derives from a
+/// node can be kept in a ``NodeMap`` instead of in the node itself.
+/// The parser numbers the nodes from 1 in source order, so a script gets the same ids every time
+/// it is parsed. Nodes made after parsing have ``NodeId::NONE``.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub usize);
+
+impl NodeId {
+    pub const NONE: NodeId = NodeId(0);
+
+    pub fn is_none(self) -> bool {
+        self == NodeId::NONE
+    }
+}
+
+/// Side table of data attached to nodes.
+pub type NodeMap<T> = FxHashMap<NodeId, T>;
+
+#[derive(Clone, Debug)]
 pub struct Node {
     pub base: NodeBase,
+    pub id: NodeId,
+    /// char position where the node starts.
     pub pos: usize,
+    /// char position just after the node ends.
+    pub end: usize,
+    /// Comments attached to a statement when parsing with ``Parser::with_comments``: the ones
+    /// just before it, and the one after it on the same line.
+    pub comments: Vec<Comment>,
+}
+
+/// ``id``, ``end`` and ``comments`` are not compared, so that a node built by hand equals the
+/// parsed one.
+impl PartialEq for Node {
+    fn eq(&self, other: &Node) -> bool {
+        self.base == other.base && self.pos == other.pos
+    }
+}
+
+macro_rules! children {
+    ($node:expr, $($mut_:tt)?) => { {
+        let mut children = vec![];
+        match $node.base {
+            NodeBase::StatementList(ref $($mut_)? nodes)
+            | NodeBase::Block(ref $($mut_)? nodes)
+            | NodeBase::Array(ref $($mut_)? nodes) => children.extend(nodes),
+            NodeBase::FunctionDecl(_, ref $($mut_)? params, ref $($mut_)? body)
+            | NodeBase::FunctionExpr(_, ref $($mut_)? params, ref $($mut_)? body)
+            | NodeBase::ArrowFunction(ref $($mut_)? params, ref $($mut_)? body) => {
+                for param in params {
+                    if let Some(init) = & $($mut_)? param.init {
+                        children.push(init)
+                    }
+                }
+                children.push(&$($mut_)? **body)
+            }
+            NodeBase::VarDecl(_, ref $($mut_)? init, _) | NodeBase::Return(ref $($mut_)? init) => {
+                if let Some(init) = init {
+                    children.push(&$($mut_)? **init)
+                }
+            }
+            NodeBase::Member(ref $($mut_)? node, _)
+            | NodeBase::New(ref $($mut_)? node)
+            | NodeBase::UnaryOp(ref $($mut_)? node, _)
+            | NodeBase::Label(_, ref $($mut_)? node)
+            | NodeBase::Throw(ref $($mut_)? node)
+            | NodeBase::Spread(ref $($mut_)? node) => children.push(&$($mut_)? **node),
+            NodeBase::Call(ref $($mut_)? callee, ref $($mut_)? args) => {
+                children.push(&$($mut_)? **callee);
+                children.extend(args)
+            }
+            NodeBase::Index(ref $($mut_)? a, ref $($mut_)? b)
+            | NodeBase::While(ref $($mut_)? a, ref $($mut_)? b)
+            | NodeBase::Assign(ref $($mut_)? a, ref $($mut_)? b)
+            | NodeBase::BinaryOp(ref $($mut_)? a, ref $($mut_)? b, _) => {
+                children.push(&$($mut_)? **a);
+                children.push(&$($mut_)? **b)
+            }
+            NodeBase::If(ref $($mut_)? a, ref $($mut_)? b, ref $($mut_)? c)
+            | NodeBase::TernaryOp(ref $($mut_)? a, ref $($mut_)? b, ref $($mut_)? c) => {
+                children.push(&$($mut_)? **a);
+                children.push(&$($mut_)? **b);
+                children.push(&$($mut_)? **c)
+            }
+            NodeBase::For(ref $($mut_)? a, ref $($mut_)? b, ref $($mut_)? c, ref $($mut_)? d) => {
+                children.push(&$($mut_)? **a);
+                children.push(&$($mut_)? **b);
+                children.push(&$($mut_)? **c);
+                children.push(&$($mut_)? **d)
+            }
+            NodeBase::Try(ref $($mut_)? try_clause, ref $($mut_)? catch, ref $($mut_)? param, ref $($mut_)? finally) => {
+                children.push(&$($mut_)? **try_clause);
+                children.push(&$($mut_)? **param);
+                children.push(&$($mut_)? **catch);
+                children.push(&$($mut_)? **finally)
+            }
+            NodeBase::Object(ref $($mut_)? properties) => {
+                for property in properties {
+                    match property {
+                        PropertyDefinition::IdentifierReference(_) => {}
+                        PropertyDefinition::Property(_, node)
+                        | PropertyDefinition::MethodDefinition(_, _, node)
+                        | PropertyDefinition::SpreadObject(node) => children.push(node),
+                    }
+                }
+            }
+            NodeBase::JSXElement(ref $($mut_)? element) => {
+                for attribute in & $($mut_)? element.attributes {
+                    match attribute {
+                        JSXAttribute::Attribute(_, node) | JSXAttribute::Spread(node) => {
+                            children.push(node)
+                        }
+                    }
+                }
+                children.extend(& $($mut_)? element.children)
+            }
+            NodeBase::Identifier(_)
+            | NodeBase::Break(_)
+            | NodeBase::Continue(_)
+            | NodeBase::Debugger
+            | NodeBase::This
+            | NodeBase::Null
+            | NodeBase::String(_)
+            | NodeBase::Boolean(_)
+            | NodeBase::Number(_)
+            | NodeBase::Nope => {}
+        }
+        children
+    } };
 }
 
 #[derive(Clone, Debug, PartialEq, Copy)]
@@ -73,10 +220,12 @@ pub enum NodeBase {
     Continue(Option<String>),
     Try(Box<Node>, Box<Node>, Box<Node>, Box<Node>), // Try, Catch, Param, Finally
     Throw(Box<Node>),
+    Debugger,
     Array(Vec<Node>),
     Object(Vec<PropertyDefinition>),
     Identifier(String),
     Spread(Box<Node>),
+    JSXElement(Box<JSXElement>),
     This,
     // Arguments,
     // Undefined,
@@ -91,8 +240,62 @@ impl Node {
     pub fn new(base: NodeBase, pos: usize) -> Node {
         Node {
             base: base,
+            id: NodeId::NONE,
             pos: pos,
+            end: pos,
+            comments: vec![],
+        }
+    }
+
+    pub fn new_span(base: NodeBase, pos: usize, end: usize) -> Node {
+        Node {
+            base: base,
+            id: NodeId::NONE,
+            pos: pos,
+            end: ::std::cmp::max(pos, end),
+            comments: vec![],
+        }
+    }
+
+    /// The position where the source of this node starts. Binary operations, calls and member
+    /// accesses are positioned at their operator, after their leftmost operand.
+    pub fn start(&self) -> usize {
+        match self.base {
+            NodeBase::BinaryOp(ref lhs, _, _)
+            | NodeBase::Assign(ref lhs, _)
+            | NodeBase::TernaryOp(ref lhs, _, _)
+            | NodeBase::Member(ref lhs, _)
+            | NodeBase::Index(ref lhs, _)
+            | NodeBase::Call(ref lhs, _) => ::std::cmp::min(self.pos, lhs.start()),
+            NodeBase::UnaryOp(ref expr, UnaryOp::PoInc)
+            | NodeBase::UnaryOp(ref expr, UnaryOp::PoDec) => {
+                ::std::cmp::min(self.pos, expr.start())
+            }
+            _ => self.pos,
+        }
+    }
+
+    /// The nodes directly inside this node, in source order.
+    pub fn children(&self) -> Vec<&Node> {
+        children!(self,)
+    }
+
+    pub fn children_mut(&mut self) -> Vec<&mut Node> {
+        children!(self, mut)
+    }
+
+    /// Number this node and all the nodes inside it in source order, starting from ``first``.
+    /// Returns the id following the last one.
+    pub fn assign_ids(&mut self, first: usize) -> usize {
+        let mut next = first;
+        // Not recursive, so that deeply nested nodes can be numbered.
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            node.id = NodeId(next);
+            next += 1;
+            stack.extend(node.children_mut().into_iter().rev());
         }
+        next
     }
     /*
     pub fn definitely_returns(&self) -> bool {
@@ -258,6 +461,7 @@ pub enum BinOp {
     Shl,
     Shr,
     ZFShr,
+    In,
     Comma,
     Assign,
 }
@@ -3,12 +3,21 @@ use crate::node::{
     BinOp, FormalParameter, FormalParameters, MethodDefinitionKind, Node, NodeBase,
     PropertyDefinition, UnaryOp, VarKind,
 };
-use crate::token::{get_string_for_symbol, Keyword, Kind, Symbol, Token};
+#[cfg(feature = "jsx")]
+use crate::node::{JSXAttribute, JSXElement};
+use crate::source_transform::{OffsetTable, TransformedSource};
+use crate::token::{get_string_for_symbol, Comment, Keyword, Kind, Symbol, Token};
+use crate::util::StackLimit;
+use crate::vm::source_position::SourceText;
 use std::fs::OpenOptions;
 use std::io::Read;
 use std::path::Path;
 
-use ansi_term::Colour;
+use ansi_term::{ANSIString, Colour};
+
+/// Maximum depth of the AST. Deeper trees would overflow the native stack when they are
+/// compiled or dropped.
+pub const MAX_NESTING_DEPTH: usize = 10_000;
 
 macro_rules! expect {
     ($self:ident, $kind:expr, $msg:expr) => {{
@@ -38,18 +47,90 @@ pub enum Error {
     Expect(usize, String),          // position, error msg
     InvalidToken(usize),
     General(usize, String),
+    Redeclaration(usize, usize, String), // position, position of the previous declaration, name
 }
 
 #[derive(Clone, Debug)]
 pub struct Parser {
     pub file_name: String,
     pub lexer: lexer::Lexer,
+    /// If true, record syntax errors in ``diagnostics`` and continue parsing from the next
+    /// statement.
+    recover: bool,
+    /// Syntax errors recorded while recovering.
+    pub diagnostics: Vec<Error>,
+    /// Depth of the AST being built.
+    depth: usize,
+    stack_limit: StackLimit,
+    /// Index in ``lexer.comments`` of the first comment not yet attached to a node.
+    next_comment: usize,
+    pub options: ParserOptions,
+    /// The script before a source transform, if ``lexer.code`` is transformed.
+    original: Option<OriginalSource>,
+}
+
+/// See ``Parser::replace_source``.
+#[derive(Clone, Debug)]
+struct OriginalSource {
+    code: String,
+    pos_line_list: Vec<(usize, usize)>,
+    offsets: OffsetTable,
+}
+
+/// Syntax accepted by the parser. See ``Parser::with_options``.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParserOptions {
+    /// The goal symbol the source is parsed as.
+    pub goal: Goal,
+    /// The latest edition of ECMAScript (as a year) whose syntax is accepted.
+    /// Syntax introduced in later editions is a syntax error.
+    pub ecma_version: u32,
+    /// Accept legacy syntax in Annex B, e.g. ``010`` as an octal literal. Never accepted in
+    /// strict code.
+    /// https://tc39.github.io/ecma262/#sec-additional-syntax
+    pub annex_b: bool,
+    /// Parse JSX into ``NodeBase::JSXElement``. Without the ``jsx`` feature, JSX is reported as
+    /// an unsupported feature instead of an unexpected token.
+    pub jsx: bool,
+    /// Accept TypeScript and strip the type-only syntax, e.g. type annotations, interfaces and
+    /// ``as`` expressions. Enums are transformed into objects. Types are not checked.
+    /// Without the ``typescript`` feature, parsing fails as an unsupported feature.
+    pub typescript: bool,
+}
+
+/// https://tc39.github.io/ecma262/#sec-types-of-source-code
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Goal {
+    Script,
+    /// Module code, which is always strict.
+    Module,
+}
+
+/// The latest edition of ECMAScript whose syntax the parser knows.
+pub const LATEST_ECMA_VERSION: u32 = 2019;
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            goal: Goal::Script,
+            ecma_version: LATEST_ECMA_VERSION,
+            annex_b: true,
+            jsx: false,
+            typescript: false,
+        }
+    }
+}
+
+impl ParserOptions {
+    pub fn is_strict(&self) -> bool {
+        self.goal == Goal::Module
+    }
 }
 
 #[derive(Clone, Debug)]
 /// Information about a script (module), e.g. file name, source text, and correspondence between char postions and line numbers.
 pub struct ScriptInfo {
-    /// File name with Absolute path.
+    /// File name with Absolute path, or the name the host gave to the script.
     pub file_name: String,
     /// Script text.
     pub code: String,
@@ -57,20 +138,82 @@ pub struct ScriptInfo {
     pub pos_line_list: Vec<(usize, usize)>,
 }
 
+impl ScriptInfo {
+    /// The text of the line ``line``, counted from the line offset given to the parser.
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let first_line = self.pos_line_list.first().map_or(1, |(_, line)| *line);
+        self.code.lines().nth(line.checked_sub(first_line)?)
+    }
+}
+
 impl Parser {
     pub fn new(file_name: impl Into<String>, code: impl Into<String>) -> Parser {
         Parser {
             file_name: file_name.into(),
             lexer: lexer::Lexer::new(code.into()),
+            recover: false,
+            diagnostics: vec![],
+            depth: 0,
+            stack_limit: StackLimit::new(),
+            next_comment: 0,
+            options: ParserOptions::default(),
+            original: None,
+        }
+    }
+
+    /// Restrict or extend the syntax accepted. See ``ParserOptions``.
+    pub fn with_options(mut self, options: ParserOptions) -> Parser {
+        self.lexer.legacy_octal = options.annex_b && !options.is_strict();
+        self.lexer.jsx = options.jsx;
+        self.options = options;
+        self
+    }
+
+    /// Number lines as if the script started at line ``line_offset + 1`` of ``file_name``, for
+    /// a script embedded in a larger document such as an HTML page.
+    pub fn with_line_offset(mut self, line_offset: usize) -> Parser {
+        self.lexer.line = line_offset + 1;
+        self.lexer.pos_line_list = vec![(0, line_offset + 1)];
+        if let Some(original) = &mut self.original {
+            original.pos_line_list = lexer::pos_line_list(&original.code, line_offset + 1);
         }
+        self
+    }
+
+    /// Parse ``transformed`` instead of the script given, e.g. the result of a source transform.
+    /// Positions in diagnostics, compiled code and ``into_script_info`` are mapped back to the
+    /// original script with the offset table. Must be called before parsing.
+    pub fn replace_source(&mut self, transformed: TransformedSource) {
+        let code = std::mem::replace(&mut self.lexer.code, transformed.code);
+        let pos_line_list = lexer::pos_line_list(&code, self.lexer.pos_line_list[0].1);
+        self.original = Some(OriginalSource {
+            code,
+            pos_line_list,
+            offsets: transformed.offsets,
+        });
+    }
+
+    /// Keep comments, and attach them to statements. See ``Node::comments``.
+    /// All the comments, including those not attached to any statement, are in
+    /// ``lexer.comments``.
+    pub fn with_comments(mut self) -> Parser {
+        self.lexer.collect_comments = true;
+        self
     }
 
     /// Load file and generate Parser from the file.
+    /// A ``.ts`` file is parsed as TypeScript. See ``ParserOptions::typescript``.
     /// ## Arguments
     /// * `file_name` - A module file name.
     pub fn load_module(file_name: impl Into<String>) -> Result<Parser, Error> {
         let file_name = file_name.into();
-        let path = Path::new(&file_name).with_extension("js");
+        let path = Path::new(&file_name);
+        let typescript = path.extension().map_or(false, |ext| ext == "ts");
+        let path = if typescript {
+            path.to_path_buf()
+        } else {
+            path.with_extension("js")
+        };
         let absolute_path = match path.canonicalize() {
             Ok(path) => path,
             Err(ioerr) => {
@@ -96,14 +239,43 @@ impl Parser {
             }
         };
 
-        Ok(Parser::new(absolute_path.to_string_lossy(), file_body))
+        Ok(
+            Parser::new(absolute_path.to_string_lossy(), file_body).with_options(ParserOptions {
+                typescript,
+                ..ParserOptions::default()
+            }),
+        )
+    }
+
+    /// The script being parsed, to compile it with the positions of its instructions.
+    pub fn source_text(&self) -> SourceText<'_> {
+        match &self.original {
+            Some(original) => SourceText {
+                code: &original.code,
+                pos_line_list: &original.pos_line_list,
+                offsets: Some(&original.offsets),
+            },
+            None => SourceText {
+                code: &self.lexer.code,
+                pos_line_list: &self.lexer.pos_line_list,
+                offsets: None,
+            },
+        }
     }
 
+    /// The script before a source transform, if any.
     pub fn into_script_info(self) -> ScriptInfo {
-        ScriptInfo {
-            file_name: self.file_name,
-            code: self.lexer.code,
-            pos_line_list: self.lexer.pos_line_list,
+        match self.original {
+            Some(original) => ScriptInfo {
+                file_name: self.file_name,
+                code: original.code,
+                pos_line_list: original.pos_line_list,
+            },
+            None => ScriptInfo {
+                file_name: self.file_name,
+                code: self.lexer.code,
+                pos_line_list: self.lexer.pos_line_list,
+            },
         }
     }
 
@@ -112,13 +284,28 @@ impl Parser {
     /// * `pos` - A char position in the source script.
     /// * `msg` - An error message text.
     pub fn show_error_at(&self, pos: usize, msg: impl Into<String>) {
-        let (source_at_err_point, _pos, line) = self.lexer.get_code_around_err_point(pos);
+        self.show_message_at(Colour::Red.bold().paint("SyntaxError"), pos, msg.into())
+    }
+
+    /// Display a warning at ``pos`` in the source script.
+    pub fn show_warning_at(&self, pos: usize, msg: impl Into<String>) {
+        self.show_message_at(Colour::Yellow.bold().paint("Warning"), pos, msg.into())
+    }
+
+    /// Display a note on the previous error, e.g. where a redeclared name was first declared.
+    pub fn show_note_at(&self, pos: usize, msg: impl Into<String>) {
+        self.show_message_at(Colour::Cyan.bold().paint("Note"), pos, msg.into())
+    }
+
+    fn show_message_at(&self, label: ANSIString, pos: usize, msg: String) {
+        let source = self.source_text();
+        let pos = source.original_pos(pos);
+        let (source_at_pos, _pos, _line) =
+            lexer::code_around_err_point(source.code, source.pos_line_list, pos);
+        let (line, column) = lexer::line_column(source.code, source.pos_line_list, pos);
         eprintln!(
-            "{}: line {}: {}\n{}",
-            Colour::Red.bold().paint("SyntaxError"),
-            line,
-            msg.into(): String,
-            source_at_err_point,
+            "{} at {}:{}:{}: {}\n{}",
+            label, self.file_name, line, column, msg, source_at_pos,
         );
     }
 
@@ -126,22 +313,50 @@ impl Parser {
     /// ## Arguments
     /// * `err` - parser::Error.
     pub fn handle_error(&self, err: &Error) {
+        self.show_error_at(self.error_pos(err), Parser::error_message(err));
+        if let Error::Redeclaration(_, prev_pos, _) = err {
+            self.show_note_at(*prev_pos, "Previously declared here")
+        }
+    }
+
+    /// The char position where ``err`` occurred.
+    pub fn error_pos(&self, err: &Error) -> usize {
         match err {
             Error::NormalEOF => unreachable!(),
-            Error::Expect(pos, msg)
-            | Error::General(pos, msg)
-            | Error::UnexpectedToken(pos, msg) => {
-                self.show_error_at(*pos, msg.clone());
-            }
-            Error::UnexpectedEOF(msg) => {
-                self.show_error_at(self.lexer.pos, format!("unexpected EOF. {}", msg))
+            Error::Expect(pos, _)
+            | Error::General(pos, _)
+            | Error::UnexpectedToken(pos, _)
+            | Error::InvalidToken(pos)
+            | Error::UnsupportedFeature(pos)
+            | Error::Redeclaration(pos, _, _) => *pos,
+            Error::UnexpectedEOF(_) => self.lexer.pos,
+        }
+    }
+
+    /// Syntax error message without the position.
+    /// ## Arguments
+    /// * `err` - parser::Error.
+    pub fn error_message(err: &Error) -> String {
+        match err {
+            Error::NormalEOF => unreachable!(),
+            Error::Expect(_, msg) | Error::General(_, msg) | Error::UnexpectedToken(_, msg) => {
+                msg.clone()
             }
-            Error::InvalidToken(pos) => self.show_error_at(*pos, "Invalid token."),
-            Error::UnsupportedFeature(pos) => {
-                self.show_error_at(*pos, "Unsupported feature.");
+            Error::UnexpectedEOF(msg) => format!("unexpected EOF. {}", msg),
+            Error::InvalidToken(_) => "Invalid token.".to_string(),
+            Error::UnsupportedFeature(_) => "Unsupported feature.".to_string(),
+            Error::Redeclaration(_, _, name) => {
+                format!("Identifier '{}' has already been declared", name)
             }
         }
     }
+
+    /// Display all the syntax errors.
+    pub fn handle_errors(&self, errs: &[Error]) {
+        for err in errs {
+            self.handle_error(err)
+        }
+    }
 }
 
 impl Parser {
@@ -149,11 +364,148 @@ impl Parser {
         self.lexer.tokenize_all()?;
         self.read_script()
     }
+
+    /// Parse the whole script. On a syntax error, skip to the next statement and go on
+    /// parsing, so that all the syntax errors are reported at once.
+    pub fn parse_all_with_recovery(&mut self) -> Result<Node, Vec<Error>> {
+        self.lexer.tokenize_all().map_err(|err| vec![err])?;
+        self.recover = true;
+        let res = self.read_script();
+        self.recover = false;
+        let mut errs = ::std::mem::replace(&mut self.diagnostics, vec![]);
+        match res {
+            Ok(node) if errs.is_empty() => Ok(node),
+            Ok(_) => Err(errs),
+            Err(err) => {
+                errs.push(err);
+                Err(errs)
+            }
+        }
+    }
+
+    /// Record the error if recovering, and skip tokens to the next statement boundary:
+    /// a semicolon or line terminator not nested in brackets, or before a '}' closing the
+    /// current block. ``start`` is the token position where the statement began.
+    fn recover_from_error(&mut self, err: Error, start: usize) -> Result<(), Error> {
+        match err {
+            Error::NormalEOF | Error::UnexpectedEOF(_) => return Err(err),
+            _ if !self.recover => return Err(err),
+            _ => self.diagnostics.push(err),
+        }
+
+        // Always make progress.
+        if self.lexer.token_pos == start {
+            self.lexer.next()?;
+        }
+
+        let mut depth = 0;
+        while let Ok(tok) = self.lexer.peek(0) {
+            match tok.kind {
+                Kind::Symbol(Symbol::OpeningParen)
+                | Kind::Symbol(Symbol::OpeningBrace)
+                | Kind::Symbol(Symbol::OpeningBoxBracket) => depth += 1,
+                Kind::Symbol(Symbol::ClosingBrace) if depth == 0 => break,
+                Kind::Symbol(Symbol::ClosingParen)
+                | Kind::Symbol(Symbol::ClosingBrace)
+                | Kind::Symbol(Symbol::ClosingBoxBracket) => {
+                    if depth > 0 {
+                        depth -= 1
+                    }
+                }
+                Kind::Symbol(Symbol::Semicolon) | Kind::LineTerminator if depth == 0 => {
+                    self.lexer.next()?;
+                    break;
+                }
+                _ => {}
+            }
+            self.lexer.next()?;
+        }
+
+        Ok(())
+    }
+
+    /// Take the comments between the previous token and the next one.
+    fn take_leading_comments(&mut self) -> Vec<Comment> {
+        let prev_end = self.lexer.get_prev_end();
+        let next_pos = match self.lexer.peek_skip_lineterminator() {
+            Ok(tok) => tok.pos,
+            Err(_) => return vec![],
+        };
+        let comments = &self.lexer.comments;
+        while self.next_comment < comments.len() && comments[self.next_comment].pos < prev_end {
+            self.next_comment += 1;
+        }
+        let start = self.next_comment;
+        while self.next_comment < comments.len() && comments[self.next_comment].end <= next_pos {
+            self.next_comment += 1;
+        }
+        comments[start..self.next_comment].to_vec()
+    }
+
+    /// Attach ``leading_comments`` and the comment following ``node`` on the same line.
+    fn attach_comments(&mut self, mut node: Node, leading_comments: Vec<Comment>) -> Node {
+        node.comments = leading_comments;
+        // The next token is a line terminator if the statement is the last one on the line.
+        let next_pos = self
+            .lexer
+            .peek(0)
+            .map_or(self.lexer.code.len(), |tok| tok.pos);
+        if let Some(comment) = self.lexer.comments.get(self.next_comment) {
+            if comment.pos >= node.end && comment.end <= next_pos {
+                node.comments.push(comment.clone());
+                self.next_comment += 1;
+            }
+        }
+        node
+    }
+
+    /// Call ``read`` one level deeper in the AST. Fail with a syntax error instead of
+    /// overflowing the native stack on deeply nested input.
+    /// Error if ``feature`` introduced in ECMAScript ``version`` is not accepted by the options.
+    fn require_ecma_version(&self, version: u32, feature: &str, pos: usize) -> Result<(), Error> {
+        if self.options.ecma_version < version {
+            return Err(Error::General(
+                pos,
+                format!("{} requires ECMAScript {} or later.", feature, version),
+            ));
+        }
+        Ok(())
+    }
+
+    fn with_depth_check(
+        &mut self,
+        read: fn(&mut Parser) -> Result<Node, Error>,
+    ) -> Result<Node, Error> {
+        let depth = self.depth;
+        let res = self.deepen().and_then(|_| read(self));
+        self.depth = depth;
+        res
+    }
+
+    /// Go one level deeper in the AST.
+    fn deepen(&mut self) -> Result<(), Error> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING_DEPTH || self.stack_limit.is_exceeded() {
+            return Err(Error::General(
+                self.lexer.get_current_pos(),
+                "Nesting is too deep".to_string(),
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl Parser {
     fn read_script(&mut self) -> Result<Node, Error> {
-        self.read_statement_list()
+        if self.options.typescript && !cfg!(feature = "typescript") {
+            return Err(Error::UnsupportedFeature(0));
+        }
+        self.depth = 0;
+        self.stack_limit = StackLimit::new();
+        self.next_comment = 0;
+        let mut node = self.read_statement_list()?;
+        node.assign_ids(1);
+        Ok(node)
     }
 }
 
@@ -184,6 +536,7 @@ impl Parser {
         let mut items = vec![];
 
         loop {
+            let start = self.lexer.token_pos;
             match self
                 .lexer
                 .next_if_skip_lineterminator(Kind::Symbol(Symbol::ClosingBrace))
@@ -192,10 +545,12 @@ impl Parser {
                     if break_when_closingbrase {
                         break;
                     } else {
-                        return Err(Error::UnexpectedToken(
+                        let err = Error::UnexpectedToken(
                             self.lexer.get_prev_pos(),
                             "unexpected token '}'.".to_string(),
-                        ));
+                        );
+                        self.recover_from_error(err, start)?;
+                        continue;
                     }
                 }
                 Ok(false) => {}
@@ -211,12 +566,14 @@ impl Parser {
                 }
             }
 
+            let start = self.lexer.token_pos;
+            let leading_comments = self.take_leading_comments();
             match self.read_statement_list_item() {
-                Ok(ok) => items.push(ok),
+                Ok(ok) => items.push(self.attach_comments(ok, leading_comments)),
                 Err(Error::NormalEOF) => {
                     return Err(Error::UnexpectedEOF("".to_string()));
                 }
-                Err(e) => return Err(e),
+                Err(e) => self.recover_from_error(e, start)?,
             }
 
             while match self
@@ -230,14 +587,28 @@ impl Parser {
         }
 
         if is_block_statement {
-            Ok(Node::new(NodeBase::Block(items), pos))
+            Ok(Node::new_span(
+                NodeBase::Block(items),
+                pos,
+                self.lexer.get_prev_end(),
+            ))
         } else {
-            Ok(Node::new(NodeBase::StatementList(items), pos))
+            Ok(Node::new_span(
+                NodeBase::StatementList(items),
+                pos,
+                self.lexer.get_prev_end(),
+            ))
         }
     }
 
     /// https://tc39.github.io/ecma262/#prod-StatementListItem
     fn read_statement_list_item(&mut self) -> Result<Node, Error> {
+        #[cfg(feature = "typescript")]
+        {
+            if let Some(declaration) = self.read_typescript_declaration()? {
+                return Ok(declaration);
+            }
+        }
         if let Ok(tok) = self.lexer.peek_skip_lineterminator() {
             match tok.kind {
                 Kind::Keyword(Keyword::Function) => self.read_declaration(),
@@ -252,6 +623,10 @@ impl Parser {
 
     /// http://www.ecma-international.org/ecma-262/9.0/index.html#prod-Statement
     fn read_statement(&mut self) -> Result<Node, Error> {
+        self.with_depth_check(Parser::read_statement_body)
+    }
+
+    fn read_statement_body(&mut self) -> Result<Node, Error> {
         let tok = self.lexer.next_skip_lineterminator()?;
 
         // Label
@@ -268,9 +643,10 @@ impl Parser {
                 );
                 // TODO: https://tc39.github.io/ecma262/#prod-LabelledStatement
                 let labeled_item = self.read_statement_list_item()?;
-                return Ok(Node::new(
+                return Ok(Node::new_span(
                     NodeBase::Label(name.clone(), Box::new(labeled_item)),
                     tok.pos,
+                    self.lexer.get_prev_end(),
                 ));
             }
         }
@@ -285,14 +661,25 @@ impl Parser {
             Kind::Keyword(Keyword::Continue) => self.read_continue_statement(),
             Kind::Keyword(Keyword::Try) => self.read_try_statement(),
             Kind::Keyword(Keyword::Throw) => self.read_throw_statement(),
+            Kind::Keyword(Keyword::Debugger) => Ok(Node::new_span(
+                NodeBase::Debugger,
+                tok.pos,
+                self.lexer.get_prev_end(),
+            )),
             Kind::Symbol(Symbol::OpeningBrace) => self.read_block_statement(),
-            Kind::Symbol(Symbol::Semicolon) => return Ok(Node::new(NodeBase::Nope, tok.pos)),
+            Kind::Symbol(Symbol::Semicolon) => {
+                return Ok(Node::new_span(
+                    NodeBase::Nope,
+                    tok.pos,
+                    self.lexer.get_prev_end(),
+                ))
+            }
             _ => {
                 self.lexer.unget();
                 is_expression_statement = true;
                 self.read_expression_statement()
             }
-        };
+        }?;
 
         match self
             .lexer
@@ -315,7 +702,7 @@ impl Parser {
             Err(e) => return Err(e),
         }
 
-        stmt
+        Ok(stmt)
     }
 }
 
@@ -337,7 +724,11 @@ impl Parser {
             }
         }
 
-        Ok(Node::new(NodeBase::StatementList(list), pos))
+        Ok(Node::new_span(
+            NodeBase::StatementList(list),
+            pos,
+            self.lexer.get_prev_end(),
+        ))
     }
 
     fn variable_declaration_continuation(&mut self) -> Result<bool, Error> {
@@ -347,7 +738,8 @@ impl Parser {
             match self.lexer.peek(i) {
                 Ok(tok) => match tok.kind {
                     Kind::LineTerminator => newline_found = true,
-                    Kind::Symbol(Symbol::Semicolon) => {
+                    // A semicolon is automatically inserted before '}'
+                    Kind::Symbol(Symbol::Semicolon) | Kind::Symbol(Symbol::ClosingBrace) => {
                         return Ok(false);
                     }
                     Kind::Symbol(Symbol::Comma) => {
@@ -379,17 +771,24 @@ impl Parser {
                 ));
             }
         };
+        #[cfg(feature = "typescript")]
+        self.skip_variable_type()?;
 
         if self
             .lexer
             .next_if_skip_lineterminator(Kind::Symbol(Symbol::Assign))?
         {
-            Ok(Node::new(
+            Ok(Node::new_span(
                 NodeBase::VarDecl(name, Some(Box::new(self.read_initializer()?)), VarKind::Var),
                 pos,
+                self.lexer.get_prev_end(),
             ))
         } else {
-            Ok(Node::new(NodeBase::VarDecl(name, None, VarKind::Var), pos))
+            Ok(Node::new_span(
+                NodeBase::VarDecl(name, None, VarKind::Var),
+                pos,
+                self.lexer.get_prev_end(),
+            ))
         }
     }
 
@@ -418,22 +817,24 @@ impl Parser {
         if let Ok(expect_else_tok) = self.lexer.next_skip_lineterminator() {
             if expect_else_tok.kind == Kind::Keyword(Keyword::Else) {
                 let else_ = self.read_statement()?;
-                return Ok(Node::new(
+                return Ok(Node::new_span(
                     NodeBase::If(Box::new(cond), Box::new(then_), Box::new(else_)),
                     pos,
+                    self.lexer.get_prev_end(),
                 ));
             } else {
                 self.lexer.unget();
             }
         }
 
-        Ok(Node::new(
+        Ok(Node::new_span(
             NodeBase::If(
                 Box::new(cond),
                 Box::new(then_),
                 Box::new(Node::new(NodeBase::Nope, pos_else)),
             ),
             pos,
+            self.lexer.get_prev_end(),
         ))
     }
 }
@@ -450,9 +851,10 @@ impl Parser {
 
         let body = self.read_statement()?;
 
-        Ok(Node::new(
+        Ok(Node::new_span(
             NodeBase::While(Box::new(cond), Box::new(body)),
             pos,
+            self.lexer.get_prev_end(),
         ))
     }
 
@@ -469,13 +871,21 @@ impl Parser {
             Kind::Keyword(Keyword::Let) | Kind::Keyword(Keyword::Const) => {
                 self.read_declaration()?
             }
-            Kind::Symbol(Symbol::Semicolon) => Node::new(NodeBase::Nope, self.lexer.get_prev_pos()),
+            Kind::Symbol(Symbol::Semicolon) => Node::new_span(
+                NodeBase::Nope,
+                self.lexer.get_prev_pos(),
+                self.lexer.get_prev_end(),
+            ),
             _ => self.read_expression()?,
         };
         expect!(self, Kind::Symbol(Symbol::Semicolon), "expect ';'");
 
         let cond = if self.lexer.next_if(Kind::Symbol(Symbol::Semicolon)) {
-            Node::new(NodeBase::Boolean(true), self.lexer.get_prev_pos())
+            Node::new_span(
+                NodeBase::Boolean(true),
+                self.lexer.get_prev_pos(),
+                self.lexer.get_prev_end(),
+            )
         } else {
             let step = self.read_expression()?;
             expect!(self, Kind::Symbol(Symbol::Semicolon), "expect ';'");
@@ -483,7 +893,11 @@ impl Parser {
         };
 
         let step = if self.lexer.next_if(Kind::Symbol(Symbol::ClosingParen)) {
-            Node::new(NodeBase::Nope, self.lexer.get_prev_pos())
+            Node::new_span(
+                NodeBase::Nope,
+                self.lexer.get_prev_pos(),
+                self.lexer.get_prev_end(),
+            )
         } else {
             let step = self.read_expression()?;
             expect!(self, Kind::Symbol(Symbol::ClosingParen), "expect ')'");
@@ -492,7 +906,7 @@ impl Parser {
 
         let body = self.read_statement()?;
 
-        let for_node = Node::new(
+        let for_node = Node::new_span(
             NodeBase::For(
                 Box::new(init),
                 Box::new(cond),
@@ -500,9 +914,14 @@ impl Parser {
                 Box::new(body),
             ),
             pos,
+            self.lexer.get_prev_end(),
         );
 
-        Ok(Node::new(NodeBase::Block(vec![for_node]), pos))
+        Ok(Node::new_span(
+            NodeBase::Block(vec![for_node]),
+            pos,
+            self.lexer.get_prev_end(),
+        ))
     }
 }
 
@@ -515,9 +934,17 @@ impl Parser {
             | Kind::Symbol(Symbol::Semicolon)
             | Kind::Symbol(Symbol::ClosingBrace) => {
                 self.lexer.unget();
-                Ok(Node::new(NodeBase::Break(None), pos))
+                Ok(Node::new_span(
+                    NodeBase::Break(None),
+                    pos,
+                    self.lexer.get_prev_end(),
+                ))
             }
-            Kind::Identifier(name) => Ok(Node::new(NodeBase::Break(Some(name)), pos)),
+            Kind::Identifier(name) => Ok(Node::new_span(
+                NodeBase::Break(Some(name)),
+                pos,
+                self.lexer.get_prev_end(),
+            )),
             _ => Err(Error::UnexpectedToken(
                 tok.pos,
                 "expected ';', identifier or line terminator".to_string(),
@@ -533,9 +960,17 @@ impl Parser {
             | Kind::Symbol(Symbol::Semicolon)
             | Kind::Symbol(Symbol::ClosingBrace) => {
                 self.lexer.unget();
-                Ok(Node::new(NodeBase::Continue(None), pos))
+                Ok(Node::new_span(
+                    NodeBase::Continue(None),
+                    pos,
+                    self.lexer.get_prev_end(),
+                ))
             }
-            Kind::Identifier(name) => Ok(Node::new(NodeBase::Continue(Some(name)), pos)),
+            Kind::Identifier(name) => Ok(Node::new_span(
+                NodeBase::Continue(Some(name)),
+                pos,
+                self.lexer.get_prev_end(),
+            )),
             _ => Err(Error::UnexpectedToken(
                 tok.pos,
                 "expected ';', identifier or line terminator".to_string(),
@@ -546,21 +981,24 @@ impl Parser {
 
 macro_rules! expression { ( $name:ident, $lower:ident, [ $( $op:path ),* ] ) => {
     fn $name (&mut self) -> Result<Node, Error> {
+        let depth = self.depth;
         let mut lhs = self. $lower ()?;
         while let Ok(tok) = self.lexer.peek_skip_lineterminator() {
             match tok.kind {
                 Kind::Symbol(ref op) if $( op == &$op )||* => {
                     self.lexer.next_skip_lineterminator().unwrap();
+                    self.deepen()?;
                     let pos = self.lexer.get_current_pos();
-                    lhs = Node::new(NodeBase::BinaryOp(
+                    lhs = Node::new_span(NodeBase::BinaryOp(
                         Box::new(lhs),
                         Box::new(self. $lower ()?),
                         op.as_binop().unwrap(),
-                    ), pos);
+                    ), pos, self.lexer.get_prev_end());
                 }
                 _ => break
             }
         }
+        self.depth = depth;
         Ok(lhs)
     }
 } }
@@ -574,8 +1012,12 @@ impl Parser {
     expression!(read_expression, read_assignment_expression, [Symbol::Comma]);
 
     /// https://tc39.github.io/ecma262/#prod-AssignmentExpression
-    // TODO: Implement all features.
     fn read_assignment_expression(&mut self) -> Result<Node, Error> {
+        self.with_depth_check(Parser::read_assignment_expression_body)
+    }
+
+    // TODO: Implement all features.
+    fn read_assignment_expression_body(&mut self) -> Result<Node, Error> {
         self.lexer.skip_lineterminator()?;
         let pos = self.lexer.get_current_pos();
 
@@ -589,6 +1031,7 @@ impl Parser {
                 if f.is_err() {
                     self.lexer.token_pos = save_pos;
                 } else {
+                    self.require_ecma_version(2015, "Arrow functions", pos)?;
                     return f;
                 }
             }
@@ -596,6 +1039,7 @@ impl Parser {
             Kind::Identifier(_) => match self.lexer.peek(1) {
                 Ok(tok) => {
                     if tok.kind == Kind::Symbol(Symbol::FatArrow) {
+                        self.require_ecma_version(2015, "Arrow functions", pos)?;
                         return self.read_arrow_function(false);
                     }
                 }
@@ -610,31 +1054,34 @@ impl Parser {
             macro_rules! assignop {
                 ($op:ident) => {{
                     let lhs_pos = lhs.pos;
-                    lhs = Node::new(
+                    lhs = Node::new_span(
                         NodeBase::Assign(
                             Box::new(lhs.clone()),
-                            Box::new(Node::new(
+                            Box::new(Node::new_span(
                                 NodeBase::BinaryOp(
                                     Box::new(lhs),
                                     Box::new(self.read_assignment_expression()?),
                                     BinOp::$op,
                                 ),
                                 pos,
+                                self.lexer.get_prev_end(),
                             )),
                         ),
                         lhs_pos,
+                        self.lexer.get_prev_end(),
                     );
                 }};
             }
             match tok.kind {
                 Kind::Symbol(Symbol::Assign) => {
                     let lhs_pos = lhs.pos;
-                    lhs = Node::new(
+                    lhs = Node::new_span(
                         NodeBase::Assign(
                             Box::new(lhs),
                             Box::new(self.read_assignment_expression()?),
                         ),
                         lhs_pos,
+                        self.lexer.get_prev_end(),
                     )
                 }
                 Kind::Symbol(Symbol::AssignAdd) => assignop!(Add),
@@ -660,9 +1107,10 @@ impl Parser {
                     let then_ = self.read_assignment_expression()?;
                     expect!(self, Kind::Symbol(Symbol::Colon), "expect ':'");
                     let else_ = self.read_assignment_expression()?;
-                    return Ok(Node::new(
+                    return Ok(Node::new_span(
                         NodeBase::TernaryOp(Box::new(lhs), Box::new(then_), Box::new(else_)),
                         pos,
+                        self.lexer.get_prev_end(),
                     ));
                 }
                 _ => self.lexer.unget(),
@@ -715,11 +1163,44 @@ impl Parser {
     );
 
     /// https://tc39.github.io/ecma262/#prod-RelationalExpression
-    expression!(
-        read_relational_expression,
-        read_shift_expression,
-        [Symbol::Lt, Symbol::Gt, Symbol::Le, Symbol::Ge]
-    );
+    /// Unlike the other binary operators, ``in`` is a keyword.
+    fn read_relational_expression(&mut self) -> Result<Node, Error> {
+        let depth = self.depth;
+        let mut lhs = self.read_type_assertion_expression()?;
+        while let Ok(tok) = self.lexer.peek_skip_lineterminator() {
+            let op = match tok.kind {
+                Kind::Symbol(ref op)
+                    if [Symbol::Lt, Symbol::Gt, Symbol::Le, Symbol::Ge].contains(op) =>
+                {
+                    op.as_binop().unwrap()
+                }
+                Kind::Keyword(Keyword::In) => BinOp::In,
+                _ => break,
+            };
+            self.lexer.next_skip_lineterminator().unwrap();
+            self.deepen()?;
+            let pos = self.lexer.get_current_pos();
+            lhs = Node::new_span(
+                NodeBase::BinaryOp(
+                    Box::new(lhs),
+                    Box::new(self.read_type_assertion_expression()?),
+                    op,
+                ),
+                pos,
+                self.lexer.get_prev_end(),
+            );
+        }
+        self.depth = depth;
+        Ok(lhs)
+    }
+
+    /// A ShiftExpression, followed by TypeScript ``as`` and ``satisfies`` if any.
+    fn read_type_assertion_expression(&mut self) -> Result<Node, Error> {
+        let expr = self.read_shift_expression()?;
+        #[cfg(feature = "typescript")]
+        self.skip_type_assertions()?;
+        Ok(expr)
+    }
 
     /// https://tc39.github.io/ecma262/#prod-ShiftExpression
     expression!(
@@ -744,6 +1225,10 @@ impl Parser {
 
     /// https://tc39.github.io/ecma262/#prod-ExponentiationExpression
     fn read_exponentiation_expression(&mut self) -> Result<Node, Error> {
+        self.with_depth_check(Parser::read_exponentiation_expression_body)
+    }
+
+    fn read_exponentiation_expression_body(&mut self) -> Result<Node, Error> {
         if self.is_unary_expression() {
             return self.read_unary_expression();
         }
@@ -751,13 +1236,15 @@ impl Parser {
         let lhs = self.read_update_expression()?;
         if let Ok(tok) = self.lexer.next() {
             if let Kind::Symbol(Symbol::Exp) = tok.kind {
-                return Ok(Node::new(
+                self.require_ecma_version(2016, "Exponentiation operator", tok.pos)?;
+                return Ok(Node::new_span(
                     NodeBase::BinaryOp(
                         Box::new(lhs),
                         Box::new(self.read_exponentiation_expression()?),
                         BinOp::Exp,
                     ),
                     pos,
+                    self.lexer.get_prev_end(),
                 ));
             } else {
                 self.lexer.unget();
@@ -784,36 +1271,58 @@ impl Parser {
 
     /// https://tc39.github.io/ecma262/#prod-UnaryExpression
     fn read_unary_expression(&mut self) -> Result<Node, Error> {
+        self.with_depth_check(Parser::read_unary_expression_body)
+    }
+
+    fn read_unary_expression_body(&mut self) -> Result<Node, Error> {
         let pos = self.lexer.get_current_pos();
         let tok = self.lexer.next()?;
         match tok.kind {
-            Kind::Keyword(Keyword::Delete) => Ok(Node::new(
-                NodeBase::UnaryOp(Box::new(self.read_unary_expression()?), UnaryOp::Delete),
-                pos,
-            )),
-            Kind::Keyword(Keyword::Void) => Ok(Node::new(
+            Kind::Keyword(Keyword::Delete) => {
+                let expr = self.read_unary_expression()?;
+                if let NodeBase::Identifier(_) = expr.base {
+                    if self.options.is_strict() {
+                        return Err(Error::General(
+                            pos,
+                            "Delete of an unqualified identifier in strict mode.".to_string(),
+                        ));
+                    }
+                }
+                Ok(Node::new_span(
+                    NodeBase::UnaryOp(Box::new(expr), UnaryOp::Delete),
+                    pos,
+                    self.lexer.get_prev_end(),
+                ))
+            }
+            Kind::Keyword(Keyword::Void) => Ok(Node::new_span(
                 NodeBase::UnaryOp(Box::new(self.read_unary_expression()?), UnaryOp::Void),
                 pos,
+                self.lexer.get_prev_end(),
             )),
-            Kind::Keyword(Keyword::Typeof) => Ok(Node::new(
+            Kind::Keyword(Keyword::Typeof) => Ok(Node::new_span(
                 NodeBase::UnaryOp(Box::new(self.read_unary_expression()?), UnaryOp::Typeof),
                 pos,
+                self.lexer.get_prev_end(),
             )),
-            Kind::Symbol(Symbol::Add) => Ok(Node::new(
+            Kind::Symbol(Symbol::Add) => Ok(Node::new_span(
                 NodeBase::UnaryOp(Box::new(self.read_unary_expression()?), UnaryOp::Plus),
                 pos,
+                self.lexer.get_prev_end(),
             )),
-            Kind::Symbol(Symbol::Sub) => Ok(Node::new(
+            Kind::Symbol(Symbol::Sub) => Ok(Node::new_span(
                 NodeBase::UnaryOp(Box::new(self.read_unary_expression()?), UnaryOp::Minus),
                 pos,
+                self.lexer.get_prev_end(),
             )),
-            Kind::Symbol(Symbol::BitwiseNot) => Ok(Node::new(
+            Kind::Symbol(Symbol::BitwiseNot) => Ok(Node::new_span(
                 NodeBase::UnaryOp(Box::new(self.read_unary_expression()?), UnaryOp::BitwiseNot),
                 pos,
+                self.lexer.get_prev_end(),
             )),
-            Kind::Symbol(Symbol::Not) => Ok(Node::new(
+            Kind::Symbol(Symbol::Not) => Ok(Node::new_span(
                 NodeBase::UnaryOp(Box::new(self.read_unary_expression()?), UnaryOp::Not),
                 pos,
+                self.lexer.get_prev_end(),
             )),
             _ => {
                 self.lexer.unget();
@@ -830,23 +1339,25 @@ impl Parser {
             Kind::Symbol(Symbol::Inc) => {
                 self.lexer.next_skip_lineterminator().unwrap();
                 let pos = self.lexer.get_prev_pos();
-                return Ok(Node::new(
+                return Ok(Node::new_span(
                     NodeBase::UnaryOp(
                         Box::new(self.read_left_hand_side_expression()?),
                         UnaryOp::PrInc,
                     ),
                     pos,
+                    self.lexer.get_prev_end(),
                 ));
             }
             Kind::Symbol(Symbol::Dec) => {
                 self.lexer.next_skip_lineterminator().unwrap();
                 let pos = self.lexer.get_prev_pos();
-                return Ok(Node::new(
+                return Ok(Node::new_span(
                     NodeBase::UnaryOp(
                         Box::new(self.read_left_hand_side_expression()?),
                         UnaryOp::PrDec,
                     ),
                     pos,
+                    self.lexer.get_prev_end(),
                 ));
             }
             _ => {}
@@ -858,16 +1369,18 @@ impl Parser {
             match tok.kind {
                 Kind::Symbol(Symbol::Inc) => {
                     self.lexer.next().unwrap();
-                    return Ok(Node::new(
+                    return Ok(Node::new_span(
                         NodeBase::UnaryOp(Box::new(e), UnaryOp::PoInc),
                         pos,
+                        self.lexer.get_prev_end(),
                     ));
                 }
                 Kind::Symbol(Symbol::Dec) => {
                     self.lexer.next().unwrap();
-                    return Ok(Node::new(
+                    return Ok(Node::new_span(
                         NodeBase::UnaryOp(Box::new(e), UnaryOp::PoDec),
                         pos,
+                        self.lexer.get_prev_end(),
                     ));
                 }
                 _ => {}
@@ -906,27 +1419,44 @@ impl Parser {
         {
             Ok(true) => {
                 let args = self.read_arguments()?;
-                lhs = Node::new(NodeBase::Call(Box::new(lhs), args), pos)
+                lhs = Node::new_span(
+                    NodeBase::Call(Box::new(lhs), args),
+                    pos,
+                    self.lexer.get_prev_end(),
+                )
             }
             _ => {
                 panic!("CallExpression MUST start with MemberExpression.");
             }
         };
 
+        let depth = self.depth;
         while let Ok(tok) = self.lexer.next_skip_lineterminator() {
             let pos_ = self.lexer.get_current_pos();
+            self.deepen()?;
             match tok.kind {
                 Kind::Symbol(Symbol::OpeningParen) => {
                     let args = self.read_arguments()?;
-                    lhs = Node::new(NodeBase::Call(Box::new(lhs), args), pos)
+                    lhs = Node::new_span(
+                        NodeBase::Call(Box::new(lhs), args),
+                        pos,
+                        self.lexer.get_prev_end(),
+                    )
                 }
                 Kind::Symbol(Symbol::Point) => match self.lexer.next_skip_lineterminator()?.kind {
                     Kind::Identifier(name) => {
-                        lhs = Node::new(NodeBase::Member(Box::new(lhs), name), pos)
+                        lhs = Node::new_span(
+                            NodeBase::Member(Box::new(lhs), name),
+                            pos,
+                            self.lexer.get_prev_end(),
+                        )
                     }
                     Kind::Keyword(kw) => {
-                        lhs =
-                            Node::new(NodeBase::Member(Box::new(lhs), kw.to_str().to_owned()), pos)
+                        lhs = Node::new_span(
+                            NodeBase::Member(Box::new(lhs), kw.to_str().to_owned()),
+                            pos,
+                            self.lexer.get_prev_end(),
+                        )
                     }
                     _ => {
                         return Err(Error::Expect(pos_, "expect identifier".to_string()));
@@ -940,7 +1470,18 @@ impl Parser {
                             "expect ']'".to_string(),
                         ));
                     }
-                    lhs = Node::new(NodeBase::Index(Box::new(lhs), Box::new(idx)), pos);
+                    lhs = Node::new_span(
+                        NodeBase::Index(Box::new(lhs), Box::new(idx)),
+                        pos,
+                        self.lexer.get_prev_end(),
+                    );
+                }
+                #[cfg(feature = "typescript")]
+                Kind::Symbol(Symbol::Not) if self.is_non_null_assertion() => {}
+                #[cfg(feature = "typescript")]
+                Kind::Symbol(Symbol::Lt) if self.is_call_type_arguments() => {
+                    self.lexer.unget();
+                    self.skip_type_arguments()?;
                 }
                 _ => {
                     self.lexer.unget();
@@ -949,6 +1490,7 @@ impl Parser {
             }
         }
 
+        self.depth = depth;
         Ok(lhs)
     }
 
@@ -960,25 +1502,42 @@ impl Parser {
         {
             self.lexer.next_skip_lineterminator()?;
             let call_pos = self.lexer.get_current_pos();
-            let lhs = self.read_member_expression()?;
+            let lhs = self.with_depth_check(Parser::read_member_expression)?;
             expect!(self, Kind::Symbol(Symbol::OpeningParen), "expect '('.");
             let args = self.read_arguments()?;
-            let call_node = Node::new(NodeBase::Call(Box::new(lhs), args), call_pos);
-            let new_node = Node::new(NodeBase::New(Box::new(call_node)), pos);
+            let call_node = Node::new_span(
+                NodeBase::Call(Box::new(lhs), args),
+                call_pos,
+                self.lexer.get_prev_end(),
+            );
+            let new_node = Node::new_span(
+                NodeBase::New(Box::new(call_node)),
+                pos,
+                self.lexer.get_prev_end(),
+            );
             new_node
         } else {
             self.read_primary_expression()?
         };
+        let depth = self.depth;
         while let Ok(tok) = self.lexer.next_skip_lineterminator() {
             let pos_ = self.lexer.get_current_pos();
+            self.deepen()?;
             match tok.kind {
                 Kind::Symbol(Symbol::Point) => match self.lexer.next_skip_lineterminator()?.kind {
                     Kind::Identifier(name) => {
-                        lhs = Node::new(NodeBase::Member(Box::new(lhs), name), pos)
+                        lhs = Node::new_span(
+                            NodeBase::Member(Box::new(lhs), name),
+                            pos,
+                            self.lexer.get_prev_end(),
+                        )
                     }
                     Kind::Keyword(kw) => {
-                        lhs =
-                            Node::new(NodeBase::Member(Box::new(lhs), kw.to_str().to_owned()), pos)
+                        lhs = Node::new_span(
+                            NodeBase::Member(Box::new(lhs), kw.to_str().to_owned()),
+                            pos,
+                            self.lexer.get_prev_end(),
+                        )
                     }
                     _ => {
                         return Err(Error::Expect(pos_, "expect identifier".to_string()));
@@ -992,7 +1551,18 @@ impl Parser {
                             "expect ']'".to_string(),
                         ));
                     }
-                    lhs = Node::new(NodeBase::Index(Box::new(lhs), Box::new(idx)), pos);
+                    lhs = Node::new_span(
+                        NodeBase::Index(Box::new(lhs), Box::new(idx)),
+                        pos,
+                        self.lexer.get_prev_end(),
+                    );
+                }
+                #[cfg(feature = "typescript")]
+                Kind::Symbol(Symbol::Not) if self.is_non_null_assertion() => {}
+                #[cfg(feature = "typescript")]
+                Kind::Symbol(Symbol::Lt) if self.is_call_type_arguments() => {
+                    self.lexer.unget();
+                    self.skip_type_arguments()?;
                 }
                 _ => {
                     self.lexer.unget();
@@ -1001,6 +1571,7 @@ impl Parser {
             }
         }
 
+        self.depth = depth;
         Ok(lhs)
     }
 
@@ -1048,29 +1619,57 @@ impl Parser {
         let tok = self.lexer.next_skip_lineterminator()?;
 
         match tok.kind {
-            Kind::Keyword(Keyword::This) => Ok(Node::new(NodeBase::This, tok.pos)),
-            // Kind::Keyword(Keyword::Arguments) => Ok(Node::new(NodeBase::Arguments, tok.pos)),
+            Kind::Keyword(Keyword::This) => Ok(Node::new_span(
+                NodeBase::This,
+                tok.pos,
+                self.lexer.get_prev_end(),
+            )),
+            // Kind::Keyword(Keyword::Arguments) => Ok(Node::new_span(NodeBase::Arguments, tok.pos, self.lexer.get_prev_end())),
             Kind::Keyword(Keyword::Function) => self.read_function_expression(),
             Kind::Symbol(Symbol::OpeningParen) => {
-                let expr = self.read_expression();
+                let expr = self.read_expression()?;
                 expect!(self, Kind::Symbol(Symbol::ClosingParen), "expect ')'");
-                expr
+                Ok(expr)
             }
             Kind::Symbol(Symbol::OpeningBoxBracket) => self.read_array_literal(),
             Kind::Symbol(Symbol::OpeningBrace) => self.read_object_literal(),
-            Kind::Identifier(ref i) if i == "true" => {
-                Ok(Node::new(NodeBase::Boolean(true), tok.pos))
-            }
-            Kind::Identifier(ref i) if i == "false" => {
-                Ok(Node::new(NodeBase::Boolean(false), tok.pos))
-            }
+            Kind::Identifier(ref i) if i == "true" => Ok(Node::new_span(
+                NodeBase::Boolean(true),
+                tok.pos,
+                self.lexer.get_prev_end(),
+            )),
+            Kind::Identifier(ref i) if i == "false" => Ok(Node::new_span(
+                NodeBase::Boolean(false),
+                tok.pos,
+                self.lexer.get_prev_end(),
+            )),
             // Kind::Identifier(ref i) if i == "undefined" => {
-            //     Ok(Node::new(NodeBase::Undefined, tok.pos))
+            //     Ok(Node::new_span(NodeBase::Undefined, tok.pos, self.lexer.get_prev_end()))
             // }
-            Kind::Identifier(ref i) if i == "null" => Ok(Node::new(NodeBase::Null, tok.pos)),
-            Kind::Identifier(ident) => Ok(Node::new(NodeBase::Identifier(ident), tok.pos)),
-            Kind::String(s) => Ok(Node::new(NodeBase::String(s), tok.pos)),
-            Kind::Number(num) => Ok(Node::new(NodeBase::Number(num), tok.pos)),
+            Kind::Identifier(ref i) if i == "null" => Ok(Node::new_span(
+                NodeBase::Null,
+                tok.pos,
+                self.lexer.get_prev_end(),
+            )),
+            Kind::Identifier(ident) => Ok(Node::new_span(
+                NodeBase::Identifier(ident),
+                tok.pos,
+                self.lexer.get_prev_end(),
+            )),
+            Kind::String(s) => Ok(Node::new_span(
+                NodeBase::String(s),
+                tok.pos,
+                self.lexer.get_prev_end(),
+            )),
+            Kind::Number(num) => Ok(Node::new_span(
+                NodeBase::Number(num),
+                tok.pos,
+                self.lexer.get_prev_end(),
+            )),
+            #[cfg(feature = "jsx")]
+            Kind::Symbol(Symbol::Lt) if self.options.jsx => self.read_jsx_element(tok.pos),
+            #[cfg(not(feature = "jsx"))]
+            Kind::Symbol(Symbol::Lt) if self.options.jsx => Err(Error::UnsupportedFeature(tok.pos)),
             _ => Err(Error::UnexpectedToken(
                 tok.pos,
                 format!("unexpected token."),
@@ -1085,6 +1684,8 @@ impl Parser {
         if is_parenthesized_param {
             expect!(self, Kind::Symbol(Symbol::OpeningParen), "expect '('");
             params = self.read_formal_parameters()?;
+            #[cfg(feature = "typescript")]
+            self.skip_type_annotation()?;
         } else {
             let param_name = match self.lexer.next()?.kind {
                 Kind::Identifier(s) => s,
@@ -1104,14 +1705,16 @@ impl Parser {
             self.read_block()?
         } else {
             let pos = self.lexer.get_current_pos();
-            Node::new(
+            Node::new_span(
                 NodeBase::Return(Some(Box::new(self.read_assignment_expression()?))),
                 pos,
+                self.lexer.get_prev_end(),
             )
         };
-        Ok(Node::new(
+        Ok(Node::new_span(
             NodeBase::ArrowFunction(params, Box::new(body)),
             params_pos,
+            self.lexer.get_prev_end(),
         ))
     }
 
@@ -1124,18 +1727,23 @@ impl Parser {
         } else {
             None
         };
+        #[cfg(feature = "typescript")]
+        self.skip_type_parameters()?;
 
         expect!(self, Kind::Symbol(Symbol::OpeningParen), "expect '('");
 
         let params = self.read_formal_parameters()?;
+        #[cfg(feature = "typescript")]
+        self.skip_type_annotation()?;
 
         expect!(self, Kind::Symbol(Symbol::OpeningBrace), "expect '{'");
 
         let body = self.read_block()?;
 
-        Ok(Node::new(
+        Ok(Node::new_span(
             NodeBase::FunctionExpr(name, params, Box::new(body)),
             pos,
+            self.lexer.get_prev_end(),
         ))
     }
 
@@ -1147,7 +1755,11 @@ impl Parser {
         loop {
             // TODO: Support all features.
             while self.lexer.next_if(Kind::Symbol(Symbol::Comma)) {
-                elements.push(Node::new(NodeBase::Nope, pos));
+                elements.push(Node::new_span(
+                    NodeBase::Nope,
+                    pos,
+                    self.lexer.get_prev_end(),
+                ));
             }
 
             if self.lexer.next_if(Kind::Symbol(Symbol::ClosingBoxBracket)) {
@@ -1162,16 +1774,26 @@ impl Parser {
                 .lexer
                 .next_if_skip_lineterminator(Kind::Symbol(Symbol::Spread))?
             {
+                let pos = self.lexer.get_prev_pos();
+                self.require_ecma_version(2015, "Spread elements", pos)?;
                 let node = self.read_assignment_expression()?;
                 let pos = node.pos;
-                elements.push(Node::new(NodeBase::Spread(Box::new(node)), pos));
+                elements.push(Node::new_span(
+                    NodeBase::Spread(Box::new(node)),
+                    pos,
+                    self.lexer.get_prev_end(),
+                ));
             } else {
                 elements.push(self.read_assignment_expression()?);
             }
             self.lexer.next_if(Kind::Symbol(Symbol::Comma));
         }
 
-        Ok(Node::new(NodeBase::Array(elements), pos))
+        Ok(Node::new_span(
+            NodeBase::Array(elements),
+            pos,
+            self.lexer.get_prev_end(),
+        ))
     }
 
     /// https://tc39.github.io/ecma262/#prod-ObjectLiteral
@@ -1207,7 +1829,11 @@ impl Parser {
             }
         }
 
-        Ok(Node::new(NodeBase::Object(elements), pos))
+        Ok(Node::new_span(
+            NodeBase::Object(elements),
+            pos,
+            self.lexer.get_prev_end(),
+        ))
     }
 
     /// https://tc39.github.io/ecma262/#prod-PropertyDefinition
@@ -1225,6 +1851,8 @@ impl Parser {
             .lexer
             .next_if_skip_lineterminator(Kind::Symbol(Symbol::Spread))?
         {
+            let pos = self.lexer.get_prev_pos();
+            self.require_ecma_version(2018, "Object spread", pos)?;
             let node = self.read_assignment_expression()?;
             return Ok(PropertyDefinition::SpreadObject(node));
         }
@@ -1261,6 +1889,7 @@ impl Parser {
                 }
             }
 
+            self.require_ecma_version(2015, "Shorthand properties", tok.pos)?;
             return Ok(PropertyDefinition::IdentifierReference(name));
         }
 
@@ -1278,21 +1907,37 @@ impl Parser {
 
         // no LineTerminator here
         if self.lexer.next_if(Kind::LineTerminator) {
-            return Ok(Node::new(NodeBase::Return(None), pos));
+            return Ok(Node::new_span(
+                NodeBase::Return(None),
+                pos,
+                self.lexer.get_prev_end(),
+            ));
         }
 
         if self.lexer.next_if(Kind::Symbol(Symbol::Semicolon)) {
-            return Ok(Node::new(NodeBase::Return(None), pos));
+            return Ok(Node::new_span(
+                NodeBase::Return(None),
+                pos,
+                self.lexer.get_prev_end(),
+            ));
         }
 
         if self.lexer.peek(0)?.kind == Kind::Symbol(Symbol::ClosingBrace) {
-            return Ok(Node::new(NodeBase::Return(None), pos));
+            return Ok(Node::new_span(
+                NodeBase::Return(None),
+                pos,
+                self.lexer.get_prev_end(),
+            ));
         }
 
         let expr = self.read_expression()?;
         self.lexer.next_if(Kind::Symbol(Symbol::Semicolon));
 
-        Ok(Node::new(NodeBase::Return(Some(Box::new(expr))), pos))
+        Ok(Node::new_span(
+            NodeBase::Return(Some(Box::new(expr))),
+            pos,
+            self.lexer.get_prev_end(),
+        ))
     }
 }
 
@@ -1319,25 +1964,39 @@ impl Parser {
             .unwrap_or(false);
         let pos_catch = self.lexer.get_current_pos();
         let (catch, param) = if is_catch {
-            skip_symbol_or_error!(self.lexer, Symbol::OpeningParen);
-            // TODO: should accept BindingPattern
-            let pos_param = self.lexer.get_current_pos();
-            let catch_param = match self.lexer.next()?.kind {
-                Kind::Identifier(s) => Node::new(NodeBase::Identifier(s), pos_param),
-                _ => {
-                    return Err(Error::UnexpectedToken(
+            // https://tc39.github.io/ecma262/#prod-Catch
+            // CatchParameter is optional: `catch { ... }`
+            let catch_param = if self.lexer.next_if(Kind::Symbol(Symbol::OpeningParen)) {
+                // TODO: should accept BindingPattern
+                let pos_param = self.lexer.get_current_pos();
+                let catch_param = match self.lexer.next()?.kind {
+                    Kind::Identifier(s) => Node::new_span(
+                        NodeBase::Identifier(s),
                         pos_param,
-                        "expected identifier.".to_string(),
-                    ));
-                }
+                        self.lexer.get_prev_end(),
+                    ),
+                    _ => {
+                        return Err(Error::UnexpectedToken(
+                            pos_param,
+                            "expected identifier.".to_string(),
+                        ));
+                    }
+                };
+                skip_symbol_or_error!(self.lexer, Symbol::ClosingParen);
+                catch_param
+            } else {
+                Node::new_span(
+                    NodeBase::Nope,
+                    self.lexer.get_current_pos(),
+                    self.lexer.get_prev_end(),
+                )
             };
-            skip_symbol_or_error!(self.lexer, Symbol::ClosingParen);
             skip_symbol_or_error!(self.lexer, Symbol::OpeningBrace);
             (self.read_block()?, catch_param)
         } else {
             (
-                Node::new(NodeBase::Nope, pos_catch),
-                Node::new(NodeBase::Nope, pos_catch),
+                Node::new_span(NodeBase::Nope, pos_catch, self.lexer.get_prev_end()),
+                Node::new_span(NodeBase::Nope, pos_catch, self.lexer.get_prev_end()),
             )
         };
         let is_finally = self
@@ -1349,10 +2008,16 @@ impl Parser {
             skip_symbol_or_error!(self.lexer, Symbol::OpeningBrace);
             self.read_block_statement()?
         } else {
-            Node::new(NodeBase::Nope, pos_finally)
+            if !is_catch {
+                return Err(Error::Expect(
+                    pos_finally,
+                    "expected 'catch' or 'finally'".to_string(),
+                ));
+            }
+            Node::new_span(NodeBase::Nope, pos_finally, self.lexer.get_prev_end())
         };
 
-        Ok(Node::new(
+        Ok(Node::new_span(
             NodeBase::Try(
                 Box::new(try_clause),
                 Box::new(catch),
@@ -1360,6 +2025,7 @@ impl Parser {
                 Box::new(finally),
             ),
             pos_try,
+            self.lexer.get_prev_end(),
         ))
     }
 }
@@ -1395,7 +2061,11 @@ impl Parser {
         let expr = self.read_expression()?;
         self.lexer.next_if(Kind::Symbol(Symbol::Semicolon));
 
-        Ok(Node::new(NodeBase::Throw(Box::new(expr)), pos_throw))
+        Ok(Node::new_span(
+            NodeBase::Throw(Box::new(expr)),
+            pos_throw,
+            self.lexer.get_prev_end(),
+        ))
     }
 }
 
@@ -1404,8 +2074,10 @@ impl Parser {
         let tok = self.lexer.next_skip_lineterminator()?;
         match tok.kind {
             Kind::Keyword(Keyword::Function) => self.read_function_declaration(),
-            Kind::Keyword(Keyword::Const) => self.read_lexical_declaration(true),
-            Kind::Keyword(Keyword::Let) => self.read_lexical_declaration(false),
+            Kind::Keyword(Keyword::Const) | Kind::Keyword(Keyword::Let) => {
+                self.require_ecma_version(2015, "Lexical declarations", tok.pos)?;
+                self.read_lexical_declaration(tok.kind == Kind::Keyword(Keyword::Const))
+            }
             _ => unreachable!(),
         }
     }
@@ -1432,6 +2104,8 @@ impl Parser {
                     ));
                 }
             };
+            #[cfg(feature = "typescript")]
+            self.skip_variable_type()?;
 
             if self
                 .lexer
@@ -1439,9 +2113,18 @@ impl Parser {
             {
                 let init = Some(Box::new(self.read_initializer()?));
                 let decl = NodeBase::VarDecl(name, init, var_kind);
-                list.push(Node::new(decl, pos))
+                list.push(Node::new_span(decl, pos, self.lexer.get_prev_end()))
+            } else if is_const {
+                return Err(Error::General(
+                    pos,
+                    "Missing initializer in const declaration".to_string(),
+                ));
             } else {
-                list.push(Node::new(NodeBase::VarDecl(name, None, var_kind), pos))
+                list.push(Node::new_span(
+                    NodeBase::VarDecl(name, None, var_kind),
+                    pos,
+                    self.lexer.get_prev_end(),
+                ))
             }
 
             if !self.variable_declaration_continuation()? {
@@ -1449,7 +2132,11 @@ impl Parser {
             }
         }
 
-        Ok(Node::new(NodeBase::StatementList(list), pos))
+        Ok(Node::new_span(
+            NodeBase::StatementList(list),
+            pos,
+            self.lexer.get_prev_end(),
+        ))
     }
 
     /// https://tc39.github.io/ecma262/#prod-FunctionDeclaration
@@ -1463,18 +2150,33 @@ impl Parser {
                 "expect function name".to_string(),
             ));
         };
+        #[cfg(feature = "typescript")]
+        self.skip_type_parameters()?;
 
         expect!(self, Kind::Symbol(Symbol::OpeningParen), "expect '('");
 
         let params = self.read_formal_parameters()?;
 
+        #[cfg(feature = "typescript")]
+        {
+            self.skip_type_annotation()?;
+            if self.is_function_signature() {
+                return Ok(Node::new_span(
+                    NodeBase::Nope,
+                    pos,
+                    self.lexer.get_prev_end(),
+                ));
+            }
+        }
+
         expect!(self, Kind::Symbol(Symbol::OpeningBrace), "expect '{'");
 
         let body = self.read_block()?;
 
-        Ok(Node::new(
+        Ok(Node::new_span(
             NodeBase::FunctionDecl(name, params, Box::new(body)),
             pos,
+            self.lexer.get_prev_end(),
         ))
     }
 
@@ -1486,22 +2188,35 @@ impl Parser {
             return Ok(vec![]);
         }
 
-        let mut params = vec![];
+        let mut params: FormalParameters = vec![];
+        let mut param_pos = vec![];
 
         loop {
             let mut rest_param = false;
 
-            params.push(
-                if self
-                    .lexer
-                    .next_if_skip_lineterminator(Kind::Symbol(Symbol::Spread))?
-                {
-                    rest_param = true;
-                    self.read_function_rest_parameter()?
-                } else {
-                    self.read_formal_parameter()?
-                },
-            );
+            let param = if self
+                .lexer
+                .next_if_skip_lineterminator(Kind::Symbol(Symbol::Spread))?
+            {
+                rest_param = true;
+                let pos = self.lexer.get_prev_pos();
+                self.require_ecma_version(2015, "Rest parameters", pos)?;
+                self.read_function_rest_parameter()?
+            } else {
+                self.read_formal_parameter()?
+            };
+
+            // https://tc39.github.io/ecma262/#sec-function-definitions-static-semantics-early-errors
+            let pos = self.lexer.get_prev_pos();
+            #[cfg(feature = "typescript")]
+            self.skip_parameter_type()?;
+            if self.options.is_strict() {
+                if let Some(i) = params.iter().position(|p| p.name == param.name) {
+                    return Err(Error::Redeclaration(pos, param_pos[i], param.name));
+                }
+            }
+            params.push(param);
+            param_pos.push(pos);
 
             if self.lexer.next_if(Kind::Symbol(Symbol::ClosingParen)) {
                 break;
@@ -1552,6 +2267,761 @@ impl Parser {
     }
 }
 
+/// https://facebook.github.io/jsx/
+#[cfg(feature = "jsx")]
+impl Parser {
+    /// Read an element or a fragment after ``<``.
+    fn read_jsx_element(&mut self, pos: usize) -> Result<Node, Error> {
+        let name = if self
+            .lexer
+            .next_if_skip_lineterminator(Kind::Symbol(Symbol::Gt))?
+        {
+            None
+        } else {
+            Some(self.read_jsx_element_name()?)
+        };
+
+        let mut attributes = vec![];
+        let mut self_closing = false;
+        while name.is_some() {
+            let tok = self.lexer.next_skip_lineterminator()?;
+            match tok.kind {
+                Kind::Symbol(Symbol::Gt) => break,
+                Kind::Symbol(Symbol::Div) => {
+                    expect!(self, Kind::Symbol(Symbol::Gt), "expect '>'");
+                    self_closing = true;
+                    break;
+                }
+                Kind::Symbol(Symbol::OpeningBrace) => {
+                    expect!(self, Kind::Symbol(Symbol::Spread), "expect '...'");
+                    let value = self.read_assignment_expression()?;
+                    expect!(self, Kind::Symbol(Symbol::ClosingBrace), "expect '}'");
+                    attributes.push(JSXAttribute::Spread(value));
+                }
+                Kind::Identifier(mut name) => {
+                    if self
+                        .lexer
+                        .next_if_skip_lineterminator(Kind::Symbol(Symbol::Colon))?
+                    {
+                        name = format!("{}:{}", name, self.read_jsx_identifier()?);
+                    }
+                    let value = if self
+                        .lexer
+                        .next_if_skip_lineterminator(Kind::Symbol(Symbol::Assign))?
+                    {
+                        self.read_jsx_attribute_value()?
+                    } else {
+                        Node::new(NodeBase::Boolean(true), tok.pos)
+                    };
+                    attributes.push(JSXAttribute::Attribute(name, value));
+                }
+                _ => {
+                    return Err(Error::UnexpectedToken(
+                        tok.pos,
+                        "expect JSX attribute".to_string(),
+                    ))
+                }
+            }
+        }
+
+        let children = if self_closing {
+            vec![]
+        } else {
+            self.read_jsx_children(&name)?
+        };
+
+        Ok(Node::new_span(
+            NodeBase::JSXElement(Box::new(JSXElement {
+                name,
+                attributes,
+                children,
+            })),
+            pos,
+            self.lexer.get_prev_end(),
+        ))
+    }
+
+    fn read_jsx_identifier(&mut self) -> Result<String, Error> {
+        let tok = self.lexer.next_skip_lineterminator()?;
+        match tok.kind {
+            Kind::Identifier(name) => Ok(name),
+            _ => Err(Error::Expect(tok.pos, "expect identifier".to_string())),
+        }
+    }
+
+    /// ``a``, ``a.b.c`` or ``a:b``.
+    fn read_jsx_element_name(&mut self) -> Result<String, Error> {
+        let mut name = self.read_jsx_identifier()?;
+        if self
+            .lexer
+            .next_if_skip_lineterminator(Kind::Symbol(Symbol::Colon))?
+        {
+            return Ok(format!("{}:{}", name, self.read_jsx_identifier()?));
+        }
+        while self
+            .lexer
+            .next_if_skip_lineterminator(Kind::Symbol(Symbol::Point))?
+        {
+            name = format!("{}.{}", name, self.read_jsx_identifier()?);
+        }
+        Ok(name)
+    }
+
+    fn read_jsx_attribute_value(&mut self) -> Result<Node, Error> {
+        let tok = self.lexer.next_skip_lineterminator()?;
+        match tok.kind {
+            Kind::String(s) => Ok(Node::new_span(
+                NodeBase::String(s),
+                tok.pos,
+                self.lexer.get_prev_end(),
+            )),
+            Kind::Symbol(Symbol::OpeningBrace) => {
+                let value = self.read_assignment_expression()?;
+                expect!(self, Kind::Symbol(Symbol::ClosingBrace), "expect '}'");
+                Ok(value)
+            }
+            Kind::Symbol(Symbol::Lt) => self.read_jsx_element(tok.pos),
+            _ => Err(Error::UnexpectedToken(
+                tok.pos,
+                "expect JSX attribute value".to_string(),
+            )),
+        }
+    }
+
+    /// Read the children and the closing tag, which must have the same ``name`` as the opening
+    /// tag.
+    fn read_jsx_children(&mut self, name: &Option<String>) -> Result<Vec<Node>, Error> {
+        let mut children = vec![];
+        loop {
+            let tok = match self.lexer.next() {
+                Ok(tok) => tok,
+                Err(Error::NormalEOF) => {
+                    return Err(Error::UnexpectedEOF("expect closing tag".to_string()))
+                }
+                Err(err) => return Err(err),
+            };
+            match tok.kind {
+                Kind::JSXText(text) => {
+                    if let Some(text) = jsx_text(&text) {
+                        children.push(Node::new_span(
+                            NodeBase::String(text),
+                            tok.pos,
+                            self.lexer.get_prev_end(),
+                        ));
+                    }
+                }
+                Kind::Symbol(Symbol::OpeningBrace) => {
+                    // An empty expression, e.g. one with only a comment, is no child.
+                    if !self
+                        .lexer
+                        .next_if_skip_lineterminator(Kind::Symbol(Symbol::ClosingBrace))?
+                    {
+                        children.push(self.read_assignment_expression()?);
+                        expect!(self, Kind::Symbol(Symbol::ClosingBrace), "expect '}'");
+                    }
+                }
+                Kind::Symbol(Symbol::Lt) => {
+                    if !self
+                        .lexer
+                        .next_if_skip_lineterminator(Kind::Symbol(Symbol::Div))?
+                    {
+                        children.push(self.read_jsx_element(tok.pos)?);
+                        continue;
+                    }
+                    let closing_name = if self
+                        .lexer
+                        .next_if_skip_lineterminator(Kind::Symbol(Symbol::Gt))?
+                    {
+                        None
+                    } else {
+                        let closing_name = self.read_jsx_element_name()?;
+                        expect!(self, Kind::Symbol(Symbol::Gt), "expect '>'");
+                        Some(closing_name)
+                    };
+                    if &closing_name != name {
+                        return Err(Error::General(
+                            tok.pos,
+                            format!(
+                                "Expected corresponding JSX closing tag for <{}>",
+                                name.as_ref().map_or("", |name| name.as_str())
+                            ),
+                        ));
+                    }
+                    return Ok(children);
+                }
+                _ => {
+                    return Err(Error::UnexpectedToken(
+                        tok.pos,
+                        "unexpected token in JSX children".to_string(),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// Whitespace with line terminators around a JSX text is removed, and the lines in it are
+/// joined with a space. Returns None if nothing is left.
+#[cfg(feature = "jsx")]
+fn jsx_text(text: &str) -> Option<String> {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let last = lines.len() - 1;
+    let mut joined = "".to_string();
+    for (i, mut line) in lines.into_iter().enumerate() {
+        if i != 0 {
+            line = line.trim_start();
+        }
+        if i != last {
+            line = line.trim_end();
+        }
+        if line.is_empty() {
+            continue;
+        }
+        if !joined.is_empty() {
+            joined.push(' ');
+        }
+        joined.push_str(line);
+    }
+    if joined.is_empty() {
+        None
+    } else {
+        Some(joined)
+    }
+}
+
+/// TypeScript syntax, which is skipped except for enums.
+/// https://github.com/microsoft/TypeScript/blob/master/doc/spec.md
+#[cfg(feature = "typescript")]
+impl Parser {
+    /// Read an interface, a type alias, an enum or an ambient declaration (``declare ...``) if
+    /// the next statement is one of them. Only an enum leaves code behind.
+    fn read_typescript_declaration(&mut self) -> Result<Option<Node>, Error> {
+        if !self.options.typescript || self.lexer.skip_lineterminator().is_err() {
+            return Ok(None);
+        }
+        let tok = self.lexer.peek(0)?;
+        // Contextual keywords start a declaration only when followed by a name on the same
+        // line, e.g. ``type = 1`` is an assignment.
+        let next = match self.lexer.peek(1) {
+            Ok(next) => next.kind,
+            Err(_) => return Ok(None),
+        };
+        match (tok.kind, next) {
+            (Kind::Identifier(ref keyword), Kind::Identifier(_)) if keyword == "interface" => {
+                self.lexer.next()?;
+                self.skip_interface_declaration()?
+            }
+            (Kind::Identifier(ref keyword), Kind::Identifier(_)) if keyword == "type" => {
+                self.lexer.next()?;
+                self.skip_type_alias_declaration()?
+            }
+            (Kind::Identifier(ref keyword), Kind::Identifier(_)) if keyword == "enum" => {
+                self.lexer.next()?;
+                return self.read_enum_declaration(tok.pos).map(Some);
+            }
+            // A const enum is not inlined, but transformed as a plain enum.
+            (Kind::Keyword(Keyword::Const), Kind::Identifier(ref keyword)) if keyword == "enum" => {
+                self.lexer.next()?;
+                self.lexer.next()?;
+                return self.read_enum_declaration(tok.pos).map(Some);
+            }
+            (Kind::Identifier(ref keyword), Kind::Identifier(_))
+            | (Kind::Identifier(ref keyword), Kind::Keyword(Keyword::Var))
+            | (Kind::Identifier(ref keyword), Kind::Keyword(Keyword::Let))
+            | (Kind::Identifier(ref keyword), Kind::Keyword(Keyword::Const))
+            | (Kind::Identifier(ref keyword), Kind::Keyword(Keyword::Function))
+                if keyword == "declare" =>
+            {
+                self.skip_ambient_declaration()
+            }
+            _ => return Ok(None),
+        }
+        Ok(Some(Node::new_span(
+            NodeBase::Nope,
+            tok.pos,
+            self.lexer.get_prev_end(),
+        )))
+    }
+
+    /// ``interface I<T> extends J, K { ... }``, after ``interface``.
+    fn skip_interface_declaration(&mut self) -> Result<(), Error> {
+        self.lexer.next()?;
+        self.skip_type_parameters()?;
+        if self.lexer.next_if(Kind::Identifier("extends".to_string())) {
+            loop {
+                self.skip_type()?;
+                if !self
+                    .lexer
+                    .next_if_skip_lineterminator(Kind::Symbol(Symbol::Comma))?
+                {
+                    break;
+                }
+            }
+        }
+        expect!(self, Kind::Symbol(Symbol::OpeningBrace), "expect '{'");
+        self.skip_brackets()
+    }
+
+    /// ``type T<U> = ...``, after ``type``.
+    fn skip_type_alias_declaration(&mut self) -> Result<(), Error> {
+        self.lexer.next()?;
+        self.skip_type_parameters()?;
+        expect!(self, Kind::Symbol(Symbol::Assign), "expect '='");
+        self.skip_type()
+    }
+
+    /// Skip ``declare ...`` up to the end of the statement, as it declares no code.
+    fn skip_ambient_declaration(&mut self) {
+        let mut depth = 0;
+        while let Ok(tok) = self.lexer.peek(0) {
+            match tok.kind {
+                Kind::Symbol(Symbol::OpeningParen)
+                | Kind::Symbol(Symbol::OpeningBrace)
+                | Kind::Symbol(Symbol::OpeningBoxBracket) => depth += 1,
+                Kind::Symbol(Symbol::ClosingBrace) if depth == 0 => break,
+                Kind::Symbol(Symbol::ClosingParen)
+                | Kind::Symbol(Symbol::ClosingBrace)
+                | Kind::Symbol(Symbol::ClosingBoxBracket) => {
+                    if depth > 0 {
+                        depth -= 1
+                    }
+                }
+                Kind::Symbol(Symbol::Semicolon) | Kind::LineTerminator if depth == 0 => break,
+                _ => {}
+            }
+            self.lexer.next().unwrap();
+        }
+    }
+
+    /// ``enum E { A, B = 2 }``, after ``enum``, as
+    /// ``var E = {}; E[E["A"] = 0] = "A"; E[E["B"] = 2] = "B";``.
+    /// A member without an initializer is the previous member plus one. Members initialized
+    /// with a string have no reverse mapping.
+    fn read_enum_declaration(&mut self, pos: usize) -> Result<Node, Error> {
+        let name = match self.lexer.next()?.kind {
+            Kind::Identifier(name) => name,
+            _ => unreachable!(),
+        };
+        expect!(self, Kind::Symbol(Symbol::OpeningBrace), "expect '{'");
+
+        let object = Node::new(NodeBase::Identifier(name.clone()), pos);
+        let mut list = vec![Node::new(
+            NodeBase::VarDecl(
+                name,
+                Some(Box::new(Node::new(NodeBase::Object(vec![]), pos))),
+                VarKind::Var,
+            ),
+            pos,
+        )];
+        let mut next_value = Some(0.0);
+
+        loop {
+            if self
+                .lexer
+                .next_if_skip_lineterminator(Kind::Symbol(Symbol::ClosingBrace))?
+            {
+                break;
+            }
+
+            let tok = self.lexer.next_skip_lineterminator()?;
+            let member_pos = tok.pos;
+            let member = match tok.kind {
+                Kind::Identifier(member) | Kind::String(member) => member,
+                _ => return Err(Error::Expect(member_pos, "expect enum member".to_string())),
+            };
+            let mut has_reverse_mapping = true;
+            let value = if self
+                .lexer
+                .next_if_skip_lineterminator(Kind::Symbol(Symbol::Assign))?
+            {
+                let value = self.read_assignment_expression()?;
+                next_value = match value.base {
+                    NodeBase::Number(n) => Some(n + 1.0),
+                    NodeBase::UnaryOp(ref operand, UnaryOp::Minus) => match operand.base {
+                        NodeBase::Number(n) => Some(1.0 - n),
+                        _ => None,
+                    },
+                    NodeBase::String(_) => {
+                        has_reverse_mapping = false;
+                        None
+                    }
+                    _ => None,
+                };
+                value
+            } else if let Some(n) = next_value {
+                next_value = Some(n + 1.0);
+                Node::new(NodeBase::Number(n), member_pos)
+            } else {
+                return Err(Error::General(
+                    member_pos,
+                    "Enum member must have initializer.".to_string(),
+                ));
+            };
+
+            let index = |key: Node| {
+                Node::new(
+                    NodeBase::Index(Box::new(object.clone()), Box::new(key)),
+                    member_pos,
+                )
+            };
+            let assign = |lhs: Node, rhs: Node| {
+                Node::new(NodeBase::Assign(Box::new(lhs), Box::new(rhs)), member_pos)
+            };
+            let name = Node::new(NodeBase::String(member), member_pos);
+            let forward = assign(index(name.clone()), value);
+            list.push(if has_reverse_mapping {
+                assign(index(forward), name)
+            } else {
+                forward
+            });
+
+            if self
+                .lexer
+                .next_if_skip_lineterminator(Kind::Symbol(Symbol::ClosingBrace))?
+            {
+                break;
+            }
+            expect!(self, Kind::Symbol(Symbol::Comma), "expect ',' or '}'");
+        }
+
+        Ok(Node::new_span(
+            NodeBase::StatementList(list),
+            pos,
+            self.lexer.get_prev_end(),
+        ))
+    }
+
+    /// ``!`` in ``let a!: T``, and the type annotation.
+    fn skip_variable_type(&mut self) -> Result<(), Error> {
+        if self.options.typescript {
+            self.lexer.next_if(Kind::Symbol(Symbol::Not));
+        }
+        self.skip_type_annotation()
+    }
+
+    /// ``?`` in ``function f(a?: T)``, and the type annotation.
+    fn skip_parameter_type(&mut self) -> Result<(), Error> {
+        if self.options.typescript {
+            self.lexer.next_if(Kind::Symbol(Symbol::Question));
+        }
+        self.skip_type_annotation()
+    }
+
+    /// ``: T`` of a variable, a parameter, or the return value of a function.
+    fn skip_type_annotation(&mut self) -> Result<(), Error> {
+        if self.options.typescript && self.lexer.next_if(Kind::Symbol(Symbol::Colon)) {
+            self.skip_type()?;
+        }
+        Ok(())
+    }
+
+    /// ``<T extends U = V, ...>`` of a generic function or type.
+    fn skip_type_parameters(&mut self) -> Result<(), Error> {
+        if !self.options.typescript || !self.lexer.next_if(Kind::Symbol(Symbol::Lt)) {
+            return Ok(());
+        }
+        loop {
+            let tok = self.lexer.next_skip_lineterminator()?;
+            if !tok.is_identifier() {
+                return Err(Error::Expect(tok.pos, "expect type parameter".to_string()));
+            }
+            if self.lexer.next_if(Kind::Identifier("extends".to_string())) {
+                self.skip_type()?;
+            }
+            if self.lexer.next_if(Kind::Symbol(Symbol::Assign)) {
+                self.skip_type()?;
+            }
+            if !self
+                .lexer
+                .next_if_skip_lineterminator(Kind::Symbol(Symbol::Comma))?
+                || self.lexer.peek_skip_lineterminator()?.kind == Kind::Symbol(Symbol::Gt)
+            {
+                break;
+            }
+        }
+        self.expect_closing_angle_bracket()
+    }
+
+    /// ``as T``, ``as const`` and ``satisfies T`` after an expression.
+    fn skip_type_assertions(&mut self) -> Result<(), Error> {
+        while self.options.typescript {
+            if self.lexer.next_if(Kind::Identifier("as".to_string())) {
+                if !self.lexer.next_if(Kind::Keyword(Keyword::Const)) {
+                    self.skip_type()?;
+                }
+            } else if self
+                .lexer
+                .next_if(Kind::Identifier("satisfies".to_string()))
+            {
+                self.skip_type()?;
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the ``!`` just read is a non-null assertion ``a!``, which is not preceded by a
+    /// line terminator.
+    fn is_non_null_assertion(&self) -> bool {
+        self.options.typescript
+            && self.lexer.token_pos >= 2
+            && self.lexer.buf[self.lexer.token_pos - 2].kind != Kind::LineTerminator
+    }
+
+    /// Whether the ``<`` just read starts the type arguments of a call, e.g. ``f<T>(a)``, rather
+    /// than a less-than operator. The tokens up to the matching ``>`` must be able to form types,
+    /// and be followed by ``(``.
+    fn is_call_type_arguments(&self) -> bool {
+        if !self.options.typescript {
+            return false;
+        }
+        let (mut angle_depth, mut brace_depth) = (0, 0);
+        let tokens = self.lexer.buf.iter().skip(self.lexer.token_pos - 1);
+        let mut tokens = tokens.filter(|tok| tok.kind != Kind::LineTerminator);
+        while let Some(tok) = tokens.next() {
+            match tok.kind {
+                Kind::Symbol(Symbol::Lt) => angle_depth += 1,
+                Kind::Symbol(Symbol::Gt) => angle_depth -= 1,
+                Kind::Symbol(Symbol::Shr) => angle_depth -= 2,
+                Kind::Symbol(Symbol::ZFShr) => angle_depth -= 3,
+                Kind::Symbol(Symbol::OpeningBrace) => brace_depth += 1,
+                Kind::Symbol(Symbol::ClosingBrace) => brace_depth -= 1,
+                Kind::Symbol(Symbol::Semicolon) if brace_depth > 0 => {}
+                Kind::Identifier(_)
+                | Kind::Keyword(_)
+                | Kind::String(_)
+                | Kind::Number(_)
+                | Kind::Symbol(Symbol::OpeningParen)
+                | Kind::Symbol(Symbol::ClosingParen)
+                | Kind::Symbol(Symbol::OpeningBoxBracket)
+                | Kind::Symbol(Symbol::ClosingBoxBracket)
+                | Kind::Symbol(Symbol::Point)
+                | Kind::Symbol(Symbol::Comma)
+                | Kind::Symbol(Symbol::Colon)
+                | Kind::Symbol(Symbol::Question)
+                | Kind::Symbol(Symbol::Or)
+                | Kind::Symbol(Symbol::And)
+                | Kind::Symbol(Symbol::Sub)
+                | Kind::Symbol(Symbol::FatArrow) => {}
+                _ => return false,
+            }
+            if angle_depth <= 0 || brace_depth < 0 {
+                return angle_depth == 0
+                    && brace_depth == 0
+                    && tokens.next().map(|tok| &tok.kind)
+                        == Some(&Kind::Symbol(Symbol::OpeningParen));
+            }
+        }
+        false
+    }
+
+    /// Whether the function just read has no body, as an overload signature.
+    fn is_function_signature(&mut self) -> bool {
+        self.options.typescript
+            && self
+                .lexer
+                .peek_skip_lineterminator()
+                .map_or(true, |tok| tok.kind != Kind::Symbol(Symbol::OpeningBrace))
+    }
+
+    fn skip_type(&mut self) -> Result<(), Error> {
+        let depth = self.depth;
+        let res = self.deepen().and_then(|_| self.skip_type_body());
+        self.depth = depth;
+        res
+    }
+
+    fn skip_type_body(&mut self) -> Result<(), Error> {
+        self.skip_union_type()?;
+        // A conditional type: ``T extends U ? X : Y``
+        if self.lexer.next_if(Kind::Identifier("extends".to_string())) {
+            self.skip_union_type()?;
+            expect!(self, Kind::Symbol(Symbol::Question), "expect '?'");
+            self.skip_type()?;
+            expect!(self, Kind::Symbol(Symbol::Colon), "expect ':'");
+            self.skip_type()?;
+        }
+        Ok(())
+    }
+
+    /// ``A | B`` and ``A & B``, with an optional leading ``|`` or ``&``.
+    fn skip_union_type(&mut self) -> Result<(), Error> {
+        let separators = [Kind::Symbol(Symbol::Or), Kind::Symbol(Symbol::And)];
+        let mut is_first = true;
+        loop {
+            match self.lexer.peek_skip_lineterminator() {
+                Ok(ref tok) if separators.contains(&tok.kind) => {
+                    self.lexer.next_skip_lineterminator()?;
+                }
+                _ if is_first => {}
+                _ => return Ok(()),
+            }
+            is_first = false;
+            self.skip_type_operator()?;
+        }
+    }
+
+    /// ``keyof T``, ``typeof a.b``, and array types ``T[]`` and indexed access types ``T[K]``.
+    fn skip_type_operator(&mut self) -> Result<(), Error> {
+        self.lexer.skip_lineterminator()?;
+        let is_operand = matches!(
+            self.lexer.peek(1).map(|tok| tok.kind),
+            Ok(Kind::Identifier(_))
+                | Ok(Kind::Keyword(_))
+                | Ok(Kind::Symbol(Symbol::OpeningParen))
+                | Ok(Kind::Symbol(Symbol::OpeningBrace))
+                | Ok(Kind::Symbol(Symbol::OpeningBoxBracket))
+        );
+        match self.lexer.peek(0)?.kind {
+            Kind::Identifier(ref operator)
+                if is_operand
+                    && ["keyof", "unique", "readonly", "infer"].contains(&operator.as_str()) =>
+            {
+                self.lexer.next()?;
+                return self.skip_type_operator();
+            }
+            Kind::Keyword(Keyword::Typeof) => {
+                self.lexer.next()?;
+                self.skip_type_name()?;
+            }
+            _ => self.skip_primary_type()?,
+        }
+        while self.lexer.next_if(Kind::Symbol(Symbol::OpeningBoxBracket)) {
+            if !self.lexer.next_if(Kind::Symbol(Symbol::ClosingBoxBracket)) {
+                self.skip_type()?;
+                expect!(self, Kind::Symbol(Symbol::ClosingBoxBracket), "expect ']'");
+            }
+        }
+        Ok(())
+    }
+
+    fn skip_primary_type(&mut self) -> Result<(), Error> {
+        let tok = self.lexer.next_skip_lineterminator()?;
+        match tok.kind {
+            // A parenthesized type, or a function type ``(a: T) => U``
+            Kind::Symbol(Symbol::OpeningParen) => {
+                self.skip_brackets()?;
+                if self
+                    .lexer
+                    .next_if_skip_lineterminator(Kind::Symbol(Symbol::FatArrow))?
+                {
+                    self.skip_type()?;
+                }
+            }
+            // A generic function type ``<T>(a: T) => T``, or a constructor type
+            // ``new (a: T) => U``
+            Kind::Symbol(Symbol::Lt) | Kind::Keyword(Keyword::New) => {
+                self.lexer.unget();
+                if !self.lexer.next_if(Kind::Keyword(Keyword::New)) {
+                    self.skip_type_parameters()?;
+                }
+                expect!(self, Kind::Symbol(Symbol::OpeningParen), "expect '('");
+                self.skip_brackets()?;
+                expect!(self, Kind::Symbol(Symbol::FatArrow), "expect '=>'");
+                self.skip_type()?;
+            }
+            // An object type or a tuple type
+            Kind::Symbol(Symbol::OpeningBrace) | Kind::Symbol(Symbol::OpeningBoxBracket) => {
+                self.skip_brackets()?;
+            }
+            Kind::Identifier(_) => {
+                self.lexer.unget();
+                self.skip_type_name()?;
+                if self.lexer.peek(0).map(|tok| tok.kind) == Ok(Kind::Symbol(Symbol::Lt)) {
+                    self.skip_type_arguments()?;
+                }
+                // A type predicate ``a is T``
+                if self.lexer.next_if(Kind::Identifier("is".to_string())) {
+                    self.skip_type()?;
+                }
+            }
+            Kind::Symbol(Symbol::Sub) => match self.lexer.next()?.kind {
+                Kind::Number(_) => {}
+                _ => return Err(Error::Expect(tok.pos, "expect type".to_string())),
+            },
+            Kind::Keyword(Keyword::Void)
+            | Kind::Keyword(Keyword::This)
+            | Kind::String(_)
+            | Kind::Number(_) => {}
+            _ => return Err(Error::Expect(tok.pos, "expect type".to_string())),
+        }
+        Ok(())
+    }
+
+    /// ``a.b.c``
+    fn skip_type_name(&mut self) -> Result<(), Error> {
+        loop {
+            let tok = self.lexer.next_skip_lineterminator()?;
+            if !tok.is_identifier() {
+                return Err(Error::Expect(tok.pos, "expect identifier".to_string()));
+            }
+            if !self.lexer.next_if(Kind::Symbol(Symbol::Point)) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// ``<T, U>`` of a generic type.
+    fn skip_type_arguments(&mut self) -> Result<(), Error> {
+        expect!(self, Kind::Symbol(Symbol::Lt), "expect '<'");
+        loop {
+            self.skip_type()?;
+            if !self
+                .lexer
+                .next_if_skip_lineterminator(Kind::Symbol(Symbol::Comma))?
+            {
+                break;
+            }
+        }
+        self.expect_closing_angle_bracket()
+    }
+
+    /// Read ``>``. The first ``>`` of ``>>`` in ``A<B<C>>`` is split from the token.
+    fn expect_closing_angle_bracket(&mut self) -> Result<(), Error> {
+        self.lexer.skip_lineterminator()?;
+        let tok = &mut self.lexer.buf[self.lexer.token_pos];
+        let rest = match tok.kind {
+            Kind::Symbol(Symbol::Gt) => None,
+            Kind::Symbol(Symbol::Shr) => Some(Symbol::Gt),
+            Kind::Symbol(Symbol::ZFShr) => Some(Symbol::Shr),
+            Kind::Symbol(Symbol::Ge) => Some(Symbol::Assign),
+            Kind::Symbol(Symbol::AssignShr) => Some(Symbol::Ge),
+            _ => return Err(Error::Expect(tok.pos, "expect '>'".to_string())),
+        };
+        match rest {
+            Some(rest) => {
+                tok.kind = Kind::Symbol(rest);
+                tok.pos += 1;
+            }
+            None => {
+                self.lexer.next()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Skip to the bracket closing the one just read, e.g. an object type ``{ a: T }``.
+    fn skip_brackets(&mut self) -> Result<(), Error> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.lexer.next_skip_lineterminator() {
+                Ok(tok) => match tok.kind {
+                    Kind::Symbol(Symbol::OpeningParen)
+                    | Kind::Symbol(Symbol::OpeningBrace)
+                    | Kind::Symbol(Symbol::OpeningBoxBracket) => depth += 1,
+                    Kind::Symbol(Symbol::ClosingParen)
+                    | Kind::Symbol(Symbol::ClosingBrace)
+                    | Kind::Symbol(Symbol::ClosingBoxBracket) => depth -= 1,
+                    _ => {}
+                },
+                Err(_) => return Err(Error::UnexpectedEOF("expect a closing bracket".to_string())),
+            }
+        }
+        Ok(())
+    }
+}
+
 #[test]
 fn number() {
     let mut parser = Parser::new("test", "12345".to_string());
@@ -1788,6 +3258,7 @@ fn simple_expr_rel() {
         ("1 + 2 > 3", BinOp::Gt, 8),
         ("1 + 2 <= 3", BinOp::Le, 9),
         ("1 + 2 >= 3", BinOp::Ge, 9),
+        ("1 + 2 in 3", BinOp::In, 9),
     ]
     .iter()
     {
@@ -2653,6 +4124,22 @@ fn asi2() {
         )
     )
 }
+#[test]
+fn debugger() {
+    let mut parser = Parser::new("test", "debugger; debugger\ndebugger".to_string());
+    assert_eq!(
+        parser.parse_all().unwrap(),
+        Node::new(
+            NodeBase::StatementList(vec![
+                Node::new(NodeBase::Debugger, 0),
+                Node::new(NodeBase::Debugger, 10),
+                Node::new(NodeBase::Debugger, 19),
+            ]),
+            0
+        )
+    );
+}
+
 #[test]
 fn throw() {
     let mut parser = Parser::new("test", "throw 10".to_string());
@@ -2697,7 +4184,14 @@ fn try_catch1() {
             0
         )
     );
-    for input in ["try {} catch", "try {} catch {}", "try {} catch(7)"].iter() {
+    for input in [
+        "try {}",
+        "try {} catch",
+        "try {} catch(7)",
+        "try {} catch() {}",
+    ]
+    .iter()
+    {
         let mut parser = Parser::new("test", input.to_string());
         parser.parse_all().expect_err("should be error");
     }
@@ -2742,3 +4236,341 @@ fn try_catch3() {
         )
     );
 }
+
+#[test]
+fn try_catch4() {
+    let mut parser = Parser::new("test", "try {} catch {}".to_string());
+    assert_eq!(
+        parser.parse_all().unwrap(),
+        Node::new(
+            NodeBase::StatementList(vec![Node::new(
+                NodeBase::Try(
+                    Box::new(Node::new(NodeBase::Block(vec![]), 4)),
+                    Box::new(Node::new(NodeBase::StatementList(vec![]), 13)),
+                    Box::new(Node::new(NodeBase::Nope, 13)),
+                    Box::new(Node::new(NodeBase::Nope, 15)),
+                ),
+                0
+            )]),
+            0
+        )
+    );
+}
+
+#[test]
+fn const_without_initializer() {
+    for input in ["const a", "const a = 1, b", "for (const i; i < 1; ) {}"].iter() {
+        let mut parser = Parser::new("test", input.to_string());
+        parser.parse_all().expect_err("should be error");
+    }
+    for input in ["let a, b = 1", "{ const a = 1 }"].iter() {
+        let mut parser = Parser::new("test", input.to_string());
+        parser.parse_all().unwrap();
+    }
+}
+
+#[test]
+fn parse_with_recovery() {
+    let mut parser = Parser::new(
+        "test",
+        "var 7\nvar a = 1\nfunction f() { var 8; return a }\na = ;\n}\na".to_string(),
+    );
+    let errs = parser.parse_all_with_recovery().unwrap_err();
+    let positions: Vec<usize> = errs
+        .iter()
+        .map(|err| match err {
+            Error::UnexpectedToken(pos, _) => *pos,
+            err => panic!("unexpected error: {:?}", err),
+        })
+        .collect();
+    assert_eq!(positions, vec![4, 35, 53, 55]);
+
+    let mut parser = Parser::new("test", "if (a) { b c } else { d e }\nok()".to_string());
+    assert_eq!(parser.parse_all_with_recovery().unwrap_err().len(), 2);
+
+    let mut parser = Parser::new("test", "var a = 1\n{ a }".to_string());
+    parser.parse_all_with_recovery().unwrap();
+}
+
+#[test]
+fn node_span() {
+    let mut parser = Parser::new("test", "f(a, b) + 1;\nif (x) {\n  y\n}".to_string());
+    let node = parser.parse_all().unwrap();
+    let items = match node.base {
+        NodeBase::StatementList(items) => items,
+        _ => panic!(),
+    };
+    assert_eq!(items[0].end, 11);
+    match &items[0].base {
+        NodeBase::BinaryOp(lhs, _, _) => assert_eq!((lhs.pos, lhs.end), (0, 7)),
+        _ => panic!(),
+    }
+    assert_eq!((items[1].pos, items[1].end), (13, 27));
+    assert_eq!(parser.lexer.get_line_column(items[1].end - 1), (4, 1));
+}
+
+#[test]
+fn deep_nesting() {
+    let n = 100_000;
+    for code in [
+        format!("{}1{}", "(".repeat(n), ")".repeat(n)),
+        format!("{}{}", "{".repeat(n), "}".repeat(n)),
+        format!("{}1", "!".repeat(n)),
+        format!("2{}", "**2".repeat(n)),
+        format!("1{}", "+1".repeat(n)),
+        format!("a{}", ".b".repeat(n)),
+        format!("a{}", "()".repeat(n)),
+        format!("{}A(){}", "new ".repeat(n), "()".repeat(n)),
+    ]
+    .iter()
+    {
+        let mut parser = Parser::new("test", code.clone());
+        match parser.parse_all() {
+            Err(Error::General(_, msg)) => assert_eq!(msg, "Nesting is too deep"),
+            res => panic!("{:?}", res.map(|_| ())),
+        }
+    }
+
+    Parser::new("test", format!("{}1{}", "(".repeat(10), ")".repeat(10)))
+        .parse_all()
+        .unwrap();
+    Parser::new("test", format!("1{}", "+1".repeat(5000)))
+        .parse_all()
+        .unwrap();
+}
+
+#[test]
+fn comment_attachment() {
+    let code = "/** Doc for f */\n\
+                function f() {\n\
+                  // inside\n\
+                  return 1; // one\n\
+                }\n\
+                let a = /* inline */ 2; b(); /* two */\n\
+                // dangling";
+    let mut parser = Parser::new("test", code).with_comments();
+    let node = parser.parse_all().unwrap();
+    let comments = |node: &Node| -> Vec<String> {
+        node.comments
+            .iter()
+            .map(|comment| comment.content().trim().to_string())
+            .collect()
+    };
+    let items = match node.base {
+        NodeBase::StatementList(ref items) => items,
+        _ => panic!(),
+    };
+    assert_eq!(comments(&items[0]), vec!["* Doc for f"]);
+    match items[0].base {
+        NodeBase::FunctionDecl(
+            _,
+            _,
+            box Node {
+                base: NodeBase::StatementList(ref body),
+                ..
+            },
+        ) => assert_eq!(comments(&body[0]), vec!["inside", "one"]),
+        _ => panic!(),
+    }
+    assert_eq!(comments(&items[1]), Vec::<String>::new());
+    assert_eq!(comments(&items[2]), vec!["two"]);
+    assert_eq!(parser.lexer.comments.len(), 6);
+    assert!(parser.lexer.comments[3].is_block());
+
+    // Comments are not collected by default.
+    let mut parser = Parser::new("test", code);
+    let node = parser.parse_all().unwrap();
+    assert!(parser.lexer.comments.is_empty());
+    match node.base {
+        NodeBase::StatementList(ref items) => assert!(items[0].comments.is_empty()),
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn line_offset() {
+    let mut parser = Parser::new("page.html", "a\n  b c").with_line_offset(9);
+    let pos = match parser.parse_all() {
+        Err(Error::UnexpectedToken(pos, _)) => pos,
+        res => panic!("{:?}", res.map(|_| ())),
+    };
+    assert_eq!(parser.lexer.get_line_column(pos), (11, 5));
+    assert_eq!(parser.lexer.buf[0].line, 10);
+}
+
+#[test]
+fn parser_options() {
+    let parse = |code: &str, options: ParserOptions| {
+        Parser::new("test", code)
+            .with_options(options)
+            .parse_all()
+            .map(|_| ())
+    };
+    let es5 = ParserOptions {
+        ecma_version: 5,
+        ..ParserOptions::default()
+    };
+    let module = ParserOptions {
+        goal: Goal::Module,
+        ..ParserOptions::default()
+    };
+
+    let es2015_code = [
+        "let a = 1",
+        "a => a",
+        "[...a]",
+        "({ a })",
+        "function f(...a) {}",
+    ];
+    for code in &es2015_code {
+        assert!(parse(code, ParserOptions::default()).is_ok(), "{}", code);
+        assert!(parse(code, es5.clone()).is_err(), "{}", code);
+    }
+    let es2017 = ParserOptions {
+        ecma_version: 2017,
+        ..ParserOptions::default()
+    };
+    assert!(parse("2 ** 3", es2017.clone()).is_ok());
+    assert!(parse("({ ...a })", es2017).is_err());
+
+    assert!(parse("010; delete a", ParserOptions::default()).is_ok());
+    assert!(parse("010", module.clone()).is_err());
+    assert!(parse("delete a", module.clone()).is_err());
+    assert!(parse("delete a.b; 0o10", module).is_ok());
+    let no_annex_b = ParserOptions {
+        annex_b: false,
+        ..ParserOptions::default()
+    };
+    assert!(parse("010", no_annex_b).is_err());
+
+    let jsx = ParserOptions {
+        jsx: true,
+        ..ParserOptions::default()
+    };
+    #[cfg(not(feature = "jsx"))]
+    assert_eq!(parse("<div />", jsx), Err(Error::UnsupportedFeature(0)));
+    #[cfg(feature = "jsx")]
+    assert!(parse("<div />", jsx).is_ok());
+
+    let typescript = ParserOptions {
+        typescript: true,
+        ..ParserOptions::default()
+    };
+    #[cfg(not(feature = "typescript"))]
+    assert_eq!(
+        parse("let a", typescript),
+        Err(Error::UnsupportedFeature(0))
+    );
+    #[cfg(feature = "typescript")]
+    assert!(parse("let a: number = 1", typescript).is_ok());
+}
+
+#[cfg(feature = "typescript")]
+#[test]
+fn typescript() {
+    let parse = |code: &str, typescript: bool| {
+        let node = Parser::new("test", code)
+            .with_options(ParserOptions {
+                typescript,
+                ..ParserOptions::default()
+            })
+            .parse_all();
+        // Compare the structure only, as the positions differ.
+        node.map(|node| format!("{:?}", node).replace(char::is_numeric, ""))
+    };
+    let assert_stripped = |ts: &str, js: &str| {
+        assert_eq!(parse(ts, true), parse(js, false), "{}", ts);
+    };
+
+    assert_stripped(
+        "let a: number = 1, b!: string[]; const c: Array<Map<string, number>> = d",
+        "let a = 1, b; const c = d",
+    );
+    assert_stripped(
+        "function f<T extends object = {}>(a: T, b?: number, ...c: Array<T>): a is T {\n  return a as any as T\n}",
+        "function f(a, b, ...c) {\n  return a\n}",
+    );
+    assert_stripped(
+        "var g = (a: number): (b: string) => void => a!.b!(c!)\n!d",
+        "var g = (a) => a.b(c)\n!d",
+    );
+    let declarations = [
+        "interface I<T> extends J<T>, K {\n  a: T; b(): void\n}",
+        "type U = | 'a' | { b: keyof typeof x }[]",
+        "declare module 'm' {\n  export const a: number\n}",
+        "declare function h(): void",
+        "function f(a: string): void;",
+    ];
+    for declaration in &declarations {
+        assert_stripped(
+            &format!("{}\nfunction f(a) {{}}", declaration),
+            ";function f(a) {}",
+        );
+    }
+    assert_stripped(
+        "let t: T extends (infer U)[] ? U : never = u<A<B>>(v), w = x < y",
+        "let t = u(v), w = x < y",
+    );
+    assert_stripped(
+        "x = y satisfies Z; z = [1] as const; a < b as number > c",
+        "x = y; z = [1]; a < b > c",
+    );
+    assert_stripped(
+        "let type = 1; type += 2; declare(x, y)",
+        "let type = 1; type += 2; declare(x, y)",
+    );
+    assert_eq!(
+        parse("enum E { A = f(), B }", true),
+        Err(Error::General(
+            18,
+            "Enum member must have initializer.".to_string()
+        ))
+    );
+}
+
+#[test]
+fn duplicate_parameters() {
+    let parse = |code: &str, goal: Goal| {
+        Parser::new("test", code)
+            .with_options(ParserOptions {
+                goal,
+                ..ParserOptions::default()
+            })
+            .parse_all()
+            .map(|_| ())
+    };
+    assert!(parse("function f(a, b, a) {}", Goal::Script).is_ok());
+    assert_eq!(
+        parse("function f(a, b, a) {}", Goal::Module),
+        Err(Error::Redeclaration(17, 11, "a".to_string()))
+    );
+    assert_eq!(
+        parse("function f(a, ...a) {}", Goal::Module),
+        Err(Error::Redeclaration(17, 11, "a".to_string()))
+    );
+}
+
+#[test]
+fn node_ids() {
+    fn preorder(node: &Node, ids: &mut Vec<usize>) {
+        ids.push(node.id.0);
+        for child in node.children() {
+            preorder(child, ids)
+        }
+    }
+
+    use crate::node::NodeId;
+
+    let code = "var x = f(1); function f(a, b) { return { a, b, c: a ? [b] : 0 } }";
+    let node = Parser::new("test", code).parse_all().unwrap();
+    let mut ids = vec![];
+    preorder(&node, &mut ids);
+    assert_eq!(ids, (1..=ids.len()).collect::<Vec<_>>());
+    let declaration = &node.children()[0].children()[0];
+    assert_eq!(declaration.id, NodeId(3));
+    match declaration.base {
+        NodeBase::VarDecl(_, Some(ref call), _) => assert_eq!(call.id, NodeId(4)),
+        _ => panic!(),
+    }
+    assert!(Node::new(NodeBase::Nope, 0).id.is_none());
+}
@@ -0,0 +1,356 @@
+//! Loading modules from https:, http: and data: URLs, enabled by the ``remote-modules``
+//! feature. Modules are fetched with the ``curl`` command, only from the allowed hosts, and
+//! cached on disk so that later runs work offline.
+
+use rustc_hash::FxHashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Redirects followed when fetching a module.
+const MAX_REDIRECTS: usize = 10;
+
+#[derive(Clone, Debug)]
+pub struct RemoteModules {
+    /// Hosts which modules may be fetched from. ``*.example.com`` also allows the subdomains.
+    /// data: URLs are always allowed.
+    pub allowed_hosts: Vec<String>,
+    /// Where fetched modules are cached. Not cached on disk if None.
+    pub cache_dir: Option<PathBuf>,
+    /// Modules fetched by this VM, by URL.
+    fetched: FxHashMap<String, String>,
+}
+
+impl Default for RemoteModules {
+    fn default() -> Self {
+        RemoteModules {
+            allowed_hosts: vec![],
+            cache_dir: default_cache_dir(),
+            fetched: FxHashMap::default(),
+        }
+    }
+}
+
+/// ``$RAPIDUS_CACHE_DIR``, or ``~/.cache/rapidus/modules``.
+fn default_cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("RAPIDUS_CACHE_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache/rapidus/modules"))
+}
+
+impl RemoteModules {
+    /// Get the source of the module at ``url``.
+    pub fn fetch(&mut self, url: &str) -> Result<String, String> {
+        if url.starts_with("data:") {
+            return decode_data_url(url);
+        }
+
+        self.check_allowed(url, url)?;
+
+        if let Some(source) = self.fetched.get(url) {
+            return Ok(source.clone());
+        }
+        let cache_file = self.cache_dir.as_ref().map(|dir| {
+            let mut hasher = DefaultHasher::new();
+            url.hash(&mut hasher);
+            dir.join(format!("{:016x}.js", hasher.finish()))
+        });
+        let source = match cache_file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+        {
+            Some(source) => source,
+            None => {
+                let source = self.download(url)?;
+                if let Some(file) = cache_file {
+                    // The module is just fetched again next time if it cannot be cached.
+                    let _ = fs::create_dir_all(file.parent().unwrap())
+                        .and_then(|_| fs::write(file, source.as_str()));
+                }
+                source
+            }
+        };
+        self.fetched.insert(url.to_string(), source.clone());
+        Ok(source)
+    }
+
+    /// Check that ``url``, which ``module_url`` is fetched from, is on an allowed host.
+    fn check_allowed(&self, module_url: &str, url: &str) -> Result<(), String> {
+        let host = host(url).ok_or_else(|| format!("Invalid module URL '{}'", url))?;
+        if !self.is_allowed(host) {
+            return Err(format!(
+                "Cannot load '{}': {} is not an allowed host (see --allow-remote)",
+                module_url, host
+            ));
+        }
+        Ok(())
+    }
+
+    /// Download ``url``, following redirects only to allowed hosts.
+    fn download(&self, url: &str) -> Result<String, String> {
+        let mut location = url.to_string();
+        for _ in 0..MAX_REDIRECTS {
+            match download(url, &location)? {
+                Download::Body(source) => return Ok(source),
+                Download::Redirect(to) => {
+                    if !to.starts_with("https://") && !to.starts_with("http://") {
+                        return Err(format!("Cannot load '{}': redirected to '{}'", url, to));
+                    }
+                    self.check_allowed(url, &to)?;
+                    location = to;
+                }
+            }
+        }
+        Err(format!("Cannot load '{}': too many redirects", url))
+    }
+
+    fn is_allowed(&self, host: &str) -> bool {
+        self.allowed_hosts.iter().any(|allowed| {
+            if allowed.starts_with("*.") {
+                host == &allowed[2..] || host.ends_with(&allowed[1..])
+            } else {
+                host == allowed
+            }
+        })
+    }
+}
+
+enum Download {
+    Body(String),
+    Redirect(String),
+}
+
+/// Download ``location`` for the module at ``url``. Redirects are not followed by curl, so that
+/// the host of each one can be checked.
+fn download(url: &str, location: &str) -> Result<Download, String> {
+    let output = Command::new("curl")
+        .args(&["--fail", "--silent", "--show-error"])
+        .args(&["--write-out", "\n%{redirect_url}"])
+        .args(&["--proto", "=https,http", location])
+        .output()
+        .map_err(|err| format!("Cannot load '{}': cannot run curl: {}", url, err))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Cannot load '{}': {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let output = String::from_utf8(output.stdout)
+        .map_err(|_| format!("Cannot load '{}': not UTF-8", url))?;
+    // The body is followed by a line of the redirect URL, which is empty if not redirected.
+    let mut parts = output.rsplitn(2, '\n');
+    let redirect = parts.next().unwrap();
+    let body = parts.next().unwrap_or("");
+    if redirect.is_empty() {
+        Ok(Download::Body(body.to_string()))
+    } else {
+        Ok(Download::Redirect(redirect.to_string()))
+    }
+}
+
+/// Whether ``specifier`` is a URL this module can load.
+pub fn is_url(specifier: &str) -> bool {
+    ["https:", "http:", "data:"]
+        .iter()
+        .any(|scheme| specifier.starts_with(scheme))
+}
+
+/// Resolve a relative specifier required from a module loaded from an http(s) URL.
+/// Return None if ``specifier`` is neither a URL nor relative to a remote ``referrer``.
+pub fn resolve(specifier: &str, referrer: Option<&str>) -> Option<String> {
+    if is_url(specifier) {
+        return Some(specifier.to_string());
+    }
+    let referrer = referrer.filter(|r| is_url(r) && !r.starts_with("data:"))?;
+    let origin_end = referrer.find("://")? + 3;
+    let path_start = referrer[origin_end..]
+        .find('/')
+        .map_or(referrer.len(), |pos| origin_end + pos);
+    let (origin, path) = referrer.split_at(path_start);
+    let path = path.split(|c| c == '?' || c == '#').next().unwrap_or("");
+
+    let mut segments = if specifier.starts_with('/') {
+        vec![]
+    } else if specifier.starts_with("./") || specifier.starts_with("../") {
+        // The directory of the referrer.
+        let mut segments = path.split('/').skip(1).collect::<Vec<&str>>();
+        segments.pop();
+        segments
+    } else {
+        return None;
+    };
+    for segment in specifier.trim_start_matches('/').split('/') {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    Some(format!("{}/{}", origin, segments.join("/")))
+}
+
+/// The host of an http(s) URL, without the user and the port.
+fn host(url: &str) -> Option<&str> {
+    let authority = url.splitn(2, "://").nth(1)?;
+    let authority = authority
+        .split(|c| c == '/' || c == '?' || c == '#')
+        .next()
+        .unwrap_or("");
+    let host = authority.rsplit('@').next().unwrap_or("");
+    let host = host.split(':').next().unwrap_or("");
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
+
+/// https://fetch.spec.whatwg.org/#data-url-processor
+fn decode_data_url(url: &str) -> Result<String, String> {
+    let body = &url["data:".len()..];
+    let comma = body
+        .find(',')
+        .ok_or_else(|| "Invalid data: URL: missing ','".to_string())?;
+    let (media_type, data) = (&body[..comma], &body[comma + 1..]);
+    let mut params = media_type.split(';').map(|s| s.trim());
+    let mime = params.next().unwrap_or("").to_ascii_lowercase();
+    let is_base64 = params.any(|param| param.eq_ignore_ascii_case("base64"));
+    match mime.as_str() {
+        "" | "text/javascript" | "application/javascript" => {}
+        mime => return Err(format!("Invalid data: URL: unsupported type '{}'", mime)),
+    }
+
+    let bytes = percent_decode(data);
+    let bytes = if is_base64 {
+        base64_decode(&bytes).ok_or_else(|| "Invalid data: URL: bad base64".to_string())?
+    } else {
+        bytes
+    };
+    String::from_utf8(bytes).map_err(|_| "Invalid data: URL: not UTF-8".to_string())
+}
+
+fn percent_decode(s: &str) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = |b: u8| (b as char).to_digit(16);
+        match (bytes[i], bytes.get(i + 1), bytes.get(i + 2)) {
+            (b'%', Some(&h), Some(&l)) if hex(h).is_some() && hex(l).is_some() => {
+                decoded.push((hex(h).unwrap() * 16 + hex(l).unwrap()) as u8);
+                i += 3;
+            }
+            (b, _, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// https://infra.spec.whatwg.org/#forgiving-base64-decode
+fn base64_decode(input: &[u8]) -> Option<Vec<u8>> {
+    let mut input = input
+        .iter()
+        .filter(|b| !b.is_ascii_whitespace())
+        .map(|b| *b)
+        .collect::<Vec<u8>>();
+    if input.len() % 4 == 0 {
+        while input.last() == Some(&b'=') {
+            input.pop();
+        }
+    }
+    if input.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut decoded = vec![];
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for b in input {
+        let value = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = buffer << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(decoded)
+}
+
+#[test]
+fn data_url() {
+    assert_eq!(decode_data_url("data:,1%2B1"), Ok("1+1".to_string()));
+    assert_eq!(
+        decode_data_url("data:text/javascript;base64,bW9kdWxlLmV4cG9ydHMgPSA0Mg=="),
+        Ok("module.exports = 42".to_string())
+    );
+    assert_eq!(
+        decode_data_url("data:application/javascript;charset=utf-8,a%20b"),
+        Ok("a b".to_string())
+    );
+    assert!(decode_data_url("data:text/plain,1").is_err());
+    assert!(decode_data_url("data:;base64,@@@@").is_err());
+    assert!(decode_data_url("data:1").is_err());
+}
+
+#[test]
+fn remote_resolve() {
+    let referrer = Some("https://cdn.example.com/pkg/lib/index.js?v=1");
+    assert_eq!(
+        resolve("./util.js", referrer),
+        Some("https://cdn.example.com/pkg/lib/util.js".to_string())
+    );
+    assert_eq!(
+        resolve("../a/./b.js", referrer),
+        Some("https://cdn.example.com/pkg/a/b.js".to_string())
+    );
+    assert_eq!(
+        resolve("/x.js", referrer),
+        Some("https://cdn.example.com/x.js".to_string())
+    );
+    assert_eq!(resolve("lodash", referrer), None);
+    assert_eq!(resolve("./util.js", Some("/home/a.js")), None);
+    assert_eq!(resolve("data:,1", None), Some("data:,1".to_string()));
+
+    assert_eq!(
+        host("https://user@cdn.example.com:443/a"),
+        Some("cdn.example.com")
+    );
+    assert_eq!(host("https:///a"), None);
+}
+
+#[test]
+fn allowed_hosts() {
+    let mut remote = RemoteModules {
+        allowed_hosts: vec!["cdn.example.com".to_string(), "*.esm.sh".to_string()],
+        cache_dir: None,
+        fetched: FxHashMap::default(),
+    };
+    assert!(remote.is_allowed("cdn.example.com"));
+    assert!(remote.is_allowed("esm.sh"));
+    assert!(remote.is_allowed("a.esm.sh"));
+    assert!(!remote.is_allowed("example.com"));
+    assert!(!remote.is_allowed("evil-esm.sh"));
+    assert!(remote.fetch("https://example.com/a.js").is_err());
+    assert!(remote
+        .check_allowed("https://a.esm.sh/x.js", "https://example.com/x.js")
+        .is_err());
+}
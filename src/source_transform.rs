@@ -0,0 +1,118 @@
+//! Source transforms, which embedders register with ``VM::set_source_transform`` to rewrite
+//! every script and module before it is parsed, e.g. to expand custom macros, substitute
+//! environment variables or add instrumentation. A transform gives an offset table along with
+//! the new source, so that syntax errors, runtime errors and stack traces point at the original
+//! source.
+
+use std::ops::Range;
+
+/// Called with the file name and the source of a script, and returns the source to parse.
+/// An ``Err`` is thrown as an Error where the script was loaded.
+pub type SourceTransform = Box<dyn FnMut(&str, &str) -> Result<TransformedSource, String>>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransformedSource {
+    pub code: String,
+    /// Maps positions in ``code`` to the original source.
+    pub offsets: OffsetTable,
+}
+
+impl TransformedSource {
+    /// ``code`` as it is.
+    pub fn unchanged(code: impl Into<String>) -> Self {
+        TransformedSource {
+            code: code.into(),
+            offsets: OffsetTable::default(),
+        }
+    }
+
+    /// Replace the byte ranges of ``code`` by the strings given with them. The ranges must not
+    /// overlap. Positions in a replacement are mapped to the start of the range it replaced.
+    pub fn replace(code: &str, mut replacements: Vec<(Range<usize>, String)>) -> Self {
+        replacements.sort_by_key(|(range, _)| range.start);
+        let mut new_code = String::new();
+        let mut offsets = OffsetTable::default();
+        let mut copied_from = 0;
+        for (range, text) in replacements {
+            offsets.copied(new_code.len(), copied_from);
+            new_code.push_str(&code[copied_from..range.start]);
+            offsets.inserted(new_code.len(), range.start);
+            new_code.push_str(&text);
+            copied_from = range.end;
+        }
+        offsets.copied(new_code.len(), copied_from);
+        new_code.push_str(&code[copied_from..]);
+        TransformedSource {
+            code: new_code,
+            offsets,
+        }
+    }
+}
+
+/// Positions in a transformed source and where they come from in the original source. The
+/// transformed source is split into segments, each of which is either copied from the original
+/// source or inserted by the transform. An empty table maps every position to itself.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OffsetTable {
+    /// (start in the transformed source, start in the original source, copied or not), sorted.
+    segments: Vec<(usize, usize, bool)>,
+}
+
+impl OffsetTable {
+    /// The transformed source from ``transformed`` is copied from the original source from
+    /// ``original``.
+    pub fn copied(&mut self, transformed: usize, original: usize) {
+        self.push(transformed, original, true)
+    }
+
+    /// The transformed source from ``transformed`` is inserted at ``original``.
+    pub fn inserted(&mut self, transformed: usize, original: usize) {
+        self.push(transformed, original, false)
+    }
+
+    fn push(&mut self, transformed: usize, original: usize, copied: bool) {
+        // An empty segment is replaced by the next one.
+        if self.segments.last().map(|s| s.0) == Some(transformed) {
+            self.segments.pop();
+        }
+        assert!(self.segments.last().map_or(true, |s| s.0 < transformed));
+        self.segments.push((transformed, original, copied));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Get the position in the original source of ``pos`` in the transformed source.
+    pub fn original_pos(&self, pos: usize) -> usize {
+        match self.segments.iter().take_while(|s| s.0 <= pos).last() {
+            Some(&(transformed, original, true)) => original + (pos - transformed),
+            Some(&(_, original, false)) => original,
+            None => pos,
+        }
+    }
+}
+
+#[test]
+fn offset_table() {
+    let code = "let a = __A__; let bc = __BC__;";
+    let transformed = TransformedSource::replace(
+        code,
+        vec![(24..30, "'bc'".to_string()), (8..13, "1234567".to_string())],
+    );
+    assert_eq!(transformed.code, "let a = 1234567; let bc = 'bc';");
+    let offsets = &transformed.offsets;
+    assert_eq!(offsets.original_pos(4), 4);
+    // In the replacement
+    assert_eq!(offsets.original_pos(8), 8);
+    assert_eq!(offsets.original_pos(14), 8);
+    // After the replacement
+    assert_eq!(offsets.original_pos(15), 13);
+    assert_eq!(offsets.original_pos(21), 19);
+    assert_eq!(offsets.original_pos(27), 24);
+    assert_eq!(offsets.original_pos(30), 30);
+
+    let unchanged = TransformedSource::unchanged(code);
+    assert!(unchanged.offsets.is_empty());
+    assert_eq!(unchanged.offsets.original_pos(10), 10);
+}
@@ -7,6 +7,12 @@ pub struct Token {
     /// char position of previous token.
     /// lexer generate this after tokenizing.
     pub prev_pos: usize,
+    /// char position just after this token.
+    pub end: usize,
+    /// 1-based line and column of ``pos``.
+    /// lexer generate these after tokenizing.
+    pub line: usize,
+    pub column: usize,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -17,6 +23,12 @@ pub enum Kind {
     String(String),
     Symbol(Symbol),
     LineTerminator,
+    /// Only produced by ``TokenStream::with_trivia``. Holds the comment including delimiters.
+    Comment(String),
+    /// Only produced by ``TokenStream::with_trivia``.
+    Whitespace,
+    /// Text between JSX tags, as written.
+    JSXText(String),
     EOF,
 }
 
@@ -112,12 +124,39 @@ pub enum Symbol {
     FatArrow,
 }
 
+/// A comment collected by the lexer when ``Lexer::collect_comments`` is set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comment {
+    /// The comment including delimiters.
+    pub text: String,
+    pub pos: usize,
+    pub end: usize,
+}
+
+impl Comment {
+    pub fn is_block(&self) -> bool {
+        self.text.starts_with("/*")
+    }
+
+    /// The comment without delimiters.
+    pub fn content(&self) -> &str {
+        if self.is_block() {
+            &self.text[2..self.text.len() - 2]
+        } else {
+            &self.text[2..]
+        }
+    }
+}
+
 impl Token {
     pub fn new_number(f: f64, pos: usize) -> Token {
         Token {
             kind: Kind::Number(f),
             pos: pos,
             prev_pos: 0,
+            end: pos,
+            line: 0,
+            column: 0,
         }
     }
 
@@ -126,6 +165,9 @@ impl Token {
             kind: Kind::Identifier(ident),
             pos: pos,
             prev_pos: 0,
+            end: pos,
+            line: 0,
+            column: 0,
         }
     }
 
@@ -134,6 +176,9 @@ impl Token {
             kind: Kind::Keyword(keyword),
             pos: pos,
             prev_pos: 0,
+            end: pos,
+            line: 0,
+            column: 0,
         }
     }
 
@@ -142,6 +187,9 @@ impl Token {
             kind: Kind::String(s),
             pos: pos,
             prev_pos: 0,
+            end: pos,
+            line: 0,
+            column: 0,
         }
     }
 
@@ -150,6 +198,9 @@ impl Token {
             kind: Kind::Symbol(symbol),
             pos: pos,
             prev_pos: 0,
+            end: pos,
+            line: 0,
+            column: 0,
         }
     }
 
@@ -158,6 +209,42 @@ impl Token {
             kind: Kind::LineTerminator,
             pos: pos,
             prev_pos: 0,
+            end: pos,
+            line: 0,
+            column: 0,
+        }
+    }
+
+    pub fn new_comment(comment: String, pos: usize) -> Token {
+        Token {
+            kind: Kind::Comment(comment),
+            pos: pos,
+            prev_pos: 0,
+            end: pos,
+            line: 0,
+            column: 0,
+        }
+    }
+
+    pub fn new_jsx_text(text: String, pos: usize) -> Token {
+        Token {
+            kind: Kind::JSXText(text),
+            pos: pos,
+            prev_pos: 0,
+            end: pos,
+            line: 0,
+            column: 0,
+        }
+    }
+
+    pub fn new_whitespace(pos: usize) -> Token {
+        Token {
+            kind: Kind::Whitespace,
+            pos: pos,
+            prev_pos: 0,
+            end: pos,
+            line: 0,
+            column: 0,
         }
     }
 }
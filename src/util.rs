@@ -13,6 +13,36 @@ pub fn get_raw_bytes<T>(p: &T) -> Vec<u8> {
     get_raw_bytes_with_size(p, mem::size_of::<T>())
 }
 
+/// Native stack size that a recursive tree walk (parsing, code generation) may use before
+/// giving up with an error instead of overflowing the stack.
+/// Threads that run the parser need at least twice this much stack.
+pub const STACK_SIZE_LIMIT: usize = 1024 * 1024;
+
+/// Measures the native stack used since it was created, assuming the stack grows downward.
+#[derive(Clone, Debug)]
+pub struct StackLimit {
+    base: usize,
+}
+
+impl StackLimit {
+    pub fn new() -> Self {
+        StackLimit {
+            base: stack_pointer(),
+        }
+    }
+
+    pub fn is_exceeded(&self) -> bool {
+        self.base.saturating_sub(stack_pointer()) > STACK_SIZE_LIMIT
+    }
+}
+
+/// Approximate address of the top of the native stack.
+#[inline(never)]
+fn stack_pointer() -> usize {
+    let marker = 0u8;
+    &marker as *const u8 as usize
+}
+
 pub fn print_bytes(bytes: Vec<u8>) {
     for (i, x) in bytes.iter().enumerate() {
         if i % 16 == 0 {
@@ -0,0 +1,143 @@
+//! Access checks of host objects. ``VM::guarded_object`` makes a view of a host object whose
+//! internal methods ask the host's callback before each access to its properties, so that the
+//! host can implement read-only views or audit accesses without copying the object. The view
+//! is a proxy without a handler, so scripts see it as the object itself.
+
+use crate::vm::{
+    error::{message, RuntimeError},
+    internal_methods::{internal_methods, InternalMethods},
+    jsvalue::value::{Property, PropertyDescriptor, SetResult, Value},
+    vm::{VMValueResult, VM},
+};
+use std::rc::Rc;
+
+/// The kind of an access to a property of a guarded object.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    /// Getting the property or its descriptor, or checking that it exists by ``in``. A
+    /// property denied so looks absent, except that getting it throws a TypeError.
+    Get,
+    /// Assigning or defining the property.
+    Set,
+    Delete,
+    /// Listing the keys of the properties, e.g. by ``Object.keys``. The key given to the
+    /// callback is undefined.
+    Enumerate,
+}
+
+/// Called with the key and the kind of each access. Ok(false) denies the access: a denied read
+/// or enumeration throws a TypeError, and a denied write fails as on a frozen object (which is
+/// a TypeError in strict code).
+pub type AccessCheck = Rc<dyn Fn(&mut VM, Value, Access) -> Result<bool, RuntimeError>>;
+
+/// The internal methods of guarded objects.
+pub struct GuardedObject;
+
+/// Ask the access check of the guarded object ``obj``, and get its target if allowed.
+fn check(
+    vm: &mut VM,
+    obj: Value,
+    key: Value,
+    access: Access,
+) -> Result<Option<Value>, RuntimeError> {
+    let (target, id) = {
+        let info = obj.as_proxy();
+        (info.target, info.access_check.unwrap())
+    };
+    let check = vm.access_checks[id].clone();
+    Ok(if check(vm, key, access)? {
+        Some(target)
+    } else {
+        None
+    })
+}
+
+/// Ask the access check for a read, which throws a TypeError if denied.
+fn check_read(vm: &mut VM, obj: Value, key: Value, access: Access) -> Result<Value, RuntimeError> {
+    match check(vm, obj, key, access)? {
+        Some(target) => Ok(target),
+        None if access == Access::Enumerate => {
+            Err(vm.throw_type_error(message::enumeration_denied()))
+        }
+        None => Err(vm.throw_type_error(message::property_access_denied(key))),
+    }
+}
+
+impl InternalMethods for GuardedObject {
+    fn get_prototype_of(&self, vm: &mut VM, obj: Value) -> Result<Value, RuntimeError> {
+        let target = obj.as_proxy().target;
+        internal_methods(target).get_prototype_of(vm, target)
+    }
+
+    fn get_own_property(
+        &self,
+        vm: &mut VM,
+        obj: Value,
+        key: Value,
+    ) -> Result<Option<Property>, RuntimeError> {
+        match check(vm, obj, key, Access::Get)? {
+            Some(target) => internal_methods(target).get_own_property(vm, target, key),
+            None => Ok(None),
+        }
+    }
+
+    fn define_own_property(
+        &self,
+        vm: &mut VM,
+        obj: Value,
+        key: Value,
+        desc: PropertyDescriptor,
+    ) -> Result<bool, RuntimeError> {
+        match check(vm, obj, key, Access::Set)? {
+            Some(target) => internal_methods(target).define_own_property(vm, target, key, desc),
+            None => Ok(false),
+        }
+    }
+
+    fn has_property(&self, vm: &mut VM, obj: Value, key: Value) -> Result<bool, RuntimeError> {
+        match check(vm, obj, key, Access::Get)? {
+            Some(target) => internal_methods(target).has_property(vm, target, key),
+            None => Ok(false),
+        }
+    }
+
+    fn get(&self, vm: &mut VM, obj: Value, key: Value) -> Result<Property, RuntimeError> {
+        let target = check_read(vm, obj, key, Access::Get)?;
+        internal_methods(target).get(vm, target, key)
+    }
+
+    fn set(
+        &self,
+        vm: &mut VM,
+        obj: Value,
+        key: Value,
+        val: Value,
+    ) -> Result<SetResult, RuntimeError> {
+        match check(vm, obj, key, Access::Set)? {
+            Some(target) => internal_methods(target).set(vm, target, key, val),
+            None => Ok(SetResult::Rejected),
+        }
+    }
+
+    fn delete(&self, vm: &mut VM, obj: Value, key: Value) -> Result<bool, RuntimeError> {
+        match check(vm, obj, key, Access::Delete)? {
+            Some(target) => internal_methods(target).delete(vm, target, key),
+            None => Ok(false),
+        }
+    }
+
+    fn own_property_keys(&self, vm: &mut VM, obj: Value) -> Result<Vec<Value>, RuntimeError> {
+        let target = check_read(vm, obj, Value::undefined(), Access::Enumerate)?;
+        internal_methods(target).own_property_keys(vm, target)
+    }
+
+    fn call(&self, vm: &mut VM, obj: Value, args: &[Value], this: Value) -> VMValueResult {
+        let target = obj.as_proxy().target;
+        internal_methods(target).call(vm, target, args, this)
+    }
+
+    fn construct(&self, vm: &mut VM, obj: Value, args: &[Value]) -> VMValueResult {
+        let target = obj.as_proxy().target;
+        internal_methods(target).construct(vm, target, args)
+    }
+}
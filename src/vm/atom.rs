@@ -0,0 +1,142 @@
+//! The atom table. Property keys and the names in constant tables are interned, so that there
+//! is only one ``Atom`` for each string, and atoms are compared and hashed by their pointers
+//! instead of their contents. Like shapes, the table is shared by all the VMs of a thread.
+//!
+//! An atom is dropped from the table some time after its last user, so that the keys of
+//! objects used as dictionaries do not stay forever.
+
+use rustc_hash::FxHashSet;
+use std::cell::RefCell;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+/// The number of atoms under which the table is never pruned.
+const MIN_PRUNE_LEN: usize = 256;
+
+#[derive(Clone)]
+pub struct Atom(Rc<str>);
+
+struct AtomTable {
+    atoms: FxHashSet<Rc<str>>,
+    /// The number of atoms at which the atoms no one uses are dropped.
+    prune_len: usize,
+}
+
+thread_local! {
+    static ATOMS: RefCell<AtomTable> = RefCell::new(AtomTable {
+        atoms: FxHashSet::default(),
+        prune_len: MIN_PRUNE_LEN,
+    });
+}
+
+impl Atom {
+    /// The atom of ``s``, which is made if there is none.
+    pub fn new(s: &str) -> Atom {
+        Atom::lookup(s).unwrap_or_else(|| Atom::insert(Rc::from(s)))
+    }
+
+    /// The atom of ``s`` if there is one. A string without an atom is not a key of any shape,
+    /// so that a lookup by it fails without making one.
+    pub fn lookup(s: &str) -> Option<Atom> {
+        ATOMS.with(|table| table.borrow().atoms.get(s).cloned().map(Atom))
+    }
+
+    fn insert(s: Rc<str>) -> Atom {
+        ATOMS.with(|table| {
+            let table = &mut *table.borrow_mut();
+            if table.atoms.len() >= table.prune_len {
+                table.atoms.retain(|atom| Rc::strong_count(atom) > 1);
+                table.prune_len = (table.atoms.len() * 2).max(MIN_PRUNE_LEN);
+            }
+            table.atoms.insert(s.clone());
+            Atom(s)
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Atom {
+    fn eq(&self, other: &Atom) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Atom {}
+
+impl Hash for Atom {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0.as_ptr() as usize).hash(state)
+    }
+}
+
+impl PartialEq<str> for Atom {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Atom {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl std::ops::Deref for Atom {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Atom {
+    fn from(s: &str) -> Atom {
+        Atom::new(s)
+    }
+}
+
+impl From<String> for Atom {
+    fn from(s: String) -> Atom {
+        Atom::lookup(&s).unwrap_or_else(|| Atom::insert(Rc::from(s)))
+    }
+}
+
+impl From<&String> for Atom {
+    fn from(s: &String) -> Atom {
+        Atom::new(s)
+    }
+}
+
+impl std::fmt::Debug for Atom {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl std::fmt::Display for Atom {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[test]
+fn atoms() {
+    let a = Atom::new("length");
+    let b = Atom::from("length".to_string());
+    assert_eq!(a, b);
+    assert!(Rc::ptr_eq(&a.0, &b.0));
+    assert_ne!(a, Atom::new("size"));
+    assert_eq!(a, "length");
+    assert_eq!(Atom::lookup("length"), Some(a.clone()));
+
+    // The atoms no one uses are dropped once the table grows.
+    drop(Atom::new("unused atom"));
+    for i in 0..MIN_PRUNE_LEN * 2 {
+        Atom::new(&i.to_string());
+    }
+    assert_eq!(Atom::lookup("unused atom"), None);
+    assert_eq!(Atom::lookup("length"), Some(a));
+}
@@ -0,0 +1,624 @@
+//! Compiled scripts in a binary format, so that a script can be run again without parsing and
+//! compiling it (``rapidus --compile foo.js -o foo.rbc``).
+//!
+//! A file consists of:
+//! - the magic number ``RBC\0``, the format version and the version of Rapidus that wrote it,
+//!   since the bytecode changes between versions,
+//! - the script text, if it was given, to show where runtime errors occur,
+//! - the constants referenced by the bytecode, renumbered from 0,
+//! - the functions, inner functions first and the global code last, each with the lines and
+//!   columns of its instructions.
+//!
+//! Integers are little endian and lengths are u32.
+//! The constants are appended to the constant table of the VM that loads the file, and the
+//! constant ids in the bytecode are relocated to them.
+
+use crate::build_info::VERSION;
+use crate::bytecode_gen::{read_int32, ByteCode, VMInst};
+use crate::parser::ScriptInfo;
+use crate::vm::constant::{Constant, SpecialPropertyKind};
+use crate::vm::factory::FunctionId;
+use crate::vm::jsvalue::function::{
+    CallCaches, ExceptionHandler, FuncInfoRef, FunctionObjectKind, FunctionParameter, HandlerKind,
+    ThisMode, UserFunctionInfo,
+};
+use crate::vm::jsvalue::value::Value;
+use crate::vm::source_position::PositionTable;
+use crate::vm::vm::VM;
+use rustc_hash::FxHashMap;
+
+pub const MAGIC: &[u8; 4] = b"RBC\0";
+pub const FORMAT_VERSION: u32 = 16;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The data does not start with the magic number.
+    NotBytecode,
+    /// The data was written by another version of the format or of Rapidus.
+    VersionMismatch,
+    /// The data is truncated or has an invalid value.
+    Corrupted,
+    /// The script refers to a constant that can not be written, e.g. a built-in function.
+    UnsupportedConstant,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let msg = match self {
+            Error::NotBytecode => "not a compiled script",
+            Error::VersionMismatch => "compiled by another version of Rapidus",
+            Error::Corrupted => "the compiled script is corrupted",
+            Error::UnsupportedConstant => "the script has a constant that can not be serialized",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+/// Serialize the script compiled to ``global_info`` by ``vm``. ``script_info`` is included to
+/// show the positions of runtime errors when the script is loaded.
+pub fn serialize(
+    vm: &VM,
+    global_info: FuncInfoRef,
+    script_info: Option<&ScriptInfo>,
+) -> Result<Vec<u8>, Error> {
+    let mut serializer = Serializer {
+        vm,
+        constant_ids: FxHashMap::default(),
+        constants: Writer::new(),
+        constant_count: 0,
+        function_indexes: FxHashMap::default(),
+        functions: Writer::new(),
+        function_count: 0,
+    };
+    serializer.function(global_info)?;
+
+    let mut writer = Writer::new();
+    writer.bytes(MAGIC);
+    writer.u32(FORMAT_VERSION);
+    writer.str(VERSION);
+    match script_info {
+        Some(info) => {
+            writer.u8(1);
+            writer.str(&info.file_name);
+            writer.str(&info.code);
+            writer.usize(info.pos_line_list.len());
+            for (pos, line) in &info.pos_line_list {
+                writer.usize(*pos);
+                writer.usize(*line);
+            }
+        }
+        None => writer.u8(0),
+    }
+    writer.usize(serializer.constant_count);
+    writer.bytes(&serializer.constants.buf);
+    writer.usize(serializer.function_count);
+    writer.bytes(&serializer.functions.buf);
+    Ok(writer.buf)
+}
+
+/// Load a script serialized by ``serialize`` into ``vm``. The returned function can be run with
+/// ``VM::run_global``.
+pub fn deserialize(vm: &mut VM, bytes: &[u8]) -> Result<FuncInfoRef, Error> {
+    let mut reader = Reader { bytes, pos: 0 };
+    if reader.bytes(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+        return Err(Error::NotBytecode);
+    }
+    if reader.u32()? != FORMAT_VERSION || reader.string()? != VERSION {
+        return Err(Error::VersionMismatch);
+    }
+
+    let script_info = match reader.u8()? {
+        0 => None,
+        1 => {
+            let file_name = reader.string()?;
+            let code = reader.string()?;
+            let mut pos_line_list = vec![];
+            for _ in 0..reader.usize()? {
+                pos_line_list.push((reader.usize()?, reader.usize()?));
+            }
+            Some(ScriptInfo {
+                file_name,
+                code,
+                pos_line_list,
+            })
+        }
+        _ => return Err(Error::Corrupted),
+    };
+
+    // Function values are created after the functions are read, in place of undefined.
+    let mut constant_ids = vec![];
+    let mut function_values = vec![];
+    for _ in 0..reader.usize()? {
+        let constant = match reader.u8()? {
+            0 => Constant::String(reader.string()?.into()),
+            1 => Constant::Value(match reader.u8()? {
+                0 => Value::Number(reader.f64()?),
+                1 => Value::bool(reader.u8()? != 0),
+                2 => {
+                    let string = reader.string()?;
+                    vm.factory.string(string)
+                }
+                3 => Value::Other(reader.u32()? as i32),
+                4 => {
+                    function_values.push((vm.constant_table.table.len(), reader.usize()?));
+                    Value::undefined()
+                }
+                _ => return Err(Error::Corrupted),
+            }),
+            2 => Constant::LexicalEnvironmentInfo {
+                names: reader.strings()?,
+            },
+            3 => {
+                let mut special_properties = FxHashMap::default();
+                for _ in 0..reader.usize()? {
+                    let index = reader.usize()?;
+                    let kind = match reader.u8()? {
+                        0 => SpecialPropertyKind::Getter,
+                        1 => SpecialPropertyKind::Setter,
+                        2 => SpecialPropertyKind::Spread,
+                        _ => return Err(Error::Corrupted),
+                    };
+                    special_properties.insert(index, kind);
+                }
+                Constant::ObjectLiteralInfo(special_properties)
+            }
+            _ => return Err(Error::Corrupted),
+        };
+        constant_ids.push(vm.constant_table.table.len());
+        vm.constant_table.table.push(constant);
+    }
+
+    let module_func_id = vm.factory.new_func_id();
+    let function_count = reader.usize()?;
+    let mut functions: Vec<FuncInfoRef> = vec![];
+    for index in 0..function_count {
+        let func_id = if index + 1 == function_count {
+            module_func_id
+        } else {
+            vm.factory.new_func_id()
+        };
+        let func_name = match reader.u8()? {
+            0 => None,
+            _ => Some(reader.string()?),
+        };
+        let mut params = vec![];
+        for _ in 0..reader.usize()? {
+            params.push(FunctionParameter {
+                name: reader.string()?,
+                rest_param: reader.u8()? != 0,
+                register: match reader.u8()? {
+                    0 => None,
+                    1 => Some(reader.usize()?),
+                    _ => return Err(Error::Corrupted),
+                },
+            });
+        }
+        let var_names = reader.strings()?;
+        let register_names = reader.strings()?;
+        if params
+            .iter()
+            .any(|param| param.register.map_or(false, |i| i >= register_names.len()))
+        {
+            return Err(Error::Corrupted);
+        }
+        let lex_names = reader.strings()?;
+        let mut func_decls = vec![];
+        for _ in 0..reader.usize()? {
+            let decl = reader.usize()?;
+            func_decls.push(*functions.get(decl).ok_or(Error::Corrupted)?);
+        }
+        let captured_names = reader.strings()?;
+        let len = reader.usize()?;
+        let code = relocate(
+            reader.bytes(len)?.to_vec(),
+            &constant_ids,
+            register_names.len(),
+        )?;
+        let mut exception_table = vec![];
+        for _ in 0..reader.usize()? {
+            exception_table.push(ExceptionHandler {
+                start: reader.usize()?,
+                end: reader.usize()?,
+                handler: reader.usize()?,
+                kind: match reader.u8()? {
+                    0 => HandlerKind::Catch,
+                    1 => HandlerKind::Finally,
+                    _ => return Err(Error::Corrupted),
+                },
+                env_depth: reader.usize()?,
+                finally_depth: reader.usize()?,
+            });
+        }
+        let constructible = reader.u8()? != 0;
+        let this_mode = match reader.u8()? {
+            0 => ThisMode::Lexical,
+            1 => ThisMode::Global,
+            2 => ThisMode::Strict,
+            _ => return Err(Error::Corrupted),
+        };
+        let strict = reader.u8()? != 0;
+        let len = reader.usize()?;
+        let positions =
+            PositionTable::from_bytes(reader.bytes(len)?.to_vec()).ok_or(Error::Corrupted)?;
+        let location = match reader.u8()? {
+            0 => None,
+            1 => Some((reader.usize()?, reader.usize()?)),
+            _ => return Err(Error::Corrupted),
+        };
+
+        let info = UserFunctionInfo {
+            func_name,
+            func_id,
+            module_func_id,
+            params,
+            var_names,
+            register_names,
+            lex_names,
+            func_decls,
+            captured_names,
+            code,
+            exception_table,
+            constructible,
+            this_mode,
+            strict,
+            positions,
+            location,
+            call_caches: CallCaches::default(),
+        };
+        functions.push(vm.factory.alloc_user_func_info(func_id, info));
+    }
+    if reader.pos != bytes.len() {
+        return Err(Error::Corrupted);
+    }
+
+    for (id, index) in function_values {
+        let info = *functions.get(index).ok_or(Error::Corrupted)?;
+        let val = vm.factory.function(info, None);
+        *vm.constant_table.get_mut(id) = Constant::Value(val);
+    }
+    if let Some(script_info) = script_info {
+        vm.script_info.push((module_func_id, script_info));
+    }
+
+    functions.last().cloned().ok_or(Error::Corrupted)
+}
+
+/// Replace the constant ids in ``code`` with ``constant_ids[id]``.
+fn relocate(
+    mut code: ByteCode,
+    constant_ids: &[usize],
+    register_count: usize,
+) -> Result<ByteCode, Error> {
+    let mut pc = 0;
+    while pc < code.len() {
+        let inst = code[pc];
+        let size = VMInst::get_inst_size(inst).ok_or(Error::Corrupted)?;
+        if pc + size > code.len() {
+            return Err(Error::Corrupted);
+        }
+        if VMInst::has_constant_operand(inst) {
+            let id = read_int32(&code, pc + 1) as usize;
+            let id = *constant_ids.get(id).ok_or(Error::Corrupted)?;
+            code[pc + 1..pc + 5].copy_from_slice(&(id as i32).to_le_bytes());
+        }
+        if inst == VMInst::GET_REGISTER || inst == VMInst::SET_REGISTER {
+            if read_int32(&code, pc + 1) as usize >= register_count {
+                return Err(Error::Corrupted);
+            }
+        }
+        pc += size;
+    }
+    Ok(code)
+}
+
+struct Serializer<'a> {
+    vm: &'a VM,
+    /// Ids in the file of the constants written so far, by id in the constant table.
+    constant_ids: FxHashMap<usize, usize>,
+    constants: Writer,
+    constant_count: usize,
+    /// Indexes in the file of the functions written so far, by function id.
+    function_indexes: FxHashMap<FunctionId, usize>,
+    functions: Writer,
+    function_count: usize,
+}
+
+impl<'a> Serializer<'a> {
+    /// Write the function ``info`` after its inner functions, and return its index.
+    fn function(&mut self, info: FuncInfoRef) -> Result<usize, Error> {
+        if let Some(index) = self.function_indexes.get(&info.func_id) {
+            return Ok(*index);
+        }
+
+        let mut code = info.code.clone();
+        let mut pc = 0;
+        while pc < code.len() {
+            let inst = code[pc];
+            if VMInst::has_constant_operand(inst) {
+                let id = self.constant(read_int32(&code, pc + 1) as usize)?;
+                code[pc + 1..pc + 5].copy_from_slice(&(id as i32).to_le_bytes());
+            }
+            pc += VMInst::get_inst_size(inst).unwrap();
+        }
+        let func_decls = info
+            .func_decls
+            .iter()
+            .map(|decl| self.function(*decl))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let writer = &mut self.functions;
+        match info.func_name {
+            Some(ref name) => {
+                writer.u8(1);
+                writer.str(name);
+            }
+            None => writer.u8(0),
+        }
+        writer.usize(info.params.len());
+        for param in &info.params {
+            writer.str(&param.name);
+            writer.u8(param.rest_param as u8);
+            match param.register {
+                Some(index) => {
+                    writer.u8(1);
+                    writer.usize(index);
+                }
+                None => writer.u8(0),
+            }
+        }
+        writer.strings(&info.var_names);
+        writer.strings(&info.register_names);
+        writer.strings(&info.lex_names);
+        writer.usize(func_decls.len());
+        for index in func_decls {
+            writer.usize(index);
+        }
+        writer.strings(&info.captured_names);
+        writer.usize(code.len());
+        writer.bytes(&code);
+        writer.usize(info.exception_table.len());
+        for handler in &info.exception_table {
+            writer.usize(handler.start);
+            writer.usize(handler.end);
+            writer.usize(handler.handler);
+            writer.u8(match handler.kind {
+                HandlerKind::Catch => 0,
+                HandlerKind::Finally => 1,
+            });
+            writer.usize(handler.env_depth);
+            writer.usize(handler.finally_depth);
+        }
+        writer.u8(info.constructible as u8);
+        writer.u8(match info.this_mode {
+            ThisMode::Lexical => 0,
+            ThisMode::Global => 1,
+            ThisMode::Strict => 2,
+        });
+        writer.u8(info.strict as u8);
+        let positions = info.positions.as_bytes();
+        writer.usize(positions.len());
+        writer.bytes(positions);
+        match info.location {
+            Some((line, column)) => {
+                writer.u8(1);
+                writer.usize(line);
+                writer.usize(column);
+            }
+            None => writer.u8(0),
+        }
+
+        let index = self.function_count;
+        self.function_count += 1;
+        self.function_indexes.insert(info.func_id, index);
+        Ok(index)
+    }
+
+    /// Write the constant ``id`` if it is not written yet, and return its id in the file.
+    fn constant(&mut self, id: usize) -> Result<usize, Error> {
+        if let Some(file_id) = self.constant_ids.get(&id) {
+            return Ok(*file_id);
+        }
+
+        let mut writer = Writer::new();
+        match self.vm.constant_table.get(id) {
+            Constant::String(string) => {
+                writer.u8(0);
+                writer.str(string);
+            }
+            Constant::Value(val) => {
+                writer.u8(1);
+                match val {
+                    Value::Number(n) => {
+                        writer.u8(0);
+                        writer.f64(*n);
+                    }
+                    Value::Bool(b) => {
+                        writer.u8(1);
+                        writer.u8(*b);
+                    }
+                    Value::String(_) => {
+                        writer.u8(2);
+                        writer.str(val.into_str());
+                    }
+                    Value::Other(other) => {
+                        writer.u8(3);
+                        writer.u32(*other as u32);
+                    }
+                    Value::Object(_) if val.is_function_object() => {
+                        let info = match val.as_function().kind {
+                            FunctionObjectKind::User { info, .. } => info,
+                            FunctionObjectKind::Builtin(_)
+                            | FunctionObjectKind::Native(_)
+                            | FunctionObjectKind::Bound { .. } => {
+                                return Err(Error::UnsupportedConstant)
+                            }
+                        };
+                        writer.u8(4);
+                        writer.usize(self.function(info)?);
+                    }
+                    Value::Object(_) => return Err(Error::UnsupportedConstant),
+                }
+            }
+            Constant::LexicalEnvironmentInfo { names } => {
+                writer.u8(2);
+                writer.strings(names);
+            }
+            Constant::ObjectLiteralInfo(special_properties) => {
+                writer.u8(3);
+                let mut special_properties: Vec<_> = special_properties.iter().collect();
+                special_properties.sort_by_key(|(index, _)| **index);
+                writer.usize(special_properties.len());
+                for (index, kind) in special_properties {
+                    writer.usize(*index);
+                    writer.u8(match kind {
+                        SpecialPropertyKind::Getter => 0,
+                        SpecialPropertyKind::Setter => 1,
+                        SpecialPropertyKind::Spread => 2,
+                    });
+                }
+            }
+        }
+
+        let file_id = self.constant_count;
+        self.constant_count += 1;
+        self.constants.bytes(&writer.buf);
+        self.constant_ids.insert(id, file_id);
+        Ok(file_id)
+    }
+}
+
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn new() -> Self {
+        Writer { buf: vec![] }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn u8(&mut self, n: u8) {
+        self.buf.push(n);
+    }
+
+    fn u32(&mut self, n: u32) {
+        self.bytes(&n.to_le_bytes());
+    }
+
+    fn usize(&mut self, n: usize) {
+        self.u32(n as u32);
+    }
+
+    fn f64(&mut self, n: f64) {
+        self.bytes(&n.to_bits().to_le_bytes());
+    }
+
+    fn str(&mut self, string: &str) {
+        self.usize(string.len());
+        self.bytes(string.as_bytes());
+    }
+
+    fn strings(&mut self, strings: &[String]) {
+        self.usize(strings.len());
+        for string in strings {
+            self.str(string);
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or(Error::Corrupted)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn usize(&mut self) -> Result<usize, Error> {
+        Ok(self.u32()? as usize)
+    }
+
+    fn f64(&mut self) -> Result<f64, Error> {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(self.bytes(8)?);
+        Ok(f64::from_bits(u64::from_le_bytes(buf)))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let len = self.usize()?;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| Error::Corrupted)
+    }
+
+    fn strings(&mut self) -> Result<Vec<String>, Error> {
+        let mut strings = vec![];
+        for _ in 0..self.usize()? {
+            strings.push(self.string()?);
+        }
+        Ok(strings)
+    }
+}
+
+#[test]
+fn bytecode_file() {
+    use crate::parser::Parser;
+
+    let code = "function f(x) { return x * 2 }
+        var o = { a: 1, get b() { return this.a + 1 }, ...{ c: 3 } };
+        let s = 'str';
+        try { throw 1 } catch (e) { s += e } finally { s += '!' }
+        [f(21), o.b, o.c, s, (() => typeof f)()]";
+    let mut parser = Parser::new("test.js", code);
+    let mut node = parser.parse_all().unwrap();
+    let mut vm = VM::new();
+    let global_info = vm
+        .compile_with_source(&mut node, true, parser.source_text())
+        .unwrap();
+    let positions = global_info.positions.clone();
+    assert!(!positions.is_empty());
+    let script_info = parser.into_script_info();
+    let bytes = serialize(&vm, global_info, Some(&script_info)).unwrap();
+
+    // Load into a VM whose constant table is not empty.
+    let mut vm = VM::new();
+    let mut node = Parser::new("other.js", "'other'").parse_all().unwrap();
+    vm.compile(&mut node, true).unwrap();
+    let global_info = deserialize(&mut vm, &bytes).unwrap();
+    assert_eq!(global_info.positions, positions);
+    vm.current_context = vm.create_global_context(global_info);
+    let val = vm.run().unwrap();
+    assert_eq!(val.debug_string(true), "[ 42, 2, 3, 'str1!', 'function' ]");
+    assert_eq!(vm.script_info.last().unwrap().1.file_name, "test.js");
+
+    assert_eq!(deserialize(&mut vm, b"RBC").err(), Some(Error::NotBytecode));
+    let mut stale = bytes.clone();
+    stale[4] += 1;
+    assert_eq!(
+        deserialize(&mut vm, &stale).err(),
+        Some(Error::VersionMismatch)
+    );
+    assert_eq!(
+        deserialize(&mut vm, &bytes[..bytes.len() - 1]).err(),
+        Some(Error::Corrupted)
+    );
+}
@@ -1,17 +1,25 @@
 use crate::bytecode_gen::{ByteCode, ByteCodeGenerator, VMInst};
 use crate::node::{
-    BinOp, FormalParameter, FormalParameters, MethodDefinitionKind, Node, NodeBase,
+    BinOp, FormalParameter, FormalParameters, MethodDefinitionKind, Node, NodeBase, NodeMap,
     PropertyDefinition, UnaryOp, VarKind,
 };
+use crate::util::StackLimit;
 use crate::vm::constant::{ConstantTable, SpecialProperties, SpecialPropertyKind};
+use crate::vm::constant_folding::fold_constants;
+use crate::vm::dead_code::eliminate_dead_code;
 use crate::vm::factory::FunctionId;
 use crate::vm::jsvalue::function::{
-    DestinationKind, Exception, FuncInfoRef, ThisMode, UserFunctionInfo,
+    CallCaches, ExceptionHandler, FuncInfoRef, HandlerKind, ThisMode, UserFunctionInfo,
 };
 use crate::vm::jsvalue::value;
 use crate::vm::jsvalue::value::Value;
+#[cfg(feature = "jsx")]
+use crate::vm::jsx::{transform_jsx, FactoryCall, JSXTransform};
+use crate::vm::scope_analysis::{FunctionScope, Resolution};
+use crate::vm::source_position::{PositionTable, SourceText};
 use crate::vm::vm::Factory;
-use rustc_hash::FxHashMap;
+#[cfg(feature = "jsx")]
+use std::rc::Rc;
 
 pub type CodeGenResult = Result<(), Error>;
 
@@ -26,6 +34,15 @@ pub struct Error {
 pub enum ErrorKind {
     General,
     Unimplemented,
+    /// A name is declared twice. Holds the position of the previous declaration.
+    Redeclaration(usize),
+}
+
+/// A diagnostic on code that compiles, e.g. unreachable code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    pub msg: String,
+    pub token_pos: usize,
 }
 
 #[derive(Debug)]
@@ -33,10 +50,17 @@ pub struct CodeGenerator<'a> {
     pub bytecode_generator: ByteCodeGenerator<'a>,
     pub factory: &'a mut Factory,
     pub function_stack: Vec<FunctionInfo>,
-    pub to_source_map: FxHashMap<FunctionId, ToSourcePos>,
+    /// The script being compiled, to give the compiled functions their position tables.
+    pub source: Option<SourceText<'a>>,
     /// A position in the bytecode of the current node.
     pub node_pos: usize,
     pub module_func_id: FunctionId,
+    pub warnings: Vec<Warning>,
+    #[cfg(feature = "jsx")]
+    pub jsx_transform: Rc<dyn JSXTransform>,
+    stack_limit: StackLimit,
+    /// The scopes of the functions not compiled yet, keyed by the ids of their bodies.
+    scopes: NodeMap<FunctionScope>,
 }
 
 #[derive(Debug, Clone)]
@@ -47,16 +71,21 @@ pub struct FunctionInfo {
     pub lex_names: Vec<String>,
     pub func_decls: Vec<FuncInfoRef>,
     pub level: Vec<Level>,
-    pub exception_table: Vec<Exception>,
+    pub exception_table: Vec<ExceptionHandler>,
     pub to_source_pos: ToSourcePos,
     pub module_func_id: FunctionId,
+    pub scope: FunctionScope,
+    /// Names of the variables stored in registers, sorted. See ``FunctionScope::register_names``.
+    pub register_names: Vec<String>,
+    /// Whether the function is strict code.
+    pub strict: bool,
 }
 
 #[derive(Debug, Clone)]
-/// Table of correspondence of an instruction pointer and char position on script.
+/// Table of correspondence of an instruction pointer and char position on script, turned into
+/// a ``PositionTable`` when the function is compiled.
 pub struct ToSourcePos {
     table: Vec<(usize, usize)>,
-    module_func_id: FunctionId,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -66,6 +95,7 @@ pub enum Level {
         names: Vec<String>,
     },
     TryOrCatch {
+        /// ``JMP_SUB``s to the finally clause, by break and continue leaving the clause.
         finally_jmp_instr_pos: Vec<usize>,
     },
     Finally,
@@ -85,21 +115,34 @@ impl<'a> CodeGenerator<'a> {
             bytecode_generator: ByteCodeGenerator::new(constant_table),
             factory,
             function_stack: vec![FunctionInfo::new(None, module_func_id) /* = global */],
-            to_source_map: FxHashMap::default(),
+            source: None,
             node_pos: 0,
             module_func_id,
+            warnings: vec![],
+            #[cfg(feature = "jsx")]
+            jsx_transform: Rc::new(FactoryCall::default()),
+            stack_limit: StackLimit::new(),
+            scopes: NodeMap::default(),
         }
     }
 
-    pub fn compile(&mut self, node: &Node, use_value: bool) -> Result<FuncInfoRef, Error> {
+    pub fn compile(&mut self, node: &mut Node, use_value: bool) -> Result<FuncInfoRef, Error> {
         let mut iseq = vec![];
+        self.stack_limit = StackLimit::new();
+        #[cfg(feature = "jsx")]
+        transform_jsx(node, &*self.jsx_transform, &self.stack_limit);
+        fold_constants(node, &self.stack_limit);
+        eliminate_dead_code(node, &self.stack_limit, &mut self.warnings);
+        let (scope, scopes) = FunctionScope::analyze_script(node, &self.stack_limit);
+        self.scopes = scopes;
+        self.set_scope(scope)?;
+        self.current_function().strict = has_use_strict_directive(node);
         self.visit(node, &mut iseq, use_value)?;
         self.bytecode_generator.append_return(&mut iseq);
 
         let function_info = self.function_stack.pop().unwrap();
         let module_id = self.module_func_id;
-        self.to_source_map
-            .insert(module_id, function_info.to_source_pos.clone());
+        let positions = self.position_table(&function_info.to_source_pos);
 
         let user_func_info = UserFunctionInfo {
             func_name: None,
@@ -107,12 +150,18 @@ impl<'a> CodeGenerator<'a> {
             module_func_id: module_id,
             params: vec![],
             var_names: function_info.var_names,
+            register_names: vec![],
             lex_names: function_info.lex_names,
             func_decls: function_info.func_decls,
+            captured_names: function_info.scope.captured_names(),
             constructible: false,
             this_mode: ThisMode::Global,
+            strict: function_info.strict,
             code: iseq,
             exception_table: function_info.exception_table,
+            positions,
+            location: self.source.map(|source| source.line_column(node.pos)),
+            call_caches: CallCaches::default(),
         };
 
         Ok(self.factory.alloc_user_func_info(module_id, user_func_info))
@@ -124,6 +173,12 @@ impl<'a> CodeGenerator<'a> {
 impl<'a> CodeGenerator<'a> {
     fn visit(&mut self, node: &Node, iseq: &mut ByteCode, use_value: bool) -> CodeGenResult {
         self.node_pos = node.pos;
+        if self.stack_limit.is_exceeded() {
+            return Err(Error::new_general_error(
+                "Nesting is too deep".to_string(),
+                node.pos,
+            ));
+        }
         match node.base {
             NodeBase::StatementList(ref node_list) => {
                 self.visit_statement_list(node_list, iseq, use_value)?
@@ -174,13 +229,17 @@ impl<'a> CodeGenerator<'a> {
                 self.visit_call(&*callee, args, iseq, use_value)?
             }
             NodeBase::Throw(ref val) => self.visit_throw(val, iseq)?,
+            NodeBase::Debugger => {
+                self.save_source_pos(iseq);
+                self.bytecode_generator.append_debugger(iseq)
+            }
             NodeBase::Return(ref val) => self.visit_return(val, iseq)?,
             NodeBase::New(ref expr) => self.visit_new(&*expr, iseq, use_value)?,
             NodeBase::Object(ref properties) => self.visit_object_literal(properties, iseq)?,
             NodeBase::Array(ref elems) => self.visit_array_literal(elems, iseq)?,
             NodeBase::Identifier(ref name) => {
                 self.save_source_pos(iseq);
-                self.bytecode_generator.append_get_value(name, iseq);
+                self.append_get_variable(name, iseq);
                 if !use_value {
                     self.bytecode_generator.append_pop(iseq);
                 }
@@ -225,6 +284,12 @@ impl<'a> CodeGenerator<'a> {
                         .append_push_const(Value::empty(), iseq)
                 }
             }
+            NodeBase::JSXElement(_) => {
+                return Err(Error::new_unimplemented_error(
+                    "JSX (enable the jsx feature)".to_string(),
+                    node.pos,
+                ))
+            }
             NodeBase::TernaryOp(ref condition, ref then_clause, ref else_clause) => {
                 self.visit_ternary_op(&*condition, &*then_clause, &*else_clause, iseq, use_value)?
             }
@@ -261,7 +326,12 @@ impl<'a> CodeGenerator<'a> {
 
         self.current_function().level.push(Level::new_block_level());
 
+        self.hoist_block_function_decls(node_list, iseq)?;
+
         for node in node_list {
+            if let NodeBase::FunctionDecl(_, _, _) = node.base {
+                continue;
+            }
             self.visit(node, iseq, use_value)?;
         }
 
@@ -323,6 +393,34 @@ impl<'a> CodeGenerator<'a> {
         Ok(())
     }
 
+    /// Evaluate the condition of a loop and jump out if it is false. Return the position of the
+    /// jump, whose destination is to be replaced. A comparison is fused with the jump into one
+    /// instruction, which is the most common condition of loops.
+    fn visit_loop_cond(&mut self, cond: &Node, iseq: &mut ByteCode) -> Result<isize, Error> {
+        if let NodeBase::BinaryOp(ref lhs, ref rhs, ref op) = cond.base {
+            let inst = match op {
+                BinOp::Lt => Some(VMInst::LT),
+                BinOp::Le => Some(VMInst::LE),
+                BinOp::Gt => Some(VMInst::GT),
+                BinOp::Ge => Some(VMInst::GE),
+                _ => None,
+            };
+            if let Some(inst) = inst {
+                self.visit(lhs, iseq, true)?;
+                self.visit(rhs, iseq, true)?;
+                let cond_pos = iseq.len() as isize;
+                self.bytecode_generator
+                    .append_cmp_jmp_if_false(inst, 0, iseq);
+                return Ok(cond_pos);
+            }
+        }
+
+        self.visit(cond, iseq, true)?;
+        let cond_pos = iseq.len() as isize;
+        self.bytecode_generator.append_jmp_if_false(0, iseq);
+        Ok(cond_pos)
+    }
+
     pub fn visit_while(&mut self, cond: &Node, body: &Node, iseq: &mut ByteCode) -> CodeGenResult {
         // name:
         //   while(...) {} // <- this while is named 'name'
@@ -336,10 +434,7 @@ impl<'a> CodeGenerator<'a> {
 
         // self.bytecode_generator.append_loop_start(iseq);
 
-        self.visit(cond, iseq, true)?;
-
-        let cond_pos = iseq.len() as isize;
-        self.bytecode_generator.append_jmp_if_false(0, iseq);
+        let cond_pos = self.visit_loop_cond(cond, iseq)?;
 
         self.visit(body, iseq, false)?;
 
@@ -377,17 +472,24 @@ impl<'a> CodeGenerator<'a> {
 
         self.visit(init, iseq, false)?;
 
-        let start = iseq.len() as isize;
+        // Closures made in the body capture the bindings of their own iteration, so the
+        // bindings are copied before each iteration (CreatePerIterationEnvironment).
+        let per_iteration_env = is_let_declaration(init);
+        if per_iteration_env {
+            self.bytecode_generator.append_copy_env(iseq);
+        }
 
-        self.visit(cond, iseq, true)?;
+        let start = iseq.len() as isize;
 
-        let cond_pos = iseq.len() as isize;
-        self.bytecode_generator.append_jmp_if_false(0, iseq);
+        let cond_pos = self.visit_loop_cond(cond, iseq)?;
 
         self.visit(body, iseq, false)?;
 
         let continue_pos = iseq.len() as isize;
 
+        if per_iteration_env {
+            self.bytecode_generator.append_copy_env(iseq);
+        }
         self.visit(step, iseq, false)?;
 
         let loop_pos = iseq.len() as isize;
@@ -425,6 +527,8 @@ impl<'a> CodeGenerator<'a> {
     }
 
     pub fn visit_continue(&mut self, _name: &Option<String>, iseq: &mut ByteCode) -> CodeGenResult {
+        self.unwind_loop(iseq);
+
         let continue_instr_pos = iseq.len();
         self.bytecode_generator.append_jmp(0, iseq);
 
@@ -445,47 +549,37 @@ impl<'a> CodeGenerator<'a> {
         finally: &Node,
         iseq: &mut ByteCode,
     ) -> CodeGenResult {
-        // TODO: Refine code
-
         let has_catch = catch.base != NodeBase::Nope;
 
-        // Try block
-        let (try_, try_to_finally, leave_try) = {
-            let try_start = iseq.len() as usize;
-
-            self.current_function()
-                .level
-                .push(Level::new_try_or_catch_level());
+        // Lexical environments pushed and finally clauses being run before this try statement
+        // are restored when a handler is entered.
+        let env_depth = self.current_function().block_depth();
+        let finally_depth = self.current_function().finally_depth();
 
-            self.visit(try_clause, iseq, false)?;
-
-            let try_ = self.current_function().level.pop().unwrap();
-
-            let try_to_finally = iseq.len() as usize;
-            self.bytecode_generator.append_jmp_sub(0, iseq);
-
-            let leave_try = iseq.len() as usize;
-            self.bytecode_generator.append_jmp_sub(0, iseq);
-
-            let try_end = iseq.len() as usize;
-            self.current_function().exception_table.push(Exception {
-                start: try_start,
-                end: try_end,
-                dst_kind: if has_catch {
-                    DestinationKind::Catch
-                } else {
-                    DestinationKind::Finally
-                },
-            });
+        // Try block
+        let try_start = iseq.len();
+        self.current_function()
+            .level
+            .push(Level::new_try_or_catch_level());
+        self.visit(try_clause, iseq, false)?;
+        let mut finally_jmps = self
+            .current_function()
+            .level
+            .pop()
+            .unwrap()
+            .as_try_or_catch();
 
-            (try_, try_to_finally, leave_try)
-        };
+        let try_to_finally = iseq.len();
+        self.bytecode_generator.append_jmp_sub(0, iseq);
+        let leave_try = iseq.len();
+        self.bytecode_generator.append_jmp(0, iseq);
+        let try_end = iseq.len();
 
         // Catch block
-        let (catch_, catch_to_finally, leave_catch) = if has_catch {
-            let catch_start = iseq.len() as usize;
+        let catch_jmps = if has_catch {
             let param_name = match param.base {
-                NodeBase::Identifier(ref name) => name.clone(),
+                NodeBase::Identifier(ref name) => Some(name.clone()),
+                NodeBase::Nope => None,
                 _ => unimplemented!(),
             };
 
@@ -498,91 +592,94 @@ impl<'a> CodeGenerator<'a> {
                 .add_lex_env_info(vec![]);
             self.bytecode_generator.append_push_env(env_id as u32, iseq);
             self.current_function().level.push(Level::Block {
-                names: vec![param_name.clone()],
+                names: param_name.iter().cloned().collect(),
             });
-            self.save_source_pos(iseq);
-            self.bytecode_generator.append_set_value(&param_name, iseq);
+            if let Some(param_name) = param_name {
+                self.save_source_pos(iseq);
+                self.bytecode_generator.append_decl_let(&param_name, iseq);
+            } else {
+                // Discard the thrown value
+                self.bytecode_generator.append_pop(iseq);
+            }
 
             self.visit(catch, iseq, false)?;
 
             self.bytecode_generator.append_pop_env(iseq);
 
             let names = self.current_function().level.pop().unwrap().as_block();
-            let catch_ = self.current_function().level.pop().unwrap();
+            finally_jmps.extend(
+                self.current_function()
+                    .level
+                    .pop()
+                    .unwrap()
+                    .as_try_or_catch(),
+            );
             *self
                 .bytecode_generator
                 .constant_table
                 .get_mut(env_id)
                 .as_lex_env_info_mut() = names;
 
-            let catch_to_finally = iseq.len() as usize;
+            let catch_to_finally = iseq.len();
             self.bytecode_generator.append_jmp_sub(0, iseq);
-
-            let leave_catch = iseq.len() as usize;
-            self.bytecode_generator.append_jmp_sub(0, iseq);
-
-            let catch_end = iseq.len() as usize;
-            self.current_function().exception_table.push(Exception {
-                start: catch_start,
-                end: catch_end,
-                dst_kind: DestinationKind::Finally,
-            });
-
-            (catch_, catch_to_finally, leave_catch)
+            let leave_catch = iseq.len();
+            self.bytecode_generator.append_jmp(0, iseq);
+            Some((catch_to_finally, leave_catch))
         } else {
-            (Level::new_try_or_catch_level(), 0, 0)
+            None
         };
 
-        // Finally block
-        let finally_start = iseq.len() as usize;
-
-        let has_return_try =
-            try_.set_jmp_to_finally(finally_start, &mut self.bytecode_generator, iseq)
-                || catch_.set_jmp_to_finally(finally_start, &mut self.bytecode_generator, iseq);
-
+        // Finally block, run as a subroutine
+        let finally_start = iseq.len();
         self.current_function().level.push(Level::Finally);
         self.visit(finally, iseq, false)?;
-
-        let finally_to_outer_finally_jmp_instr_pos = iseq.len();
-        if has_return_try
-            && self
-                .current_function()
-                .find_last_try_or_catch()
-                .map(|try_or_catch| {
-                    try_or_catch
-                        .as_try_or_catch_mut()
-                        .push(finally_to_outer_finally_jmp_instr_pos)
-                })
-                .is_some()
-        {
-            self.bytecode_generator.append_jmp(0, iseq);
-        }
-
         assert_eq!(self.current_function().level.pop().unwrap(), Level::Finally);
         self.bytecode_generator.append_return_sub(iseq);
+        let finally_end = iseq.len();
 
-        let finally_end = iseq.len() as usize;
-
-        self.bytecode_generator.replace_int32(
-            (finally_start - try_to_finally) as i32 - 5,
-            &mut iseq[try_to_finally + 1..try_to_finally + 5],
-        );
-        self.bytecode_generator.replace_int32(
-            (finally_end - leave_try) as i32 - 5,
-            &mut iseq[leave_try + 1..leave_try + 5],
-        );
-
-        if has_catch {
-            self.bytecode_generator.replace_int32(
-                (finally_start - catch_to_finally) as i32 - 5,
-                &mut iseq[catch_to_finally + 1..catch_to_finally + 5],
-            );
+        let mut jmps = vec![(try_to_finally, finally_start), (leave_try, finally_end)];
+        jmps.extend(finally_jmps.into_iter().map(|pos| (pos, finally_start)));
+        if let Some((catch_to_finally, leave_catch)) = catch_jmps {
+            jmps.push((catch_to_finally, finally_start));
+            jmps.push((leave_catch, finally_end));
+        }
+        for (instr_pos, dst) in jmps {
             self.bytecode_generator.replace_int32(
-                (finally_end - leave_catch) as i32 - 5,
-                &mut iseq[leave_catch + 1..leave_catch + 5],
+                (dst - instr_pos) as i32 - 5,
+                &mut iseq[instr_pos + 1..instr_pos + 5],
             );
         }
 
+        // Handlers of inner try statements are already in the table, so the innermost handler
+        // comes first. An exception in the try block is caught by the catch clause, while a
+        // return from it runs the finally clause.
+        let handler = |start, end, handler, kind| ExceptionHandler {
+            start,
+            end,
+            handler,
+            kind,
+            env_depth,
+            finally_depth,
+        };
+        let exception_table = &mut self.current_function().exception_table;
+        if has_catch {
+            exception_table.push(handler(try_start, try_end, try_end, HandlerKind::Catch));
+        }
+        exception_table.push(handler(
+            try_start,
+            try_end,
+            finally_start,
+            HandlerKind::Finally,
+        ));
+        if has_catch {
+            exception_table.push(handler(
+                try_end,
+                finally_start,
+                finally_start,
+                HandlerKind::Finally,
+            ));
+        }
+
         Ok(())
     }
 
@@ -598,6 +695,29 @@ impl<'a> CodeGenerator<'a> {
         Ok(())
     }
 
+    /// Function declarations in a block are created when the block is entered, so that they
+    /// close over the block's lexical environment. The function is bound to a var of the
+    /// enclosing function, which is undefined until then.
+    /// https://tc39.github.io/ecma262/#sec-block-level-function-declarations-web-legacy-compatibility-semantics
+    fn hoist_block_function_decls(
+        &mut self,
+        node_list: &Vec<Node>,
+        iseq: &mut ByteCode,
+    ) -> CodeGenResult {
+        for node in node_list {
+            if let NodeBase::FunctionDecl(ref name, ref params, ref body) = node.base {
+                let func_info = self.visit_function(Some(name.clone()), params, body, true)?;
+                self.current_function().var_names.push(name.clone());
+                let val = self.factory.function(func_info, None);
+                self.bytecode_generator.append_push_const(val, iseq);
+                self.bytecode_generator.append_set_outer_env(iseq);
+                self.bytecode_generator.append_set_value(name, iseq);
+            }
+        }
+
+        Ok(())
+    }
+
     fn visit_function_expr(
         &mut self,
         name: &Option<String>,
@@ -626,8 +746,13 @@ impl<'a> CodeGenerator<'a> {
         body: &Node,
         arrow_function: bool,
     ) -> Result<FuncInfoRef, Error> {
+        // Functions in strict code are strict.
+        let strict = self.current_function().strict || has_use_strict_directive(body);
         self.function_stack
             .push(FunctionInfo::new(name, self.module_func_id));
+        self.current_function().strict = strict;
+        self.analyze_scope(params, body)?;
+        self.current_function().register_names = self.current_function().scope.register_names();
 
         let mut func_iseq = vec![];
 
@@ -648,33 +773,46 @@ impl<'a> CodeGenerator<'a> {
                  }| value::FunctionParameter {
                     name: name.clone(),
                     rest_param: *is_rest_param,
+                    register: self.register(name),
                 },
             )
             .collect();
 
         let function_info = self.function_stack.pop().unwrap();
+        let register_names = function_info.register_names;
+        let var_names = function_info
+            .var_names
+            .into_iter()
+            .filter(|name| register_names.binary_search(name).is_err())
+            .collect();
 
         let func_id = self.factory.new_func_id();
-
-        self.to_source_map
-            .insert(func_id, function_info.to_source_pos);
+        let positions = self.position_table(&function_info.to_source_pos);
 
         let user_func_info = UserFunctionInfo {
             func_name: function_info.name,
             func_id,
             module_func_id: self.module_func_id,
             params,
-            var_names: function_info.var_names,
+            var_names,
+            register_names,
             lex_names: function_info.lex_names,
             func_decls: function_info.func_decls,
+            captured_names: function_info.scope.captured_names(),
             constructible: arrow_function,
-            this_mode: if arrow_function {
-                ThisMode::Global
-            } else {
+            this_mode: if !arrow_function {
                 ThisMode::Lexical
+            } else if function_info.strict {
+                ThisMode::Strict
+            } else {
+                ThisMode::Global
             },
+            strict: function_info.strict,
             code: func_iseq,
             exception_table: function_info.exception_table,
+            positions,
+            location: self.source.map(|source| source.line_column(body.pos)),
+            call_caches: CallCaches::default(),
         };
 
         let func_ref = self.factory.alloc_user_func_info(func_id, user_func_info);
@@ -712,22 +850,29 @@ impl<'a> CodeGenerator<'a> {
             check_duplicate(&mut cur_func.lex_names, name, node)
         }
 
-        // let mut is_initialized = false;
-
-        if let &Some(ref init) = init {
-            self.visit(&*init, iseq, true)?;
-            self.save_source_pos(iseq);
-            self.bytecode_generator.append_set_value(name, iseq);
-            // is_initialized = true;
-        }
-
         match kind {
             VarKind::Var => {
+                if let &Some(ref init) = init {
+                    self.visit(&*init, iseq, true)?;
+                    self.save_source_pos(iseq);
+                    self.append_set_variable(name, iseq);
+                }
                 self.current_function().var_names.push(name.clone());
             }
-            VarKind::Let => let_decl(self, node, name.clone())?,
-            // TODO: Const needs double-assignment check
-            VarKind::Const => let_decl(self, node, name.clone())?,
+            VarKind::Let | VarKind::Const => {
+                let_decl(self, node, name.clone())?;
+                // Until initialized here, the binding is in TDZ (temporal dead zone).
+                match init {
+                    Some(init) => self.visit(&*init, iseq, true)?,
+                    None => self.bytecode_generator.append_push_undefined(iseq),
+                }
+                self.save_source_pos(iseq);
+                if *kind == VarKind::Const {
+                    self.bytecode_generator.append_decl_const(name, iseq);
+                } else {
+                    self.bytecode_generator.append_decl_let(name, iseq);
+                }
+            }
         }
 
         Ok(())
@@ -741,10 +886,10 @@ impl<'a> CodeGenerator<'a> {
         use_value: bool,
     ) -> CodeGenResult {
         self.visit(parent, iseq, true)?;
-        let property = self.factory.string(member.clone());
-        self.bytecode_generator.append_push_const(property, iseq);
+        let key_id = self.property_key(member);
         self.save_source_pos(iseq);
-        self.bytecode_generator.append_get_member(iseq);
+        self.bytecode_generator
+            .append_get_named_member(key_id, iseq);
 
         if !use_value {
             self.bytecode_generator.append_pop(iseq);
@@ -779,6 +924,22 @@ impl<'a> CodeGenerator<'a> {
         iseq: &mut ByteCode,
         use_value: bool,
     ) -> CodeGenResult {
+        if let UnaryOp::Delete = op {
+            return self.visit_delete(expr, iseq, use_value);
+        }
+
+        // A variable which is not declared is "undefined" rather than a ReferenceError.
+        if let (UnaryOp::Typeof, NodeBase::Identifier(ref name)) = (op, &expr.base) {
+            if self.register(name).is_none() && !self.is_upvalue(name) {
+                self.save_source_pos(iseq);
+                self.bytecode_generator.append_typeof_value(name, iseq);
+                if !use_value {
+                    self.bytecode_generator.append_pop(iseq);
+                }
+                return Ok(());
+            }
+        }
+
         self.visit(expr, iseq, true)?;
 
         match op {
@@ -788,31 +949,30 @@ impl<'a> CodeGenerator<'a> {
             &UnaryOp::Not => self.bytecode_generator.append_lnot(iseq),
             &UnaryOp::BitwiseNot => self.bytecode_generator.append_not(iseq),
             &UnaryOp::PrInc => {
-                self.bytecode_generator.append_push_int8(1, iseq);
-                self.bytecode_generator.append_add(iseq);
+                self.bytecode_generator.append_inc(iseq);
                 if use_value {
                     self.bytecode_generator.append_double(iseq);
                 }
                 self.assign_stack_top_to(expr, iseq)?;
             }
             &UnaryOp::PrDec => {
-                self.bytecode_generator.append_push_int8(1, iseq);
-                self.bytecode_generator.append_sub(iseq);
+                self.bytecode_generator.append_dec(iseq);
                 if use_value {
                     self.bytecode_generator.append_double(iseq);
                 }
                 self.assign_stack_top_to(expr, iseq)?;
             }
             &UnaryOp::PoInc => {
+                // The value is the old one converted to a number.
+                self.bytecode_generator.append_posi(iseq);
                 self.bytecode_generator.append_double(iseq);
-                self.bytecode_generator.append_push_int8(1, iseq);
-                self.bytecode_generator.append_add(iseq);
+                self.bytecode_generator.append_inc(iseq);
                 self.assign_stack_top_to(expr, iseq)?;
             }
             &UnaryOp::PoDec => {
+                self.bytecode_generator.append_posi(iseq);
                 self.bytecode_generator.append_double(iseq);
-                self.bytecode_generator.append_push_int8(1, iseq);
-                self.bytecode_generator.append_sub(iseq);
+                self.bytecode_generator.append_dec(iseq);
                 self.assign_stack_top_to(expr, iseq)?;
             }
             _ => unimplemented!(),
@@ -825,6 +985,37 @@ impl<'a> CodeGenerator<'a> {
         Ok(())
     }
 
+    fn visit_delete(&mut self, expr: &Node, iseq: &mut ByteCode, use_value: bool) -> CodeGenResult {
+        match expr.base {
+            NodeBase::Member(ref parent, ref property) => {
+                self.visit(&*parent, iseq, true)?;
+                let key_id = self.property_key(property);
+                self.bytecode_generator.append_push_const_id(key_id, iseq);
+                self.save_source_pos(iseq);
+                self.bytecode_generator.append_delete_member(iseq);
+            }
+            NodeBase::Index(ref parent, ref index) => {
+                self.visit(&*parent, iseq, true)?;
+                self.visit(&*index, iseq, true)?;
+                self.save_source_pos(iseq);
+                self.bytecode_generator.append_delete_member(iseq);
+            }
+            // Variables are not deletable. Unlike the spec, neither are the global variables
+            // made by assigning to undeclared names.
+            NodeBase::Identifier(_) => self.bytecode_generator.append_push_bool(false, iseq),
+            _ => {
+                self.visit(expr, iseq, false)?;
+                self.bytecode_generator.append_push_bool(true, iseq);
+            }
+        }
+
+        if !use_value {
+            self.bytecode_generator.append_pop(iseq);
+        }
+
+        Ok(())
+    }
+
     fn visit_binary_op(
         &mut self,
         lhs: &Node,
@@ -857,6 +1048,38 @@ impl<'a> CodeGenerator<'a> {
 
                 return Ok(());
             }
+            &BinOp::LOr => {
+                self.visit(lhs, iseq, true)?;
+
+                self.bytecode_generator.append_double(iseq);
+
+                let lhs_cond_pos = iseq.len() as isize;
+                self.bytecode_generator.append_jmp_if_false(0, iseq);
+
+                let lhs_true_pos = iseq.len() as isize;
+                self.bytecode_generator.append_jmp(0, iseq);
+
+                let pos = iseq.len() as isize;
+                self.bytecode_generator.replace_int32(
+                    (pos - lhs_cond_pos) as i32 - 5,
+                    &mut iseq[lhs_cond_pos as usize + 1..lhs_cond_pos as usize + 5],
+                );
+
+                self.bytecode_generator.append_pop(iseq);
+
+                self.visit(rhs, iseq, true)?;
+
+                let pos = iseq.len() as isize;
+                self.bytecode_generator.replace_int32(
+                    (pos - lhs_true_pos) as i32 - 5,
+                    &mut iseq[lhs_true_pos as usize + 1..lhs_true_pos as usize + 5],
+                );
+                if !use_value {
+                    self.bytecode_generator.append_pop(iseq);
+                }
+
+                return Ok(());
+            }
             // http://www.ecma-international.org/ecma-262/9.0/index.html#sec-comma-operator
             &BinOp::Comma => {
                 self.visit(lhs, iseq, false)?;
@@ -893,6 +1116,7 @@ impl<'a> CodeGenerator<'a> {
             &BinOp::Shl => self.bytecode_generator.append_shl(iseq),
             &BinOp::Shr => self.bytecode_generator.append_shr(iseq),
             &BinOp::ZFShr => self.bytecode_generator.append_zfshr(iseq),
+            &BinOp::In => self.bytecode_generator.append_in(iseq),
             _ => unimplemented!(),
         }
 
@@ -969,13 +1193,20 @@ impl<'a> CodeGenerator<'a> {
 
         match callee.base {
             NodeBase::Member(ref parent, ref property_name) => {
-                self.bytecode_generator
-                    .append_push_const(self.factory.string(property_name.clone()), iseq);
+                let key_id = self.property_key(property_name);
+                self.bytecode_generator.append_push_const_id(key_id, iseq);
                 self.visit(&*parent, iseq, true)?;
                 self.save_source_pos(iseq);
                 self.bytecode_generator
                     .append_call_method(args.len() as u32, iseq);
             }
+            // A possibly direct eval, which runs the code in the scope of the caller.
+            NodeBase::Identifier(ref name) if name == "eval" => {
+                self.visit(callee, iseq, true)?;
+                self.save_source_pos(iseq);
+                self.bytecode_generator
+                    .append_call_eval(args.len() as u32, iseq);
+            }
             _ => {
                 self.visit(callee, iseq, true)?;
                 self.save_source_pos(iseq);
@@ -993,12 +1224,6 @@ impl<'a> CodeGenerator<'a> {
     fn visit_throw(&mut self, val: &Node, iseq: &mut ByteCode) -> CodeGenResult {
         self.visit(val, iseq, true)?;
 
-        if self.current_function().in_try_or_catch() {
-            self.unwind_try_or_catch(iseq);
-        } else if self.current_function().in_finally() {
-            self.unwind_finally(iseq);
-        }
-
         self.save_source_pos(iseq);
         self.bytecode_generator.append_throw(iseq);
         Ok(())
@@ -1006,16 +1231,16 @@ impl<'a> CodeGenerator<'a> {
 
     fn visit_return(&mut self, val: &Option<Box<Node>>, iseq: &mut ByteCode) -> CodeGenResult {
         if let Some(val) = val {
+            if self.current_function().strict && !self.current_function().in_try_or_catch() {
+                return self.visit_tail_position(val, iseq);
+            }
             self.visit(val, iseq, true)?
         } else {
             self.bytecode_generator.append_push_undefined(iseq);
         }
 
         if self.current_function().in_try_or_catch() {
-            self.current_function()
-                .get_last_try_or_catch()
-                .as_try_or_catch_mut()
-                .push(iseq.len() as usize);
+            // The VM runs the enclosing finally clauses before returning.
             self.bytecode_generator.append_return_try(iseq);
         } else {
             self.bytecode_generator.append_return(iseq);
@@ -1024,6 +1249,106 @@ impl<'a> CodeGenerator<'a> {
         Ok(())
     }
 
+    /// Return the value of ``val``, which is in tail position of strict code outside try and
+    /// catch blocks (whose handlers must stay). The calls in tail position, which are ``val``
+    /// itself, the branches of ``?:``, the rhs of ``&&`` and ``||`` and the last operand of
+    /// ``,``, reuse the frame of the caller, except eval.
+    /// https://tc39.github.io/ecma262/#sec-isintailposition
+    fn visit_tail_position(&mut self, val: &Node, iseq: &mut ByteCode) -> CodeGenResult {
+        match val.base {
+            NodeBase::Call(ref callee, ref args) => match callee.base {
+                NodeBase::Identifier(ref name) if name == "eval" => {}
+                NodeBase::Member(ref parent, ref property_name) => {
+                    for arg in args.iter().rev() {
+                        self.visit(arg, iseq, true)?
+                    }
+                    let key_id = self.property_key(property_name);
+                    self.bytecode_generator.append_push_const_id(key_id, iseq);
+                    self.visit(&*parent, iseq, true)?;
+                    self.save_source_pos(iseq);
+                    self.bytecode_generator
+                        .append_tail_call_method(args.len() as u32, iseq);
+                    // Reached if the VM could not reuse the frame, and made an ordinary call.
+                    self.bytecode_generator.append_return(iseq);
+                    return Ok(());
+                }
+                _ => {
+                    for arg in args.iter().rev() {
+                        self.visit(arg, iseq, true)?
+                    }
+                    self.visit(callee, iseq, true)?;
+                    self.save_source_pos(iseq);
+                    self.bytecode_generator
+                        .append_tail_call(args.len() as u32, iseq);
+                    self.bytecode_generator.append_return(iseq);
+                    return Ok(());
+                }
+            },
+            NodeBase::TernaryOp(ref cond, ref then_exp, ref else_exp) => {
+                self.visit(cond, iseq, true)?;
+
+                let cond_pos = iseq.len() as isize;
+                self.bytecode_generator.append_jmp_if_false(0, iseq);
+
+                self.visit_tail_position(then_exp, iseq)?;
+
+                let pos = iseq.len() as isize;
+                self.bytecode_generator.replace_int32(
+                    (pos - cond_pos) as i32 - 5,
+                    &mut iseq[cond_pos as usize + 1..cond_pos as usize + 5],
+                );
+
+                return self.visit_tail_position(else_exp, iseq);
+            }
+            NodeBase::BinaryOp(ref lhs, ref rhs, BinOp::LAnd) => {
+                self.visit(lhs, iseq, true)?;
+                self.bytecode_generator.append_double(iseq);
+
+                let lhs_cond_pos = iseq.len() as isize;
+                self.bytecode_generator.append_jmp_if_false(0, iseq);
+
+                self.bytecode_generator.append_pop(iseq);
+                self.visit_tail_position(rhs, iseq)?;
+
+                // Return the falsy lhs.
+                let pos = iseq.len() as isize;
+                self.bytecode_generator.replace_int32(
+                    (pos - lhs_cond_pos) as i32 - 5,
+                    &mut iseq[lhs_cond_pos as usize + 1..lhs_cond_pos as usize + 5],
+                );
+                self.bytecode_generator.append_return(iseq);
+                return Ok(());
+            }
+            NodeBase::BinaryOp(ref lhs, ref rhs, BinOp::LOr) => {
+                self.visit(lhs, iseq, true)?;
+                self.bytecode_generator.append_double(iseq);
+
+                let lhs_cond_pos = iseq.len() as isize;
+                self.bytecode_generator.append_jmp_if_false(0, iseq);
+
+                // Return the truthy lhs.
+                self.bytecode_generator.append_return(iseq);
+
+                let pos = iseq.len() as isize;
+                self.bytecode_generator.replace_int32(
+                    (pos - lhs_cond_pos) as i32 - 5,
+                    &mut iseq[lhs_cond_pos as usize + 1..lhs_cond_pos as usize + 5],
+                );
+                self.bytecode_generator.append_pop(iseq);
+                return self.visit_tail_position(rhs, iseq);
+            }
+            NodeBase::BinaryOp(ref lhs, ref rhs, BinOp::Comma) => {
+                self.visit(lhs, iseq, false)?;
+                return self.visit_tail_position(rhs, iseq);
+            }
+            _ => {}
+        }
+
+        self.visit(val, iseq, true)?;
+        self.bytecode_generator.append_return(iseq);
+        Ok(())
+    }
+
     fn visit_new(&mut self, expr: &Node, iseq: &mut ByteCode, use_value: bool) -> CodeGenResult {
         let (callee, args) = match expr.base {
             NodeBase::Call(ref callee, ref args) => (&*callee, args),
@@ -1037,10 +1362,10 @@ impl<'a> CodeGenerator<'a> {
         match callee.base {
             NodeBase::Member(ref parent, ref property_name) => {
                 self.visit(parent, iseq, true)?;
-                let property = self.factory.string(property_name.clone());
-                self.bytecode_generator.append_push_const(property, iseq);
+                let key_id = self.property_key(property_name);
                 self.save_source_pos(iseq);
-                self.bytecode_generator.append_get_member(iseq);
+                self.bytecode_generator
+                    .append_get_named_member(key_id, iseq);
             }
             _ => {
                 self.visit(callee, iseq, true)?;
@@ -1072,15 +1397,15 @@ impl<'a> CodeGenerator<'a> {
                 // { name }
                 IdentifierReference(name) => {
                     self.save_source_pos(iseq);
-                    self.bytecode_generator.append_get_value(name, iseq);
-                    self.bytecode_generator
-                        .append_push_const(self.factory.string(name.clone()), iseq);
+                    self.append_get_variable(name, iseq);
+                    let key_id = self.property_key(name);
+                    self.bytecode_generator.append_push_const_id(key_id, iseq);
                 }
                 // { name: val }
                 Property(name, val) => {
                     self.visit(&val, iseq, true)?;
-                    self.bytecode_generator
-                        .append_push_const(self.factory.string(name.clone()), iseq);
+                    let key_id = self.property_key(name);
+                    self.bytecode_generator.append_push_const_id(key_id, iseq);
                 }
                 // { get name(){ node }}
                 // { set name(){ node }}
@@ -1095,8 +1420,8 @@ impl<'a> CodeGenerator<'a> {
                         }
                     };
                     self.visit(&node, iseq, true)?;
-                    self.bytecode_generator
-                        .append_push_const(self.factory.string(name.clone()), iseq);
+                    let key_id = self.property_key(name);
+                    self.bytecode_generator.append_push_const_id(key_id, iseq);
                 }
                 // { ...node }
                 SpreadObject(node) => {
@@ -1134,14 +1459,14 @@ impl<'a> CodeGenerator<'a> {
         match dst.base {
             NodeBase::Identifier(ref name) => {
                 self.save_source_pos(iseq);
-                self.bytecode_generator.append_set_value(name, iseq);
+                self.append_set_variable(name, iseq);
             }
             NodeBase::Member(ref parent, ref property) => {
                 self.visit(&*parent, iseq, true)?;
-                let property = self.factory.string(property.clone());
-                self.bytecode_generator.append_push_const(property, iseq);
+                let key_id = self.property_key(property);
                 self.save_source_pos(iseq);
-                self.bytecode_generator.append_set_member(iseq);
+                self.bytecode_generator
+                    .append_set_named_member(key_id, iseq);
             }
             NodeBase::Index(ref parent, ref index) => {
                 self.visit(&*parent, iseq, true)?;
@@ -1160,10 +1485,81 @@ impl<'a> CodeGenerator<'a> {
         Ok(())
     }
 
+    /// Get the constant id of the property key ``name``.
+    fn property_key(&mut self, name: &str) -> usize {
+        let factory = &mut self.factory;
+        self.bytecode_generator
+            .constant_table
+            .add_property_key(name, || factory.string(name))
+    }
+
     fn current_function(&mut self) -> &mut FunctionInfo {
         self.function_stack.last_mut().unwrap()
     }
 
+    /// The scopes of all the functions are found before compiling the script, except for the
+    /// nodes made without ids.
+    fn analyze_scope(&mut self, params: &FormalParameters, body: &Node) -> CodeGenResult {
+        let scope = match self.scopes.remove(&body.id) {
+            Some(scope) => scope,
+            None => FunctionScope::analyze(params, body, &self.stack_limit),
+        };
+        self.set_scope(scope)
+    }
+
+    fn set_scope(&mut self, scope: FunctionScope) -> CodeGenResult {
+        if let Some(ref redeclaration) = scope.redeclaration {
+            return Err(Error::new_redeclaration_error(
+                &redeclaration.name,
+                redeclaration.pos,
+                redeclaration.prev_pos,
+            ));
+        }
+        self.current_function().scope = scope;
+        Ok(())
+    }
+
+    /// A free variable of the current function declared in an enclosing function is captured
+    /// from the environment the function closes over, skipping its own environments.
+    /// A direct eval in a function in between may declare a var shadowing it, so the variable is
+    /// looked up by name in that case.
+    fn is_upvalue(&self, name: &str) -> bool {
+        let (current, enclosing) = self.function_stack.split_last().unwrap();
+        match current
+            .scope
+            .resolve(name, enclosing.iter().map(|func| &func.scope))
+        {
+            Resolution::Upvalue { .. } => true,
+            Resolution::Local | Resolution::Dynamic => false,
+        }
+    }
+
+    /// The register of the current function ``name`` is stored in, if any.
+    fn register(&self, name: &String) -> Option<usize> {
+        let current = self.function_stack.last().unwrap();
+        current.register_names.binary_search(name).ok()
+    }
+
+    fn append_get_variable(&mut self, name: &String, iseq: &mut ByteCode) {
+        if let Some(index) = self.register(name) {
+            self.bytecode_generator.append_get_register(index, iseq);
+        } else if self.is_upvalue(name) {
+            self.bytecode_generator.append_get_upvalue(name, iseq);
+        } else {
+            self.bytecode_generator.append_get_value(name, iseq);
+        }
+    }
+
+    fn append_set_variable(&mut self, name: &String, iseq: &mut ByteCode) {
+        if let Some(index) = self.register(name) {
+            self.bytecode_generator.append_set_register(index, iseq);
+        } else if self.is_upvalue(name) {
+            self.bytecode_generator.append_set_upvalue(name, iseq);
+        } else {
+            self.bytecode_generator.append_set_value(name, iseq);
+        }
+    }
+
     /// Save the position in bytecode corresponds to the current node.
     fn save_source_pos(&mut self, iseq: &mut ByteCode) {
         let node_pos = self.node_pos;
@@ -1172,38 +1568,32 @@ impl<'a> CodeGenerator<'a> {
             .append(iseq.len(), node_pos);
     }
 
-    fn unwind_try_or_catch(&mut self, iseq: &mut ByteCode) {
-        for level in self.current_function().level.clone().iter().rev() {
-            match level {
-                &Level::TryOrCatch { .. } => break,
-                &Level::Block { .. } => self.bytecode_generator.append_pop_env(iseq),
-                _ => {}
-            }
-        }
-    }
-
-    fn unwind_finally(&mut self, iseq: &mut ByteCode) {
-        for level in self.current_function().level.clone().iter().rev() {
-            match level {
-                &Level::Finally => break,
-                &Level::Block { .. } => self.bytecode_generator.append_pop_env(iseq),
-                _ => {}
-            }
+    fn position_table(&self, to_source_pos: &ToSourcePos) -> PositionTable {
+        match self.source {
+            Some(source) => PositionTable::new(to_source_pos, source),
+            None => PositionTable::default(),
         }
     }
 
+    /// Leave the blocks, try statements and finally clauses between a break or continue and its
+    /// loop, from the innermost: pop the lexical environments of the blocks, run the finally
+    /// clauses of the try statements, and discard the finally clauses being run.
     fn unwind_loop(&mut self, iseq: &mut ByteCode) {
-        let mut count = 0;
-        for level in self.current_function().level.iter().rev() {
-            match level {
-                &Level::Loop { .. } => break,
-                &Level::Block { .. } => count += 1,
-                _ => {}
+        for i in (0..self.current_function().level.len()).rev() {
+            match self.current_function().level[i] {
+                Level::Loop { .. } => break,
+                Level::Block { .. } => self.bytecode_generator.append_pop_env(iseq),
+                Level::TryOrCatch { .. } => {
+                    let jmp_sub_pos = iseq.len();
+                    self.bytecode_generator.append_jmp_sub(0, iseq);
+                    self.current_function().level[i]
+                        .as_try_or_catch_mut()
+                        .push(jmp_sub_pos);
+                }
+                Level::Finally => self.bytecode_generator.append_drop_sub(iseq),
+                Level::Function => {}
             }
         }
-        for _ in 0..count {
-            self.bytecode_generator.append_pop_env(iseq);
-        }
     }
 }
 
@@ -1225,6 +1615,14 @@ impl Error {
             kind: ErrorKind::Unimplemented,
         }
     }
+
+    pub fn new_redeclaration_error(name: &str, token_pos: usize, prev_pos: usize) -> Self {
+        Error {
+            msg: format!("Identifier '{}' has already been declared", name),
+            token_pos,
+            kind: ErrorKind::Redeclaration(prev_pos),
+        }
+    }
 }
 
 // FunctionInfo
@@ -1239,8 +1637,11 @@ impl FunctionInfo {
             param_names: vec![],
             level: vec![Level::Function],
             exception_table: vec![],
-            to_source_pos: ToSourcePos::new(module_func_id),
+            to_source_pos: ToSourcePos::new(),
             module_func_id,
+            scope: FunctionScope::default(),
+            register_names: vec![],
+            strict: false,
         }
     }
 
@@ -1255,30 +1656,23 @@ impl FunctionInfo {
             .is_some()
     }
 
-    pub fn in_finally(&self) -> bool {
+    /// Number of block levels, each of which pushes a lexical environment at runtime.
+    pub fn block_depth(&self) -> usize {
         self.level
             .iter()
-            .rev()
-            .find(|level| *level == &Level::Finally)
-            .is_some()
-    }
-
-    pub fn get_last_try_or_catch(&mut self) -> &mut Level {
-        self.level
-            .iter_mut()
-            .rev()
-            .find(|level| match level {
-                &Level::TryOrCatch { .. } => true,
+            .filter(|level| match level {
+                &Level::Block { .. } => true,
                 _ => false,
             })
-            .unwrap()
+            .count()
     }
 
-    pub fn find_last_try_or_catch(&mut self) -> Option<&mut Level> {
-        self.level.iter_mut().rev().find(|level| match level {
-            &Level::TryOrCatch { .. } => true,
-            _ => false,
-        })
+    /// Number of finally levels, each of which is run as a subroutine at runtime.
+    pub fn finally_depth(&self) -> usize {
+        self.level
+            .iter()
+            .filter(|level| *level == &Level::Finally)
+            .count()
     }
 
     pub fn get_last_loop(&mut self) -> &mut Level {
@@ -1317,6 +1711,24 @@ impl Level {
         }
     }
 
+    pub fn as_try_or_catch(self) -> Vec<usize> {
+        match self {
+            Level::TryOrCatch {
+                finally_jmp_instr_pos,
+            } => finally_jmp_instr_pos,
+            _ => panic!(),
+        }
+    }
+
+    pub fn as_try_or_catch_mut(&mut self) -> &mut Vec<usize> {
+        match self {
+            Level::TryOrCatch {
+                ref mut finally_jmp_instr_pos,
+            } => finally_jmp_instr_pos,
+            _ => panic!(),
+        }
+    }
+
     pub fn as_block(self) -> Vec<String> {
         match self {
             Level::Block { names } => names,
@@ -1372,66 +1784,49 @@ impl Level {
             );
         }
     }
+}
 
-    pub fn as_try_or_catch(self) -> Vec<usize> {
-        match self {
-            Level::TryOrCatch {
-                finally_jmp_instr_pos,
-            } => finally_jmp_instr_pos,
-            _ => panic!(),
-        }
+impl ToSourcePos {
+    pub fn new() -> Self {
+        Self { table: vec![] }
     }
 
-    pub fn as_try_or_catch_mut(&mut self) -> &mut Vec<usize> {
-        match self {
-            Level::TryOrCatch {
-                ref mut finally_jmp_instr_pos,
-            } => finally_jmp_instr_pos,
-            _ => panic!(),
-        }
+    pub fn append(&mut self, bp: usize, np: usize) {
+        self.table.push((bp, np));
     }
 
-    pub fn set_jmp_to_finally(
-        self,
-        dst: usize,
-        bytecode_generator: &mut ByteCodeGenerator,
-        iseq: &mut ByteCode,
-    ) -> bool {
-        let finally_jmp_instr_pos = self.as_try_or_catch();
-        let mut has_return_from_try_or_catch = false;
-        for instr_pos in finally_jmp_instr_pos {
-            has_return_from_try_or_catch |= iseq[instr_pos] == VMInst::RETURN_TRY;
-            assert!(match iseq[instr_pos] {
-                VMInst::RETURN_TRY | VMInst::JMP => true,
-                _ => false,
-            });
-            bytecode_generator.replace_int32(
-                (dst - instr_pos) as i32 - 5,
-                &mut iseq[instr_pos as usize + 1..instr_pos as usize + 5],
-            );
-        }
-        has_return_from_try_or_catch
+    /// Pairs of an instruction pointer and a char position, in the order they were appended.
+    pub fn entries(&self) -> &[(usize, usize)] {
+        &self.table
     }
 }
 
-impl ToSourcePos {
-    pub fn new(module_func_id: FunctionId) -> Self {
-        Self {
-            module_func_id,
-            table: vec![],
-        }
-    }
-
-    pub fn append(&mut self, bp: usize, np: usize) {
-        self.table.push((bp, np));
+/// Whether the directive prologue of the script or function body ``body`` has a Use Strict
+/// Directive.
+/// https://tc39.github.io/ecma262/#sec-directive-prologues-and-the-use-strict-directive
+fn has_use_strict_directive(body: &Node) -> bool {
+    match body.base {
+        NodeBase::StatementList(ref nodes) | NodeBase::Block(ref nodes) => nodes
+            .iter()
+            .take_while(|node| match node.base {
+                NodeBase::String(_) => true,
+                _ => false,
+            })
+            .any(|node| match node.base {
+                NodeBase::String(ref s) => s == "use strict",
+                _ => false,
+            }),
+        _ => false,
     }
+}
 
-    pub fn get_node_pos(&self, bytecode_offset: usize) -> Option<usize> {
-        for (bp, np) in &self.table {
-            if *bp == bytecode_offset {
-                return Some(*np);
-            }
-        }
-        None
+/// Whether ``node``, the initialization of a for statement, declares bindings by let.
+fn is_let_declaration(node: &Node) -> bool {
+    match node.base {
+        NodeBase::StatementList(ref nodes) => nodes.iter().any(|node| match node.base {
+            NodeBase::VarDecl(_, _, VarKind::Let) => true,
+            _ => false,
+        }),
+        _ => false,
     }
 }
@@ -1,9 +1,10 @@
+use crate::vm::atom::Atom;
 use crate::vm::jsvalue::value::Value;
 use rustc_hash::FxHashMap;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Constant {
-    String(String),
+    String(Atom),
     Value(Value),
     LexicalEnvironmentInfo { names: Vec<String> },
     ObjectLiteralInfo(SpecialProperties),
@@ -21,14 +22,34 @@ pub enum SpecialPropertyKind {
 #[derive(Clone, Debug, PartialEq)]
 pub struct ConstantTable {
     pub table: Vec<Constant>,
+    /// Ids of the string values used as property keys, by property name.
+    property_keys: FxHashMap<Atom, usize>,
 }
 
 impl ConstantTable {
     pub fn new() -> Self {
-        ConstantTable { table: vec![] }
+        ConstantTable {
+            table: vec![],
+            property_keys: FxHashMap::default(),
+        }
+    }
+
+    /// Get the id of the string value for the property key ``name``, shared by all the
+    /// accesses to the property. ``key`` makes the value when ``name`` is new.
+    pub fn add_property_key(&mut self, name: &str, key: impl FnOnce() -> Value) -> usize {
+        let name = Atom::new(name);
+        if let Some(id) = self.property_keys.get(&name) {
+            return *id;
+        }
+
+        let id = self.table.len();
+        self.table.push(Constant::Value(key()));
+        self.property_keys.insert(name, id);
+        id
     }
 
-    pub fn add_string(&mut self, string: String) -> usize {
+    pub fn add_string(&mut self, string: impl Into<Atom>) -> usize {
+        let string = string.into();
         for (i, constant) in self.table.iter().enumerate() {
             match constant {
                 Constant::String(string_) if &string == string_ => return i,
@@ -88,7 +109,7 @@ impl ConstantTable {
 }
 
 impl Constant {
-    pub fn as_string(&self) -> &String {
+    pub fn as_string(&self) -> &Atom {
         match self {
             Constant::String(string) => string,
             _ => panic!(),
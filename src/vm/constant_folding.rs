@@ -0,0 +1,273 @@
+//! Constant folding before bytecode generation.
+//! Operations whose operands are all literals are evaluated at compile time, e.g. ``1 + 2 * 3``
+//! becomes ``7`` and ``"a" + "b"`` becomes ``"ab"``.
+//! An operation is folded only if the result is the same as the one the VM computes at runtime.
+
+use crate::node::{BinOp, FormalParameter, Node, NodeBase, PropertyDefinition, UnaryOp};
+use crate::util::StackLimit;
+use crate::vm::jsvalue::value::number_to_str;
+
+/// Fold the constant expressions in ``node`` and its descendants.
+pub fn fold_constants(node: &mut Node, stack_limit: &StackLimit) {
+    if stack_limit.is_exceeded() {
+        return;
+    }
+
+    match node.base {
+        NodeBase::StatementList(ref mut nodes)
+        | NodeBase::Block(ref mut nodes)
+        | NodeBase::Array(ref mut nodes) => fold_list(nodes, stack_limit),
+        NodeBase::FunctionDecl(_, ref mut params, ref mut body)
+        | NodeBase::FunctionExpr(_, ref mut params, ref mut body)
+        | NodeBase::ArrowFunction(ref mut params, ref mut body) => {
+            for FormalParameter { init, .. } in params {
+                if let Some(init) = init {
+                    fold_constants(init, stack_limit)
+                }
+            }
+            fold_constants(body, stack_limit)
+        }
+        NodeBase::VarDecl(_, Some(ref mut node), _)
+        | NodeBase::Return(Some(ref mut node))
+        | NodeBase::Member(ref mut node, _)
+        | NodeBase::New(ref mut node)
+        | NodeBase::UnaryOp(ref mut node, _)
+        | NodeBase::Label(_, ref mut node)
+        | NodeBase::Throw(ref mut node)
+        | NodeBase::Spread(ref mut node) => fold_constants(node, stack_limit),
+        NodeBase::Call(ref mut callee, ref mut args) => {
+            fold_callee(callee, stack_limit);
+            fold_list(args, stack_limit)
+        }
+        NodeBase::Index(ref mut lhs, ref mut rhs)
+        | NodeBase::While(ref mut lhs, ref mut rhs)
+        | NodeBase::Assign(ref mut lhs, ref mut rhs)
+        | NodeBase::BinaryOp(ref mut lhs, ref mut rhs, _) => {
+            fold_constants(lhs, stack_limit);
+            fold_constants(rhs, stack_limit)
+        }
+        NodeBase::If(ref mut a, ref mut b, ref mut c)
+        | NodeBase::TernaryOp(ref mut a, ref mut b, ref mut c) => {
+            fold_constants(a, stack_limit);
+            fold_constants(b, stack_limit);
+            fold_constants(c, stack_limit)
+        }
+        NodeBase::For(ref mut a, ref mut b, ref mut c, ref mut d)
+        | NodeBase::Try(ref mut a, ref mut b, ref mut c, ref mut d) => {
+            fold_constants(a, stack_limit);
+            fold_constants(b, stack_limit);
+            fold_constants(c, stack_limit);
+            fold_constants(d, stack_limit)
+        }
+        NodeBase::Object(ref mut properties) => {
+            for property in properties {
+                match property {
+                    PropertyDefinition::Property(_, node)
+                    | PropertyDefinition::MethodDefinition(_, _, node)
+                    | PropertyDefinition::SpreadObject(node) => fold_constants(node, stack_limit),
+                    PropertyDefinition::IdentifierReference(_) => {}
+                }
+            }
+        }
+        _ => {}
+    }
+
+    fold(node)
+}
+
+/// Fold the callee of a call. ``(true && o.m)()`` calls ``o.m`` with ``this`` undefined unlike
+/// ``o.m()``, so ``&&`` and ``||`` whose operand is a property reference are left as they are.
+fn fold_callee(callee: &mut Node, stack_limit: &StackLimit) {
+    if let NodeBase::BinaryOp(ref mut lhs, ref mut rhs, BinOp::LAnd)
+    | NodeBase::BinaryOp(ref mut lhs, ref mut rhs, BinOp::LOr) = callee.base
+    {
+        fold_constants(lhs, stack_limit);
+        fold_constants(rhs, stack_limit);
+        if is_property_reference(&lhs.base) || is_property_reference(&rhs.base) {
+            return;
+        }
+        fold(callee)
+    } else {
+        fold_constants(callee, stack_limit)
+    }
+}
+
+fn is_property_reference(node: &NodeBase) -> bool {
+    match node {
+        NodeBase::Member(_, _) | NodeBase::Index(_, _) => true,
+        _ => false,
+    }
+}
+
+fn fold_list(nodes: &mut Vec<Node>, stack_limit: &StackLimit) {
+    for node in nodes {
+        fold_constants(node, stack_limit)
+    }
+}
+
+/// Fold ``node`` whose operands are already folded.
+fn fold(node: &mut Node) {
+    // ``a && b`` and ``a || b`` evaluate to one of the operands.
+    if let NodeBase::BinaryOp(ref mut lhs, ref mut rhs, ref op) = node.base {
+        if *op == BinOp::LAnd || *op == BinOp::LOr {
+            if let Some(lhs_bool) = to_boolean(&lhs.base) {
+                let result = if lhs_bool == (*op == BinOp::LAnd) {
+                    rhs
+                } else {
+                    lhs
+                };
+                *node = ::std::mem::replace(&mut **result, Node::new(NodeBase::Nope, 0));
+            }
+            return;
+        }
+    }
+
+    let folded = match node.base {
+        NodeBase::UnaryOp(ref expr, ref op) => fold_unary_op(&expr.base, op),
+        NodeBase::BinaryOp(ref lhs, ref rhs, ref op) => fold_binary_op(&lhs.base, &rhs.base, op),
+        _ => None,
+    };
+    if let Some(base) = folded {
+        node.base = base;
+    }
+}
+
+fn fold_unary_op(expr: &NodeBase, op: &UnaryOp) -> Option<NodeBase> {
+    match (op, expr) {
+        (UnaryOp::Minus, NodeBase::Number(n)) => number(-n),
+        (UnaryOp::Plus, NodeBase::Number(n)) => number(*n),
+        (UnaryOp::BitwiseNot, NodeBase::Number(n)) => number(!to_int32(*n)? as f64),
+        (UnaryOp::Not, expr) => to_boolean(expr).map(|b| NodeBase::Boolean(!b)),
+        _ => None,
+    }
+}
+
+fn fold_binary_op(lhs: &NodeBase, rhs: &NodeBase, op: &BinOp) -> Option<NodeBase> {
+    match (lhs, rhs) {
+        (NodeBase::Number(x), NodeBase::Number(y)) => fold_number_op(*x, *y, op),
+        (NodeBase::String(x), NodeBase::String(y)) => match op {
+            BinOp::Add => Some(NodeBase::String(format!("{}{}", x, y))),
+            BinOp::Eq | BinOp::SEq => Some(NodeBase::Boolean(x == y)),
+            BinOp::Ne | BinOp::SNe => Some(NodeBase::Boolean(x != y)),
+            BinOp::Lt => Some(NodeBase::Boolean(x < y)),
+            BinOp::Gt => Some(NodeBase::Boolean(x > y)),
+            BinOp::Le => Some(NodeBase::Boolean(x <= y)),
+            BinOp::Ge => Some(NodeBase::Boolean(x >= y)),
+            _ => None,
+        },
+        (NodeBase::String(x), NodeBase::Number(y)) if *op == BinOp::Add => {
+            Some(NodeBase::String(format!("{}{}", x, number_to_str(*y))))
+        }
+        (NodeBase::Number(x), NodeBase::String(y)) if *op == BinOp::Add => {
+            Some(NodeBase::String(format!("{}{}", number_to_str(*x), y)))
+        }
+        (NodeBase::Boolean(x), NodeBase::Boolean(y)) => match op {
+            BinOp::Eq | BinOp::SEq => Some(NodeBase::Boolean(x == y)),
+            BinOp::Ne | BinOp::SNe => Some(NodeBase::Boolean(x != y)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn fold_number_op(x: f64, y: f64, op: &BinOp) -> Option<NodeBase> {
+    match op {
+        BinOp::Add => number(x + y),
+        BinOp::Sub => number(x - y),
+        BinOp::Mul => number(x * y),
+        BinOp::Div => number(x / y),
+        BinOp::Exp => number(x.powf(y)),
+        BinOp::Rem => match to_int32(x)?.checked_rem(to_int32(y)?)? {
+            // The result is -0.
+            0 if x < 0.0 => None,
+            n => number(n as f64),
+        },
+        BinOp::And => number((to_int32(x)? & to_int32(y)?) as f64),
+        BinOp::Or => number((to_int32(x)? | to_int32(y)?) as f64),
+        BinOp::Xor => number((to_int32(x)? ^ to_int32(y)?) as f64),
+        BinOp::Shl => number((to_int32(x)? << shift_count(y)?) as f64),
+        BinOp::Shr => number((to_int32(x)? >> shift_count(y)?) as f64),
+        BinOp::ZFShr if x >= 0.0 => number((to_int32(x)? as u32 >> shift_count(y)?) as f64),
+        BinOp::Eq | BinOp::SEq => Some(NodeBase::Boolean(x == y)),
+        BinOp::Ne | BinOp::SNe => Some(NodeBase::Boolean(x != y)),
+        BinOp::Lt => Some(NodeBase::Boolean(x < y)),
+        BinOp::Gt => Some(NodeBase::Boolean(x > y)),
+        BinOp::Le => Some(NodeBase::Boolean(x <= y)),
+        BinOp::Ge => Some(NodeBase::Boolean(x >= y)),
+        _ => None,
+    }
+}
+
+/// -0 is not folded, since it can not be written as a number literal.
+fn number(n: f64) -> Option<NodeBase> {
+    if n == 0.0 && n.is_sign_negative() {
+        None
+    } else {
+        Some(NodeBase::Number(n))
+    }
+}
+
+/// Only integers in the range of i32 are converted, so that the result is not affected by how
+/// the VM converts the others.
+fn to_int32(n: f64) -> Option<i32> {
+    if n.fract() == 0.0 && (i32::MIN as f64) <= n && n <= (i32::MAX as f64) {
+        Some(n as i32)
+    } else {
+        None
+    }
+}
+
+fn shift_count(n: f64) -> Option<u32> {
+    to_int32(n).filter(|n| *n >= 0).map(|n| n as u32 & 0x1f)
+}
+
+/// https://tc39.github.io/ecma262/#sec-toboolean
+fn to_boolean(base: &NodeBase) -> Option<bool> {
+    match base {
+        NodeBase::Boolean(b) => Some(*b),
+        NodeBase::Number(n) => Some(*n != 0.0 && !n.is_nan()),
+        NodeBase::String(s) => Some(!s.is_empty()),
+        NodeBase::Null => Some(false),
+        _ => None,
+    }
+}
+
+#[test]
+fn fold_constant_expressions() {
+    use crate::parser::Parser;
+
+    let folded = |code: &str| {
+        let mut node = Parser::new("test", code).parse_all().unwrap();
+        fold_constants(&mut node, &StackLimit::new());
+        match node.base {
+            NodeBase::StatementList(mut nodes) => nodes.remove(0).base,
+            _ => unreachable!(),
+        }
+    };
+
+    assert_eq!(folded("1 + 2 * 3"), NodeBase::Number(7.0));
+    assert_eq!(folded("'a' + 'b' + 1"), NodeBase::String("ab1".to_string()));
+    assert_eq!(folded("(1 << 4) | 3"), NodeBase::Number(19.0));
+    assert_eq!(folded("!(1 < 2) || 'x' === 'x'"), NodeBase::Boolean(true));
+    assert_eq!(folded("true && a"), NodeBase::Identifier("a".to_string()));
+    match folded("(true && o.m)()") {
+        NodeBase::Call(ref callee, _) => match callee.base {
+            NodeBase::BinaryOp(_, _, BinOp::LAnd) => {}
+            _ => panic!(),
+        },
+        _ => panic!(),
+    }
+    assert_eq!(folded("-7 % 2"), NodeBase::Number(-1.0));
+    match folded("-0") {
+        NodeBase::UnaryOp(_, UnaryOp::Minus) => {}
+        _ => panic!(),
+    }
+    match folded("0 * -1") {
+        NodeBase::BinaryOp(_, ref rhs, BinOp::Mul) => assert_eq!(rhs.base, NodeBase::Number(-1.0)),
+        _ => panic!(),
+    }
+    match folded("a + 1 + 2") {
+        NodeBase::BinaryOp(_, ref rhs, BinOp::Add) => assert_eq!(rhs.base, NodeBase::Number(2.0)),
+        _ => panic!(),
+    }
+}
@@ -0,0 +1,74 @@
+//! Coverage feedback of scripts for coverage-guided fuzzers. With ``VM::enable_coverage``, the
+//! VM counts the edges between the basic blocks of the scripts it runs in a bitmap, like the one
+//! of AFL, so that a fuzzer can tell the inputs which take new paths through the scripts (not
+//! through the engine) from the others.
+//!
+//! A block is identified by its function and the pc it starts at. It is entered by a branch or
+//! a jump, a call of the function, or a catch or finally clause. The edge from the previous
+//! block is hashed into an index of the bitmap, so that distinct edges may share a count.
+
+use crate::vm::factory::FunctionId;
+
+#[derive(Clone, Debug)]
+pub struct Coverage {
+    map: Vec<u8>,
+    /// The hash of the previous block, shifted so that the edges a->b and b->a differ.
+    prev: usize,
+}
+
+impl Coverage {
+    /// A bitmap of ``size`` counts, rounded up to a power of two.
+    pub fn new(size: usize) -> Coverage {
+        Coverage {
+            map: vec![0; size.max(1).next_power_of_two()],
+            prev: 0,
+        }
+    }
+
+    /// The count of each edge, saturating at 255.
+    pub fn map(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// The number of edges counted at least once.
+    pub fn edges(&self) -> usize {
+        self.map.iter().filter(|count| **count != 0).count()
+    }
+
+    /// Clear the counts, e.g. before the next input is run.
+    pub fn reset(&mut self) {
+        for count in self.map.iter_mut() {
+            *count = 0;
+        }
+        self.prev = 0;
+    }
+
+    /// Count the edge to the block at ``pc`` of the function ``func_id``.
+    pub fn enter_block(&mut self, func_id: FunctionId, pc: usize) {
+        let block = hash(func_id.0, pc) & (self.map.len() - 1);
+        let count = &mut self.map[block ^ self.prev];
+        *count = count.saturating_add(1);
+        self.prev = block >> 1;
+    }
+}
+
+/// Spread the ids of the blocks, which are small and close to each other, over the bitmap.
+fn hash(func_id: usize, pc: usize) -> usize {
+    let h = (func_id as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ (pc as u64);
+    (h.wrapping_mul(0xbf58_476d_1ce4_e5b9) >> 32) as usize
+}
+
+#[test]
+fn edges() {
+    let mut coverage = Coverage::new(1000);
+    assert_eq!(coverage.map().len(), 1024);
+    coverage.enter_block(FunctionId(0), 0);
+    coverage.enter_block(FunctionId(0), 10);
+    coverage.enter_block(FunctionId(0), 0);
+    coverage.enter_block(FunctionId(0), 10);
+    // 0 (from the start), 0->10, 10->0, and 0->10 again.
+    assert_eq!(coverage.edges(), 3);
+    assert_eq!(coverage.map().iter().map(|n| *n as usize).sum::<usize>(), 4);
+    coverage.reset();
+    assert_eq!(coverage.edges(), 0);
+}
@@ -0,0 +1,249 @@
+//! Dead code elimination before bytecode generation.
+//! Statements following an unconditional ``return``, ``throw``, ``break`` or ``continue`` in the
+//! same statement list are dropped, and so are branches of ``if`` never taken because the
+//! condition is a literal (possibly after constant folding).
+//! Statements declaring a var or a function are kept, since the declarations are hoisted.
+
+use crate::node::{Node, NodeBase, PropertyDefinition, VarKind};
+use crate::util::StackLimit;
+use crate::vm::codegen::Warning;
+
+/// Eliminate the dead code in ``node`` and its descendants. A warning is added to ``warnings``
+/// for each run of unreachable statements dropped.
+pub fn eliminate_dead_code(node: &mut Node, stack_limit: &StackLimit, warnings: &mut Vec<Warning>) {
+    if stack_limit.is_exceeded() {
+        return;
+    }
+
+    match node.base {
+        NodeBase::StatementList(ref mut nodes) | NodeBase::Block(ref mut nodes) => {
+            for node in nodes.iter_mut() {
+                eliminate_dead_code(node, stack_limit, warnings)
+            }
+            drop_unreachable_statements(nodes, warnings)
+        }
+        NodeBase::FunctionDecl(_, _, ref mut body)
+        | NodeBase::FunctionExpr(_, _, ref mut body)
+        | NodeBase::ArrowFunction(_, ref mut body)
+        | NodeBase::Label(_, ref mut body)
+        | NodeBase::While(_, ref mut body)
+        | NodeBase::For(_, _, _, ref mut body) => eliminate_dead_code(body, stack_limit, warnings),
+        NodeBase::If(_, ref mut then, ref mut else_) => {
+            eliminate_dead_code(then, stack_limit, warnings);
+            eliminate_dead_code(else_, stack_limit, warnings)
+        }
+        NodeBase::Try(ref mut try_clause, ref mut catch, _, ref mut finally) => {
+            eliminate_dead_code(try_clause, stack_limit, warnings);
+            eliminate_dead_code(catch, stack_limit, warnings);
+            eliminate_dead_code(finally, stack_limit, warnings)
+        }
+        _ => visit_functions_in_expression(node, stack_limit, warnings),
+    }
+
+    eliminate_untaken_branch(node)
+}
+
+/// Functions may appear in any expression, e.g. ``f(function() { return; g() })``.
+fn visit_functions_in_expression(
+    node: &mut Node,
+    stack_limit: &StackLimit,
+    warnings: &mut Vec<Warning>,
+) {
+    if stack_limit.is_exceeded() {
+        return;
+    }
+
+    match node.base {
+        NodeBase::FunctionExpr(_, _, ref mut body) | NodeBase::ArrowFunction(_, ref mut body) => {
+            eliminate_dead_code(body, stack_limit, warnings)
+        }
+        NodeBase::Array(ref mut nodes) => {
+            for node in nodes {
+                visit_functions_in_expression(node, stack_limit, warnings)
+            }
+        }
+        NodeBase::Call(ref mut callee, ref mut args) => {
+            visit_functions_in_expression(callee, stack_limit, warnings);
+            for node in args {
+                visit_functions_in_expression(node, stack_limit, warnings)
+            }
+        }
+        NodeBase::Object(ref mut properties) => {
+            for property in properties {
+                match property {
+                    PropertyDefinition::Property(_, node)
+                    | PropertyDefinition::MethodDefinition(_, _, node)
+                    | PropertyDefinition::SpreadObject(node) => {
+                        visit_functions_in_expression(node, stack_limit, warnings)
+                    }
+                    PropertyDefinition::IdentifierReference(_) => {}
+                }
+            }
+        }
+        NodeBase::VarDecl(_, Some(ref mut node), _)
+        | NodeBase::Return(Some(ref mut node))
+        | NodeBase::Member(ref mut node, _)
+        | NodeBase::New(ref mut node)
+        | NodeBase::UnaryOp(ref mut node, _)
+        | NodeBase::Throw(ref mut node)
+        | NodeBase::Spread(ref mut node) => {
+            visit_functions_in_expression(node, stack_limit, warnings)
+        }
+        NodeBase::Index(ref mut lhs, ref mut rhs)
+        | NodeBase::Assign(ref mut lhs, ref mut rhs)
+        | NodeBase::BinaryOp(ref mut lhs, ref mut rhs, _) => {
+            visit_functions_in_expression(lhs, stack_limit, warnings);
+            visit_functions_in_expression(rhs, stack_limit, warnings)
+        }
+        NodeBase::TernaryOp(ref mut a, ref mut b, ref mut c) => {
+            visit_functions_in_expression(a, stack_limit, warnings);
+            visit_functions_in_expression(b, stack_limit, warnings);
+            visit_functions_in_expression(c, stack_limit, warnings)
+        }
+        _ => {}
+    }
+}
+
+fn drop_unreachable_statements(nodes: &mut Vec<Node>, warnings: &mut Vec<Warning>) {
+    let first_unreachable = match nodes.iter().position(completes_abruptly) {
+        Some(i) if i + 1 < nodes.len() => i + 1,
+        _ => return,
+    };
+
+    let mut dropped = false;
+    let mut i = first_unreachable;
+    while i < nodes.len() {
+        if has_hoisted_declaration(&nodes[i]) {
+            i += 1;
+            continue;
+        }
+        let node = nodes.remove(i);
+        if !dropped {
+            warnings.push(Warning {
+                msg: "Unreachable code.".to_string(),
+                token_pos: node.pos,
+            });
+            dropped = true;
+        }
+    }
+}
+
+/// ``if`` whose condition is a literal is replaced by the branch taken.
+fn eliminate_untaken_branch(node: &mut Node) {
+    let taken = match node.base {
+        NodeBase::If(ref cond, ref mut then, ref mut else_) => {
+            let (taken, untaken) = match literal_to_boolean(&cond.base) {
+                Some(true) => (then, else_),
+                Some(false) => (else_, then),
+                None => return,
+            };
+            if has_hoisted_declaration(untaken) {
+                return;
+            }
+            ::std::mem::replace(&mut **taken, Node::new(NodeBase::Nope, 0))
+        }
+        _ => return,
+    };
+    *node = match taken.base {
+        // No else clause
+        NodeBase::Nope => Node::new(NodeBase::StatementList(vec![]), node.pos),
+        _ => taken,
+    };
+}
+
+/// Whether the statement always completes with return, throw, break or continue.
+fn completes_abruptly(node: &Node) -> bool {
+    match node.base {
+        NodeBase::Return(_) | NodeBase::Throw(_) | NodeBase::Break(_) | NodeBase::Continue(_) => {
+            true
+        }
+        NodeBase::Block(ref nodes) => nodes.iter().any(completes_abruptly),
+        NodeBase::If(_, ref then, ref else_) => {
+            completes_abruptly(then) && completes_abruptly(else_)
+        }
+        _ => false,
+    }
+}
+
+/// Whether removing the statement would remove a binding: a var or a function declared in it,
+/// or a lexical declaration directly in the statement list.
+fn has_hoisted_declaration(node: &Node) -> bool {
+    match node.base {
+        NodeBase::VarDecl(_, _, _) | NodeBase::FunctionDecl(_, _, _) => true,
+        NodeBase::StatementList(ref nodes) => nodes.iter().any(has_hoisted_declaration),
+        NodeBase::Block(ref nodes) => nodes.iter().any(declares_var),
+        _ => declares_var(node),
+    }
+}
+
+/// Whether a var or a function is declared in the statement, not including nested functions.
+fn declares_var(node: &Node) -> bool {
+    match node.base {
+        NodeBase::VarDecl(_, _, VarKind::Var) | NodeBase::FunctionDecl(_, _, _) => true,
+        NodeBase::StatementList(ref nodes) | NodeBase::Block(ref nodes) => {
+            nodes.iter().any(declares_var)
+        }
+        NodeBase::If(_, ref then, ref else_) => declares_var(then) || declares_var(else_),
+        NodeBase::While(_, ref body) | NodeBase::Label(_, ref body) => declares_var(body),
+        NodeBase::For(ref init, _, _, ref body) => declares_var(init) || declares_var(body),
+        NodeBase::Try(ref try_clause, ref catch, _, ref finally) => {
+            declares_var(try_clause) || declares_var(catch) || declares_var(finally)
+        }
+        _ => false,
+    }
+}
+
+/// https://tc39.github.io/ecma262/#sec-toboolean
+fn literal_to_boolean(base: &NodeBase) -> Option<bool> {
+    match base {
+        NodeBase::Boolean(b) => Some(*b),
+        NodeBase::Number(n) => Some(*n != 0.0 && !n.is_nan()),
+        NodeBase::String(s) => Some(!s.is_empty()),
+        NodeBase::Null => Some(false),
+        _ => None,
+    }
+}
+
+#[test]
+fn eliminate_unreachable_code() {
+    use crate::parser::Parser;
+
+    let mut node = Parser::new(
+        "test",
+        "function f() { return 1; g(); var v; function h() {} } if (false) { a() } else { b() }",
+    )
+    .parse_all()
+    .unwrap();
+    let mut warnings = vec![];
+    eliminate_dead_code(&mut node, &StackLimit::new(), &mut warnings);
+    assert_eq!(warnings.len(), 1);
+    let nodes = match node.base {
+        NodeBase::StatementList(nodes) => nodes,
+        _ => unreachable!(),
+    };
+    match nodes[0].base {
+        NodeBase::FunctionDecl(_, _, ref body) => match body.base {
+            NodeBase::StatementList(ref body) => {
+                assert_eq!(body.len(), 3);
+                match body[1].base {
+                    NodeBase::StatementList(ref decls) => match decls[0].base {
+                        NodeBase::VarDecl(ref name, _, _) => assert_eq!(name, "v"),
+                        _ => panic!(),
+                    },
+                    _ => panic!(),
+                }
+            }
+            _ => panic!(),
+        },
+        _ => panic!(),
+    }
+    match nodes[1].base {
+        NodeBase::Block(ref nodes) => match nodes[0].base {
+            NodeBase::Call(ref callee, _) => {
+                assert_eq!(callee.base, NodeBase::Identifier("b".to_string()))
+            }
+            _ => panic!(),
+        },
+        _ => panic!(),
+    }
+}
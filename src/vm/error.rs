@@ -1,5 +1,5 @@
 use crate::vm::exec_context::ExecContext;
-use crate::vm::jsvalue::value::Value;
+use crate::vm::jsvalue::value::{ErrorType, Value};
 use crate::vm::vm::{Factory, FunctionId};
 use ansi_term::Colour;
 
@@ -20,9 +20,16 @@ pub enum ErrorKind {
     Unknown,
     Type(String),
     Reference(String),
+    Range(String),
     General(String),
     Exception(Value),
     Unimplemented,
+    /// The script was stopped by the timeout or the interrupt handle. It can not be caught by
+    /// scripts. See ``interrupt``.
+    ExecutionTerminated,
+    /// The script ran out of the fuel given by ``VM::set_fuel``, and can be resumed by
+    /// ``VM::resume``.
+    OutOfFuel,
 }
 
 impl RuntimeError {
@@ -52,6 +59,14 @@ impl RuntimeError {
         RuntimeError::default(ErrorKind::Reference(msg.into()))
     }
 
+    pub fn range(msg: impl Into<String>) -> RuntimeError {
+        RuntimeError::default(ErrorKind::Range(msg.into()))
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        self.kind == ErrorKind::ExecutionTerminated
+    }
+
     pub fn error_add_info(mut self, context: &ExecContext) -> RuntimeError {
         self.func_id = context.func_ref.func_id;
         self.module_func_id = context.func_ref.module_func_id;
@@ -60,18 +75,211 @@ impl RuntimeError {
     }
 
     /// convert RuntimeError -> Value
+    /// Use ``VM::error_to_value`` to also record the stack in the error.
     pub fn to_value(self, factory: &mut Factory) -> Value {
-        match self.kind {
-            ErrorKind::Exception(v) => v,
-            ErrorKind::Type(s) => factory.error(format!("Type error: {}", s)),
-            ErrorKind::General(s) => factory.error(format!("Error: {}", s)),
-            ErrorKind::Reference(s) => factory.error(format!("Reference error: {}", s)),
-            ErrorKind::Unimplemented => factory.error("Unimplemented"),
-            ErrorKind::Unknown => factory.error("Unknown"),
+        match self.kind.into_error() {
+            Ok((error_type, message)) => factory.native_error(error_type, message),
+            Err(val) => val,
         }
     }
 }
 
+impl ErrorKind {
+    /// The type and the message of the error to throw, or the thrown value.
+    pub fn into_error(self) -> Result<(ErrorType, String), Value> {
+        match self {
+            ErrorKind::Exception(val) => Err(val),
+            ErrorKind::Type(msg) => Ok((ErrorType::TypeError, msg)),
+            ErrorKind::Reference(msg) => Ok((ErrorType::ReferenceError, msg)),
+            ErrorKind::Range(msg) => Ok((ErrorType::RangeError, msg)),
+            ErrorKind::General(msg) => Ok((ErrorType::Error, msg)),
+            ErrorKind::Unimplemented => Ok((ErrorType::Error, "Unimplemented".to_string())),
+            ErrorKind::Unknown => Ok((ErrorType::Error, "Unknown".to_string())),
+            ErrorKind::ExecutionTerminated => {
+                Ok((ErrorType::Error, "Execution terminated".to_string()))
+            }
+            ErrorKind::OutOfFuel => Ok((ErrorType::Error, "Out of fuel".to_string())),
+        }
+    }
+}
+
+/// Messages of the errors thrown by the VM and the builtins, so that the same problem is
+/// reported the same way everywhere.
+pub mod message {
+    use crate::vm::jsvalue::value::Value;
+
+    pub fn not_a_function(val: Value) -> String {
+        format!("{} is not a function", val.debug_string(true))
+    }
+
+    pub fn not_a_constructor(val: Value) -> String {
+        format!("{} is not a constructor", val.debug_string(true))
+    }
+
+    pub fn not_iterable(val: Value) -> String {
+        format!("{} is not iterable", val.debug_string(true))
+    }
+
+    pub fn not_a_symbol(val: Value) -> String {
+        format!("{} is not a symbol", val.debug_string(true))
+    }
+
+    pub fn cannot_convert_to_primitive() -> String {
+        "Cannot convert object to primitive value".to_string()
+    }
+
+    /// ``to`` is "number" or "string".
+    pub fn cannot_convert_symbol(to: &str) -> String {
+        format!("Cannot convert a Symbol value to a {}", to)
+    }
+
+    pub fn not_an_entry_object(val: Value) -> String {
+        format!(
+            "Iterator value {} is not an entry object",
+            val.debug_string(true)
+        )
+    }
+
+    /// ``method`` is called with ``this`` of the wrong type.
+    pub fn incompatible_receiver(method: &str, this: Value) -> String {
+        format!(
+            "Method {} called on incompatible receiver {}",
+            method,
+            this.debug_string(true)
+        )
+    }
+
+    pub fn cannot_read_property(key: Value, val: Value) -> String {
+        format!(
+            "Cannot read property '{}' of {}",
+            key.to_string(),
+            val.to_string()
+        )
+    }
+
+    pub fn cannot_set_property(key: Value, val: Value) -> String {
+        format!(
+            "Cannot set property '{}' of {}",
+            key.to_string(),
+            val.to_string()
+        )
+    }
+
+    pub fn read_only_property(key: Value, val: Value) -> String {
+        format!(
+            "Cannot assign to read only property '{}' of {}",
+            key.to_string(),
+            val.debug_string(true)
+        )
+    }
+
+    pub fn cannot_delete_property(key: Value, val: Value) -> String {
+        format!(
+            "Cannot delete property '{}' of {}",
+            key.to_string(),
+            val.debug_string(true)
+        )
+    }
+
+    pub fn property_access_denied(key: Value) -> String {
+        format!("Access to property '{}' is denied", key.to_string())
+    }
+
+    pub fn enumeration_denied() -> String {
+        "Enumerating the properties is denied".to_string()
+    }
+
+    pub fn cannot_use_in_operator(key: Value, val: Value) -> String {
+        format!(
+            "Cannot use 'in' operator to search for '{}' in {}",
+            key.to_string(),
+            val.debug_string(true)
+        )
+    }
+
+    pub fn cannot_convert_to_object(val: Value) -> String {
+        format!("Cannot convert {} to object", val.to_string())
+    }
+
+    pub fn cannot_redefine_property(key: Value) -> String {
+        format!("Cannot redefine property: {}", key.to_string())
+    }
+
+    /// ``method`` is called on a value which is not an object.
+    pub fn called_on_non_object(method: &str) -> String {
+        format!("{} called on non-object", method)
+    }
+
+    pub fn property_description_must_be_object(val: Value) -> String {
+        format!(
+            "Property description must be an object: {}",
+            val.debug_string(true)
+        )
+    }
+
+    /// The descriptor has both ``get`` or ``set``, and ``value`` or ``writable``.
+    pub fn invalid_property_descriptor() -> String {
+        "Invalid property descriptor. Cannot both specify accessors and a value or writable attribute"
+            .to_string()
+    }
+
+    /// ``field`` is ``get`` or ``set``.
+    pub fn accessor_must_be_function(field: &str, val: Value) -> String {
+        format!(
+            "{} must be a function: {}",
+            if field == "get" { "Getter" } else { "Setter" },
+            val.debug_string(true)
+        )
+    }
+
+    pub fn cannot_create_proxy() -> String {
+        "Cannot create proxy with a non-object as target or handler".to_string()
+    }
+
+    /// The trap ``trap`` of a proxy handler is neither a function nor undefined.
+    pub fn proxy_trap_not_function(trap: &str, val: Value) -> String {
+        format!(
+            "'{}' on proxy: trap {} is not a function",
+            trap,
+            val.debug_string(true)
+        )
+    }
+
+    /// The trap ``trap`` of a proxy handler returned a non-object where an object is needed.
+    pub fn proxy_trap_returned_non_object(trap: &str, val: Value) -> String {
+        format!(
+            "'{}' on proxy: trap returned non-object ({})",
+            trap,
+            val.debug_string(true)
+        )
+    }
+
+    /// An element of the array returned by the ``ownKeys`` trap is not a property key.
+    pub fn proxy_own_keys_invalid_key(val: Value) -> String {
+        format!("{} is not a valid property name", val.debug_string(true))
+    }
+
+    pub fn invalid_array_length() -> String {
+        "Invalid array length".to_string()
+    }
+
+    pub fn not_defined(name: &str) -> String {
+        format!("{} is not defined", name)
+    }
+
+    pub fn before_initialization(name: &str) -> String {
+        format!("Cannot access '{}' before initialization", name)
+    }
+
+    pub fn assignment_to_constant() -> String {
+        "Assignment to constant variable.".to_string()
+    }
+
+    pub fn assignment_to_undeclared(name: &str) -> String {
+        format!("Assignment to undeclared identifier '{}'", name)
+    }
+}
+
 pub fn runtime_error(msg: impl Into<String>) {
     eprintln!(
         "{}: {}",
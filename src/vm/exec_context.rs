@@ -1,13 +1,13 @@
 #![macro_use]
 //use crate::bytecode_gen::ByteCode;
-use crate::vm::jsvalue::function::{FuncInfoRef, UserFunctionInfo};
+use crate::vm::jsvalue::function::{ExceptionHandler, FuncInfoRef, HandlerKind, UserFunctionInfo};
 //use crate::gc;
 use crate::vm::error::ErrorKind;
-use crate::vm::error::RuntimeError;
+use crate::vm::error::{message, RuntimeError};
 //use crate::vm::jsvalue::function::Exception;
-use crate::vm::jsvalue::value::{BoxedValue, Value};
+use crate::vm::jsvalue::value::{BoxedValue, ErrorType, Value};
 use crate::vm::vm::{CallMode, Factory, VMResult};
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use std::ops::{Deref, DerefMut};
 
 #[derive(Debug, Clone, Copy)]
@@ -20,6 +20,9 @@ pub struct ExecContext {
     pub pc: usize,
     pub current_inst_pc: usize,
     pub stack: Vec<BoxedValue>,
+    /// Variables of the function not bound in the environments, by their indexes in
+    /// ``UserFunctionInfo::register_names``.
+    pub registers: Vec<Value>,
     pub func_ref: FuncInfoRef,
     //pub bytecode: ByteCode,
     //pub exception_table: Vec<Exception>,
@@ -35,12 +38,26 @@ pub struct ExecContext {
     pub variable_environment: LexicalEnvironmentRef,
     pub lexical_environment: LexicalEnvironmentRef,
     pub saved_lexical_environment: Vec<LexicalEnvironmentRef>,
+    /// How each finally clause being run is resumed at its end, innermost last.
+    pub subroutine_stack: Vec<SubroutineKind>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubroutineKind {
+    /// Entered by ``JMP_SUB`` after the try or catch clause completed; continue at the position.
+    Ordinary(usize),
+    /// Entered by an exception, which is at the stack top; throw it again.
+    Throw,
+    /// Entered by a return, whose value is at the stack top; return it.
+    Return,
 }
 
 #[derive(Debug, Clone)]
 pub struct LexicalEnvironment {
     pub record: EnvironmentRecord,
     pub outer: Option<LexicalEnvironmentRef>,
+    /// Names of bindings declared by const.
+    pub immutable_bindings: FxHashSet<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -72,6 +89,7 @@ impl ExecContext {
             pc: 0,
             current_inst_pc: 0,
             stack: vec![],
+            registers: vec![],
             func_ref,
             this,
             constructor_call: false,
@@ -79,6 +97,7 @@ impl ExecContext {
             variable_environment: var_env,
             lexical_environment: lex_env,
             saved_lexical_environment: vec![],
+            subroutine_stack: vec![],
         }
     }
     pub fn empty() -> Self {
@@ -86,6 +105,7 @@ impl ExecContext {
             pc: 0,
             current_inst_pc: 0,
             stack: vec![],
+            registers: vec![],
             func_ref: FuncInfoRef::default(),
             this: Value::undefined(),
             constructor_call: false,
@@ -93,6 +113,7 @@ impl ExecContext {
             variable_environment: LexicalEnvironmentRef::new_null(),
             lexical_environment: LexicalEnvironmentRef::new_null(),
             saved_lexical_environment: vec![],
+            subroutine_stack: vec![],
         }
     }
 
@@ -109,6 +130,33 @@ impl ExecContext {
         self
     }
 
+    /// Find the innermost handler covering the instruction at ``pc``. Catch clauses are skipped
+    /// if ``finally_only``, as a return only runs finally clauses.
+    pub fn find_handler(&self, pc: usize, finally_only: bool) -> Option<ExceptionHandler> {
+        self.func_ref
+            .exception_table
+            .iter()
+            .find(|handler| {
+                handler.start <= pc
+                    && pc < handler.end
+                    && (!finally_only || handler.kind == HandlerKind::Finally)
+            })
+            .cloned()
+    }
+
+    /// Leave the blocks and the finally clauses entered in the range of ``handler``, and jump to
+    /// it. If it is a finally clause, it is resumed by ``subroutine``.
+    pub fn enter_handler(&mut self, handler: &ExceptionHandler, subroutine: SubroutineKind) {
+        while self.saved_lexical_environment.len() > handler.env_depth {
+            self.lexical_environment = self.saved_lexical_environment.pop().unwrap();
+        }
+        self.subroutine_stack.truncate(handler.finally_depth);
+        if handler.kind == HandlerKind::Finally {
+            self.subroutine_stack.push(subroutine);
+        }
+        self.pc = handler.handler;
+    }
+
     fn append_function(&mut self, factory: &mut Factory, info: FuncInfoRef) {
         let name = info.func_name.clone().unwrap();
         let val = factory.function(info, self.lexical_environment);
@@ -140,18 +188,6 @@ impl ExecContext {
         }
     }
 
-    pub fn error_general(&self, msg: impl Into<String>) -> RuntimeError {
-        RuntimeError::new(ErrorKind::General(msg.into()), self)
-    }
-
-    pub fn error_type(&self, msg: impl Into<String>) -> RuntimeError {
-        RuntimeError::new(ErrorKind::Type(msg.into()), self)
-    }
-
-    pub fn error_reference(&self, msg: impl Into<String>) -> RuntimeError {
-        RuntimeError::new(ErrorKind::Reference(msg.into()), self)
-    }
-
     pub fn error_exception(&self, val: Value) -> RuntimeError {
         RuntimeError::new(ErrorKind::Exception(val), self)
     }
@@ -166,6 +202,7 @@ impl LexicalEnvironment {
         LexicalEnvironment {
             record: EnvironmentRecord::Declarative(FxHashMap::default()),
             outer,
+            immutable_bindings: FxHashSet::default(),
         }
     }
 
@@ -173,15 +210,17 @@ impl LexicalEnvironment {
         LexicalEnvironment {
             record: EnvironmentRecord::Object(object),
             outer,
+            immutable_bindings: FxHashSet::default(),
         }
     }
 
     pub fn new_global_initialized(factory: &mut Factory) -> Self {
-        use crate::builtin::{deep_seq, parse_float, require};
+        use crate::builtin::{deep_seq, eval, parse_float, require};
         use crate::builtins;
 
         let log = factory.builtin_function("log", builtins::console::console_log);
         let parse_float = factory.builtin_function("parseFloat", parse_float);
+        let eval = factory.builtin_function("eval", eval);
         let require = factory.builtin_function("require", require);
         let deep_seq = factory.builtin_function("__assert_deep_seq", deep_seq);
         let console = make_normal_object!(factory,
@@ -190,53 +229,82 @@ impl LexicalEnvironment {
         let object_constructor = builtins::object::object(factory);
         let function_constructor = builtins::function::function(factory);
         let array_constructor = builtins::array::array(factory);
+        let number_constructor = builtins::number::number(factory);
         let symbol_constructor = builtins::symbol::symbol(factory);
         let error_constructor = builtins::error::error(factory);
+        let type_error_constructor = builtins::error::native_error(factory, ErrorType::TypeError);
+        let reference_error_constructor =
+            builtins::error::native_error(factory, ErrorType::ReferenceError);
+        let range_error_constructor = builtins::error::native_error(factory, ErrorType::RangeError);
+        let syntax_error_constructor =
+            builtins::error::native_error(factory, ErrorType::SyntaxError);
         let math_object = builtins::math::math(factory);
+        let map_constructor = builtins::map::map(factory);
+        let set_constructor = builtins::set::set(factory);
+        let proxy_constructor = builtins::proxy::proxy(factory);
+        let reflect_object = builtins::reflect::reflect(factory);
+        let json_object = builtins::json::json(factory);
+        let rapidus_object = builtins::rapidus::rapidus(factory);
+        let process_object = builtins::rapidus::process(factory);
+        let global_object = make_normal_object!(
+            factory,
+            undefined  => false,false,false: Value::undefined(),
+            NaN        => false,false,false: Value::Number(::std::f64::NAN),
+            Infinity   => false,false,false: Value::Number(::std::f64::INFINITY),
+            require    => true, false, true: require,
+            __assert_deep_seq    => true, false, true: deep_seq,
+            parseFloat => true, false, true: parse_float,
+            eval       => true, false, true: eval,
+            console    => true, false, true: console,
+            Object     => true, false, true: object_constructor,
+            Function   => true, false, true: function_constructor,
+            Array      => true, false, true: array_constructor,
+            Number     => true, false, true: number_constructor,
+            Symbol     => true, false, true: symbol_constructor,
+            Error      => true, false, true: error_constructor,
+            TypeError  => true, false, true: type_error_constructor,
+            ReferenceError => true, false, true: reference_error_constructor,
+            RangeError => true, false, true: range_error_constructor,
+            SyntaxError => true, false, true: syntax_error_constructor,
+            Math       => true, false, true: math_object,
+            Map        => true, false, true: map_constructor,
+            Set        => true, false, true: set_constructor,
+            Proxy      => true, false, true: proxy_constructor,
+            Reflect    => true, false, true: reflect_object,
+            JSON       => true, false, true: json_object,
+            Rapidus    => true, false, true: rapidus_object,
+            process    => true, false, true: process_object
+        );
+        #[cfg(feature = "intl")]
+        {
+            let intl_object = builtins::intl::intl(factory);
+            global_object.set_property("Intl", intl_object);
+        }
         LexicalEnvironment {
-            record: EnvironmentRecord::Global(make_normal_object!(
-                factory,
-                undefined  => false,false,false: Value::undefined(),
-                NaN        => false,false,false: Value::Number(::std::f64::NAN),
-                Infinity   => false,false,false: Value::Number(::std::f64::INFINITY),
-                require    => true, false, true: require,
-                __assert_deep_seq    => true, false, true: deep_seq,
-                parseFloat => true, false, true: parse_float,
-                console    => true, false, true: console,
-                Object     => true, false, true: object_constructor,
-                Function   => true, false, true: function_constructor,
-                Array      => true, false, true: array_constructor,
-                Symbol     => true, false, true: symbol_constructor,
-                Error      => true, false, true: error_constructor,
-                Math       => true, false, true: math_object
-            )),
+            record: EnvironmentRecord::Global(global_object),
             outer: None,
+            immutable_bindings: FxHashSet::default(),
         }
     }
 
-    pub fn get_value(&self, name: impl Into<String>) -> Result<Value, RuntimeError> {
-        let name = name.into();
+    pub fn get_value(&self, name: &str) -> Result<Value, RuntimeError> {
         match self.record {
             EnvironmentRecord::Function { ref record, .. }
             | EnvironmentRecord::Module { ref record, .. }
-            | EnvironmentRecord::Declarative(ref record) => match record.get(&name) {
+            | EnvironmentRecord::Declarative(ref record) => match record.get(name) {
                 Some(binding) if binding == &Value::uninitialized() => {
-                    return Err(RuntimeError::reference(format!(
-                        "'{}' is not defined",
-                        name
+                    return Err(RuntimeError::reference(message::before_initialization(
+                        name,
                     )));
                 }
                 Some(binding) => return Ok(*binding),
                 None => {}
             },
             EnvironmentRecord::Global(obj) | EnvironmentRecord::Object(obj) => {
-                if obj.has_own_property(name.as_str()) {
-                    let val = obj.get_property(name.as_str());
+                if obj.has_own_property(name) {
+                    let val = obj.get_property(name);
                     if val == Value::uninitialized() {
-                        return Err(RuntimeError::reference(format!(
-                            "'{}' is not defined",
-                            name
-                        )));
+                        return Err(RuntimeError::reference(message::not_defined(name)));
                     }
                     return Ok(val);
                 }
@@ -246,18 +314,23 @@ impl LexicalEnvironment {
         if let Some(outer) = self.outer {
             outer.get_value(name)
         } else {
-            Err(RuntimeError::reference(format!(
-                "'{}' is not defined",
-                name
-            )))
+            Err(RuntimeError::reference(message::not_defined(name)))
         }
     }
 
-    pub fn set_value(&mut self, name: String, val: Value) -> VMResult {
+    pub fn set_value(&mut self, name: &str, val: Value) -> VMResult {
         match self.record {
             EnvironmentRecord::Function { ref mut record, .. }
             | EnvironmentRecord::Module { ref mut record, .. }
-            | EnvironmentRecord::Declarative(ref mut record) => match record.get_mut(&name) {
+            | EnvironmentRecord::Declarative(ref mut record) => match record.get_mut(name) {
+                Some(binding) if *binding == Value::uninitialized() => {
+                    return Err(RuntimeError::reference(message::before_initialization(
+                        name,
+                    )));
+                }
+                Some(_) if self.immutable_bindings.contains(name) => {
+                    return Err(RuntimeError::typeerr(message::assignment_to_constant()));
+                }
                 Some(binding) => {
                     *binding = val;
                     return Ok(());
@@ -273,9 +346,8 @@ impl LexicalEnvironment {
         if let Some(mut outer) = self.outer {
             outer.set_value(name, val)
         } else {
-            Err(RuntimeError::reference(format!(
-                "Assignment to undeclared identifier '{}'",
-                name
+            Err(RuntimeError::reference(message::assignment_to_undeclared(
+                name,
             )))
         }
     }
@@ -288,12 +360,47 @@ impl LexicalEnvironment {
                 record.insert(name.into(), val);
             }
             EnvironmentRecord::Global(obj) | EnvironmentRecord::Object(obj) => {
-                obj.set_property(name, val);
+                obj.set_property(name.into(), val);
             }
         };
         return Ok(());
     }
 
+    /// https://tc39.github.io/ecma262/#sec-declarative-environment-records-hasbinding-n
+    pub fn has_own_binding(&self, name: &str) -> bool {
+        match self.record {
+            EnvironmentRecord::Function { ref record, .. }
+            | EnvironmentRecord::Module { ref record, .. }
+            | EnvironmentRecord::Declarative(ref record) => record.contains_key(name),
+            EnvironmentRecord::Global(obj) | EnvironmentRecord::Object(obj) => {
+                obj.has_own_property(name)
+            }
+        }
+    }
+
+    /// Whether ``name`` is bound in this environment or an outer one.
+    /// https://tc39.github.io/ecma262/#sec-getidentifierreference
+    pub fn has_binding(&self, name: &str) -> bool {
+        if self.has_own_binding(name) {
+            return true;
+        }
+        match self.outer {
+            Some(outer) => outer.has_binding(name),
+            None => false,
+        }
+    }
+
+    /// Initialize the binding declared by let or const in this environment.
+    /// https://tc39.github.io/ecma262/#sec-declarative-environment-records-initializebinding-n-v
+    pub fn initialize_binding(&mut self, name: String, val: Value, is_const: bool) -> VMResult {
+        if is_const {
+            self.immutable_bindings.insert(name.clone());
+        } else {
+            self.immutable_bindings.remove(&name);
+        }
+        self.set_own_value(name, val)
+    }
+
     pub fn get_global_object(&self) -> Value {
         match self.record {
             EnvironmentRecord::Global(obj) => obj,
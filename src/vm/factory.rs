@@ -3,12 +3,13 @@ use crate::gc;
 use crate::vm::{
     jsvalue::prototype::ObjectPrototypes,
     jsvalue::value::{
-        ArrayObjectInfo, ErrorObjectInfo, FuncInfoRef, FunctionObjectInfo, FunctionObjectKind,
-        ObjectInfo, ObjectKind, Property, SymbolInfo, UserFunctionInfo, Value,
+        ArrayObjectInfo, DataProperty, ErrorObjectInfo, ErrorType, FuncInfoRef, FunctionObjectInfo,
+        FunctionObjectKind, MapObjectInfo, ObjectInfo, ObjectKind, Property, PropertyMap,
+        ProxyObjectInfo, SymbolInfo, UserFunctionInfo, Value, WellKnownSymbols,
     },
     vm::{LexicalEnvironmentRef, LexicalEnvironment, EnvironmentRecord, FunctionParameter},
 };
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 #[derive(Clone, Hash, Copy)]
 pub struct FunctionId(pub usize);
@@ -32,12 +33,28 @@ impl std::fmt::Debug for FunctionId {
     }
 }
 
+/// A member of a namespace object made by ``Factory::frozen_namespace``.
+#[derive(Clone, Debug)]
+pub enum NamespaceMember<'a> {
+    Number(f64),
+    String(&'a str),
+    Boolean(bool),
+    Value(Value),
+    /// A nested namespace, frozen as well.
+    Namespace(&'a [(&'a str, NamespaceMember<'a>)]),
+}
+
 #[derive(Debug)]
 pub struct Factory {
     pub memory_allocator: gc::MemoryAllocator,
     pub object_prototypes: ObjectPrototypes,
+    pub well_known_symbols: WellKnownSymbols,
     pub func_refs: Vec<Option<FuncInfoRef>>,
     pub next_func_id: usize,
+    /// The empty string and the strings of one ASCII character, which are created very often
+    /// (e.g. by indexing strings), indexed by the character. The empty string is at 0 since NUL
+    /// can not be in a string. Numbers need no cache since they are not allocated.
+    string_cache: Vec<Value>,
 }
 
 impl Factory {
@@ -45,12 +62,22 @@ impl Factory {
         let mut factory = Factory {
             memory_allocator,
             object_prototypes,
+            well_known_symbols: WellKnownSymbols::dummy(),
             func_refs: vec![None; 30],
             next_func_id: 1,
+            string_cache: vec![],
         };
         let func_ref =
             factory.alloc_user_func_info(FunctionId::default(), UserFunctionInfo::default());
         factory.func_refs[0] = Some(func_ref);
+        let mut string_cache = vec![];
+        for c in 0..128u8 {
+            let body = if c == 0 { vec![] } else { vec![c] };
+            let val = Value::String(factory.alloc(std::ffi::CString::new(body).unwrap()));
+            factory.memory_allocator.lock_value(val);
+            string_cache.push(val);
+        }
+        factory.string_cache = string_cache;
         factory
     }
 
@@ -91,6 +118,11 @@ impl Factory {
         func_ref
     }
 
+    /// Get the function ``func_id``, or None if it has not been compiled.
+    pub fn find_func_ref(&self, func_id: FunctionId) -> Option<FuncInfoRef> {
+        self.func_refs.get(func_id.0).and_then(|func_ref| *func_ref)
+    }
+
     pub fn get_func_ref(&self, func_id: FunctionId) -> FuncInfoRef {
         if func_id.0 >= self.func_refs.len() {
             panic!("FunctionId is not exists.");
@@ -122,11 +154,17 @@ impl Factory {
 impl Factory {
     /// Generate Value for a string.
     pub fn string(&mut self, body: impl Into<String>) -> Value {
-        Value::String(self.alloc(std::ffi::CString::new(body.into()).unwrap()))
+        let body = body.into();
+        match body.as_bytes() {
+            [] => return self.string_cache[0],
+            [c] if *c != 0 && c.is_ascii() => return self.string_cache[*c as usize],
+            _ => {}
+        }
+        Value::String(self.alloc(std::ffi::CString::new(body).unwrap()))
     }
 
     /// Generate Value for an object.
-    pub fn object(&mut self, property: FxHashMap<String, Property>) -> Value {
+    pub fn object(&mut self, property: PropertyMap) -> Value {
         Value::Object(self.alloc(ObjectInfo {
             kind: ObjectKind::Ordinary,
             prototype: self.object_prototypes.object,
@@ -135,6 +173,94 @@ impl Factory {
         }))
     }
 
+    /// Generate a Boolean, Number or String object wrapping ``val``. Like the other string
+    /// values, a String object has the read-only ``length`` and the characters as properties.
+    /// Booleans have no prototype of their own, so Boolean objects inherit Object.prototype.
+    /// https://tc39.github.io/ecma262/#sec-toobject
+    pub fn primitive_wrapper(&mut self, val: Value) -> Value {
+        let mut property = PropertyMap::default();
+        let prototype = match val {
+            Value::Number(_) => self.object_prototypes.number,
+            Value::String(_) => {
+                let s = val.into_str();
+                for (i, c) in s.chars().enumerate() {
+                    let c = self.string(c.to_string());
+                    property.insert(
+                        i.to_string(),
+                        Property::Data(DataProperty {
+                            val: c,
+                            writable: false,
+                            enumerable: true,
+                            configurable: false,
+                        }),
+                    );
+                }
+                let length = s.chars().fold(0, |x, c| x + c.len_utf16());
+                property.insert(
+                    "length".to_string(),
+                    Property::Data(DataProperty {
+                        val: Value::Number(length as f64),
+                        writable: false,
+                        enumerable: false,
+                        configurable: false,
+                    }),
+                );
+                self.object_prototypes.string
+            }
+            _ => self.object_prototypes.object,
+        };
+        Value::Object(self.alloc(ObjectInfo {
+            kind: ObjectKind::PrimitiveWrapper(val),
+            prototype,
+            property,
+            sym_property: FxHashMap::default(),
+        }))
+    }
+
+    /// Generate a namespace object such as a table of constants or an enum in one call.
+    /// The properties are enumerable, read-only and not configurable, also in the nested
+    /// namespaces. Equal strings in ``members`` share one string value.
+    /// Note that objects are always extensible for now, so properties can still be added.
+    pub fn frozen_namespace(&mut self, members: &[(&str, NamespaceMember)]) -> Value {
+        self.frozen_namespace_with_strings(members, &mut FxHashMap::default())
+    }
+
+    fn frozen_namespace_with_strings<'a>(
+        &mut self,
+        members: &[(&str, NamespaceMember<'a>)],
+        strings: &mut FxHashMap<&'a str, Value>,
+    ) -> Value {
+        let mut property = PropertyMap::default();
+        for (name, member) in members {
+            let val = match *member {
+                NamespaceMember::Number(n) => Value::Number(n),
+                NamespaceMember::String(s) => match strings.get(s) {
+                    Some(val) => *val,
+                    None => {
+                        let val = self.string(s);
+                        strings.insert(s, val);
+                        val
+                    }
+                },
+                NamespaceMember::Boolean(b) => Value::bool(b),
+                NamespaceMember::Value(val) => val,
+                NamespaceMember::Namespace(members) => {
+                    self.frozen_namespace_with_strings(members, strings)
+                }
+            };
+            property.insert(
+                name.to_string(),
+                Property::Data(DataProperty {
+                    val,
+                    writable: false,
+                    enumerable: true,
+                    configurable: false,
+                }),
+            );
+        }
+        self.object(property)
+    }
+
     /// Generate Value for a JS function.
     pub fn function(
         &mut self,
@@ -142,7 +268,7 @@ impl Factory {
         outer_env: impl Into<Option<LexicalEnvironmentRef>>,
     ) -> Value {
         let name_prop = self.string(info.func_name.clone().unwrap_or("".to_string()));
-        let prototype = self.object(FxHashMap::default());
+        let prototype = self.object(PropertyMap::default());
 
         let f = Value::Object(self.alloc(ObjectInfo {
             prototype: self.object_prototypes.function,
@@ -154,6 +280,7 @@ impl Factory {
             kind: ObjectKind::Function(FunctionObjectInfo {
                 name: info.func_name.clone(),
                 kind: FunctionObjectKind::User{info, outer_env: outer_env.into()},
+                constructible: info.constructible,
             }),
             sym_property: FxHashMap::default(),
         }));
@@ -171,13 +298,72 @@ impl Factory {
         &mut self,
         name: impl Into<String>,
         func: crate::builtin::BuiltinFuncTy,
+    ) -> Value {
+        self.function_of_kind(name, FunctionObjectKind::Builtin(func), false)
+    }
+
+    /// Generate Value for a built-in function that can be called by new.
+    pub fn builtin_constructor(
+        &mut self,
+        name: impl Into<String>,
+        func: crate::builtin::BuiltinFuncTy,
+    ) -> Value {
+        self.function_of_kind(name, FunctionObjectKind::Builtin(func), true)
+    }
+
+    /// Generate Value for the function given by the embedder. See ``VM::native_function``.
+    pub fn native_function(&mut self, name: impl Into<String>, id: usize) -> Value {
+        self.function_of_kind(name, FunctionObjectKind::Native(id), true)
+    }
+
+    /// Generate Value for a function calling ``target`` with ``this`` and ``args``. See
+    /// ``FunctionObjectKind::Bound``.
+    pub fn bound_function(&mut self, target: Value, this: Value, args: Vec<Value>) -> Value {
+        let target_info = target.as_function();
+        let name = format!(
+            "bound {}",
+            target_info.name.as_ref().map_or("", |s| s.as_str())
+        );
+        // Functions other than bound ones have no length property, so count the parameters.
+        let target_length = match (target.get_property("length"), &target_info.kind) {
+            (Value::Number(length), _) => length,
+            (_, FunctionObjectKind::User { info, .. }) => {
+                info.params.iter().filter(|param| !param.rest_param).count() as f64
+            }
+            _ => 0.0,
+        };
+        let length = (target_length - args.len() as f64).max(0.0);
+        let constructible = target_info.constructible;
+        let func = self.function_of_kind(
+            name,
+            FunctionObjectKind::Bound { target, this, args },
+            constructible,
+        );
+        func.get_object_info().property.insert(
+            "length".to_string(),
+            Property::Data(DataProperty {
+                val: Value::Number(length),
+                writable: false,
+                enumerable: false,
+                configurable: true,
+            }),
+        );
+        func
+    }
+
+    fn function_of_kind(
+        &mut self,
+        name: impl Into<String>,
+        kind: FunctionObjectKind,
+        constructible: bool,
     ) -> Value {
         let name: String = name.into();
         let name_prop = self.string(name.clone());
         Value::Object(self.alloc(ObjectInfo {
             kind: ObjectKind::Function(FunctionObjectInfo {
                 name: Some(name),
-                kind: FunctionObjectKind::Builtin(func),
+                kind,
+                constructible,
             }),
             prototype: self.object_prototypes.function,
             property: make_property_map!(
@@ -209,11 +395,63 @@ impl Factory {
         }))
     }
 
+    pub fn map(&mut self) -> Value {
+        Value::Object(self.alloc(ObjectInfo {
+            kind: ObjectKind::Map(MapObjectInfo::new()),
+            prototype: self.object_prototypes.map,
+            property: make_property_map!(),
+            sym_property: FxHashMap::default(),
+        }))
+    }
+
+    pub fn set(&mut self) -> Value {
+        Value::Object(self.alloc(ObjectInfo {
+            kind: ObjectKind::Set(MapObjectInfo::new()),
+            prototype: self.object_prototypes.set,
+            property: make_property_map!(),
+            sym_property: FxHashMap::default(),
+        }))
+    }
+
+    /// Generate Value for a proxy of ``target``. Its prototype is given by ``target`` (or the
+    /// ``getPrototypeOf`` trap), so the one in ``ObjectInfo`` is null.
+    pub fn proxy(&mut self, target: Value, handler: Value) -> Value {
+        self.proxy_object(ProxyObjectInfo {
+            target,
+            handler,
+            access_check: None,
+        })
+    }
+
+    /// Generate Value for a guarded object viewing ``target``. See ``VM::guarded_object``.
+    pub fn guarded_object(&mut self, target: Value, access_check: usize) -> Value {
+        self.proxy_object(ProxyObjectInfo {
+            target,
+            handler: Value::undefined(),
+            access_check: Some(access_check),
+        })
+    }
+
+    fn proxy_object(&mut self, info: ProxyObjectInfo) -> Value {
+        Value::Object(self.alloc(ObjectInfo {
+            kind: ObjectKind::Proxy(info),
+            prototype: Value::null(),
+            property: make_property_map!(),
+            sym_property: FxHashMap::default(),
+        }))
+    }
+
     pub fn error(&mut self, message: impl Into<String>) -> Value {
+        self.native_error(ErrorType::Error, message)
+    }
+
+    /// Generate an instance of Error or a native error. Use ``VM::create_error`` to also record
+    /// the stack.
+    pub fn native_error(&mut self, error_type: ErrorType, message: impl Into<String>) -> Value {
         let message = self.string(message.into());
         Value::Object(self.alloc(ObjectInfo {
             kind: ObjectKind::Error(ErrorObjectInfo::new()),
-            prototype: self.object_prototypes.error,
+            prototype: self.object_prototypes.native_error(error_type),
             property: make_property_map!(
                 message => true, false, true: message
             ),
@@ -227,9 +465,17 @@ impl Factory {
         constructor_func: BuiltinFuncTy,
         prototype: Value,
     ) -> Value {
-        let ary = self.builtin_function(constructor_name, constructor_func);
-        ary.set_property("prototype", prototype);
-        ary.get_property("prototype").set_constructor(ary);
+        let ary = self.builtin_constructor(constructor_name, constructor_func);
+        ary.get_object_info().property.insert(
+            "prototype".to_string(),
+            Property::Data(DataProperty {
+                val: prototype,
+                writable: false,
+                enumerable: false,
+                configurable: false,
+            }),
+        );
+        prototype.set_constructor(ary);
         ary
     }
 }
@@ -250,6 +496,7 @@ impl Factory {
                 record
             }),
             outer,
+            immutable_bindings: FxHashSet::default(),
         };
 
         LexicalEnvironmentRef(self.alloc(env))
@@ -299,33 +546,91 @@ impl Factory {
                     for name in &user_func.var_names {
                         record.insert(name.clone(), Value::undefined());
                     }
-                    for (i, FunctionParameter { name, rest_param }) in
-                        user_func.params.iter().enumerate()
-                    {
-                        record.insert(
-                            name.clone(),
-                            if *rest_param {
-                                self.array(
-                                    (*args)
-                                        .get(i..)
-                                        .unwrap_or(&vec![])
-                                        .iter()
-                                        .map(|elem| Property::new_data_simple(*elem))
-                                        .collect::<Vec<Property>>(),
-                                )
-                            } else {
-                                *args.get(i).unwrap_or(&Value::undefined())
-                            },
-                        );
+                    for (i, param) in user_func.params.iter().enumerate() {
+                        if param.register.is_none() {
+                            let val = self.argument(param, i, args);
+                            record.insert(param.name.clone(), val);
+                        }
                     }
                     record
                 },
                 this,
             },
             outer: outer_env,
+            immutable_bindings: FxHashSet::default(),
         };
 
         LexicalEnvironmentRef(self.alloc(env))
     }
 
-}
\ No newline at end of file
+    /// The registers of a call of ``user_func``, with the parameters stored in them.
+    pub fn create_registers(&mut self, user_func: FuncInfoRef, args: &[Value]) -> Vec<Value> {
+        let mut registers = vec![Value::undefined(); user_func.register_names.len()];
+        for (i, param) in user_func.params.iter().enumerate() {
+            if let Some(index) = param.register {
+                registers[index] = self.argument(param, i, args);
+            }
+        }
+        registers
+    }
+
+    /// The value of ``param``, the ``i``th parameter, given ``args``.
+    fn argument(&mut self, param: &FunctionParameter, i: usize, args: &[Value]) -> Value {
+        if param.rest_param {
+            self.array(
+                args.get(i..)
+                    .unwrap_or(&[])
+                    .iter()
+                    .map(|elem| Property::new_data_simple(*elem))
+                    .collect::<Vec<Property>>(),
+            )
+        } else {
+            *args.get(i).unwrap_or(&Value::undefined())
+        }
+    }
+}
+#[test]
+fn string_cache() {
+    use crate::gc::MemoryAllocator;
+    let mut factory = Factory::new(MemoryAllocator::new(), ObjectPrototypes::dummy());
+    assert_eq!(factory.string(""), factory.string(""));
+    assert_eq!(factory.string("a"), factory.string("a"));
+    assert_eq!(factory.string("\x7f"), factory.string("\x7f"));
+    assert_ne!(factory.string("死"), factory.string("死"));
+    assert_ne!(factory.string("a"), factory.string("b"));
+    assert_ne!(factory.string("ab"), factory.string("ab"));
+}
+
+#[test]
+fn frozen_namespace() {
+    use crate::gc::MemoryAllocator;
+    let mut factory = Factory::new(MemoryAllocator::new(), ObjectPrototypes::dummy());
+    let namespace = factory.frozen_namespace(&[
+        ("A", NamespaceMember::Number(1.0)),
+        ("B", NamespaceMember::String("x")),
+        (
+            "C",
+            NamespaceMember::Namespace(&[
+                ("D", NamespaceMember::Boolean(true)),
+                ("E", NamespaceMember::String("x")),
+            ]),
+        ),
+    ]);
+    let property = &namespace.get_object_info().property;
+    assert_eq!(
+        property["A"],
+        Property::Data(DataProperty {
+            val: Value::Number(1.0),
+            writable: false,
+            enumerable: true,
+            configurable: false,
+        })
+    );
+    let nested = property["C"].as_data().val;
+    assert!(!nested.get_object_info().property["D"].as_data().writable);
+    assert_eq!(nested.get_property("D"), Value::bool(true));
+    assert_eq!(namespace.get_property("B"), nested.get_property("E"));
+
+    namespace.set_property("A", Value::Number(2.0));
+    assert_eq!(namespace.get_property("A"), Value::Number(1.0));
+}
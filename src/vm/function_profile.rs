@@ -0,0 +1,169 @@
+//! Per-function execution time and allocation profile.
+//! Unlike `Profiler`, this only does work when a function is entered or left, so it is cheap
+//! enough to leave on for whole programs.
+
+use crate::vm::factory::FunctionId;
+use rustc_hash::FxHashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, Default)]
+pub struct FunctionProfileEntry {
+    pub name: String,
+    pub calls: usize,
+    /// Time spent in the function including its callees.
+    /// Recursive calls are counted once.
+    pub total_time: Duration,
+    /// Time spent in the function itself.
+    pub self_time: Duration,
+    /// Bytes allocated while the function (including its callees) ran.
+    pub total_allocated: usize,
+    /// Bytes allocated by the function itself.
+    pub self_allocated: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Frame {
+    func_id: FunctionId,
+    /// Depth of the context stack when the function was entered.
+    depth: usize,
+    start: Instant,
+    allocated_at_start: usize,
+    child_time: Duration,
+    child_allocated: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FunctionProfiler {
+    frames: Vec<Frame>,
+    /// Number of frames on ``frames`` for each function, to count recursive calls once.
+    active: FxHashMap<FunctionId, usize>,
+    entries: FxHashMap<FunctionId, FunctionProfileEntry>,
+}
+
+impl FunctionProfiler {
+    pub fn new() -> Self {
+        FunctionProfiler::default()
+    }
+
+    /// Record that the function ``func_id`` was entered.
+    /// ``allocated`` is the total number of bytes allocated so far.
+    pub fn enter(&mut self, func_id: FunctionId, name: &str, depth: usize, allocated: usize) {
+        let entry = self
+            .entries
+            .entry(func_id)
+            .or_insert_with(|| FunctionProfileEntry {
+                name: name.to_string(),
+                ..FunctionProfileEntry::default()
+            });
+        entry.calls += 1;
+        *self.active.entry(func_id).or_insert(0) += 1;
+        self.frames.push(Frame {
+            func_id,
+            depth,
+            start: Instant::now(),
+            allocated_at_start: allocated,
+            child_time: Duration::from_secs(0),
+            child_allocated: 0,
+        });
+    }
+
+    /// Record that the functions entered deeper than ``depth`` were left, either by returning
+    /// or by unwinding.
+    pub fn leave(&mut self, depth: usize, allocated: usize) {
+        while self
+            .frames
+            .last()
+            .map_or(false, |frame| frame.depth > depth)
+        {
+            self.pop_frame(allocated);
+        }
+    }
+
+    /// Leave all the functions still running.
+    pub fn finish(&mut self, allocated: usize) {
+        while !self.frames.is_empty() {
+            self.pop_frame(allocated);
+        }
+    }
+
+    fn pop_frame(&mut self, allocated: usize) {
+        let frame = self.frames.pop().unwrap();
+        let time = frame.start.elapsed();
+        let bytes = allocated - frame.allocated_at_start;
+
+        let active = self.active.get_mut(&frame.func_id).unwrap();
+        *active -= 1;
+        let is_outermost = *active == 0;
+
+        let entry = self.entries.get_mut(&frame.func_id).unwrap();
+        entry.self_time += time.checked_sub(frame.child_time).unwrap_or_default();
+        entry.self_allocated += bytes - frame.child_allocated;
+        if is_outermost {
+            entry.total_time += time;
+            entry.total_allocated += bytes;
+        }
+
+        if let Some(parent) = self.frames.last_mut() {
+            parent.child_time += time;
+            parent.child_allocated += bytes;
+        }
+    }
+
+    /// Get the entries sorted by self time, longest first.
+    pub fn entries(&self) -> Vec<&FunctionProfileEntry> {
+        let mut entries: Vec<&FunctionProfileEntry> = self.entries.values().collect();
+        entries.sort_by(|a, b| b.self_time.cmp(&a.self_time).then(a.name.cmp(&b.name)));
+        entries
+    }
+
+    pub fn print_summary(&self) {
+        println!("# function profile");
+        println!(
+            "{:>8} {:>12} {:>12} {:>14} {:>14}  function",
+            "calls", "total us", "self us", "total bytes", "self bytes"
+        );
+        for entry in self.entries() {
+            println!(
+                "{:>8} {:>12} {:>12} {:>14} {:>14}  {}",
+                entry.calls,
+                entry.total_time.as_micros(),
+                entry.self_time.as_micros(),
+                entry.total_allocated,
+                entry.self_allocated,
+                entry.name
+            );
+        }
+    }
+}
+
+#[test]
+fn function_profile() {
+    let mut profiler = FunctionProfiler::new();
+    profiler.enter(FunctionId(0), "<script>", 0, 0);
+    profiler.enter(FunctionId(1), "f", 1, 10);
+    // Recursive call
+    profiler.enter(FunctionId(1), "f", 2, 20);
+    profiler.enter(FunctionId(2), "g", 3, 25);
+    // g throws, and the exception is caught in the outer f.
+    profiler.leave(1, 40);
+    profiler.leave(0, 50);
+    profiler.enter(FunctionId(2), "g", 1, 50);
+    profiler.finish(100);
+
+    let entries = profiler.entries();
+    let entry = |name| *entries.iter().find(|entry| entry.name == name).unwrap();
+    let script = entry("<script>");
+    let f = entry("f");
+    let g = entry("g");
+    assert_eq!((script.calls, f.calls, g.calls), (1, 2, 2));
+    assert_eq!((script.total_allocated, script.self_allocated), (100, 10));
+    assert_eq!(
+        (f.total_allocated, f.self_allocated),
+        (40, 20 - 10 + 25 - 20 + 50 - 40)
+    );
+    assert_eq!(
+        (g.total_allocated, g.self_allocated),
+        (40 - 25 + 100 - 50, 15 + 50)
+    );
+    assert!(script.total_time >= f.total_time);
+}
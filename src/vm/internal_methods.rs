@@ -0,0 +1,165 @@
+//! The essential internal methods of objects. The VM and the builtins operate on objects only
+//! through ``InternalMethods``, so that proxies intercept every operation the same way.
+//! https://tc39.github.io/ecma262/#sec-object-internal-methods-and-internal-slots
+
+use crate::builtins::proxy::ProxyObject;
+use crate::vm::{
+    access_check::GuardedObject,
+    error::RuntimeError,
+    jsvalue::value::{Property, PropertyDescriptor, SetResult, Value},
+    vm::{VMValueResult, VM},
+};
+
+/// The internal methods of the object ``obj`` given to each method. Property keys are strings,
+/// symbols or, for array elements, numbers.
+pub trait InternalMethods {
+    /// \[\[GetPrototypeOf\]\]
+    fn get_prototype_of(&self, vm: &mut VM, obj: Value) -> Result<Value, RuntimeError>;
+
+    /// \[\[GetOwnProperty\]\]
+    fn get_own_property(
+        &self,
+        vm: &mut VM,
+        obj: Value,
+        key: Value,
+    ) -> Result<Option<Property>, RuntimeError>;
+
+    /// \[\[DefineOwnProperty\]\]. False if the property cannot be changed so.
+    fn define_own_property(
+        &self,
+        vm: &mut VM,
+        obj: Value,
+        key: Value,
+        desc: PropertyDescriptor,
+    ) -> Result<bool, RuntimeError>;
+
+    /// \[\[HasProperty\]\]
+    fn has_property(&self, vm: &mut VM, obj: Value, key: Value) -> Result<bool, RuntimeError>;
+
+    /// \[\[Get\]\]. An accessor property is given as it is, and the VM calls its getter with the
+    /// object it was got from as this.
+    fn get(&self, vm: &mut VM, obj: Value, key: Value) -> Result<Property, RuntimeError>;
+
+    /// \[\[Set\]\]
+    fn set(
+        &self,
+        vm: &mut VM,
+        obj: Value,
+        key: Value,
+        val: Value,
+    ) -> Result<SetResult, RuntimeError>;
+
+    /// \[\[Delete\]\]. False if the property is not configurable.
+    fn delete(&self, vm: &mut VM, obj: Value, key: Value) -> Result<bool, RuntimeError>;
+
+    /// \[\[OwnPropertyKeys\]\]
+    fn own_property_keys(&self, vm: &mut VM, obj: Value) -> Result<Vec<Value>, RuntimeError>;
+
+    /// \[\[Call\]\]. Only called on callable objects.
+    fn call(&self, vm: &mut VM, obj: Value, args: &[Value], this: Value) -> VMValueResult;
+
+    /// \[\[Construct\]\]. Only called on constructors.
+    fn construct(&self, vm: &mut VM, obj: Value, args: &[Value]) -> VMValueResult;
+}
+
+/// The internal methods of every object other than proxies. Arrays, whose elements and length
+/// are special, are handled by ``ObjectInfo``.
+pub struct OrdinaryObject;
+
+/// Get the internal methods of the object ``obj``.
+pub fn internal_methods(obj: Value) -> &'static dyn InternalMethods {
+    if obj.is_proxy_object() {
+        if obj.as_proxy().access_check.is_some() {
+            &GuardedObject
+        } else {
+            &ProxyObject
+        }
+    } else {
+        &OrdinaryObject
+    }
+}
+
+impl InternalMethods for OrdinaryObject {
+    fn get_prototype_of(&self, _vm: &mut VM, obj: Value) -> Result<Value, RuntimeError> {
+        Ok(obj.get_object_info().get_prototype())
+    }
+
+    fn get_own_property(
+        &self,
+        _vm: &mut VM,
+        obj: Value,
+        key: Value,
+    ) -> Result<Option<Property>, RuntimeError> {
+        Ok(obj.get_object_info().get_own_property(key))
+    }
+
+    fn define_own_property(
+        &self,
+        vm: &mut VM,
+        obj: Value,
+        key: Value,
+        desc: PropertyDescriptor,
+    ) -> Result<bool, RuntimeError> {
+        obj.get_object_info()
+            .define_own_property(&mut vm.factory.memory_allocator, key, desc)
+    }
+
+    /// https://tc39.github.io/ecma262/#sec-ordinaryhasproperty
+    fn has_property(&self, vm: &mut VM, obj: Value, key: Value) -> Result<bool, RuntimeError> {
+        let info = obj.get_object_info();
+        if info.get_own_property(key).is_some() {
+            return Ok(true);
+        }
+        let prototype = info.get_prototype();
+        if !prototype.is_object() {
+            return Ok(false);
+        }
+        internal_methods(prototype).has_property(vm, prototype, key)
+    }
+
+    fn get(&self, vm: &mut VM, obj: Value, key: Value) -> Result<Property, RuntimeError> {
+        let info = obj.get_object_info();
+        if let Some(prop) = info.get_own_property_for_get(&mut vm.factory, key) {
+            return Ok(prop);
+        }
+        let prototype = info.get_prototype();
+        if !prototype.is_object() {
+            return Ok(Property::new_data_simple(Value::undefined()));
+        }
+        internal_methods(prototype).get(vm, prototype, key)
+    }
+
+    /// Inherited accessors and read-only properties are looked up in ordinary objects only.
+    fn set(
+        &self,
+        vm: &mut VM,
+        obj: Value,
+        key: Value,
+        val: Value,
+    ) -> Result<SetResult, RuntimeError> {
+        obj.get_object_info()
+            .set_property_by_value(&mut vm.factory.memory_allocator, key, val)
+    }
+
+    fn delete(&self, _vm: &mut VM, obj: Value, key: Value) -> Result<bool, RuntimeError> {
+        Ok(obj.get_object_info().delete_property_by_value(key))
+    }
+
+    /// Only the keys which are strings, in the order of ``ObjectInfo::own_properties``.
+    fn own_property_keys(&self, vm: &mut VM, obj: Value) -> Result<Vec<Value>, RuntimeError> {
+        Ok(obj
+            .get_object_info()
+            .own_properties()
+            .into_iter()
+            .map(|(key, _)| vm.factory.string(key))
+            .collect())
+    }
+
+    fn call(&self, vm: &mut VM, obj: Value, args: &[Value], this: Value) -> VMValueResult {
+        vm.call_function(obj, args, this)
+    }
+
+    fn construct(&self, vm: &mut VM, obj: Value, args: &[Value]) -> VMValueResult {
+        vm.construct(obj, args)
+    }
+}
@@ -0,0 +1,115 @@
+//! Termination of scripts which run too long. ``VM::set_timeout`` limits the time of each run,
+//! and ``VM::interrupt_handle`` gives a handle with which another thread can stop the script.
+//! A terminated script gets ``ErrorKind::ExecutionTerminated``, which try/catch and finally
+//! clauses do not see, so that a script can not keep itself running.
+//!
+//! ``VM::set_fuel`` meters scripts by the number of instructions instead. A script which runs
+//! out of fuel is paused with ``ErrorKind::OutOfFuel``, and ``VM::resume`` continues it after
+//! ``VM::add_fuel``. The frames run by builtins (e.g. the callback of ``Array.prototype.map``)
+//! can not be paused, so running out of fuel in them terminates the script.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant};
+
+/// The number of instructions run between two checks, since reading the clock on each
+/// instruction would be slow.
+const CHECK_INTERVAL: u32 = 1024;
+
+/// Terminates the script the VM is running. It may be sent to and triggered from another
+/// thread.
+#[derive(Clone, Debug, Default)]
+pub struct InterruptHandle(Arc<AtomicBool>);
+
+impl InterruptHandle {
+    /// Terminate the script soon. If the VM is not running, the next script it runs is
+    /// terminated.
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug)]
+pub struct Interrupt {
+    handle: InterruptHandle,
+    pub timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    /// The number of instructions the script may run, or None if not metered.
+    pub fuel: Option<u64>,
+    /// The number of instructions run since the fuel was set.
+    pub fuel_consumed: u64,
+    /// The number of nested ``VM::run``s. The deadline is set when the outermost one starts.
+    depth: usize,
+    countdown: u32,
+}
+
+impl Interrupt {
+    pub fn new() -> Self {
+        Interrupt {
+            handle: InterruptHandle::default(),
+            timeout: None,
+            deadline: None,
+            fuel: None,
+            fuel_consumed: 0,
+            depth: 0,
+            countdown: CHECK_INTERVAL,
+        }
+    }
+
+    pub fn handle(&self) -> InterruptHandle {
+        self.handle.clone()
+    }
+
+    pub fn enter(&mut self) {
+        if self.depth == 0 {
+            self.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        }
+        self.depth += 1;
+    }
+
+    pub fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Whether any script is being run.
+    pub fn is_running(&self) -> bool {
+        self.depth > 0
+    }
+
+    /// Whether the frames being run are run by a builtin.
+    pub fn is_nested(&self) -> bool {
+        self.depth > 1
+    }
+
+    /// Consume the fuel for an instruction. Return false if there is none left.
+    pub fn consume_fuel(&mut self) -> bool {
+        match self.fuel {
+            Some(0) => false,
+            Some(ref mut fuel) => {
+                *fuel -= 1;
+                self.fuel_consumed += 1;
+                true
+            }
+            None => true,
+        }
+    }
+
+    /// Whether the script must be terminated, called on each instruction. The request of the
+    /// handle is consumed, so that the VM can run scripts again.
+    pub fn check(&mut self) -> bool {
+        self.countdown -= 1;
+        if self.countdown > 0 {
+            return false;
+        }
+        self.countdown = CHECK_INTERVAL;
+        if (self.handle.0).swap(false, Ordering::Relaxed) {
+            return true;
+        }
+        match self.deadline {
+            Some(deadline) => Instant::now() >= deadline,
+            None => false,
+        }
+    }
+}
@@ -32,22 +32,43 @@ impl ArrayObjectInfo {
         self.elems[idx]
     }
 
-    pub fn set_element(&mut self, idx: usize, val_: Value) -> Option<Value> {
+    /// Get the element at ``idx``. None if it is a hole or out of range.
+    pub fn get_own_element(&self, idx: usize) -> Option<Property> {
+        match self.elems.get(idx) {
+            Some(Property::Data(DataProperty { val, .. })) if val.is_empty() => None,
+            elem => elem.copied(),
+        }
+    }
+
+    /// The ``length`` property, which is always writable.
+    pub fn length_property(&self) -> Property {
+        Property::Data(DataProperty {
+            val: Value::Number(self.elems.len() as f64),
+            writable: true,
+            enumerable: false,
+            configurable: false,
+        })
+    }
+
+    pub fn set_element(&mut self, idx: usize, val_: Value) -> SetResult {
         // Extend
         if idx >= self.elems.len() {
             self.set_length(idx + 1);
         }
 
         match self.elems[idx] {
+            Property::Data(DataProperty {
+                writable: false, ..
+            }) => SetResult::Rejected,
             Property::Data(DataProperty { ref mut val, .. }) => {
                 *val = val_;
-                None
+                SetResult::Done
             }
             Property::Accessor(AccessorProperty { set, .. }) => {
                 if set.is_undefined() {
-                    None
+                    SetResult::Rejected
                 } else {
-                    Some(set)
+                    SetResult::CallSetter(set)
                 }
             }
         }
@@ -78,19 +99,31 @@ impl ArrayObjectInfo {
 impl ArrayObjectInfo {
     /// https://tc39.github.io/ecma262/#sec-array.prototype.join
     pub fn join(&self, separator: Option<String>) -> String {
+        self.join_within(separator, usize::MAX).unwrap()
+    }
+
+    /// Same as ``join``, but gives up and returns None once the result gets longer than
+    /// ``max_len``.
+    pub fn join_within(&self, separator: Option<String>, max_len: usize) -> Option<String> {
         let separator = separator.unwrap_or(",".to_string());
         let separator_str = separator.as_str();
         let mut res = "".to_string();
         for (i, elem) in self.elems.iter().enumerate() {
+            if i != 0 {
+                res.push_str(separator_str);
+            }
             if let Some(data) = elem.get_data() {
-                res += &(data.val.to_string()
-                    + if self.elems.len() - 1 != i {
-                        separator_str
-                    } else {
-                        ""
-                    });
+                match data.val {
+                    // Holes as well as undefined and null are empty.
+                    val if val.is_empty() || val.is_undefined() || val.is_null() => {}
+                    Value::Number(n) => res.push_str(&number_to_str(n)),
+                    val => res.push_str(&val.to_string()),
+                }
+            }
+            if res.len() > max_len {
+                return None;
             }
         }
-        res
+        Some(res)
     }
 }
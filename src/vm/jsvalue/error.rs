@@ -10,3 +10,26 @@ impl ErrorObjectInfo {
         }
     }
 }
+
+/// Error and the native errors, each of which has its own prototype inheriting Error.prototype.
+/// https://tc39.github.io/ecma262/#sec-native-error-types-used-in-this-standard
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorType {
+    Error,
+    TypeError,
+    ReferenceError,
+    RangeError,
+    SyntaxError,
+}
+
+impl ErrorType {
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorType::Error => "Error",
+            ErrorType::TypeError => "TypeError",
+            ErrorType::ReferenceError => "ReferenceError",
+            ErrorType::RangeError => "RangeError",
+            ErrorType::SyntaxError => "SyntaxError",
+        }
+    }
+}
@@ -3,11 +3,17 @@ use crate::builtin::BuiltinFuncTy;
 use crate::bytecode_gen::ByteCode;
 use crate::vm::exec_context::LexicalEnvironmentRef;
 use crate::vm::factory::{Factory, FunctionId};
+use crate::vm::jsvalue::value::Value;
+use crate::vm::source_position::PositionTable;
+use rustc_hash::FxHashMap;
+use std::cell::RefCell;
 
 #[derive(Clone, Debug)]
 pub struct FunctionObjectInfo {
     pub name: Option<String>,
     pub kind: FunctionObjectKind,
+    /// Whether the function has the internal method \[\[Construct\]\], i.e. can be called by new
+    pub constructible: bool,
 }
 
 #[derive(Clone)]
@@ -18,6 +24,18 @@ pub enum FunctionObjectKind {
         info: FuncInfoRef,
     },
     Builtin(BuiltinFuncTy),
+    /// Function given by the embedder. The index in ``VM::native_functions``.
+    Native(usize),
+    /// Function created by Function.prototype.bind, which calls ``target`` with ``this`` and
+    /// ``args`` followed by the arguments given to it.
+    Bound {
+        /// Internal slot \[\[BoundTargetFunction\]\]
+        target: Value,
+        /// Internal slot \[\[BoundThis\]\]
+        this: Value,
+        /// Internal slot \[\[BoundArguments\]\]
+        args: Vec<Value>,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -36,23 +54,42 @@ pub struct UserFunctionInfo {
     /// Varaible declared names
     pub var_names: Vec<String>,
 
+    /// Names of the variables stored in the registers of the frame, by their indexes. They are
+    /// not bound in the environments
+    pub register_names: Vec<String>,
+
     /// Lexically declared names
     pub lex_names: Vec<String>,
 
     /// Declared functions to initialize
     pub func_decls: Vec<FuncInfoRef>,
 
+    /// Declared names referenced by inner functions
+    pub captured_names: Vec<String>,
+
     /// Bytecode to execute
     pub code: ByteCode,
 
-    /// Exception table
-    pub exception_table: Vec<Exception>,
+    /// Exception handler table, innermost handlers first
+    pub exception_table: Vec<ExceptionHandler>,
+
+    /// Lines and columns of the instructions. Empty if compiled without the source text
+    pub positions: PositionTable,
+
+    /// Line and column of the function body. None if compiled without the source text
+    pub location: Option<(usize, usize)>,
 
     /// Represent if constructible or not
     pub constructible: bool,
 
     /// Internal slot \[\[ThisMode\]\]
     pub this_mode: ThisMode,
+
+    /// Internal slot \[\[Strict\]\]
+    pub strict: bool,
+
+    /// Callees seen at the call sites in ``code``
+    pub call_caches: CallCaches,
 }
 
 #[derive(Clone, Debug, Copy)]
@@ -89,6 +126,71 @@ impl std::ops::Deref for FuncInfoRef {
     }
 }
 
+/// Inline caches of the call sites of a function, keyed by the pc of the call instruction.
+/// The cached callees are not traced, so the VM clears the caches after every sweep of the GC.
+#[derive(Clone, Debug, Default)]
+pub struct CallCaches(RefCell<FxHashMap<usize, CallSiteCache>>);
+
+#[derive(Clone, Copy, Debug)]
+pub enum CallSiteCache {
+    /// Every call from the site has called ``callee``, the closure of ``info`` in ``outer_env``.
+    Monomorphic {
+        callee: Value,
+        info: FuncInfoRef,
+        outer_env: Option<LexicalEnvironmentRef>,
+    },
+    /// The site has called different functions, or a builtin or bound function.
+    Megamorphic,
+}
+
+impl CallCaches {
+    pub fn get(&self, pc: usize) -> Option<CallSiteCache> {
+        self.0.borrow().get(&pc).map(|cache| *cache)
+    }
+
+    /// Record that the site at ``pc`` called ``callee``, which is not the cached callee.
+    pub fn record(&self, pc: usize, callee: Value) {
+        let mut caches = self.0.borrow_mut();
+        let cache = match caches.get(&pc) {
+            None if callee.is_function_object() => match callee.as_function().kind {
+                FunctionObjectKind::User { info, outer_env } => {
+                    log::trace!("call site {}: uninitialized -> monomorphic", pc);
+                    CallSiteCache::Monomorphic {
+                        callee,
+                        info,
+                        outer_env,
+                    }
+                }
+                FunctionObjectKind::Builtin(_)
+                | FunctionObjectKind::Native(_)
+                | FunctionObjectKind::Bound { .. } => {
+                    log::trace!("call site {}: uninitialized -> megamorphic", pc);
+                    CallSiteCache::Megamorphic
+                }
+            },
+            // Not a function, which throws a TypeError.
+            None => return,
+            Some(_) => {
+                log::trace!("call site {}: monomorphic -> megamorphic", pc);
+                CallSiteCache::Megamorphic
+            }
+        };
+        caches.insert(pc, cache);
+    }
+
+    /// The function always called from the site at ``pc``, e.g. to decide to inline it.
+    pub fn monomorphic_target(&self, pc: usize) -> Option<FuncInfoRef> {
+        match self.get(pc)? {
+            CallSiteCache::Monomorphic { info, .. } => Some(info),
+            CallSiteCache::Megamorphic => None,
+        }
+    }
+
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear()
+    }
+}
+
 #[derive(Clone, Debug, Copy, PartialEq)]
 pub enum ThisMode {
     Lexical,
@@ -100,21 +202,34 @@ pub enum ThisMode {
 pub struct FunctionParameter {
     pub name: String,
     pub rest_param: bool,
+    /// The register the parameter is stored in, if it is not bound in the environment
+    pub register: Option<usize>,
 }
 
-#[derive(Clone, Debug)]
-pub struct Exception {
-    /// Throws may happen in bytecode's range of [start, end)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExceptionHandler {
+    /// The handler covers the instructions in bytecode's range of [start, end)
     pub start: usize,
     pub end: usize,
 
-    /// Kind of throw's destination
-    pub dst_kind: DestinationKind,
+    /// Position of the first instruction of the handler
+    pub handler: usize,
+
+    pub kind: HandlerKind,
+
+    /// Number of lexical environments pushed when entering the try statement
+    pub env_depth: usize,
+
+    /// Number of finally clauses being run when entering the try statement
+    pub finally_depth: usize,
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub enum DestinationKind {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HandlerKind {
+    /// A catch clause, which receives the thrown value.
     Catch,
+    /// A finally clause, run when the covered instructions are left by a throw or a return, and
+    /// resuming it at its end.
     Finally,
 }
 
@@ -126,12 +241,18 @@ impl UserFunctionInfo {
             module_func_id,
             params: vec![],
             var_names: vec![],
+            register_names: vec![],
             lex_names: vec![],
             func_decls: vec![],
+            captured_names: vec![],
             constructible: false,
             this_mode: ThisMode::Global,
+            strict: false,
             code: vec![0x0c, 0x28], // [PUSH_UNDEFINED][RETURN]
             exception_table: vec![],
+            positions: PositionTable::default(),
+            location: None,
+            call_caches: CallCaches::default(),
         }
     }
 
@@ -142,12 +263,18 @@ impl UserFunctionInfo {
             module_func_id: FunctionId::default(),
             params: vec![],
             var_names: vec![],
+            register_names: vec![],
             lex_names: vec![],
             func_decls: vec![],
+            captured_names: vec![],
             constructible: false,
             this_mode: ThisMode::Global,
+            strict: false,
             code: vec![0x0c, 0x28], // [PUSH_UNDEFINED][RETURN]
             exception_table: vec![],
+            positions: PositionTable::default(),
+            location: None,
+            call_caches: CallCaches::default(),
         }
     }
 }
@@ -171,6 +298,8 @@ impl ::std::fmt::Debug for FunctionObjectKind {
             match self {
                 FunctionObjectKind::User { info, .. } => format!("{:?}", info),
                 FunctionObjectKind::Builtin(_) => "[BuiltinFunction]".to_string(),
+                FunctionObjectKind::Native(_) => "[NativeFunction]".to_string(),
+                FunctionObjectKind::Bound { .. } => "[BoundFunction]".to_string(),
             }
         )
     }
@@ -0,0 +1,172 @@
+use super::value::*;
+use rustc_hash::FxHashMap;
+
+/// Internal slot \[\[MapData\]\] (or \[\[SetData\]\] for Set objects, whose entries have the same
+/// key and value).
+/// https://tc39.github.io/ecma262/#sec-map-objects
+#[derive(Clone, Debug)]
+pub struct MapObjectInfo {
+    /// Entries in insertion order. A deleted entry is left as `None` while forEach iterates over
+    /// the entries so that the index of every live entry does not change, which lets forEach
+    /// visit entries added during the iteration and skip entries deleted during the iteration.
+    entries: Vec<Option<(Value, Value)>>,
+    /// The index in ``entries`` of each key.
+    index: FxHashMap<MapKey, usize>,
+    /// The number of forEach iterating over the entries. The deleted entries are removed only
+    /// when it is zero.
+    iterations: usize,
+}
+
+/// A key of ``MapObjectInfo::index``. Two keys are equal iff the values are SameValueZero.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum MapKey {
+    Number(u64),
+    Bool(u8),
+    String(String),
+    Object(usize),
+    Other(i32),
+}
+
+impl MapKey {
+    fn new(key: Value) -> Self {
+        match key {
+            Value::Number(n) if n.is_nan() => MapKey::Number(std::f64::NAN.to_bits()),
+            Value::Number(n) if n == 0.0 => MapKey::Number(0f64.to_bits()),
+            Value::Number(n) => MapKey::Number(n.to_bits()),
+            Value::Bool(b) => MapKey::Bool(b),
+            Value::String(_) => MapKey::String(key.into_str().to_string()),
+            Value::Object(obj) => MapKey::Object(obj as usize),
+            Value::Other(other) => MapKey::Other(other),
+        }
+    }
+}
+
+impl MapObjectInfo {
+    pub fn new() -> Self {
+        MapObjectInfo {
+            entries: vec![],
+            index: FxHashMap::default(),
+            iterations: 0,
+        }
+    }
+
+    /// Number of live entries.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Number of slots including deleted ones. Use this as the upper bound of an iteration.
+    #[inline]
+    pub fn slots_len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the 'idx'th entry, or None if it has been deleted.
+    pub fn get_entry(&self, idx: usize) -> Option<(Value, Value)> {
+        self.entries.get(idx).and_then(|entry| *entry)
+    }
+
+    fn find(&self, key: Value) -> Option<usize> {
+        self.index.get(&MapKey::new(key)).map(|idx| *idx)
+    }
+
+    pub fn get(&self, key: Value) -> Option<Value> {
+        self.find(key).map(|idx| self.entries[idx].unwrap().1)
+    }
+
+    pub fn has(&self, key: Value) -> bool {
+        self.find(key).is_some()
+    }
+
+    pub fn set(&mut self, key: Value, val: Value) {
+        // -0 is normalized to +0.
+        let key = match key {
+            Value::Number(n) if n == 0.0 => Value::Number(0.0),
+            _ => key,
+        };
+
+        if let Some(idx) = self.find(key) {
+            self.entries[idx] = Some((key, val));
+            return;
+        }
+
+        self.index.insert(MapKey::new(key), self.entries.len());
+        self.entries.push(Some((key, val)));
+    }
+
+    pub fn delete(&mut self, key: Value) -> bool {
+        match self.index.remove(&MapKey::new(key)) {
+            Some(idx) => {
+                self.entries[idx] = None;
+                self.compact();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for entry in &mut self.entries {
+            *entry = None;
+        }
+        self.index.clear();
+        self.compact();
+    }
+
+    /// Called before forEach iterates over the entries by their indices.
+    pub fn begin_iteration(&mut self) {
+        self.iterations += 1;
+    }
+
+    /// Called after forEach, even if the callback has thrown.
+    pub fn end_iteration(&mut self) {
+        self.iterations -= 1;
+        self.compact();
+    }
+
+    /// Remove the deleted entries if they are more than half of the slots, and no iteration
+    /// depends on the indices of the entries.
+    fn compact(&mut self) {
+        let deleted = self.entries.len() - self.index.len();
+        if self.iterations > 0 || deleted * 2 <= self.entries.len() {
+            return;
+        }
+        self.entries.retain(|entry| entry.is_some());
+        for (idx, entry) in self.entries.iter().enumerate() {
+            let (key, _) = entry.unwrap();
+            self.index.insert(MapKey::new(key), idx);
+        }
+    }
+
+    /// Iterate over live entries.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (Value, Value)> + 'a {
+        self.entries.iter().filter_map(|entry| *entry)
+    }
+}
+
+#[test]
+fn compact_deleted_entries() {
+    let mut map = MapObjectInfo::new();
+    for i in 0..100 {
+        map.set(Value::Number(i as f64), Value::Number(0.0));
+        map.delete(Value::Number(i as f64));
+    }
+    assert_eq!(map.size(), 0);
+    assert!(map.slots_len() <= 1);
+
+    map.set(Value::Number(-0.0), Value::Number(1.0));
+    map.set(Value::Number(std::f64::NAN), Value::Number(2.0));
+    map.begin_iteration();
+    map.clear();
+    map.set(Value::Number(0.0), Value::Number(3.0));
+    // The indices do not move while iterating.
+    assert_eq!(
+        map.get_entry(2),
+        Some((Value::Number(0.0), Value::Number(3.0)))
+    );
+    map.end_iteration();
+    assert_eq!(map.slots_len(), 1);
+    assert_eq!(map.get(Value::Number(-0.0)), Some(Value::Number(3.0)));
+    assert!(!map.has(Value::Number(std::f64::NAN)));
+}
@@ -3,6 +3,9 @@ pub mod value;
 pub mod array;
 pub mod error;
 pub mod function;
+pub mod map;
 pub mod object;
 pub mod prototype;
+pub mod proxy;
+pub mod shape;
 pub mod symbol;
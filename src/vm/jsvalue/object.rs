@@ -1,6 +1,8 @@
 use super::super::super::gc::MemoryAllocator;
 use super::super::error;
 use super::value::*;
+use crate::vm::atom::Atom;
+pub use crate::vm::jsvalue::shape::PropertyMap;
 use crate::vm::vm::Factory;
 pub use rustc_hash::FxHashMap;
 
@@ -10,8 +12,8 @@ pub struct ObjectInfo {
     pub kind: ObjectKind,
     /// Internal slot \[\[Prototype\]\]
     pub prototype: Value,
-    /// Properties
-    pub property: FxHashMap<String, Property>,
+    /// Properties with string keys
+    pub property: PropertyMap,
     /// Symbol properties
     pub sym_property: FxHashMap<usize, Property>,
 }
@@ -38,6 +40,12 @@ pub enum ObjectKind {
     Array(ArrayObjectInfo),
     Symbol(SymbolInfo),
     Error(ErrorObjectInfo),
+    Map(MapObjectInfo),
+    Set(MapObjectInfo),
+    Proxy(ProxyObjectInfo),
+    /// A Boolean, Number or String object. The value is its \[\[BooleanData\]\],
+    /// \[\[NumberData\]\] or \[\[StringData\]\].
+    PrimitiveWrapper(Value),
     Ordinary,
 }
 
@@ -63,6 +71,28 @@ pub struct AccessorProperty {
     pub configurable: bool,
 }
 
+/// A property descriptor, given to ``Object.defineProperty``. Absent fields are None.
+/// https://tc39.github.io/ecma262/#sec-property-descriptor-specification-type
+#[derive(Clone, PartialEq, Debug, Copy, Default)]
+pub struct PropertyDescriptor {
+    pub value: Option<Value>,
+    pub writable: Option<bool>,
+    pub get: Option<Value>,
+    pub set: Option<Value>,
+    pub enumerable: Option<bool>,
+    pub configurable: Option<bool>,
+}
+
+/// What assigning to a property leaves for the VM to do.
+#[derive(Clone, PartialEq, Debug, Copy)]
+pub enum SetResult {
+    Done,
+    /// The property is an accessor, own or inherited, whose setter is to be called.
+    CallSetter(Value),
+    /// The property is read-only, or an accessor without a setter. A TypeError in strict code.
+    Rejected,
+}
+
 impl ObjectInfo {
     pub fn has_own_property(&self, key: &str) -> bool {
         self.property.contains_key(key)
@@ -78,33 +108,45 @@ impl ObjectInfo {
         factory: &mut Factory,
         key: Value,
     ) -> Result<Property, error::RuntimeError> {
+        match self.get_own_property_for_get(factory, key) {
+            Some(prop) => Ok(prop),
+            None => self.get_inherited_property(factory, key),
+        }
+    }
+
+    /// Get the own property ``key`` as \[\[Get\]\] sees it: ``__proto__`` is the prototype
+    /// unless the object has its own (e.g. made by ``JSON.parse``), and arrays have every index,
+    /// which is undefined if it is out of the elements.
+    pub fn get_own_property_for_get(&self, factory: &mut Factory, key: Value) -> Option<Property> {
         // Annoying
         if key.is_string() && key.into_str() == "__proto__" {
-            return Ok(Property::new_data_simple(self.get_prototype()));
+            return Some(
+                self.property
+                    .get("__proto__")
+                    .copied()
+                    .unwrap_or_else(|| Property::new_data_simple(self.get_prototype())),
+            );
         }
 
         if key.is_symbol() {
             let id = key.get_symbol_info().id;
-            return match self.sym_property.get(&id) {
-                Some(prop) => Ok(*prop),
-                None => self.prototype.get_property_by_value(factory, key),
-            };
+            return self.sym_property.get(&id).copied();
         }
 
         match self.kind {
             ObjectKind::Array(ref info) => {
                 if let Some(idx) = key.is_array_index() {
-                    return Ok(info.get_element(idx));
+                    return Some(info.get_element(idx));
                 }
 
                 if let Some(idx) =
                     key.is_canonical_numeric_index_string(&mut factory.memory_allocator)
                 {
-                    return Ok(info.get_element(idx));
+                    return Some(info.get_element(idx));
                 }
 
                 if key.is_string() && key.into_str() == "length" {
-                    return Ok(Property::new_data_simple(Value::Number(
+                    return Some(Property::new_data_simple(Value::Number(
                         info.elems.len() as f64
                     )));
                 }
@@ -112,30 +154,189 @@ impl ObjectInfo {
             _ => {}
         }
 
-        match self.property.get(key.to_string().as_str()) {
-            Some(prop) => Ok(*prop),
-            None => {
-                let proto = self.prototype;
-                if proto.is_null() {
-                    return Ok(Property::new_data_simple(Value::undefined()));
-                };
-                proto.get_property_by_value(factory, key)
-            }
+        self.property.get(&key.to_property_key_str()).copied()
+    }
+
+    /// Look ``key`` up in the prototype chain. Missing properties are undefined.
+    fn get_inherited_property(
+        &self,
+        factory: &mut Factory,
+        key: Value,
+    ) -> Result<Property, error::RuntimeError> {
+        if !self.prototype.is_object() {
+            return Ok(Property::new_data_simple(Value::undefined()));
         }
+        self.prototype.get_property_by_value(factory, key)
     }
 
+    /// Get the value of the data property ``key``, own or inherited. Accessor properties are
+    /// undefined since their getters cannot be called here; use ``VM::get_property_by_value``
+    /// for them.
     pub fn get_property(&self, key: &str) -> Value {
         match self.property.get(key) {
-            Some(prop) => prop.as_data().val,
+            Some(Property::Data(data)) => data.val,
+            Some(Property::Accessor(_)) => Value::undefined(),
             None => self.prototype.get_property(key),
         }
     }
 
-    pub fn set_property(&mut self, key: String, val: Value) {
+    /// Find the property ``key`` in the prototype chain, not including the object itself.
+    fn find_inherited_property(&self, key: Value) -> Option<Property> {
+        let mut proto = self.prototype;
+        while let Value::Object(info) = proto {
+            let info = ObjectRef(info);
+            let prop = if key.is_symbol() {
+                info.sym_property.get(&key.get_symbol_info().id)
+            } else {
+                info.property.get(&key.to_property_key_str())
+            };
+            if let Some(prop) = prop {
+                return Some(*prop);
+            }
+            proto = info.prototype;
+        }
+        None
+    }
+
+    /// Get the own property ``key``.
+    pub fn get_own_property(&self, key: Value) -> Option<Property> {
+        if key.is_symbol() {
+            return self.sym_property.get(&key.get_symbol_info().id).copied();
+        }
+
+        if let ObjectKind::Array(ref info) = self.kind {
+            if let Some(idx) = array_index(key) {
+                return info.get_own_element(idx);
+            }
+            if key.is_string() && key.into_str() == "length" {
+                return Some(info.length_property());
+            }
+        }
+
+        self.property.get(&key.to_property_key_str()).copied()
+    }
+
+    /// The own properties with string keys: array elements, then the other keys which are
+    /// array indexes in ascending order, then the rest in the order they were added.
+    /// https://tc39.github.io/ecma262/#sec-ordinaryownpropertykeys
+    pub fn own_properties(&self) -> Vec<(String, Property)> {
+        let mut properties = vec![];
+        if let ObjectKind::Array(ref info) = self.kind {
+            for idx in 0..info.get_length() {
+                if let Some(prop) = info.get_own_element(idx) {
+                    properties.push((idx.to_string(), prop));
+                }
+            }
+            properties.push(("length".to_string(), info.length_property()));
+        }
+
+        let mut keys = self.property.keys().collect::<Vec<_>>();
+        keys.sort_by(|key1, key2| {
+            let idx = |key: &str| key.parse::<u32>().ok().filter(|idx| idx.to_string() == key);
+            match (idx(key1), idx(key2)) {
+                (Some(idx1), Some(idx2)) => idx1.cmp(&idx2),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                // The other keys stay in the order they were added.
+                (None, None) => std::cmp::Ordering::Equal,
+            }
+        });
+        for key in keys {
+            properties.push((key.to_string(), self.property[key.as_str()]));
+        }
+        properties
+    }
+
+    /// Define the own property ``key`` by ``desc``. Return false if it is a non-configurable
+    /// property which cannot be changed so.
+    /// https://tc39.github.io/ecma262/#sec-ordinarydefineownproperty
+    pub fn define_own_property(
+        &mut self,
+        allocator: &mut MemoryAllocator,
+        key: Value,
+        desc: PropertyDescriptor,
+    ) -> Result<bool, error::RuntimeError> {
+        let current = self.get_own_property(key);
+
+        if let ObjectKind::Array(ref mut info) = self.kind {
+            if key.is_string() && key.into_str() == "length" {
+                // The length of arrays is always writable.
+                let new = match desc.apply(current) {
+                    Some(Property::Data(data)) if data.writable => data,
+                    _ => return Ok(false),
+                };
+                let new_length = match new.val.is_array_index() {
+                    Some(new_length) => new_length,
+                    None => {
+                        return Err(error::RuntimeError::range(
+                            error::message::invalid_array_length(),
+                        ))
+                    }
+                };
+                allocator.limits.check_array_length(new_length)?;
+                info.set_length(new_length);
+                return Ok(true);
+            }
+
+            if let Some(idx) = array_index(key) {
+                let new = match desc.apply(current) {
+                    Some(new) => new,
+                    None => return Ok(false),
+                };
+                if idx >= info.get_length() {
+                    allocator.limits.check_array_length(idx + 1)?;
+                    info.set_length(idx + 1);
+                }
+                info.elems[idx] = new;
+                return Ok(true);
+            }
+        }
+
+        let new = match desc.apply(current) {
+            Some(new) => new,
+            None => return Ok(false),
+        };
+        if current.is_none() {
+            let count = self.property.len() + self.sym_property.len();
+            allocator.limits.check_property_count(count + 1)?;
+        }
+        if key.is_symbol() {
+            self.sym_property.insert(key.get_symbol_info().id, new);
+        } else {
+            self.property.insert(&*key.to_property_key_str(), new);
+        }
+        Ok(true)
+    }
+
+    /// Delete the own property ``key``. Return false if it is not configurable.
+    /// https://tc39.github.io/ecma262/#sec-ordinarydelete
+    pub fn delete_property_by_value(&mut self, key: Value) -> bool {
+        match self.get_own_property(key) {
+            None => return true,
+            Some(prop) if !prop.is_configurable() => return false,
+            Some(_) => {}
+        }
+
+        if let ObjectKind::Array(ref mut info) = self.kind {
+            if let Some(idx) = array_index(key) {
+                // Leave a hole.
+                info.elems[idx] = Property::new_data_simple(Value::empty());
+                return true;
+            }
+        }
+
+        if key.is_symbol() {
+            self.sym_property.remove(&key.get_symbol_info().id);
+        } else {
+            self.property.remove(&key.to_property_key_str());
+        }
+        true
+    }
+
+    pub fn set_property(&mut self, key: impl Into<Atom>, val: Value) {
         let property = self
             .property
-            .entry(key)
-            .or_insert_with(|| Property::new_data_simple(Value::undefined()));
+            .get_or_insert_with(key, || Property::new_data_simple(Value::undefined()));
         let data = property.as_data_mut();
         if data.writable {
             data.val = val;
@@ -147,64 +348,87 @@ impl ObjectInfo {
         allocator: &mut MemoryAllocator,
         key: Value,
         val_: Value,
-    ) -> Result<Option<Value>, error::RuntimeError> {
+    ) -> Result<SetResult, error::RuntimeError> {
         // Annoying
-        if key.is_string() && key.into_str() == "__proto__" {
+        if key.is_string()
+            && key.into_str() == "__proto__"
+            && !self.property.contains_key("__proto__")
+        {
             self.prototype = val_;
-            return Ok(None);
+            return Ok(SetResult::Done);
         }
 
         match self.kind {
             ObjectKind::Array(ref mut info) => {
-                if let Some(idx) = key.is_array_index() {
-                    return Ok(info.set_element(idx, val_));
-                }
-
-                if let Some(idx) = key.is_canonical_numeric_index_string(allocator) {
+                let idx = key
+                    .is_array_index()
+                    .or_else(|| key.is_canonical_numeric_index_string(allocator));
+                if let Some(idx) = idx {
+                    if idx >= info.get_length() {
+                        allocator.limits.check_array_length(idx + 1)?;
+                    }
                     return Ok(info.set_element(idx, val_));
                 }
 
                 if key.is_string() && key.into_str() == "length" {
                     if let Some(new_length) = val_.is_array_index() {
+                        allocator.limits.check_array_length(new_length)?;
                         info.set_length(new_length);
-                        return Ok(None);
+                        return Ok(SetResult::Done);
                     }
                 }
             }
             _ => {}
         }
 
+        let own = if key.is_symbol() {
+            self.sym_property.get(&key.get_symbol_info().id).copied()
+        } else {
+            self.property.get(&key.to_property_key_str()).copied()
+        };
+        // An inherited accessor or read-only property also decides the assignment.
+        // https://tc39.github.io/ecma262/#sec-ordinarysetwithowndescriptor
+        match own.or_else(|| self.find_inherited_property(key)) {
+            Some(Property::Accessor(AccessorProperty { set, .. })) => {
+                return Ok(if set.is_undefined() {
+                    SetResult::Rejected
+                } else {
+                    SetResult::CallSetter(set)
+                });
+            }
+            Some(Property::Data(DataProperty {
+                writable: false, ..
+            })) => return Ok(SetResult::Rejected),
+            _ => {}
+        }
+
+        if own.is_none() {
+            let count = self.property.len() + self.sym_property.len();
+            allocator.limits.check_property_count(count + 1)?;
+        }
+        let new_property = || Property::new_data_simple(Value::undefined());
         let property = if key.is_symbol() {
             let id = key.get_symbol_info().id;
-            self.sym_property
-                .entry(id)
-                .or_insert_with(|| Property::new_data_simple(Value::undefined()))
+            self.sym_property.entry(id).or_insert_with(new_property)
         } else {
             self.property
-                .entry(key.to_string())
-                .or_insert_with(|| Property::new_data_simple(Value::undefined()))
+                .get_or_insert_with(&*key.to_property_key_str(), new_property)
         };
+        property.as_data_mut().val = val_;
+        Ok(SetResult::Done)
+    }
+}
 
-        match property {
-            Property::Data(DataProperty {
-                ref mut val,
-                writable,
-                ..
-            }) => {
-                if *writable {
-                    *val = val_;
-                }
-                Ok(None)
-            }
-            Property::Accessor(AccessorProperty { set, .. }) => {
-                if set.is_undefined() {
-                    Ok(None)
-                } else {
-                    Ok(Some(*set))
-                }
-            }
-        }
+/// The array index ``key`` is, either a number or its string.
+fn array_index(key: Value) -> Option<usize> {
+    if key.is_string() {
+        let key = key.into_str();
+        return key
+            .parse()
+            .ok()
+            .filter(|idx: &usize| idx.to_string() == key);
     }
+    key.is_array_index()
 }
 
 impl Property {
@@ -221,6 +445,16 @@ impl Property {
         })
     }
 
+    /// Generate an accessor property which has only a getter.
+    pub fn new_getter(get: Value) -> Self {
+        Property::Accessor(AccessorProperty {
+            get,
+            set: Value::undefined(),
+            enumerable: false,
+            configurable: true,
+        })
+    }
+
     pub fn as_data(self) -> DataProperty {
         match self {
             Property::Data(data) => data,
@@ -255,4 +489,109 @@ impl Property {
             _ => None,
         }
     }
+
+    pub fn is_enumerable(&self) -> bool {
+        match self {
+            Property::Data(DataProperty { enumerable, .. })
+            | Property::Accessor(AccessorProperty { enumerable, .. }) => *enumerable,
+        }
+    }
+
+    pub fn is_configurable(&self) -> bool {
+        match self {
+            Property::Data(DataProperty { configurable, .. })
+            | Property::Accessor(AccessorProperty { configurable, .. }) => *configurable,
+        }
+    }
+}
+
+impl PropertyDescriptor {
+    pub fn is_accessor_descriptor(&self) -> bool {
+        self.get.is_some() || self.set.is_some()
+    }
+
+    pub fn is_data_descriptor(&self) -> bool {
+        self.value.is_some() || self.writable.is_some()
+    }
+
+    /// Get the property ``current`` changed by the descriptor, or a new property if None.
+    /// Return None if ``current`` is not configurable and cannot be changed so.
+    /// https://tc39.github.io/ecma262/#sec-validateandapplypropertydescriptor
+    pub fn apply(&self, current: Option<Property>) -> Option<Property> {
+        let current = match current {
+            Some(current) => current,
+            None => return Some(self.to_property(false, false)),
+        };
+
+        if !current.is_configurable() {
+            if self.configurable == Some(true) {
+                return None;
+            }
+            if self
+                .enumerable
+                .map_or(false, |e| e != current.is_enumerable())
+            {
+                return None;
+            }
+        }
+        let enumerable = self.enumerable.unwrap_or(current.is_enumerable());
+        let configurable = self.configurable.unwrap_or(current.is_configurable());
+
+        match current {
+            Property::Data(data) if !self.is_accessor_descriptor() => {
+                if !data.configurable && !data.writable {
+                    if self.writable == Some(true) {
+                        return None;
+                    }
+                    if self.value.map_or(false, |val| !val.same_value(data.val)) {
+                        return None;
+                    }
+                }
+                Some(Property::Data(DataProperty {
+                    val: self.value.unwrap_or(data.val),
+                    writable: self.writable.unwrap_or(data.writable),
+                    enumerable,
+                    configurable,
+                }))
+            }
+            Property::Accessor(accessor) if !self.is_data_descriptor() => {
+                if !accessor.configurable
+                    && (self.get.map_or(false, |get| get != accessor.get)
+                        || self.set.map_or(false, |set| set != accessor.set))
+                {
+                    return None;
+                }
+                Some(Property::Accessor(AccessorProperty {
+                    get: self.get.unwrap_or(accessor.get),
+                    set: self.set.unwrap_or(accessor.set),
+                    enumerable,
+                    configurable,
+                }))
+            }
+            // Turn a data property into an accessor property, or vice versa.
+            _ if !current.is_configurable() => None,
+            _ => Some(self.to_property(enumerable, configurable)),
+        }
+    }
+
+    /// Make a property of the descriptor, with false for the absent attributes.
+    fn to_property(&self, enumerable: bool, configurable: bool) -> Property {
+        let enumerable = self.enumerable.unwrap_or(enumerable);
+        let configurable = self.configurable.unwrap_or(configurable);
+        if self.is_accessor_descriptor() {
+            Property::Accessor(AccessorProperty {
+                get: self.get.unwrap_or(Value::undefined()),
+                set: self.set.unwrap_or(Value::undefined()),
+                enumerable,
+                configurable,
+            })
+        } else {
+            Property::Data(DataProperty {
+                val: self.value.unwrap_or(Value::undefined()),
+                writable: self.writable.unwrap_or(false),
+                enumerable,
+                configurable,
+            })
+        }
+    }
 }
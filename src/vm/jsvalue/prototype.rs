@@ -1,8 +1,10 @@
 #![macro_use]
 use super::value::*;
+use super::shape::PropertyMap;
 use super::value::Value;
+use crate::builtin::BuiltinFuncTy;
 use crate::builtins;
-use crate::builtins::{array, function};
+use crate::builtins::{array, function, map, set};
 use crate::vm::vm::Factory;
 use rustc_hash::FxHashMap;
 
@@ -11,9 +13,16 @@ pub struct ObjectPrototypes {
     pub object: Value,
     pub function: Value,
     pub string: Value,
+    pub number: Value,
     pub array: Value,
     pub symbol: Value,
     pub error: Value,
+    pub type_error: Value,
+    pub reference_error: Value,
+    pub range_error: Value,
+    pub syntax_error: Value,
+    pub map: Value,
+    pub set: Value,
 }
 
 impl ObjectPrototypes {
@@ -31,6 +40,7 @@ impl ObjectPrototypes {
                 kind: ObjectKind::Function(FunctionObjectInfo {
                     name: None,
                     kind: FunctionObjectKind::User{ info: default_func_ref, outer_env: None },
+                    constructible: false,
                 }),
                 prototype: object_prototype,
                 property: make_property_map!(),
@@ -44,9 +54,19 @@ impl ObjectPrototypes {
                 function::function_prototype_call,
             );
 
+            let function_prototype_bind = Value::builtin_function_with_proto(
+                &mut factory.memory_allocator,
+                function_prototype,
+                "bind",
+                function::function_prototype_bind,
+            );
+
             let mut info = function_prototype.get_object_info();
             info.prototype = object_prototype;
-            info.property = make_property_map!(call: function_prototype_call);
+            info.property = make_property_map!(
+                call: function_prototype_call,
+                bind: function_prototype_bind
+            );
 
             function_prototype
         };
@@ -66,10 +86,60 @@ impl ObjectPrototypes {
                 builtins::string::string_prototype_split,
             );
 
+            let locale_compare = Value::builtin_function_with_proto(
+                &mut factory.memory_allocator,
+                function_prototype,
+                "localeCompare",
+                builtins::string::string_prototype_locale_compare,
+            );
+
+            let to_string = Value::builtin_function_with_proto(
+                &mut factory.memory_allocator,
+                function_prototype,
+                "toString",
+                builtins::string::string_prototype_to_string,
+            );
+
+            let value_of = Value::builtin_function_with_proto(
+                &mut factory.memory_allocator,
+                function_prototype,
+                "valueOf",
+                builtins::string::string_prototype_value_of,
+            );
+
             Value::Object(factory.alloc(ObjectInfo {
                 kind: ObjectKind::Ordinary,
                 prototype: object_prototype,
-                property: make_property_map!(indexOf: index_of, split: split),
+                property: make_property_map!(
+                    indexOf: index_of,
+                    split: split,
+                    localeCompare: locale_compare,
+                    toString: to_string,
+                    valueOf: value_of
+                ),
+                sym_property: FxHashMap::default(),
+            }))
+        };
+
+        let number_prototype = {
+            let to_string = Value::builtin_function_with_proto(
+                &mut factory.memory_allocator,
+                function_prototype,
+                "toString",
+                builtins::number::number_prototype_to_string,
+            );
+
+            let value_of = Value::builtin_function_with_proto(
+                &mut factory.memory_allocator,
+                function_prototype,
+                "valueOf",
+                builtins::number::number_prototype_value_of,
+            );
+
+            Value::Object(factory.alloc(ObjectInfo {
+                kind: ObjectKind::Ordinary,
+                prototype: object_prototype,
+                property: make_property_map!(toString: to_string, valueOf: value_of),
                 sym_property: FxHashMap::default(),
             }))
         };
@@ -119,14 +189,105 @@ impl ObjectPrototypes {
             }))
         };
 
+        // https://tc39.github.io/ecma262/#sec-properties-of-the-error-prototype-object
         let error_prototype = {
+            let name = factory.string("Error");
+            let message = factory.string("");
             Value::Object(factory.alloc(ObjectInfo {
                 kind: ObjectKind::Error(ErrorObjectInfo {
                     stack_trace: "".to_string(),
                 }),
                 prototype: object_prototype,
-                // TODO: https://tc39.github.io/ecma262/#sec-properties-of-the-error-prototype-object
-                property: make_property_map!(),
+                property: make_property_map!(
+                    name    => true, false, true: name,
+                    message => true, false, true: message
+                ),
+                sym_property: FxHashMap::default(),
+            }))
+        };
+
+        // https://tc39.github.io/ecma262/#sec-properties-of-the-nativeerror-prototype-objects
+        let mut native_error_prototype = |error_type: ErrorType| {
+            let name = factory.string(error_type.name());
+            let message = factory.string("");
+            Value::Object(factory.alloc(ObjectInfo {
+                kind: ObjectKind::Ordinary,
+                prototype: error_prototype,
+                property: make_property_map!(
+                    name    => true, false, true: name,
+                    message => true, false, true: message
+                ),
+                sym_property: FxHashMap::default(),
+            }))
+        };
+        let type_error_prototype = native_error_prototype(ErrorType::TypeError);
+        let reference_error_prototype = native_error_prototype(ErrorType::ReferenceError);
+        let range_error_prototype = native_error_prototype(ErrorType::RangeError);
+        let syntax_error_prototype = native_error_prototype(ErrorType::SyntaxError);
+
+        let map_prototype = {
+            let mut property = PropertyMap::default();
+            for (name, func) in &[
+                ("get", map::map_prototype_get as BuiltinFuncTy),
+                ("set", map::map_prototype_set),
+                ("has", map::map_prototype_has),
+                ("delete", map::map_prototype_delete),
+                ("clear", map::map_prototype_clear),
+                ("forEach", map::map_prototype_for_each),
+            ] {
+                let func = Value::builtin_function_with_proto(
+                    &mut factory.memory_allocator,
+                    function_prototype,
+                    *name,
+                    *func,
+                );
+                property.insert(name.to_string(), Property::new_data_simple(func));
+            }
+            let size = Value::builtin_function_with_proto(
+                &mut factory.memory_allocator,
+                function_prototype,
+                "size",
+                map::map_prototype_size,
+            );
+            property.insert("size".to_string(), Property::new_getter(size));
+
+            Value::Object(factory.alloc(ObjectInfo {
+                kind: ObjectKind::Ordinary,
+                prototype: object_prototype,
+                property,
+                sym_property: FxHashMap::default(),
+            }))
+        };
+
+        let set_prototype = {
+            let mut property = PropertyMap::default();
+            for (name, func) in &[
+                ("add", set::set_prototype_add as BuiltinFuncTy),
+                ("has", set::set_prototype_has),
+                ("delete", set::set_prototype_delete),
+                ("clear", set::set_prototype_clear),
+                ("forEach", set::set_prototype_for_each),
+            ] {
+                let func = Value::builtin_function_with_proto(
+                    &mut factory.memory_allocator,
+                    function_prototype,
+                    *name,
+                    *func,
+                );
+                property.insert(name.to_string(), Property::new_data_simple(func));
+            }
+            let size = Value::builtin_function_with_proto(
+                &mut factory.memory_allocator,
+                function_prototype,
+                "size",
+                set::set_prototype_size,
+            );
+            property.insert("size".to_string(), Property::new_getter(size));
+
+            Value::Object(factory.alloc(ObjectInfo {
+                kind: ObjectKind::Ordinary,
+                prototype: object_prototype,
+                property,
                 sym_property: FxHashMap::default(),
             }))
         };
@@ -135,9 +296,26 @@ impl ObjectPrototypes {
             object: object_prototype,
             function: function_prototype,
             string: string_prototype,
+            number: number_prototype,
             array: array_prototype,
             symbol: symbol_prototype,
             error: error_prototype,
+            type_error: type_error_prototype,
+            reference_error: reference_error_prototype,
+            range_error: range_error_prototype,
+            syntax_error: syntax_error_prototype,
+            map: map_prototype,
+            set: set_prototype,
+        }
+    }
+
+    pub fn native_error(&self, error_type: ErrorType) -> Value {
+        match error_type {
+            ErrorType::Error => self.error,
+            ErrorType::TypeError => self.type_error,
+            ErrorType::ReferenceError => self.reference_error,
+            ErrorType::RangeError => self.range_error,
+            ErrorType::SyntaxError => self.syntax_error,
         }
     }
 
@@ -146,9 +324,16 @@ impl ObjectPrototypes {
     object: Value::undefined(),
     function: Value::undefined(),
     string: Value::undefined(),
+    number: Value::undefined(),
     array: Value::undefined(),
     symbol: Value::undefined(),
     error: Value::undefined(),
+    type_error: Value::undefined(),
+    reference_error: Value::undefined(),
+    range_error: Value::undefined(),
+    syntax_error: Value::undefined(),
+    map: Value::undefined(),
+    set: Value::undefined(),
     }
     }
 }
@@ -0,0 +1,14 @@
+use super::value::Value;
+
+/// Internal slots \[\[ProxyTarget\]\] and \[\[ProxyHandler\]\].
+/// https://tc39.github.io/ecma262/#sec-proxy-object-internal-methods-and-internal-slots
+#[derive(Clone, Debug)]
+pub struct ProxyObjectInfo {
+    pub target: Value,
+    /// The object whose methods (traps) are called instead of the internal methods of
+    /// ``target``. Undefined for guarded objects.
+    pub handler: Value,
+    /// The index of the callback in ``VM::access_checks`` if this is a guarded object made by
+    /// ``VM::guarded_object``.
+    pub access_check: Option<usize>,
+}
@@ -0,0 +1,294 @@
+//! Shapes (hidden classes) of objects. The properties of an object are a flat vector of
+//! values, and its shape maps the keys to the indexes in the vector. Objects which got the same
+//! keys in the same order share the shape, which they reach through the transitions from the
+//! empty shape, so that the keys are not stored per object, and a property lookup of an object
+//! can be cached by its shape.
+//!
+//! An object with many properties, or one which lost a property, gets a shape of its own (a
+//! dictionary shape) instead, which changes in place as keys are added and removed. Its objects
+//! are used as dictionaries, whose shapes would not be shared anyway.
+//!
+//! The keys are atoms, so that looking one up hashes a pointer. A key without an atom is in no
+//! shape.
+
+use super::object::Property;
+use crate::vm::atom::Atom;
+use rustc_hash::FxHashMap;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+/// The number of keys above which an object gets a dictionary shape.
+const MAX_SHARED_KEYS: usize = 32;
+
+pub struct Shape {
+    /// The index of the value of each key.
+    slots: FxHashMap<Atom, usize>,
+    /// The key of each value, in the order the keys were added to shared shapes.
+    keys: Vec<Atom>,
+    /// The shape this one was made from by adding a key. Keeps the transition to this shape
+    /// reachable while objects have it.
+    parent: Option<Rc<Shape>>,
+    /// The shapes made from this one by adding a key. They are dropped with their last object.
+    transitions: RefCell<FxHashMap<Atom, Weak<Shape>>>,
+    dictionary: bool,
+}
+
+thread_local! {
+    static EMPTY: Rc<Shape> = Rc::new(Shape {
+        slots: FxHashMap::default(),
+        keys: vec![],
+        parent: None,
+        transitions: RefCell::new(FxHashMap::default()),
+        dictionary: false,
+    });
+}
+
+impl Shape {
+    /// The index in the property vector of the value of ``key``.
+    pub fn slot(&self, key: &str) -> Option<usize> {
+        self.slot_of_atom(&Atom::lookup(key)?)
+    }
+
+    pub fn slot_of_atom(&self, key: &Atom) -> Option<usize> {
+        self.slots.get(key).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_dictionary(&self) -> bool {
+        self.dictionary
+    }
+
+    /// The shape made by adding ``key`` to the shared shape ``shape``.
+    fn add_key(shape: &Rc<Shape>, key: Atom) -> Rc<Shape> {
+        let mut transitions = shape.transitions.borrow_mut();
+        if let Some(next) = transitions.get(&key).and_then(|next| next.upgrade()) {
+            return next;
+        }
+        // Forget the transitions to dropped shapes, which objects used as dictionaries leave.
+        if transitions.len() >= 16 && transitions.len().is_power_of_two() {
+            transitions.retain(|_, next| next.upgrade().is_some());
+        }
+        let mut next = shape.to_dictionary();
+        next.dictionary = false;
+        next.parent = Some(shape.clone());
+        next.insert_key(key.clone());
+        let next = Rc::new(next);
+        transitions.insert(key, Rc::downgrade(&next));
+        next
+    }
+
+    /// A dictionary shape with the keys of this one.
+    fn to_dictionary(&self) -> Shape {
+        Shape {
+            slots: self.slots.clone(),
+            keys: self.keys.clone(),
+            parent: None,
+            transitions: RefCell::new(FxHashMap::default()),
+            dictionary: true,
+        }
+    }
+
+    fn insert_key(&mut self, key: Atom) {
+        self.slots.insert(key.clone(), self.keys.len());
+        self.keys.push(key);
+    }
+}
+
+/// The properties with string keys of an object.
+#[derive(Clone)]
+pub struct PropertyMap {
+    shape: Rc<Shape>,
+    values: Vec<Property>,
+}
+
+impl Default for PropertyMap {
+    fn default() -> Self {
+        PropertyMap {
+            shape: EMPTY.with(|empty| empty.clone()),
+            values: vec![],
+        }
+    }
+}
+
+impl PropertyMap {
+    pub fn shape(&self) -> &Rc<Shape> {
+        &self.shape
+    }
+
+    /// The value at ``slot``, given by ``Shape::slot`` of the shape of this map.
+    pub fn get_by_slot(&self, slot: usize) -> &Property {
+        &self.values[slot]
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.shape.slot(key).is_some()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Property> {
+        let slot = self.shape.slot(key)?;
+        Some(&self.values[slot])
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Property> {
+        let slot = self.shape.slot(key)?;
+        Some(&mut self.values[slot])
+    }
+
+    /// Set the property ``key``, and return the previous one.
+    pub fn insert(&mut self, key: impl Into<Atom>, prop: Property) -> Option<Property> {
+        let key = key.into();
+        if let Some(slot) = self.shape.slot_of_atom(&key) {
+            return Some(std::mem::replace(&mut self.values[slot], prop));
+        }
+        if self.shape.dictionary {
+            dictionary_mut(&mut self.shape).insert_key(key);
+        } else if self.shape.len() >= MAX_SHARED_KEYS {
+            let mut shape = self.shape.to_dictionary();
+            shape.insert_key(key);
+            self.shape = Rc::new(shape);
+        } else {
+            self.shape = Shape::add_key(&self.shape, key);
+        }
+        self.values.push(prop);
+        None
+    }
+
+    /// Get the property ``key``, adding the one made by ``default`` if there is none.
+    pub fn get_or_insert_with(
+        &mut self,
+        key: impl Into<Atom>,
+        default: impl FnOnce() -> Property,
+    ) -> &mut Property {
+        let key = key.into();
+        let slot = match self.shape.slot_of_atom(&key) {
+            Some(slot) => slot,
+            None => {
+                self.insert(key, default());
+                self.values.len() - 1
+            }
+        };
+        &mut self.values[slot]
+    }
+
+    /// Remove the property ``key``, which makes the shape a dictionary shape. The other keys
+    /// keep their order, and the ones after ``key`` move down a slot.
+    pub fn remove(&mut self, key: &str) -> Option<Property> {
+        let key = Atom::lookup(key)?;
+        let slot = self.shape.slot_of_atom(&key)?;
+        if !self.shape.dictionary {
+            self.shape = Rc::new(self.shape.to_dictionary());
+        }
+        let shape = dictionary_mut(&mut self.shape);
+        shape.slots.remove(&key);
+        shape.keys.remove(slot);
+        for moved in &shape.keys[slot..] {
+            *shape.slots.get_mut(moved).unwrap() -= 1;
+        }
+        Some(self.values.remove(slot))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Atom> {
+        self.shape.keys.iter()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Atom, &Property)> {
+        self.shape.keys.iter().zip(self.values.iter())
+    }
+}
+
+/// ``Rc::make_mut`` for dictionary shapes: copy the shape if another object has it, e.g.
+/// after the object was cloned.
+fn dictionary_mut(shape: &mut Rc<Shape>) -> &mut Shape {
+    if Rc::get_mut(shape).is_none() {
+        *shape = Rc::new(shape.to_dictionary());
+    }
+    Rc::get_mut(shape).unwrap()
+}
+
+impl<'a> IntoIterator for &'a PropertyMap {
+    type Item = (&'a Atom, &'a Property);
+    type IntoIter = std::iter::Zip<std::slice::Iter<'a, Atom>, std::slice::Iter<'a, Property>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.shape.keys.iter().zip(self.values.iter())
+    }
+}
+
+impl std::ops::Index<&str> for PropertyMap {
+    type Output = Property;
+
+    fn index(&self, key: &str) -> &Property {
+        self.get(key).expect("no such property")
+    }
+}
+
+impl std::fmt::Debug for PropertyMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl std::fmt::Debug for Shape {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Shape")
+            .field("keys", &self.keys)
+            .field("dictionary", &self.dictionary)
+            .finish()
+    }
+}
+
+#[test]
+fn shapes() {
+    let prop = |n: f64| Property::new_data_simple(super::value::Value::Number(n));
+    let mut a = PropertyMap::default();
+    a.insert("x".to_string(), prop(1.0));
+    a.insert("y".to_string(), prop(2.0));
+    let mut b = PropertyMap::default();
+    b.insert("x".to_string(), prop(3.0));
+    b.insert("y".to_string(), prop(4.0));
+    // The same keys in the same order share the shape.
+    assert!(Rc::ptr_eq(a.shape(), b.shape()));
+    assert_eq!(b["y"], prop(4.0));
+    let slot = a.shape().slot("y").unwrap();
+    assert_eq!(*b.get_by_slot(slot), prop(4.0));
+    assert_eq!(a.insert("x".to_string(), prop(5.0)), Some(prop(1.0)));
+    assert!(Rc::ptr_eq(a.shape(), b.shape()));
+
+    let mut c = PropertyMap::default();
+    c.insert("y".to_string(), prop(1.0));
+    c.insert("x".to_string(), prop(2.0));
+    assert!(!Rc::ptr_eq(a.shape(), c.shape()));
+
+    // Removing a key makes a dictionary shape of the object's own.
+    assert_eq!(a.remove("x"), Some(prop(5.0)));
+    assert!(a.shape().is_dictionary());
+    assert_eq!(a.get("x"), None);
+    assert_eq!(a["y"], prop(2.0));
+    assert_eq!(b["x"], prop(3.0));
+    a.insert("z".to_string(), prop(6.0));
+    assert_eq!(a.keys().collect::<Vec<_>>(), ["y", "z"]);
+    // The keys after the removed one keep their order.
+    a.insert("w".to_string(), prop(7.0));
+    assert_eq!(a.remove("y"), Some(prop(2.0)));
+    assert_eq!(a.keys().collect::<Vec<_>>(), ["z", "w"]);
+    assert_eq!(a["z"], prop(6.0));
+    assert_eq!(a["w"], prop(7.0));
+
+    let mut d = PropertyMap::default();
+    for i in 0..MAX_SHARED_KEYS + 1 {
+        d.insert(i.to_string(), prop(i as f64));
+    }
+    assert!(d.shape().is_dictionary());
+    assert_eq!(d.len(), MAX_SHARED_KEYS + 1);
+}
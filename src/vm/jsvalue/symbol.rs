@@ -16,6 +16,27 @@ impl SymbolInfo {
     }
 }
 
+/// The symbols the spec refers to as @@name, e.g. @@toPrimitive for ``Symbol.toPrimitive``.
+#[derive(Debug, Clone)]
+pub struct WellKnownSymbols {
+    pub to_primitive: Value,
+}
+
+impl WellKnownSymbols {
+    pub fn dummy() -> Self {
+        Self {
+            to_primitive: Value::undefined(),
+        }
+    }
+
+    /// The symbols are never collected, since the VM looks them up even if scripts can not.
+    pub fn new(factory: &mut Factory) -> Self {
+        let to_primitive = factory.symbol(Some("Symbol.toPrimitive".to_string()));
+        factory.memory_allocator.lock_value(to_primitive);
+        Self { to_primitive }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct GlobalSymbolRegistry {
     list: Vec<(String, Value)>,
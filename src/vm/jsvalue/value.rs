@@ -2,14 +2,18 @@ use super::super::error;
 pub use super::array::ArrayObjectInfo;
 pub use super::error::*;
 pub use super::function::*;
+pub use super::map::MapObjectInfo;
 pub use super::object::*;
 pub use super::prototype::*;
+pub use super::proxy::ProxyObjectInfo;
 pub use super::symbol::*;
 use crate::builtin::BuiltinFuncTy;
 use crate::gc;
+use crate::vm::atom::Atom;
 use crate::vm::exec_context::LexicalEnvironmentRef;
 use crate::vm::vm::Factory;
 pub use rustc_hash::FxHashMap;
+use std::borrow::Cow;
 use std::ffi::CString;
 
 pub const UNINITIALIZED: i32 = 0;
@@ -46,7 +50,7 @@ macro_rules! make_property_map_sub {
          $configurable:ident
     ),*) => { {
         #[allow(unused_mut)]
-        let mut record = rustc_hash::FxHashMap::default();
+        let mut record = crate::vm::jsvalue::shape::PropertyMap::default();
         $( record.insert(
             (stringify!($property_name)).to_string(),
             crate::vm::jsvalue::object::Property::Data(crate::vm::jsvalue::object::DataProperty {
@@ -78,7 +82,7 @@ macro_rules! make_normal_object {
             crate::vm::jsvalue::object::ObjectInfo {
                 kind: crate::vm::jsvalue::object::ObjectKind::Ordinary,
                 prototype: $factory.object_prototypes.object,
-                property: rustc_hash::FxHashMap::default(),
+                property: crate::vm::jsvalue::shape::PropertyMap::default(),
                 sym_property: rustc_hash::FxHashMap::default()
             }
         ))
@@ -88,7 +92,7 @@ macro_rules! make_normal_object {
             ObjectInfo {
                 kind: ObjectKind::Ordinary,
                 prototype: $object_prototypes.object,
-                property: FxHashMap::default(),
+                property: crate::vm::jsvalue::shape::PropertyMap::default(),
                 sym_property: FxHashMap::default()
             }
         ))
@@ -137,6 +141,10 @@ impl std::fmt::Display for Value {
                     ObjectKind::Array(_) => write!(f, "Array"),
                     ObjectKind::Symbol(_) => write!(f, "Symbol"),
                     ObjectKind::Error(_) => write!(f, "Error"),
+                    ObjectKind::Map(_) => write!(f, "Map"),
+                    ObjectKind::Set(_) => write!(f, "Set"),
+                    ObjectKind::Proxy(_) => write!(f, "Proxy"),
+                    ObjectKind::PrimitiveWrapper(_) => write!(f, "PrimitiveWrapper"),
                 }
             }
         }
@@ -190,6 +198,7 @@ impl Value {
             kind: ObjectKind::Function(FunctionObjectInfo {
                 name: Some(name),
                 kind: FunctionObjectKind::Builtin(func),
+                constructible: false,
             }),
             prototype: proto,
             property: make_property_map!(
@@ -247,6 +256,41 @@ impl Value {
         }
     }
 
+    pub fn is_proxy_object(&self) -> bool {
+        match self {
+            Value::Object(info) => match ObjectRef(*info).kind {
+                ObjectKind::Proxy(_) => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether the value has the internal method \[\[Call\]\]: a function, or a proxy of a
+    /// callable object.
+    pub fn is_callable(&self) -> bool {
+        match self {
+            Value::Object(info) => match ObjectRef(*info).kind {
+                ObjectKind::Function(_) => true,
+                ObjectKind::Proxy(ref info) => info.target.is_callable(),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether the value has the internal method \[\[Construct\]\], i.e. can be called by new.
+    pub fn is_constructor(&self) -> bool {
+        match self {
+            Value::Object(info) => match ObjectRef(*info).kind {
+                ObjectKind::Function(ref info) => info.constructible,
+                ObjectKind::Proxy(ref info) => info.target.is_constructor(),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
     pub fn is_array_object(&self) -> bool {
         match self {
             Value::Object(info) => match ObjectRef(*info).kind {
@@ -268,6 +312,26 @@ impl Value {
         }
     }
 
+    pub fn is_map_object(&self) -> bool {
+        match self {
+            Value::Object(info) => match ObjectRef(*info).kind {
+                ObjectKind::Map(_) => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    pub fn is_set_object(&self) -> bool {
+        match self {
+            Value::Object(info) => match ObjectRef(*info).kind {
+                ObjectKind::Set(_) => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
     pub fn is_string(&self) -> bool {
         match self {
             Value::String(_) => true,
@@ -337,7 +401,7 @@ impl Value {
         }
     }
 
-    pub fn get_object_properties(&self) -> Option<&FxHashMap<String, Property>> {
+    pub fn get_object_properties(&self) -> Option<&PropertyMap> {
         match self {
             Value::Object(obj_info) => Some(&unsafe { &**obj_info }.property),
             _ => None,
@@ -381,13 +445,17 @@ impl Value {
                 return string_get_property(factory, cstrp_to_str(*s), key);
             }
             Value::Other(_) => {
-                return Err(error::RuntimeError::typeerr(format!(
-                    "TypeError: Cannot read property '{}' of {}",
-                    key.to_string(),
-                    self.to_string()
-                )));
+                return Err(error::RuntimeError::typeerr(
+                    error::message::cannot_read_property(key, *self),
+                ));
+            }
+            Value::Number(_) => {
+                return factory
+                    .object_prototypes
+                    .number
+                    .get_object_info()
+                    .get_property_by_value(factory, key);
             }
-            // TODO: Number
             _ => {}
         }
 
@@ -397,9 +465,9 @@ impl Value {
         }
     }
 
-    pub fn set_property(&self, key: impl Into<String>, val: Value) {
+    pub fn set_property(&self, key: impl Into<Atom>, val: Value) {
         match self {
-            Value::Object(obj_info) => ObjectRef(*obj_info).set_property(key.into(), val),
+            Value::Object(obj_info) => ObjectRef(*obj_info).set_property(key, val),
             _ => {}
         }
     }
@@ -409,17 +477,15 @@ impl Value {
         allocator: &mut gc::MemoryAllocator,
         key: Value,
         val: Value,
-    ) -> Result<Option<Value>, error::RuntimeError> {
+    ) -> Result<SetResult, error::RuntimeError> {
         match self {
             Value::Object(obj_info) => {
                 ObjectRef(*obj_info).set_property_by_value(allocator, key, val)
             }
-            Value::Other(_) => Err(error::RuntimeError::typeerr(format!(
-                "TypeError: Cannot set property '{}' of {}",
-                key.to_string(),
-                self.to_string()
-            ))),
-            _ => Ok(None),
+            Value::Other(_) => Err(error::RuntimeError::typeerr(
+                error::message::cannot_set_property(key, *self),
+            )),
+            _ => Ok(SetResult::Done),
         }
     }
 
@@ -468,6 +534,19 @@ impl Value {
         }
     }
 
+    pub fn as_function_mut(&self) -> &mut FunctionObjectInfo {
+        match self {
+            Value::Object(obj) => {
+                let obj = unsafe { &mut **obj };
+                match obj.kind {
+                    ObjectKind::Function(ref mut info) => return info,
+                    _ => panic!(),
+                }
+            }
+            e => panic!("{:?}", e),
+        }
+    }
+
     pub fn as_array_mut(&self) -> &mut ArrayObjectInfo {
         match self {
             Value::Object(obj) => {
@@ -481,6 +560,33 @@ impl Value {
         }
     }
 
+    pub fn as_proxy(&self) -> &ProxyObjectInfo {
+        match self {
+            Value::Object(obj) => {
+                let obj = unsafe { &**obj };
+                match obj.kind {
+                    ObjectKind::Proxy(ref info) => info,
+                    _ => panic!(),
+                }
+            }
+            e => panic!("{:?}", e),
+        }
+    }
+
+    /// Returns \[\[MapData\]\] of a Map object or \[\[SetData\]\] of a Set object.
+    pub fn as_map_mut(&self) -> &mut MapObjectInfo {
+        match self {
+            Value::Object(obj) => {
+                let obj = unsafe { &mut **obj };
+                match obj.kind {
+                    ObjectKind::Map(ref mut info) | ObjectKind::Set(ref mut info) => return info,
+                    _ => panic!(),
+                }
+            }
+            e => panic!("{:?}", e),
+        }
+    }
+
     pub fn get_object_info(&self) -> ObjectRef {
         match self {
             Value::Object(obj) => ObjectRef(*obj),
@@ -505,6 +611,27 @@ impl Value {
         }
     }
 
+    /// The primitive value of a Boolean, Number or String object, or ``self`` if it is not one.
+    pub fn unwrap_primitive(self) -> Value {
+        match self {
+            Value::Object(obj) => match unsafe { &*obj }.kind {
+                ObjectKind::PrimitiveWrapper(val) => val,
+                _ => self,
+            },
+            _ => self,
+        }
+    }
+
+    /// The name of the constructor of the object wrapping ``self``, which is a primitive value.
+    pub fn wrapper_name(self) -> &'static str {
+        match self {
+            Value::Bool(_) => "Boolean",
+            Value::Number(_) => "Number",
+            Value::String(_) => "String",
+            _ => panic!(),
+        }
+    }
+
     pub fn into_str(self) -> &'static str {
         match self {
             Value::String(s) => cstrp_to_str(s),
@@ -542,18 +669,7 @@ impl Value {
             Value::Bool(0) => 0.0,
             Value::Bool(1) => 1.0,
             Value::Number(n) => *n,
-            Value::String(s) => {
-                let s = cstrp_to_str(*s);
-                if s == "Infinity" || s == "-Infinity" {
-                    ::std::f64::INFINITY
-                } else if s.len() == 0 {
-                    0.0
-                } else if s.chars().all(|c| c.is_whitespace()) {
-                    0.0
-                } else {
-                    s.parse::<f64>().unwrap_or(::std::f64::NAN)
-                }
-            }
+            Value::String(s) => string_to_number(cstrp_to_str(*s)),
             Value::Object(_) => self
                 .to_primitive(allocator, Some(PreferredType::Number))
                 .to_number(allocator),
@@ -562,6 +678,14 @@ impl Value {
         }
     }
 
+    /// ``to_string`` of a property key, which borrows strings.
+    pub fn to_property_key_str(&self) -> Cow<'_, str> {
+        match self {
+            Value::String(s) => Cow::Borrowed(cstrp_to_str(*s)),
+            _ => Cow::Owned(self.to_string()),
+        }
+    }
+
     // TODO: https://www.ecma-international.org/ecma-262/6.0/#sec-tostring
     pub fn to_string(&self) -> String {
         match self {
@@ -570,20 +694,31 @@ impl Value {
             Value::String(s) => cstrp_to_str(*s).to_string(),
             Value::Other(UNDEFINED) => "undefined".to_string(),
             Value::Other(NULL) => "null".to_string(),
-            Value::Number(n) => {
-                if n.is_nan() {
-                    "NaN".to_string()
-                } else if n.is_infinite() {
-                    "Infinity".to_string()
-                } else {
-                    format!("{}", n)
-                }
-            }
+            Value::Number(n) => number_to_str(*n).into_owned(),
             Value::Object(info) => {
                 let info = ObjectRef(*info);
                 match info.kind {
                     ObjectKind::Ordinary => "[object Object]".to_string(),
                     ObjectKind::Array(ref info) => info.join(None),
+                    ObjectKind::Map(_) => "[object Map]".to_string(),
+                    ObjectKind::Set(_) => "[object Set]".to_string(),
+                    ObjectKind::PrimitiveWrapper(val) => val.to_string(),
+                    // https://tc39.github.io/ecma262/#sec-error.prototype.tostring
+                    ObjectKind::Error(_) => {
+                        let name = match self.get_property("name") {
+                            name if name.is_undefined() => "Error".to_string(),
+                            name => name.to_string(),
+                        };
+                        let message = match self.get_property("message") {
+                            message if message.is_undefined() => "".to_string(),
+                            message => message.to_string(),
+                        };
+                        match (name.as_str(), message.as_str()) {
+                            (name, "") => name.to_string(),
+                            ("", message) => message.to_string(),
+                            (name, message) => format!("{}: {}", name, message),
+                        }
+                    }
                     _ => "[unimplemented]".to_string(), // TODO
                 }
             }
@@ -626,6 +761,8 @@ impl Value {
     }
 
     /// https://tc39.github.io/ecma262/#sec-toprimitive
+    ///
+    /// It calls no methods of objects. The operators use ``operations::to_primitive``, which does.
     pub fn to_primitive(
         &self,
         allocator: &mut gc::MemoryAllocator,
@@ -637,8 +774,6 @@ impl Value {
 
         let mut hint = preferred_type.unwrap_or(PreferredType::Default);
 
-        // TODO: Call @@toPrimitive if present
-
         if hint == PreferredType::Default {
             hint = PreferredType::Number
         }
@@ -676,6 +811,10 @@ impl Value {
                     ObjectKind::Function(_) => None,
                     ObjectKind::Array(_) => None,
                     ObjectKind::Error(_) => None,
+                    ObjectKind::Map(_) => None,
+                    ObjectKind::Set(_) => None,
+                    ObjectKind::Proxy(_) => None,
+                    ObjectKind::PrimitiveWrapper(val) => Some(val),
                     ObjectKind::Symbol(_) => Some(self), // TODO
                 }
             }
@@ -686,59 +825,7 @@ impl Value {
 }
 
 impl Value {
-    // TODO: https://www.ecma-international.org/ecma-262/6.0/#sec-addition-operator-plus-runtime-semantics-evaluation
-    pub fn add(self, allocator: &mut gc::MemoryAllocator, val: Value) -> Self {
-        let lprim = self.to_primitive(allocator, None);
-        let rprim = val.to_primitive(allocator, None);
-        match (lprim, rprim) {
-            (Value::Number(x), Value::Number(y)) => Value::Number(x + y),
-            (Value::String(x), Value::String(y)) => {
-                let x = cstrp_to_str(x);
-                let y = cstrp_to_str(y);
-                let cat = format!("{}{}", x, y);
-                Value::string(allocator, cat)
-            }
-            (Value::String(x), _) => {
-                let x = cstrp_to_str(x);
-                Value::string(allocator, format!("{}{}", x, rprim.to_string()))
-            }
-            (_, Value::String(y)) => {
-                let y = cstrp_to_str(y);
-                Value::string(allocator, format!("{}{}", lprim.to_string(), y))
-            }
-            (x, y) => Value::Number(x.to_number(allocator) + y.to_number(allocator)),
-        }
-    }
-
     // https://www.ecma-international.org/ecma-262/6.0/#sec-subtraction-operator-minus-runtime-semantics-evaluation
-    pub fn sub(self, allocator: &mut gc::MemoryAllocator, val: Value) -> Self {
-        match (self, val) {
-            (Value::Number(x), Value::Number(y)) => Value::Number(x - y),
-            (x, y) => Value::Number(x.to_number(allocator) - y.to_number(allocator)),
-        }
-    }
-
-    pub fn mul(self, val: Value) -> Self {
-        match (self, val) {
-            (Value::Number(x), Value::Number(y)) => Value::Number(x * y),
-            _ => Value::undefined(),
-        }
-    }
-
-    pub fn div(self, val: Value) -> Self {
-        match (self, val) {
-            (Value::Number(x), Value::Number(y)) => Value::Number(x / y),
-            _ => Value::undefined(),
-        }
-    }
-
-    pub fn rem(self, val: Value) -> Self {
-        match (self, val) {
-            (Value::Number(x), Value::Number(y)) => Value::Number((x as i64 % y as i64) as f64),
-            _ => Value::undefined(),
-        }
-    }
-
     pub fn exp(self, allocator: &mut gc::MemoryAllocator, val: Value) -> Self {
         Value::Number(self.to_number(allocator).powf(val.to_number(allocator)))
     }
@@ -774,31 +861,6 @@ impl Value {
         Value::Number((self.to_uint32(allocator) >> (val.to_uint32(allocator) & 0x1f)) as f64)
     }
 
-    // TODO: https://www.ecma-international.org/ecma-262/6.0/#sec-abstract-equality-comparison
-    pub fn eq(self, allocator: &mut gc::MemoryAllocator, val: Value) -> Self {
-        if self.is_same_type_as(&val) {
-            return self.strict_eq(val);
-        }
-
-        match (self, val) {
-            (Value::Other(NULL), Value::Other(UNDEFINED)) => return Value::bool(true),
-            (Value::Other(UNDEFINED), Value::Other(NULL)) => return Value::bool(true),
-            _ => {}
-        }
-
-        match (self, val) {
-            (Value::Number(x), Value::String(_)) => Value::bool(x == val.to_number(allocator)),
-            (Value::String(_), Value::Number(y)) => Value::bool(self.to_number(allocator) == y),
-            (Value::Bool(_), Value::Number(y)) => Value::bool(self.to_number(allocator) == y),
-            (Value::Number(x), Value::Bool(_)) => Value::bool(x == val.to_number(allocator)),
-            // (Value::Number(x), Value::Number(y)) => Value::Bool(if x == y { 1 } else { 0 }),
-            // (Value::Number(_), obj) | (Value::String(_), obj) => self.eq(val),
-            (Value::Object(_), _) => self.to_primitive(allocator, None).eq(allocator, val),
-            (_, Value::Object(_)) => val.to_primitive(allocator, None).eq(allocator, self),
-            _ => Value::bool(false),
-        }
-    }
-
     // TODO: https://www.ecma-international.org/ecma-262/6.0/#sec-strict-equality-comparison
     pub fn strict_eq(self, val: Value) -> Value {
         Value::bool(self.strict_eq_bool(val))
@@ -829,64 +891,28 @@ impl Value {
         }
     }
 
-    pub fn ne(self, allocator: &mut gc::MemoryAllocator, val: Value) -> Self {
-        Value::bool(!self.eq(allocator, val).into_bool())
-    }
-
     pub fn strict_ne(self, val: Value) -> Self {
         Value::bool(!self.strict_eq(val).into_bool())
     }
 
-    /// https://tc39.github.io/ecma262/#sec-abstract-relational-comparison
-    pub fn cmp(self, allocator: &mut gc::MemoryAllocator, val: Value) -> Self {
-        let px = self.to_primitive(allocator, None);
-        let py = val.to_primitive(allocator, None);
-
-        if let (Value::String(x), Value::String(y)) = (px, py) {
-            return Value::bool(cstrp_to_str(x) < cstrp_to_str(y));
-        }
-
-        let nx = px.to_number(allocator);
-        let ny = py.to_number(allocator);
-
-        if nx.is_nan() || ny.is_nan() {
-            return Value::undefined();
-        }
-
-        if nx == ny {
-            return Value::Bool(0);
-        }
-
-        Value::bool(nx < ny)
-    }
-
-    pub fn lt(self, allocator: &mut gc::MemoryAllocator, val: Value) -> Self {
-        match self.cmp(allocator, val) {
-            Value::Other(UNDEFINED) => Value::Bool(0),
-            otherwise => otherwise,
-        }
-    }
-
-    pub fn le(self, allocator: &mut gc::MemoryAllocator, val: Value) -> Self {
-        match val.cmp(allocator, self) {
-            Value::Other(UNDEFINED) | Value::Bool(1) => Value::Bool(0),
-            _ => Value::Bool(1),
+    /// https://tc39.github.io/ecma262/#sec-samevalue
+    pub fn same_value(self, val: Value) -> bool {
+        match (self, val) {
+            (Value::Number(x), Value::Number(y)) if x == 0.0 && y == 0.0 => {
+                x.is_sign_negative() == y.is_sign_negative()
+            }
+            _ => self.same_value_zero(val),
         }
     }
 
-    // TODO: https://www.ecma-international.org/ecma-262/6.0/#sec-unary-minus-operator-runtime-semantics-evaluation
-    pub fn minus(self) -> Self {
-        match self {
-            Value::Number(n) => Value::Number(-n),
-            _ => Value::undefined(),
+    /// https://tc39.github.io/ecma262/#sec-samevaluezero
+    pub fn same_value_zero(self, val: Value) -> bool {
+        match (self, val) {
+            (Value::Number(x), Value::Number(y)) if x.is_nan() && y.is_nan() => true,
+            _ => self.strict_eq_bool(val),
         }
     }
 
-    // TODO: https://www.ecma-international.org/ecma-262/6.0/#sec-unary-plus-operator-runtime-semantics-evaluation
-    pub fn positive(self, allocator: &mut gc::MemoryAllocator) -> Self {
-        Value::Number(self.to_number(allocator))
-    }
-
     pub fn is_same_type_as(&self, val: &Value) -> bool {
         match (self, val) {
             (Value::Other(UNINITIALIZED), Value::Other(UNINITIALIZED))
@@ -915,7 +941,12 @@ impl Value {
                     ObjectKind::Function(_) => "function",
                     ObjectKind::Array(_) => "object",
                     ObjectKind::Symbol(_) => "symbol",
-                    ObjectKind::Error(_) => "error",
+                    ObjectKind::Error(_) => "object",
+                    ObjectKind::Map(_) => "object",
+                    ObjectKind::Set(_) => "object",
+                    ObjectKind::Proxy(_) if self.is_callable() => "function",
+                    ObjectKind::Proxy(_) => "object",
+                    ObjectKind::PrimitiveWrapper(_) => "object",
                     ObjectKind::Ordinary => "object",
                 }
             }
@@ -926,7 +957,27 @@ impl Value {
 
 impl Value {
     pub fn debug_string(&self, nest: bool) -> String {
-        fn property_string(sorted_key_val: Vec<(&String, &Property)>) -> String {
+        fn property_value_string(prop: &Property) -> String {
+            match prop {
+                Property::Data(DataProperty { val, .. }) => val.debug_string(true),
+                Property::Accessor(AccessorProperty { get, set, .. }) => {
+                    let s_get = if get.is_undefined() { "" } else { "Getter" };
+                    let s_set = if set.is_undefined() { "" } else { "Setter" };
+                    format!(
+                        "[{}{}{}]",
+                        s_get,
+                        if !get.is_undefined() && !set.is_undefined() {
+                            "/"
+                        } else {
+                            ""
+                        },
+                        s_set
+                    )
+                }
+            }
+        }
+
+        fn property_string(sorted_key_val: Vec<(&Atom, &Property)>) -> String {
             sorted_key_val
                 .iter()
                 .enumerate()
@@ -935,23 +986,7 @@ impl Value {
                         "{}'{}': {}{}",
                         acc,
                         tupple.0,
-                        match tupple.1 {
-                            Property::Data(DataProperty { val, .. }) => val.debug_string(true),
-                            Property::Accessor(AccessorProperty { get, set, .. }) => {
-                                let s_get = if get.is_undefined() { "" } else { "Getter" };
-                                let s_set = if set.is_undefined() { "" } else { "Setter" };
-                                format!(
-                                    "[{}{}{}]",
-                                    s_get,
-                                    if !get.is_undefined() && !set.is_undefined() {
-                                        "/"
-                                    } else {
-                                        ""
-                                    },
-                                    s_set
-                                )
-                            }
-                        },
+                        property_value_string(tupple.1),
                         if i != sorted_key_val.len() - 1 {
                             ", "
                         } else {
@@ -970,15 +1005,7 @@ impl Value {
             Value::Bool(1) => "true".to_string(),
             Value::Bool(0) => "false".to_string(),
             Value::Bool(_) => unreachable!(),
-            Value::Number(n) => {
-                if n.is_nan() {
-                    "NaN".to_string()
-                } else if n.is_infinite() {
-                    "Infinity".to_string()
-                } else {
-                    format!("{}", n)
-                }
-            }
+            Value::Number(n) => number_to_str(*n).into_owned(),
             Value::String(s) => {
                 let s = cstrp_to_str(*s);
                 if nest {
@@ -991,10 +1018,9 @@ impl Value {
                 let obj_info = ObjectRef(*obj_info);
                 match obj_info.kind {
                     ObjectKind::Ordinary => {
-                        let mut sorted_key_val =
-                            (&obj_info.property)
-                                .iter()
-                                .collect::<Vec<(&String, &Property)>>();
+                        let mut sorted_key_val = (&obj_info.property)
+                            .iter()
+                            .collect::<Vec<(&Atom, &Property)>>();
                         sorted_key_val
                             .sort_by(|(key1, _), (key2, _)| key1.as_str().cmp(key2.as_str()));
 
@@ -1007,6 +1033,26 @@ impl Value {
                     ObjectKind::Error(ref _info) => {
                         format!("Error({})", obj_info.get_property("message").to_string())
                     }
+                    ObjectKind::Map(ref map_info) => {
+                        let entries = map_info
+                            .iter()
+                            .map(|(key, val)| {
+                                format!("{} => {}", key.debug_string(true), val.debug_string(true))
+                            })
+                            .collect::<Vec<String>>();
+                        format!("Map {{ {} }}", entries.join(", "))
+                    }
+                    ObjectKind::Set(ref set_info) => {
+                        let entries = set_info
+                            .iter()
+                            .map(|(key, _)| key.debug_string(true))
+                            .collect::<Vec<String>>();
+                        format!("Set {{ {} }}", entries.join(", "))
+                    }
+                    ObjectKind::Proxy(ref proxy_info) => proxy_info.target.debug_string(nest),
+                    ObjectKind::PrimitiveWrapper(val) => {
+                        format!("[{}: {}]", val.wrapper_name(), val.debug_string(true))
+                    }
                     ObjectKind::Function(ref func_info) => {
                         if let Some(ref name) = func_info.name {
                             format!("[Function: {}]", name)
@@ -1017,10 +1063,9 @@ impl Value {
                     ObjectKind::Array(ref ary_info) => {
                         let mut string = "[ ".to_string();
 
-                        let mut sorted_key_val =
-                            (&obj_info.property)
-                                .iter()
-                                .collect::<Vec<(&String, &Property)>>();
+                        let mut sorted_key_val = (&obj_info.property)
+                            .iter()
+                            .collect::<Vec<(&Atom, &Property)>>();
                         sorted_key_val
                             .sort_by(|(key1, _), (key2, _)| key1.as_str().cmp(key2.as_str()));
 
@@ -1029,7 +1074,7 @@ impl Value {
                         let mut i = 0;
                         while i < length {
                             let mut empty_elems = 0;
-                            while i < length && Value::empty() == ary_info.elems[i].as_data().val {
+                            while i < length && ary_info.get_own_element(i).is_none() {
                                 empty_elems += 1;
                                 i += 1;
                             }
@@ -1055,7 +1100,7 @@ impl Value {
                             string = format!(
                                 "{}{}{}",
                                 string,
-                                ary_info.elems[i].as_data().val.debug_string(true),
+                                property_value_string(&ary_info.elems[i]),
                                 if is_last_idx(i) && sorted_key_val.len() == 0 {
                                     " "
                                 } else {
@@ -1085,3 +1130,288 @@ pub fn is_integer(n: f64) -> bool {
 pub fn cstrp_to_str(p: *mut CString) -> &'static str {
     unsafe { &*p }.to_str().unwrap()
 }
+
+/// Digits of 000 to 999, so that small integers such as loop counters and array indices are
+/// converted to strings without formatting them.
+static SMALL_INTEGER_DIGITS: [u8; 3000] = small_integer_digits();
+
+const fn small_integer_digits() -> [u8; 3000] {
+    let mut digits = [0; 3000];
+    let mut n = 0;
+    while n < 1000 {
+        digits[n * 3] = b'0' + (n / 100) as u8;
+        digits[n * 3 + 1] = b'0' + (n / 10 % 10) as u8;
+        digits[n * 3 + 2] = b'0' + (n % 10) as u8;
+        n += 1;
+    }
+    digits
+}
+
+/// https://tc39.github.io/ecma262/#sec-tonumber-applied-to-the-string-type
+pub fn string_to_number(s: &str) -> f64 {
+    let s = s.trim_matches(is_white_space_or_line_terminator);
+    let bytes = s.as_bytes();
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    if bytes.len() > 2 && bytes[0] == b'0' {
+        match bytes[1] {
+            b'x' | b'X' => return non_decimal_to_number(&s[2..], 16),
+            b'o' | b'O' => return non_decimal_to_number(&s[2..], 8),
+            b'b' | b'B' => return non_decimal_to_number(&s[2..], 2),
+            _ => {}
+        }
+    }
+    let unsigned = match bytes[0] {
+        b'+' | b'-' => &s[1..],
+        _ => s,
+    };
+    if unsigned == "Infinity" {
+        return if bytes[0] == b'-' {
+            ::std::f64::NEG_INFINITY
+        } else {
+            ::std::f64::INFINITY
+        };
+    }
+    // str::parse also takes e.g. "inf" and "NaN", which are not numbers here.
+    if !is_decimal_digits(unsigned) {
+        return ::std::f64::NAN;
+    }
+    s.parse().unwrap_or(::std::f64::NAN)
+}
+
+/// WhiteSpace and LineTerminator. Unlike ``char::is_whitespace``, U+FEFF is included and U+0085
+/// is not.
+fn is_white_space_or_line_terminator(c: char) -> bool {
+    c == '\u{feff}' || (c.is_whitespace() && c != '\u{85}')
+}
+
+/// StrUnsignedDecimalLiteral except Infinity, e.g. "1", "1.", ".5" and "1.5e-3".
+fn is_decimal_digits(s: &str) -> bool {
+    fn skip_digits(bytes: &[u8], i: &mut usize) -> usize {
+        let start = *i;
+        while *i < bytes.len() && bytes[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i - start
+    }
+
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    let mut digits = skip_digits(bytes, &mut i);
+    if i < bytes.len() && bytes[i] == b'.' {
+        i += 1;
+        digits += skip_digits(bytes, &mut i);
+    }
+    if digits == 0 {
+        return false;
+    }
+    if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+        i += 1;
+        if i < bytes.len() && (bytes[i] == b'+' || bytes[i] == b'-') {
+            i += 1;
+        }
+        if skip_digits(bytes, &mut i) == 0 {
+            return false;
+        }
+    }
+    i == bytes.len()
+}
+
+/// The digits of a hex, octal or binary literal, rounded to the nearest double.
+fn non_decimal_to_number(digits: &str, radix: u32) -> f64 {
+    let bits_per_digit = radix.trailing_zeros();
+    let mut mantissa: u128 = 0;
+    let mut exponent = 0;
+    for c in digits.chars() {
+        let digit = match c.to_digit(radix) {
+            Some(digit) => digit,
+            None => return ::std::f64::NAN,
+        };
+        if mantissa >> 120 == 0 {
+            mantissa = mantissa << bits_per_digit | u128::from(digit);
+        } else {
+            // 120 bits are far more than a double has, so the rest only tells whether the
+            // mantissa is rounded up, which the lowest bit does as well.
+            exponent += bits_per_digit as i32;
+            if digit != 0 {
+                mantissa |= 1;
+            }
+        }
+    }
+    mantissa as f64 * 2f64.powi(exponent)
+}
+
+/// https://tc39.github.io/ecma262/#sec-tostring-applied-to-the-number-type
+pub fn number_to_str(n: f64) -> Cow<'static, str> {
+    if n.is_nan() {
+        Cow::Borrowed("NaN")
+    } else if n == std::f64::INFINITY {
+        Cow::Borrowed("Infinity")
+    } else if n == std::f64::NEG_INFINITY {
+        Cow::Borrowed("-Infinity")
+    } else if 0.0 <= n && n <= 999.0 && is_integer(n) {
+        let n = n as usize;
+        let start = n * 3
+            + match n {
+                0..=9 => 2,
+                10..=99 => 1,
+                _ => 0,
+            };
+        Cow::Borrowed(std::str::from_utf8(&SMALL_INTEGER_DIGITS[start..n * 3 + 3]).unwrap())
+    } else if n < 0.0 {
+        Cow::Owned(format!("-{}", finite_number_to_string(-n)))
+    } else {
+        Cow::Owned(finite_number_to_string(n))
+    }
+}
+
+/// The steps of Number::toString for a positive finite ``n``.
+fn finite_number_to_string(n: f64) -> String {
+    let (digits, exponent) = shortest_digits(n);
+    // n = 0.digits * 10^point, with k digits
+    let k = digits.len() as i32;
+    let point = exponent + 1;
+    if k <= point && point <= 21 {
+        digits + &"0".repeat((point - k) as usize)
+    } else if 0 < point && point <= 21 {
+        let (int, frac) = digits.split_at(point as usize);
+        format!("{}.{}", int, frac)
+    } else if -6 < point && point <= 0 {
+        format!("0.{}{}", "0".repeat(-point as usize), digits)
+    } else {
+        let sign = if point - 1 < 0 { '-' } else { '+' };
+        let (first, rest) = digits.split_at(1);
+        let dot = if rest.is_empty() { "" } else { "." };
+        format!("{}{}{}e{}{}", first, dot, rest, sign, (point - 1).abs())
+    }
+}
+
+/// The shortest digits which round trip to a positive finite ``n``, and the exponent of the
+/// first digit. They are those std formats ``n`` with by ``{:e}`` (Grisu, falling back to
+/// Dragon4), except that of two digits as close to ``n``, the spec takes the even one.
+fn shortest_digits(n: f64) -> (String, i32) {
+    let (digits, exponent) = split_scientific(&format!("{:e}", n));
+    let k = digits.len();
+
+    // Two k-digit candidates are as close to n only if n is halfway between them, i.e. n is
+    // exactly the k+1 digits ending with 5.
+    let (halfway, halfway_exponent) = split_scientific(&format!("{:.*e}", k, n));
+    if !halfway.ends_with('5') || halfway_exponent != exponent {
+        return (digits, exponent);
+    }
+    // A double has at most 767 significant digits.
+    let (exact, _) = split_scientific(&format!("{:.767e}", n));
+    if exact[k + 1..].bytes().any(|d| d != b'0') {
+        return (digits, exponent);
+    }
+
+    let lower = halfway[..k].to_string();
+    let mut upper = lower.clone().into_bytes();
+    for d in upper.iter_mut().rev() {
+        if *d == b'9' {
+            *d = b'0';
+        } else {
+            *d += 1;
+            break;
+        }
+    }
+    let upper = String::from_utf8(upper).unwrap();
+    let round_trips =
+        |digits: &str| format!("0.{}e{}", digits, exponent + 1).parse::<f64>() == Ok(n);
+    [lower, upper]
+        .iter()
+        .find(|digits| digits.ends_with(|d| "02468".contains(d)) && round_trips(digits))
+        .map_or((digits, exponent), |even| (even.clone(), exponent))
+}
+
+/// Split ``d.ddde-7`` into ``dddd`` and -7.
+fn split_scientific(scientific: &str) -> (String, i32) {
+    let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap());
+    (mantissa.replace('.', ""), exponent[1..].parse().unwrap())
+}
+
+#[test]
+fn small_integer_to_string() {
+    assert_eq!(number_to_str(0.0), "0");
+    assert_eq!(number_to_str(-0.0), "0");
+    assert_eq!(number_to_str(7.0), "7");
+    assert_eq!(number_to_str(42.0), "42");
+    assert_eq!(number_to_str(100.0), "100");
+    assert_eq!(number_to_str(999.0), "999");
+    assert!(match number_to_str(999.0) {
+        Cow::Borrowed(_) => true,
+        Cow::Owned(_) => false,
+    });
+    assert_eq!(number_to_str(1000.0), "1000");
+    assert_eq!(number_to_str(1.5), "1.5");
+    assert_eq!(number_to_str(-3.0), "-3");
+    for n in 0..1000 {
+        assert_eq!(number_to_str(n as f64), n.to_string());
+    }
+}
+
+#[test]
+fn string_to_number_() {
+    let cases = [
+        ("", 0.0),
+        (" \t\n\u{a0}\u{feff}\u{2028} ", 0.0),
+        ("  42  ", 42.0),
+        ("+1.5", 1.5),
+        ("-1.5e3", -1500.0),
+        ("1.", 1.0),
+        (".5", 0.5),
+        ("1e-7", 1e-7),
+        ("0.30000000000000004", 0.1 + 0.2),
+        ("007", 7.0),
+        ("Infinity", ::std::f64::INFINITY),
+        ("-Infinity", ::std::f64::NEG_INFINITY),
+        (" +Infinity ", ::std::f64::INFINITY),
+        ("0x1F", 31.0),
+        ("0XfF", 255.0),
+        ("0o17", 15.0),
+        ("0b101", 5.0),
+        ("0x20000000000001", 9007199254740992.0),
+        ("0x20000000000003", 9007199254740996.0),
+        ("0x1000000000000000000000000000000000000001", 2f64.powi(156)),
+    ];
+    for (s, expected) in &cases {
+        assert_eq!(string_to_number(s), *expected, "{:?}", s);
+    }
+    assert!(string_to_number("-0").is_sign_negative());
+    for s in &[
+        ".", "e5", "1e", "1e+", "1.2.3", "0x", "-0x1", "0x1g", "0b2", "0o8", "inf", "NaN",
+        "infinity", "1_000", "\u{85}1", "1 2", "--1",
+    ] {
+        assert!(string_to_number(s).is_nan(), "{:?}", s);
+    }
+}
+
+#[test]
+fn number_to_string() {
+    let cases = [
+        (0.1 + 0.2, "0.30000000000000004"),
+        (0.5, "0.5"),
+        (-0.0, "0"),
+        (123.456, "123.456"),
+        (1e20, "100000000000000000000"),
+        (1e21, "1e+21"),
+        (1.5e21, "1.5e+21"),
+        (123456789012345680000.0, "123456789012345680000"),
+        (1e-6, "0.000001"),
+        (1.5e-6, "0.0000015"),
+        (1e-7, "1e-7"),
+        (1.25e-7, "1.25e-7"),
+        (-1.5e300, "-1.5e+300"),
+        (5e-324, "5e-324"),
+        (std::f64::MAX, "1.7976931348623157e+308"),
+        (std::f64::NEG_INFINITY, "-Infinity"),
+        (9007199254740993.0, "9007199254740992"),
+        // Halfway between ...13.2 and ...13.3, so the even one.
+        (1752348300637913.25, "1752348300637913.2"),
+        (1752348300637913.75, "1752348300637913.8"),
+    ];
+    for (n, expected) in &cases {
+        assert_eq!(number_to_str(*n), *expected);
+    }
+}
@@ -0,0 +1,223 @@
+//! Transform of JSX elements into ordinary expressions before bytecode generation.
+//! How an element is transformed is up to the embedder: see ``JSXTransform``. By default,
+//! ``<a b={c}>d</a>`` becomes ``React.createElement("a", { b: c }, "d")``.
+
+use crate::node::{FormalParameter, JSXAttribute, JSXElement, Node, NodeBase, PropertyDefinition};
+use crate::util::StackLimit;
+use std::fmt::Debug;
+
+/// Converts a JSX element into an expression.
+pub trait JSXTransform: Debug {
+    /// ``element`` is at ``pos`` in the script. Its attributes and children are already
+    /// transformed.
+    fn transform(&self, element: JSXElement, pos: usize) -> Node;
+}
+
+/// Calls ``factory(type, props, ...children)``, where ``type`` is a string for an intrinsic
+/// element like ``<div>`` and a reference for a component like ``<Foo>``, and ``props`` is
+/// ``null`` if there are no attributes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FactoryCall {
+    /// The function called for an element, e.g. ``h`` or ``React.createElement``.
+    pub factory: String,
+    /// The type of a fragment ``<>...</>``, e.g. ``React.Fragment``.
+    pub fragment: String,
+}
+
+impl Default for FactoryCall {
+    fn default() -> Self {
+        FactoryCall {
+            factory: "React.createElement".to_string(),
+            fragment: "React.Fragment".to_string(),
+        }
+    }
+}
+
+impl JSXTransform for FactoryCall {
+    fn transform(&self, element: JSXElement, pos: usize) -> Node {
+        let JSXElement {
+            name,
+            attributes,
+            children,
+        } = element;
+
+        let ty = match name {
+            None => reference(&self.fragment, pos),
+            // https://facebook.github.io/jsx/#sec-jsx-element-names
+            Some(ref name) if is_intrinsic_element(name) => {
+                Node::new(NodeBase::String(name.clone()), pos)
+            }
+            Some(ref name) => reference(name, pos),
+        };
+
+        let props = if attributes.is_empty() {
+            Node::new(NodeBase::Null, pos)
+        } else {
+            let properties = attributes
+                .into_iter()
+                .map(|attribute| match attribute {
+                    JSXAttribute::Attribute(name, value) => {
+                        PropertyDefinition::Property(name, value)
+                    }
+                    JSXAttribute::Spread(value) => PropertyDefinition::SpreadObject(value),
+                })
+                .collect();
+            Node::new(NodeBase::Object(properties), pos)
+        };
+
+        let mut args = vec![ty, props];
+        args.extend(children);
+        Node::new(
+            NodeBase::Call(Box::new(reference(&self.factory, pos)), args),
+            pos,
+        )
+    }
+}
+
+/// A name starting with a lowercase letter or containing ``-`` or ``:`` is an intrinsic
+/// element, e.g. ``div`` and ``svg:rect``.
+fn is_intrinsic_element(name: &str) -> bool {
+    !name.contains('.')
+        && (name.starts_with(|c: char| c.is_lowercase())
+            || name.contains('-')
+            || name.contains(':'))
+}
+
+/// ``a.b.c`` as an identifier followed by member accesses.
+fn reference(path: &str, pos: usize) -> Node {
+    let mut names = path.split('.');
+    let first = names.next().unwrap_or("").to_string();
+    names.fold(Node::new(NodeBase::Identifier(first), pos), |node, name| {
+        Node::new(NodeBase::Member(Box::new(node), name.to_string()), pos)
+    })
+}
+
+/// Transform the JSX elements in ``node`` and its descendants with ``transform``.
+pub fn transform_jsx(node: &mut Node, transform: &dyn JSXTransform, stack_limit: &StackLimit) {
+    if stack_limit.is_exceeded() {
+        return;
+    }
+
+    let visit = |node: &mut Node| transform_jsx(node, transform, stack_limit);
+    match node.base {
+        NodeBase::StatementList(ref mut nodes)
+        | NodeBase::Block(ref mut nodes)
+        | NodeBase::Array(ref mut nodes) => nodes.iter_mut().for_each(visit),
+        NodeBase::FunctionDecl(_, ref mut params, ref mut body)
+        | NodeBase::FunctionExpr(_, ref mut params, ref mut body)
+        | NodeBase::ArrowFunction(ref mut params, ref mut body) => {
+            for FormalParameter { init, .. } in params {
+                if let Some(init) = init {
+                    visit(init)
+                }
+            }
+            visit(body)
+        }
+        NodeBase::VarDecl(_, Some(ref mut node), _)
+        | NodeBase::Return(Some(ref mut node))
+        | NodeBase::Member(ref mut node, _)
+        | NodeBase::New(ref mut node)
+        | NodeBase::UnaryOp(ref mut node, _)
+        | NodeBase::Label(_, ref mut node)
+        | NodeBase::Throw(ref mut node)
+        | NodeBase::Spread(ref mut node) => visit(node),
+        NodeBase::Call(ref mut callee, ref mut args) => {
+            visit(callee);
+            args.iter_mut().for_each(visit)
+        }
+        NodeBase::Index(ref mut lhs, ref mut rhs)
+        | NodeBase::While(ref mut lhs, ref mut rhs)
+        | NodeBase::Assign(ref mut lhs, ref mut rhs)
+        | NodeBase::BinaryOp(ref mut lhs, ref mut rhs, _) => {
+            visit(lhs);
+            visit(rhs)
+        }
+        NodeBase::If(ref mut a, ref mut b, ref mut c)
+        | NodeBase::TernaryOp(ref mut a, ref mut b, ref mut c) => {
+            visit(a);
+            visit(b);
+            visit(c)
+        }
+        NodeBase::For(ref mut a, ref mut b, ref mut c, ref mut d)
+        | NodeBase::Try(ref mut a, ref mut b, ref mut c, ref mut d) => {
+            visit(a);
+            visit(b);
+            visit(c);
+            visit(d)
+        }
+        NodeBase::Object(ref mut properties) => {
+            for property in properties {
+                match property {
+                    PropertyDefinition::Property(_, node)
+                    | PropertyDefinition::MethodDefinition(_, _, node)
+                    | PropertyDefinition::SpreadObject(node) => visit(node),
+                    PropertyDefinition::IdentifierReference(_) => {}
+                }
+            }
+        }
+        NodeBase::JSXElement(ref mut element) => {
+            for attribute in &mut element.attributes {
+                match attribute {
+                    JSXAttribute::Attribute(_, node) | JSXAttribute::Spread(node) => visit(node),
+                }
+            }
+            element.children.iter_mut().for_each(visit);
+        }
+        _ => {}
+    }
+
+    let element = match node.base {
+        NodeBase::JSXElement(ref mut element) => ::std::mem::replace(
+            &mut **element,
+            JSXElement {
+                name: None,
+                attributes: vec![],
+                children: vec![],
+            },
+        ),
+        _ => return,
+    };
+    *node = transform.transform(element, node.pos);
+}
+
+#[test]
+fn transform_to_factory_calls() {
+    use crate::parser::{Parser, ParserOptions};
+
+    let transform = |code: &str| {
+        let mut node = Parser::new("test", code)
+            .with_options(ParserOptions {
+                jsx: true,
+                ..ParserOptions::default()
+            })
+            .parse_all()
+            .unwrap();
+        let factory = FactoryCall {
+            factory: "h".to_string(),
+            fragment: "F".to_string(),
+        };
+        transform_jsx(&mut node, &factory, &StackLimit::new());
+        node
+    };
+    let parse = |code: &str| Parser::new("test", code).parse_all().unwrap();
+    let assert_transformed = |jsx: &str, js: &str| {
+        // Compare the structure only, as the positions differ.
+        assert_eq!(
+            format!("{:?}", transform(jsx)).replace(char::is_numeric, ""),
+            format!("{:?}", parse(js)).replace(char::is_numeric, ""),
+            "{}",
+            jsx
+        );
+    };
+
+    assert_transformed("<div />", "h('div', null)");
+    assert_transformed(
+        "<a.B x=\"1\" y={z} {...w} data-v>t</a.B>",
+        "h(a.B, { x: '1', y: z, ...w, 'data-v': true }, 't')",
+    );
+    assert_transformed(
+        "<>\n  <Foo>{ok && <b>yes</b>}</Foo>\n  a  b\n</>",
+        "h(F, null, h(Foo, null, ok && h('b', null, 'yes')), 'a  b')",
+    );
+    assert_transformed("x < y", "x < y");
+}
@@ -0,0 +1,66 @@
+//! Limits on the sizes of the values a script can make and on the depth of calls, so that a
+//! script can not exhaust the memory of the embedder, e.g. by doubling a string in a loop, or
+//! overflow the native stack by deep recursion.
+//! Exceeding a limit throws a RangeError. Set them with ``VM::limits``.
+
+use crate::vm::error::RuntimeError;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    /// Maximum length of a string in bytes.
+    pub max_string_length: usize,
+    /// Maximum length of an array. Arrays are always dense in this engine, so the default is
+    /// much lower than 2^32-1 of the spec.
+    pub max_array_length: usize,
+    /// Maximum number of the properties of an object, not counting array elements.
+    pub max_property_count: usize,
+    /// Maximum number of the frames being run.
+    pub max_call_depth: usize,
+    /// Maximum size in bytes of the native stack used by the scripts being run. The functions
+    /// called by builtins, e.g. the callbacks of ``Array.prototype.forEach`` and getters, are
+    /// run on the native stack, so the default is low enough for the 2 MiB stack of a thread.
+    pub max_native_stack_size: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            // The same as V8 on 64-bit platforms.
+            max_string_length: (1 << 30) - 25,
+            max_array_length: 1 << 26,
+            max_property_count: 1 << 24,
+            max_call_depth: 10000,
+            max_native_stack_size: 1 << 20,
+        }
+    }
+}
+
+impl Limits {
+    pub fn check_string_length(&self, len: usize) -> Result<(), RuntimeError> {
+        if len > self.max_string_length {
+            return Err(RuntimeError::range("Invalid string length"));
+        }
+        Ok(())
+    }
+
+    pub fn check_array_length(&self, len: usize) -> Result<(), RuntimeError> {
+        if len > self.max_array_length {
+            return Err(RuntimeError::range("Invalid array length"));
+        }
+        Ok(())
+    }
+
+    pub fn check_property_count(&self, count: usize) -> Result<(), RuntimeError> {
+        if count > self.max_property_count {
+            return Err(RuntimeError::range("Too many properties"));
+        }
+        Ok(())
+    }
+
+    pub fn check_call_depth(&self, depth: usize, stack_size: usize) -> Result<(), RuntimeError> {
+        if depth > self.max_call_depth || stack_size > self.max_native_stack_size {
+            return Err(RuntimeError::range("Maximum call stack size exceeded"));
+        }
+        Ok(())
+    }
+}
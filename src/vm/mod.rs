@@ -1,8 +1,25 @@
 #[macro_use]
 pub mod jsvalue;
+pub mod access_check;
+pub mod atom;
+pub mod bytecode_file;
 pub mod codegen;
 pub mod constant;
+pub mod constant_folding;
+pub mod coverage;
+pub mod dead_code;
 pub mod error;
 pub mod exec_context;
 pub mod factory;
+pub mod function_profile;
+pub mod internal_methods;
+pub mod interrupt;
+#[cfg(feature = "jsx")]
+pub mod jsx;
+pub mod limits;
+pub mod operations;
+pub mod realm;
+pub mod scope_analysis;
+pub mod source_position;
+pub mod stack_trace;
 pub mod vm;
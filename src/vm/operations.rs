@@ -0,0 +1,579 @@
+//! The abstract operations the operators are defined with: ToPrimitive, ToNumber and ToString,
+//! and the comparisons of ``==`` and ``<``. Unlike the conversions of ``Value``, they call the
+//! methods of objects (``@@toPrimitive``, ``valueOf`` and ``toString``), so they take the VM and
+//! may throw.
+//!
+//! The methods may run the GC. The operands given must be reachable from the roots, e.g. be on
+//! the stack, while the values made on the way are kept alive here.
+//!
+//! https://tc39.github.io/ecma262/#sec-abstract-operations
+
+use crate::vm::{
+    error::{message, RuntimeError},
+    jsvalue::value::{PreferredType, Value},
+    vm::{VMValueResult, VM},
+};
+
+/// The types of the spec. Symbols are objects in ``Value``.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Undefined,
+    Null,
+    Boolean,
+    String,
+    Symbol,
+    Number,
+    Object,
+}
+
+fn type_of(val: Value) -> Type {
+    match val {
+        Value::Bool(_) => Type::Boolean,
+        Value::String(_) => Type::String,
+        Value::Number(_) => Type::Number,
+        Value::Object(_) if val.is_symbol() => Type::Symbol,
+        Value::Object(_) => Type::Object,
+        _ if val.is_null() => Type::Null,
+        _ => Type::Undefined,
+    }
+}
+
+/// Run ``f`` with ``val`` on the stack, so that the GC run by ``f`` does not collect it.
+fn keeping<T>(
+    vm: &mut VM,
+    val: Value,
+    f: impl FnOnce(&mut VM) -> Result<T, RuntimeError>,
+) -> Result<T, RuntimeError> {
+    vm.current_context.stack.push(val.into());
+    let result = f(vm);
+    vm.current_context.stack.pop();
+    result
+}
+
+/// https://tc39.github.io/ecma262/#sec-getmethod
+fn get_method(vm: &mut VM, val: Value, key: Value) -> VMValueResult {
+    let func = vm.get_property_by_value(val, key)?;
+    if func.is_undefined() || func.is_null() {
+        return Ok(Value::undefined());
+    }
+    if !func.is_callable() {
+        return Err(vm.throw_type_error(message::not_a_function(func)));
+    }
+    Ok(func)
+}
+
+/// https://tc39.github.io/ecma262/#sec-toprimitive
+pub fn to_primitive(vm: &mut VM, val: Value, preferred_type: PreferredType) -> VMValueResult {
+    if type_of(val) != Type::Object {
+        return Ok(val);
+    }
+
+    let to_primitive = vm.factory.well_known_symbols.to_primitive;
+    let exotic_to_prim = get_method(vm, val, to_primitive)?;
+    if !exotic_to_prim.is_undefined() {
+        let hint = vm.factory.string(match preferred_type {
+            PreferredType::Default => "default",
+            PreferredType::Number => "number",
+            PreferredType::String => "string",
+        });
+        let result = vm.call_function(exotic_to_prim, &[hint], val)?;
+        if type_of(result) == Type::Object {
+            return Err(vm.throw_type_error(message::cannot_convert_to_primitive()));
+        }
+        return Ok(result);
+    }
+
+    let hint = match preferred_type {
+        PreferredType::Default => PreferredType::Number,
+        hint => hint,
+    };
+    ordinary_to_primitive(vm, val, hint)
+}
+
+/// https://tc39.github.io/ecma262/#sec-ordinarytoprimitive
+///
+/// Object.prototype has neither ``valueOf`` nor ``toString`` yet, so an object which has neither
+/// of them is converted by ``Value::to_string`` (e.g. to "[object Object]") instead of being a
+/// TypeError.
+pub fn ordinary_to_primitive(vm: &mut VM, val: Value, hint: PreferredType) -> VMValueResult {
+    let method_names = match hint {
+        PreferredType::String => ["toString", "valueOf"],
+        _ => ["valueOf", "toString"],
+    };
+    let mut has_method = false;
+    for name in &method_names {
+        let key = vm.factory.string(*name);
+        let method = vm.get_property_by_value(val, key)?;
+        if method.is_callable() {
+            has_method = true;
+            let result = vm.call_function(method, &[], val)?;
+            if type_of(result) != Type::Object {
+                return Ok(result);
+            }
+        }
+    }
+
+    if has_method {
+        return Err(vm.throw_type_error(message::cannot_convert_to_primitive()));
+    }
+    Ok(vm.factory.string(val.to_string()))
+}
+
+/// https://tc39.github.io/ecma262/#sec-tonumber
+pub fn to_number(vm: &mut VM, val: Value) -> Result<f64, RuntimeError> {
+    match type_of(val) {
+        Type::Symbol => Err(vm.throw_type_error(message::cannot_convert_symbol("number"))),
+        Type::Object => {
+            let prim = to_primitive(vm, val, PreferredType::Number)?;
+            to_number(vm, prim)
+        }
+        _ => Ok(val.to_number(&mut vm.factory.memory_allocator)),
+    }
+}
+
+/// https://tc39.github.io/ecma262/#sec-tostring
+pub fn to_string(vm: &mut VM, val: Value) -> Result<String, RuntimeError> {
+    match type_of(val) {
+        Type::Symbol => Err(vm.throw_type_error(message::cannot_convert_symbol("string"))),
+        Type::Object => {
+            let prim = to_primitive(vm, val, PreferredType::String)?;
+            to_string(vm, prim)
+        }
+        _ => Ok(val.to_string()),
+    }
+}
+
+/// https://tc39.github.io/ecma262/#sec-topropertykey
+///
+/// Only objects are converted. Other keys are left as they are, since numbers are taken as array
+/// indices, and the others are converted to strings by ``Value::to_property_key_str``.
+pub fn to_property_key(vm: &mut VM, key: Value) -> VMValueResult {
+    if type_of(key) != Type::Object {
+        return Ok(key);
+    }
+    match to_primitive(vm, key, PreferredType::String)? {
+        key if key.is_symbol() => Ok(key),
+        key => {
+            let key = to_string(vm, key)?;
+            Ok(vm.factory.string(key))
+        }
+    }
+}
+
+/// https://tc39.github.io/ecma262/#sec-addition-operator-plus-runtime-semantics-evaluation
+pub fn add(vm: &mut VM, lval: Value, rval: Value) -> VMValueResult {
+    let lprim = to_primitive(vm, lval, PreferredType::Default)?;
+    let rprim = keeping(vm, lprim, |vm| {
+        to_primitive(vm, rval, PreferredType::Default)
+    })?;
+
+    if type_of(lprim) == Type::String || type_of(rprim) == Type::String {
+        let lstr = to_string(vm, lprim)?;
+        let rstr = to_string(vm, rprim)?;
+        vm.factory
+            .memory_allocator
+            .limits
+            .check_string_length(lstr.len() + rstr.len())?;
+        return Ok(vm.factory.string(lstr + &rstr));
+    }
+
+    Ok(Value::Number(to_number(vm, lprim)? + to_number(vm, rprim)?))
+}
+
+/// Apply the numeric operator ``op`` to ``lval`` and ``rval``, converted by ToNumber in this
+/// order (which calls their ``valueOf``). Used by the operators other than ``+``.
+/// https://tc39.github.io/ecma262/#sec-applystringornumericbinaryoperator
+pub fn numeric_operation(
+    vm: &mut VM,
+    lval: Value,
+    rval: Value,
+    op: impl FnOnce(f64, f64) -> f64,
+) -> VMValueResult {
+    let lnum = to_number(vm, lval)?;
+    let rnum = to_number(vm, rval)?;
+    Ok(Value::Number(op(lnum, rnum)))
+}
+
+/// https://tc39.github.io/ecma262/#sec-abstract-equality-comparison
+pub fn loosely_equal(vm: &mut VM, x: Value, y: Value) -> Result<bool, RuntimeError> {
+    let allocator = &mut vm.factory.memory_allocator;
+    match (type_of(x), type_of(y)) {
+        (tx, ty) if tx == ty => Ok(x.strict_eq_bool(y)),
+        (Type::Null, Type::Undefined) | (Type::Undefined, Type::Null) => Ok(true),
+        (Type::Number, Type::String) | (Type::String, Type::Number) => {
+            Ok(x.to_number(allocator) == y.to_number(allocator))
+        }
+        (Type::Boolean, _) => {
+            let nx = Value::Number(x.to_number(allocator));
+            loosely_equal(vm, nx, y)
+        }
+        (_, Type::Boolean) => {
+            let ny = Value::Number(y.to_number(allocator));
+            loosely_equal(vm, x, ny)
+        }
+        (Type::String, Type::Object)
+        | (Type::Number, Type::Object)
+        | (Type::Symbol, Type::Object) => {
+            let py = to_primitive(vm, y, PreferredType::Default)?;
+            loosely_equal(vm, x, py)
+        }
+        (Type::Object, Type::String)
+        | (Type::Object, Type::Number)
+        | (Type::Object, Type::Symbol) => {
+            let px = to_primitive(vm, x, PreferredType::Default)?;
+            loosely_equal(vm, px, y)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// https://tc39.github.io/ecma262/#sec-abstract-relational-comparison
+///
+/// Whether ``x`` is less than ``y``, or None (undefined in the spec) if either is NaN.
+/// ``left_first`` is whether ``x`` is converted before ``y``, i.e. appears first in the script.
+pub fn less_than(
+    vm: &mut VM,
+    x: Value,
+    y: Value,
+    left_first: bool,
+) -> Result<Option<bool>, RuntimeError> {
+    let (px, py) = if left_first {
+        let px = to_primitive(vm, x, PreferredType::Number)?;
+        let py = keeping(vm, px, |vm| to_primitive(vm, y, PreferredType::Number))?;
+        (px, py)
+    } else {
+        let py = to_primitive(vm, y, PreferredType::Number)?;
+        let px = keeping(vm, py, |vm| to_primitive(vm, x, PreferredType::Number))?;
+        (px, py)
+    };
+
+    if let (Value::String(_), Value::String(_)) = (px, py) {
+        // Strings are compared by their UTF-16 code units, not by their code points.
+        let (sx, sy) = (px.into_str(), py.into_str());
+        return Ok(Some(sx.encode_utf16().lt(sy.encode_utf16())));
+    }
+
+    let nx = to_number(vm, px)?;
+    let ny = to_number(vm, py)?;
+    if nx.is_nan() || ny.is_nan() {
+        return Ok(None);
+    }
+    Ok(Some(nx < ny))
+}
+
+/// ``x < y``
+pub fn lt(vm: &mut VM, x: Value, y: Value) -> Result<bool, RuntimeError> {
+    Ok(less_than(vm, x, y, true)? == Some(true))
+}
+
+/// ``x > y``
+pub fn gt(vm: &mut VM, x: Value, y: Value) -> Result<bool, RuntimeError> {
+    Ok(less_than(vm, y, x, false)? == Some(true))
+}
+
+/// ``x <= y``
+pub fn le(vm: &mut VM, x: Value, y: Value) -> Result<bool, RuntimeError> {
+    Ok(less_than(vm, y, x, false)? == Some(false))
+}
+
+/// ``x >= y``
+pub fn ge(vm: &mut VM, x: Value, y: Value) -> Result<bool, RuntimeError> {
+    Ok(less_than(vm, x, y, true)? == Some(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn return_one(_: &mut VM, _: &[Value], _: Value) -> VMValueResult {
+        Ok(Value::Number(1.0))
+    }
+
+    fn return_b(vm: &mut VM, _: &[Value], _: Value) -> VMValueResult {
+        Ok(vm.factory.string("b"))
+    }
+
+    fn return_this(_: &mut VM, _: &[Value], this: Value) -> VMValueResult {
+        Ok(this)
+    }
+
+    fn return_hint(_: &mut VM, args: &[Value], _: Value) -> VMValueResult {
+        Ok(args[0])
+    }
+
+    /// ``{ [name]: func }``, where ``name`` is a string or a symbol.
+    fn object_with(vm: &mut VM, name: Value, func: crate::builtin::BuiltinFuncTy) -> Value {
+        let obj = vm.factory.object(make_property_map!());
+        let func = vm.factory.builtin_function("f", func);
+        vm.set_property_by_value(obj, name, func).unwrap();
+        obj
+    }
+
+    /// Every pair of ``values``, compared by ``op``, against the rows of ``expected``.
+    fn check_table(
+        vm: &mut VM,
+        values: &[Value],
+        expected: &[&str],
+        op: impl Fn(&mut VM, Value, Value) -> char,
+    ) {
+        for (x, row) in values.iter().zip(expected) {
+            for (y, expected) in values.iter().zip(row.chars()) {
+                assert_eq!(
+                    op(vm, *x, *y),
+                    expected,
+                    "{} and {}",
+                    x.debug_string(true),
+                    y.debug_string(true)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn exotic_to_primitive() {
+        let mut vm = VM::new();
+        let to_prim = vm.factory.well_known_symbols.to_primitive;
+        let obj = object_with(&mut vm, to_prim, return_hint);
+        for (hint, name) in &[
+            (PreferredType::Default, "default"),
+            (PreferredType::Number, "number"),
+            (PreferredType::String, "string"),
+        ] {
+            let prim = to_primitive(&mut vm, obj, *hint).unwrap();
+            assert_eq!(prim.to_string(), *name);
+        }
+        // @@toPrimitive is called even if there are valueOf and toString.
+        let value_of = vm.factory.string("valueOf");
+        let func = vm.factory.builtin_function("valueOf", return_one);
+        vm.set_property_by_value(obj, value_of, func).unwrap();
+        assert_eq!(
+            add(&mut vm, obj, obj).unwrap().to_string(),
+            "defaultdefault"
+        );
+
+        // It must return a primitive.
+        let obj = object_with(&mut vm, to_prim, return_this);
+        assert!(to_primitive(&mut vm, obj, PreferredType::Default).is_err());
+        // It must be callable if present.
+        let obj = vm.factory.object(make_property_map!());
+        let not_callable = vm.factory.string("x");
+        vm.set_property_by_value(obj, to_prim, not_callable)
+            .unwrap();
+        assert!(to_primitive(&mut vm, obj, PreferredType::Default).is_err());
+        // An undefined @@toPrimitive is ignored.
+        let obj = vm.factory.object(make_property_map!());
+        vm.set_property_by_value(obj, to_prim, Value::undefined())
+            .unwrap();
+        let prim = to_primitive(&mut vm, obj, PreferredType::Default).unwrap();
+        assert_eq!(prim.to_string(), "[object Object]");
+    }
+
+    #[test]
+    fn ordinary_to_primitive_order() {
+        let mut vm = VM::new();
+        let value_of = vm.factory.string("valueOf");
+        let to_string_key = vm.factory.string("toString");
+        let obj = object_with(&mut vm, value_of, return_one);
+        let func = vm.factory.builtin_function("toString", return_b);
+        vm.set_property_by_value(obj, to_string_key, func).unwrap();
+
+        // valueOf is preferred except for the string hint.
+        let prim = to_primitive(&mut vm, obj, PreferredType::Default).unwrap();
+        assert_eq!(prim.into_number(), 1.0);
+        assert_eq!(to_number(&mut vm, obj).unwrap(), 1.0);
+        assert_eq!(to_string(&mut vm, obj).unwrap(), "b");
+        assert_eq!(add(&mut vm, obj, obj).unwrap().into_number(), 2.0);
+
+        // A method which returns an object is skipped.
+        let obj = object_with(&mut vm, value_of, return_this);
+        let func = vm.factory.builtin_function("toString", return_b);
+        vm.set_property_by_value(obj, to_string_key, func).unwrap();
+        assert_eq!(to_number(&mut vm, obj).unwrap().is_nan(), true);
+        // If every method returns an object, it is a TypeError.
+        let obj = object_with(&mut vm, value_of, return_this);
+        assert!(to_primitive(&mut vm, obj, PreferredType::Number).is_err());
+        assert!(add(&mut vm, obj, Value::Number(1.0)).is_err());
+    }
+
+    #[test]
+    fn symbol_conversions() {
+        let mut vm = VM::new();
+        let sym = vm.factory.symbol(None);
+        assert!(to_primitive(&mut vm, sym, PreferredType::Default).unwrap() == sym);
+        assert!(to_number(&mut vm, sym).is_err());
+        assert!(to_string(&mut vm, sym).is_err());
+        assert!(add(&mut vm, sym, Value::Number(1.0)).is_err());
+        let empty = vm.factory.string("");
+        assert!(add(&mut vm, empty, sym).is_err());
+        assert!(lt(&mut vm, sym, Value::Number(1.0)).is_err());
+        assert_eq!(loosely_equal(&mut vm, sym, sym).unwrap(), true);
+    }
+
+    #[test]
+    fn addition() {
+        let mut vm = VM::new();
+        let one = vm.factory.string("1");
+        let obj = vm.factory.object(make_property_map!());
+        let cases = [
+            (Value::Number(1.0), Value::Number(2.0), "3"),
+            (Value::Number(1.0), one, "11"),
+            (one, Value::Number(1.0), "11"),
+            (Value::bool(true), Value::Number(1.0), "2"),
+            (Value::null(), Value::Number(1.0), "1"),
+            (Value::undefined(), Value::Number(1.0), "NaN"),
+            (Value::undefined(), one, "undefined1"),
+            (obj, one, "[object Object]1"),
+            (Value::Number(1.0), obj, "1[object Object]"),
+        ];
+        for (x, y, expected) in &cases {
+            assert_eq!(add(&mut vm, *x, *y).unwrap().to_string(), *expected);
+        }
+    }
+
+    #[test]
+    fn numeric_operators() {
+        let mut vm = VM::new();
+        let value_of = vm.factory.string("valueOf");
+        let one = object_with(&mut vm, value_of, return_one);
+        let two = vm.factory.string("2");
+        let sub = |vm: &mut VM, x, y| numeric_operation(vm, x, y, |x, y| x - y);
+        assert_eq!(sub(&mut vm, one, two).unwrap().into_number(), -1.0);
+        assert_eq!(sub(&mut vm, two, one).unwrap().into_number(), 1.0);
+        assert_eq!(
+            sub(&mut vm, Value::null(), one).unwrap().into_number(),
+            -1.0
+        );
+        assert!(sub(&mut vm, Value::undefined(), one)
+            .unwrap()
+            .into_number()
+            .is_nan());
+        let sym = vm.factory.symbol(None);
+        assert!(sub(&mut vm, sym, one).is_err());
+        let obj = object_with(&mut vm, value_of, return_this);
+        assert!(sub(&mut vm, one, obj).is_err());
+    }
+
+    #[test]
+    fn loose_equality() {
+        let mut vm = VM::new();
+        let strs: Vec<Value> = ["", "0", "1", "abc", "[object Object]"]
+            .iter()
+            .map(|s| vm.factory.string(*s))
+            .collect();
+        let sym = vm.factory.symbol(None);
+        let value_of = vm.factory.string("valueOf");
+        let one = object_with(&mut vm, value_of, return_one);
+        let plain = vm.factory.object(make_property_map!());
+        let values = [
+            Value::undefined(),
+            Value::null(),
+            Value::bool(true),
+            Value::bool(false),
+            Value::Number(0.0),
+            Value::Number(1.0),
+            Value::Number(::std::f64::NAN),
+            strs[0],
+            strs[1],
+            strs[2],
+            strs[3],
+            sym,
+            one,
+            plain,
+            strs[4],
+        ];
+        // As node computes them.
+        let expected = [
+            "110000000000000",
+            "110000000000000",
+            "001001000100100",
+            "000110011000000",
+            "000110011000000",
+            "001001000100100",
+            "000000000000000",
+            "000110010000000",
+            "000110001000000",
+            "001001000100100",
+            "000000000010000",
+            "000000000001000",
+            "001001000100100",
+            "000000000000011",
+            "000000000000011",
+        ];
+        check_table(&mut vm, &values, &expected, |vm, x, y| {
+            if loosely_equal(vm, x, y).unwrap() {
+                '1'
+            } else {
+                '0'
+            }
+        });
+    }
+
+    #[test]
+    fn relational_comparison() {
+        let mut vm = VM::new();
+        let strs: Vec<Value> = ["", "0", "1", "10", "9", "a", "b", "\u{ff61}", "\u{1f600}"]
+            .iter()
+            .map(|s| vm.factory.string(*s))
+            .collect();
+        let value_of = vm.factory.string("valueOf");
+        let to_string_key = vm.factory.string("toString");
+        let one = object_with(&mut vm, value_of, return_one);
+        let b = object_with(&mut vm, to_string_key, return_b);
+        let mut values = vec![
+            Value::undefined(),
+            Value::null(),
+            Value::bool(true),
+            Value::bool(false),
+            Value::Number(0.0),
+            Value::Number(-0.0),
+            Value::Number(1.0),
+            Value::Number(::std::f64::NAN),
+            Value::Number(::std::f64::INFINITY),
+        ];
+        values.extend(strs);
+        values.extend(&[one, b]);
+        // x < y is t(rue), f(alse) or u(ndefined), as node computes them.
+        let expected = [
+            "uuuuuuuuuuuuuuuuuuuu",
+            "uftffftutfftttuuuutu",
+            "uffffffutfffttuuuufu",
+            "uftffftutfftttuuuutu",
+            "uftffftutfftttuuuutu",
+            "uftffftutfftttuuuutu",
+            "uffffffutfffttuuuufu",
+            "uuuuuuuuuuuuuuuuuuuu",
+            "uffffffuffffffuuuufu",
+            "uftffftutftttttttttt",
+            "uftffftutffttttttttt",
+            "uffffffutfffttttttft",
+            "uffffffutfffftttttft",
+            "uffffffutfffffttttft",
+            "uuuuuuuuufffffftttut",
+            "uuuuuuuuufffffffttuf",
+            "uuuuuuuuufffffffffuf",
+            "uuuuuuuuuffffffftfuf",
+            "uffffffutfffttuuuufu",
+            "uuuuuuuuufffffffttuf",
+        ];
+        check_table(&mut vm, &values, &expected, |vm, x, y| {
+            match less_than(vm, x, y, true).unwrap() {
+                Some(true) => 't',
+                Some(false) => 'f',
+                None => 'u',
+            }
+        });
+        // The operators derived from it.
+        check_table(&mut vm, &values, &expected, |vm, x, y| {
+            let lt = lt(vm, x, y).unwrap();
+            assert_eq!(gt(vm, y, x).unwrap(), lt);
+            let ge = ge(vm, x, y).unwrap();
+            assert_eq!(le(vm, y, x).unwrap(), ge);
+            match (lt, ge) {
+                (true, false) => 't',
+                (false, true) => 'f',
+                (false, false) => 'u',
+                _ => unreachable!(),
+            }
+        });
+    }
+}
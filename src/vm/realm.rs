@@ -0,0 +1,26 @@
+//! Realms. A realm is a global object with its own set of builtins (e.g. ``Object.prototype``),
+//! so that the scripts run in different realms of a VM do not see each other's globals or
+//! changes to the builtins, while sharing one heap. ``VM::create_realm`` makes a realm, and
+//! ``VM::eval_in_realm`` runs a script in it.
+//!
+//! Objects are made with the builtins of the realm being run, which is not always the realm a
+//! function was defined in, e.g. when a function of one realm is called from another. Symbols
+//! registered by ``Symbol.for`` and the well-known symbols are shared by all the realms.
+
+use crate::vm::exec_context::LexicalEnvironmentRef;
+use crate::vm::jsvalue::prototype::ObjectPrototypes;
+
+/// A handle of a realm, only valid for the VM which made it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RealmId(pub(crate) usize);
+
+impl RealmId {
+    /// The realm the VM starts with.
+    pub const MAIN: RealmId = RealmId(0);
+}
+
+#[derive(Clone, Debug)]
+pub struct Realm {
+    pub global_environment: LexicalEnvironmentRef,
+    pub object_prototypes: ObjectPrototypes,
+}
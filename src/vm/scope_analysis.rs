@@ -0,0 +1,754 @@
+//! Free variable analysis of functions.
+//! A name that a function references but does not declare is a free variable of the function,
+//! and the binding it resolves to in an enclosing function is captured by the function.
+//! Redeclarations of lexically declared names are detected at the same time.
+//! ``ScopeTree`` shows how the names in a script are resolved, for debugging.
+
+use crate::lexer::line_column;
+use crate::node::{
+    FormalParameter, FormalParameters, JSXAttribute, Node, NodeBase, NodeMap, PropertyDefinition,
+    VarKind,
+};
+use crate::util::StackLimit;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::fmt::Write;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FunctionScope {
+    /// Names bound in the environments of the function itself: parameters, vars, functions,
+    /// and lexical declarations in any of its blocks.
+    pub declared: FxHashSet<String>,
+    /// Names referenced by the function or its inner functions, but not declared in it.
+    pub free: FxHashSet<String>,
+    /// Declared names referenced by inner functions.
+    pub captured: FxHashSet<String>,
+    /// Declared names bound when their declarations are evaluated or the function is entered,
+    /// rather than hoisted as undefined: lexical declarations, catch parameters and functions.
+    pub lexical: FxHashSet<String>,
+    /// ``eval(...)`` may declare vars at runtime, so the bindings of the function can not be
+    /// known statically.
+    pub has_direct_eval: bool,
+    /// The function was nested too deeply to be analyzed entirely.
+    pub is_partial: bool,
+    /// The first name declared twice in a scope where it is a SyntaxError, e.g. ``let x; let x;``.
+    /// Inner functions are not included.
+    pub redeclaration: Option<Redeclaration>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Redeclaration {
+    pub name: String,
+    pub pos: usize,
+    /// The position of the previous declaration.
+    pub prev_pos: usize,
+}
+
+impl FunctionScope {
+    /// Analyze the function with ``params`` and ``body``. Of its inner functions, only the free
+    /// variables are kept.
+    pub fn analyze(
+        params: &FormalParameters,
+        body: &Node,
+        stack_limit: &StackLimit,
+    ) -> FunctionScope {
+        FunctionScope::analyze_with_inner_functions(
+            params,
+            body,
+            stack_limit,
+            &mut NodeMap::default(),
+        )
+        .0
+    }
+
+    /// Analyze the script ``node`` and all the functions in it at once. The scopes of the
+    /// functions are keyed by the ids of their bodies.
+    pub fn analyze_script(
+        node: &Node,
+        stack_limit: &StackLimit,
+    ) -> (FunctionScope, NodeMap<FunctionScope>) {
+        let mut scopes = NodeMap::default();
+        let (scope, _) =
+            FunctionScope::analyze_with_inner_functions(&vec![], node, stack_limit, &mut scopes);
+        (scope, scopes)
+    }
+
+    /// Also returns the functions directly inside the function, and adds the scopes of all the
+    /// functions inside it to ``scopes``.
+    fn analyze_with_inner_functions<'a>(
+        params: &'a FormalParameters,
+        body: &'a Node,
+        stack_limit: &'a StackLimit,
+        scopes: &mut NodeMap<FunctionScope>,
+    ) -> (FunctionScope, Vec<InnerFunction<'a>>) {
+        let mut analyzer = Analyzer::new(stack_limit, scopes);
+        for FormalParameter { name, init, .. } in params {
+            analyzer.scope.declared.insert(name.clone());
+            if let Some(init) = init {
+                analyzer.visit(init);
+            }
+        }
+        analyzer.visit(body);
+        let inner_functions = ::std::mem::take(&mut analyzer.inner_functions);
+        (analyzer.finish(), inner_functions)
+    }
+
+    /// How ``name`` referenced in the function is looked up, given the scopes of the enclosing
+    /// functions from the outermost one. The code generator emits ``GetUpvalue`` or
+    /// ``SetUpvalue`` for ``Resolution::Upvalue``.
+    pub fn resolve<'s>(
+        &self,
+        name: &str,
+        enclosing: impl DoubleEndedIterator<Item = &'s FunctionScope>,
+    ) -> Resolution {
+        if self.declared.contains(name) {
+            return Resolution::Local;
+        }
+        if !self.is_static() || !self.is_free(name) {
+            return Resolution::Dynamic;
+        }
+        for (i, func) in enclosing.rev().enumerate() {
+            if func.declared.contains(name) {
+                return Resolution::Upvalue { depth: i + 1 };
+            }
+            if !func.is_static() {
+                return Resolution::Dynamic;
+            }
+        }
+        Resolution::Dynamic
+    }
+
+    /// Whether the bindings of the function are all known statically.
+    pub fn is_static(&self) -> bool {
+        !self.has_direct_eval && !self.is_partial
+    }
+
+    pub fn is_free(&self, name: &str) -> bool {
+        self.free.contains(name)
+    }
+
+    /// Captured names in a stable order.
+    pub fn captured_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.captured.iter().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Names to store in the registers of the frame instead of the environments, sorted: the
+    /// parameters and vars not captured by inner functions. Empty if the bindings of the
+    /// function are not known statically.
+    pub fn register_names(&self) -> Vec<String> {
+        if !self.is_static() {
+            return vec![];
+        }
+        let mut names: Vec<String> = self
+            .declared
+            .iter()
+            .filter(|name| {
+                !self.captured.contains(*name)
+                    && !self.lexical.contains(*name)
+                    && *name != "arguments"
+            })
+            .cloned()
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+/// How a name is looked up at runtime.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resolution {
+    /// Declared in the function itself.
+    Local,
+    /// Declared in the enclosing function ``depth`` levels out, and looked up from the
+    /// environment the function closes over.
+    Upvalue { depth: usize },
+    /// Looked up through the whole environment chain up to the global object, because no
+    /// enclosing function declares it, or a direct eval may declare it at runtime.
+    Dynamic,
+}
+
+/// The scopes of a script and the functions in it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScopeTree {
+    /// None for the script and anonymous functions.
+    pub name: Option<String>,
+    pub pos: usize,
+    pub scope: FunctionScope,
+    /// The names declared or referenced in the function (including its inner functions),
+    /// sorted.
+    pub resolutions: Vec<(String, Resolution)>,
+    pub children: Vec<ScopeTree>,
+}
+
+impl ScopeTree {
+    /// Analyze the script ``node`` and all the functions in it.
+    pub fn new(node: &Node, stack_limit: &StackLimit) -> ScopeTree {
+        ScopeTree::build(None, node.pos, &vec![], node, &mut vec![], stack_limit)
+    }
+
+    fn build(
+        name: Option<String>,
+        pos: usize,
+        params: &FormalParameters,
+        body: &Node,
+        enclosing: &mut Vec<FunctionScope>,
+        stack_limit: &StackLimit,
+    ) -> ScopeTree {
+        let (scope, inner_functions) = FunctionScope::analyze_with_inner_functions(
+            params,
+            body,
+            stack_limit,
+            &mut NodeMap::default(),
+        );
+        let mut resolutions: Vec<(String, Resolution)> = scope
+            .declared
+            .union(&scope.free)
+            .map(|name| (name.clone(), scope.resolve(name, enclosing.iter())))
+            .collect();
+        resolutions.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        enclosing.push(scope);
+        let children = inner_functions
+            .into_iter()
+            .map(|func| {
+                let name = func.name.cloned();
+                ScopeTree::build(
+                    name,
+                    func.pos,
+                    func.params,
+                    func.body,
+                    enclosing,
+                    stack_limit,
+                )
+            })
+            .collect();
+        let scope = enclosing.pop().unwrap();
+
+        ScopeTree {
+            name,
+            pos,
+            scope,
+            resolutions,
+            children,
+        }
+    }
+
+    /// Print the tree with the line and column of each function in ``code``, e.g.
+    /// ```text
+    /// <script> 1:1
+    ///   f: local
+    ///   function f 1:1
+    ///     console: dynamic
+    /// ```
+    pub fn dump(&self, code: &str, pos_line_list: &[(usize, usize)]) -> String {
+        let mut s = "".to_string();
+        self.dump_at(&mut s, 0, code, pos_line_list);
+        s
+    }
+
+    fn dump_at(&self, s: &mut String, indent: usize, code: &str, pos_line_list: &[(usize, usize)]) {
+        let (line, column) = line_column(code, pos_line_list, self.pos);
+        let name = match self.name {
+            Some(ref name) => format!("function {}", name),
+            None if indent == 0 => "<script>".to_string(),
+            None => "function <anonymous>".to_string(),
+        };
+        let _ = writeln!(
+            s,
+            "{:indent$}{} {}:{}",
+            "",
+            name,
+            line,
+            column,
+            indent = indent
+        );
+        let indent = indent + 2;
+        if self.scope.has_direct_eval {
+            let _ = writeln!(s, "{:indent$}(direct eval)", "", indent = indent);
+        }
+        if self.scope.is_partial {
+            let _ = writeln!(s, "{:indent$}(nested too deeply)", "", indent = indent);
+        }
+        for (name, resolution) in &self.resolutions {
+            let resolution = match resolution {
+                Resolution::Local if self.scope.captured.contains(name) => {
+                    "local, captured".to_string()
+                }
+                Resolution::Local => "local".to_string(),
+                Resolution::Upvalue { depth } => format!("upvalue, {} level(s) out", depth),
+                Resolution::Dynamic => "dynamic".to_string(),
+            };
+            let _ = writeln!(s, "{:indent$}{}: {}", "", name, resolution, indent = indent);
+        }
+        for child in &self.children {
+            child.dump_at(s, indent, code, pos_line_list);
+        }
+    }
+}
+
+/// A function found while analyzing the enclosing function.
+#[derive(Debug)]
+struct InnerFunction<'a> {
+    name: Option<&'a String>,
+    pos: usize,
+    params: &'a FormalParameters,
+    body: &'a Node,
+}
+
+#[derive(Debug)]
+struct Analyzer<'a, 's> {
+    stack_limit: &'a StackLimit,
+    /// The scopes of the inner functions analyzed so far.
+    scopes: &'s mut NodeMap<FunctionScope>,
+    scope: FunctionScope,
+    referenced: FxHashSet<String>,
+    referenced_by_inner: FxHashSet<String>,
+    inner_has_direct_eval: bool,
+    /// The function body and the blocks enclosing the node being visited.
+    blocks: Vec<BlockDeclarations>,
+    inner_functions: Vec<InnerFunction<'a>>,
+}
+
+/// Names declared in a block with their positions.
+#[derive(Debug, Default)]
+struct BlockDeclarations {
+    lexical: FxHashMap<String, usize>,
+    /// Vars declared in the block or its nested blocks, and functions declared at the top level
+    /// of the function.
+    var: FxHashMap<String, usize>,
+}
+
+impl<'a, 's> Analyzer<'a, 's> {
+    fn new(stack_limit: &'a StackLimit, scopes: &'s mut NodeMap<FunctionScope>) -> Self {
+        Analyzer {
+            stack_limit,
+            scopes,
+            scope: FunctionScope::default(),
+            referenced: FxHashSet::default(),
+            referenced_by_inner: FxHashSet::default(),
+            inner_has_direct_eval: false,
+            blocks: vec![BlockDeclarations::default()],
+            inner_functions: vec![],
+        }
+    }
+
+    fn finish(self) -> FunctionScope {
+        let Analyzer {
+            mut scope,
+            referenced,
+            referenced_by_inner,
+            inner_has_direct_eval,
+            ..
+        } = self;
+
+        scope.free = referenced
+            .union(&referenced_by_inner)
+            .filter(|name| !scope.declared.contains(*name))
+            .cloned()
+            .collect();
+        scope.captured = if inner_has_direct_eval {
+            scope.declared.clone()
+        } else {
+            referenced_by_inner
+                .into_iter()
+                .filter(|name| scope.declared.contains(name))
+                .collect()
+        };
+
+        scope
+    }
+
+    fn visit_inner_function(&mut self, node: &'a Node, name: Option<&'a String>) {
+        let (params, body) = match node.base {
+            NodeBase::FunctionDecl(_, ref params, ref body)
+            | NodeBase::FunctionExpr(_, ref params, ref body)
+            | NodeBase::ArrowFunction(ref params, ref body) => (params, body),
+            _ => unreachable!(),
+        };
+        self.inner_functions.push(InnerFunction {
+            name,
+            pos: node.pos,
+            params,
+            body,
+        });
+        let (inner, _) = FunctionScope::analyze_with_inner_functions(
+            params,
+            body,
+            self.stack_limit,
+            self.scopes,
+        );
+        self.inner_has_direct_eval |= inner.has_direct_eval;
+        self.scope.is_partial |= inner.is_partial;
+        self.referenced_by_inner.extend(inner.free.iter().cloned());
+        if !body.id.is_none() {
+            self.scopes.insert(body.id, inner);
+        }
+    }
+
+    /// https://tc39.github.io/ecma262/#sec-block-static-semantics-early-errors
+    fn declare_lexical(&mut self, name: &str, pos: usize) {
+        let block = self.blocks.last_mut().unwrap();
+        if let Some(prev_pos) = block.lexical.get(name).or_else(|| block.var.get(name)) {
+            let prev_pos = *prev_pos;
+            self.redeclared(name, pos, prev_pos);
+            return;
+        }
+        block.lexical.insert(name.to_string(), pos);
+    }
+
+    /// A var is hoisted out of the blocks, so it conflicts with lexical declarations in any of
+    /// the enclosing blocks.
+    fn declare_var(&mut self, name: &str, pos: usize) {
+        let prev_pos = self
+            .blocks
+            .iter()
+            .rev()
+            .find_map(|block| block.lexical.get(name).cloned());
+        if let Some(prev_pos) = prev_pos {
+            self.redeclared(name, pos, prev_pos);
+            return;
+        }
+        for block in &mut self.blocks {
+            block.var.entry(name.to_string()).or_insert(pos);
+        }
+    }
+
+    fn redeclared(&mut self, name: &str, pos: usize, prev_pos: usize) {
+        if self.scope.redeclaration.is_none() {
+            self.scope.redeclaration = Some(Redeclaration {
+                name: name.to_string(),
+                pos,
+                prev_pos,
+            });
+        }
+    }
+
+    fn visit_list(&mut self, nodes: &'a Vec<Node>) {
+        for node in nodes {
+            self.visit(node)
+        }
+    }
+
+    fn visit(&mut self, node: &'a Node) {
+        if self.stack_limit.is_exceeded() {
+            self.scope.is_partial = true;
+            return;
+        }
+
+        match node.base {
+            NodeBase::StatementList(ref nodes) | NodeBase::Array(ref nodes) => {
+                self.visit_list(nodes)
+            }
+            NodeBase::Block(ref nodes) => {
+                self.blocks.push(BlockDeclarations::default());
+                self.visit_list(nodes);
+                self.blocks.pop();
+            }
+            NodeBase::FunctionDecl(ref name, _, _) => {
+                self.scope.declared.insert(name.clone());
+                self.scope.lexical.insert(name.clone());
+                // Functions declared at the top level of a function are treated like vars.
+                if self.blocks.len() == 1 {
+                    self.declare_var(name, node.pos)
+                } else {
+                    self.declare_lexical(name, node.pos)
+                }
+                self.visit_inner_function(node, Some(name))
+            }
+            NodeBase::FunctionExpr(ref name, _, _) => {
+                self.visit_inner_function(node, name.as_ref())
+            }
+            NodeBase::ArrowFunction(_, _) => self.visit_inner_function(node, None),
+            NodeBase::VarDecl(ref name, ref init, ref kind) => {
+                self.scope.declared.insert(name.clone());
+                match kind {
+                    VarKind::Var => self.declare_var(name, node.pos),
+                    VarKind::Let | VarKind::Const => {
+                        self.scope.lexical.insert(name.clone());
+                        self.declare_lexical(name, node.pos)
+                    }
+                }
+                if let Some(init) = init {
+                    self.visit(init)
+                }
+            }
+            NodeBase::Call(ref callee, ref args) => {
+                if let NodeBase::Identifier(ref name) = callee.base {
+                    if name == "eval" {
+                        self.scope.has_direct_eval = true;
+                    }
+                }
+                self.visit(callee);
+                self.visit_list(args)
+            }
+            NodeBase::Try(ref try_clause, ref catch, ref param, ref finally) => {
+                if let NodeBase::Identifier(ref name) = param.base {
+                    self.scope.declared.insert(name.clone());
+                    self.scope.lexical.insert(name.clone());
+                }
+                self.visit(try_clause);
+                self.visit(catch);
+                self.visit(finally)
+            }
+            NodeBase::Object(ref properties) => {
+                for property in properties {
+                    match property {
+                        PropertyDefinition::IdentifierReference(name) => {
+                            self.referenced.insert(name.clone());
+                        }
+                        PropertyDefinition::Property(_, node)
+                        | PropertyDefinition::MethodDefinition(_, _, node)
+                        | PropertyDefinition::SpreadObject(node) => self.visit(node),
+                    }
+                }
+            }
+            NodeBase::Identifier(ref name) => {
+                self.referenced.insert(name.clone());
+            }
+            NodeBase::JSXElement(ref element) => {
+                for attribute in &element.attributes {
+                    match attribute {
+                        JSXAttribute::Attribute(_, node) | JSXAttribute::Spread(node) => {
+                            self.visit(node)
+                        }
+                    }
+                }
+                self.visit_list(&element.children)
+            }
+            NodeBase::Member(ref node, _)
+            | NodeBase::New(ref node)
+            | NodeBase::UnaryOp(ref node, _)
+            | NodeBase::Label(_, ref node)
+            | NodeBase::Throw(ref node)
+            | NodeBase::Spread(ref node) => self.visit(node),
+            NodeBase::Return(ref node) => {
+                if let Some(node) = node {
+                    self.visit(node)
+                }
+            }
+            NodeBase::Index(ref lhs, ref rhs)
+            | NodeBase::While(ref lhs, ref rhs)
+            | NodeBase::Assign(ref lhs, ref rhs)
+            | NodeBase::BinaryOp(ref lhs, ref rhs, _) => {
+                self.visit(lhs);
+                self.visit(rhs)
+            }
+            NodeBase::If(ref a, ref b, ref c) | NodeBase::TernaryOp(ref a, ref b, ref c) => {
+                self.visit(a);
+                self.visit(b);
+                self.visit(c)
+            }
+            NodeBase::For(ref init, ref cond, ref step, ref body) => {
+                self.visit(init);
+                self.visit(cond);
+                self.visit(step);
+                self.visit(body)
+            }
+            NodeBase::Break(_)
+            | NodeBase::Continue(_)
+            | NodeBase::Debugger
+            | NodeBase::This
+            | NodeBase::Null
+            | NodeBase::String(_)
+            | NodeBase::Boolean(_)
+            | NodeBase::Number(_)
+            | NodeBase::Nope => {}
+        }
+    }
+}
+
+#[test]
+fn free_variables() {
+    use crate::parser::Parser;
+
+    let node = Parser::new(
+        "test",
+        "function f(a) {
+           var v = a + g;
+           { let l = 1; try {} catch (e) { e } }
+           return function() { return v + a + w + f }
+         }",
+    )
+    .parse_all()
+    .unwrap();
+    let stack_limit = StackLimit::new();
+    let scope = FunctionScope::analyze(&vec![], &node, &stack_limit);
+    let set = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+
+    assert_eq!(scope.declared, set(&["f"]));
+    assert_eq!(scope.free, set(&["g", "w"]));
+    assert_eq!(scope.captured, set(&["f"]));
+
+    let f = match node.base {
+        NodeBase::StatementList(ref nodes) => match nodes[0].base {
+            NodeBase::FunctionDecl(_, ref params, ref body) => {
+                FunctionScope::analyze(params, body, &stack_limit)
+            }
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    };
+    assert_eq!(f.declared, set(&["a", "v", "l", "e"]));
+    assert_eq!(f.free, set(&["g", "w", "f"]));
+    assert_eq!(f.captured, set(&["a", "v"]));
+    assert!(!f.has_direct_eval);
+    assert_eq!(f.lexical, set(&["l", "e"]));
+}
+
+#[test]
+fn register_names() {
+    use crate::parser::Parser;
+
+    let scope = |code: &str| {
+        let node = Parser::new("test", code).parse_all().unwrap();
+        match node.base {
+            NodeBase::StatementList(ref nodes) => match nodes[0].base {
+                NodeBase::FunctionDecl(_, ref params, ref body) => {
+                    FunctionScope::analyze(params, body, &StackLimit::new())
+                }
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+    };
+
+    assert_eq!(
+        scope(
+            "function f(a, b) {
+               var x = a, y; let l; function g() { return b }
+               try {} catch (e) {}
+               return x + y + l + g
+             }"
+        )
+        .register_names(),
+        vec!["a", "x", "y"]
+    );
+    assert!(scope("function f(a) { var x; eval('') }")
+        .register_names()
+        .is_empty());
+    assert!(scope("function f(a) { var x; return () => eval('') }")
+        .register_names()
+        .is_empty());
+}
+
+#[test]
+fn redeclarations() {
+    use crate::parser::Parser;
+
+    let redeclaration = |code: &str| {
+        let node = Parser::new("test", code).parse_all().unwrap();
+        FunctionScope::analyze(&vec![], &node, &StackLimit::new())
+            .redeclaration
+            .map(|Redeclaration { pos, prev_pos, .. }| (pos, prev_pos))
+    };
+
+    assert_eq!(redeclaration("let x; let x;"), Some((11, 4)));
+    assert_eq!(redeclaration("let x; { var x; }"), Some((13, 4)));
+    assert_eq!(
+        redeclaration("var f; function f() {} let f;"),
+        Some((27, 4))
+    );
+    assert_eq!(
+        redeclaration("{ function f() {} const f = 1; }"),
+        Some((24, 2))
+    );
+    assert_eq!(redeclaration("var x; var x; function x() {}"), None);
+    assert_eq!(
+        redeclaration("let x; { let x; } function f() { let x; }"),
+        None
+    );
+    assert_eq!(
+        redeclaration("for (let i = 0; ; ) {} for (let i = 0; ; ) {}"),
+        None
+    );
+}
+
+#[test]
+fn scope_tree() {
+    use crate::parser::Parser;
+
+    let mut parser = Parser::new(
+        "test",
+        "var a = 1;
+function f(b) {
+  return () => a + b + c;
+}
+function g() { eval(''); return function() { return a } }",
+    );
+    let node = parser.parse_all().unwrap();
+    let tree = ScopeTree::new(&node, &StackLimit::new());
+    let f = &tree.children[0];
+    let arrow = &f.children[0];
+    assert_eq!(
+        arrow.resolutions,
+        vec![
+            ("a".to_string(), Resolution::Upvalue { depth: 2 }),
+            ("b".to_string(), Resolution::Upvalue { depth: 1 }),
+            ("c".to_string(), Resolution::Dynamic),
+        ]
+    );
+    let in_g = &tree.children[1].children[0];
+    assert_eq!(
+        in_g.resolutions,
+        vec![("a".to_string(), Resolution::Dynamic)]
+    );
+
+    assert_eq!(
+        tree.dump(&parser.lexer.code, &parser.lexer.pos_line_list),
+        "<script> 1:1
+  a: local, captured
+  c: dynamic
+  eval: dynamic
+  f: local, captured
+  g: local, captured
+  function f 2:1
+    a: upvalue, 1 level(s) out
+    b: local, captured
+    c: dynamic
+    function <anonymous> 3:10
+      a: upvalue, 2 level(s) out
+      b: upvalue, 1 level(s) out
+      c: dynamic
+  function g 5:1
+    (direct eval)
+    a: dynamic
+    eval: dynamic
+    function <anonymous> 5:41
+      a: dynamic
+"
+    );
+}
+
+#[test]
+fn analyze_script() {
+    use crate::parser::Parser;
+
+    let node = Parser::new(
+        "test",
+        "function f(a) { return () => a + b }
+         let g = function() { eval('') }",
+    )
+    .parse_all()
+    .unwrap();
+    let stack_limit = StackLimit::new();
+    let (scope, scopes) = FunctionScope::analyze_script(&node, &stack_limit);
+    assert_eq!(scope, FunctionScope::analyze(&vec![], &node, &stack_limit));
+    assert_eq!(scopes.len(), 3);
+
+    // Each function has the scope it gets when analyzed alone.
+    let mut stack = vec![&node];
+    while let Some(node) = stack.pop() {
+        match node.base {
+            NodeBase::FunctionDecl(_, ref params, ref body)
+            | NodeBase::FunctionExpr(_, ref params, ref body)
+            | NodeBase::ArrowFunction(ref params, ref body) => assert_eq!(
+                scopes[&body.id],
+                FunctionScope::analyze(params, body, &stack_limit)
+            ),
+            _ => {}
+        }
+        stack.extend(node.children());
+    }
+    assert!(scopes.values().any(|scope| scope.has_direct_eval));
+}
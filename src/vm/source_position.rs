@@ -0,0 +1,203 @@
+//! Tables from instruction positions to lines and columns in the source, kept in every compiled
+//! function so that runtime errors and stack traces can point at the failing instruction.
+
+use crate::lexer::line_column;
+use crate::source_transform::OffsetTable;
+use crate::vm::codegen::ToSourcePos;
+
+/// The text of a script, to get the lines and columns of char positions in it.
+#[derive(Clone, Copy, Debug)]
+pub struct SourceText<'a> {
+    pub code: &'a str,
+    pub pos_line_list: &'a [(usize, usize)],
+    /// If the script was parsed after a source transform, maps the positions in the parsed
+    /// script to ``code``, the script before the transform.
+    pub offsets: Option<&'a OffsetTable>,
+}
+
+impl<'a> SourceText<'a> {
+    /// Get the position in ``code`` of ``pos`` in the parsed script.
+    pub fn original_pos(&self, pos: usize) -> usize {
+        self.offsets
+            .map_or(pos, |offsets| offsets.original_pos(pos))
+    }
+
+    pub fn line_column(&self, pos: usize) -> (usize, usize) {
+        line_column(self.code, self.pos_line_list, self.original_pos(pos))
+    }
+}
+
+/// Lines and columns of instructions, sorted by pc. Each entry is encoded as three LEB128
+/// integers: the pc delta, the zigzag-encoded line delta and the column.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PositionTable {
+    bytes: Vec<u8>,
+}
+
+impl PositionTable {
+    /// Build the table of ``to_source_pos``, whose char positions are in ``source``.
+    pub fn new(to_source_pos: &ToSourcePos, source: SourceText) -> Self {
+        let entries = to_source_pos
+            .entries()
+            .iter()
+            .map(|(pc, pos)| {
+                let (line, column) = source.line_column(*pos);
+                (*pc, line, column)
+            })
+            .collect();
+        PositionTable::from_entries(entries)
+    }
+
+    /// Build a table from ``(pc, line, column)``. If a pc appears more than once, the first
+    /// entry is kept.
+    pub fn from_entries(mut entries: Vec<(usize, usize, usize)>) -> Self {
+        entries.sort_by_key(|(pc, _, _)| *pc);
+        entries.dedup_by_key(|(pc, _, _)| *pc);
+
+        let mut bytes = vec![];
+        let (mut prev_pc, mut prev_line) = (0, 0);
+        for (pc, line, column) in entries {
+            write_uleb128(&mut bytes, (pc - prev_pc) as u64);
+            let line_delta = line as i64 - prev_line as i64;
+            write_uleb128(&mut bytes, ((line_delta << 1) ^ (line_delta >> 63)) as u64);
+            write_uleb128(&mut bytes, column as u64);
+            prev_pc = pc;
+            prev_line = line;
+        }
+        PositionTable { bytes }
+    }
+
+    /// Load a table from the bytes given by ``as_bytes``. Return None if they are corrupted.
+    pub fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
+        let table = PositionTable { bytes };
+        let mut entries = table.entries();
+        while entries.next().is_some() {}
+        if entries.pos == table.bytes.len() {
+            Some(table)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn entries(&self) -> Entries<'_> {
+        Entries {
+            bytes: &self.bytes,
+            pos: 0,
+            pc: 0,
+            line: 0,
+        }
+    }
+
+    /// Get the line and column of the instruction at ``pc``, or of the nearest instruction
+    /// before it that has an entry.
+    pub fn lookup(&self, pc: usize) -> Option<(usize, usize)> {
+        self.entries()
+            .take_while(|(entry_pc, _, _)| *entry_pc <= pc)
+            .last()
+            .map(|(_, line, column)| (line, column))
+    }
+}
+
+/// Iterator over the ``(pc, line, column)`` of a ``PositionTable``.
+pub struct Entries<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    pc: usize,
+    line: usize,
+}
+
+impl<'a> Entries<'a> {
+    fn read_uleb128(&mut self) -> Option<u64> {
+        let mut n = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = *self.bytes.get(self.pos)?;
+            if shift >= 64 {
+                return None;
+            }
+            self.pos += 1;
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(n);
+            }
+            shift += 7;
+        }
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = (usize, usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.pos;
+        let entry = (|| {
+            let pc_delta = self.read_uleb128()?;
+            let line_delta = self.read_uleb128()?;
+            let column = self.read_uleb128()?;
+            let line_delta = (line_delta >> 1) as i64 ^ -((line_delta & 1) as i64);
+            Some((pc_delta as usize, line_delta, column as usize))
+        })();
+        match entry {
+            Some((pc_delta, line_delta, column)) => {
+                self.pc += pc_delta;
+                self.line = (self.line as i64 + line_delta) as usize;
+                Some((self.pc, self.line, column))
+            }
+            None => {
+                // Stop at a truncated entry.
+                self.pos = start;
+                None
+            }
+        }
+    }
+}
+
+fn write_uleb128(bytes: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+#[test]
+fn position_table() {
+    let table = PositionTable::from_entries(vec![
+        (10, 3, 1),
+        (0, 1, 1),
+        (4, 1, 9),
+        (4, 2, 5),
+        (300, 2, 200),
+    ]);
+    assert_eq!(
+        table.entries().collect::<Vec<_>>(),
+        vec![(0, 1, 1), (4, 1, 9), (10, 3, 1), (300, 2, 200)]
+    );
+    assert_eq!(table.lookup(0), Some((1, 1)));
+    assert_eq!(table.lookup(4), Some((1, 9)));
+    assert_eq!(table.lookup(9), Some((1, 9)));
+    assert_eq!(table.lookup(1000), Some((2, 200)));
+    assert_eq!(PositionTable::default().lookup(0), None);
+    assert_eq!(PositionTable::from_entries(vec![(5, 1, 1)]).lookup(4), None);
+    // 3 bytes per entry, except for the pc delta 290 and the column 200.
+    assert_eq!(table.as_bytes().len(), 14);
+
+    let bytes = table.as_bytes().to_vec();
+    assert_eq!(PositionTable::from_bytes(bytes.clone()), Some(table));
+    assert_eq!(
+        PositionTable::from_bytes(bytes[..bytes.len() - 1].to_vec()),
+        None
+    );
+}
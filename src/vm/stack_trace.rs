@@ -0,0 +1,109 @@
+//! Stack traces of errors. The ``stack`` of an error is its first line (e.g. "TypeError: msg")
+//! followed by the first ``Error.stackTraceLimit`` frames being run when it was created. The
+//! embedder can hide frames, e.g. those of the builtins written in JavaScript, and render the
+//! frames by itself like ``Error.prepareStackTrace`` of V8.
+
+use crate::vm::jsvalue::value::Value;
+use std::fmt;
+
+/// A frame of a function being run, as shown in stack traces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackFrame {
+    /// The name of the function. None for the top level of a script.
+    pub function_name: Option<String>,
+    /// The file name of the script and the line and column being run, if known.
+    pub location: Option<(String, usize, usize)>,
+    /// Whether the function is one of the builtins written in JavaScript.
+    pub is_builtin: bool,
+}
+
+impl fmt::Display for StackFrame {
+    /// ``at name (file:line:column)`` as V8 shows it.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.function_name, &self.location) {
+            (Some(name), Some((file, line, column))) => {
+                write!(f, "at {} ({}:{}:{})", name, file, line, column)
+            }
+            (Some(name), None) => write!(f, "at {}", name),
+            (None, Some((file, line, column))) => write!(f, "at {}:{}:{}", file, line, column),
+            (None, None) => write!(f, "at <anonymous>"),
+        }
+    }
+}
+
+/// Returns true for the frames to hide from stack traces.
+pub type FrameFilter = Box<dyn Fn(&StackFrame) -> bool>;
+
+/// Renders the ``stack`` of an error from its first line and its frames.
+pub type PrepareStackTrace = Box<dyn Fn(&str, &[StackFrame]) -> String>;
+
+/// How the VM makes stack traces. By default, every frame is shown as V8 shows it.
+#[derive(Default)]
+pub struct StackTraceOptions {
+    /// Hide the frames of the builtins written in JavaScript, e.g. ``Array.prototype.forEach``.
+    pub hide_builtin_frames: bool,
+    /// Hide the frames for which it returns true.
+    pub frame_filter: Option<FrameFilter>,
+    /// Render the ``stack`` of errors instead of ``format_stack_trace``.
+    pub prepare_stack_trace: Option<PrepareStackTrace>,
+}
+
+impl StackTraceOptions {
+    pub fn is_hidden(&self, frame: &StackFrame) -> bool {
+        if self.hide_builtin_frames && frame.is_builtin {
+            return true;
+        }
+        match self.frame_filter {
+            Some(ref filter) => filter(frame),
+            None => false,
+        }
+    }
+}
+
+/// ``header`` followed by ``frames``, each on a line like ``    at f (file:line:col)``.
+pub fn format_stack_trace(header: &str, frames: &[StackFrame]) -> String {
+    let mut trace = header.to_string();
+    for frame in frames {
+        trace += &format!("\n    {}", frame);
+    }
+    trace
+}
+
+/// The number of frames in traces for the value of ``Error.stackTraceLimit``, or None if it is
+/// not a number, in which case errors have no ``stack``.
+pub fn stack_trace_limit(limit: Value) -> Option<usize> {
+    match limit {
+        Value::Number(n) if n.is_nan() || n <= 0.0 => Some(0),
+        Value::Number(n) if n >= std::usize::MAX as f64 => Some(std::usize::MAX),
+        Value::Number(n) => Some(n as usize),
+        _ => None,
+    }
+}
+
+#[test]
+fn format_frames() {
+    let frames = vec![
+        StackFrame {
+            function_name: Some("f".to_string()),
+            location: Some(("a.js".to_string(), 1, 2)),
+            is_builtin: false,
+        },
+        StackFrame {
+            function_name: None,
+            location: Some(("a.js".to_string(), 3, 1)),
+            is_builtin: false,
+        },
+    ];
+    assert_eq!(
+        format_stack_trace("Error: x", &frames),
+        "Error: x\n    at f (a.js:1:2)\n    at a.js:3:1"
+    );
+    assert_eq!(format_stack_trace("Error", &[]), "Error");
+    assert_eq!(stack_trace_limit(Value::Number(2.5)), Some(2));
+    assert_eq!(stack_trace_limit(Value::Number(-1.0)), Some(0));
+    assert_eq!(
+        stack_trace_limit(Value::Number(std::f64::INFINITY)),
+        Some(std::usize::MAX)
+    );
+    assert_eq!(stack_trace_limit(Value::undefined()), None);
+}
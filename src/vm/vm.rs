@@ -1,18 +1,37 @@
 use crate::builtins::console::debug_print;
 use crate::bytecode_gen::{inst_to_inst_name, show_inst, VMInst};
 use crate::gc;
+use crate::import_map::ImportMap;
 use crate::node::Node;
-use crate::parser::ScriptInfo;
+use crate::parser::{Parser, ScriptInfo};
+#[cfg(feature = "remote-modules")]
+use crate::remote_module::RemoteModules;
+use crate::source_transform::{SourceTransform, TransformedSource};
+use crate::vm::access_check::{Access, AccessCheck};
+use crate::vm::coverage::Coverage;
 pub use crate::vm::exec_context::{
-    EnvironmentRecord, ExecContext, LexicalEnvironment, LexicalEnvironmentRef,
+    EnvironmentRecord, ExecContext, LexicalEnvironment, LexicalEnvironmentRef, SubroutineKind,
 };
 pub use crate::vm::factory::{Factory, FunctionId};
-pub use crate::vm::jsvalue::function::{DestinationKind, FunctionParameter, ThisMode};
+use crate::vm::function_profile::FunctionProfiler;
+use crate::vm::internal_methods::internal_methods;
+use crate::vm::interrupt::Interrupt;
+pub use crate::vm::interrupt::InterruptHandle;
+pub use crate::vm::jsvalue::function::{FunctionParameter, HandlerKind, ThisMode};
+#[cfg(feature = "jsx")]
+use crate::vm::jsx::{FactoryCall, JSXTransform};
+pub use crate::vm::limits::Limits;
+pub use crate::vm::realm::{Realm, RealmId};
+use crate::vm::source_position::SourceText;
+use crate::vm::stack_trace::{
+    format_stack_trace, stack_trace_limit, StackFrame, StackTraceOptions,
+};
 use crate::vm::{
     codegen, codegen::CodeGenerator, constant, error::*, jsvalue::prototype::ObjectPrototypes,
-    jsvalue::symbol::GlobalSymbolRegistry, jsvalue::value::*,
+    jsvalue::symbol::GlobalSymbolRegistry, jsvalue::value::*, operations,
 };
 use rustc_hash::FxHashMap;
+use std::rc::Rc;
 use std::time::{Duration, Instant};
 
 pub type VMResult = Result<(), RuntimeError>;
@@ -22,20 +41,64 @@ pub type VMValueResult = Result<Value, RuntimeError>;
 pub struct VM {
     pub factory: Factory,
     pub global_environment: LexicalEnvironmentRef,
+    /// The realms indexed by ``RealmId``, starting with the main realm.
+    pub realms: Vec<Realm>,
+    /// The realm of ``global_environment`` and ``factory.object_prototypes``.
+    pub current_realm: RealmId,
     pub constant_table: constant::ConstantTable,
     pub global_symbol_registry: GlobalSymbolRegistry,
     pub current_context: ExecContext,
     pub saved_context: Vec<ExecContext>,
     pub is_called_from_native: bool,
-    ///func_id, ToSourcePos
-    pub to_source_map: FxHashMap<FunctionId, codegen::ToSourcePos>,
     pub is_profile: bool,
     pub is_trace: bool,
     ///(func_id, script_info)
     pub script_info: Vec<(FunctionId, ScriptInfo)>,
+    /// The module function ids of the builtins written in JavaScript.
+    pub builtin_modules: Vec<FunctionId>,
+    /// Which frames stack traces show, and how.
+    pub stack_trace_options: StackTraceOptions,
     pub profile: Profiler,
+    /// Per-function profile printed by `print_function_profile`. Collected only if Some.
+    pub function_profile: Option<FunctionProfiler>,
+    /// The edges between the basic blocks the scripts ran, for fuzzers. Counted only if Some.
+    pub coverage: Option<Coverage>,
+    /// Called on `debugger` statements. If None, `debugger` does nothing.
+    pub debugger_hook: Option<DebuggerHook>,
+    /// Called on exceptions no script catches. If None, they are returned by ``run``.
+    pub uncaught_exception_handler: Option<UncaughtExceptionHandler>,
+    /// Rewrites the scripts given to ``transform_source``. If None, scripts are parsed as they are.
+    pub source_transform: Option<SourceTransform>,
+    /// Functions given by the embedder with ``native_function``.
+    pub native_functions: Vec<NativeFunction>,
+    /// Callbacks given by the embedder with ``guarded_object``.
+    pub access_checks: Vec<AccessCheck>,
+    /// The timeout and the interrupt handle of scripts.
+    pub interrupt: Interrupt,
+    /// The address of the native stack when the outermost ``run`` started, or 0 if not running.
+    native_stack_base: usize,
+    /// Warnings on the code last compiled.
+    pub compile_warnings: Vec<codegen::Warning>,
+    /// Redirects the specifiers given to ``require``.
+    pub import_map: ImportMap,
+    /// Where modules may be fetched from, and the fetched modules.
+    #[cfg(feature = "remote-modules")]
+    pub remote_modules: RemoteModules,
+    /// How JSX elements are compiled.
+    #[cfg(feature = "jsx")]
+    pub jsx_transform: Rc<dyn JSXTransform>,
 }
 
+pub type DebuggerHook = Box<dyn FnMut(&mut VM)>;
+
+/// Called with the value thrown out of the outermost run and its ``stack``, if it is an error
+/// with one.
+pub type UncaughtExceptionHandler = Box<dyn FnMut(&mut VM, Value, Option<String>)>;
+
+/// A function the embedder makes callable from scripts. Unlike ``BuiltinFuncTy``, it may capture
+/// state.
+pub type NativeFunction = Rc<dyn Fn(&mut VM, &[Value], Value) -> VMValueResult>;
+
 pub struct Profiler {
     instant: Instant,
     prev_time: Duration,
@@ -44,6 +107,8 @@ pub struct Profiler {
     gc_profile: [(usize, Duration); 3],
     gc_stop_time: Duration,
     trace_string: String,
+    /// The function and the source line of the instruction traced last.
+    trace_line: Option<(FunctionId, usize)>,
     start_flag: bool,
 }
 
@@ -59,20 +124,43 @@ impl VM {
         let memory_allocator = gc::MemoryAllocator::new();
         let mut factory = Factory::new(memory_allocator, ObjectPrototypes::dummy());
         factory.object_prototypes = ObjectPrototypes::new(&mut factory);
+        factory.well_known_symbols = WellKnownSymbols::new(&mut factory);
         let global_env = LexicalEnvironment::new_global_initialized(&mut factory);
         let global_environment = LexicalEnvironmentRef(factory.alloc(global_env));
-        VM {
+        let main_realm = Realm {
             global_environment,
+            object_prototypes: factory.object_prototypes.clone(),
+        };
+        let mut vm = VM {
+            global_environment,
+            realms: vec![main_realm],
+            current_realm: RealmId::MAIN,
             factory,
             constant_table: constant::ConstantTable::new(),
             global_symbol_registry: GlobalSymbolRegistry::new(),
             current_context: ExecContext::empty(),
             saved_context: vec![],
             is_called_from_native: false,
-            to_source_map: FxHashMap::default(),
             is_profile: false,
             is_trace: false,
             script_info: vec![],
+            builtin_modules: vec![],
+            stack_trace_options: StackTraceOptions::default(),
+            debugger_hook: None,
+            uncaught_exception_handler: None,
+            source_transform: None,
+            native_functions: vec![],
+            access_checks: vec![],
+            interrupt: Interrupt::new(),
+            native_stack_base: 0,
+            compile_warnings: vec![],
+            import_map: ImportMap::default(),
+            #[cfg(feature = "remote-modules")]
+            remote_modules: RemoteModules::default(),
+            #[cfg(feature = "jsx")]
+            jsx_transform: Rc::new(FactoryCall::default()),
+            function_profile: None,
+            coverage: None,
             profile: Profiler {
                 instant: Instant::now(),
                 prev_time: Duration::from_secs(0),
@@ -80,10 +168,13 @@ impl VM {
                 gc_profile: [(0, Duration::from_secs(0)); 3],
                 current_inst: 255,
                 trace_string: "".to_string(),
+                trace_line: None,
                 inst_profile: [(0, Duration::from_micros(0)); 100],
                 start_flag: false,
             },
-        }
+        };
+        crate::builtins::self_hosted::install(&mut vm);
+        vm
     }
 
     pub fn profile(mut self) -> Self {
@@ -96,12 +187,165 @@ impl VM {
         self
     }
 
+    /// Collect per-function time and allocations. See `FunctionProfiler`.
+    pub fn profile_functions(mut self) -> Self {
+        self.function_profile = Some(FunctionProfiler::new());
+        self
+    }
+
+    /// Count the edges between the basic blocks of the scripts in a bitmap of ``size`` counts,
+    /// e.g. the size of the bitmap of the fuzzer. See ``Coverage``.
+    pub fn enable_coverage(&mut self, size: usize) {
+        self.coverage = Some(Coverage::new(size));
+    }
+
+    /// Register the callback invoked on `debugger` statements.
+    pub fn set_debugger_hook(&mut self, hook: impl FnMut(&mut VM) + 'static) {
+        self.debugger_hook = Some(Box::new(hook));
+    }
+
+    /// Register the callback invoked on exceptions thrown out of the outermost run (e.g.
+    /// ``run_global`` or ``call_function`` by the embedder), with the thrown value and its
+    /// stack. The run then returns undefined instead of the error, so that a long-running
+    /// embedder can log the exception and go on running scripts. Terminated scripts are not
+    /// exceptions and are still returned as errors.
+    pub fn set_uncaught_exception_handler(
+        &mut self,
+        handler: impl FnMut(&mut VM, Value, Option<String>) + 'static,
+    ) {
+        self.uncaught_exception_handler = Some(Box::new(handler));
+    }
+
+    /// Hide the frames for which ``filter`` returns true from stack traces.
+    pub fn set_stack_frame_filter(&mut self, filter: impl Fn(&StackFrame) -> bool + 'static) {
+        self.stack_trace_options.frame_filter = Some(Box::new(filter));
+    }
+
+    /// Register the callback rendering the ``stack`` of errors from their first line (e.g.
+    /// "TypeError: msg") and their frames, innermost first.
+    pub fn set_prepare_stack_trace(
+        &mut self,
+        prepare: impl Fn(&str, &[StackFrame]) -> String + 'static,
+    ) {
+        self.stack_trace_options.prepare_stack_trace = Some(Box::new(prepare));
+    }
+
+    /// Register the callback rewriting scripts before they are parsed. It is run on modules
+    /// loaded by ``require`` and code given to ``eval``, and on the scripts the embedder passes
+    /// to ``transform_source``.
+    pub fn set_source_transform(
+        &mut self,
+        transform: impl FnMut(&str, &str) -> Result<TransformedSource, String> + 'static,
+    ) {
+        self.source_transform = Some(Box::new(transform));
+    }
+
+    /// Run the source transform, if any, on the script ``parser`` is about to parse.
+    pub fn transform_source(&mut self, parser: &mut Parser) -> Result<(), String> {
+        if let Some(transform) = &mut self.source_transform {
+            let transformed = transform(&parser.file_name, &parser.lexer.code)?;
+            parser.replace_source(transformed);
+        }
+        Ok(())
+    }
+
+    /// Create a function object calling ``func``, for the embedder to put where scripts can
+    /// call it.
+    pub fn native_function(
+        &mut self,
+        name: impl Into<String>,
+        func: impl Fn(&mut VM, &[Value], Value) -> VMValueResult + 'static,
+    ) -> Value {
+        let id = self.native_functions.len();
+        self.native_functions.push(Rc::new(func));
+        self.factory.native_function(name, id)
+    }
+
+    /// Create a view of the host object ``target`` which calls ``check`` with the key and the
+    /// kind of each access to its properties, and denies the access if it returns false. See
+    /// ``access_check``.
+    pub fn guarded_object(
+        &mut self,
+        target: Value,
+        check: impl Fn(&mut VM, Value, Access) -> Result<bool, RuntimeError> + 'static,
+    ) -> Value {
+        let id = self.access_checks.len();
+        self.access_checks.push(Rc::new(check));
+        self.factory.guarded_object(target, id)
+    }
+
+    /// Resolve the specifiers given to ``require`` with ``import_map``.
+    pub fn with_import_map(mut self, import_map: ImportMap) -> Self {
+        self.import_map = import_map;
+        self
+    }
+
+    /// Allow ``require`` to fetch modules from ``hosts``. See ``RemoteModules::allowed_hosts``.
+    #[cfg(feature = "remote-modules")]
+    pub fn allow_remote_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.remote_modules.allowed_hosts = hosts;
+        self
+    }
+
+    /// Provide the minimal Node.js globals of ``builtins::node_compat``.
+    #[cfg(feature = "node-compat")]
+    pub fn node_compat(mut self) -> Self {
+        crate::builtins::node_compat::install(&mut self);
+        self
+    }
+
+    /// Verify the heap after every GC. See `MemoryAllocator::verify`.
+    pub fn gc_verify(mut self) -> Self {
+        self.factory.memory_allocator.verify = true;
+        self
+    }
+
+    /// Limit the sizes of the values the scripts make, and the depth of calls.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.factory.memory_allocator.limits = limits;
+        self
+    }
+
+    /// Terminate scripts running longer than ``timeout``, counted from the start of the
+    /// outermost run (e.g. ``run_global`` or ``call_function`` by the embedder).
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.interrupt.timeout = Some(timeout);
+    }
+
+    /// A handle with which another thread can terminate the running script.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.handle()
+    }
+
+    /// Let scripts run only ``fuel`` more instructions, and reset ``fuel_consumed``.
+    pub fn set_fuel(&mut self, fuel: u64) {
+        self.interrupt.fuel = Some(fuel);
+        self.interrupt.fuel_consumed = 0;
+    }
+
+    /// Give ``fuel`` more instructions to the script, e.g. before ``resume``.
+    pub fn add_fuel(&mut self, fuel: u64) {
+        self.interrupt.fuel = Some(self.interrupt.fuel.unwrap_or(0) + fuel);
+    }
+
+    /// The number of instructions run since ``set_fuel``.
+    pub fn fuel_consumed(&self) -> u64 {
+        self.interrupt.fuel_consumed
+    }
+
+    /// Continue the script paused by ``ErrorKind::OutOfFuel``. It returns what ``run`` of the
+    /// script would have returned.
+    pub fn resume(&mut self) -> VMValueResult {
+        self.run()
+    }
+
     pub fn gc_mark(&mut self) {
         let time_before_gc = self.profile.instant.elapsed();
         let gc_mode = self.factory.memory_allocator.state;
         self.factory.memory_allocator.mark(
             self.global_environment,
             &self.factory.object_prototypes,
+            &self.realms,
             &self.constant_table,
             &self.current_context,
             &self.saved_context,
@@ -111,20 +355,149 @@ impl VM {
             gc::GCState::Marking => 1,
             gc::GCState::ReadyToSweep => 2,
         };
+        if let (gc::GCState::ReadyToSweep, gc::GCState::Initial) =
+            (gc_mode, self.factory.memory_allocator.state)
+        {
+            // The callees cached at call sites may have been freed.
+            self.clear_call_caches();
+        }
         let stop_time = self.profile.instant.elapsed() - time_before_gc;
         self.profile.gc_stop_time += stop_time;
         self.profile.gc_profile[i].0 += 1;
         self.profile.gc_profile[i].1 += stop_time;
     }
 
-    pub fn compile(&mut self, node: &Node, use_value: bool) -> Result<FuncInfoRef, codegen::Error> {
+    pub fn clear_call_caches(&mut self) {
+        log::trace!("clearing the caches of call sites");
+        for func_ref in &self.factory.func_refs {
+            if let Some(func_ref) = func_ref {
+                func_ref.call_caches.clear();
+            }
+        }
+    }
+
+    /// Compile ``node`` without its source text. Errors in the compiled code have no location.
+    pub fn compile(
+        &mut self,
+        node: &mut Node,
+        use_value: bool,
+    ) -> Result<FuncInfoRef, codegen::Error> {
+        self.compile_script(node, use_value, None)
+    }
+
+    /// Compile ``node`` parsed from ``source``, keeping the lines and columns of the instructions
+    /// for error messages and stack traces.
+    pub fn compile_with_source(
+        &mut self,
+        node: &mut Node,
+        use_value: bool,
+        source: SourceText,
+    ) -> Result<FuncInfoRef, codegen::Error> {
+        self.compile_script(node, use_value, Some(source))
+    }
+
+    fn compile_script(
+        &mut self,
+        node: &mut Node,
+        use_value: bool,
+        source: Option<SourceText>,
+    ) -> Result<FuncInfoRef, codegen::Error> {
         let func_id = self.factory.new_func_id();
         let mut code_generator =
             CodeGenerator::new(&mut self.constant_table, &mut self.factory, func_id);
+        code_generator.source = source;
+        #[cfg(feature = "jsx")]
+        {
+            code_generator.jsx_transform = self.jsx_transform.clone();
+        }
+        let start = Instant::now();
         let res = code_generator.compile(node, use_value);
-        for (func_id, list) in code_generator.to_source_map {
-            self.to_source_map.insert(func_id, list);
+        self.compile_warnings = code_generator.warnings;
+        match &res {
+            Ok(_) => log::debug!(
+                "compiled script {:?} in {:?} with {} warnings",
+                func_id,
+                start.elapsed(),
+                self.compile_warnings.len()
+            ),
+            Err(err) => log::debug!("failed to compile script {:?}: {}", func_id, err.msg),
+        }
+        res
+    }
+
+    /// Make a realm with a new global object and builtins. See ``realm``.
+    pub fn create_realm(&mut self) -> RealmId {
+        let outer = self.current_realm;
+        self.factory.object_prototypes = ObjectPrototypes::new(&mut self.factory);
+        let global_env = LexicalEnvironment::new_global_initialized(&mut self.factory);
+        self.global_environment = LexicalEnvironmentRef(self.factory.alloc(global_env));
+        let realm = RealmId(self.realms.len());
+        self.realms.push(Realm {
+            global_environment: self.global_environment,
+            object_prototypes: self.factory.object_prototypes.clone(),
+        });
+        self.current_realm = realm;
+        crate::builtins::self_hosted::install(self);
+        self.enter_realm(outer);
+        realm
+    }
+
+    /// Run ``code`` as a script in ``realm``, and return its completion value. It may be called
+    /// while a script is running, e.g. from a native function.
+    pub fn eval_in_realm(&mut self, realm: RealmId, code: impl Into<String>) -> VMValueResult {
+        let outer = self.enter_realm(realm);
+        let result = self.eval_script(code.into());
+        self.enter_realm(outer);
+        result
+    }
+
+    /// Make ``realm`` the realm of the scripts run from now on, and return the previous one.
+    fn enter_realm(&mut self, realm: RealmId) -> RealmId {
+        let Realm {
+            global_environment,
+            ref object_prototypes,
+        } = self.realms[realm.0];
+        self.global_environment = global_environment;
+        self.factory.object_prototypes = object_prototypes.clone();
+        std::mem::replace(&mut self.current_realm, realm)
+    }
+
+    fn eval_script(&mut self, code: String) -> VMValueResult {
+        let mut parser = Parser::new("eval", code);
+        self.transform_source(&mut parser)
+            .map_err(|msg| self.throw_error(ErrorType::Error, msg))?;
+        let mut node = parser
+            .parse_all()
+            .map_err(|err| self.throw_error(ErrorType::SyntaxError, Parser::error_message(&err)))?;
+        let func_info = self
+            .compile_with_source(&mut node, true, parser.source_text())
+            .map_err(|err| self.throw_error(ErrorType::SyntaxError, err.msg))?;
+        self.script_info
+            .push((func_info.module_func_id, parser.into_script_info()));
+        self.run_script(func_info)
+    }
+
+    /// Run the script ``func_info`` and return its completion value. Unlike ``run_global``, it
+    /// may be called while a script is running, e.g. from a native function.
+    pub fn run_script(&mut self, func_info: FuncInfoRef) -> VMValueResult {
+        let mut context = self.create_global_context(func_info);
+        let nested = self.interrupt.is_running();
+        if nested {
+            // Return to the frame being run when the script ends.
+            context.call_mode = CallMode::FromNative;
+            let outer = std::mem::replace(&mut self.current_context, context);
+            self.saved_context.push(outer);
+        } else {
+            self.current_context = context;
         }
+        self.profile_enter("<script>");
+        // The completion value when the code has no expression statement.
+        self.current_context.stack.push(Value::undefined().into());
+        // If called from builtin func, do not GC.
+        let save = self.is_called_from_native;
+        self.is_called_from_native |= nested;
+        let res = self.run();
+        self.is_called_from_native = save;
         res
     }
 
@@ -142,7 +515,7 @@ impl VM {
         for info in &global_info.func_decls {
             let name = info.func_name.clone().unwrap();
             let val = self.factory.function(*info, lex_env);
-            lex_env.set_value(name, val).unwrap();
+            lex_env.set_value(&name, val).unwrap();
         }
 
         let context = ExecContext::new(
@@ -158,23 +531,69 @@ impl VM {
 
     pub fn run_global(&mut self, func_info: FuncInfoRef) -> VMResult {
         self.current_context = self.create_global_context(func_info);
+        self.profile_enter("<script>");
         self.run()?;
 
         Ok(())
     }
 
     pub fn call_function(&mut self, callee: Value, args: &[Value], this: Value) -> VMValueResult {
-        if !callee.is_function_object() {
-            return Err(self.current_context.error_type("Not a function"));
+        if !callee.is_callable() {
+            return Err(self.throw_type_error(message::not_a_function(callee)));
+        }
+        if callee.is_proxy_object() {
+            return internal_methods(callee).call(self, callee, args, this);
         }
 
         let info = callee.as_function();
 
         match info.kind {
             FunctionObjectKind::Builtin(func) => func(self, args, this),
+            FunctionObjectKind::Native(id) => {
+                let func = self.native_functions[id].clone();
+                func(self, args, this)
+            }
             FunctionObjectKind::User { info, outer_env } => {
                 self.call_user_function(info, outer_env, args, this, false)
             }
+            FunctionObjectKind::Bound {
+                target,
+                this,
+                args: ref bound_args,
+            } => {
+                let args = [&bound_args[..], args].concat();
+                self.call_function(target, &args, this)
+            }
+        }
+    }
+
+    /// Call ``callee`` by new from a builtin, as ``enter_constructor`` does for the new
+    /// instruction.
+    pub fn construct(&mut self, callee: Value, args: &[Value]) -> VMValueResult {
+        if !callee.is_constructor() {
+            return Err(self.throw_type_error(message::not_a_constructor(callee)));
+        }
+        if callee.is_proxy_object() {
+            return internal_methods(callee).construct(self, callee, args);
+        }
+
+        let this = self.create_this(callee);
+        match callee.as_function().kind {
+            FunctionObjectKind::User { info, outer_env } => {
+                self.call_user_function(info, outer_env, args, this, true)
+            }
+            FunctionObjectKind::Bound {
+                target,
+                args: ref bound_args,
+                ..
+            } => {
+                let args = [&bound_args[..], args].concat();
+                self.construct(target, &args)
+            }
+            FunctionObjectKind::Builtin(_) | FunctionObjectKind::Native(_) => {
+                let val = self.call_function(callee, args, this)?;
+                Ok(if val.is_object() { val } else { this })
+            }
         }
     }
 
@@ -202,8 +621,18 @@ impl VM {
         res
     }
 
+    /// Get the property ``key`` of ``parent`` by \[\[Get\]\] if it is an object. Accessors are
+    /// not called.
+    fn get_property(&mut self, parent: Value, key: Value) -> Result<Property, RuntimeError> {
+        if parent.is_object() {
+            internal_methods(parent).get(self, parent, key)
+        } else {
+            parent.get_property_by_value(&mut self.factory, key)
+        }
+    }
+
     fn get_property_to_stack_top(&mut self, parent: Value, key: Value) -> VMResult {
-        let val = parent.get_property_by_value(&mut self.factory, key)?;
+        let val = self.get_property(parent, key)?;
         match val {
             Property::Data(DataProperty { val, .. }) => {
                 self.current_context.stack.push(val.into());
@@ -219,86 +648,287 @@ impl VM {
         }
     }
 
+    /// Pop the two operands of a binary operator, and apply ``op`` to them. They are popped
+    /// after ``op`` returns, since the methods of objects it may call may run the GC.
+    fn binary_operation<T>(
+        &mut self,
+        op: impl FnOnce(&mut VM, Value, Value) -> Result<T, RuntimeError>,
+    ) -> Result<T, RuntimeError> {
+        let len = self.current_context.stack.len();
+        let lhs: Value = self.current_context.stack[len - 2].into();
+        let rhs: Value = self.current_context.stack[len - 1].into();
+        let result = op(self, lhs, rhs);
+        self.current_context.stack.truncate(len - 2);
+        result
+    }
+
     pub fn get_property_by_value(
         &mut self,
         parent: Value,
         key: Value,
     ) -> Result<Value, RuntimeError> {
-        let val = parent.get_property_by_value(&mut self.factory, key)?;
+        let val = self.get_property(parent, key)?;
         match val {
             Property::Data(DataProperty { val, .. }) => Ok(val),
             Property::Accessor(AccessorProperty { get, .. }) => {
                 if get.is_undefined() {
                     return Ok(Value::undefined());
                 }
-                self.call_function(get, &[], parent)?;
-                Ok(self.current_context.stack.pop().unwrap().into(): Value)
+                self.call_function(get, &[], parent)
             }
         }
     }
 
+    /// Assign ``val`` to the property ``key`` of ``parent``, calling the setter if the property
+    /// is an accessor. Assigning to a read-only property is ignored, or a TypeError in strict
+    /// code.
     pub fn set_property_by_value(&mut self, parent: Value, key: Value, val: Value) -> VMResult {
-        let maybe_setter =
-            parent.set_property_by_value(&mut self.factory.memory_allocator, key, val)?;
-        if let Some(setter) = maybe_setter {
-            self.call_function(setter, &[val], parent)?;
-            self.current_context.stack.pop().unwrap(); // Pop undefined (setter's return value)
+        let result = if parent.is_object() {
+            internal_methods(parent).set(self, parent, key, val)?
+        } else {
+            parent.set_property_by_value(&mut self.factory.memory_allocator, key, val)?
+        };
+        match result {
+            SetResult::Done => {}
+            SetResult::CallSetter(setter) => {
+                self.call_function(setter, &[val], parent)?;
+            }
+            SetResult::Rejected => {
+                if self.current_context.func_ref.strict {
+                    return Err(self.throw_type_error(message::read_only_property(key, parent)));
+                }
+            }
         }
         Ok(())
     }
+
+    /// Delete the property ``key`` of ``parent``. Return false if it is not configurable, or
+    /// throw a TypeError in strict code.
+    pub fn delete_property_by_value(
+        &mut self,
+        parent: Value,
+        key: Value,
+    ) -> Result<bool, RuntimeError> {
+        let deleted = match parent {
+            Value::Object(_) => {
+                let key = operations::to_property_key(self, key)?;
+                internal_methods(parent).delete(self, parent, key)?
+            }
+            Value::Other(_) => {
+                return Err(self.throw_type_error(message::cannot_convert_to_object(parent)))
+            }
+            // The length and the characters of strings are not configurable.
+            Value::String(_) => {
+                let key = operations::to_property_key(self, key)?;
+                let is_own_property = match key.is_array_index() {
+                    Some(idx) => idx < parent.into_str().encode_utf16().count(),
+                    None => key.is_string() && key.into_str() == "length",
+                };
+                !is_own_property
+            }
+            _ => true,
+        };
+        if !deleted && self.current_context.func_ref.strict {
+            return Err(self.throw_type_error(message::cannot_delete_property(key, parent)));
+        }
+        Ok(deleted)
+    }
+
+    /// Whether the object ``obj`` has the property ``key``, as by the ``in`` operator.
+    pub fn has_property(&mut self, obj: Value, key: Value) -> Result<bool, RuntimeError> {
+        internal_methods(obj).has_property(self, obj, key)
+    }
 }
 
 impl VM {
+    /// Get the script where ``error`` occurred and the line and column in it.
+    fn error_position(&self, error: &RuntimeError) -> Option<(&ScriptInfo, usize, usize)> {
+        let func = self.factory.find_func_ref(error.func_id)?;
+        self.source_position(func, error.inst_pc)
+    }
+
+    /// Get the script of the instruction at ``inst_pc`` in ``func`` and the line and column in
+    /// it.
+    fn source_position(
+        &self,
+        func: FuncInfoRef,
+        inst_pc: usize,
+    ) -> Option<(&ScriptInfo, usize, usize)> {
+        let (line, column) = func.positions.lookup(inst_pc)?;
+        let info = self.script(func.module_func_id)?;
+        Some((info, line, column))
+    }
+
+    /// Get the script whose top level is the function ``module_func_id``.
+    pub fn script(&self, module_func_id: FunctionId) -> Option<&ScriptInfo> {
+        self.script_info
+            .iter()
+            .find(|info| info.0 == module_func_id)
+            .map(|info| &info.1)
+    }
+
+    /// Get the number and the text of the source line of the instruction at ``inst_pc`` in
+    /// ``func``, to show along with its bytecode.
+    pub fn source_line(&self, func: &UserFunctionInfo, inst_pc: usize) -> Option<(usize, &str)> {
+        let (line, _) = func.positions.lookup(inst_pc)?;
+        let text = self.script(func.module_func_id)?.line(line)?;
+        Some((line, text))
+    }
+
+    /// Get where ``error`` occurred as ``file_name:line:column``, with the file name and the
+    /// line offset given to the parser of the script.
+    pub fn error_location(&self, error: &RuntimeError) -> Option<String> {
+        let (info, line, column) = self.error_position(error)?;
+        Some(format!("{}:{}:{}", info.file_name, line, column))
+    }
+
+    /// The frames being run, innermost first, except those ``stack_trace_options`` hides.
+    pub fn stack_frames(&self) -> Vec<StackFrame> {
+        let contexts =
+            std::iter::once(&self.current_context).chain(self.saved_context.iter().rev());
+        let mut frames = vec![];
+        for context in contexts {
+            let func = context.func_ref;
+            if func.as_ptr().is_null() {
+                continue;
+            }
+            let location = self
+                .source_position(func, context.current_inst_pc)
+                .map(|(info, line, column)| (info.file_name.clone(), line, column));
+            let function_name = if func.func_id == func.module_func_id {
+                None
+            } else {
+                Some(
+                    func.func_name
+                        .as_ref()
+                        .map_or("<anonymous>", |name| name.as_str())
+                        .to_string(),
+                )
+            };
+            let frame = StackFrame {
+                function_name,
+                location,
+                is_builtin: self.builtin_modules.contains(&func.module_func_id),
+            };
+            if !self.stack_trace_options.is_hidden(&frame) {
+                frames.push(frame);
+            }
+        }
+        frames
+    }
+
+    /// The frames being run, innermost first, each on a line like ``    at f (file:line:col)``.
+    pub fn stack_trace(&self) -> String {
+        self.stack_frames()
+            .iter()
+            .map(|frame| format!("    {}", frame))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// ``Error.stackTraceLimit`` of the Error constructor, found as Error.prototype.constructor.
+    fn stack_trace_limit(&self) -> Option<usize> {
+        let prototype = self.factory.object_prototypes.error;
+        let error = prototype.get_property("constructor");
+        stack_trace_limit(error.get_property("stackTraceLimit"))
+    }
+
+    /// Create an instance of Error or a native error, with ``stack`` describing where it was
+    /// created.
+    pub fn create_error(&mut self, error_type: ErrorType, message: impl Into<String>) -> Value {
+        let message = message.into();
+        let header = format!("{}: {}", error_type.name(), message);
+        let stack = self.stack_trace_limit().map(|limit| {
+            let mut frames = self.stack_frames();
+            frames.truncate(limit);
+            match self.stack_trace_options.prepare_stack_trace {
+                Some(ref prepare) => prepare(&header, &frames),
+                None => format_stack_trace(&header, &frames),
+            }
+        });
+        let error = self.factory.native_error(error_type, message);
+        if let Some(stack) = stack {
+            let stack = self.factory.string(stack);
+            error.set_property("stack", stack);
+        }
+        error
+    }
+
+    /// Throw an instance of Error or a native error from the running code.
+    pub fn throw_error(
+        &mut self,
+        error_type: ErrorType,
+        message: impl Into<String>,
+    ) -> RuntimeError {
+        let error = self.create_error(error_type, message);
+        self.current_context.error_exception(error)
+    }
+
+    pub fn throw_type_error(&mut self, message: impl Into<String>) -> RuntimeError {
+        self.throw_error(ErrorType::TypeError, message)
+    }
+
+    pub fn throw_reference_error(&mut self, message: impl Into<String>) -> RuntimeError {
+        self.throw_error(ErrorType::ReferenceError, message)
+    }
+
+    pub fn throw_range_error(&mut self, message: impl Into<String>) -> RuntimeError {
+        self.throw_error(ErrorType::RangeError, message)
+    }
+
+    /// Convert ``error`` into the value thrown to the script.
+    pub fn error_to_value(&mut self, error: RuntimeError) -> Value {
+        match error.kind.into_error() {
+            Ok((error_type, message)) => self.create_error(error_type, message),
+            Err(val) => val,
+        }
+    }
+
     pub fn show_error_message(&self, error: RuntimeError) {
         match &error.kind {
             ErrorKind::Unknown => runtime_error("UnknownError"),
             ErrorKind::Unimplemented => runtime_error("Unimplemented feature"),
+            ErrorKind::ExecutionTerminated => runtime_error("Execution terminated"),
+            ErrorKind::OutOfFuel => runtime_error("Out of fuel"),
             ErrorKind::Reference(msg) => runtime_error(format!("ReferenceError: {}", msg)),
             ErrorKind::Type(msg) => runtime_error(format!("TypeError: {}", msg)),
+            ErrorKind::Range(msg) => runtime_error(format!("RangeError: {}", msg)),
             ErrorKind::General(msg) => runtime_error(format!("Error: {}", msg)),
-            ErrorKind::Exception(ref val) => {
-                runtime_error("Uncaught Exception");
-                let pos_in_script = self
-                    .to_source_map
-                    .get(&error.func_id)
-                    .unwrap()
-                    .get_node_pos(error.inst_pc);
-                let module_func_id = error.module_func_id;
-                let info = &self
-                    .script_info
-                    .iter()
-                    .find(|info| info.0 == module_func_id)
-                    .unwrap()
-                    .1;
-                if let Some(pos) = pos_in_script {
-                    let (msg, _, line) = get_code_around_err_point(info, pos);
-                    println!("line: {}", line);
-                    println!("{}", msg);
+            ErrorKind::Exception(val) if val.is_error_object() => {
+                runtime_error(format!("Uncaught {}", val.to_string()))
+            }
+            ErrorKind::Exception(_) => runtime_error("Uncaught Exception"),
+        }
+
+        if let Some((info, line, column)) = self.error_position(&error) {
+            println!("at {}:{}:{}", info.file_name, line, column);
+            if let Some(msg) = get_code_around_err_point(info, line, column) {
+                println!("{}", msg);
+            }
+        }
+
+        if let ErrorKind::Exception(ref val) = error.kind {
+            if val.is_error_object() {
+                let stack = val.get_property("stack");
+                if !stack.is_undefined() {
+                    println!("{}", stack.to_string());
                 }
+            } else {
                 debug_print(val, false);
                 println!();
             }
         }
 
-        pub fn get_code_around_err_point(info: &ScriptInfo, pos: usize) -> (String, usize, usize) {
-            let code = info.code.as_bytes();
-            let iter = info.pos_line_list.iter();
-            let (start_pos, line) = iter.take_while(|x| x.0 <= pos).last().unwrap();
-
-            let mut iter = info.pos_line_list.iter();
-            let end_pos = match iter
-                .find(|x| x.0 > pos)
-                .unwrap_or(info.pos_line_list.last().unwrap())
-                .0
-            {
-                x if x == 0 => 0,
-                x => x - 1,
-            };
-            let surrounding_code = String::from_utf8(code[*start_pos..end_pos].to_vec())
-                .unwrap()
-                .to_string();
-            let err_point = format!("{}{}", " ".repeat(pos - start_pos), "^",);
-            (surrounding_code + "\n" + err_point.as_str(), pos, *line)
+        /// The line ``line`` of the script with ``^`` under the column ``column``.
+        fn get_code_around_err_point(
+            info: &ScriptInfo,
+            line: usize,
+            column: usize,
+        ) -> Option<String> {
+            let start_pos = info.pos_line_list.iter().find(|x| x.1 == line)?.0;
+            let surrounding_code = info.code[start_pos..].lines().next().unwrap_or("");
+            let err_point = format!("{}{}", " ".repeat(column - 1), "^");
+            Some(format!("{}\n{}", surrounding_code, err_point))
         }
     }
 }
@@ -326,85 +956,191 @@ macro_rules! read_int32 {
 
 impl VM {
     pub fn run(&mut self) -> VMValueResult {
-        #[derive(Debug, Clone)]
-        enum SubroutineKind {
-            Ordinary(usize),
-            Throw,
-            Return,
+        let outermost = self.native_stack_base == 0;
+        if outermost {
+            let probe = 0u8;
+            self.native_stack_base = &probe as *const u8 as usize;
+        }
+        self.interrupt.enter();
+        let res = self.execute();
+        self.interrupt.leave();
+        if outermost {
+            self.native_stack_base = 0;
+        }
+        match res {
+            Err(err) if !self.interrupt.is_running() => self.handle_uncaught_exception(err),
+            res => res,
+        }
+    }
+
+    /// Pass ``err`` to the handler given by ``set_uncaught_exception_handler``, if any.
+    fn handle_uncaught_exception(&mut self, err: RuntimeError) -> VMValueResult {
+        match err.kind {
+            ErrorKind::ExecutionTerminated | ErrorKind::OutOfFuel => return Err(err),
+            _ => {}
+        }
+        let mut handler = match self.uncaught_exception_handler.take() {
+            Some(handler) => handler,
+            None => return Err(err),
+        };
+        let val = self.error_to_value(err);
+        let stack = match val {
+            val if val.is_error_object() => match val.get_property("stack") {
+                stack if stack.is_undefined() => None,
+                stack => Some(stack.to_string()),
+            },
+            _ => None,
+        };
+        // The handler may run scripts, and the exceptions they throw are returned to it.
+        self.factory.memory_allocator.lock_value(val);
+        handler(self, val, stack);
+        self.factory.memory_allocator.unlock_value(val);
+        if self.uncaught_exception_handler.is_none() {
+            self.uncaught_exception_handler = Some(handler);
+        }
+        Ok(Value::undefined())
+    }
+
+    /// The size in bytes of the native stack used since the outermost ``run`` started.
+    fn native_stack_size(&self) -> usize {
+        if self.native_stack_base == 0 {
+            return 0;
         }
+        let probe = 0u8;
+        // The stack grows downward on the platforms supported.
+        self.native_stack_base
+            .saturating_sub(&probe as *const u8 as usize)
+    }
 
-        fn handle_exception(vm: &mut VM, subroutine_stack: &mut Vec<SubroutineKind>) -> VMResult {
-            let mut trycatch_found = false;
+    fn execute(&mut self) -> VMValueResult {
+        /// Unwind frames until a try/catch/finally handler covering the instruction being run is
+        /// found. The thrown value is at the stack top, and is pushed again onto the stack of the
+        /// frame which has the handler. If no handler is found in the frames run by this `run()`
+        /// (frames called from native functions are run by another `run()`), the thrown value is
+        /// returned as `ErrorKind::Exception`.
+        fn handle_exception(vm: &mut VM) -> VMResult {
             let save_error_info = vm.current_context.error_unknown();
+            let thrown: Value = vm.current_context.stack.pop().unwrap().into();
             loop {
-                for exception in &vm.current_context.func_ref.exception_table {
-                    let in_range = exception.start <= vm.current_context.pc
-                        && vm.current_context.pc < exception.end;
-                    if !in_range {
-                        continue;
-                    }
-                    match exception.dst_kind {
-                        DestinationKind::Catch => vm.current_context.pc = exception.end,
-                        DestinationKind::Finally => {
-                            subroutine_stack.push(SubroutineKind::Throw);
-                            vm.current_context.pc = exception.end
-                        }
-                    }
+                // The instruction being run in a caller frame is the call.
+                let pc = vm.current_context.current_inst_pc;
+                if let Some(handler) = vm.current_context.find_handler(pc, false) {
+                    vm.current_context.enter_handler(&handler, SubroutineKind::Throw);
+                    vm.cover_block();
+                    vm.current_context.stack.push(thrown.into());
+                    return Ok(());
+                }
 
-                    trycatch_found = true;
+                if vm.saved_context.len() == 0 {
                     break;
                 }
 
-                if trycatch_found {
+                let call_mode = vm.current_context.call_mode;
+                vm.current_context = vm.saved_context.pop().unwrap();
+                vm.profile_leave();
+                if call_mode == CallMode::FromNative {
                     break;
                 }
+            }
 
-                if vm.saved_context.len() == 0 {
+            let mut err = save_error_info;
+            err.kind = ErrorKind::Exception(thrown);
+            Err(err)
+        }
+
+        /// Unwind the frames run by this `run()` without running handlers, and make the error
+        /// terminating the script.
+        fn terminate(vm: &mut VM) -> RuntimeError {
+            let err = RuntimeError::new(ErrorKind::ExecutionTerminated, &vm.current_context);
+            while vm.saved_context.len() > 0 {
+                let call_mode = vm.current_context.call_mode;
+                vm.current_context = vm.saved_context.pop().unwrap();
+                vm.profile_leave();
+                if call_mode == CallMode::FromNative {
                     break;
                 }
-                vm.unwind_context();
             }
+            log::debug!("script terminated");
+            err
+        }
 
-            if !trycatch_found {
-                let val: Value = vm.current_context.stack.pop().unwrap().into();
-                let mut err = save_error_info;
-                err.kind = ErrorKind::Exception(val);
-                return Err(err);
-            } else {
-                Ok(())
+        /// Jump to the innermost finally clause covering the instruction being run, to run it
+        /// before returning the value at the stack top. Return false if there is no such clause.
+        fn enter_finally_for_return(vm: &mut VM) -> bool {
+            let pc = vm.current_context.current_inst_pc;
+            match vm.current_context.find_handler(pc, true) {
+                Some(handler) => {
+                    vm.current_context.enter_handler(&handler, SubroutineKind::Return);
+                    vm.cover_block();
+                    true
+                }
+                None => false,
             }
         }
 
-        let mut subroutine_stack: Vec<SubroutineKind> = vec![];
         self.profile.trace_string = "".to_string();
 
         loop {
             self.current_context.current_inst_pc = self.current_context.pc;
+            if self.interrupt.check() {
+                return Err(terminate(self));
+            }
+            if !self.interrupt.consume_fuel() {
+                if self.interrupt.is_nested() {
+                    return Err(terminate(self));
+                }
+                let err = RuntimeError::new(ErrorKind::OutOfFuel, &self.current_context);
+                return Err(err);
+            }
             if self.is_profile || self.is_trace {
                 self.trace_print();
             }
 
             macro_rules! type_error {
                 ($msg:expr) => {{
-                    let val = self
-                        .current_context
-                        .error_type($msg)
-                        .to_value(&mut self.factory);
+                    let val = self.create_error(ErrorType::TypeError, $msg);
                     self.current_context.stack.push(val.into());
-                    handle_exception(self, &mut subroutine_stack)?;
+                    handle_exception(self)?;
                     continue;
                 }};
             }
 
+            macro_rules! return_from_function {
+                () => {{
+                    let call_mode = self.current_context.call_mode;
+                    if self.saved_context.len() == 0 {
+                        break;
+                    };
+                    self.unwind_context();
+
+                    if call_mode == CallMode::FromNative {
+                        break;
+                    }
+                    // If call from built-in func, do not GC.
+                    if !self.is_called_from_native {
+                        self.gc_mark()
+                    };
+
+                    if self.is_trace {
+                        self.profile.trace_string = format!(
+                            "{}\n<-- return\n  module_id:{:?} func_id:{:?}",
+                            self.profile.trace_string,
+                            self.current_context.func_ref.module_func_id,
+                            self.current_context.func_ref.func_id
+                        );
+                    };
+                }};
+            }
+
             macro_rules! etry {
                 ($val:expr) => {{
                     match $val {
                         Ok(ok) => ok,
+                        Err(ref err) if err.is_terminated() => return Err(terminate(self)),
                         Err(err) => {
-                            let err = err.error_add_info(&self.current_context);
-                            let val = err.to_value(&mut self.factory);
+                            let val = self.error_to_value(err);
                             self.current_context.stack.push(val.into());
-                            handle_exception(self, &mut subroutine_stack)?;
+                            handle_exception(self)?;
                             continue;
                         }
                     }
@@ -417,52 +1153,60 @@ impl VM {
                 // TODO: Macro for bin ops?
                 VMInst::ADD => {
                     self.current_context.pc += 1;
-                    let rhs = self.current_context.stack.pop().unwrap();
-                    let lhs = self.current_context.stack.pop().unwrap();
-                    /*
-                    let res: f64 =
-                        unsafe { std::mem::transmute(rhs): f64 + std::mem::transmute(lhs): f64 };
-
-                    if !res.is_nan() {
-                        self.current_context
-                            .stack
-                            .push(unsafe { std::mem::transmute(res) });
-                    } else {
-                        */
-                    let rhs_val: Value = rhs.into();
-                    let lhs_val: Value = lhs.into();
-                    self.current_context.stack.push(
-                        lhs_val
-                            .add(&mut self.factory.memory_allocator, rhs_val)
-                            .into(),
-                    );
-                    //}
+                    let val = etry!(self.binary_operation(|vm, lhs, rhs| match (lhs, rhs) {
+                        // Fast path for loop counters
+                        (Value::Number(lhs), Value::Number(rhs)) => Ok(Value::Number(lhs + rhs)),
+                        _ => operations::add(vm, lhs, rhs),
+                    }));
+                    self.current_context.stack.push(val.into());
                 }
                 VMInst::SUB => {
                     self.current_context.pc += 1;
-                    let rhs: Value = self.current_context.stack.pop().unwrap().into();
-                    let lhs: Value = self.current_context.stack.pop().unwrap().into();
-                    self.current_context
-                        .stack
-                        .push(lhs.sub(&mut self.factory.memory_allocator, rhs).into());
+                    let val = etry!(self.binary_operation(|vm, lhs, rhs| match (lhs, rhs) {
+                        (Value::Number(lhs), Value::Number(rhs)) => Ok(Value::Number(lhs - rhs)),
+                        _ => operations::numeric_operation(vm, lhs, rhs, |x, y| x - y),
+                    }));
+                    self.current_context.stack.push(val.into());
+                }
+                VMInst::INC | VMInst::DEC => {
+                    self.current_context.pc += 1;
+                    // The operand stays on the stack while valueOf or toString may run.
+                    let val: Value = (*self.current_context.stack.last().unwrap()).into();
+                    let n = match val {
+                        Value::Number(n) => Ok(n),
+                        _ => operations::to_number(self, val),
+                    };
+                    self.current_context.stack.pop();
+                    let n = etry!(n);
+                    let n = if inst == VMInst::INC {
+                        n + 1.0
+                    } else {
+                        n - 1.0
+                    };
+                    self.current_context.stack.push(Value::Number(n).into());
                 }
                 VMInst::MUL => {
                     self.current_context.pc += 1;
-                    let rhs: Value = self.current_context.stack.pop().unwrap().into();
-                    let lhs: Value = self.current_context.stack.pop().unwrap().into();
-                    self.current_context.stack.push(lhs.mul(rhs).into());
+                    let val = etry!(self.binary_operation(|vm, lhs, rhs| {
+                        operations::numeric_operation(vm, lhs, rhs, |x, y| x * y)
+                    }));
+                    self.current_context.stack.push(val.into());
                 }
                 VMInst::DIV => {
                     self.current_context.pc += 1;
-                    let rhs: Value = self.current_context.stack.pop().unwrap().into();
-                    let lhs: Value = self.current_context.stack.pop().unwrap().into();
-                    self.current_context.stack.push(lhs.div(rhs).into());
+                    let val = etry!(self.binary_operation(|vm, lhs, rhs| {
+                        operations::numeric_operation(vm, lhs, rhs, |x, y| x / y)
+                    }));
+                    self.current_context.stack.push(val.into());
                 }
                 VMInst::REM => {
                     self.current_context.pc += 1;
-                    let rhs: Value = self.current_context.stack.pop().unwrap().into();
-                    let lhs: Value = self.current_context.stack.pop().unwrap().into();
-                    self.current_context.stack.push(lhs.rem(rhs).into());
+                    // % of f64 keeps the sign of x (e.g. -4 % 2 is -0) as the spec does.
+                    // https://tc39.github.io/ecma262/#sec-numeric-types-number-remainder
+                    let val = etry!(self.binary_operation(|vm, lhs, rhs| {
+                        operations::numeric_operation(vm, lhs, rhs, |x, y| x % y)
+                    }));
+                    self.current_context.stack.push(val.into());
                 }
                 VMInst::EXP => {
                     self.current_context.pc += 1;
@@ -474,11 +1218,8 @@ impl VM {
                 }
                 VMInst::EQ => {
                     self.current_context.pc += 1;
-                    let rhs: Value = self.current_context.stack.pop().unwrap().into();
-                    let lhs: Value = self.current_context.stack.pop().unwrap().into();
-                    self.current_context
-                        .stack
-                        .push(lhs.eq(&mut self.factory.memory_allocator, rhs).into());
+                    let val = etry!(self.binary_operation(operations::loosely_equal));
+                    self.current_context.stack.push(Value::bool(val).into());
                 }
                 VMInst::SEQ => {
                     self.current_context.pc += 1;
@@ -488,11 +1229,8 @@ impl VM {
                 }
                 VMInst::NE => {
                     self.current_context.pc += 1;
-                    let rhs: Value = self.current_context.stack.pop().unwrap().into();
-                    let lhs: Value = self.current_context.stack.pop().unwrap().into();
-                    self.current_context
-                        .stack
-                        .push(lhs.ne(&mut self.factory.memory_allocator, rhs).into());
+                    let eq = etry!(self.binary_operation(operations::loosely_equal));
+                    self.current_context.stack.push(Value::bool(!eq).into());
                 }
                 VMInst::SNE => {
                     self.current_context.pc += 1;
@@ -502,35 +1240,23 @@ impl VM {
                 }
                 VMInst::LT => {
                     self.current_context.pc += 1;
-                    let rhs: Value = self.current_context.stack.pop().unwrap().into();
-                    let lhs: Value = self.current_context.stack.pop().unwrap().into();
-                    self.current_context
-                        .stack
-                        .push(lhs.lt(&mut self.factory.memory_allocator, rhs).into());
+                    let val = etry!(self.binary_operation(operations::lt));
+                    self.current_context.stack.push(Value::bool(val).into());
                 }
                 VMInst::LE => {
                     self.current_context.pc += 1;
-                    let rhs: Value = self.current_context.stack.pop().unwrap().into();
-                    let lhs: Value = self.current_context.stack.pop().unwrap().into();
-                    self.current_context
-                        .stack
-                        .push(lhs.le(&mut self.factory.memory_allocator, rhs).into());
+                    let val = etry!(self.binary_operation(operations::le));
+                    self.current_context.stack.push(Value::bool(val).into());
                 }
                 VMInst::GT => {
                     self.current_context.pc += 1;
-                    let rhs: Value = self.current_context.stack.pop().unwrap().into();
-                    let lhs: Value = self.current_context.stack.pop().unwrap().into();
-                    self.current_context
-                        .stack
-                        .push(rhs.lt(&mut self.factory.memory_allocator, lhs).into());
+                    let val = etry!(self.binary_operation(operations::gt));
+                    self.current_context.stack.push(Value::bool(val).into());
                 }
                 VMInst::GE => {
                     self.current_context.pc += 1;
-                    let rhs: Value = self.current_context.stack.pop().unwrap().into();
-                    let lhs: Value = self.current_context.stack.pop().unwrap().into();
-                    self.current_context
-                        .stack
-                        .push(rhs.le(&mut self.factory.memory_allocator, lhs).into());
+                    let val = etry!(self.binary_operation(operations::ge));
+                    self.current_context.stack.push(Value::bool(val).into());
                 }
                 VMInst::AND => {
                     self.current_context.pc += 1;
@@ -590,15 +1316,21 @@ impl VM {
                 }
                 VMInst::NEG => {
                     self.current_context.pc += 1;
-                    let val: Value = self.current_context.stack.pop().unwrap().into();
-                    self.current_context.stack.push(val.minus().into());
+                    // The operand stays on the stack while valueOf or toString may run.
+                    let val: Value = (*self.current_context.stack.last().unwrap()).into();
+                    let n = operations::to_number(self, val);
+                    self.current_context.stack.pop();
+                    let n = etry!(n);
+                    self.current_context.stack.push(Value::Number(-n).into());
                 }
                 VMInst::POSI => {
                     self.current_context.pc += 1;
-                    let val: Value = self.current_context.stack.pop().unwrap().into();
-                    self.current_context
-                        .stack
-                        .push(val.positive(&mut self.factory.memory_allocator).into());
+                    // The operand stays on the stack while valueOf or toString may run.
+                    let val: Value = (*self.current_context.stack.last().unwrap()).into();
+                    let n = operations::to_number(self, val);
+                    self.current_context.stack.pop();
+                    let n = etry!(n);
+                    self.current_context.stack.push(Value::Number(n).into());
                 }
                 VMInst::LNOT => {
                     self.current_context.pc += 1;
@@ -608,8 +1340,10 @@ impl VM {
                 }
                 VMInst::PUSH_INT8 => {
                     self.current_context.pc += 1;
-                    read_int8!(self, num, f64);
-                    self.current_context.stack.push(Value::Number(num).into());
+                    read_int8!(self, num, i8);
+                    self.current_context
+                        .stack
+                        .push(Value::Number(num as f64).into());
                 }
                 VMInst::PUSH_INT32 => {
                     self.current_context.pc += 1;
@@ -654,7 +1388,7 @@ impl VM {
                     self.current_context.pc += 1;
                     let val: Value = self.current_context.stack.pop().unwrap().into();
                     if !val.is_array_object() {
-                        type_error!("Not an array.")
+                        type_error!(message::not_iterable(val))
                     }
                     let ary = val.as_array_mut();
                     let len = ary.get_length();
@@ -676,21 +1410,94 @@ impl VM {
                     let val: Value = self.current_context.stack.pop().unwrap().into();
                     etry!(self.set_property_by_value(parent, property, val))
                 }
+                VMInst::DELETE_MEMBER => {
+                    self.current_context.pc += 1;
+                    let property: Value = self.current_context.stack.pop().unwrap().into();
+                    let parent: Value = self.current_context.stack.pop().unwrap().into();
+                    let deleted = etry!(self.delete_property_by_value(parent, property));
+                    self.current_context.stack.push(Value::bool(deleted).into());
+                }
+                VMInst::IN => {
+                    self.current_context.pc += 1;
+                    let rhs: Value = self.current_context.stack.pop().unwrap().into();
+                    let lhs: Value = self.current_context.stack.pop().unwrap().into();
+                    let has = if rhs.is_object() {
+                        self.has_property(rhs, lhs)
+                    } else {
+                        let message = message::cannot_use_in_operator(lhs, rhs);
+                        Err(self.throw_type_error(message))
+                    };
+                    let has = etry!(has);
+                    self.current_context.stack.push(Value::bool(has).into());
+                }
+                VMInst::GET_NAMED_MEMBER => {
+                    self.current_context.pc += 1;
+                    read_int32!(self, key_id, usize);
+                    let property = *self.constant_table.get(key_id).as_value();
+                    let parent: Value = self.current_context.stack.pop().unwrap().into();
+                    etry!(self.get_property_to_stack_top(parent, property))
+                }
+                VMInst::SET_NAMED_MEMBER => {
+                    self.current_context.pc += 1;
+                    read_int32!(self, key_id, usize);
+                    let property = *self.constant_table.get(key_id).as_value();
+                    let parent: Value = self.current_context.stack.pop().unwrap().into();
+                    let val: Value = self.current_context.stack.pop().unwrap().into();
+                    etry!(self.set_property_by_value(parent, property, val))
+                }
                 VMInst::SET_VALUE => {
                     self.current_context.pc += 1;
                     read_int32!(self, name_id, usize);
                     let val = self.current_context.stack.pop().unwrap();
-                    let name = self.constant_table.get(name_id).as_string().clone();
+                    let name = self.constant_table.get(name_id).as_string();
                     etry!(self
                         .current_context
                         .lex_env_mut()
                         .set_value(name, val.into()));
                 }
+                VMInst::GET_UPVALUE => {
+                    self.current_context.pc += 1;
+                    read_int32!(self, name_id, usize);
+                    let name = self.constant_table.get(name_id).as_string();
+                    let val = etry!(self.closure_environment().get_value(name));
+                    self.current_context.stack.push(val.into());
+                }
+                VMInst::SET_UPVALUE => {
+                    self.current_context.pc += 1;
+                    read_int32!(self, name_id, usize);
+                    let val = self.current_context.stack.pop().unwrap();
+                    let name = self.constant_table.get(name_id).as_string();
+                    let mut env = self.closure_environment();
+                    etry!(env.set_value(name, val.into()));
+                }
+                VMInst::GET_REGISTER => {
+                    self.current_context.pc += 1;
+                    read_int32!(self, index, usize);
+                    let val = self.current_context.registers[index];
+                    self.current_context.stack.push(val.into());
+                }
+                VMInst::SET_REGISTER => {
+                    self.current_context.pc += 1;
+                    read_int32!(self, index, usize);
+                    let val = self.current_context.stack.pop().unwrap();
+                    self.current_context.registers[index] = val.into();
+                }
+                VMInst::DECL_LET | VMInst::DECL_CONST => {
+                    self.current_context.pc += 1;
+                    read_int32!(self, name_id, usize);
+                    let val = self.current_context.stack.pop().unwrap();
+                    let name = self.constant_table.get(name_id).as_string().to_string();
+                    etry!(self.current_context.lex_env_mut().initialize_binding(
+                        name,
+                        val.into(),
+                        inst == VMInst::DECL_CONST
+                    ));
+                }
                 VMInst::GET_VALUE => {
                     self.current_context.pc += 1;
                     read_int32!(self, name_id, usize);
                     let string = self.constant_table.get(name_id).as_string();
-                    let val = etry!(self.current_context.lex_env().get_value(string.clone()));
+                    let val = etry!(self.current_context.lex_env().get_value(string));
                     self.current_context.stack.push(val.into());
                 }
                 VMInst::CONSTRUCT => {
@@ -701,7 +1508,7 @@ impl VM {
                     for _ in 0..argc {
                         args.push(self.current_context.stack.pop().unwrap().into());
                     }
-                    self.enter_constructor(callee, &args)?;
+                    etry!(self.enter_constructor(callee, &args))
                 }
                 VMInst::CALL => {
                     self.current_context.pc += 1;
@@ -711,7 +1518,50 @@ impl VM {
                     for _ in 0..argc {
                         args.push(self.current_context.stack.pop().unwrap().into());
                     }
-                    etry!(self.enter_function(callee, &args, self.current_context.this, false))
+                    etry!(self.enter_function_cached(callee, &args, Value::undefined()))
+                }
+                VMInst::TAIL_CALL => {
+                    self.current_context.pc += 1;
+                    read_int32!(self, argc, usize);
+                    let callee: Value = self.current_context.stack.pop().unwrap().into();
+                    let mut args: Vec<Value> = vec![];
+                    for _ in 0..argc {
+                        args.push(self.current_context.stack.pop().unwrap().into());
+                    }
+                    etry!(self.enter_tail_call(callee, &args, Value::undefined()))
+                }
+                VMInst::TAIL_CALL_METHOD => {
+                    self.current_context.pc += 1;
+                    read_int32!(self, argc, usize);
+                    let parent: Value = self.current_context.stack.pop().unwrap().into();
+                    let method: Value = self.current_context.stack.pop().unwrap().into();
+                    let mut args: Vec<Value> = vec![];
+                    for _ in 0..argc {
+                        args.push(self.current_context.stack.pop().unwrap().into());
+                    }
+                    let callee = etry!(self.get_property_by_value(parent, method));
+                    etry!(self.enter_tail_call(callee, &args, parent))
+                }
+                VMInst::CALL_EVAL => {
+                    self.current_context.pc += 1;
+                    read_int32!(self, argc, usize);
+                    let callee: Value = self.current_context.stack.pop().unwrap().into();
+                    let mut args: Vec<Value> = vec![];
+                    for _ in 0..argc {
+                        args.push(self.current_context.stack.pop().unwrap().into());
+                    }
+                    match args.get(0) {
+                        _ if !VM::is_intrinsic_eval(callee) => {
+                            etry!(self.enter_function(callee, &args, Value::undefined(), false))
+                        }
+                        Some(code @ Value::String(_)) => etry!(self.prepare_context_for_eval(
+                            code.to_string(),
+                            true,
+                            CallMode::OrdinaryCall
+                        )),
+                        Some(val) => self.current_context.stack.push((*val).into()),
+                        None => self.current_context.stack.push(Value::undefined().into()),
+                    }
                 }
                 VMInst::CALL_METHOD => {
                     self.current_context.pc += 1;
@@ -722,12 +1572,8 @@ impl VM {
                     for _ in 0..argc {
                         args.push(self.current_context.stack.pop().unwrap().into());
                     }
-                    let callee = match etry!(parent.get_property_by_value(&mut self.factory, method))
-                    {
-                        Property::Data(DataProperty { val, .. }) => val,
-                        _ => type_error!("Not a function"),
-                    };
-                    etry!(self.enter_function(callee, &args, parent, false))
+                    let callee = etry!(self.get_property_by_value(parent, method));
+                    etry!(self.enter_function_cached(callee, &args, parent))
                 }
                 VMInst::SET_OUTER_ENV => {
                     self.current_context.pc += 1;
@@ -744,7 +1590,7 @@ impl VM {
                 }
                 VMInst::CREATE_ARRAY => {
                     self.current_context.pc += 1;
-                    self.create_array()?;
+                    etry!(self.create_array());
                     //self.gc_mark();
                 }
                 VMInst::DOUBLE => {
@@ -766,6 +1612,12 @@ impl VM {
                         .unwrap();
                     self.current_context.lexical_environment = lex_env;
                 }
+                VMInst::COPY_ENV => {
+                    self.current_context.pc += 1;
+                    let env = (*self.current_context.lexical_environment).clone();
+                    self.current_context.lexical_environment =
+                        LexicalEnvironmentRef(self.factory.alloc(env));
+                }
                 VMInst::POP => {
                     self.current_context.pc += 1;
                     self.current_context.stack.pop();
@@ -779,65 +1631,90 @@ impl VM {
                         self.current_context.pc =
                             (self.current_context.pc as isize + dst as isize) as usize;
                     }
+                    self.cover_block();
+                }
+                VMInst::LT_JMP_IF_FALSE
+                | VMInst::LE_JMP_IF_FALSE
+                | VMInst::GT_JMP_IF_FALSE
+                | VMInst::GE_JMP_IF_FALSE => {
+                    self.current_context.pc += 1;
+                    read_int32!(self, dst, i32);
+                    let cond = etry!(self.binary_operation(|vm, lhs, rhs| match (lhs, rhs) {
+                        // Fast path for loop counters
+                        (Value::Number(lhs), Value::Number(rhs)) => Ok(match inst {
+                            VMInst::LT_JMP_IF_FALSE => lhs < rhs,
+                            VMInst::LE_JMP_IF_FALSE => lhs <= rhs,
+                            VMInst::GT_JMP_IF_FALSE => lhs > rhs,
+                            _ => lhs >= rhs,
+                        }),
+                        _ => match inst {
+                            VMInst::LT_JMP_IF_FALSE => operations::lt(vm, lhs, rhs),
+                            VMInst::LE_JMP_IF_FALSE => operations::le(vm, lhs, rhs),
+                            VMInst::GT_JMP_IF_FALSE => operations::gt(vm, lhs, rhs),
+                            _ => operations::ge(vm, lhs, rhs),
+                        },
+                    }));
+                    if !cond {
+                        self.current_context.pc =
+                            (self.current_context.pc as isize + dst as isize) as usize;
+                    }
+                    self.cover_block();
                 }
                 VMInst::JMP => {
                     self.current_context.pc += 1;
                     read_int32!(self, dst, i32);
                     self.current_context.pc =
                         (self.current_context.pc as isize + dst as isize) as usize;
+                    self.cover_block();
                 }
                 VMInst::JMP_SUB => {
                     self.current_context.pc += 1;
                     read_int32!(self, dst, i32);
-                    subroutine_stack.push(SubroutineKind::Ordinary(self.current_context.pc));
-                    self.current_context.pc =
-                        (self.current_context.pc as isize + dst as isize) as usize;
+                    let pc = self.current_context.pc;
+                    self.current_context
+                        .subroutine_stack
+                        .push(SubroutineKind::Ordinary(pc));
+                    self.current_context.pc = (pc as isize + dst as isize) as usize;
+                    self.cover_block();
                 }
                 VMInst::RETURN_TRY => {
                     self.current_context.pc += 1;
-                    read_int32!(self, dst, i32);
-                    self.current_context.pc =
-                        (self.current_context.pc as isize + dst as isize) as usize;
-                    subroutine_stack.push(SubroutineKind::Return);
+                    if !enter_finally_for_return(self) {
+                        return_from_function!();
+                    }
                 }
                 VMInst::RETURN_SUB => {
                     self.current_context.pc += 1;
-                    match subroutine_stack.pop().unwrap() {
-                        SubroutineKind::Ordinary(pos) => self.current_context.pc = pos,
-                        SubroutineKind::Throw => handle_exception(self, &mut subroutine_stack)?,
+                    match self.current_context.subroutine_stack.pop().unwrap() {
+                        SubroutineKind::Ordinary(pos) => {
+                            self.current_context.pc = pos;
+                            self.cover_block();
+                        }
+                        SubroutineKind::Throw => handle_exception(self)?,
+                        // Run the finally clauses enclosing this one before returning.
                         SubroutineKind::Return => {
-                            self.unwind_context();
+                            if !enter_finally_for_return(self) {
+                                return_from_function!();
+                            }
+                        }
+                    }
+                }
+                VMInst::DROP_SUB => {
+                    self.current_context.pc += 1;
+                    match self.current_context.subroutine_stack.pop().unwrap() {
+                        SubroutineKind::Ordinary(_) => {}
+                        SubroutineKind::Throw | SubroutineKind::Return => {
+                            self.current_context.stack.pop();
                         }
                     }
                 }
                 VMInst::THROW => {
                     self.current_context.pc += 1;
-                    handle_exception(self, &mut subroutine_stack)?;
+                    handle_exception(self)?;
                 }
                 VMInst::RETURN => {
                     self.current_context.pc += 1;
-                    let call_mode = self.current_context.call_mode;
-                    if self.saved_context.len() == 0 {
-                        break;
-                    };
-                    self.unwind_context();
-
-                    if call_mode == CallMode::FromNative {
-                        break;
-                    }
-                    // If call from built-in func, do not GC.
-                    if !self.is_called_from_native {
-                        self.gc_mark()
-                    };
-
-                    if self.is_trace {
-                        self.profile.trace_string = format!(
-                            "{}\n<-- return\n  module_id:{:?} func_id:{:?}",
-                            self.profile.trace_string,
-                            self.current_context.func_ref.module_func_id,
-                            self.current_context.func_ref.func_id
-                        );
-                    };
+                    return_from_function!();
                 }
                 VMInst::TYPEOF => {
                     self.current_context.pc += 1;
@@ -846,6 +1723,27 @@ impl VM {
                     let type_str_val = self.factory.string(type_str.to_string());
                     self.current_context.stack.push(type_str_val.into());
                 }
+                VMInst::TYPEOF_VALUE => {
+                    self.current_context.pc += 1;
+                    read_int32!(self, name_id, usize);
+                    let name = self.constant_table.get(name_id).as_string();
+                    let lex_env = self.current_context.lex_env();
+                    // Bindings before their initialization are still a ReferenceError.
+                    let val = if lex_env.has_binding(name) {
+                        etry!(lex_env.get_value(name))
+                    } else {
+                        Value::undefined()
+                    };
+                    let type_str_val = self.factory.string(val.type_of().to_string());
+                    self.current_context.stack.push(type_str_val.into());
+                }
+                VMInst::DEBUGGER => {
+                    self.current_context.pc += 1;
+                    if let Some(mut hook) = self.debugger_hook.take() {
+                        hook(self);
+                        self.debugger_hook = Some(hook);
+                    }
+                }
                 VMInst::END => break,
                 _ => {
                     print!("Not yet implemented VMInst: ");
@@ -899,6 +1797,15 @@ impl VM {
         self.profile.start_flag = true;
 
         if self.is_trace {
+            // The source line of the instructions which follow, when it changes.
+            let func = self.current_context.func_ref;
+            let pc = self.current_context.current_inst_pc;
+            if let Some((line, text)) = self.source_line(&func, pc) {
+                if self.profile.trace_line != Some((func.func_id, line)) {
+                    println!("; {}: {}", line, text.trim());
+                    self.profile.trace_line = Some((func.func_id, line));
+                }
+            }
             self.profile.trace_string = format!(
                 "{} {}",
                 crate::bytecode_gen::show_inst(
@@ -917,6 +1824,46 @@ impl VM {
         self.profile.gc_stop_time = Duration::from_secs(0);
     }
 
+    fn total_allocated_size(&self) -> usize {
+        self.factory.memory_allocator.allocated_size + self.factory.memory_allocator.collected_size
+    }
+
+    /// Count the edge to the block at the pc of the current context, if coverage is enabled.
+    fn cover_block(&mut self) {
+        if let Some(coverage) = &mut self.coverage {
+            let context = &self.current_context;
+            coverage.enter_block(context.func_ref.func_id, context.pc);
+        }
+    }
+
+    /// Record that the function of the current context was entered.
+    fn profile_enter(&mut self, name: &str) {
+        let allocated = self.total_allocated_size();
+        let depth = self.saved_context.len();
+        let func_id = self.current_context.func_ref.func_id;
+        if let Some(profiler) = &mut self.function_profile {
+            profiler.enter(func_id, name, depth, allocated);
+        }
+    }
+
+    /// Record that the functions deeper than the current context were left.
+    fn profile_leave(&mut self) {
+        let allocated = self.total_allocated_size();
+        let depth = self.saved_context.len();
+        if let Some(profiler) = &mut self.function_profile {
+            profiler.leave(depth, allocated);
+        }
+    }
+
+    /// Print the per-function profile, finishing the functions still running.
+    pub fn print_function_profile(&mut self) {
+        let allocated = self.total_allocated_size();
+        if let Some(profiler) = &mut self.function_profile {
+            profiler.finish(allocated);
+            profiler.print_summary();
+        }
+    }
+
     pub fn print_profile(&mut self) {
         println!("# performance analysis");
 
@@ -1004,6 +1951,12 @@ impl VM {
         };
         self.current_context = prev_context;
         self.current_context.stack.push(return_value.into());
+        self.profile_leave();
+    }
+
+    /// The environment the running function closes over, where its captured variables are.
+    fn closure_environment(&self) -> LexicalEnvironmentRef {
+        self.current_context.variable_environment.outer.unwrap()
     }
 
     fn push_env(&mut self, id: usize) -> VMResult {
@@ -1022,7 +1975,7 @@ impl VM {
 
     fn create_object(&mut self, id: usize) -> VMResult {
         let special_properties = self.constant_table.get(id).as_object_literal_info();
-        let mut properties = FxHashMap::default();
+        let mut properties = PropertyMap::default();
 
         let mut i = 0;
         loop {
@@ -1050,14 +2003,15 @@ impl VM {
                     }
                 } else {
                     let AccessorProperty { get, set, .. } = properties
-                        .entry(name)
-                        .or_insert(Property::Accessor(AccessorProperty {
-                            get: Value::undefined(),
-                            set: Value::undefined(),
-                            // TODO
-                            enumerable: true,
-                            configurable: true,
-                        }))
+                        .get_or_insert_with(name, || {
+                            Property::Accessor(AccessorProperty {
+                                get: Value::undefined(),
+                                set: Value::undefined(),
+                                // TODO
+                                enumerable: true,
+                                configurable: true,
+                            })
+                        })
                         .as_accessor_mut();
                     match kind {
                         Getter => *get = val,
@@ -1100,6 +2054,11 @@ impl VM {
                 configurable: true,
             }));
         }
+        // Spread elements may make a longer array than the literal.
+        self.factory
+            .memory_allocator
+            .limits
+            .check_array_length(elems.len())?;
 
         let ary = self.factory.array(elems);
         self.current_context.stack.push(ary.into());
@@ -1107,15 +2066,39 @@ impl VM {
         Ok(())
     }
 
+    /// Call ``callee`` by new with a new object whose prototype is ``callee.prototype`` (or that
+    /// of the target of a bound function). The result is the object unless ``callee`` returns
+    /// another object.
+    /// https://tc39.github.io/ecma262/#sec-ordinarycreatefromconstructor
     fn enter_constructor(&mut self, callee: Value, args: &[Value]) -> VMResult {
-        let this = Value::Object(self.factory.alloc(ObjectInfo {
+        if !callee.is_constructor() {
+            return Err(self.throw_type_error(message::not_a_constructor(callee)));
+        }
+        // A proxy makes the object by its target or its construct trap.
+        let this = if callee.is_proxy_object() {
+            Value::undefined()
+        } else {
+            self.create_this(callee)
+        };
+        self.enter_function(callee, args, this, true)
+    }
+
+    /// The new object given to the function ``callee`` called by new.
+    fn create_this(&mut self, callee: Value) -> Value {
+        let mut target = callee;
+        while let FunctionObjectKind::Bound { target: inner, .. } = target.as_function().kind {
+            target = inner;
+        }
+        let prototype = match target.get_property("prototype") {
+            prototype if prototype.is_object() => prototype,
+            _ => self.factory.object_prototypes.object,
+        };
+        Value::Object(self.factory.alloc(ObjectInfo {
             kind: ObjectKind::Ordinary,
-            prototype: callee.get_property("prototype"),
-            property: FxHashMap::default(),
+            prototype,
+            property: PropertyMap::default(),
             sym_property: FxHashMap::default(),
-        }));
-
-        self.enter_function(callee, args, this, true)
+        }))
     }
 
     fn enter_function(
@@ -1125,17 +2108,48 @@ impl VM {
         this: Value,
         constructor_call: bool,
     ) -> VMResult {
+        if callee.is_proxy_object() && callee.is_callable() {
+            let methods = internal_methods(callee);
+            let val = if constructor_call {
+                methods.construct(self, callee, args)?
+            } else {
+                methods.call(self, callee, args, this)?
+            };
+            self.current_context.stack.push(val.into());
+            return Ok(());
+        }
         if !callee.is_function_object() {
-            return Err(self.current_context.error_type("Not a function"));
+            let message = if constructor_call {
+                message::not_a_constructor(callee)
+            } else {
+                message::not_a_function(callee)
+            };
+            return Err(self.throw_type_error(message));
         }
 
         let info = callee.as_function();
         let ret = match info.kind {
             FunctionObjectKind::Builtin(func) => {
                 let val = func(self, args, this)?;
-                self.current_context.stack.push(val.into());
+                self.push_call_result(val, this, constructor_call);
+                Ok(())
+            }
+            FunctionObjectKind::Native(id) => {
+                let func = self.native_functions[id].clone();
+                let val = func(self, args, this)?;
+                self.push_call_result(val, this, constructor_call);
                 Ok(())
             }
+            FunctionObjectKind::Bound {
+                target,
+                this: bound_this,
+                args: ref bound_args,
+            } => {
+                let args = [&bound_args[..], args].concat();
+                // The new object given by new is used instead of the bound this.
+                let this = if constructor_call { this } else { bound_this };
+                self.enter_function(target, &args, this, constructor_call)
+            }
             FunctionObjectKind::User {
                 ref info,
                 outer_env,
@@ -1159,6 +2173,58 @@ impl VM {
         ret
     }
 
+    /// Push the value a builtin or native function returned. Called by new, the function gives
+    /// the new object ``this`` unless it returns another object.
+    fn push_call_result(&mut self, val: Value, this: Value, constructor_call: bool) {
+        let val = if constructor_call && !val.is_object() {
+            this
+        } else {
+            val
+        };
+        self.current_context.stack.push(val.into());
+    }
+
+    /// Call ``callee`` from the call instruction being run. If the call site has always called
+    /// ``callee``, the closure is entered directly without checking its kind again.
+    fn enter_function_cached(&mut self, callee: Value, args: &[Value], this: Value) -> VMResult {
+        let pc = self.current_context.current_inst_pc;
+        let func_ref = self.current_context.func_ref;
+        match func_ref.call_caches.get(pc) {
+            Some(CallSiteCache::Monomorphic {
+                callee: cached,
+                info,
+                outer_env,
+            }) if cached == callee && !self.is_trace => {
+                return self.enter_user_function(info, outer_env, args, this, false);
+            }
+            Some(CallSiteCache::Monomorphic { .. }) | None => {
+                func_ref.call_caches.record(pc, callee)
+            }
+            Some(CallSiteCache::Megamorphic) => {}
+        }
+        self.enter_function(callee, args, this, false)
+    }
+
+    /// Call ``callee`` with ``this`` in the frame of the running function, which returns what
+    /// ``callee`` returns. The frame is reused only for user functions; the frames of
+    /// constructors (which may return ``this`` instead) and modules are not.
+    fn enter_tail_call(&mut self, callee: Value, args: &[Value], this: Value) -> VMResult {
+        let mode = self.current_context.call_mode;
+        if !callee.is_function_object()
+            || mode == CallMode::ModuleCall
+            || self.current_context.constructor_call
+        {
+            return self.enter_function_cached(callee, args, this);
+        }
+        let (info, outer_env) = match callee.as_function().kind {
+            FunctionObjectKind::User { info, outer_env } => (info, outer_env),
+            _ => return self.enter_function_cached(callee, args, this),
+        };
+        self.current_context = self.saved_context.pop().unwrap();
+        self.profile_leave();
+        self.prepare_context_for_function_invokation(info, outer_env, args, this, mode, false)
+    }
+
     /// Prepare a new context before invoking function.
     /// 1. Push current context to the context stack.
     /// 2. Set `this`.
@@ -1174,15 +2240,15 @@ impl VM {
         mode: CallMode,
         constructor_call: bool,
     ) -> Result<(), RuntimeError> {
+        let stack_size = self.native_stack_size();
+        self.factory
+            .memory_allocator
+            .limits
+            .check_call_depth(self.saved_context.len() + 1, stack_size)?;
         let context = std::mem::replace(&mut self.current_context, ExecContext::empty());
         self.saved_context.push(context);
 
-        let this = if user_func.this_mode == ThisMode::Lexical {
-            // Arrow function
-            outer_env.unwrap().get_this_binding()
-        } else {
-            this
-        };
+        let this = self.bind_this(user_func, outer_env, this);
 
         let var_env_ref = self
             .factory
@@ -1195,15 +2261,128 @@ impl VM {
         for info in &user_func.func_decls {
             let name = info.func_name.clone().unwrap();
             let func = self.factory.function(*info, lex_env_ref);
-            lex_env_ref.set_value(name, func)?;
+            lex_env_ref.set_value(&name, func)?;
         }
 
-        let context = ExecContext::new(var_env_ref, lex_env_ref, user_func, this, mode)
+        let mut context = ExecContext::new(var_env_ref, lex_env_ref, user_func, this, mode)
             .constructor_call(constructor_call);
+        context.registers = self.factory.create_registers(user_func, args);
         self.current_context = context;
+        self.cover_block();
+        if self.function_profile.is_some() {
+            let name = match (mode, &user_func.func_name) {
+                (CallMode::ModuleCall, _) => "<module>",
+                (_, Some(name)) => name.as_str(),
+                (_, None) => "<anonymous>",
+            };
+            self.profile_enter(name);
+        }
+        Ok(())
+    }
+
+    /// Get ``this`` of a call of ``user_func`` given ``this`` by the caller: the receiver of a
+    /// method call, the new object of a constructor call and undefined for the other calls.
+    /// Arrow functions ignore it and use ``this`` of the scope they are defined in. Non-strict
+    /// functions get the global object instead of undefined or null, and an object wrapping a
+    /// primitive value.
+    /// https://tc39.github.io/ecma262/#sec-ordinarycallbindthis
+    pub fn bind_this(
+        &mut self,
+        user_func: FuncInfoRef,
+        outer_env: Option<LexicalEnvironmentRef>,
+        this: Value,
+    ) -> Value {
+        match user_func.this_mode {
+            ThisMode::Lexical => outer_env.unwrap().get_this_binding(),
+            ThisMode::Strict => this,
+            ThisMode::Global if this.is_undefined() || this.is_null() => {
+                self.global_environment.get_global_object()
+            }
+            ThisMode::Global if this.is_object() => this,
+            ThisMode::Global => self.factory.primitive_wrapper(this),
+        }
+    }
+
+    /// Prepare a new context running ``code`` given to eval.
+    /// Direct eval runs the code in the scope and with ``this`` of the caller, declaring its vars
+    /// in the variable environment of the caller. Indirect eval runs the code in the global scope.
+    /// https://tc39.github.io/ecma262/#sec-performeval
+    pub fn prepare_context_for_eval(
+        &mut self,
+        code: String,
+        direct: bool,
+        mode: CallMode,
+    ) -> Result<(), RuntimeError> {
+        let mut parser = Parser::new("eval", code);
+        self.transform_source(&mut parser)
+            .map_err(|msg| self.throw_error(ErrorType::Error, msg))?;
+        let mut node = parser
+            .parse_all()
+            .map_err(|err| self.throw_error(ErrorType::SyntaxError, Parser::error_message(&err)))?;
+        let eval_info = self
+            .compile_with_source(&mut node, true, parser.source_text())
+            .map_err(|err| self.throw_error(ErrorType::SyntaxError, err.msg))?;
+        self.script_info
+            .push((eval_info.module_func_id, parser.into_script_info()));
+
+        let (mut var_env, outer, this) = if direct {
+            (
+                self.current_context.variable_environment,
+                self.current_context.lexical_environment,
+                self.current_context.this,
+            )
+        } else {
+            // Vars of the script are declared in the variable environment of the global context.
+            let global_context = self.saved_context.first().unwrap_or(&self.current_context);
+            let var_env = global_context.variable_environment;
+            let global_object = self.global_environment.get_global_object();
+            (var_env, var_env, global_object)
+        };
+
+        for name in &eval_info.var_names {
+            if !var_env.has_own_binding(name) {
+                var_env.set_own_value(name.clone(), Value::undefined())?;
+            }
+        }
+
+        let lex_env = self
+            .factory
+            .create_lexical_environment(&eval_info.lex_names, outer);
+
+        for info in &eval_info.func_decls {
+            let name = info.func_name.clone().unwrap();
+            let func = self.factory.function(*info, lex_env);
+            var_env.set_own_value(name, func)?;
+        }
+
+        let context = std::mem::replace(&mut self.current_context, ExecContext::empty());
+        self.saved_context.push(context);
+        self.current_context = ExecContext::new(var_env, lex_env, eval_info, this, mode);
+        self.cover_block();
+        // The completion value when the code has no expression statement.
+        self.current_context.stack.push(Value::undefined().into());
+        if self.function_profile.is_some() {
+            self.profile_enter("<eval>");
+        }
         Ok(())
     }
 
+    /// Whether ``callee`` is the eval function of the global object, which is called as direct
+    /// eval by ``eval(...)``.
+    fn is_intrinsic_eval(callee: Value) -> bool {
+        if !callee.is_function_object() {
+            return false;
+        }
+        match callee.as_function().kind {
+            FunctionObjectKind::Builtin(func) => {
+                func as *const () == crate::builtin::eval as *const ()
+            }
+            FunctionObjectKind::User { .. }
+            | FunctionObjectKind::Native(_)
+            | FunctionObjectKind::Bound { .. } => false,
+        }
+    }
+
     fn enter_user_function(
         &mut self,
         user_func: FuncInfoRef,
@@ -1212,10 +2391,6 @@ impl VM {
         this: Value,
         constructor_call: bool,
     ) -> VMResult {
-        if !user_func.constructible && constructor_call {
-            return Err(self.current_context.error_type("Not a constructor"));
-        }
-
         self.prepare_context_for_function_invokation(
             user_func,
             outer_env,
@@ -12,3 +12,48 @@ let obj = {
 assert(obj.x, 35)
 obj.x = 123
 assert(obj.x, 1230)
+
+// Accessors inherited from a prototype are called with the object as this.
+function Point(x) {
+  this._x = x
+}
+Point.prototype = {
+  get x() {
+    return this._x
+  },
+  set x(val) {
+    this._x = val * 10
+  },
+  get double() {
+    return function() {
+      return this._x * 2
+    }
+  },
+  get readOnly() {
+    return 'r'
+  }
+}
+let p = new Point(1)
+p.x = 2
+assert(p.x, 20)
+assert(p._x, 20)
+assert(p.double(), 40)
+p.readOnly = 'w'
+assert(p.readOnly, 'r')
+
+// Read-only properties are not assigned, and throw in strict code.
+function f() {}
+f.name = 'g'
+assert(f.name, 'f')
+function strictAssign(obj, key) {
+  'use strict'
+  try {
+    obj[key] = 1
+    return 'assigned'
+  } catch (e) {
+    return e.name === 'TypeError'
+  }
+}
+assert(strictAssign(f, 'name'), true)
+assert(strictAssign(p, 'readOnly'), true)
+assert(strictAssign(p, 'y'), 'assigned')
@@ -22,3 +22,29 @@ assert(c.join(), '1,2,3')
 assert(c.join(undefined), '1,2,3')
 assert(c.join('_'), '1_2_3')
 assert(c.join(2), '12223')
+
+// Self-hosted methods (src/builtins/js/array.js)
+let d = [1, 2, 3, 4]
+let visited = []
+d.forEach(function(elem, i, ary) {
+  visited.push([this.k, elem, i, ary === d])
+}, { k: 'k' })
+assert(visited, [['k', 1, 0, true], ['k', 2, 1, true], ['k', 3, 2, true], ['k', 4, 3, true]])
+assert(d.filter(elem => elem % 2 === 0), [2, 4])
+assert(d.reduce((acc, elem) => acc + elem), 10)
+assert(d.reduce((acc, elem) => acc + elem, 'x'), 'x1234')
+assert(d.some(elem => elem > 3), true)
+assert(d.some(elem => elem > 4), false)
+assert(d.every(elem => elem > 0), true)
+assert(d.every(elem => elem > 1), false)
+assert(d.find(elem => elem > 2), 3)
+assert(d.find(elem => elem > 4), undefined)
+assert(d.findIndex(elem => elem > 2), 2)
+assert(d.findIndex(elem => elem > 4), -1)
+let reduce_error = 'no error'
+try {
+  [].reduce((acc, elem) => acc + elem)
+} catch (e) {
+  reduce_error = e.message
+}
+assert(reduce_error, 'Reduce of empty array with no initial value')
@@ -0,0 +1,54 @@
+var assert = require('assert').deepStrictEqual
+
+// The same function from one call site
+function add(a, b) {
+  return a + b
+}
+var sum = 0
+for (var i = 0; i < 10; i++) sum = add(sum, i)
+assert(sum, 45)
+
+// Different closures of the same function from one call site
+function counter(start) {
+  return function() {
+    return start++
+  }
+}
+var counters = [counter(0), counter(10), counter(100)]
+var results = []
+for (var i = 0; i < 6; i++) results.push(counters[i % 3]())
+assert(results, [0, 10, 100, 1, 11, 101])
+
+// A call site calling a user function, then a builtin
+function call(f, x) {
+  return f(x)
+}
+assert(call(function(x) { return x * 2 }, 4), 8)
+assert(call(function(x) { return x * 3 }, 4), 12)
+assert(call(parseFloat, '1.5'), 1.5)
+
+// A cached call site calling a non-function
+var g = function() { return 1 }
+var caught = []
+for (var i = 0; i < 3; i++) {
+  try {
+    caught.push(g())
+  } catch (e) {
+    caught.push(e.name)
+  }
+  if (i == 1) g = 1
+}
+assert(caught, [1, 1, 'TypeError'])
+
+// Methods called with different receivers
+function Box(value) {
+  this.value = value
+}
+Box.prototype.get = function() {
+  return this.value
+}
+var a = new Box('a')
+var b = new Box('b')
+var values = []
+for (var i = 0; i < 4; i++) values.push((i % 2 ? b : a).get())
+assert(values, ['a', 'b', 'a', 'b'])
@@ -0,0 +1,67 @@
+var assert = require('assert').deepStrictEqual
+
+function caught(f) {
+  try {
+    f()
+  } catch (e) {
+    return e.name
+  }
+}
+
+function Point(x, y) {
+  this.x = x
+  this.y = y
+}
+Point.prototype.sum = function() {
+  return this.x + this.y
+}
+var p = new Point(1, 2)
+assert(p.__proto__ === Point.prototype, true)
+assert(p.sum(), 3)
+
+function NoPrototype() {
+  this.a = 1
+}
+NoPrototype.prototype = 1
+assert(new NoPrototype().__proto__ === Object.prototype, true)
+
+function ReturnsObject() {
+  this.a = 1
+  return { b: 2 }
+}
+function ReturnsPrimitive() {
+  this.a = 1
+  return 2
+}
+assert(new ReturnsObject(), { b: 2 })
+assert(new ReturnsPrimitive().a, 1)
+
+var map = new Map([[1, 2]])
+assert(map.__proto__ === Map.prototype, true)
+assert(map.get(1), 2)
+assert(new Error('e').__proto__ === Error.prototype, true)
+assert(new Array(1, 2), [1, 2])
+
+assert(caught(() => new parseFloat('1')), 'TypeError')
+assert(caught(() => new Symbol()), 'TypeError')
+assert(caught(() => new (() => 1)()), 'TypeError')
+
+var bound = Point.bind({ z: 0 }, 10)
+assert(bound.name, 'bound Point')
+assert(bound.length, 1)
+var q = new bound(20)
+assert(q.__proto__ === Point.prototype, true)
+assert(q.sum(), 30)
+assert(q.z, undefined)
+
+function add(a, b) {
+  return this.base + a + b
+}
+var addBound = add.bind({ base: 100 }, 1)
+assert(addBound(2), 103)
+assert(addBound.call({ base: 0 }, 2), 103)
+assert(add.bind({ base: 0 }, 1, 2, 3).length, 0)
+assert(add.bind({ base: 1 }).bind({ base: 2 }, 1)(1), 3)
+assert(caught(() => Function.prototype.bind.call(1)), 'TypeError')
+var boundArrow = (() => 1).bind(null)
+assert(caught(() => new boundArrow()), 'TypeError')
@@ -0,0 +1,85 @@
+let assert = require('assert').deepStrictEqual
+
+function throwsTypeError(f) {
+  try {
+    f()
+  } catch (e) {
+    return e.name === 'TypeError'
+  }
+  return false
+}
+
+// Deleting an element leaves a hole, and the length is kept.
+let ary = [1, 2, 3]
+assert(delete ary[2], true)
+assert(ary.length, 3)
+assert(2 in ary, false)
+assert(ary[2], undefined)
+assert(delete ary['0'], true)
+assert(0 in ary, false)
+assert(ary.join(), ',2,')
+assert(delete ary[10], true)
+assert(delete ary.length, false)
+assert(ary.length, 3)
+assert(
+  throwsTypeError(function() {
+    'use strict'
+    delete ary.length
+  }),
+  true
+)
+
+// Keys are converted as by [[Get]].
+let obj = { x: 1, 1: 2 }
+assert(delete obj[{ toString: function() { return 'x' } }], true)
+assert('x' in obj, false)
+assert(delete obj[1], true)
+assert(Object.keys(obj), [])
+let sym = Symbol()
+obj[sym] = 1
+assert(delete obj[sym], true)
+assert(sym in obj, false)
+
+// The other properties keep their order.
+let a = { x: 1, y: 2, z: 3, w: 4 }
+assert(delete a.x, true)
+assert(Reflect.ownKeys(a), ['y', 'z', 'w'])
+assert(delete a.z, true)
+a.x = 5
+assert(Reflect.ownKeys(a), ['y', 'w', 'x'])
+assert([a.y, a.w, a.x], [2, 4, 5])
+
+// The length and the characters of strings are not configurable.
+assert(delete 'abc'.length, false)
+assert(delete 'abc'[2], false)
+assert(delete 'abc'[3], true)
+assert(delete 'abc'.foo, true)
+assert(
+  throwsTypeError(function() {
+    'use strict'
+    delete 'abc'[0]
+  }),
+  true
+)
+
+// Other primitives have no own properties.
+assert(delete (1).x, true)
+assert(delete true.x, true)
+assert(
+  throwsTypeError(function() {
+    delete undefined.x
+  }),
+  true
+)
+
+// The prototypes of functions are not configurable.
+function F() {}
+assert(delete F.prototype, false)
+assert(delete F.name, true)
+assert(delete Object.prototype, false)
+assert(typeof Object.prototype, 'object')
+
+// Other than references.
+let v = 1
+assert(delete v, false)
+assert(delete 1, true)
@@ -0,0 +1,62 @@
+const assert = (n, l, r) => {
+  if (l !== r) {
+    throw n
+  }
+}
+
+const caught = f => {
+  try {
+    f()
+  } catch (e) {
+    return e
+  }
+}
+
+const e = new TypeError('x')
+assert(1, e.name, 'TypeError')
+assert(2, e.message, 'x')
+assert(3, TypeError.prototype.name, 'TypeError')
+assert(4, new RangeError().message, '')
+assert(5, new SyntaxError('s').name, 'SyntaxError')
+assert(6, new Error('m').name, 'Error')
+
+const not_a_function = caught(() => undefined())
+assert(7, not_a_function.name, 'TypeError')
+assert(8, not_a_function.message, 'undefined is not a function')
+
+const not_defined = caught(() => not_declared)
+assert(9, not_defined.name, 'ReferenceError')
+assert(10, not_defined.message, 'not_declared is not defined')
+
+const not_a_constructor = caught(() => new (() => 1)())
+assert(11, not_a_constructor.name, 'TypeError')
+
+function thrower() {
+  return null.x
+}
+const read_null = caught(thrower)
+assert(12, read_null.message, "Cannot read property 'x' of null")
+assert(13, read_null.stack.indexOf("TypeError: Cannot read property 'x' of null"), 0)
+assert(14, read_null.stack.indexOf('    at thrower') > 0, true)
+
+function make_error() {
+  return new ReferenceError('r')
+}
+assert(15, make_error().stack.indexOf('ReferenceError: r\n    at make_error'), 0)
+
+const set_receiver = caught(() => Set.prototype.add.call(1, 2))
+assert(16, set_receiver.message, 'Method Set.prototype.add called on incompatible receiver 1')
+
+// Error.stackTraceLimit is the number of frames in the stack.
+assert(17, Error.stackTraceLimit, 10)
+function recurse(n) {
+  return n === 0 ? new Error('deep') : recurse(n - 1)
+}
+assert(18, recurse(20).stack.split('\n').length, 11)
+Error.stackTraceLimit = 2
+assert(19, recurse(20).stack.split('\n').length, 3)
+Error.stackTraceLimit = 0
+assert(20, new TypeError('t').stack, 'TypeError: t')
+Error.stackTraceLimit = undefined
+assert(21, new Error('e').stack, undefined)
+Error.stackTraceLimit = 10
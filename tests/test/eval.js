@@ -0,0 +1,57 @@
+const assert = (n, l, r) => {
+  if (l !== r) {
+    throw n
+  }
+}
+
+assert(1, eval('1 + 2'), 3)
+assert(2, eval(42), 42)
+assert(3, eval(), undefined)
+
+// Direct eval shares the scope of the caller.
+function direct(a) {
+  var b = 10
+  eval('var c = a + b')
+  return c
+}
+assert(4, direct(1), 11)
+
+// A var declared by eval shadows the binding of the enclosing function.
+var x = 'global'
+function outer() {
+  var x = 'outer'
+  function middle() {
+    eval('var x = "eval"')
+    return function() { return x }
+  }
+  return middle()()
+}
+assert(5, outer(), 'eval')
+
+// Indirect eval runs in the global scope.
+function indirect() {
+  var x = 'local'
+  const e = eval
+  return e('x')
+}
+assert(6, indirect(), 'global')
+
+function assignByEval() {
+  let n = 1
+  eval('n = n + 1')
+  return n
+}
+assert(7, assignByEval(), 2)
+
+let err
+try {
+  eval('var = 1')
+} catch (e) {
+  err = e
+}
+assert(8, err.name, 'SyntaxError')
+
+function thisInEval() {
+  return eval('this.v')
+}
+assert(9, thisInEval.call({ v: 5 }), 5)
@@ -6,3 +6,23 @@ for (let i = 1; i <= 20; i++) {
   if (i === 10) break
 }
 assert(total, 55)
+
+// Conditions compiled into a compare-and-branch instruction
+let count = 0
+for (let i = 10; i > 0; i -= 2) count++
+assert(count, 5)
+count = 0
+for (let i = 10; i >= 0; i -= 2) count++
+assert(count, 6)
+count = 0
+for (let i = 0; i < NaN; i++) count++
+assert(count, 0)
+count = 0
+for (let s = 'a'; s < 'aaaa'; s += 'a') count++
+assert(count, 3)
+count = 0
+for (let i = 1; i <= '3'; i++) count++
+assert(count, 3)
+count = 0
+for (let i = 0; i < undefined; i++) count++
+assert(count, 0)
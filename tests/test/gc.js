@@ -0,0 +1,23 @@
+let assert = require('assert').deepStrictEqual
+
+function make(i) {
+  return { i: i, ary: [i, 'x' + i], get: () => i }
+}
+
+let kept = []
+for (let i = 0; i < 3000; i++) {
+  const obj = make(i)
+  if (i % 1000 === 0) kept.push(obj)
+}
+assert(kept.length, 3)
+assert(kept[2].i, 2000)
+assert(kept[2].ary, [2000, 'x2000'])
+assert(kept[2].get(), 2000)
+
+// The prototype is reachable only from the instance
+function F() {}
+F.prototype.m = () => 'm'
+let f = new F()
+F = undefined
+for (let i = 0; i < 3000; i++) make(i)
+assert(f.m(), 'm')
@@ -0,0 +1,32 @@
+const assert = (n, l, r) => {
+  if (l !== r) {
+    throw n
+  }
+}
+
+assert(1, f(), 'f')
+function f() { return 'f' }
+
+function g() { { { var x = 1 } } return x }
+assert(2, g(), 1)
+
+function h() { var r = typeof y; if (false) { var y = 2 } return r }
+assert(3, h(), 'undefined')
+
+function k() {
+  { let a = 5; return inner(); function inner() { return a } }
+}
+assert(4, k(), 5)
+
+assert(5, typeof blk, 'undefined')
+{ function blk() { return 'blk' } }
+assert(6, blk(), 'blk')
+
+function c() { if (true) { return d() } function d() { return 'd' } }
+assert(7, c(), 'd')
+
+function e() { let x = 1; { function f() { return x } } return f() }
+assert(8, e(), 1)
+
+for (var i = 0; i < 2; i++) { var j = i }
+assert(9, j, 1)
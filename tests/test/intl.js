@@ -0,0 +1,80 @@
+let assert = require('assert').deepStrictEqual
+
+assert('a'.localeCompare('b'), -1)
+assert('b'.localeCompare('a'), 1)
+assert('a'.localeCompare('a'), 0)
+assert('a'.localeCompare('B'), -1)
+assert('a'.localeCompare('A'), -1)
+assert('a'.localeCompare('á', undefined, { sensitivity: 'base' }), 0)
+assert('a'.localeCompare('A', undefined, { sensitivity: 'accent' }), 0)
+assert('2'.localeCompare('10'), 1)
+assert('2'.localeCompare('10', undefined, { numeric: true }), -1)
+
+function sort(array, compare) {
+  const sorted = [...array]
+  for (let i = 1; i < sorted.length; i++) {
+    const elem = sorted[i]
+    let j = i - 1
+    while (j >= 0 && compare(sorted[j], elem) > 0) {
+      sorted[j + 1] = sorted[j]
+      j--
+    }
+    sorted[j + 1] = elem
+  }
+  return sorted
+}
+
+const collator = new Intl.Collator('en')
+assert(sort(['b', 'a', 'C', 'á', 'A'], collator.compare), ['a', 'A', 'á', 'b', 'C'])
+assert(collator.resolvedOptions().sensitivity, 'variant')
+
+const numeric = new Intl.Collator(undefined, { numeric: true, sensitivity: 'base' })
+assert(sort(['item10', 'item9', 'Item1'], numeric.compare), ['Item1', 'item9', 'item10'])
+assert(numeric.compare('résumé', 'RESUME'), 0)
+assert(numeric.resolvedOptions().numeric, true)
+
+let thrown = false
+try {
+  new Intl.Collator('en', { sensitivity: 'none' })
+} catch (e) {
+  thrown = true
+}
+assert(thrown, true)
+
+const plural = new Intl.PluralRules('en-US')
+assert(plural.select(1), 'one')
+assert(plural.select(0), 'other')
+assert(plural.select(2), 'other')
+assert(new Intl.PluralRules('en', { minimumFractionDigits: 1 }).select(1), 'other')
+
+const ordinal = new Intl.PluralRules('en', { type: 'ordinal' })
+const ordinals = ['one', 'two', 'few', 'other', 'other', 'other', 'other', 'one', 'two']
+assert([1, 2, 3, 4, 11, 12, 13, 21, 102].map(n => ordinal.select(n)), ordinals)
+assert(ordinal.resolvedOptions().type, 'ordinal')
+
+// Locales without built-in data fall back to the root locale.
+const root = new Intl.PluralRules('ja')
+assert(root.select(1), 'other')
+assert(root.resolvedOptions().locale, 'und')
+
+const number = new Intl.NumberFormat('en')
+assert(number.format(1234567.891), '1,234,567.891')
+assert(number.format(-0.12345), '-0.123')
+assert(number.format(NaN), 'NaN')
+assert(number.resolvedOptions().maximumFractionDigits, 3)
+
+const fixed = new Intl.NumberFormat(undefined, {
+  minimumFractionDigits: 2,
+  maximumFractionDigits: 2,
+  useGrouping: false
+})
+assert(fixed.format(1234.5), '1234.50')
+assert(new Intl.NumberFormat('en', { style: 'percent' }).format(0.256), '26%')
+
+thrown = false
+try {
+  new Intl.NumberFormat('en', { minimumFractionDigits: 3, maximumFractionDigits: 1 })
+} catch (e) {
+  thrown = e.name === 'RangeError'
+}
+assert(thrown, true)
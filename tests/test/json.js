@@ -0,0 +1,67 @@
+var assert = require('assert').deepStrictEqual
+
+function caught(f) {
+  try {
+    f()
+  } catch (e) {
+    return e.name
+  }
+}
+
+assert(JSON.parse('{"a": [1, 2.5e1, "x\\u0041"], "b": {"c": null}, "d": true}'), {
+  a: [1, 25, 'xA'],
+  b: { c: null },
+  d: true,
+})
+assert(JSON.parse(' "s" '), 's')
+assert(JSON.parse('-0.5'), -0.5)
+assert(JSON.parse('[]'), [])
+// A later duplicate key overrides the earlier one.
+assert(JSON.parse('{"a": 1, "a": 2}').a, 2)
+// __proto__ is an own property, not the prototype.
+var proto = JSON.parse('{"__proto__": 1, "b": {"__proto__": {"x": 2}}}')
+assert(proto.__proto__, 1)
+assert(Reflect.ownKeys(proto), ['__proto__', 'b'])
+assert(Reflect.getPrototypeOf(proto) === Reflect.getPrototypeOf({}), true)
+assert(proto.b.__proto__, { x: 2 })
+assert(proto.b.x, undefined)
+proto.__proto__ = 3
+assert(proto.__proto__, 3)
+assert(Reflect.getPrototypeOf(proto) === Reflect.getPrototypeOf({}), true)
+
+assert(caught(function() { JSON.parse('') }), 'SyntaxError')
+assert(caught(function() { JSON.parse('{"a": 1,}') }), 'SyntaxError')
+assert(caught(function() { JSON.parse('[1] 2') }), 'SyntaxError')
+assert(caught(function() { JSON.parse("'s'") }), 'SyntaxError')
+assert(caught(function() { JSON.parse('01') }), 'SyntaxError')
+
+// The reviver is called innermost first, with the holder as this.
+var calls = []
+var revived = JSON.parse('{"a": [1, 2], "b": 3}', function(key, val) {
+  calls.push(key)
+  if (key === 'b') return undefined
+  if (typeof val === 'number') return val * 10
+  return val
+})
+assert(calls, ['0', '1', 'a', 'b', ''])
+assert(revived, { a: [10, 20] })
+var holders = []
+JSON.parse('[1]', function(key, val) {
+  holders.push(this)
+  return val
+})
+assert(holders[0], [1])
+assert(holders[1], { '': [1] })
+
+// Deep nesting does not overflow the native stack.
+var depth = 20000
+var text = ''
+for (var i = 0; i < depth; i++) text += '['
+for (var i = 0; i < depth; i++) text += ']'
+var val = JSON.parse(text)
+var n = 0
+while (val.length) {
+  val = val[0]
+  n++
+}
+assert(n, depth - 1)
@@ -1,4 +1,4 @@
-var assert = require('assert').deepEqual
+var assert = require('assert').deepStrictEqual
 const i = 100
 let j = 0
 for (let i = 0; i < 5; i++) {
@@ -11,4 +11,64 @@ for (let i = 0; i < 5; i++) {
   }
 }
 assert(i,100)
-assert(j,35)
\ No newline at end of file
+assert(j,35)
+
+// let without an initializer is undefined
+let k
+assert(k, undefined)
+k = 5
+assert(k, 5)
+
+// Each block has its own environment
+let l = 'outer'
+{
+  let l = 'inner'
+  assert(l, 'inner')
+}
+assert(l, 'outer')
+
+// TDZ: a binding is not accessible before its declaration
+function error_message(f) {
+  try {
+    f()
+  } catch (e) {
+    return e.name + ': ' + e.message
+  }
+  return 'no error'
+}
+assert(error_message(() => { m; let m = 1 }), "ReferenceError: Cannot access 'm' before initialization")
+assert(error_message(() => { m = 1; let m }), "ReferenceError: Cannot access 'm' before initialization")
+assert(error_message(() => { let m = m }), "ReferenceError: Cannot access 'm' before initialization")
+function read_later() { return n }
+assert(error_message(read_later), "ReferenceError: Cannot access 'n' before initialization")
+const n = 'n'
+assert(read_later(), 'n')
+
+// Assignment to a const binding is a TypeError
+assert(error_message(() => { i = 1 }), 'TypeError: Assignment to constant variable.')
+assert(error_message(() => { const c = 1; c++ }), 'TypeError: Assignment to constant variable.')
+assert(error_message(() => { const c = 1; { c += 1 } }), 'TypeError: Assignment to constant variable.')
+assert(i, 100)
+
+// A const in a loop body is a fresh binding on every iteration
+let sum = 0
+for (let x = 0; x < 3; x++) {
+  const y = x * 2
+  sum += y
+}
+assert(sum, 6)
+
+// Each iteration of a for statement has its own let bindings, which closures capture
+let fs = []
+for (let i = 0; i < 3; i++) fs.push(function () { return i })
+assert(fs.map(f => f()), [0, 1, 2])
+fs = []
+for (let i = 0; i < 6; i++) {
+  let j = i * 10
+  if (i % 2) continue
+  fs.push(() => i + j)
+}
+assert(fs.map(f => f()), [0, 22, 44])
+fs = []
+for (let i = 0; i < 3; i++) fs.push(() => i++)
+assert(fs.map(f => f()), [0, 1, 2])
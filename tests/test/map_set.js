@@ -0,0 +1,72 @@
+let assert = require('assert').deepStrictEqual
+
+let m = new Map([[1, 'one'], ['two', 2]])
+assert(m.size, 2)
+assert(m.get(1), 'one')
+assert(m.get('two'), 2)
+assert(m.get(3), undefined)
+assert(m.set(NaN, 'nan') === m, true)
+assert(m.get(NaN), 'nan')
+m.set(-0, 'zero')
+assert(m.get(0), 'zero')
+assert(m.has('two'), true)
+assert(m.delete('two'), true)
+assert(m.delete('two'), false)
+assert(m.size, 3)
+
+// Entries deleted during forEach are not visited, and entries added are visited once.
+let m2 = new Map([['a', 1], ['b', 2], ['c', 3]])
+let visited = []
+m2.forEach(function(val, key) {
+  visited.push(key)
+  if (key === 'a') {
+    m2.delete('b')
+    m2.set('d', 4)
+    m2.set('a', 10)
+  }
+})
+assert(visited, ['a', 'c', 'd'])
+assert(m2.get('a'), 10)
+
+// An entry deleted and added again is visited at its new position.
+let m3 = new Map([['a', 1], ['b', 2]])
+visited = []
+m3.forEach(function(val, key) {
+  visited.push(key)
+  if (key === 'a' && val === 1) {
+    m3.delete('a')
+    m3.set('a', 2)
+  }
+})
+assert(visited, ['a', 'b', 'a'])
+
+// clear() during forEach stops the iteration unless new entries are added.
+let m4 = new Map([['a', 1], ['b', 2]])
+visited = []
+m4.forEach(function(val, key) {
+  visited.push(key)
+  if (key === 'a') {
+    m4.clear()
+    m4.set('c', 3)
+  }
+})
+assert(visited, ['a', 'c'])
+assert(m4.size, 1)
+
+let s = new Set([1, 2, 2, 3])
+assert(s.size, 3)
+assert(s.has(2), true)
+assert(s.add(4) === s, true)
+assert(s.delete(1), true)
+assert(s.has(1), false)
+
+let s2 = new Set([1, 2, 3])
+visited = []
+s2.forEach(function(val) {
+  visited.push(val)
+  if (val === 1) {
+    s2.delete(2)
+    s2.add(4)
+  }
+})
+assert(visited, [1, 3, 4])
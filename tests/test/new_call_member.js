@@ -31,3 +31,11 @@ let g = function(x) {
 assert(0, new f().g(), 'OK')
 assert(1, new o.f('O')('K'), 'OK')
 assert(2, new new g(5)(4).ans, 20)
+
+const p = { x: 1, y: { x: 2 } }
+p.x = p.x + p.y.x
+p['x'] += 1
+p.y.x = p['y']['x'] * 10
+assert(3, p.x, 4)
+assert(4, p.y.x, 20)
+assert(5, { x: 'x' }.x, 'x')
@@ -0,0 +1,41 @@
+var assert = require('assert').deepStrictEqual
+
+function caught(f) {
+  try {
+    f()
+  } catch (e) {
+    return e.name
+  }
+}
+
+var buf = Buffer.from('héllo')
+assert(buf.length, 6)
+assert(buf[1], 0xc3)
+assert(buf.__proto__ === Buffer.prototype, true)
+assert(buf.toString(), 'héllo')
+assert(buf.toString('hex'), '68c3a96c6c6f')
+assert(buf.toString('base64'), 'aMOpbGxv')
+assert(buf.join(','), '104,195,169,108,108,111')
+
+assert(Buffer.from('68c3a96c6c6f', 'hex').toString(), 'héllo')
+assert(Buffer.from('aMOpbGxv', 'base64').toString('utf8'), 'héllo')
+assert(Buffer.from([256 + 65, -1]).toString('hex'), '41ff')
+assert(Buffer.from(buf).toString('utf-8'), 'héllo')
+
+assert(Buffer.byteLength('héllo'), 6)
+assert(Buffer.byteLength('68c3a9', 'hex'), 3)
+assert(Buffer.byteLength('aMOpbGxv', 'base64'), 6)
+assert(Buffer.byteLength(buf), 6)
+
+assert(caught(() => Buffer.from('a', 'latin2')), 'TypeError')
+assert(caught(() => Buffer.from(1)), 'TypeError')
+assert(caught(() => new Buffer(1)), 'TypeError')
+assert(caught(() => Buffer.from([0]).toString()), 'RangeError')
+
+var start = process.hrtime()
+assert(start.length, 2)
+assert(start[1] < 1e9, true)
+var elapsed = process.hrtime(start)
+assert(elapsed[0] >= 0 && elapsed[1] >= 0, true)
+var t0 = process.hrtime.bigint()
+assert(process.hrtime.bigint() >= t0, true)
@@ -29,3 +29,122 @@ assert(3 >= 4, false)
 let a = 13
 assert(a == 13 ? true : false, true)
 assert(a != 7 ? true : false, true)
+assert(0 && 5, 0)
+assert(3 && 5, 5)
+assert(0 || 5, 5)
+assert(3 || 5, 3)
+assert('' || null, null)
+let count = 0
+let inc = function() {
+  count++
+  return true
+}
+false && inc()
+true || inc()
+assert(count, 0)
+true && inc()
+false || inc()
+assert(count, 2)
+// Increments convert the operand to a number.
+let s = '41'
+assert(++s, 42)
+let t = '1'
+t--
+assert(t, 0)
+let big = 2147483647
+big++
+assert(big, 2147483648)
+assert(0.5 + 0.25, 0.75)
+let nan = 1 - 'x'
+assert(nan !== nan, true)
+// Objects are converted by @@toPrimitive, or else by valueOf and toString.
+let order = ''
+let money = {
+  valueOf: function() { order += 'v'; return 5 },
+  toString: function() { order += 's'; return '$5' }
+}
+assert(money + 1, 6)
+assert(money == 5, true)
+assert(money < 6, true)
+assert(order, 'vvv')
+let hints = []
+let hinted = {}
+hinted[Symbol.toPrimitive] = function(hint) {
+  hints.push(hint)
+  return 1
+}
+assert(hinted + '', '1')
+assert(hinted > 0, true)
+assert(hints.join(), 'default,number')
+// So are the operands of the other arithmetic operators.
+let five = { valueOf: function() { return 5 } }
+assert(five - 1, 4)
+assert(five * 2, 10)
+assert(five / 2, 2.5)
+assert(five % 2, 1)
+assert(-five, -5)
+assert(five++, 5)
+assert(five, 6)
+five = { valueOf: function() { return 5 } }
+assert(--five, 4)
+let digits = '3'
+assert(digits++, 3)
+assert(digits, 4)
+assert(hinted - 1, 0)
+assert(hints.join(), 'default,number,number')
+let prices = [{ valueOf: function() { return 3 } }]
+prices[0] *= 2
+assert(prices[0], 6)
+// The left operand is converted first, even for >.
+order = ''
+let left = { valueOf: function() { order += 'l'; return 1 } }
+let right = { valueOf: function() { order += 'r'; return 2 } }
+assert(left > right, false)
+assert(order, 'lr')
+assert('a' < 'b', true)
+assert('10' < '9', true)
+assert('10' < 9, false)
+assert(null == undefined, true)
+assert(null == 0, false)
+assert(undefined < 1, false)
+assert(undefined >= 1, false)
+// Numbers are converted to the shortest strings which round trip.
+assert('' + (0.1 + 0.2), '0.30000000000000004')
+assert('' + 1e21, '1e+21')
+assert('' + 1e20, '100000000000000000000')
+assert('' + 1e-7, '1e-7')
+assert('' + 0.000001, '0.000001')
+assert('' + -1.5e-300, '-1.5e-300')
+assert('' + -Infinity, '-Infinity')
+
+// String to number conversion
+assert(+' 0x1F ', 31)
+assert(+'0b101', 5)
+assert(+'0o17', 15)
+assert(Number('1e3'), 1000)
+assert(Number(' \n-1.5e-2\t'), -0.015)
+assert(+'-Infinity', -Infinity)
+assert(+'', 0)
+assert(+'   ', 0)
+assert(+'1.', 1)
+assert(+'.5', 0.5)
+assert(Number(), 0)
+assert(Number(true), 1)
+assert(Number({ valueOf: function() { return '7' } }), 7)
+assert(+{ toString: function() { return '0x10' } }, 16)
+assert('' + +'0b102', 'NaN')
+assert('' + +'.', 'NaN')
+assert('' + +'-0x1', 'NaN')
+assert('' + Number('inf'), 'NaN')
+assert('' + Number('1e'), 'NaN')
+// Relational comparison and == convert strings to numbers the same way as unary +.
+assert('1e3' < 2000, true)
+assert(' 0x1F ' < 32, true)
+assert(' 0x1F ' > 30, true)
+assert('' < 1, true)
+assert('' > -1, true)
+assert('0b101' == 5, true)
+assert('0b102' < 1 || '0b102' >= 1, false)
+assert('10' < 9, false)
+assert('0x10' > 15, true)
+assert(' 2 ' == 2, true)
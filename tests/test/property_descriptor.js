@@ -0,0 +1,132 @@
+let assert = require('assert').deepStrictEqual
+
+function throwsTypeError(f) {
+  try {
+    f()
+  } catch (e) {
+    return e.name === 'TypeError'
+  }
+  return false
+}
+
+// Absent attributes are false.
+let obj = { a: 1 }
+assert(Object.defineProperty(obj, 'b', { value: 2 }) === obj, true)
+assert(obj.b, 2)
+assert(Object.getOwnPropertyDescriptor(obj, 'a'), {
+  value: 1,
+  writable: true,
+  enumerable: true,
+  configurable: true
+})
+assert(Object.getOwnPropertyDescriptor(obj, 'b'), {
+  value: 2,
+  writable: false,
+  enumerable: false,
+  configurable: false
+})
+assert(Object.getOwnPropertyDescriptor(obj, 'c'), undefined)
+
+// [[Set]] ignores read-only properties, or throws in strict code.
+obj.b = 3
+assert(obj.b, 2)
+assert(
+  throwsTypeError(function() {
+    'use strict'
+    obj.b = 3
+  }),
+  true
+)
+
+// [[Delete]] keeps non-configurable properties, or throws in strict code.
+assert(delete obj.a, true)
+assert(obj.a, undefined)
+assert(delete obj.b, false)
+assert(obj.b, 2)
+assert(delete obj.nothing, true)
+assert(
+  throwsTypeError(function() {
+    'use strict'
+    delete obj['b']
+  }),
+  true
+)
+
+// Enumeration skips non-enumerable properties. Array indexes come first, then the other keys in
+// the order they were added.
+let keys = { z: 1, a: 3 }
+keys[1] = 2
+keys[0] = 4
+Object.defineProperty(keys, 'hidden', { value: 5, enumerable: false })
+assert(Object.keys(keys), ['0', '1', 'z', 'a'])
+assert(Object.keys([7, 8]), ['0', '1'])
+assert(Object.keys('ab'), [])
+assert(throwsTypeError(() => Object.keys(null)), true)
+
+// A non-configurable property can not be redefined, except for the value of a writable one.
+let fixed = {}
+Object.defineProperty(fixed, 'x', { value: 1, writable: true })
+Object.defineProperty(fixed, 'x', { value: 2 })
+assert(fixed.x, 2)
+Object.defineProperty(fixed, 'x', { writable: false })
+assert(throwsTypeError(() => Object.defineProperty(fixed, 'x', { value: 3 })), true)
+assert(throwsTypeError(() => Object.defineProperty(fixed, 'x', { enumerable: true })), true)
+assert(throwsTypeError(() => Object.defineProperty(fixed, 'x', { get: () => 1 })), true)
+Object.defineProperty(fixed, 'x', { value: 2 })
+assert(fixed.x, 2)
+
+// Accessor properties
+let point = { _x: 1 }
+Object.defineProperty(point, 'x', {
+  get: function() {
+    return this._x * 10
+  },
+  set: function(val) {
+    this._x = val
+  },
+  configurable: true
+})
+point.x = 5
+assert(point.x, 50)
+let desc = Object.getOwnPropertyDescriptor(point, 'x')
+assert(typeof desc.get, 'function')
+assert(desc.set === undefined, false)
+assert(desc.enumerable, false)
+assert(desc.configurable, true)
+// A configurable accessor property can be turned into a data property.
+Object.defineProperty(point, 'x', { value: 3 })
+assert(Object.getOwnPropertyDescriptor(point, 'x'), {
+  value: 3,
+  writable: false,
+  enumerable: false,
+  configurable: true
+})
+
+// Invalid descriptors
+assert(throwsTypeError(() => Object.defineProperty({}, 'x', 1)), true)
+assert(throwsTypeError(() => Object.defineProperty({}, 'x', { get: 1 })), true)
+assert(throwsTypeError(() => Object.defineProperty({}, 'x', { get: () => 1, value: 1 })), true)
+assert(throwsTypeError(() => Object.defineProperty(1, 'x', {})), true)
+
+// Array elements
+let ary = [1, 2, 3]
+Object.defineProperty(ary, 1, { value: 20, writable: false })
+ary[1] = 200
+assert(ary[1], 20)
+Object.defineProperty(ary, '5', { value: 6, writable: true, enumerable: true, configurable: true })
+assert(ary.length, 6)
+assert(Object.getOwnPropertyDescriptor(ary, 'length'), {
+  value: 6,
+  writable: true,
+  enumerable: false,
+  configurable: false
+})
+assert(delete ary[0], true)
+assert(ary[0], undefined)
+assert(Object.keys(ary), ['1', '2', '5'])
+assert(delete ary.length, false)
+
+// getOwnPropertyDescriptors
+assert(Object.getOwnPropertyDescriptors({ a: 1 }), {
+  a: { value: 1, writable: true, enumerable: true, configurable: true }
+})
@@ -28,3 +28,29 @@ var object = new Error()
 assert(object.__proto__ === Error.prototype, true)
 //var object = new Date()
 //assert(object.__proto__ === Date.prototype)
+
+// Methods on user prototypes are found through the prototype chain.
+function Animal(name) {
+  this.name = name
+}
+Animal.prototype.speak = function() {
+  return this.name + ' speaks'
+}
+function Dog(name) {
+  this.name = name
+}
+Dog.prototype = new Animal('')
+Dog.prototype.bark = function() {
+  return this.name + ' barks'
+}
+var dog = new Dog('Rex')
+assert(dog.speak(), 'Rex speaks')
+assert(dog.bark(), 'Rex barks')
+assert(dog.fly, undefined)
+assert(dog[Symbol()], undefined)
+// Assigning shadows the inherited property.
+dog.speak = function() {
+  return 'woof'
+}
+assert(dog.speak(), 'woof')
+assert(new Dog('Max').speak(), 'Max speaks')
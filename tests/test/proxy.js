@@ -0,0 +1,148 @@
+var assert = require('assert').deepStrictEqual
+
+function caught(f) {
+  try {
+    f()
+  } catch (e) {
+    return e.name
+  }
+}
+
+// Without traps, every operation is forwarded to the target.
+var target = { a: 1 }
+var p = new Proxy(target, {})
+assert(p.a, 1)
+p.b = 2
+assert(target.b, 2)
+assert('b' in p, true)
+assert(delete p.b, true)
+assert('b' in target, false)
+assert(Object.keys(p), ['a'])
+assert(typeof p, 'object')
+
+// get
+var log = []
+var handler = {
+  get: function(t, key, receiver) {
+    log.push(key)
+    return key in t ? t[key] : 'default'
+  },
+}
+p = new Proxy({ x: 10 }, handler)
+assert(p.x, 10)
+assert(p.y, 'default')
+assert(p[0], 'default')
+assert(log, ['x', 'y', '0'])
+
+// The trap is called with the handler as this, and the proxy as the receiver.
+var self, recv
+handler = {
+  get: function(t, key, receiver) {
+    self = this
+    recv = receiver
+  },
+}
+p = new Proxy({}, handler)
+p.foo
+assert(self === handler, true)
+assert(recv === p, true)
+
+// set
+target = {}
+p = new Proxy(target, {
+  set: function(t, key, val) {
+    t[key] = val * 2
+    return true
+  },
+})
+p.a = 5
+assert(target.a, 10)
+
+// A set trap returning false is an error only in strict code.
+p = new Proxy({}, { set: function() { return false } })
+p.a = 1
+assert(caught(function() { 'use strict'; p.a = 1 }), 'TypeError')
+
+// has
+p = new Proxy({}, {
+  has: function(t, key) {
+    return key.length === 3
+  },
+})
+assert('abc' in p, true)
+assert('ab' in p, false)
+
+// in looks up the prototype chain, through proxies too.
+var child = {}
+child.__proto__ = p
+assert('xyz' in child, true)
+
+// deleteProperty
+target = { a: 1, b: 2 }
+p = new Proxy(target, {
+  deleteProperty: function(t, key) {
+    if (key === 'a') return false
+    delete t[key]
+    return true
+  },
+})
+assert(delete p.a, false)
+assert(delete p.b, true)
+assert(Object.keys(target), ['a'])
+
+// ownKeys
+p = new Proxy({ a: 1, b: 2, c: 3 }, {
+  ownKeys: function(t) {
+    return ['c', 'a', 'z']
+  },
+})
+assert(Object.keys(p), ['c', 'a'])
+p = new Proxy({}, { ownKeys: function() { return [1] } })
+assert(caught(function() { Object.keys(p) }), 'TypeError')
+
+// apply
+function sum(a, b) {
+  return a + b
+}
+p = new Proxy(sum, {
+  apply: function(t, thisArg, args) {
+    return t(args[0], args[1]) * 10
+  },
+})
+assert(typeof p, 'function')
+assert(p(1, 2), 30)
+assert(p.call(null, 2, 3), 50)
+assert([1, 2].map(new Proxy(function(x) { return x + 1 }, {})), [2, 3])
+
+// construct
+function Point(x, y) {
+  this.x = x
+  this.y = y
+}
+p = new Proxy(Point, {})
+var pt = new p(1, 2)
+assert(pt.x + pt.y, 3)
+assert(pt.__proto__ === Point.prototype, true)
+p = new Proxy(Point, {
+  construct: function(t, args, newTarget) {
+    return { made: args.length }
+  },
+})
+assert(new p(1, 2, 3), { made: 3 })
+p = new Proxy(Point, { construct: function() { return 1 } })
+assert(caught(function() { new p() }), 'TypeError')
+
+// A proxy of an object is neither callable nor a constructor.
+p = new Proxy({}, {})
+assert(caught(function() { p() }), 'TypeError')
+assert(caught(function() { new p() }), 'TypeError')
+
+// A proxy of a proxy.
+p = new Proxy(new Proxy({ a: 1 }, { get: function() { return 'inner' } }), {})
+assert(p.a, 'inner')
+
+// Errors
+assert(caught(function() { new Proxy(1, {}) }), 'TypeError')
+assert(caught(function() { new Proxy({}, null) }), 'TypeError')
+assert(caught(function() { new Proxy({}, { get: 1 }).a }), 'TypeError')
+assert(caught(function() { 'a' in 1 }), 'TypeError')
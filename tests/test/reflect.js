@@ -0,0 +1,92 @@
+var assert = require('assert').deepStrictEqual
+
+function caught(f) {
+  try {
+    f()
+  } catch (e) {
+    return e.name
+  }
+}
+
+// get and set
+var obj = { a: 1 }
+assert(Reflect.get(obj, 'a'), 1)
+assert(Reflect.get(obj, 'b'), undefined)
+assert(Reflect.get([5, 6], 1), 6)
+assert(Reflect.set(obj, 'b', 2), true)
+assert(obj.b, 2)
+
+// The receiver is the this of accessors.
+var accessors = {}
+Object.defineProperty(accessors, 'x', {
+  get: function() { return this.v },
+  set: function(v) { this.v = v * 2 },
+})
+var receiver = { v: 10 }
+assert(Reflect.get(accessors, 'x', receiver), 10)
+assert(Reflect.set(accessors, 'x', 3, receiver), true)
+assert(receiver.v, 6)
+
+// Setting a read-only property gives false instead of throwing.
+Object.defineProperty(obj, 'ro', { value: 1, writable: false })
+assert(Reflect.set(obj, 'ro', 2), false)
+assert(obj.ro, 1)
+
+// has
+assert(Reflect.has(obj, 'a'), true)
+assert(Reflect.has(function() {}, 'call'), true)
+assert(Reflect.has(obj, 'c'), false)
+
+// ownKeys
+assert(Reflect.ownKeys({ x: 1, y: 2 }), ['x', 'y'])
+assert(Reflect.ownKeys([1, 2]), ['0', '1', 'length'])
+
+// defineProperty
+var frozen = {}
+assert(Reflect.defineProperty(frozen, 'a', { value: 1, configurable: false }), true)
+assert(Reflect.defineProperty(frozen, 'a', { value: 2 }), false)
+assert(frozen.a, 1)
+
+// getPrototypeOf
+function Point(x, y) {
+  this.x = x
+  this.y = y
+}
+assert(Reflect.getPrototypeOf(new Point(1, 2)) === Point.prototype, true)
+assert(Reflect.getPrototypeOf([]) === Array.prototype, true)
+
+// apply and construct
+function sum(a, b) {
+  return this.base + a + b
+}
+assert(Reflect.apply(sum, { base: 10 }, [1, 2]), 13)
+var pt = Reflect.construct(Point, [3, 4])
+assert(pt.x + pt.y, 7)
+assert(pt.__proto__ === Point.prototype, true)
+
+// Reflect on proxies calls the traps.
+var log = []
+var p = new Proxy({ a: 1 }, {
+  get: function(t, key) {
+    log.push('get ' + key)
+    return t[key]
+  },
+  has: function(t, key) {
+    log.push('has ' + key)
+    return key in t
+  },
+  ownKeys: function(t) {
+    log.push('ownKeys')
+    return ['a']
+  },
+})
+assert(Reflect.get(p, 'a'), 1)
+assert(Reflect.has(p, 'a'), true)
+assert(Reflect.ownKeys(p), ['a'])
+assert(log, ['get a', 'has a', 'ownKeys'])
+
+// Errors
+assert(caught(function() { Reflect.get(1, 'a') }), 'TypeError')
+assert(caught(function() { Reflect.apply(1, null, []) }), 'TypeError')
+assert(caught(function() { Reflect.apply(sum, null, 1) }), 'TypeError')
+assert(caught(function() { Reflect.construct(Math.random, []) }), 'TypeError')
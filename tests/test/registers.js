@@ -0,0 +1,85 @@
+let assert = require('assert').deepStrictEqual
+
+// Parameters and vars not captured by inner functions are stored in registers.
+function sum(a, b, ...rest) {
+  var total = a + b
+  for (var i = 0; i < rest.length; i++) total += rest[i]
+  return total
+}
+assert(sum(1, 2), 3)
+assert(sum(1, 2, 3, 4), 10)
+
+function hoisted() {
+  var before = x
+  var x = 1
+  var x
+  return [before, x, typeof before]
+}
+assert(hoisted(), [undefined, 1, 'undefined'])
+
+function missing(a, b) {
+  return [a, b]
+}
+assert(missing(1), [1, undefined])
+
+// Each call has its own registers.
+function fact(n) {
+  var m = n
+  if (m < 2) return 1
+  return m * fact(m - 1)
+}
+assert(fact(10), 3628800)
+
+// A let in a block shadows a var of the same name in the environments.
+function shadow() {
+  var x = 1
+  var inner
+  {
+    let x = 2
+    inner = x
+  }
+  return [x, inner]
+}
+assert(shadow(), [1, 2])
+
+// Captured variables stay in the environments, next to the registers.
+function counter(start) {
+  var step = 1
+  var count = start
+  var unused = 0
+  return function() {
+    unused++
+    count += step
+    return count
+  }
+}
+var next = counter(10)
+next()
+assert(next(), 12)
+
+function withCatch(x) {
+  var e = 'var'
+  try {
+    throw 'thrown'
+  } catch (e) {
+    x = e
+  }
+  return [x, e]
+}
+assert(withCatch(0), ['thrown', 'var'])
+
+// Direct eval may refer to any variable, so nothing is stored in registers.
+function withEval(a) {
+  var b = 2
+  return eval('a + b')
+}
+assert(withEval(1), 3)
+
+// Objects only referenced from registers survive GC.
+function keep() {
+  var first = { value: 'first' }
+  var last
+  for (var i = 0; i < 3000; i++) last = { value: i, ary: [i] }
+  return [first.value, last.value, last.ary]
+}
+assert(keep(), ['first', 2999, [2999]])
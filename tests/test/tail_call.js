@@ -0,0 +1,114 @@
+'use strict'
+var assert = require('assert').deepStrictEqual
+
+// Mutual recursion deeper than the frames would allow without reusing them.
+function isEven(n) {
+  if (n === 0) return true
+  return isOdd(n - 1)
+}
+function isOdd(n) {
+  if (n === 0) return false
+  return isEven(n - 1)
+}
+assert(isEven(100000), true)
+assert(isOdd(7), true)
+
+// Accumulators and closures.
+function sum(n, acc) {
+  return n === 0 ? acc : sumStep(n, acc)
+}
+function sumStep(n, acc) {
+  return sum(n - 1, acc + n)
+}
+assert(sum(100000, 0), 5000050000)
+var add = x => y => x + y
+function apply(f, x) {
+  return f(x)
+}
+assert(apply(add(1), 2), 3)
+
+// Calls in the branches of ?:, the rhs of && and || and the last operand of , are in tail
+// position too.
+function even(n) {
+  return n === 0 ? true : odd(n - 1)
+}
+function odd(n) {
+  return n === 0 ? false : even(n - 1)
+}
+assert(even(100000), true)
+assert(odd(100001), true)
+assert(even(7), false)
+function countAnd(n) {
+  return n === 0 || countAnd(n - 1)
+}
+assert(countAnd(100000), true)
+function countOr(n) {
+  return n > 0 && countOr(n - 1)
+}
+assert(countOr(100000), false)
+function countComma(n, acc) {
+  return n === 0 ? acc : (acc.push(n), countComma(n - 1, acc))
+}
+assert(countComma(100000, []).length, 100000)
+
+// Method calls keep the receiver.
+var counter = {
+  count: 0,
+  down: function(n) {
+    this.count++
+    return n === 0 ? this.count : this.down(n - 1)
+  },
+}
+assert(counter.down(100000), 100001)
+
+// Tail calls of builtins and bound functions are ordinary calls.
+function parse(s) {
+  return parseFloat(s)
+}
+assert(parse('1.5'), 1.5)
+function boundThis() {
+  return this
+}
+function callBound() {
+  return boundThis.bind(42)()
+}
+assert(callBound(), 42)
+function plainThis() {
+  return boundThis()
+}
+assert(plainThis(), undefined)
+
+// A constructor returns the new object, whatever the call in tail position returns.
+function Point(x) {
+  this.x = x
+  return sum(x, 0)
+}
+assert(new Point(3).x, 3)
+
+// The handlers of try and catch blocks stay.
+function thrower() {
+  throw 'thrown'
+}
+function catcher() {
+  try {
+    return thrower()
+  } catch (e) {
+    return e
+  }
+}
+assert(catcher(), 'thrown')
+function withFinally() {
+  var log = []
+  function inner() {
+    try {
+      return thrower()
+    } finally {
+      log.push('finally')
+    }
+  }
+  try {
+    inner()
+  } catch (e) {}
+  return log
+}
+assert(withFinally(), ['finally'])
@@ -0,0 +1,68 @@
+var assert = require('assert').deepStrictEqual
+
+var global = this
+var obj = {
+  method: function() {
+    function plain() {
+      return this
+    }
+    var arrow = () => this
+    return [this === obj, plain() === global, arrow() === obj]
+  },
+  strict: function() {
+    'use strict'
+    function plain() {
+      return this
+    }
+    return [this === obj, plain()]
+  },
+}
+assert(obj.method(), [true, true, true])
+assert(obj.strict(), [true, undefined])
+
+function sloppyThis() {
+  return this
+}
+function strictThis() {
+  'use strict'
+  return this
+}
+assert(sloppyThis() === global, true)
+assert(sloppyThis.call(undefined) === global, true)
+assert(sloppyThis.call(null) === global, true)
+assert(sloppyThis.call(obj) === obj, true)
+assert(strictThis(), undefined)
+assert(strictThis.call(null), null)
+assert(strictThis.call(obj) === obj, true)
+
+function Point(x) {
+  this.x = x
+  this.self = () => this
+}
+var p = new Point(1)
+assert(p.x, 1)
+assert(p.self() === p, true)
+assert(global.x, undefined)
+
+var method = obj.strict
+assert(method.call(obj), [true, undefined])
+
+// Primitive values are wrapped in objects for non-strict functions.
+assert(typeof sloppyThis.call(5), 'object')
+assert(typeof sloppyThis.call('ab'), 'object')
+assert(typeof sloppyThis.call(true), 'object')
+assert(strictThis.call(5), 5)
+assert(sloppyThis.call(5) + 1, 6)
+assert(sloppyThis.call('ab') + 'c', 'abc')
+assert(sloppyThis.call('ab').length, 2)
+assert(sloppyThis.call('ab')[1], 'b')
+assert(sloppyThis.call(5) === sloppyThis.call(5), false)
+Number.prototype.twice = function() {
+  return this + this
+}
+Reflect.getPrototypeOf(sloppyThis.call('')).position = function(c) {
+  return this.indexOf(c)
+}
+var n = 21
+assert(n.twice(), 42)
+assert('xyz'.position('y'), 1)
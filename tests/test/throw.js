@@ -0,0 +1,33 @@
+let assert = require('assert').deepStrictEqual
+
+let caught = function(f) {
+  try {
+    f()
+  } catch (e) {
+    return e
+  }
+  return 'not thrown'
+}
+
+// Any value can be thrown.
+assert(caught(() => { throw 1 }), 1)
+assert(caught(() => { throw 'str' }), 'str')
+assert(caught(() => { throw null }), null)
+assert(caught(() => { throw { a: 1 } }), { a: 1 })
+assert(caught(() => { throw new Error('e') }).message, 'e')
+
+// Unwinding through nested calls.
+function inner() {
+  throw 'inner'
+}
+function outer() {
+  inner()
+  return 'unreachable'
+}
+assert(caught(outer), 'inner')
+
+// Unwinding through a native function calling back into JS.
+assert(caught(() => [1, 2, 3].map(x => { if (x == 2) throw x * 10 })), 20)
+
+// Unwinding out of a module.
+assert(caught(() => require('./tests/test/throw_module.js')), 'module')
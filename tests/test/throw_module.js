@@ -0,0 +1 @@
+throw 'module'
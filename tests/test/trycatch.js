@@ -31,3 +31,210 @@ try {
 }
 
 assert(a, [0, 2, 123, 10110])
+
+// Optional catch binding
+let caught = false
+try {
+  throw 1
+} catch {
+  caught = true
+}
+assert(caught, true)
+
+// Lexical environments entered in try are left when an exception is caught
+let x = 'outer'
+try {
+  let x = 'inner'
+  {
+    let y = x
+    null.foo
+  }
+} catch (e) {
+  assert(x, 'outer')
+}
+assert(x, 'outer')
+
+// Rethrow from finally
+function rethrow() {
+  try {
+    throw 'first'
+  } finally {
+    a.push('finally')
+  }
+}
+a = []
+try {
+  rethrow()
+} catch (e) {
+  a.push(e)
+}
+assert(a, ['finally', 'first'])
+
+// Throw in finally replaces the exception being thrown
+try {
+  try {
+    throw 'first'
+  } finally {
+    throw 'second'
+  }
+} catch (e) {
+  assert(e, 'second')
+}
+
+// Return in finally overrides both return in try and a thrown value
+function return_in_finally1() {
+  try {
+    return 1
+  } finally {
+    return 2
+  }
+}
+assert(return_in_finally1(), 2)
+
+function return_in_finally2() {
+  try {
+    throw 1
+  } finally {
+    return 3
+  }
+}
+assert(return_in_finally2(), 3)
+
+// Return in try runs finally
+a = []
+function return_in_try() {
+  try {
+    return 'try'
+  } finally {
+    a.push('finally')
+  }
+}
+assert(return_in_try(), 'try')
+assert(a, ['finally'])
+
+// Return runs the enclosing finally clauses, innermost first
+a = []
+function nested_finally(ret) {
+  try {
+    try {
+      if (ret) return 'inner'
+      a.push('try')
+    } finally {
+      a.push('finally1')
+    }
+    a.push('after')
+  } catch (e) {
+    a.push('catch')
+  } finally {
+    a.push('finally2')
+  }
+  return 'end'
+}
+assert(nested_finally(true), 'inner')
+assert(a, ['finally1', 'finally2'])
+a = []
+assert(nested_finally(false), 'end')
+assert(a, ['try', 'finally1', 'after', 'finally2'])
+
+// An exception thrown from a finally clause of a callee reaches the caller's handlers
+function throw_in_finally() {
+  try {
+    a.push('try')
+  } finally {
+    throw 'from finally'
+  }
+}
+a = []
+for (let i = 0; i < 2; i++) {
+  try {
+    try {
+      throw_in_finally()
+    } finally {
+      a.push('finally')
+    }
+  } catch (e) {
+    a.push(e)
+  }
+}
+assert(a, ['try', 'finally', 'from finally', 'try', 'finally', 'from finally'])
+
+// A caught exception leaves the finally clauses it was thrown in
+a = []
+try {
+  a.push('try')
+} finally {
+  try {
+    try {
+      a.push('inner try')
+    } finally {
+      throw 'inner'
+    }
+  } catch (e) {
+    a.push(e)
+  }
+  a.push('outer finally')
+}
+a.push('end')
+assert(a, ['try', 'inner try', 'inner', 'outer finally', 'end'])
+
+// break and continue run the finally clauses they leave
+let s = 0
+while (true) {
+  try {
+    break
+  } finally {
+    s = 1
+  }
+}
+assert(s, 1)
+let log = []
+for (let i = 0; i < 3; i++) {
+  try {
+    if (i == 1) continue
+    if (i == 2) break
+    log.push('t' + i)
+  } finally {
+    log.push('f' + i)
+  }
+}
+assert(log, ['t0', 'f0', 'f1', 'f2'])
+log = []
+for (let i = 0; i < 2; i++) {
+  try {
+    try {
+      throw 'e' + i
+    } catch (e) {
+      let x = e
+      log.push(x)
+      continue
+    } finally {
+      log.push('inner')
+    }
+  } finally {
+    log.push('outer')
+  }
+}
+assert(log, ['e0', 'inner', 'outer', 'e1', 'inner', 'outer'])
+// break in a finally clause discards the exception
+log = []
+while (true) {
+  try {
+    throw 'lost'
+  } finally {
+    log.push('finally')
+    break
+  }
+}
+log.push('end')
+assert(log, ['finally', 'end'])
+function break_in_finally() {
+  for (;;) {
+    try {
+      return 'return'
+    } finally {
+      break
+    }
+  }
+  return 'after loop'
+}
+assert(break_in_finally(), 'after loop')
@@ -9,4 +9,30 @@ assert(typeof [] == 'object')
 assert(typeof (() => 0) == 'function')
 assert(typeof function() {} == 'function')
 assert(typeof Symbol() == 'symbol')
-assert(typeof new Error('message') == 'error')
+assert(typeof new Error('message') == 'object')
+assert(typeof undefined == 'undefined')
+assert(typeof null == 'object')
+assert(typeof true == 'boolean')
+assert(typeof new Map() == 'object')
+assert(typeof new Proxy({}, {}) == 'object')
+assert(typeof new Proxy(function() {}, {}) == 'function')
+assert(typeof Math.random == 'function')
+assert(typeof typeof 1 == 'string')
+
+// Variables which are not declared are undefined.
+assert(typeof notDeclared == 'undefined')
+assert((function() { return typeof notDeclared })() == 'undefined')
+var declared = 1
+assert(typeof declared == 'number')
+assert((function(param) { return typeof param })() == 'undefined')
+assert((function() { let f = () => typeof local; let local = 's'; return f() })() == 'string')
+
+// Bindings before their initialization are still a ReferenceError.
+let error
+try {
+  typeof beforeInit
+  let beforeInit = 1
+} catch (e) {
+  error = e
+}
+assert(error.name == 'ReferenceError')
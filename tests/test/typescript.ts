@@ -0,0 +1,35 @@
+var assert = require('assert').deepStrictEqual
+
+interface Point {
+  x: number
+  y?: number
+}
+type Pair<T> = [T, T]
+
+enum Color { Red, Green = 5, Blue }
+assert(Color.Red, 0)
+assert(Color.Blue, 6)
+assert(Color[5], 'Green')
+const enum Direction { Up = 'UP', Down = -1, Left }
+assert(Direction.Up, 'UP')
+assert(Direction.Left, 0)
+
+declare const window: { document: any }
+
+function first<T>(pair: Pair<T>): T
+function first(pair: any): any {
+  return pair[0]
+}
+assert(first<number>([1, 2] as Pair<number>), 1)
+
+function norm(p: Point, scale?: number): number {
+  const y: number = p.y!
+  return (p.x * p.x + y * y) * scale!
+}
+assert(norm({ x: 3, y: 4 }, 2), 50)
+
+let add = (a: number, b?: number): number => a + (b as number)
+assert(add(1, 2), 3)
+
+let nested: Array<Array<number>>= [[1]]
+assert(nested[0][0] as any as number, 1)
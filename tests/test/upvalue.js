@@ -0,0 +1,65 @@
+const assert = (n, l, r) => {
+  if (l !== r) {
+    throw n
+  }
+}
+
+function counter() {
+  let count = 0
+  return function() {
+    count = count + 1
+    return count
+  }
+}
+const c1 = counter(), c2 = counter()
+c1()
+c1()
+assert(1, c1(), 3)
+assert(2, c2(), 1)
+
+function adder(a) {
+  return b => c => a + b + c
+}
+assert(3, adder(1)(2)(3), 6)
+
+function shadow() {
+  let x = 'outer'
+  return function() {
+    { let x = 'inner'; if (x !== 'inner') return x }
+    return x
+  }
+}
+assert(4, shadow()(), 'outer')
+
+function share() {
+  var v = 1
+  const get = () => v
+  const set = n => { v = n }
+  return [get, set]
+}
+const accessors = share(), get = accessors[0], set = accessors[1]
+set(5)
+assert(5, get(), 5)
+
+let top = 1
+function readTop() { return top }
+top = 2
+assert(6, readTop(), 2)
+
+function blockCapture() {
+  const fs = []
+  for (let i = 0; i < 3; i++) {
+    let j = i
+    fs.push(() => j)
+  }
+  return fs[0]() + fs[1]() + fs[2]()
+}
+assert(7, blockCapture(), 3)
+
+function constCapture() {
+  const k = 1
+  return () => { k = 2 }
+}
+let threw = false
+try { constCapture()() } catch (e) { threw = true }
+assert(8, threw, true)
@@ -7,3 +7,12 @@ while (i <= 20) {
   i++
 }
 assert(total, 55)
+
+let n = 0
+while (n < 5) n++
+assert(n, 5)
+while (n >= 2) n--
+assert(n, 1)
+let s = ''
+while (s.length < 3 && s <= 'bbb') s += 'b'
+assert(s, 'bbb')
@@ -54,9 +54,9 @@ pub fn runtime_error(text: &str) -> String {
     let mut vm = vm::vm::VM::new();
 
     let mut parser = parser::Parser::new("test", text);
-    let node = parser.parse_all().unwrap();
+    let mut node = parser.parse_all().unwrap();
 
-    let func_info = vm.compile(&node, true).unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
     match vm.run_global(func_info) {
         Ok(()) => panic!(),
         Err(err) => return format!("{:?}", err),
@@ -70,15 +70,11 @@ pub fn execute_script(text: String) -> String {
     let mut vm = vm::vm::VM::new();
 
     let mut parser = parser::Parser::new("test", text);
-    let node = parser.parse_all().unwrap();
-    let func_info = vm.compile(&node, true).unwrap();
-    vm.run_global(func_info).unwrap();
-    let val: Value = vm
-        .current_context
-        .stack
-        .pop()
-        .unwrap_or(Value::undefined().into())
-        .into();
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    // The completion value of the script is returned by ``run``, not left on the stack.
+    vm.current_context = vm.create_global_context(func_info);
+    let val: Value = vm.run().unwrap();
     val.debug_string(true)
 }
 
@@ -97,6 +93,18 @@ fn vm_test() {
     execute_script("for(var i = 0; i < 4; i++){ i }".to_string());
 }
 
+#[test]
+fn number_to_string() {
+    test_code("(255).toString(16)", "'ff'");
+    test_code("(-0.5).toString(2)", "'-0.1'");
+    test_code("(12.5).toString()", "'12.5'");
+    test_code("var n = 35; n.toString(36.9)", "'z'");
+    test_code(
+        "var name; try { (1).toString(1) } catch (e) { name = e.name } name",
+        "'RangeError'",
+    );
+}
+
 #[test]
 fn string_test1() {
     test_code("'死して屍拾う者なし'[4]", "'拾'");
@@ -106,6 +114,174 @@ fn string_test1() {
 fn string_test2() {
     test_code("'死して屍拾う者なし'.length", "9");
 }
+
+#[test]
+fn deep_nesting_test() {
+    let mut vm = vm::vm::VM::new();
+    let code = format!("1{}", "+1".repeat(9000));
+    let mut node = parser::Parser::new("test", code).parse_all().unwrap();
+    assert!(vm.compile(&mut node, true).is_err());
+}
+
+#[test]
+fn redeclaration_test() {
+    let mut vm = vm::vm::VM::new();
+    let mut node = parser::Parser::new("test", "let x = 1; { var x = 2; }")
+        .parse_all()
+        .unwrap();
+    let err = vm.compile(&mut node, true).unwrap_err();
+    assert_eq!(err.token_pos, 17);
+    assert_eq!(err.kind, vm::codegen::ErrorKind::Redeclaration(4));
+}
+
+#[test]
+fn constant_folding_test() {
+    test_code(
+        "[1 + 2 * 3, 'a' + 'b' + 1, -1 - 1, 1 / (0 * -1), -7 % 2, 1 / (-4 % 2), 1 << 31, !'' && 'x']",
+        "[7, 'ab1', -2, -Infinity, -1, -Infinity, -2147483648, 'x']",
+    );
+    // The callee is called with this undefined, not as a method of o.
+    test_code(
+        "var o = { m: function() { return this === o } }; var t = true; \
+         [(true && o.m)(), (t && o.m)(), (false || o.m)(), o.m()]",
+        "[false, false, false, true]",
+    );
+}
+
+#[test]
+fn dead_code_elimination_test() {
+    test_code(
+        "var v = 1; function f() { v = 2; return g(); v = 3; var v; function g() { return v } } if (false) { v = 4 } [f(), v]",
+        "[2, 1]",
+    );
+}
+
+#[cfg(feature = "jsx")]
+#[test]
+fn jsx_test() {
+    use rapidus::vm::jsx::FactoryCall;
+    use std::rc::Rc;
+
+    let code = "
+        function h(type, props, ...children) {
+            var attrs = props === null ? '' : ' id=' + props.id + ' hidden=' + props.hidden;
+            return '<' + type + attrs + '>' + children.join('') + '</' + type + '>'
+        }
+        const F = 'fragment', name = 'x';
+        <>
+          <p id=\"a\" hidden>Hello, {name}!</p>
+          {[1, 2].map(i => <b>{i}</b>)}
+          <F />
+        </>";
+    let mut vm = vm::vm::VM::new();
+    vm.jsx_transform = Rc::new(FactoryCall {
+        factory: "h".to_string(),
+        fragment: "F".to_string(),
+    });
+    let mut node = parser::Parser::new("test", code)
+        .with_options(parser::ParserOptions {
+            jsx: true,
+            ..parser::ParserOptions::default()
+        })
+        .parse_all()
+        .unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    vm.current_context = vm.create_global_context(func_info);
+    let val = vm.run().unwrap();
+    assert_eq!(
+        val.to_string(),
+        "<fragment><p id=a hidden=true>Hello, x!</p><b>1</b>,<b>2</b><fragment></fragment></fragment>"
+    );
+}
+
+#[cfg(feature = "typescript")]
+#[test]
+fn typescript_test() {
+    let mut parser = parser::Parser::load_module("tests/test/typescript.ts").unwrap();
+    let mut node = parser.parse_all().unwrap();
+    let mut vm = vm::vm::VM::new();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    vm.run_global(func_info).unwrap();
+}
+
+#[test]
+fn property_key_test() {
+    let code = "var o = { x: 1 }; o.x = o.x + 1; o.y = o.x; o.x";
+    let mut vm = vm::vm::VM::new();
+    let mut node = parser::Parser::new("test", code).parse_all().unwrap();
+    vm.compile(&mut node, true).unwrap();
+    let keys = vm
+        .constant_table
+        .table
+        .iter()
+        .filter(|constant| match constant {
+            vm::constant::Constant::Value(val) => val.is_string() && val.to_string() == "x",
+            _ => false,
+        })
+        .count();
+    assert_eq!(keys, 1);
+}
+
+#[test]
+fn error_location_test() {
+    let mut vm = vm::vm::VM::new();
+    let mut parser = parser::Parser::new("page.html", "let a = 1\n  a + b").with_line_offset(20);
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm
+        .compile_with_source(&mut node, true, parser.source_text())
+        .unwrap();
+    vm.script_info
+        .push((func_info.module_func_id, parser.into_script_info()));
+    let err = vm.run_global(func_info).unwrap_err();
+    assert_eq!(vm.error_location(&err), Some("page.html:22:7".to_string()));
+
+    let mut vm = vm::vm::VM::new();
+    let mut parser = parser::Parser::new("f.js", "function f() {\n  return 1 + x\n}\nf()");
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    vm.script_info
+        .push((func_info.module_func_id, parser.into_script_info()));
+    let err = vm.run_global(func_info).unwrap_err();
+    // Compiled without the source text.
+    assert_eq!(vm.error_location(&err), None);
+
+    let mut vm = vm::vm::VM::new();
+    let mut parser = parser::Parser::new("f.js", "function f() {\n  return 1 + x\n}\nf()");
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm
+        .compile_with_source(&mut node, true, parser.source_text())
+        .unwrap();
+    vm.script_info
+        .push((func_info.module_func_id, parser.into_script_info()));
+    let err = vm.run_global(func_info).unwrap_err();
+    assert_eq!(vm.error_location(&err), Some("f.js:2:14".to_string()));
+}
+
+#[test]
+fn unicode_identifier_test() {
+    test_code("let café = 1, 変数 = 2; caf\\u00e9 + \\u{5909}数", "3");
+}
+
+#[test]
+fn hoisting_test() {
+    assert_file("hoisting");
+}
+
+#[test]
+fn upvalue_test() {
+    assert_file("upvalue");
+}
+
+#[test]
+fn eval_test() {
+    assert_file("eval");
+}
+
+#[test]
+fn error_types_test() {
+    assert_file("error_types");
+}
+
 #[test]
 fn operator_test() {
     test_code("+(5>3)+60%7+(3>=5)+!!5+(-6)", "0");
@@ -121,6 +297,36 @@ fn this_test() {
     test_file("this", "[1,101,124]");
 }
 
+#[test]
+fn this_binding() {
+    assert_file("this_binding")
+}
+
+#[test]
+fn construct() {
+    assert_file("construct")
+}
+
+#[test]
+fn proxy() {
+    assert_file("proxy")
+}
+
+#[test]
+fn reflect() {
+    assert_file("reflect")
+}
+
+#[test]
+fn json() {
+    assert_file("json")
+}
+
+#[test]
+fn tail_call() {
+    assert_file("tail_call")
+}
+
 #[test]
 fn prototype_test() {
     assert_file("prototypes");
@@ -131,6 +337,16 @@ fn accessor_property() {
     assert_file("accessor_property");
 }
 
+#[test]
+fn property_descriptor() {
+    assert_file("property_descriptor");
+}
+
+#[test]
+fn delete() {
+    assert_file("delete");
+}
+
 #[test]
 fn trinity() {
     assert_file("trinity");
@@ -141,11 +357,88 @@ fn closure() {
     assert_file("closure");
 }
 
+#[test]
+fn registers() {
+    assert_file("registers");
+}
+
+#[test]
+fn register_variables() {
+    use rapidus::bytecode_gen::VMInst;
+
+    let mut vm = vm::vm::VM::new();
+    let code = "function f(a, b) { var x = a; var y = b; return () => y } f";
+    let mut node = parser::Parser::new("test", code).parse_all().unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    vm.current_context = vm.create_global_context(func_info);
+    let f: Value = vm.run().unwrap();
+    let info = match f.as_function().kind {
+        vm::jsvalue::function::FunctionObjectKind::User { info, .. } => info,
+        _ => unreachable!(),
+    };
+    assert_eq!(info.register_names, vec!["a", "b", "x"]);
+    assert_eq!(info.var_names, vec!["y"]);
+    assert_eq!(
+        info.params.iter().map(|p| p.register).collect::<Vec<_>>(),
+        vec![Some(0), Some(1)]
+    );
+
+    let mut insts = vec![];
+    let mut pc = 0;
+    while pc < info.code.len() {
+        insts.push(info.code[pc]);
+        pc += VMInst::get_inst_size(info.code[pc]).unwrap();
+    }
+    assert!(insts.contains(&VMInst::GET_REGISTER));
+    assert!(insts.contains(&VMInst::SET_REGISTER));
+    assert!(!insts.contains(&VMInst::GET_VALUE));
+}
+
+#[test]
+fn call_site_cache() {
+    assert_file("call_site_cache");
+}
+
+#[test]
+fn call_site_cache_state() {
+    use rapidus::bytecode_gen::VMInst;
+
+    let mut vm = vm::vm::VM::new();
+    let code = "function f() {} for (let i = 0; i < 3; i++) f(); f()";
+    let mut node = parser::Parser::new("test", code).parse_all().unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    vm.run_global(func_info).unwrap();
+
+    let mut calls = vec![];
+    let mut pc = 0;
+    while pc < func_info.code.len() {
+        if func_info.code[pc] == VMInst::CALL {
+            calls.push(pc);
+        }
+        pc += VMInst::get_inst_size(func_info.code[pc]).unwrap();
+    }
+    assert_eq!(calls.len(), 2);
+    for pc in &calls {
+        let target = func_info.call_caches.monomorphic_target(*pc).unwrap();
+        assert_eq!(target.func_name, Some("f".to_string()));
+    }
+
+    vm.clear_call_caches();
+    assert!(calls
+        .iter()
+        .all(|pc| func_info.call_caches.get(*pc).is_none()));
+}
+
 #[test]
 fn trycatch() {
     assert_file("trycatch");
 }
 
+#[test]
+fn throw() {
+    assert_file("throw");
+}
+
 #[test]
 fn r#typeof() {
     assert_file("typeof");
@@ -166,6 +459,26 @@ fn r#for() {
     assert_file("for")
 }
 
+#[test]
+fn fused_loop_condition() {
+    use rapidus::bytecode_gen::VMInst;
+
+    let mut vm = vm::vm::VM::new();
+    let mut node = parser::Parser::new("test", "for (let i = 0; i < 10; i++) {}")
+        .parse_all()
+        .unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    let mut insts = vec![];
+    let mut pc = 0;
+    while pc < func_info.code.len() {
+        insts.push(func_info.code[pc]);
+        pc += VMInst::get_inst_size(func_info.code[pc]).unwrap();
+    }
+    assert!(insts.contains(&VMInst::LT_JMP_IF_FALSE));
+    assert!(!insts.contains(&VMInst::LT));
+    assert!(!insts.contains(&VMInst::JMP_IF_FALSE));
+}
+
 #[test]
 fn r#if() {
     assert_file("if")
@@ -222,6 +535,79 @@ fn test_module() {
     assert_file("test_module_caller")
 }
 
+#[test]
+fn import_map() {
+    use rapidus::import_map::ImportMap;
+    let import_map = ImportMap::parse(
+        r#"{ "imports": { "counter": "./tests/test/test_module.js", "fs": null } }"#,
+        ".",
+    )
+    .unwrap();
+    let mut vm = vm::vm::VM::new().with_import_map(import_map);
+    let mut parser = parser::Parser::new(
+        "test",
+        "let mod = require('counter'); mod.countup(5); mod.count()",
+    );
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    vm.current_context = vm.create_global_context(func_info);
+    assert_eq!(vm.run().unwrap().debug_string(true), "15");
+
+    let mut parser = parser::Parser::new("test", "require('fs')");
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    assert!(vm.run_global(func_info).is_err());
+}
+
+#[test]
+fn source_transform() {
+    use rapidus::source_transform::TransformedSource;
+    let mut vm = vm::vm::VM::new();
+    vm.set_source_transform(|_, code| {
+        let replacements = code
+            .match_indices("__NAME__")
+            .map(|(i, s)| (i..i + s.len(), "'rapidus'".to_string()))
+            .collect();
+        Ok(TransformedSource::replace(code, replacements))
+    });
+
+    let mut parser = parser::Parser::new("test", "__NAME__ + eval('__NA' + 'ME__.length')");
+    vm.transform_source(&mut parser).unwrap();
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    vm.current_context = vm.create_global_context(func_info);
+    assert_eq!(vm.run().unwrap().debug_string(true), "'rapidus7'");
+
+    // Errors point at the source before the transform.
+    let mut parser = parser::Parser::new("t.js", "__NAME__ + __NAME__\n__NAME__; s + b");
+    vm.transform_source(&mut parser).unwrap();
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm
+        .compile_with_source(&mut node, true, parser.source_text())
+        .unwrap();
+    vm.script_info
+        .push((func_info.module_func_id, parser.into_script_info()));
+    let err = vm.run_global(func_info).unwrap_err();
+    assert_eq!(vm.error_location(&err), Some("t.js:2:11".to_string()));
+    assert_eq!(
+        vm.script_info.last().unwrap().1.code,
+        "__NAME__ + __NAME__\n__NAME__; s + b"
+    );
+}
+
+#[test]
+#[cfg(feature = "remote-modules")]
+fn data_url_module() {
+    test_code(
+        "require('data:text/javascript,module.exports%20%3D%2041') + 1",
+        "42",
+    );
+    // No hosts are allowed by default.
+    runtime_error("require('https://example.com/a.js')");
+    // Remote modules can not load local files.
+    runtime_error("require('data:,require(%22assert%22)')");
+}
+
 #[test]
 fn function_methods() {
     assert_file("function_methods")
@@ -232,6 +618,557 @@ fn string_methods() {
     assert_file("string_methods")
 }
 
+#[test]
+#[cfg(feature = "intl")]
+fn intl() {
+    assert_file("intl")
+}
+
+#[test]
+#[cfg(feature = "node-compat")]
+fn node_compat() {
+    println!("tests/test/node_compat.js");
+    let mut vm = vm::vm::VM::new().node_compat();
+    let mut parser = parser::Parser::new("test", load_file("node_compat"));
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    vm.run_global(func_info).unwrap();
+
+    // Only installed on request
+    test_code(
+        "typeof Buffer + typeof process.hrtime",
+        "'undefinedundefined'",
+    );
+}
+
+#[test]
+fn multiple_declarators() {
+    test_code("var a = 1, b, c = a + 1; [a, b, c]", "[1, undefined, 2]");
+    test_code(
+        "function f() { return b }; var a = f(), b = 3; [a, f()]",
+        "[undefined, 3]",
+    );
+    test_code(
+        "let a = 1, b = a + 1; const c = 3, d = b * c; [a, b, c, d]",
+        "[1, 2, 3, 6]",
+    );
+    test_code(
+        "for (var i = 0, j = 10; i < j; i++, j--) {}; [i, j]",
+        "[5, 5]",
+    );
+}
+
+#[test]
+fn letconst() {
+    assert_file("letconst")
+}
+
+#[test]
+fn map_set() {
+    assert_file("map_set")
+}
+
+#[test]
+fn gc() {
+    assert_file("gc")
+}
+
+#[test]
+fn gc_verify() {
+    for file_name in [
+        "gc",
+        "closure",
+        "array",
+        "map_set",
+        "trycatch",
+        "call_site_cache",
+        "registers",
+        "construct",
+        "proxy",
+    ]
+    .iter()
+    {
+        println!("{}", format!("tests/test/{}.js", file_name));
+        let mut vm = vm::vm::VM::new().gc_verify();
+        let mut parser = parser::Parser::new("test", load_file(*file_name));
+        let mut node = parser.parse_all().unwrap();
+        let func_info = vm.compile(&mut node, true).unwrap();
+        vm.run_global(func_info).unwrap();
+    }
+}
+
+#[test]
+fn limits() {
+    let limits = vm::vm::Limits {
+        max_string_length: 1000,
+        max_array_length: 100,
+        max_property_count: 50,
+        max_call_depth: 100,
+        ..Default::default()
+    };
+    let code = "
+        let caught = [];
+        function check(f) { try { f() } catch (e) { if (e.name === 'RangeError') caught.push(e) } }
+        check(function () { let s = 'a'; while (true) s += s });
+        check(function () { let a = []; while (true) a.push(1, 2, 3) });
+        check(function () { let a = []; a[100] = 1 });
+        check(function () { let a = []; a.length = 1000 });
+        check(function () { new Array(101) });
+        check(function () { let a = []; for (let i = 0; i < 100; i++) a.push('abcdefghijk'); a.join() });
+        check(function () { let o = {}; for (let i = 0; ; i++) o['p' + i] = i });
+        check(function () { function f() { f() } f() });
+        let a = []; a[99] = 1;
+        caught.length + a.length";
+    let mut vm = vm::vm::VM::new().limits(limits);
+    let mut node = parser::Parser::new("test", code).parse_all().unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    vm.current_context = vm.create_global_context(func_info);
+    let val: Value = vm.run().unwrap();
+    assert_eq!(val.debug_string(true), "108");
+
+    // Deep recursion, also through builtins, throws before the native stack overflows.
+    let code = "
+        let caught = [];
+        function check(f) { try { f() } catch (e) { caught.push(e.message) } }
+        check(function () { function f(n) { return f(n + 1) + 1 } f(0) });
+        check(function () { function f() { [1].forEach(f) } f() });
+        check(function () { let o = { get x() { return o.x } }; o.x });
+        caught.join()";
+    let mut vm = vm::vm::VM::new();
+    let mut node = parser::Parser::new("test", code).parse_all().unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    vm.current_context = vm.create_global_context(func_info);
+    let val: Value = vm.run().unwrap();
+    assert_eq!(
+        val.to_string(),
+        ["Maximum call stack size exceeded"; 3].join(",")
+    );
+}
+
+#[test]
+fn debugger_statement() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let count = Rc::new(Cell::new(0));
+    let count_ = count.clone();
+    let mut vm = vm::vm::VM::new();
+    vm.set_debugger_hook(move |vm| {
+        let x = vm.current_context.lex_env().get_value("x").unwrap();
+        assert_eq!(x, Value::Number(count_.get() as f64));
+        count_.set(count_.get() + 1);
+    });
+    let mut parser = parser::Parser::new("test", "for (let x = 0; x < 3; x++) { debugger }");
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    vm.run_global(func_info).unwrap();
+    assert_eq!(count.get(), 3);
+
+    // Without a hook, debugger does nothing.
+    test_code("debugger; 1", "1");
+}
+
+#[test]
+fn stack_trace_options() {
+    use rapidus::vm::stack_trace::StackFrame;
+
+    let stack = |vm: &mut vm::vm::VM| {
+        let code = "let e; [1].forEach(function cb() { e = new Error('x') }); e.stack";
+        let mut parser = parser::Parser::new("test.js", code);
+        let mut node = parser.parse_all().unwrap();
+        let func_info = vm
+            .compile_with_source(&mut node, true, parser.source_text())
+            .unwrap();
+        vm.script_info
+            .push((func_info.module_func_id, parser.into_script_info()));
+        vm.current_context = vm.create_global_context(func_info);
+        vm.run().unwrap().to_string()
+    };
+
+    // The builtin calling cb is shown unless hidden.
+    let mut vm = vm::vm::VM::new();
+    let lines: Vec<String> = stack(&mut vm).lines().map(|l| l.to_string()).collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "Error: x");
+    assert!(lines[1].starts_with("    at cb (test.js:1:"));
+    assert!(lines[2].contains("(array.js:"));
+    assert!(lines[3].starts_with("    at test.js:1:"));
+
+    let mut vm = vm::vm::VM::new();
+    vm.stack_trace_options.hide_builtin_frames = true;
+    assert_eq!(stack(&mut vm).lines().count(), 3);
+
+    let mut vm = vm::vm::VM::new();
+    vm.set_stack_frame_filter(|frame| frame.function_name.is_none());
+    vm.set_prepare_stack_trace(|header, frames: &[StackFrame]| {
+        let names: Vec<&str> = frames
+            .iter()
+            .map(|frame| frame.function_name.as_ref().unwrap().as_str())
+            .collect();
+        format!("{} in {}", header, names.join(" < "))
+    });
+    assert_eq!(stack(&mut vm), "Error: x in cb < <anonymous>");
+}
+
+#[test]
+fn uncaught_exception_handler() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let run = |vm: &mut vm::vm::VM, code: &str| {
+        let mut parser = parser::Parser::new("test.js", code);
+        let mut node = parser.parse_all().unwrap();
+        let func_info = vm
+            .compile_with_source(&mut node, true, parser.source_text())
+            .unwrap();
+        vm.script_info
+            .push((func_info.module_func_id, parser.into_script_info()));
+        vm.run_global(func_info)
+    };
+
+    let mut vm = vm::vm::VM::new();
+    let log = Rc::new(RefCell::new(vec![]));
+    let log_ = log.clone();
+    vm.set_uncaught_exception_handler(move |_vm, val, stack| {
+        log_.borrow_mut().push((val.to_string(), stack));
+    });
+
+    // Exceptions caught by scripts, even across builtins, are not passed to the handler.
+    let code = "try { [1].forEach(function() { throw 1 }) } catch (e) {}";
+    run(&mut vm, code).unwrap();
+    assert!(log.borrow().is_empty());
+
+    run(&mut vm, "function f() { null.x } f()").unwrap();
+    run(&mut vm, "[1].forEach(function() { throw 'thrown' })").unwrap();
+    // The VM goes on running scripts.
+    run(&mut vm, "var x = 1").unwrap();
+    {
+        let log = log.borrow();
+        assert_eq!(log.len(), 2);
+        assert!(log[0].0.starts_with("TypeError: "));
+        let stack = log[0].1.as_ref().unwrap();
+        assert!(stack.contains("\n    at f (test.js:1:"));
+        assert_eq!(log[1], ("thrown".to_string(), None));
+    }
+
+    // Terminated scripts are still returned as errors.
+    vm.set_fuel(100);
+    assert!(run(&mut vm, "while (true) {}").is_err());
+    assert_eq!(log.borrow().len(), 2);
+}
+
+#[test]
+fn realms() {
+    use rapidus::vm::realm::RealmId;
+
+    let mut vm = vm::vm::VM::new().gc_verify();
+    let realm = vm.create_realm();
+    let main = RealmId::MAIN;
+    vm.eval_in_realm(
+        main,
+        "x = 1; Array.prototype.first = function() { return this[0] }",
+    )
+    .unwrap();
+    // Globals and changes to the builtins are not seen from the other realm.
+    let code = "typeof x + ',' + typeof [].first";
+    let val = vm.eval_in_realm(realm, code).unwrap();
+    assert_eq!(val.to_string(), "undefined,undefined");
+    let val = vm.eval_in_realm(main, code).unwrap();
+    assert_eq!(val.to_string(), "number,function");
+    let code = "a = []; for (var i = 0; i < 10000; i++) a.push({ i: i }); a.length";
+    let val = vm.eval_in_realm(realm, code).unwrap();
+    assert_eq!(val, Value::Number(10000.0));
+    assert_eq!(
+        vm.eval_in_realm(realm, "a[9999].i").unwrap(),
+        Value::Number(9999.0)
+    );
+
+    // Scripts can be run in other realms while a script is running.
+    let eval = vm.native_function("evalInRealm", move |vm, args, _this| {
+        vm.eval_in_realm(realm, args[0].to_string())
+    });
+    vm.global_environment
+        .get_global_object()
+        .set_property("evalInRealm", eval);
+    let code = "var y = evalInRealm('var y = 2; y * 3'); y + ',' + evalInRealm('typeof x')";
+    let val = vm.eval_in_realm(main, code).unwrap();
+    assert_eq!(val.to_string(), "6,undefined");
+
+    // Errors are thrown in the realm running the script.
+    let err = vm.eval_in_realm(realm, "var").unwrap_err();
+    assert!(vm.error_to_value(err).is_error_object());
+    let code = "var r; try { evalInRealm('throw 1') } catch (e) { r = e + 1 } r";
+    assert_eq!(vm.eval_in_realm(main, code).unwrap(), Value::Number(2.0));
+    assert_eq!(vm.current_realm, main);
+}
+
+#[test]
+fn guarded_object() {
+    use rapidus::vm::access_check::Access;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let mut vm = vm::vm::VM::new();
+    let target = vm.factory.object(Default::default());
+    target.set_property("public", Value::Number(1.0));
+    target.set_property("secret", Value::Number(2.0));
+    let log = Rc::new(RefCell::new(vec![]));
+    let log_ = log.clone();
+    // A read-only view hiding ``secret``.
+    let view = vm.guarded_object(target, move |_vm, key, access| {
+        log_.borrow_mut().push(access);
+        Ok(match access {
+            Access::Get => key.to_string() != "secret",
+            Access::Enumerate => true,
+            Access::Set | Access::Delete => false,
+        })
+    });
+    vm.global_environment
+        .get_global_object()
+        .set_property("view", view);
+
+    let code = "
+        function caught(f) { try { f() } catch (e) { return e.name } }
+        view.public = 10
+        delete view.public
+        var strict = caught(function() { 'use strict'; view.public = 10 });
+        [view.public, 'public' in view, Object.keys(view).length, typeof view, strict,
+         caught(function() { view.secret }), 'secret' in view].join()
+    ";
+    let mut parser = parser::Parser::new("test", code);
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    vm.current_context = vm.create_global_context(func_info);
+    let val = vm.run().unwrap();
+    assert_eq!(val.to_string(), "1,true,1,object,TypeError,TypeError,false");
+    assert_eq!(target.get_property("public"), Value::Number(1.0));
+    assert_eq!(log.borrow()[..2], [Access::Set, Access::Delete]);
+}
+
+/// Run ``code`` as a script on ``vm``.
+fn run_on(vm: &mut vm::vm::VM, code: &str) -> vm::vm::VMValueResult {
+    let mut parser = parser::Parser::new("test", code);
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    vm.current_context = vm.create_global_context(func_info);
+    vm.run()
+}
+
+#[test]
+fn execution_timeout() {
+    use std::time::Duration;
+
+    let mut vm = vm::vm::VM::new();
+    vm.set_timeout(Duration::from_millis(50));
+    // Neither catch nor finally clauses run on termination.
+    let err = run_on(
+        &mut vm,
+        "this.ran = false; try { while (true) {} } catch (e) { this.ran = true } \
+         finally { this.ran = true }",
+    )
+    .unwrap_err();
+    assert!(err.is_terminated());
+    // The VM can run scripts again, each of which has its own time.
+    assert_eq!(run_on(&mut vm, "this.ran").unwrap(), Value::bool(false));
+}
+
+#[test]
+fn interrupt_handle() {
+    use std::thread;
+    use std::time::Duration;
+
+    let mut vm = vm::vm::VM::new();
+    let handle = vm.interrupt_handle();
+    let thread = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.interrupt();
+    });
+    // Terminates the frames run by builtins too.
+    let err = run_on(
+        &mut vm,
+        "try { [1].forEach(function() { while (true) {} }) } catch (e) {}",
+    )
+    .unwrap_err();
+    thread.join().unwrap();
+    assert!(err.is_terminated());
+    assert_eq!(vm.saved_context.len(), 0);
+    assert_eq!(run_on(&mut vm, "1 + 1").unwrap(), Value::Number(2.0));
+}
+
+#[test]
+fn fuel() {
+    use rapidus::vm::error::ErrorKind;
+
+    let mut vm = vm::vm::VM::new();
+    vm.set_fuel(100);
+    let mut res = run_on(&mut vm, "var n = 0; while (n < 1000) n++; n");
+    assert_eq!(res.clone().unwrap_err().kind, ErrorKind::OutOfFuel);
+    assert_eq!(vm.fuel_consumed(), 100);
+    while res.is_err() {
+        vm.add_fuel(100);
+        res = vm.resume();
+    }
+    assert_eq!(res.unwrap(), Value::Number(1000.0));
+    // The frames run by builtins can not be paused.
+    vm.set_fuel(100);
+    let err = run_on(&mut vm, "[1].forEach(function() { while (true) {} })").unwrap_err();
+    assert!(err.is_terminated());
+}
+
+#[test]
+fn tail_call_frames() {
+    let mut vm = vm::vm::VM::new();
+    let depth = vm.native_function("depth", |vm, _args, _this| {
+        Ok(Value::Number(vm.saved_context.len() as f64))
+    });
+    vm.global_environment
+        .get_global_object()
+        .set_property("depth", depth);
+    let code = "
+        function strict(n) { 'use strict'; if (n === 0) return depth(); return strict(n - 1) }
+        function sloppy(n) { if (n === 0) return depth(); return sloppy(n - 1) }
+        [strict(0), strict(100), sloppy(0), sloppy(100)].join()
+    ";
+    // Only strict functions reuse the frames.
+    assert_eq!(run_on(&mut vm, code).unwrap().to_string(), "1,1,1,101");
+}
+
+#[test]
+fn coverage() {
+    let mut vm = vm::vm::VM::new();
+    vm.enable_coverage(1 << 16);
+    let f = run_on(
+        &mut vm,
+        "(function (x) { if (x > 1) { if (x > 2) return 3; return 2 } return 1 })",
+    )
+    .unwrap();
+    let mut run = |x: f64| {
+        vm.coverage.as_mut().unwrap().reset();
+        vm.call_function(f, &[Value::Number(x)], Value::undefined())
+            .unwrap();
+        vm.coverage.as_ref().unwrap().map().to_vec()
+    };
+    let (one, two, three) = (run(1.0), run(2.0), run(3.0));
+    // The same path counts the same edges.
+    assert_eq!(run(1.0), one);
+    assert_ne!(one, two);
+    assert_ne!(two, three);
+    let edges = |map: &[u8]| map.iter().filter(|count| **count != 0).count();
+    assert!(edges(&one) < edges(&two));
+}
+
+#[test]
+fn json_stream() {
+    use rapidus::builtins::json::JsonStream;
+
+    let mut vm = vm::vm::VM::new();
+    let mut stream = JsonStream::new();
+    assert_eq!(stream.feed(&mut vm, "{\"a\": [1,").unwrap().len(), 0);
+    let values = stream.feed(&mut vm, " 2]}\n\"x\"\n3").unwrap();
+    assert_eq!(values.len(), 2);
+    let a = values[0].get_object_info().get_property("a");
+    assert_eq!(a.as_array_mut().get_length(), 2);
+    assert_eq!(values[1].debug_string(true), "'x'");
+    // The number at the end may go on until the end of the text.
+    assert_eq!(stream.finish(&mut vm).unwrap(), vec![Value::Number(3.0)]);
+
+    let mut stream = JsonStream::elements();
+    let values = stream.feed(&mut vm, " [[1], {\"b\"").unwrap();
+    assert_eq!(values.len(), 1);
+    assert_eq!(values[0].as_array_mut().get_length(), 1);
+    let values = stream.feed(&mut vm, ": true}, 2]").unwrap();
+    assert_eq!(values.len(), 2);
+    assert_eq!(values[1], Value::Number(2.0));
+    assert_eq!(stream.finish(&mut vm).unwrap().len(), 0);
+
+    let mut stream = JsonStream::elements();
+    assert!(stream.feed(&mut vm, "{}").is_err());
+    let mut stream = JsonStream::new().with_max_depth(2);
+    assert!(stream.feed(&mut vm, "[[[]]]").is_err());
+}
+
+#[test]
+fn version() {
+    test_code("process.versions.rapidus === Rapidus.version", "true");
+    test_code("typeof Rapidus.version", "'string'");
+}
+
+#[test]
+fn inspect() {
+    use rapidus::builtins::help::describe;
+
+    let mut vm = vm::vm::VM::new();
+    let code = "function add(a, ...rest) {\n  return a + rest[0]\n}\nadd";
+    let mut parser = parser::Parser::new("test", code);
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm
+        .compile_with_source(&mut node, true, parser.source_text())
+        .unwrap();
+    vm.script_info
+        .push((func_info.module_func_id, parser.into_script_info()));
+    vm.current_context = vm.create_global_context(func_info);
+    let add = vm.run().unwrap();
+    assert_eq!(
+        describe(&vm, add),
+        "function add(a, ...rest)\n  at test:1:26\n  | function add(a, ...rest) {\n  |   return a + rest[0]\n  | }"
+    );
+
+    let log = vm.current_context.lex_env().get_value("console").unwrap();
+    let log = log.get_property("log");
+    assert_eq!(
+        describe(&vm, log),
+        "console.log(...values) [builtin]\n  Print the values separated by spaces."
+    );
+    assert_eq!(describe(&vm, Value::Number(1.0)), "1");
+}
+
+#[test]
+fn disassemble() {
+    use rapidus::builtins::help::disassemble;
+
+    let mut vm = vm::vm::VM::new();
+    let code = "function f(x) { return x * 3 }\nvar g = function(y) { return -y }";
+    let mut parser = parser::Parser::new("test", code);
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm.compile(&mut node, true).unwrap();
+    let text = disassemble(&vm, &func_info);
+    // The script, then f declared in it, then the function expression.
+    let funcs = text.split("\n\n").collect::<Vec<_>>();
+    assert_eq!(funcs.len(), 3);
+    assert!(funcs[0].starts_with("function (anonymous)() [id "));
+    assert!(funcs[1].starts_with("function f(x) [id "));
+    assert!(funcs[1].contains("\n  00000 ") && funcs[1].contains(" Mul"));
+    assert!(funcs[2].contains("(y) [id ") && funcs[2].contains(" Neg"));
+    assert!(!text.contains("\n  ; "));
+
+    // With the source, each line is shown before the instructions compiled from it.
+    let mut parser = parser::Parser::new("test", code);
+    let mut node = parser.parse_all().unwrap();
+    let func_info = vm
+        .compile_with_source(&mut node, true, parser.source_text())
+        .unwrap();
+    vm.script_info
+        .push((func_info.module_func_id, parser.into_script_info()));
+    let text = disassemble(&vm, &func_info);
+    let funcs = text.split("\n\n").collect::<Vec<_>>();
+    assert!(funcs[0].contains("\n  ; 2: var g = function(y) { return -y }\n  00006 SetValue 'g'"));
+    assert_eq!(
+        funcs[1]
+            .lines()
+            .filter(|line| line.starts_with("  ; "))
+            .collect::<Vec<_>>(),
+        vec!["  ; 1: function f(x) { return x * 3 }"]
+    );
+}
+
+#[test]
+fn construct_error() {
+    test_code(
+        "let r; function f() { new 5() }; try { f() } catch (e) { r = 'caught' }; r",
+        "'caught'",
+    );
+}
+
 #[test]
 fn runtime_error1() {
     runtime_error("let a = {}; a.b.c");
@@ -262,3 +1199,60 @@ use test::Bencher;
 fn bench_fibo(b: &mut Bencher) {
     b.iter(|| assert_file("fibo"));
 }
+
+/// A loop whose condition compiles into a compare-and-branch instruction.
+#[bench]
+fn bench_loop_fused(b: &mut Bencher) {
+    b.iter(|| execute_script("let n = 0; for (let i = 0; i < 100000; i++) n++; n".to_string()));
+}
+
+/// The same loop as ``bench_loop_fused``, with a condition compiled into a comparison and a
+/// separate conditional jump.
+#[bench]
+fn bench_loop_unfused(b: &mut Bencher) {
+    b.iter(|| execute_script("let n = 0; for (let i = 0; !(i >= 100000); i++) n++; n".to_string()));
+}
+
+/// Calls of the same closure from one call site, which are entered through the call site cache.
+#[bench]
+fn bench_call_monomorphic(b: &mut Bencher) {
+    b.iter(|| {
+        execute_script(
+            "function inc(x) { return x + 1 } let n = 0; for (let i = 0; i < 10000; i++) n = inc(n); n"
+                .to_string(),
+        )
+    });
+}
+
+/// Parse throughput in MB/s, on a bundle of about 256KB made of the test scripts.
+#[bench]
+fn bench_parse(b: &mut Bencher) {
+    let scripts: Vec<String> = [
+        "closure",
+        "label",
+        "letconst",
+        "map_set",
+        "operator",
+        "prototypes",
+        "qsort",
+        "spread_op",
+        "string_methods",
+        "trycatch",
+    ]
+    .iter()
+    .map(|name| load_file(*name))
+    .collect();
+    let mut bundle = String::new();
+    while bundle.len() < 256 * 1024 {
+        for script in &scripts {
+            bundle += script;
+            bundle += "\n";
+        }
+    }
+    b.bytes = bundle.len() as u64;
+    b.iter(|| {
+        parser::Parser::new("bundle", bundle.clone())
+            .parse_all()
+            .unwrap()
+    });
+}